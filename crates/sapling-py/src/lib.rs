@@ -1,9 +1,7 @@
 use pyo3::prelude::*;
-use sapling::*;
-use std::collections::HashMap;
 
 #[pymodule]
-fn _sapling(m: &Bound<'_, PyModule>) -> PyResult<()> {
+fn _sapling(_m: &Bound<'_, PyModule>) -> PyResult<()> {
     // m.add_class::<PyPatch>()?;
     // m.add_class::<PyPatchSet>()?;
     // m.add_class::<PySnippet>()?;
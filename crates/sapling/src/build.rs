@@ -0,0 +1,343 @@
+//! Compiling a grammar from a build script.
+//!
+//! A crate that ships a grammar keeps its `grammar.json` in the source tree
+//! and turns it into parse tables while it builds, so the tables can never
+//! drift from the grammar:
+//!
+//! ```no_run
+//! // build.rs
+//! fn main() {
+//!     sapling::build::Builder::new("grammar.json").compile();
+//! }
+//! ```
+//!
+//! ```ignore
+//! // src/lib.rs
+//! mod calc {
+//!     include!(concat!(env!("OUT_DIR"), "/calc.rs"));
+//! }
+//! ```
+//!
+//! The grammar is validated and its tables built before anything is
+//! written; any problem fails the build with the grammar's path and the
+//! diagnostic, such as the symbols involved in an unresolved conflict.
+
+use crate::emit;
+use crate::grammar::{parse_grammar, Grammar, GrammarError};
+use crate::tables::TableError;
+use crate::validate::report::GrammarReport;
+use crate::validate::{Diagnostic, Severity};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Compiles a `grammar.json` into a Rust module in `OUT_DIR`.
+#[derive(Debug, Clone)]
+pub struct Builder {
+    grammar: PathBuf,
+    out_file: Option<PathBuf>,
+    scanner: Option<String>,
+}
+
+/// Why a grammar could not be compiled.
+#[derive(Debug)]
+pub enum BuildError {
+    /// A file could not be read or written.
+    Io {
+        /// The file.
+        path: PathBuf,
+        /// The underlying error.
+        error: std::io::Error,
+    },
+    /// `OUT_DIR` is not set, so the script is not being run by Cargo, and no
+    /// absolute output path was given.
+    OutDir,
+    /// The grammar file is not a valid grammar.
+    Grammar {
+        /// The grammar file.
+        path: PathBuf,
+        /// The underlying error.
+        error: GrammarError,
+    },
+    /// The grammar failed validation.
+    Validation {
+        /// The grammar file.
+        path: PathBuf,
+        /// Everything validation found, including the warnings and notes
+        /// alongside the errors.
+        diagnostics: Vec<Diagnostic>,
+    },
+    /// The grammar declares external tokens but no scanner was named with
+    /// [`Builder::external_scanner`].
+    MissingScanner {
+        /// The grammar file.
+        path: PathBuf,
+        /// The grammar's name.
+        name: String,
+    },
+    /// The grammar's parse tables could not be built.
+    Tables {
+        /// The grammar file.
+        path: PathBuf,
+        /// The underlying error.
+        error: TableError,
+    },
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (path, message) = match self {
+            BuildError::Io { path, error } => (path, error.to_string()),
+            BuildError::OutDir => {
+                return write!(
+                f,
+                "error: OUT_DIR is not set; run from a build script or give an absolute out_file"
+            )
+            }
+            BuildError::Grammar { path, error } => (path, error.to_string()),
+            BuildError::Validation { path, diagnostics } => {
+                // Rendered as `sapling validate` renders them, each with
+                // its own location.
+                let report = GrammarReport {
+                    path: path.display().to_string(),
+                    diagnostics: diagnostics.clone(),
+                };
+                return f.write_str(report.to_human().trim_end());
+            }
+            BuildError::MissingScanner { path, name } => (
+                path,
+                format!(
+                    "grammar `{name}` declares external tokens; \
+                     name their scanner with `external_scanner`"
+                ),
+            ),
+            BuildError::Tables { path, error } => (path, error.to_string()),
+        };
+        // The first line is the headline; anything after it, such as a
+        // conflict's interpretations, is indented under the path.
+        let mut lines = message.lines();
+        writeln!(f, "error: {}", lines.next().unwrap_or_default())?;
+        write!(f, "  --> {}", path.display())?;
+        for line in lines {
+            if line.is_empty() {
+                write!(f, "\n   |")?;
+            } else {
+                write!(f, "\n   | {line}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for BuildError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BuildError::Io { error, .. } => Some(error),
+            BuildError::Grammar { error, .. } => Some(error),
+            BuildError::Tables { error, .. } => Some(error),
+            BuildError::OutDir
+            | BuildError::Validation { .. }
+            | BuildError::MissingScanner { .. } => None,
+        }
+    }
+}
+
+impl Builder {
+    /// Starts a build of the grammar at `grammar`, a path relative to the
+    /// package root when run by Cargo.
+    #[must_use]
+    pub fn new(grammar: impl AsRef<Path>) -> Self {
+        Self {
+            grammar: grammar.as_ref().to_path_buf(),
+            out_file: None,
+            scanner: None,
+        }
+    }
+
+    /// Names the [`ExternalScanner`](crate::scanner::ExternalScanner)
+    /// implementing the grammar's external tokens. The generated module
+    /// creates one with `Default` for each parse.
+    ///
+    /// `path` is the type's path as the generated module sees it, such as
+    /// `crate::scanner::IndentScanner` for a scanner in the crate being
+    /// built, or the full path of one from a dependency. Build scripts are
+    /// compiled apart from that crate, so the type can't be named here.
+    #[must_use]
+    pub fn external_scanner(mut self, path: &str) -> Self {
+        self.scanner = Some(path.to_string());
+        self
+    }
+
+    /// Sets where the module is written. A relative path is taken from
+    /// `OUT_DIR`; the default is the grammar's name with an `.rs` extension.
+    #[must_use]
+    pub fn out_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.out_file = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Compiles the grammar, failing the build script with the rendered
+    /// diagnostic if it cannot be.
+    pub fn compile(self) {
+        if let Err(error) = self.try_compile() {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+    }
+
+    /// Compiles the grammar, returning the path of the generated module.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`BuildError`] if the grammar cannot be read, is invalid,
+    /// or has no parse table, or if the module cannot be written.
+    pub fn try_compile(self) -> Result<PathBuf, BuildError> {
+        println!("cargo:rerun-if-changed={}", self.grammar.display());
+        let grammar = self.load()?;
+        let module = emit::rust::emit(&grammar, self.scanner.as_deref()).map_err(|error| {
+            BuildError::Tables {
+                path: self.grammar.clone(),
+                error,
+            }
+        })?;
+        let out_file = self
+            .out_file
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(format!("{}.rs", grammar.name)));
+        let out_file = if out_file.is_absolute() {
+            out_file
+        } else {
+            PathBuf::from(std::env::var_os("OUT_DIR").ok_or(BuildError::OutDir)?).join(out_file)
+        };
+        if let Some(parent) = out_file.parent() {
+            fs::create_dir_all(parent).map_err(|error| BuildError::Io {
+                path: parent.to_path_buf(),
+                error,
+            })?;
+        }
+        fs::write(&out_file, module).map_err(|error| BuildError::Io {
+            path: out_file.clone(),
+            error,
+        })?;
+        Ok(out_file)
+    }
+
+    /// Reads and validates the grammar.
+    fn load(&self) -> Result<Grammar, BuildError> {
+        let path = &self.grammar;
+        let json = fs::read_to_string(path).map_err(|error| BuildError::Io {
+            path: path.clone(),
+            error,
+        })?;
+        let grammar = parse_grammar(&json).map_err(|error| BuildError::Grammar {
+            path: path.clone(),
+            error,
+        })?;
+        let report = GrammarReport::check(path.display().to_string(), &json);
+        if report.count(Severity::Error) > 0 {
+            return Err(BuildError::Validation {
+                path: path.clone(),
+                diagnostics: report.diagnostics,
+            });
+        }
+        let has_externals = grammar
+            .externals
+            .as_ref()
+            .is_some_and(|externals| !externals.is_empty());
+        if has_externals && self.scanner.is_none() {
+            return Err(BuildError::MissingScanner {
+                path: path.clone(),
+                name: grammar.name,
+            });
+        }
+        Ok(grammar)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_grammar(dir: &Path, json: &str) -> PathBuf {
+        let path = dir.join("grammar.json");
+        fs::write(&path, json).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_compile_writes_module() {
+        let dir = tempfile::tempdir().unwrap();
+        let grammar = write_grammar(
+            dir.path(),
+            r#"{"name": "tiny", "rules": {"word": {"type": "PATTERN", "value": "[a-z]+"}}}"#,
+        );
+        let out = Builder::new(&grammar)
+            .out_file(dir.path().join("gen/tiny.rs"))
+            .try_compile()
+            .unwrap();
+        let module = fs::read_to_string(out).unwrap();
+        assert!(module.contains("pub static LANGUAGE: Language"));
+    }
+
+    #[test]
+    fn test_conflicts_are_rendered_with_the_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let grammar = write_grammar(
+            dir.path(),
+            r#"{"name": "sum", "rules": {"sum": {"type": "CHOICE", "members": [
+                {"type": "SEQ", "members": [
+                    {"type": "SYMBOL", "name": "sum"},
+                    {"type": "STRING", "value": "+"},
+                    {"type": "SYMBOL", "name": "sum"}
+                ]},
+                {"type": "STRING", "value": "1"}
+            ]}}}"#,
+        );
+        let error = Builder::new(&grammar)
+            .out_file(dir.path().join("sum.rs"))
+            .try_compile()
+            .unwrap_err();
+        let rendered = error.to_string();
+        assert!(
+            rendered.starts_with("error: unresolved conflict"),
+            "{rendered}"
+        );
+        assert!(rendered.contains(&format!("  --> {}", grammar.display())));
+        assert!(
+            rendered.contains("   | possible interpretations:"),
+            "{rendered}"
+        );
+    }
+
+    #[test]
+    fn test_validation_renders_every_diagnostic() {
+        let dir = tempfile::tempdir().unwrap();
+        let grammar = write_grammar(
+            dir.path(),
+            r#"{"name": "bad", "rules": {
+                "program": {"type": "SEQ", "members": [
+                    {"type": "SYMBOL", "name": "expr"},
+                    {"type": "SYMBOL", "name": "stmt"}
+                ]},
+                "unused": {"type": "STRING", "value": "x"}
+            }}"#,
+        );
+        let error = Builder::new(&grammar)
+            .out_file(dir.path().join("bad.rs"))
+            .try_compile()
+            .unwrap_err();
+        let rendered = error.to_string();
+        let path = grammar.display();
+        assert_eq!(
+            rendered,
+            format!(
+                "error[undefined-symbol]: undefined symbol 'expr' referenced in rule 'program'\n  \
+                 --> {path}:2\n\
+                 error[undefined-symbol]: undefined symbol 'stmt' referenced in rule 'program'\n  \
+                 --> {path}:2\n\
+                 warning[unreachable-rule]: unreachable rule 'unused'\n  \
+                 --> {path}:6"
+            )
+        );
+    }
+}
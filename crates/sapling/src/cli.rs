@@ -3,111 +3,1247 @@
 //! Command-line interface for sapling.
 #![allow(clippy::multiple_crate_versions)]
 
-/// Command-line interface for applying patches from JSON.
+/// Command-line interface for working with grammars.
 #[cfg(feature = "cli")]
 pub mod inner {
     use facet::Facet;
-    use sapling::Grammar;
+    use sapling::corpus::{self, Outcome};
+    use sapling::emit::ebnf::{self, Notation};
+    use sapling::grammar::unknown_keys;
+    use sapling::graph::RuleGraph;
+    use sapling::import::{self, Format};
+    use sapling::railroad;
+    use sapling::stats::GrammarStats;
+    use sapling::tags::{tags, TagsConfiguration};
+    use sapling::tree::render;
+    use sapling::validate::report::{self, GrammarReport};
+    use sapling::validate::Severity;
+    use sapling::{
+        emit, json, parse_grammar, query, tables, validate, Grammar, Language, Node, NodeTypes,
+        Parser, Query, QueryCursor,
+    };
     use std::fs;
-    use std::io::{self, Read};
+    use std::io::{self, IsTerminal};
+    use std::path::{Path, PathBuf};
+    use std::time::Instant;
+
+    /// A subcommand: its name, arguments, summary and options.
+    struct Command {
+        name: &'static str,
+        arguments: &'static str,
+        summary: &'static str,
+        options: &'static [(&'static str, &'static str)],
+    }
+
+    const COMMANDS: &[Command] = &[
+        Command {
+            name: "validate",
+            arguments: "[OPTIONS] <GRAMMAR_JSON>...",
+            summary: "Check grammar.json files for problems",
+            options: &[
+                (
+                    "-f, --format <FORMAT>",
+                    "Print diagnostics as human (default), json or sarif",
+                ),
+                ("--deny-warnings", "Fail on warnings as well as errors"),
+            ],
+        },
+        Command {
+            name: "fmt",
+            arguments: "[--check] <GRAMMAR_JSON>...",
+            summary: "Rewrite grammar.json files in canonical form",
+            options: &[(
+                "--check",
+                "List the files that are not formatted, and change nothing",
+            )],
+        },
+        Command {
+            name: "parse",
+            arguments: "--grammar <GRAMMAR_JSON> [OPTIONS] <PATH>...",
+            summary: "Print the syntax tree of the source files under each path",
+            options: &[
+                ("-g, --grammar <GRAMMAR_JSON>", "The grammar to parse with"),
+                (
+                    "-f, --format <FORMAT>",
+                    "Print trees as sexp (default), tree, json or xml",
+                ),
+                (
+                    "-e, --extension <EXT>",
+                    "Only read files with this extension from directories",
+                ),
+                (
+                    "-q, --quiet",
+                    "Only print the errors and missing nodes of failing files",
+                ),
+                ("-t, --time", "Print how long each file took to parse"),
+                ("-s, --stat", "Print how many files parsed without errors"),
+            ],
+        },
+        Command {
+            name: "generate",
+            arguments: "[OPTIONS] <GRAMMAR_JSON>",
+            summary: "Write a grammar's parse tables module and node-types.json",
+            options: &[
+                (
+                    "-o, --output <DIR>",
+                    "The directory to write to (default: the grammar's)",
+                ),
+                (
+                    "--scanner <PATH>",
+                    "The Rust path of the external scanner, for external tokens",
+                ),
+            ],
+        },
+        Command {
+            name: "query",
+            arguments: "<GRAMMAR_JSON> <QUERY_SCM> <FILE>...",
+            summary: "Print the captures of a query in each file",
+            options: &[],
+        },
+        Command {
+            name: "test",
+            arguments: "[--update] <GRAMMAR_JSON> [PATH]...",
+            summary: "Run the corpus tests under each path (default: test/corpus)",
+            options: &[(
+                "-u, --update",
+                "Rewrite the expected trees of failing tests",
+            )],
+        },
+        Command {
+            name: "check-queries",
+            arguments: "<GRAMMAR_JSON> <QUERY_FILE>...",
+            summary: "Check .scm query files against a grammar",
+            options: &[],
+        },
+        Command {
+            name: "tags",
+            arguments: "[OPTIONS] <GRAMMAR_JSON> <TAGS_SCM> <PATH>...",
+            summary: "Write a ctags file for the source files under each path",
+            options: &[
+                (
+                    "--locals <LOCALS_SCM>",
+                    "Leave out references to local variables",
+                ),
+                (
+                    "-e, --extension <EXT>",
+                    "Only read files with this extension from directories",
+                ),
+            ],
+        },
+        Command {
+            name: "stats",
+            arguments: "[OPTIONS] <GRAMMAR_JSON>...",
+            summary: "Print size and complexity metrics of each grammar",
+            options: &[
+                (
+                    "-f, --format <FORMAT>",
+                    "Print metrics as human (default) or json",
+                ),
+                (
+                    "--no-tables",
+                    "Skip building parse tables, leaving out state counts",
+                ),
+            ],
+        },
+        Command {
+            name: "graph",
+            arguments: "[OPTIONS] <GRAMMAR_JSON>",
+            summary: "Print the graph of which rules refer to which",
+            options: &[
+                (
+                    "-f, --format <FORMAT>",
+                    "Print the graph as dot (default) or mermaid",
+                ),
+                ("--focus <RULE>", "Only show the rules around this one"),
+                (
+                    "-d, --depth <N>",
+                    "How many references from the focused rule to follow (default: 1)",
+                ),
+                (
+                    "--collapse-hidden",
+                    "Link rules through hidden rules instead of showing them",
+                ),
+                ("--color", "Colour each group of mutually recursive rules"),
+            ],
+        },
+        Command {
+            name: "railroad",
+            arguments: "[OPTIONS] <GRAMMAR_JSON>",
+            summary: "Write a railroad diagram of each rule and a page of them all",
+            options: &[(
+                "-o, --output <DIR>",
+                "The directory to write to (default: railroad, next to the grammar)",
+            )],
+        },
+        Command {
+            name: "ebnf",
+            arguments: "[OPTIONS] <GRAMMAR_JSON>",
+            summary: "Print a grammar in EBNF",
+            options: &[(
+                "-n, --notation <NOTATION>",
+                "Print ISO/IEC 14977 EBNF as iso or XML-style EBNF as w3c (default)",
+            )],
+        },
+        Command {
+            name: "import",
+            arguments: "[OPTIONS] <FILE>",
+            summary: "Translate an ANTLR4, pest or ungrammar grammar into grammar.json",
+            options: &[
+                (
+                    "-f, --format <FORMAT>",
+                    "antlr, pest or ungrammar (default: from the file extension)",
+                ),
+                (
+                    "-n, --name <NAME>",
+                    "The grammar's name (default: the file name; ANTLR grammars name themselves)",
+                ),
+                (
+                    "-s, --start <RULE>",
+                    "The rule a pest grammar starts from (default: one no other rule uses)",
+                ),
+                (
+                    "-o, --output <FILE>",
+                    "The file to write to (default: standard output)",
+                ),
+            ],
+        },
+    ];
+
+    fn print_usage() {
+        println!("Usage: sapling <COMMAND> [ARGS]...");
+        println!();
+        println!("Validate, generate, test and run tree-sitter grammars.");
+        println!();
+        println!("Commands:");
+        for command in COMMANDS {
+            println!("  {:<15}{}", command.name, command.summary);
+        }
+        println!();
+        println!("Run `sapling <COMMAND> --help` for the arguments of a command.");
+        if cfg!(feature = "js") {
+            println!();
+            println!(
+                "Commands other than validate and fmt also take a grammar.js for <GRAMMAR_JSON>,"
+            );
+            println!("and generate writes the grammar.json it evaluates to.");
+        }
+    }
+
+    fn print_command_usage(command: &Command) {
+        println!("Usage: sapling {} {}", command.name, command.arguments);
+        println!();
+        println!("{}.", command.summary);
+        if !command.options.is_empty() {
+            println!();
+            println!("Options:");
+            let width = command
+                .options
+                .iter()
+                .map(|(flags, _)| flags.len())
+                .max()
+                .unwrap_or_default();
+            for (flags, description) in command.options {
+                println!("  {flags:<width$}  {description}");
+            }
+        }
+    }
 
     #[derive(Facet)]
-    struct Args {
-        /// Path to JSON file containing patches (reads from stdin if not provided)
+    struct ValidateArgs {
+        /// The grammar.json files to check
+        #[facet(positional)]
+        grammars: Vec<String>,
+
+        /// How to print diagnostics
+        #[facet(named, short = 'f', default)]
+        format: Option<String>,
+
+        /// Fail on warnings as well as errors
+        #[facet(named)]
+        deny_warnings: bool,
+    }
+
+    #[derive(Facet)]
+    struct FmtArgs {
+        /// The grammar.json files to format
+        #[facet(positional)]
+        grammars: Vec<String>,
+
+        /// Only check whether the files are formatted
+        #[facet(named)]
+        check: bool,
+    }
+
+    #[derive(Facet)]
+    struct ParseArgs {
+        /// The grammar to parse with
+        #[facet(named, short = 'g')]
+        grammar: String,
+
+        /// The files, or directories of files, to parse
+        #[facet(positional)]
+        paths: Vec<String>,
+
+        /// How to print trees
+        #[facet(named, short = 'f', default)]
+        format: Option<String>,
+
+        /// Only read files with these extensions from directories
+        #[facet(named, short = 'e', default)]
+        extension: Vec<String>,
+
+        /// Only print the errors and missing nodes of failing files
+        #[facet(named, short = 'q')]
+        quiet: bool,
+
+        /// Print how long each file took to parse
+        #[facet(named, short = 't')]
+        time: bool,
+
+        /// Print how many files parsed without errors
+        #[facet(named, short = 's')]
+        stat: bool,
+    }
+
+    #[derive(Facet)]
+    struct GenerateArgs {
+        /// The grammar to generate from
+        #[facet(positional)]
+        grammar: String,
+
+        /// The directory to write to
+        #[facet(named, short = 'o', default)]
+        output: Option<String>,
+
+        /// The Rust path of the external scanner
+        #[facet(named, default)]
+        scanner: Option<String>,
+    }
+
+    #[derive(Facet)]
+    struct QueryArgs {
+        /// The grammar to parse with
+        #[facet(positional)]
+        grammar: String,
+
+        /// The query file
+        #[facet(positional)]
+        query: String,
+
+        /// The files to query
+        #[facet(positional)]
+        files: Vec<String>,
+    }
+
+    #[derive(Facet)]
+    struct TestArgs {
+        /// The grammar to test
+        #[facet(positional)]
+        grammar: String,
+
+        /// Corpus files, or directories of them
         #[facet(positional, default)]
-        patch_file: Option<String>,
+        paths: Vec<String>,
 
-        /// Preview changes without writing to disk
-        #[facet(named, short = 'n')]
-        dry_run: bool,
+        /// Rewrite the expected trees of failing tests
+        #[facet(named, short = 'u')]
+        update: bool,
+    }
 
-        /// Show verbose output
-        #[facet(named, short = 'v')]
-        verbose: bool,
+    #[derive(Facet)]
+    struct CheckQueriesArgs {
+        /// The grammar to check against
+        #[facet(positional)]
+        grammar: String,
 
-        /// Show this help message
-        #[facet(named, short = 'h')]
-        help: bool,
+        /// The query files to check
+        #[facet(positional)]
+        queries: Vec<String>,
     }
 
-    fn print_usage() {
-        println!("Usage: sapling [OPTIONS] [PATCH_FILE]");
-        println!();
-        println!("Apply syntactic patches to source files with char-level granularity.");
-        println!();
-        println!("Arguments:");
-        println!("  [PATCH_FILE]  Path to JSON file containing patches (reads from stdin if not provided)");
-        println!();
-        println!("Options:");
-        println!("  -n, --dry-run  Preview changes without writing to disk");
-        println!("  -v, --verbose  Show verbose output");
-        println!("  -h, --help     Show this help message");
+    #[derive(Facet)]
+    struct TagsArgs {
+        /// The grammar to parse with
+        #[facet(positional)]
+        grammar: String,
+
+        /// The tags query
+        #[facet(positional)]
+        tags: String,
+
+        /// Source files, or directories of them
+        #[facet(positional)]
+        paths: Vec<String>,
+
+        /// The locals query
+        #[facet(named, default)]
+        locals: Option<String>,
+
+        /// Extensions of the files to read from directories
+        #[facet(named, short = 'e', default)]
+        extension: Vec<String>,
+    }
+
+    #[derive(Facet)]
+    struct StatsArgs {
+        /// The grammar.json files to measure
+        #[facet(positional)]
+        grammars: Vec<String>,
+
+        /// How to print metrics
+        #[facet(named, short = 'f', default)]
+        format: Option<String>,
+
+        /// Skip building parse tables
+        #[facet(named)]
+        no_tables: bool,
+    }
+
+    #[derive(Facet)]
+    struct GraphArgs {
+        /// The grammar to draw
+        #[facet(positional)]
+        grammar: String,
+
+        /// How to print the graph
+        #[facet(named, short = 'f', default)]
+        format: Option<String>,
+
+        /// The rule to show the neighbourhood of
+        #[facet(named, default)]
+        focus: Option<String>,
+
+        /// How many references from the focused rule to follow
+        #[facet(named, short = 'd', default)]
+        depth: Option<usize>,
+
+        /// Link rules through hidden rules instead of showing them
+        #[facet(named)]
+        collapse_hidden: bool,
+
+        /// Colour each group of mutually recursive rules
+        #[facet(named)]
+        color: bool,
+    }
+
+    #[derive(Facet)]
+    struct RailroadArgs {
+        /// The grammar to draw
+        #[facet(positional)]
+        grammar: String,
+
+        /// The directory to write to
+        #[facet(named, short = 'o', default)]
+        output: Option<String>,
+    }
+
+    #[derive(Facet)]
+    struct EbnfArgs {
+        /// The grammar to print
+        #[facet(positional)]
+        grammar: String,
+
+        /// The EBNF notation to print in
+        #[facet(named, short = 'n', default)]
+        notation: Option<String>,
+    }
+
+    #[derive(Facet)]
+    struct ImportArgs {
+        /// The grammar to import
+        #[facet(positional)]
+        file: String,
+
+        /// The notation it is written in
+        #[facet(named, short = 'f', default)]
+        format: Option<String>,
+
+        /// The name of the grammar
+        #[facet(named, short = 'n', default)]
+        name: Option<String>,
+
+        /// The rule a pest grammar starts from
+        #[facet(named, short = 's', default)]
+        start: Option<String>,
+
+        /// The file to write to
+        #[facet(named, short = 'o', default)]
+        output: Option<String>,
     }
 
     #[cfg(feature = "cli")]
-    /// Entry point for the `sapling` command-line interface.
+    /// Entry point for the `sapling` command-line interface: runs it,
+    /// printing the error of a command that fails and exiting with status 1.
+    pub fn main() {
+        if let Err(error) = run() {
+            eprintln!("error: {error}");
+            std::process::exit(1);
+        }
+    }
+
+    /// Runs the subcommand named by the first argument with the rest, or
+    /// prints the usage for `--help` or no arguments.
     ///
-    /// Reads JSON patches from a file or stdin and applies them to their target files.
+    /// Arguments that don't fit a command print its usage and exit with
+    /// status 2, and commands that find problems in their input, such as
+    /// grammars that fail validation or files that don't parse, report them
+    /// and exit with status 1 themselves.
     ///
     /// # Errors
     ///
-    /// Returns an [`io::Error`] if:
-    /// - command-line argument parsing fails,
-    /// - the input file cannot be read,
-    /// - patch JSON is malformed,
-    /// - or writing the modified files fails.
+    /// Returns the I/O error of a command that fails: a file or directory
+    /// that can't be read or written, an [`io::ErrorKind::InvalidData`]
+    /// error naming an input file, such as a grammar or query, whose
+    /// contents are invalid or whose tables can't be built, and an
+    /// [`io::ErrorKind::InvalidInput`] error for an option value the command
+    /// doesn't accept.
+    fn run() -> io::Result<()> {
+        let raw_args: Vec<String> = std::env::args().skip(1).collect();
+        let Some((name, args)) = raw_args.split_first() else {
+            print_usage();
+            std::process::exit(2);
+        };
+        if matches!(name.as_str(), "-h" | "--help" | "help") {
+            print_usage();
+            return Ok(());
+        }
+        let Some(command) = COMMANDS.iter().find(|command| command.name == name) else {
+            eprintln!("error: unknown command '{name}'");
+            eprintln!();
+            print_usage();
+            std::process::exit(2);
+        };
+        if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+            print_command_usage(command);
+            return Ok(());
+        }
+
+        match command.name {
+            "validate" => validate_grammars(&arguments(command, args)),
+            "fmt" => format_grammars(&arguments(command, args)),
+            "parse" => parse_files(&arguments(command, args)),
+            "generate" => generate(&arguments(command, args)),
+            "query" => query_files(&arguments(command, args)),
+            "test" => run_corpus(&arguments(command, args)),
+            "check-queries" => check_queries(&arguments(command, args)),
+            "tags" => write_tags(&arguments(command, args)),
+            "stats" => print_stats(&arguments(command, args)),
+            "graph" => print_graph(&arguments(command, args)),
+            "railroad" => write_railroad(&arguments(command, args)),
+            "ebnf" => print_ebnf(&arguments(command, args)),
+            "import" => import_grammar(&arguments(command, args)),
+            _ => unreachable!("every command is handled"),
+        }
+    }
+
+    /// Reads a command's arguments, printing its usage and exiting if they
+    /// don't fit.
+    fn arguments<T: Facet<'static>>(command: &Command, args: &[String]) -> T {
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        facet_args::from_slice(&args).unwrap_or_else(|error| {
+            eprintln!("error: {error}");
+            eprintln!();
+            eprintln!("Usage: sapling {} {}", command.name, command.arguments);
+            std::process::exit(2);
+        })
+    }
+
+    /// Reads a file, naming it in the error if it can't be.
+    fn read(path: impl AsRef<Path>) -> io::Result<String> {
+        let path = path.as_ref();
+        fs::read_to_string(path)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", path.display())))
+    }
+
+    /// An error about the contents of the file at `path`.
+    fn invalid_data(path: &str, error: impl std::fmt::Display) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, format!("{path}: {error}"))
+    }
+
+    /// Reads a `grammar.json`, or evaluates a `grammar.js`.
+    fn load_grammar(path: &str) -> io::Result<Grammar> {
+        if is_grammar_js(path) {
+            return evaluate_grammar_js(path);
+        }
+        parse_grammar(&read(path)?).map_err(|e| invalid_data(path, e))
+    }
+
+    fn is_grammar_js(path: &str) -> bool {
+        Path::new(path)
+            .extension()
+            .is_some_and(|extension| extension == "js")
+    }
+
+    #[cfg(feature = "js")]
+    fn evaluate_grammar_js(path: &str) -> io::Result<Grammar> {
+        sapling::grammar::js::evaluate_str(&read(path)?, path).map_err(|e| invalid_data(path, e))
+    }
+
+    #[cfg(not(feature = "js"))]
+    fn evaluate_grammar_js(path: &str) -> io::Result<Grammar> {
+        Err(invalid_data(
+            path,
+            "reading grammar.js needs sapling built with the js feature; \
+             pass the grammar.json tree-sitter generates from it instead",
+        ))
+    }
+
+    /// Rewrites each grammar in canonical form, printing the paths of those
+    /// that changed. With `--check`, nothing is written, and the command
+    /// exits non-zero if any grammar is not already formatted. A grammar
+    /// with keys sapling does not model is refused, as rewriting it would
+    /// drop them.
+    fn format_grammars(args: &FmtArgs) -> io::Result<()> {
+        let mut unformatted = 0;
+        for path in &args.grammars {
+            if is_grammar_js(path) {
+                return Err(invalid_data(
+                    path,
+                    "fmt formats grammar.json files, not the grammar.js they are generated from",
+                ));
+            }
+            let source = read(path)?;
+            let mut grammar = parse_grammar(&source).map_err(|e| invalid_data(path, e))?;
+            let unknown = unknown_keys(&source).map_err(|e| invalid_data(path, e))?;
+            if !unknown.is_empty() {
+                return Err(invalid_data(
+                    path,
+                    format!(
+                        "formatting would drop keys sapling does not know: {}",
+                        unknown.join(", ")
+                    ),
+                ));
+            }
+            grammar.canonicalize();
+            let formatted = grammar.to_json() + "\n";
+            if formatted == source {
+                continue;
+            }
+            unformatted += 1;
+            if args.check {
+                println!("{path}: not formatted");
+            } else {
+                fs::write(path, formatted)?;
+                println!("{path}");
+            }
+        }
+        if args.check && unformatted > 0 {
+            std::process::exit(1);
+        }
+        Ok(())
+    }
+
+    /// Reads a grammar and builds its parse tables. A grammar with external
+    /// tokens is refused, as there is no scanner to lex them with here.
+    fn load_language(path: &str) -> io::Result<Language> {
+        let grammar = load_grammar(path)?;
+        if has_externals(&grammar) {
+            return Err(invalid_data(
+                path,
+                "the grammar declares external tokens, which need a scanner; \
+                 generate its tables with --scanner and parse through those",
+            ));
+        }
+        Ok(tables::build(&grammar)
+            .map_err(|e| invalid_data(path, e))?
+            .into_language())
+    }
+
+    /// Whether a grammar declares external tokens, which only a scanner can
+    /// lex.
+    fn has_externals(grammar: &Grammar) -> bool {
+        grammar
+            .externals
+            .as_ref()
+            .is_some_and(|externals| !externals.is_empty())
+    }
+
+    /// Validates each grammar, printing what the checks find, and exits
+    /// non-zero on errors, or on warnings with `--deny-warnings`.
+    fn validate_grammars(args: &ValidateArgs) -> io::Result<()> {
+        let format = args.format.as_deref().unwrap_or("human");
+        if !matches!(format, "human" | "json" | "sarif") {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown format '{format}'; expected human, json or sarif"),
+            ));
+        }
+        let mut reports = Vec::new();
+        for path in &args.grammars {
+            reports.push(GrammarReport::check(path, &read(path)?));
+        }
+        let count =
+            |severity| -> usize { reports.iter().map(|report| report.count(severity)).sum() };
+        let (errors, warnings) = (count(Severity::Error), count(Severity::Warning));
+        match format {
+            "json" => println!("{}", report::to_json(&reports)),
+            "sarif" => println!("{}", report::to_sarif(&reports)),
+            _ => {
+                for report in &reports {
+                    print!("{}", report.to_human());
+                }
+                if errors + warnings > 0 {
+                    eprintln!("{errors} error(s), {warnings} warning(s)");
+                }
+            }
+        }
+        if errors > 0 || (args.deny_warnings && warnings > 0) {
+            std::process::exit(1);
+        }
+        Ok(())
+    }
+
+    /// Prints the tree of each file, exiting non-zero if any has errors.
+    fn parse_files(args: &ParseArgs) -> io::Result<()> {
+        let format = args.format.as_deref().unwrap_or("sexp");
+        if !matches!(format, "sexp" | "tree" | "json" | "xml") {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown format '{format}'; expected sexp, tree, json or xml"),
+            ));
+        }
+        let language = load_language(&args.grammar)?;
+        let mut parser = Parser::new(&language);
+        let mut files = Vec::new();
+        for path in &args.paths {
+            collect_files(Path::new(path), &args.extension, &mut files)?;
+        }
+        if format == "xml" && !args.quiet {
+            println!("<?xml version=\"1.0\"?>");
+            println!("<sources>");
+        }
+        let mut failed = 0;
+        for file in &files {
+            let path = file.display().to_string();
+            let source = read(file)?;
+            let start = Instant::now();
+            let tree = parser.parse(source.as_str());
+            let elapsed = start.elapsed();
+            if args.quiet {
+                if tree.has_error() {
+                    let mut problems = Vec::new();
+                    find_problems(&tree, &mut problems);
+                    for node in problems {
+                        let (start, end) = (node.start_position(), node.end_position());
+                        if node.is_missing() {
+                            println!(
+                                "{path}: MISSING {} [{}, {}]",
+                                node.kind(),
+                                start.row,
+                                start.column
+                            );
+                        } else {
+                            println!(
+                                "{path}: ERROR [{}, {}] - [{}, {}]",
+                                start.row, start.column, end.row, end.column
+                            );
+                        }
+                    }
+                }
+            } else {
+                match format {
+                    "tree" => print!("{}", render::render_tree(&tree)),
+                    "json" => println!(
+                        "{{\"path\":{},\"tree\":{}}}",
+                        json_string(&path),
+                        render::render_json(&tree)
+                    ),
+                    "xml" => println!(
+                        "<source name=\"{}\">{}</source>",
+                        xml_attribute(&path),
+                        render::render_xml(&tree, source.as_bytes())
+                    ),
+                    _ => println!("{}", corpus::format_sexp(&tree.to_sexp())),
+                }
+                if tree.has_error() {
+                    eprintln!("{path}: parsed with errors");
+                }
+            }
+            if args.time {
+                eprintln!("{path}: {:.2} ms", elapsed.as_secs_f64() * 1000.0);
+            }
+            if tree.has_error() {
+                failed += 1;
+            }
+        }
+        if format == "xml" && !args.quiet {
+            println!("</sources>");
+        }
+        if args.stat {
+            let total = files.len();
+            #[allow(clippy::cast_precision_loss)]
+            let percentage = if total == 0 {
+                100.0
+            } else {
+                (total - failed) as f64 / total as f64 * 100.0
+            };
+            println!(
+                "Total parses: {total}; successful parses: {}; failed parses: {failed}; \
+                 success percentage: {percentage:.2}%",
+                total - failed
+            );
+        }
+        if failed > 0 {
+            std::process::exit(1);
+        }
+        Ok(())
+    }
+
+    /// Collects the outermost `ERROR` and the `MISSING` nodes of a tree.
+    fn find_problems<'tree>(node: &'tree Node, problems: &mut Vec<&'tree Node>) {
+        if node.is_error() || node.is_missing() {
+            problems.push(node);
+        } else if node.has_error() {
+            for child in node.children() {
+                find_problems(child, problems);
+            }
+        }
+    }
+
+    /// A string as a JSON string literal.
+    fn json_string(text: &str) -> String {
+        let mut out = String::new();
+        json::write_string(&mut out, text);
+        out
+    }
+
+    /// A string escaped for use in an XML attribute value.
+    fn xml_attribute(text: &str) -> String {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    }
+
+    /// Writes the parse tables module and `node-types.json` of a grammar,
+    /// printing the path of each file written.
+    fn generate(args: &GenerateArgs) -> io::Result<()> {
+        let path = &args.grammar;
+        let grammar = load_grammar(path)?;
+        validate(&grammar).map_err(|e| invalid_data(path, e.message))?;
+        if has_externals(&grammar) && args.scanner.is_none() {
+            return Err(invalid_data(
+                path,
+                "the grammar declares external tokens; name their scanner with --scanner",
+            ));
+        }
+        let module = emit::rust::emit(&grammar, args.scanner.as_deref())
+            .map_err(|e| invalid_data(path, e))?;
+        let node_types = NodeTypes::from_grammar(&grammar).map_err(|e| invalid_data(path, e))?;
+
+        let output = match &args.output {
+            Some(output) => PathBuf::from(output),
+            None => Path::new(path)
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default(),
+        };
+        fs::create_dir_all(&output)?;
+        let mut files = vec![
+            (format!("{}.rs", grammar.name), module),
+            ("node-types.json".to_string(), node_types.to_json()),
+        ];
+        // Written as tree-sitter writes it, without a final newline.
+        if is_grammar_js(path) {
+            files.push(("grammar.json".to_string(), grammar.to_json()));
+        }
+        for (file, contents) in files {
+            let file = output.join(file);
+            fs::write(&file, contents)?;
+            println!("{}", file.display());
+        }
+        Ok(())
+    }
+
+    /// Prints each capture of a query in each file, in document order, as
+    /// `path:line:column: @capture "text"`.
+    fn query_files(args: &QueryArgs) -> io::Result<()> {
+        let language = load_language(&args.grammar)?;
+        let query = Query::for_language(&language, &read(&args.query)?)
+            .map_err(|e| invalid_data(&args.query, e))?;
+        let mut parser = Parser::new(&language);
+        let cursor = QueryCursor::new();
+        for path in &args.files {
+            let source = read(path)?;
+            let tree = parser.parse(source.as_str());
+            for (query_match, index) in cursor.captures(&query, &tree, source.as_bytes()) {
+                let capture = &query_match.captures[index];
+                let start = capture.node.start_position();
+                println!(
+                    "{path}:{}:{}: @{} {:?}",
+                    start.row + 1,
+                    start.column + 1,
+                    query.capture_names()[capture.index],
+                    &source[capture.node.byte_range()]
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs the corpus tests in the files under each path against a grammar,
+    /// printing a line per test and a diff per failure, and exiting non-zero
+    /// if any failed.
     ///
-    /// The process will also exit with a non-zero status if patch application fails.
-    pub fn main() -> io::Result<()> {
-        let args: Args = facet_args::from_std_args()
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("{e}")))?;
+    /// With `--update`, the expected trees of failing tests are rewritten
+    /// to the trees their inputs parse into instead.
+    fn run_corpus(args: &TestArgs) -> io::Result<()> {
+        let language = load_language(&args.grammar)?;
+        let mut files = Vec::new();
+        if args.paths.is_empty() {
+            collect_files(Path::new("test/corpus"), &[], &mut files)?;
+        }
+        for path in &args.paths {
+            collect_files(Path::new(path), &[], &mut files)?;
+        }
+        let color = io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+        let paint = |code: &str, text: &str| {
+            if color {
+                format!("\x1b[{code}m{text}\x1b[0m")
+            } else {
+                text.to_string()
+            }
+        };
 
-        if args.help {
-            print_usage();
-            std::process::exit(0);
+        let (mut passed, mut skipped, mut rewritten) = (0, 0, 0);
+        let mut failures = Vec::new();
+        for file in files {
+            let source = read(&file)?;
+            let tests = corpus::parse_corpus(&source);
+            println!("{}:", file.display());
+            let mut expectations = Vec::new();
+            for test in &tests {
+                match test.run(&language) {
+                    Outcome::Passed => {
+                        passed += 1;
+                        println!("  {} {}", paint("32", "✓"), test.name);
+                    }
+                    Outcome::Skipped => {
+                        skipped += 1;
+                        println!("  {} {} (skipped)", paint("33", "⌀"), test.name);
+                    }
+                    Outcome::Failed { actual } if args.update && !test.attributes.error => {
+                        rewritten += 1;
+                        println!("  {} {} (updated)", paint("34", "✎"), test.name);
+                        expectations.push((test, actual));
+                    }
+                    Outcome::Failed { actual } => {
+                        println!("  {} {}", paint("31", "✗"), test.name);
+                        let diff = if test.attributes.error {
+                            format!("expected errors, but parsed into:\n{actual}\n")
+                        } else {
+                            corpus::diff(
+                                &corpus::format_sexp(&test.expected),
+                                &corpus::format_sexp(&actual),
+                                color,
+                            )
+                        };
+                        failures.push((test.name.clone(), diff));
+                    }
+                }
+            }
+            if !expectations.is_empty() {
+                let updates: Vec<_> = expectations
+                    .iter()
+                    .map(|(test, actual)| (*test, actual.as_str()))
+                    .collect();
+                fs::write(&file, corpus::update(&source, &updates))?;
+            }
+        }
+
+        for (n, (name, diff)) in failures.iter().enumerate() {
+            println!();
+            println!("{}. {name}:", n + 1);
+            print!("{diff}");
+        }
+        println!();
+        print!(
+            "{passed} passed, {} failed, {skipped} skipped",
+            failures.len()
+        );
+        if args.update {
+            print!(", {rewritten} updated");
+        }
+        println!();
+        if !failures.is_empty() {
+            std::process::exit(1);
+        }
+        Ok(())
+    }
+
+    /// Checks query files against a grammar, printing each problem as
+    /// `path:line:column: message` and exiting non-zero if any were found.
+    fn check_queries(args: &CheckQueriesArgs) -> io::Result<()> {
+        let grammar = load_grammar(&args.grammar)?;
+        let node_types =
+            NodeTypes::from_grammar(&grammar).map_err(|e| invalid_data(&args.grammar, e))?;
+
+        let mut error_count = 0;
+        for path in &args.queries {
+            let source = read(path)?;
+            for error in query::validate(&node_types, &source) {
+                println!(
+                    "{path}:{}:{}: {}",
+                    error.row + 1,
+                    error.column + 1,
+                    error.message
+                );
+                error_count += 1;
+            }
+        }
+        if error_count > 0 {
+            eprintln!("{error_count} problem(s) found");
+            std::process::exit(1);
+        }
+        Ok(())
+    }
+
+    /// Tags the files under each path with a grammar and its `tags.scm`,
+    /// printing the definitions as a sorted ctags file.
+    fn write_tags(args: &TagsArgs) -> io::Result<()> {
+        let language = load_language(&args.grammar)?;
+        let locals = match &args.locals {
+            Some(path) => read(path)?,
+            None => String::new(),
+        };
+        let config = TagsConfiguration::new(&language, &read(&args.tags)?, &locals)
+            .map_err(|e| invalid_data(&args.tags, e))?;
+        let mut files = Vec::new();
+        for path in &args.paths {
+            collect_files(Path::new(path), &args.extension, &mut files)?;
+        }
+        let mut lines = Vec::new();
+        for file in files {
+            let source = fs::read(&file)?;
+            for tag in tags(&config, &source) {
+                if !tag.is_definition {
+                    continue;
+                }
+                let name = String::from_utf8_lossy(&source[tag.name_range.clone()]);
+                let line = tag.span.start.row + 1;
+                lines.push(format!(
+                    "{name}\t{}\t{line};\"\t{}\tline:{line}",
+                    file.display(),
+                    tag.kind
+                ));
+            }
+        }
+        lines.sort();
+        println!("!_TAG_FILE_FORMAT\t2\t/extended format/");
+        println!("!_TAG_FILE_SORTED\t1\t/0=unsorted, 1=sorted, 2=foldcase/");
+        println!("!_TAG_PROGRAM_NAME\tsapling\t//");
+        for line in lines {
+            println!("{line}");
+        }
+        Ok(())
+    }
+
+    /// Prints the metrics of each grammar, as text blocks separated by blank
+    /// lines or as a JSON object per line. State counts are left out of
+    /// grammars whose tables can't be built.
+    fn print_stats(args: &StatsArgs) -> io::Result<()> {
+        let format = args.format.as_deref().unwrap_or("human");
+        if !matches!(format, "human" | "json") {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown format '{format}'; expected human or json"),
+            ));
+        }
+        for (i, path) in args.grammars.iter().enumerate() {
+            let grammar = load_grammar(path)?;
+            let mut stats = GrammarStats::new(&grammar).map_err(|e| invalid_data(path, e))?;
+            if !args.no_tables {
+                match tables::build(&grammar) {
+                    Ok(tables) => stats = stats.with_tables(&tables),
+                    Err(e) => eprintln!("{path}: no parse tables: {e}"),
+                }
+            }
+            if format == "json" {
+                println!(
+                    "{{\"path\":{},\"stats\":{}}}",
+                    json_string(path),
+                    stats.to_json()
+                );
+            } else {
+                if i > 0 {
+                    println!();
+                }
+                print!("{}", stats.to_human());
+            }
+        }
+        Ok(())
+    }
+
+    /// Prints the rule reference graph of a grammar, collapsed and focused
+    /// as asked.
+    fn print_graph(args: &GraphArgs) -> io::Result<()> {
+        let format = args.format.as_deref().unwrap_or("dot");
+        if !matches!(format, "dot" | "mermaid") {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown format '{format}'; expected dot or mermaid"),
+            ));
+        }
+        let mut graph = RuleGraph::new(&load_grammar(&args.grammar)?);
+        if args.collapse_hidden {
+            graph = graph.collapse_hidden();
+        }
+        if let Some(rule) = &args.focus {
+            graph = graph.focus(rule, args.depth.unwrap_or(1)).ok_or_else(|| {
+                let reason = if args.collapse_hidden && rule.starts_with('_') {
+                    "it is hidden, and hidden rules are collapsed"
+                } else {
+                    "there is no such rule"
+                };
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("cannot focus on '{rule}': {reason}"),
+                )
+            })?;
+        }
+        match format {
+            "mermaid" => print!("{}", graph.to_mermaid(args.color)),
+            _ => print!("{}", graph.to_dot(args.color)),
+        }
+        Ok(())
+    }
+
+    /// Writes an SVG railroad diagram of each rule of a grammar and an
+    /// `index.html` page with all of them, printing the path of each file
+    /// written.
+    fn write_railroad(args: &RailroadArgs) -> io::Result<()> {
+        let grammar = load_grammar(&args.grammar)?;
+        let output = args.output.as_ref().map_or_else(
+            || {
+                Path::new(&args.grammar)
+                    .parent()
+                    .unwrap_or(Path::new(""))
+                    .join("railroad")
+            },
+            PathBuf::from,
+        );
+        fs::create_dir_all(&output)?;
+        for (name, rule) in &grammar.rules {
+            let path = output.join(format!("{name}.svg"));
+            fs::write(&path, railroad::render_svg(rule))?;
+            println!("{}", path.display());
         }
+        let path = output.join("index.html");
+        fs::write(&path, railroad::render_html(&grammar))?;
+        println!("{}", path.display());
+        Ok(())
+    }
 
-        // Read input from file or stdin
-        let input = if let Some(path) = args.patch_file {
-            fs::read_to_string(&path)?
-        } else {
-            let mut buf = String::new();
-            io::stdin().read_to_string(&mut buf)?;
-            buf
+    /// Prints a grammar in the EBNF notation asked for.
+    fn print_ebnf(args: &EbnfArgs) -> io::Result<()> {
+        let notation = match args.notation.as_deref().unwrap_or("w3c") {
+            "iso" => Notation::Iso,
+            "w3c" => Notation::W3c,
+            other => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unknown notation '{other}'; expected iso or w3c"),
+                ))
+            }
         };
+        print!("{}", ebnf::emit(&load_grammar(&args.grammar)?, notation));
+        Ok(())
+    }
 
-        // Parse patches from JSON using facet
-        let _grammar: Grammar = facet_json::from_str(&input)
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{e:?}")))?;
-
-        if args.verbose {
-            eprintln!("Loaded grammar");
-        }
-
-        // match set.apply_to_files() {
-        //     Ok(results) => {
-        //         for (file, content) in results {
-        //             if args.dry_run {
-        //                 eprintln!("Would patch: {file}");
-        //                 if args.verbose {
-        //                     println!("=== {file} ===\n{content}");
-        //                 }
-        //             } else {
-        //                 fs::write(&file, content)?;
-        //                 eprintln!("Patched: {file}");
-        //             }
-        //         }
-
-        //         if args.dry_run && !args.verbose {
-        //             eprintln!("Dry run complete. Use -v to see changes.");
-        //         }
-        //     }
-        //     Err(e) => {
-        //         eprintln!("Error: {e}");
-        //         std::process::exit(1);
-        //     }
-        // }
+    /// Translates a grammar written for another parser generator into
+    /// `grammar.json`, printing what could not be carried over as warnings.
+    fn import_grammar(args: &ImportArgs) -> io::Result<()> {
+        let path = Path::new(&args.file);
+        let format = match &args.format {
+            Some(name) => Format::from_name(name).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unknown format '{name}'; expected antlr, pest or ungrammar"),
+                )
+            })?,
+            None => path
+                .extension()
+                .and_then(|extension| Format::from_extension(&extension.to_string_lossy()))
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!(
+                            "cannot tell the format of {}; pass --format antlr, pest or ungrammar",
+                            args.file
+                        ),
+                    )
+                })?,
+        };
+        let name = args.name.clone().unwrap_or_else(|| {
+            path.file_stem()
+                .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned())
+        });
+        let source = read(path)?;
+        let imported = match (format, &args.start) {
+            (Format::Pest, start) => import::pest::import(&name, &source, start.as_deref()),
+            (_, Some(_)) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "--start only applies to pest grammars",
+                ))
+            }
+            (_, None) => import::import(format, &name, &source),
+        };
+        let mut imported = imported.map_err(|e| invalid_data(&args.file, e))?;
+        if let Some(name) = &args.name {
+            imported.grammar.name.clone_from(name);
+        }
+        for diagnostic in &imported.diagnostics {
+            let severity = diagnostic.severity;
+            match diagnostic.line {
+                Some(line) => eprintln!("{}:{line}: {severity}: {}", args.file, diagnostic.message),
+                None => eprintln!("{}: {severity}: {}", args.file, diagnostic.message),
+            }
+        }
+        let json = imported.grammar.to_json() + "\n";
+        if let Some(output) = &args.output {
+            return fs::write(output, json);
+        }
+        print!("{json}");
+        Ok(())
+    }
 
+    /// Adds `path` to `files`, or the files under it if it is a directory,
+    /// leaving out hidden entries and, when `extensions` is not empty,
+    /// files without one of them. An extension may be given with or without
+    /// its leading `.`.
+    fn collect_files(
+        path: &Path,
+        extensions: &[String],
+        files: &mut Vec<PathBuf>,
+    ) -> io::Result<()> {
+        if !path.is_dir() {
+            files.push(path.to_path_buf());
+            return Ok(());
+        }
+        let mut entries = fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<io::Result<Vec<_>>>()?;
+        entries.sort();
+        for entry in entries {
+            let hidden = entry
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+            if hidden {
+                continue;
+            }
+            if entry.is_dir() {
+                collect_files(&entry, extensions, files)?;
+            } else if extensions.is_empty()
+                || entry.extension().is_some_and(|ext| {
+                    extensions
+                        .iter()
+                        .any(|wanted| ext.to_string_lossy() == wanted.trim_start_matches('.'))
+                })
+            {
+                files.push(entry);
+            }
+        }
         Ok(())
     }
 }
@@ -0,0 +1,9 @@
+//! Rust code generation from grammars.
+//!
+//! Each generator renders a grammar, or information computed from it, as
+//! Rust source text. The output is meant to be written to a file by a build
+//! script or checked into a crate, so it is deterministic and depends only
+//! on the public `sapling` API.
+
+pub mod ast;
+pub(crate) mod names;
@@ -0,0 +1,443 @@
+//! Typed syntax tree wrappers.
+//!
+//! [`generate`] renders a grammar's node types as Rust source in the style of
+//! rust-analyzer's `ast` layer. Every named node kind gets a struct wrapping a
+//! borrowed [`Node`](crate::Node), every supertype gets an enum over its
+//! subtypes, and all of them implement [`AstNode`](crate::AstNode), whose
+//! `cast` checks a node's kind before wrapping it.
+//!
+//! Accessors follow the node types. A field that holds at most one node
+//! returns `Option<T>` and one that can hold several returns `Vec<T>`; the
+//! named children outside any field are returned by `children` the same way.
+//! When a field can hold nodes of more than one kind, it gets its own enum,
+//! named after the node and the field. Anonymous tokens have no wrapper type
+//! and are returned as plain `&Node`s.
+
+use super::names::{camel_case, unique_name, words};
+use crate::grammar::{Grammar, GrammarError};
+use crate::node_types::{ChildInfo, NodeInfo, NodeKind, NodeTypes};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+/// Generates typed wrappers for the node kinds of `grammar`.
+///
+/// # Errors
+///
+/// Returns an error if the grammar's node types cannot be computed.
+pub fn generate(grammar: &Grammar) -> Result<String, GrammarError> {
+    let node_types = NodeTypes::from_grammar(grammar)?;
+    Ok(generate_from_node_types(&grammar.name, &node_types))
+}
+
+/// Generates typed wrappers from node types that are already computed.
+#[must_use]
+pub fn generate_from_node_types(grammar_name: &str, node_types: &NodeTypes) -> String {
+    Generator::new(node_types).render(grammar_name)
+}
+
+/// Rust's strict and reserved keywords, which need escaping as method names.
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "static", "struct", "super", "trait", "true", "try", "type", "typeof",
+    "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// Names in scope in generated code, which node types must not shadow.
+const RESERVED_TYPES: &[&str] = &["AstNode", "Node", "None", "Option", "Self", "Some", "Vec"];
+
+/// How an accessor hands out the nodes it finds.
+enum Target {
+    /// Cast to a generated type.
+    Typed(String),
+    /// Returned as a plain node, for anonymous tokens.
+    Token,
+}
+
+impl Target {
+    fn rust_type(&self) -> String {
+        match self {
+            Self::Typed(name) => format!("{name}<'tree>"),
+            Self::Token => "&'tree Node".to_string(),
+        }
+    }
+}
+
+/// Where an accessor finds its nodes.
+enum Source<'a> {
+    Field(&'a str),
+    Children,
+}
+
+struct Generator<'a> {
+    node_types: &'a NodeTypes,
+    /// The type generated for each named node kind.
+    types: BTreeMap<&'a str, String>,
+    supertypes: BTreeSet<&'a str>,
+    /// Every type name handed out so far.
+    taken: BTreeSet<String>,
+}
+
+impl<'a> Generator<'a> {
+    fn new(node_types: &'a NodeTypes) -> Self {
+        let mut generator = Self {
+            node_types,
+            types: BTreeMap::new(),
+            supertypes: BTreeSet::new(),
+            taken: BTreeSet::new(),
+        };
+        for info in node_types.iter().filter(|info| info.named) {
+            let name = unique_name(&type_name(&info.kind), &mut generator.taken);
+            generator.types.insert(&info.kind, name);
+            if info.subtypes.is_some() {
+                generator.supertypes.insert(&info.kind);
+            }
+        }
+        generator
+    }
+
+    fn render(mut self, grammar_name: &str) -> String {
+        let mut out = format!(
+            "// Typed syntax tree for the `{grammar_name}` grammar, generated by sapling.\n\
+             // Do not edit by hand.\n\n\
+             use sapling::{{AstNode, Node}};\n"
+        );
+        let node_types = self.node_types;
+        for info in node_types.iter().filter(|info| info.named) {
+            match &info.subtypes {
+                Some(subtypes) => {
+                    let name = self.types[info.kind.as_str()].clone();
+                    let doc = format!("Any `{}` node.", info.kind.escape_debug());
+                    self.render_enum(&mut out, &name, &doc, subtypes);
+                }
+                None => self.render_struct(&mut out, info),
+            }
+        }
+        out
+    }
+
+    fn render_struct(&mut self, out: &mut String, info: &NodeInfo) {
+        let name = self.types[info.kind.as_str()].clone();
+        let _ = write!(
+            out,
+            "\n/// {} `{}` node.\n\
+             #[derive(Debug, Clone, Copy, PartialEq, Eq)]\n\
+             pub struct {name}<'tree>(&'tree Node);\n\n\
+             impl<'tree> AstNode<'tree> for {name}<'tree> {{\n    \
+                 fn cast(node: &'tree Node) -> Option<Self> {{\n        \
+                     (node.kind() == {:?} && node.is_named()).then_some(Self(node))\n    \
+                 }}\n\n    \
+                 fn syntax(&self) -> &'tree Node {{\n        \
+                     self.0\n    \
+                 }}\n\
+             }}\n",
+            article(&info.kind),
+            info.kind.escape_debug(),
+            info.kind,
+        );
+
+        let mut methods = Vec::new();
+        let mut enums = String::new();
+        for (field, child) in info.fields.iter().flatten() {
+            let doc = format!(
+                "The kinds of node in the `{field}` field of `{}`.",
+                info.kind
+            );
+            let target = self.target(
+                &mut enums,
+                &format!("{name}{}", type_name(field)),
+                &doc,
+                child,
+            );
+            methods.push(accessor(
+                &format!("The `{field}` field."),
+                &method_name(field),
+                &target,
+                child,
+                &Source::Field(field),
+            ));
+        }
+        if let Some(children) = &info.children {
+            let has_children_field = info
+                .fields
+                .as_ref()
+                .is_some_and(|fields| fields.contains_key("children"));
+            let method = if has_children_field {
+                "unnamed_children"
+            } else {
+                "children"
+            };
+            let doc = format!(
+                "The kinds of node among the unnamed children of `{}`.",
+                info.kind
+            );
+            let target = self.target(&mut enums, &format!("{name}Child"), &doc, children);
+            methods.push(accessor(
+                "The named children that are not in a field.",
+                method,
+                &target,
+                children,
+                &Source::Children,
+            ));
+        }
+        if !methods.is_empty() {
+            let _ = write!(
+                out,
+                "\nimpl<'tree> {name}<'tree> {{\n{}}}\n",
+                methods.join("\n")
+            );
+        }
+        out.push_str(&enums);
+    }
+
+    /// Picks the type an accessor returns, generating an enum named `name`
+    /// when the nodes can be of several kinds.
+    fn target(&mut self, enums: &mut String, name: &str, doc: &str, info: &ChildInfo) -> Target {
+        match info.types.as_slice() {
+            [] => Target::Token,
+            [kind] => match self.types.get(kind.kind.as_str()) {
+                Some(name) if kind.named => Target::Typed(name.clone()),
+                _ => Target::Token,
+            },
+            kinds => {
+                let name = unique_name(name, &mut self.taken);
+                self.render_enum(enums, &name, doc, kinds);
+                Target::Typed(name)
+            }
+        }
+    }
+
+    fn render_enum(&self, out: &mut String, name: &str, doc: &str, kinds: &[NodeKind]) {
+        let mut variant_names = BTreeSet::new();
+        let mut declarations = String::new();
+        let mut arms = String::new();
+        let mut fallbacks = Vec::new();
+        let mut syntax_arms = String::new();
+        let mut tokens = Vec::new();
+        for kind in kinds {
+            let typed = self.types.get(kind.kind.as_str()).filter(|_| kind.named);
+            let base = match typed {
+                Some(ty) => ty.clone(),
+                None => token_name(&kind.kind),
+            };
+            let variant = unique_name(&base, &mut variant_names);
+            let pattern = format!("({:?}, {})", kind.kind, kind.named);
+            if let Some(ty) = typed {
+                let _ = write!(
+                    declarations,
+                    "    /// {} `{}` node.\n    {variant}({ty}<'tree>),\n",
+                    article(&kind.kind),
+                    kind.kind.escape_debug()
+                );
+                if self.supertypes.contains(kind.kind.as_str()) {
+                    fallbacks.push(format!("{ty}::cast(node).map(Self::{variant})"));
+                } else {
+                    let _ = writeln!(
+                        arms,
+                        "            {pattern} => Some(Self::{variant}({ty}(node))),"
+                    );
+                }
+                let _ = writeln!(
+                    syntax_arms,
+                    "            Self::{variant}(node) => node.syntax(),"
+                );
+            } else {
+                let _ = write!(
+                    declarations,
+                    "    /// The `{}` token.\n    {variant}(&'tree Node),\n",
+                    kind.kind.escape_debug()
+                );
+                let _ = writeln!(
+                    arms,
+                    "            {pattern} => Some(Self::{variant}(node)),"
+                );
+                tokens.push(format!("Self::{variant}(node)"));
+            }
+        }
+        if !tokens.is_empty() {
+            let _ = writeln!(syntax_arms, "            {} => node,", tokens.join(" | "));
+        }
+
+        let cast = if arms.is_empty() {
+            format!("        {}\n", or_else_chain(&fallbacks, 12))
+        } else {
+            format!(
+                "        match (node.kind(), node.is_named()) {{\n{arms}            \
+                 _ => {},\n        }}\n",
+                or_else_chain(&fallbacks, 16)
+            )
+        };
+        let _ = write!(
+            out,
+            "\n/// {doc}\n\
+             #[derive(Debug, Clone, Copy, PartialEq, Eq)]\n\
+             pub enum {name}<'tree> {{\n{declarations}}}\n\n\
+             impl<'tree> AstNode<'tree> for {name}<'tree> {{\n    \
+                 fn cast(node: &'tree Node) -> Option<Self> {{\n{cast}    }}\n\n    \
+                 fn syntax(&self) -> &'tree Node {{\n        \
+                     match *self {{\n{syntax_arms}        }}\n    \
+                 }}\n\
+             }}\n"
+        );
+    }
+}
+
+/// Chains casts to each supertype with `or_else`, continuing lines at
+/// `indent`.
+fn or_else_chain(casts: &[String], indent: usize) -> String {
+    match casts.split_first() {
+        Some((first, rest)) => rest.iter().fold(first.clone(), |chain, next| {
+            format!("{chain}\n{:indent$}.or_else(|| {next})", "")
+        }),
+        None => "None".to_string(),
+    }
+}
+
+/// Renders one accessor method.
+fn accessor(doc: &str, method: &str, target: &Target, info: &ChildInfo, source: &Source) -> String {
+    let nodes = match source {
+        Source::Field(field) => {
+            let lookup = if info.multiple {
+                "children_by_field_name"
+            } else {
+                "child_by_field_name"
+            };
+            format!("self.0\n            .{lookup}({field:?})")
+        }
+        Source::Children => {
+            "self.0\n            .named_children()\n            .filter(|node| node.field_name().is_none())"
+                .to_string()
+        }
+    };
+    let (ty, body) = match (target, info.multiple, source) {
+        (Target::Typed(name), true, _) => (
+            format!("Vec<{}>", target.rust_type()),
+            format!("{nodes}\n            .filter_map({name}::cast)\n            .collect()"),
+        ),
+        (Target::Token, true, _) => (
+            format!("Vec<{}>", target.rust_type()),
+            format!("{nodes}\n            .collect()"),
+        ),
+        (Target::Typed(name), false, Source::Field(_)) => (
+            format!("Option<{}>", target.rust_type()),
+            format!("{nodes}\n            .and_then({name}::cast)"),
+        ),
+        (Target::Typed(name), false, Source::Children) => (
+            format!("Option<{}>", target.rust_type()),
+            format!("{nodes}\n            .find_map({name}::cast)"),
+        ),
+        (Target::Token, false, Source::Field(_)) => {
+            (format!("Option<{}>", target.rust_type()), nodes)
+        }
+        (Target::Token, false, Source::Children) => (
+            format!("Option<{}>", target.rust_type()),
+            "self.0\n            .named_children()\n            .find(|node| node.field_name().is_none())"
+                .to_string(),
+        ),
+    };
+    format!(
+        "    /// {doc}\n    #[must_use]\n    pub fn {method}(self) -> {ty} {{\n        {body}\n    }}\n"
+    )
+}
+
+/// The Rust type name for a named node kind, e.g. `FunctionDefinition`.
+///
+/// Names the generated code itself refers to, like `Node` or `None`, get a
+/// `Node` suffix so that they are not shadowed.
+fn type_name(kind: &str) -> String {
+    let mut name = camel_case(&words(kind));
+    if RESERVED_TYPES.contains(&name.as_str()) {
+        name.push_str("Node");
+    }
+    name
+}
+
+/// The enum variant name for an anonymous token, e.g. `PlusEq` for `+=`.
+fn token_name(token: &str) -> String {
+    camel_case(&words(token))
+}
+
+/// The accessor name for a field, escaped if it is a keyword.
+fn method_name(field: &str) -> String {
+    let mut name = words(field)
+        .iter()
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join("_");
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert_str(0, "field_");
+    }
+    match name.as_str() {
+        "self" | "super" | "crate" | "cast" | "syntax" => format!("{name}_field"),
+        keyword if KEYWORDS.contains(&keyword) => format!("r#{name}"),
+        _ => name,
+    }
+}
+
+/// The indefinite article to start a doc comment about `kind` with.
+fn article(kind: &str) -> &'static str {
+    if kind.starts_with(|c: char| "aeiouAEIOU".contains(c)) {
+        "An"
+    } else {
+        "A"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::parse_grammar;
+
+    #[test]
+    fn test_names() {
+        assert_eq!(type_name("function_definition"), "FunctionDefinition");
+        assert_eq!(type_name("_expression"), "Expression");
+        assert_eq!(type_name("self"), "SelfNode");
+        assert_eq!(type_name("none"), "NoneNode");
+        assert_eq!(token_name("+="), "PlusEq");
+        assert_eq!(token_name("_"), "Underscore");
+        assert_eq!(token_name("#include"), "PoundInclude");
+        assert_eq!(token_name("else if"), "ElseIf");
+        assert_eq!(method_name("type"), "r#type");
+        assert_eq!(method_name("self"), "self_field");
+        assert_eq!(method_name("return_type"), "return_type");
+    }
+
+    #[test]
+    fn test_generated_shapes() {
+        let grammar = parse_grammar(
+            r#"{
+                "name": "calc",
+                "supertypes": ["_expression"],
+                "rules": {
+                    "program": {"type": "REPEAT", "content": {"type": "SYMBOL", "name": "_expression"}},
+                    "_expression": {"type": "CHOICE", "members": [
+                        {"type": "SYMBOL", "name": "binary"},
+                        {"type": "SYMBOL", "name": "number"}
+                    ]},
+                    "binary": {"type": "PREC_LEFT", "value": 1, "content": {"type": "SEQ", "members": [
+                        {"type": "FIELD", "name": "left", "content": {"type": "SYMBOL", "name": "_expression"}},
+                        {"type": "FIELD", "name": "operator", "content": {"type": "CHOICE", "members": [
+                            {"type": "STRING", "value": "+"},
+                            {"type": "STRING", "value": "-"}
+                        ]}},
+                        {"type": "FIELD", "name": "right", "content": {"type": "SYMBOL", "name": "_expression"}}
+                    ]}},
+                    "number": {"type": "PATTERN", "value": "\\d+"}
+                }
+            }"#,
+        )
+        .unwrap();
+        let code = generate(&grammar).unwrap();
+
+        assert!(code.contains("pub enum Expression<'tree> {"));
+        assert!(code.contains("pub struct Binary<'tree>(&'tree Node);"));
+        assert!(code.contains("pub fn left(self) -> Option<Expression<'tree>> {"));
+        assert!(code.contains(
+            "pub enum BinaryOperator<'tree> {\n    /// The `+` token.\n    Plus(&'tree Node),"
+        ));
+        assert!(code.contains("pub fn children(self) -> Vec<Expression<'tree>> {"));
+        assert!(!code.contains("impl<'tree> Number<'tree>"));
+    }
+}
@@ -0,0 +1,109 @@
+//! Identifier construction shared by the code generators.
+//!
+//! Grammars name things freely: rule names can collide once cased, and
+//! tokens like `+=` have no letters at all. These helpers turn any node kind
+//! into a valid Rust identifier, spelling punctuation out the way
+//! tree-sitter's C generator does.
+
+use std::collections::BTreeSet;
+
+/// Appends a number to `name` until it is not in `taken`, then takes it.
+pub(crate) fn unique_name(name: &str, taken: &mut BTreeSet<String>) -> String {
+    let mut candidate = name.to_string();
+    let mut n = 1;
+    while taken.contains(&candidate) {
+        n += 1;
+        candidate = format!("{name}{n}");
+    }
+    taken.insert(candidate.clone());
+    candidate
+}
+
+/// The name tree-sitter's C code generator gives a punctuation character.
+fn punctuation_name(c: char) -> Option<&'static str> {
+    Some(match c {
+        '~' => "Tilde",
+        '`' => "Backtick",
+        '!' => "Bang",
+        '@' => "At",
+        '#' => "Pound",
+        '$' => "Dollar",
+        '%' => "Percent",
+        '^' => "Caret",
+        '&' => "Amp",
+        '*' => "Star",
+        '(' => "Lparen",
+        ')' => "Rparen",
+        '-' => "Dash",
+        '+' => "Plus",
+        '=' => "Eq",
+        '{' => "Lbrace",
+        '}' => "Rbrace",
+        '[' => "Lbrack",
+        ']' => "Rbrack",
+        '\\' => "Backslash",
+        '|' => "Pipe",
+        ':' => "Colon",
+        ';' => "Semi",
+        '"' => "Dquote",
+        '\'' => "Squote",
+        '<' => "Lt",
+        '>' => "Gt",
+        ',' => "Comma",
+        '.' => "Dot",
+        '?' => "Qmark",
+        '/' => "Slash",
+        '_' => "Underscore",
+        '\n' => "Lf",
+        '\r' => "Cr",
+        '\t' => "Tab",
+        '\0' => "Nul",
+        _ => return None,
+    })
+}
+
+/// Splits a node kind into words. Underscores and whitespace separate
+/// words, and any other punctuation becomes a word of its own, so that
+/// tokens like `+=` still get a name.
+pub(crate) fn words(text: &str) -> Vec<String> {
+    let only_punctuation = !text.chars().any(char::is_alphanumeric);
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() {
+            word.push(c);
+            continue;
+        }
+        if !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        if c.is_whitespace() && !matches!(c, '\n' | '\r' | '\t') || c == '_' && !only_punctuation {
+            continue;
+        }
+        match punctuation_name(c) {
+            Some(name) => words.push(name.to_string()),
+            None => words.push(format!("U{:X}", u32::from(c))),
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// Joins words in `UpperCamelCase`, prefixing names that would not start
+/// with a letter.
+pub(crate) fn camel_case(words: &[String]) -> String {
+    let mut out = String::new();
+    for word in words {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            out.extend(first.to_uppercase());
+            out.push_str(chars.as_str());
+        }
+    }
+    if out.is_empty() || out.starts_with(|c: char| c.is_ascii_digit()) {
+        out.insert_str(0, "Kind");
+    }
+    out
+}
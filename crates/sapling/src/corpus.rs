@@ -0,0 +1,521 @@
+//! Tree-sitter's test corpus format.
+//!
+//! Grammar repositories keep their tests in `test/corpus/*.txt` files. Each
+//! file holds any number of tests, written as a header with the test's
+//! name, the input, a divider and the tree the input should parse into:
+//!
+//! ```text
+//! ==============
+//! Let statements
+//! ==============
+//! let x = 1;
+//! ---
+//!
+//! (program
+//!   (let name: (identifier) value: (number)))
+//! ```
+//!
+//! Trees are compared as [`Node::to_sexp`] renders them, after normalizing
+//! the whitespace of the expected one, and field names are only compared if
+//! the expected tree has some. Lines of the expected tree starting with `;`
+//! are comments. For inputs that hold lines of `=` or `-` themselves, the
+//! header and divider lines can end in a suffix, as in `===|||` and
+//! `---|||`, and when there are several dividers the longest one separates
+//! the input from the tree.
+//!
+//! Attribute lines in the header change how a test is run:
+//!
+//! - `:skip` skips it.
+//! - `:error` expects the input to parse with errors, whatever the tree.
+//! - `:language(NAME)` runs it with the named language only, and can be
+//!   given more than once.
+//! - `:platform(OS)` runs it only on that operating system, as named by
+//!   [`std::env::consts::OS`].
+
+use crate::language::Language;
+use crate::parser::Parser;
+use std::fmt::Write;
+use std::ops::Range;
+
+/// One test of a corpus file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorpusTest {
+    /// The name given in the header.
+    pub name: String,
+    /// The text to parse, without the line break before the divider.
+    pub input: String,
+    /// The expected tree, normalized with [`normalize_sexp`].
+    pub expected: String,
+    /// The attributes given in the header.
+    pub attributes: Attributes,
+    /// The bytes of the file after the divider line, which hold the
+    /// expected tree, up to the next header.
+    pub output_range: Range<usize>,
+}
+
+/// How a [`CorpusTest`] is run, from the attribute lines of its header.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Attributes {
+    /// Whether the test is skipped.
+    pub skip: bool,
+    /// Whether the input should parse with errors.
+    pub error: bool,
+    /// The languages the test runs with, or none for any.
+    pub languages: Vec<String>,
+    /// The operating systems the test runs on, or none for any.
+    pub platforms: Vec<String>,
+}
+
+/// The result of running a [`CorpusTest`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// The input parsed into the expected tree.
+    Passed,
+    /// The input parsed into another tree, given as an S-expression.
+    Failed {
+        /// The tree the input parsed into, without field names if the
+        /// expected tree has none.
+        actual: String,
+    },
+    /// The test was not run.
+    Skipped,
+}
+
+impl CorpusTest {
+    /// Parses the input with `language` and compares the tree with the
+    /// expected one. Tests that are marked `:skip`, name other languages
+    /// or are for other platforms are skipped.
+    #[must_use]
+    pub fn run(&self, language: &Language) -> Outcome {
+        let attributes = &self.attributes;
+        let other_language = !attributes.languages.is_empty()
+            && !attributes
+                .languages
+                .iter()
+                .any(|name| *name == language.name);
+        let other_platform = !attributes.platforms.is_empty()
+            && !attributes
+                .platforms
+                .iter()
+                .any(|os| os == std::env::consts::OS);
+        if attributes.skip || other_language || other_platform {
+            return Outcome::Skipped;
+        }
+        let tree = Parser::new(language).parse(self.input.as_str());
+        let mut actual = tree.to_sexp();
+        if !self.expected.contains(": ") {
+            actual = strip_fields(&actual);
+        }
+        let passed = if attributes.error {
+            tree.has_error()
+        } else {
+            actual == self.expected
+        };
+        if passed {
+            Outcome::Passed
+        } else {
+            Outcome::Failed { actual }
+        }
+    }
+}
+
+/// Reads the tests of a corpus file. Text before the first header is
+/// ignored, as is a test without a divider.
+#[must_use]
+pub fn parse_corpus(source: &str) -> Vec<CorpusTest> {
+    let lines = lines(source);
+    let mut headers = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        match header(source, &lines, i) {
+            Some((end, suffix)) => {
+                headers.push((i, end, suffix));
+                i = end + 1;
+            }
+            None => i += 1,
+        }
+    }
+
+    let mut tests = Vec::new();
+    for (n, &(start, end, suffix)) in headers.iter().enumerate() {
+        let body_end = headers.get(n + 1).map_or(lines.len(), |next| next.0);
+        // The longest divider and its number of dashes.
+        let mut divider: Option<(usize, usize)> = None;
+        for (i, line) in lines.iter().enumerate().take(body_end).skip(end + 1) {
+            let text = line_text(source, line);
+            let rest = text.trim_start_matches('-');
+            let dashes = text.len() - rest.len();
+            if dashes >= 3 && rest == suffix && divider.is_none_or(|(_, longest)| dashes > longest)
+            {
+                divider = Some((i, dashes));
+            }
+        }
+        let Some((divider, _)) = divider else {
+            continue;
+        };
+
+        let mut names = Vec::new();
+        let mut attributes = Attributes::default();
+        for line in &lines[start + 1..end] {
+            let text = line_text(source, line).trim();
+            if text.starts_with(':') {
+                for attribute in text.split_whitespace() {
+                    attributes.add(attribute);
+                }
+            } else if !text.is_empty() {
+                names.push(text);
+            }
+        }
+
+        let mut input = &source[lines[end + 1].start..lines[divider].start];
+        input = input.strip_suffix('\n').unwrap_or(input);
+        input = input.strip_suffix('\r').unwrap_or(input);
+        let output_range =
+            lines[divider].next..lines.get(body_end).map_or(source.len(), |line| line.start);
+        tests.push(CorpusTest {
+            name: names.join(" "),
+            input: input.to_string(),
+            expected: normalize_sexp(&source[output_range.clone()]),
+            attributes,
+            output_range,
+        });
+    }
+    tests
+}
+
+impl Attributes {
+    /// Records one `:name` or `:name(value)` attribute, ignoring unknown
+    /// ones.
+    fn add(&mut self, attribute: &str) {
+        let attribute = attribute.trim_start_matches(':');
+        let (name, value) = match attribute.split_once('(') {
+            Some((name, value)) => (name, value.strip_suffix(')').unwrap_or(value)),
+            None => (attribute, ""),
+        };
+        match name {
+            "skip" => self.skip = true,
+            "error" => self.error = true,
+            "language" => self.languages.push(value.to_string()),
+            "platform" => self.platforms.push(value.to_string()),
+            _ => {}
+        }
+    }
+}
+
+/// The bytes of one line of a file: where it starts, where its text ends
+/// before any line break, and where the next line starts.
+struct Line {
+    start: usize,
+    end: usize,
+    next: usize,
+}
+
+fn lines(source: &str) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    while start < source.len() {
+        let (end, next) = match source[start..].find('\n') {
+            Some(i) => (start + i, start + i + 1),
+            None => (source.len(), source.len()),
+        };
+        lines.push(Line { start, end, next });
+        start = next;
+    }
+    lines
+}
+
+/// The text of `line`, without a trailing carriage return.
+fn line_text<'s>(source: &'s str, line: &Line) -> &'s str {
+    let text = &source[line.start..line.end];
+    text.strip_suffix('\r').unwrap_or(text)
+}
+
+/// The last line and suffix of the header starting at line `i`, if there
+/// is one: a line of at least three `=`, lines of names and attributes,
+/// and another such line with the same suffix.
+fn header<'s>(source: &'s str, lines: &[Line], i: usize) -> Option<(usize, &'s str)> {
+    let rule = |i: usize| {
+        let text = line_text(source, lines.get(i)?);
+        let suffix = text.trim_start_matches('=');
+        (text.len() - suffix.len() >= 3).then_some(suffix)
+    };
+    let suffix = rule(i)?;
+    let mut end = i + 1;
+    while lines
+        .get(end)
+        .is_some_and(|line| !line_text(source, line).starts_with('='))
+    {
+        end += 1;
+    }
+    (end > i + 1 && rule(end)? == suffix).then_some((end, suffix))
+}
+
+/// The tokens of an S-expression: parentheses, quoted strings, and words
+/// such as node kinds and field names.
+fn tokens(sexp: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut chars = sexp.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let end = match c {
+            '(' | ')' => start + 1,
+            '"' => {
+                let mut end = sexp.len();
+                while let Some((i, c)) = chars.next() {
+                    match c {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '"' => {
+                            end = i + 1;
+                            break;
+                        }
+                        _ => {}
+                    }
+                }
+                end
+            }
+            c if c.is_whitespace() => continue,
+            _ => {
+                while chars
+                    .next_if(|&(_, c)| !c.is_whitespace() && c != '(' && c != ')')
+                    .is_some()
+                {}
+                chars.peek().map_or(sexp.len(), |&(i, _)| i)
+            }
+        };
+        tokens.push(&sexp[start..end]);
+    }
+    tokens
+}
+
+/// Joins tokens the way [`Node::to_sexp`](crate::Node::to_sexp) does.
+fn join(tokens: &[&str]) -> String {
+    let mut sexp = String::new();
+    for token in tokens {
+        if !(sexp.is_empty() || sexp.ends_with('(') || *token == ")") {
+            sexp.push(' ');
+        }
+        sexp.push_str(token);
+    }
+    sexp
+}
+
+/// Removes comments and extra whitespace from an S-expression, so that it
+/// can be compared with [`Node::to_sexp`](crate::Node::to_sexp) output.
+#[must_use]
+pub fn normalize_sexp(sexp: &str) -> String {
+    let code: Vec<&str> = sexp
+        .lines()
+        .filter(|line| !line.trim_start().starts_with(';'))
+        .collect();
+    join(&tokens(&code.join("\n")))
+}
+
+/// Removes the field names from an S-expression.
+fn strip_fields(sexp: &str) -> String {
+    let tokens: Vec<&str> = tokens(sexp)
+        .into_iter()
+        .filter(|token| token.starts_with('"') || !token.ends_with(':'))
+        .collect();
+    join(&tokens)
+}
+
+/// Lays out an S-expression over several lines: each node starts a line,
+/// after its field name if it has one, indented by two spaces per level.
+#[must_use]
+pub fn format_sexp(sexp: &str) -> String {
+    let mut formatted = String::new();
+    let mut depth = 0usize;
+    let mut after_field = false;
+    for token in tokens(sexp) {
+        let is_field = !token.starts_with('"') && token.ends_with(':');
+        if token == ")" {
+            depth = depth.saturating_sub(1);
+        } else if (token == "(" || is_field) && !after_field {
+            if !formatted.is_empty() {
+                formatted.push('\n');
+                formatted.push_str(&"  ".repeat(depth));
+            }
+        } else if !(formatted.is_empty() || formatted.ends_with('(') || after_field) {
+            formatted.push(' ');
+        }
+        formatted.push_str(token);
+        if token == "(" {
+            depth += 1;
+        }
+        if is_field {
+            formatted.push(' ');
+        }
+        after_field = is_field;
+    }
+    formatted
+}
+
+/// A line diff from `expected` to `actual`. Lines only in `expected` start
+/// with `-`, lines only in `actual` with `+`, and lines in both with a
+/// space; with `color`, removed lines are red and added ones green.
+#[must_use]
+pub fn diff(expected: &str, actual: &str, color: bool) -> String {
+    let old: Vec<&str> = expected.lines().collect();
+    let new: Vec<&str> = actual.lines().collect();
+    // The length of the longest common subsequence of `old[i..]` and
+    // `new[j..]`.
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut out = String::new();
+    let mut line = |sign: char, text: &str| {
+        let (on, off) = match (color, sign) {
+            (true, '-') => ("\x1b[31m", "\x1b[0m"),
+            (true, '+') => ("\x1b[32m", "\x1b[0m"),
+            _ => ("", ""),
+        };
+        let _ = writeln!(out, "{on}{sign}{text}{off}");
+    };
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            line(' ', old[i]);
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || common[i + 1][j] >= common[i][j + 1]) {
+            line('-', old[i]);
+            i += 1;
+        } else {
+            line('+', new[j]);
+            j += 1;
+        }
+    }
+    out
+}
+
+/// Rewrites a corpus file, replacing the expected tree of each test with
+/// the S-expression given for it, laid out with [`format_sexp`].
+#[must_use]
+pub fn update(source: &str, updates: &[(&CorpusTest, &str)]) -> String {
+    let mut updates = updates.to_vec();
+    updates.sort_by_key(|(test, _)| test.output_range.start);
+    let mut rewritten = String::with_capacity(source.len());
+    let mut copied = 0;
+    for (test, sexp) in updates {
+        let range = &test.output_range;
+        rewritten.push_str(&source[copied..range.start]);
+        if !rewritten.ends_with('\n') {
+            rewritten.push('\n');
+        }
+        rewritten.push('\n');
+        rewritten.push_str(&format_sexp(sexp));
+        rewritten.push('\n');
+        if range.end < source.len() {
+            rewritten.push('\n');
+        }
+        copied = range.end;
+    }
+    rewritten.push_str(&source[copied..]);
+    rewritten
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CORPUS: &str = "\
+Tests of lets.
+
+=====
+Let
+=====
+let x = 1;
+---
+
+(program
+  ; The only statement.
+  (let name: (identifier) value: (number)))
+
+=====|||
+Dashes
+:skip
+:language(calc) :platform(linux)
+=====|||
+--- a
+----
+---|||
+(program)
+";
+
+    #[test]
+    fn test_parse_corpus() {
+        let tests = parse_corpus(CORPUS);
+        assert_eq!(tests.len(), 2);
+        assert_eq!(tests[0].name, "Let");
+        assert_eq!(tests[0].input, "let x = 1;");
+        assert_eq!(
+            tests[0].expected,
+            "(program (let name: (identifier) value: (number)))"
+        );
+        assert_eq!(tests[0].attributes, Attributes::default());
+        assert!(CORPUS[tests[0].output_range.clone()].ends_with(")))\n\n"));
+
+        assert_eq!(tests[1].name, "Dashes");
+        assert_eq!(tests[1].input, "--- a\n----");
+        assert_eq!(tests[1].expected, "(program)");
+        assert_eq!(
+            tests[1].attributes,
+            Attributes {
+                skip: true,
+                error: false,
+                languages: vec!["calc".to_string()],
+                platforms: vec!["linux".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn test_longest_divider_separates_input() {
+        let tests = parse_corpus("===\nt\n===\na\n---\nb\n-----\n\n(x)\n");
+        assert_eq!(tests[0].input, "a\n---\nb");
+        assert_eq!(tests[0].expected, "(x)");
+    }
+
+    #[test]
+    fn test_sexps_are_normalized_and_formatted() {
+        let sexp = "(program (let name: (identifier) (MISSING \")\")) (ERROR))";
+        assert_eq!(normalize_sexp(&format_sexp(sexp)), sexp);
+        assert_eq!(
+            format_sexp(sexp),
+            "(program\n  (let\n    name: (identifier)\n    (MISSING \")\"))\n  (ERROR))"
+        );
+        assert_eq!(
+            strip_fields(sexp),
+            "(program (let (identifier) (MISSING \")\")) (ERROR))"
+        );
+    }
+
+    #[test]
+    fn test_diff() {
+        assert_eq!(diff("a\nb\nc", "a\nc\nd", false), " a\n-b\n c\n+d\n");
+        assert_eq!(
+            diff("a", "b", true),
+            "\x1b[31m-a\x1b[0m\n\x1b[32m+b\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_update() {
+        let tests = parse_corpus(CORPUS);
+        let updated = update(CORPUS, &[(&tests[0], "(program (let name: (identifier)))")]);
+        assert!(updated.contains("---\n\n(program\n  (let\n    name: (identifier)))\n\n=====|||"));
+        assert_eq!(parse_corpus(&updated)[1].expected, tests[1].expected);
+
+        let updated = update(CORPUS, &[(&tests[1], "(program (x))")]);
+        assert!(updated.ends_with("---|||\n\n(program\n  (x))\n"));
+    }
+}
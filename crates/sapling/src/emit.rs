@@ -0,0 +1,14 @@
+//! Grammar translation for parser generators.
+//!
+//! Where [`codegen`](crate::codegen) writes Rust against sapling's own API,
+//! emitters render a grammar as a parser. [`lalrpop`](crate::emit::lalrpop) writes another tool's
+//! input language; tree-sitter grammars can rely on features that tool
+//! lacks, so it checks for them first and reports every one it finds rather
+//! than producing a grammar that silently means something else. [`rust`](crate::emit::rust)
+//! writes the parse and lex tables themselves, for sapling's own
+//! [`Parser`](crate::Parser). [`ebnf`](crate::emit::ebnf) renders a grammar
+//! for people instead, as ISO or W3C EBNF.
+
+pub mod ebnf;
+pub mod lalrpop;
+pub mod rust;
@@ -0,0 +1,615 @@
+//! EBNF rendering of grammars, for reading and reviewing.
+//!
+//! [`emit`] writes a grammar in one of two
+//! notations: ISO/IEC 14977 EBNF, or the EBNF of the W3C's XML
+//! specification. Either is a production per rule, in declaration order,
+//! with choices spread over lines so grammars diff well.
+//!
+//! Patterns are parsed the way the lexer parses them and written out as
+//! notation rather than as regular expressions: character classes become
+//! W3C classes such as `[a-zA-Z_]`, or, in ISO EBNF, which has none,
+//! alternatives of single characters when they are small and special
+//! sequences such as `? [a-zA-Z_] ?` when they are not. Patterns that
+//! can't be parsed are kept as they are, in a special sequence or a
+//! comment. Anchors and word boundaries are dropped, as the lexer ignores
+//! them too.
+//!
+//! What EBNF has no notation for (precedence, fields, aliases and
+//! `token()` wrappers) is noted in a comment in front of what it applies
+//! to, and the grammar's extras, external tokens, word token and declared
+//! conflicts are listed in comments before the rules.
+
+use crate::grammar::{Grammar, Rule, RuleType, RuleValue};
+use crate::tables::nfa::javascript_classes;
+use regex_syntax::hir::{Class, Hir, HirKind};
+use std::fmt::Write;
+
+/// The largest class that ISO EBNF spells out as alternatives.
+const ISO_CLASS_ALTERNATIVES: u32 = 4;
+
+/// An EBNF dialect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Notation {
+    /// ISO/IEC 14977: `rule = a , { b } , [ c ] ;`.
+    Iso,
+    /// The notation of the XML specification: `rule ::= a b* c?`.
+    W3c,
+}
+
+/// An expression in either notation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    Empty,
+    /// A string of printable characters, not containing both quotes.
+    Literal(String),
+    /// A single character that is not printable.
+    Char(u32),
+    /// A set of characters, as sorted, disjoint, inclusive ranges.
+    Class(Vec<(u32, u32)>),
+    Symbol(String),
+    Seq(Vec<Expr>),
+    Choice(Vec<Expr>),
+    Optional(Box<Expr>),
+    Repeat(Box<Expr>),
+    Repeat1(Box<Expr>),
+    /// An expression with a note on what applies to it.
+    Note(String, Box<Expr>),
+    /// Something with no notation, described in words.
+    Special(String),
+}
+
+/// How tightly an expression binds, for deciding on parentheses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Level {
+    Choice,
+    Seq,
+    Postfix,
+    Atom,
+}
+
+/// Renders `grammar` as EBNF in `notation`.
+#[must_use]
+pub fn emit(grammar: &Grammar, notation: Notation) -> String {
+    let (open, close) = comment_delimiters(notation);
+    let name = match notation {
+        Notation::Iso => "ISO/IEC 14977 EBNF",
+        Notation::W3c => "W3C EBNF",
+    };
+    let mut out = format!(
+        "{open} The {} grammar, in {name}, written by sapling. {close}\n",
+        grammar.name
+    );
+
+    let mut notes = Vec::new();
+    let list = |rules: &[Rule]| {
+        rules
+            .iter()
+            .map(|rule| render(&Expr::from_rule(rule), notation, Level::Choice))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    if let Some(extras) = &grammar.extras {
+        notes.push(format!("extras: {}", list(extras)));
+    }
+    if let Some(externals) = grammar.externals.as_ref().filter(|e| !e.is_empty()) {
+        notes.push(format!("external tokens: {}", list(externals)));
+    }
+    if let Some(word) = &grammar.word {
+        notes.push(format!("word token: {word}"));
+    }
+    for conflict in grammar.conflicts.iter().flatten() {
+        notes.push(format!("expected conflict: {}", conflict.join(", ")));
+    }
+    if !notes.is_empty() {
+        out.push('\n');
+        for note in notes {
+            let _ = writeln!(out, "{open} {} {close}", comment_text(&note, notation));
+        }
+    }
+
+    for (name, rule) in &grammar.rules {
+        out.push('\n');
+        let (define, end) = match notation {
+            Notation::Iso => (" =", " ;"),
+            Notation::W3c => (" ::=", ""),
+        };
+        let _ = write!(out, "{name}{define}");
+        // Notes on the whole rule need no brackets to show what they cover.
+        let mut expr = &Expr::from_rule(rule);
+        while let Expr::Note(note, inner) = expr {
+            let _ = write!(out, " {open} {} {close}", comment_text(note, notation));
+            expr = inner;
+        }
+        match expr {
+            // Top-level alternatives go a line each.
+            Expr::Choice(branches) if branches.len() > 1 => {
+                for (i, branch) in branches.iter().enumerate() {
+                    let lead = if i == 0 { " " } else { "\n    | " };
+                    let _ = write!(out, "{lead}{}", render(branch, notation, Level::Seq));
+                }
+            }
+            Expr::Empty if notation == Notation::Iso => {}
+            _ => {
+                let _ = write!(out, " {}", render(expr, notation, Level::Choice));
+            }
+        }
+        let _ = writeln!(out, "{end}");
+    }
+    out
+}
+
+impl Expr {
+    fn from_rule(rule: &Rule) -> Self {
+        let content = || rule.content.as_deref().map_or(Expr::Empty, Expr::from_rule);
+        let note = |note: String| Expr::Note(note, Box::new(content()));
+        match rule.rule_type {
+            RuleType::Blank => Expr::Empty,
+            RuleType::String => literal(string_value(rule)),
+            RuleType::Pattern => pattern(string_value(rule), rule.flags.as_deref()),
+            RuleType::Symbol => Expr::Symbol(rule.name.clone().unwrap_or_default()),
+            RuleType::Seq => seq(rule.members.iter().map(Expr::from_rule).collect()),
+            RuleType::Choice => {
+                let optional = rule
+                    .members
+                    .iter()
+                    .any(|member| member.rule_type == RuleType::Blank);
+                let choice = choice(
+                    rule.members
+                        .iter()
+                        .filter(|member| member.rule_type != RuleType::Blank)
+                        .map(Expr::from_rule)
+                        .collect(),
+                );
+                if optional && choice != Expr::Empty {
+                    Expr::Optional(Box::new(choice))
+                } else {
+                    choice
+                }
+            }
+            RuleType::Repeat => Expr::Repeat(Box::new(content())),
+            RuleType::Repeat1 => Expr::Repeat1(Box::new(content())),
+            RuleType::Prec => note(format!("prec({})", precedence(rule))),
+            RuleType::PrecLeft => note(format!("prec.left({})", precedence(rule))),
+            RuleType::PrecRight => note(format!("prec.right({})", precedence(rule))),
+            RuleType::PrecDynamic => note(format!("prec.dynamic({})", precedence(rule))),
+            RuleType::Field => note(format!(
+                "field {}",
+                rule.name.as_deref().unwrap_or_default()
+            )),
+            RuleType::Alias => {
+                let alias = string_value(rule);
+                if rule.named == Some(true) {
+                    note(format!("alias {alias}"))
+                } else {
+                    note(format!("alias \"{alias}\""))
+                }
+            }
+            RuleType::Token => note("token".to_string()),
+            RuleType::ImmediateToken => note("token.immediate".to_string()),
+            RuleType::Reserved => note(format!(
+                "reserved({})",
+                rule.context_name.as_deref().unwrap_or_default()
+            )),
+        }
+    }
+
+    fn from_hir(hir: &Hir) -> Self {
+        match hir.kind() {
+            HirKind::Empty | HirKind::Look(_) => Expr::Empty,
+            HirKind::Literal(literal) => self::literal(&String::from_utf8_lossy(&literal.0)),
+            HirKind::Class(Class::Unicode(class)) => class_expr(
+                class
+                    .ranges()
+                    .iter()
+                    .map(|r| (u32::from(r.start()), u32::from(r.end())))
+                    .collect(),
+            ),
+            HirKind::Class(Class::Bytes(class)) => class_expr(
+                class
+                    .ranges()
+                    .iter()
+                    .map(|r| (u32::from(r.start()), u32::from(r.end())))
+                    .collect(),
+            ),
+            HirKind::Capture(capture) => Expr::from_hir(&capture.sub),
+            HirKind::Concat(members) => seq(members.iter().map(Expr::from_hir).collect()),
+            HirKind::Alternation(members) => choice(members.iter().map(Expr::from_hir).collect()),
+            HirKind::Repetition(repetition) => {
+                let sub = Expr::from_hir(&repetition.sub);
+                let mut members = vec![sub.clone(); repetition.min.saturating_sub(1) as usize];
+                match (repetition.min, repetition.max) {
+                    (0, None) => members.push(Expr::Repeat(Box::new(sub))),
+                    (_, None) => members.push(Expr::Repeat1(Box::new(sub))),
+                    (min, Some(max)) => {
+                        if min > 0 {
+                            members.push(sub.clone());
+                        }
+                        // Optional copies nest, so `a{0,2}` is `(a a?)?`.
+                        let mut optional = None;
+                        for _ in min..max {
+                            let inner = match optional.take() {
+                                Some(rest) => seq(vec![sub.clone(), rest]),
+                                None => sub.clone(),
+                            };
+                            optional = Some(Expr::Optional(Box::new(inner)));
+                        }
+                        members.extend(optional);
+                    }
+                }
+                seq(members)
+            }
+        }
+    }
+
+    fn level(&self) -> Level {
+        match self {
+            Expr::Choice(branches) if branches.len() > 1 => Level::Choice,
+            Expr::Seq(members) if members.len() > 1 => Level::Seq,
+            // A note is bracketed with what it covers, if that is compound.
+            Expr::Optional(_) | Expr::Repeat(_) | Expr::Repeat1(_) | Expr::Note(..) => {
+                Level::Postfix
+            }
+            _ => Level::Atom,
+        }
+    }
+}
+
+fn string_value(rule: &Rule) -> &str {
+    match &rule.value {
+        Some(RuleValue::String(value)) => value,
+        _ => "",
+    }
+}
+
+fn precedence(rule: &Rule) -> String {
+    match &rule.value {
+        Some(RuleValue::Integer(value)) => value.to_string(),
+        Some(RuleValue::String(name)) => format!("'{name}'"),
+        None => "0".to_string(),
+    }
+}
+
+/// A sequence, flattened, without empty members.
+fn seq(members: Vec<Expr>) -> Expr {
+    let mut flat = Vec::new();
+    for member in members {
+        match member {
+            Expr::Empty => {}
+            Expr::Seq(inner) => flat.extend(inner),
+            member => flat.push(member),
+        }
+    }
+    match flat.len() {
+        0 => Expr::Empty,
+        1 => flat.remove(0),
+        _ => Expr::Seq(flat),
+    }
+}
+
+/// A choice, flattened, with an empty branch making it optional.
+fn choice(branches: Vec<Expr>) -> Expr {
+    let mut flat = Vec::new();
+    let mut optional = false;
+    for branch in branches {
+        match branch {
+            Expr::Empty => optional = true,
+            Expr::Choice(inner) => flat.extend(inner),
+            branch => flat.push(branch),
+        }
+    }
+    let choice = match flat.len() {
+        0 => return Expr::Empty,
+        1 => flat.remove(0),
+        _ => Expr::Choice(flat),
+    };
+    if optional {
+        Expr::Optional(Box::new(choice))
+    } else {
+        choice
+    }
+}
+
+/// A string, split where it has unprintable characters or would need
+/// both kinds of quote.
+fn literal(text: &str) -> Expr {
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+    for c in text.chars() {
+        if c.is_control() {
+            if !current.is_empty() {
+                pieces.push(Expr::Literal(std::mem::take(&mut current)));
+            }
+            pieces.push(Expr::Char(u32::from(c)));
+            continue;
+        }
+        let other = if c == '"' { '\'' } else { '"' };
+        if (c == '"' || c == '\'') && current.contains(other) {
+            pieces.push(Expr::Literal(std::mem::take(&mut current)));
+        }
+        current.push(c);
+    }
+    if !current.is_empty() {
+        pieces.push(Expr::Literal(current));
+    }
+    seq(pieces)
+}
+
+/// A class, or the character or literal it amounts to.
+fn class_expr(ranges: Vec<(u32, u32)>) -> Expr {
+    match ranges[..] {
+        [(start, end)] if start == end => match char::from_u32(start) {
+            Some(c) if !c.is_control() => Expr::Literal(c.to_string()),
+            _ => Expr::Char(start),
+        },
+        _ => Expr::Class(ranges),
+    }
+}
+
+/// Parses a pattern as the lexer does and converts it, or keeps it as a
+/// special sequence if it can't be parsed.
+fn pattern(value: &str, flags: Option<&str>) -> Expr {
+    let flags = flags.unwrap_or_default();
+    regex_syntax::ParserBuilder::new()
+        .case_insensitive(flags.contains('i'))
+        .build()
+        .parse(&javascript_classes(value))
+        .map_or_else(
+            |_| Expr::Special(format!("/{value}/{flags}")),
+            |hir| Expr::from_hir(&hir),
+        )
+}
+
+fn comment_delimiters(notation: Notation) -> (&'static str, &'static str) {
+    match notation {
+        Notation::Iso => ("(*", "*)"),
+        Notation::W3c => ("/*", "*/"),
+    }
+}
+
+/// Text for a comment, with anything that would end it broken up.
+fn comment_text(text: &str, notation: Notation) -> String {
+    match notation {
+        Notation::Iso => text.replace("*)", "* )"),
+        Notation::W3c => text.replace("*/", "* /"),
+    }
+}
+
+/// Renders an expression where something binding at least as tightly as
+/// `level` is expected, adding parentheses if it doesn't.
+fn render(expr: &Expr, notation: Notation, level: Level) -> String {
+    let text = render_bare(expr, notation);
+    if expr.level() < level {
+        format!("( {text} )")
+    } else {
+        text
+    }
+}
+
+fn render_bare(expr: &Expr, notation: Notation) -> String {
+    let iso = notation == Notation::Iso;
+    let (open, close) = comment_delimiters(notation);
+    match expr {
+        Expr::Empty => if iso { "" } else { "\"\"" }.to_string(),
+        Expr::Literal(text) => {
+            if text.contains('"') {
+                format!("'{text}'")
+            } else {
+                format!("\"{text}\"")
+            }
+        }
+        Expr::Char(c) if iso => format!("? U+{c:04X} ?"),
+        Expr::Char(c) => format!("#x{c:X}"),
+        Expr::Class(ranges) if iso => {
+            let size: u32 = ranges.iter().map(|(start, end)| end - start + 1).sum();
+            if size <= ISO_CLASS_ALTERNATIVES {
+                let branches = ranges
+                    .iter()
+                    .flat_map(|&(start, end)| start..=end)
+                    .map(|c| class_expr(vec![(c, c)]))
+                    .collect();
+                render_bare(&Expr::Choice(branches), notation)
+            } else {
+                format!("? {} ?", w3c_class(ranges))
+            }
+        }
+        Expr::Class(ranges) => w3c_class(ranges),
+        Expr::Symbol(name) => name.clone(),
+        Expr::Seq(members) => members
+            .iter()
+            .map(|member| {
+                render(
+                    member,
+                    notation,
+                    if iso { Level::Seq } else { Level::Postfix },
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(if iso { " , " } else { " " }),
+        Expr::Choice(branches) => branches
+            .iter()
+            .map(|branch| render(branch, notation, Level::Seq))
+            .collect::<Vec<_>>()
+            .join(" | "),
+        Expr::Optional(inner) if iso => format!("[ {} ]", render(inner, notation, Level::Choice)),
+        Expr::Repeat(inner) if iso => format!("{{ {} }}", render(inner, notation, Level::Choice)),
+        Expr::Repeat1(inner) if iso => format!("{{ {} }}-", render(inner, notation, Level::Choice)),
+        Expr::Optional(inner) => format!("{}?", render(inner, notation, Level::Atom)),
+        Expr::Repeat(inner) => format!("{}*", render(inner, notation, Level::Atom)),
+        Expr::Repeat1(inner) => format!("{}+", render(inner, notation, Level::Atom)),
+        Expr::Note(note, inner) => format!(
+            "{open} {} {close} {}",
+            comment_text(note, notation),
+            render(inner, notation, Level::Postfix)
+        ),
+        Expr::Special(text) if iso => format!("? {} ?", text.replace('?', "\\?")),
+        Expr::Special(text) => format!("{open} {} {close}", comment_text(text, notation)),
+    }
+}
+
+/// A W3C character class, negated when that is shorter.
+fn w3c_class(ranges: &[(u32, u32)]) -> String {
+    let complement = complement(ranges);
+    let (negated, ranges) = if !complement.is_empty() && complement.len() < ranges.len() {
+        (true, complement.as_slice())
+    } else {
+        (false, ranges)
+    };
+    let mut out = String::from(if negated { "[^" } else { "[" });
+    for &(start, end) in ranges {
+        out.push_str(&class_char(start));
+        if end > start {
+            if end > start + 1 {
+                out.push('-');
+            }
+            out.push_str(&class_char(end));
+        }
+    }
+    out.push(']');
+    out
+}
+
+/// The characters not in `ranges`, treating surrogates, which are not
+/// characters, as in every set.
+fn complement(ranges: &[(u32, u32)]) -> Vec<(u32, u32)> {
+    const SURROGATES: (u32, u32) = (0xD800, 0xDFFF);
+    let mut gaps = Vec::new();
+    let mut next = 0;
+    for &(start, end) in ranges {
+        if start > next {
+            gaps.push((next, start - 1));
+        }
+        next = end + 1;
+    }
+    if next <= 0x0010_FFFF {
+        gaps.push((next, 0x0010_FFFF));
+    }
+    let mut result = Vec::new();
+    for (start, end) in gaps {
+        if start < SURROGATES.0 && end >= SURROGATES.0 {
+            result.push((start, SURROGATES.0 - 1));
+        }
+        if end > SURROGATES.1 && start <= SURROGATES.1 {
+            result.push((SURROGATES.1 + 1, end));
+        }
+        if end < SURROGATES.0 || start > SURROGATES.1 {
+            result.push((start, end));
+        }
+    }
+    result
+}
+
+/// A character as written in a W3C class: itself if it is printable and
+/// has no meaning there, a `#x` code otherwise.
+fn class_char(c: u32) -> String {
+    match char::from_u32(c) {
+        Some(c)
+            if !c.is_control()
+                && !c.is_whitespace()
+                && !matches!(c, '[' | ']' | '^' | '-' | '\\' | '#') =>
+        {
+            c.to_string()
+        }
+        _ => format!("#x{c:X}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_grammar;
+
+    const GRAMMAR: &str = r#"{
+        "name": "sums",
+        "extras": [{"type": "PATTERN", "value": "\\s"}],
+        "word": "identifier",
+        "rules": {
+            "program": {"type": "REPEAT", "content": {"type": "SYMBOL", "name": "_expression"}},
+            "_expression": {"type": "CHOICE", "members": [
+                {"type": "SYMBOL", "name": "identifier"},
+                {"type": "SYMBOL", "name": "number"},
+                {"type": "SYMBOL", "name": "sum"}
+            ]},
+            "sum": {"type": "PREC_LEFT", "value": 1, "content": {"type": "SEQ", "members": [
+                {"type": "FIELD", "name": "left", "content": {"type": "SYMBOL", "name": "_expression"}},
+                {"type": "FIELD", "name": "operator", "content": {"type": "CHOICE", "members": [
+                    {"type": "STRING", "value": "+"},
+                    {"type": "STRING", "value": "-"}
+                ]}},
+                {"type": "FIELD", "name": "right", "content": {"type": "SYMBOL", "name": "_expression"}}
+            ]}},
+            "identifier": {"type": "PATTERN", "value": "[a-zA-Z_]\\w*"},
+            "number": {"type": "PATTERN", "value": "-?\\d+(\\.\\d{1,2})?"},
+            "string": {"type": "PATTERN", "value": "\"([^\"\\\\]|\\\\.)*\""}
+        }
+    }"#;
+
+    fn emitted(notation: Notation) -> String {
+        emit(&parse_grammar(GRAMMAR).unwrap(), notation)
+    }
+
+    #[test]
+    fn test_w3c() {
+        assert_eq!(
+            emitted(Notation::W3c),
+            r##"/* The sums grammar, in W3C EBNF, written by sapling. */
+
+/* extras: [#x9-#xD#x20#x85#xA0#x1680#x2000-#x200A#x2028#x2029#x202F#x205F#x3000] */
+/* word token: identifier */
+
+program ::= _expression*
+
+_expression ::= identifier
+    | number
+    | sum
+
+sum ::= /* prec.left(1) */ /* field left */ _expression /* field operator */ ( "+" | "-" ) /* field right */ _expression
+
+identifier ::= [A-Z_a-z] [0-9A-Z_a-z]*
+
+number ::= "-"? [0-9]+ ( "." [0-9] [0-9]? )?
+
+string ::= '"' ( [^"#x5C] | "\" [^#xA] )* '"'
+"##
+        );
+    }
+
+    #[test]
+    fn test_iso() {
+        let iso = emitted(Notation::Iso);
+        assert!(
+            iso.starts_with("(* The sums grammar, in ISO/IEC 14977 EBNF, written by sapling. *)\n")
+        );
+        assert!(iso.contains("\nprogram = { _expression } ;\n"));
+        assert!(iso.contains("\n_expression = identifier\n    | number\n    | sum ;\n"));
+        assert!(iso.contains(
+            "\nsum = (* prec.left(1) *) (* field left *) _expression , (* field operator *) ( \"+\" | \"-\" ) , \
+             (* field right *) _expression ;\n"
+        ));
+        assert!(iso.contains("\nidentifier = ? [A-Z_a-z] ? , { ? [0-9A-Z_a-z] ? } ;\n"));
+        assert!(iso.contains(
+            "\nnumber = [ \"-\" ] , { ? [0-9] ? }- , [ \".\" , ? [0-9] ? , [ ? [0-9] ? ] ] ;\n"
+        ));
+    }
+
+    #[test]
+    fn test_literals_and_classes() {
+        assert_eq!(
+            literal("a\"b'c\n"),
+            Expr::Seq(vec![
+                Expr::Literal("a\"b".to_string()),
+                Expr::Literal("'c".to_string()),
+                Expr::Char(0xA),
+            ])
+        );
+        assert_eq!(w3c_class(&[(0x61, 0x63), (0x2D, 0x2D)]), "[a-c#x2D]");
+        assert_eq!(complement(&[(0, 0x9), (0xB, 0x10_FFFF)]), [(0xA, 0xA)]);
+        assert_eq!(
+            render(&pattern("[+-]", None), Notation::Iso, Level::Choice),
+            "\"+\" | \"-\""
+        );
+        assert_eq!(
+            render(&pattern("(?=a)", None), Notation::Iso, Level::Choice),
+            "? /(\\?=a)/ ?"
+        );
+    }
+}
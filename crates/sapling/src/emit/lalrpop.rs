@@ -0,0 +1,870 @@
+//! lalrpop grammar emission.
+//!
+//! [`emit`] normalizes a grammar the same way node types are computed, then
+//! writes each rule as a lalrpop nonterminal whose actions build sapling
+//! [`Node`](crate::Node)s, so the parser lalrpop generates produces the same
+//! concrete syntax trees tree-sitter would. Visible rules build a node of
+//! their own; hidden and auxiliary rules return their children for the
+//! parent to splice in, and repetitions become left-recursive lists.
+//!
+//! Tokenizing is left to a hand-written lexer. Alongside the grammar, the
+//! emitter writes the Rust `Token` enum that the grammar's `extern` block
+//! names, with each variant documented by the string or pattern it matches.
+//!
+//! Precedence is carried over as lalrpop's `#[precedence]` and `#[assoc]`
+//! attributes, which only order the alternatives of a nonterminal that
+//! recurses on itself. A rule's operators, the productions with a
+//! precedence that start or end with the rule itself, are levelled above
+//! its other alternatives, which move into an `Atom` nonterminal of their
+//! own. Tree-sitter grammars usually write their operators in a rule of
+//! their own that recurses through a hidden choice, as `binary` does
+//! through `_expression: choice(binary, ...)`. Such a rule is inlined into
+//! the choice when nothing else refers to it, and reported as unsupported
+//! otherwise, since lalrpop cannot compare precedences across rules.
+
+use crate::codegen::names::{camel_case, unique_name, words};
+use crate::grammar::prepare::{
+    prepare, Associativity, PreparedGrammar, Production, ProductionStep, Symbol, SymbolKind,
+    SyntaxVariable, VariableType,
+};
+use crate::grammar::{Grammar, GrammarError, Rule, RuleType, RuleValue};
+use std::collections::{BTreeSet, HashSet};
+use std::fmt::{self, Write};
+
+/// A grammar rendered for lalrpop.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lalrpop {
+    /// The `.lalrpop` grammar.
+    pub grammar: String,
+    /// Rust source defining the `Token`, `Location` and `LexicalError`
+    /// types the grammar's `extern` block refers to. The grammar imports
+    /// them from a sibling module named `<grammar name>_tokens`.
+    pub tokens: String,
+}
+
+/// A tree-sitter feature that has no lalrpop equivalent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Unsupported {
+    /// A `conflicts` entry, which tree-sitter resolves at parse time by
+    /// exploring both sides with GLR.
+    Conflict(Vec<String>),
+    /// A token produced by an external scanner.
+    ExternalToken(String),
+    /// A `token.immediate`, which depends on whether whitespace preceded it.
+    ImmediateToken {
+        /// The rule using it.
+        rule: String,
+    },
+    /// A `prec.dynamic`, which only picks between GLR parse stacks.
+    DynamicPrecedence {
+        /// The rule using it.
+        rule: String,
+    },
+    /// A precedence named in the grammar's `precedences` list.
+    NamedPrecedence {
+        /// The rule using it.
+        rule: String,
+        /// The precedence name.
+        name: String,
+    },
+    /// Context-specific reserved words, which the lexer can only apply by
+    /// knowing the parse state.
+    ReservedWords,
+    /// An extra that belongs in the tree, such as a comment, or that is a
+    /// syntax rule rather than a token. lalrpop's lexer can only skip it.
+    Extra(String),
+    /// A rule whose operators recurse through another rule that cannot
+    /// absorb them.
+    IndirectPrecedence {
+        /// The rule with the operators.
+        rule: String,
+        /// The rule they recurse through.
+        through: String,
+    },
+}
+
+impl fmt::Display for Unsupported {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Unsupported::Conflict(rules) => {
+                write!(f, "conflict between {} needs GLR parsing", rules.join(", "))
+            }
+            Unsupported::ExternalToken(name) => {
+                write!(f, "token '{name}' comes from an external scanner")
+            }
+            Unsupported::ImmediateToken { rule } => {
+                write!(f, "rule '{rule}' uses an immediate token")
+            }
+            Unsupported::DynamicPrecedence { rule } => {
+                write!(
+                    f,
+                    "rule '{rule}' uses dynamic precedence, which needs GLR parsing"
+                )
+            }
+            Unsupported::NamedPrecedence { rule, name } => {
+                write!(f, "rule '{rule}' uses the named precedence '{name}'")
+            }
+            Unsupported::ReservedWords => write!(f, "the grammar declares reserved words"),
+            Unsupported::Extra(name) => {
+                write!(f, "extra '{name}' would be dropped from the tree")
+            }
+            Unsupported::IndirectPrecedence { rule, through } => {
+                write!(
+                    f,
+                    "rule '{rule}' takes its precedence through '{through}', which other rules also use"
+                )
+            }
+        }
+    }
+}
+
+/// Why a grammar could not be emitted.
+#[derive(Debug)]
+pub enum EmitError {
+    /// The grammar itself is invalid.
+    Grammar(GrammarError),
+    /// The grammar relies on features lalrpop cannot express.
+    Unsupported(Vec<Unsupported>),
+}
+
+impl fmt::Display for EmitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EmitError::Grammar(e) => write!(f, "{e}"),
+            EmitError::Unsupported(features) => {
+                write!(f, "the grammar uses features lalrpop cannot express:")?;
+                for feature in features {
+                    write!(f, "\n  - {feature}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for EmitError {}
+
+/// Renders `grammar` as a lalrpop grammar and its token enum.
+///
+/// # Errors
+///
+/// Returns [`EmitError::Grammar`] if the grammar is invalid, and
+/// [`EmitError::Unsupported`] listing every feature that lalrpop cannot
+/// express.
+pub fn emit(grammar: &Grammar) -> Result<Lalrpop, EmitError> {
+    let prepared = prepare(grammar).map_err(EmitError::Grammar)?;
+    let (inlined, indirect) = inlined_operators(&prepared);
+    let mut unsupported = unsupported_features(grammar, &prepared);
+    unsupported.extend(indirect);
+    if !unsupported.is_empty() {
+        return Err(EmitError::Unsupported(unsupported));
+    }
+    let emitter = Emitter::new(grammar, &prepared, inlined);
+    Ok(Lalrpop {
+        grammar: emitter.grammar_file(),
+        tokens: emitter.tokens_file(),
+    })
+}
+
+fn unsupported_features(grammar: &Grammar, prepared: &PreparedGrammar) -> Vec<Unsupported> {
+    fn scan(name: &str, rule: &Rule, found: &mut Vec<Unsupported>) {
+        let feature = match (rule.rule_type, &rule.value) {
+            (RuleType::ImmediateToken, _) => Some(Unsupported::ImmediateToken {
+                rule: name.to_string(),
+            }),
+            (RuleType::PrecDynamic, _) => Some(Unsupported::DynamicPrecedence {
+                rule: name.to_string(),
+            }),
+            (
+                RuleType::Prec | RuleType::PrecLeft | RuleType::PrecRight,
+                Some(RuleValue::String(precedence)),
+            ) => Some(Unsupported::NamedPrecedence {
+                rule: name.to_string(),
+                name: precedence.clone(),
+            }),
+            _ => None,
+        };
+        if let Some(feature) = feature {
+            if !found.contains(&feature) {
+                found.push(feature);
+            }
+        }
+        for child in rule.content.as_deref().into_iter().chain(&rule.members) {
+            scan(name, child, found);
+        }
+    }
+
+    let mut found: Vec<Unsupported> = grammar
+        .conflicts
+        .iter()
+        .flatten()
+        .map(|rules| Unsupported::Conflict(rules.clone()))
+        .collect();
+    found.extend(
+        prepared
+            .syntax
+            .external_tokens
+            .iter()
+            .map(|token| Unsupported::ExternalToken(token.name.clone())),
+    );
+    for (name, rule) in &grammar.rules {
+        scan(name, rule, &mut found);
+    }
+    if grammar
+        .reserved
+        .as_ref()
+        .is_some_and(|sets| !sets.is_empty())
+    {
+        found.push(Unsupported::ReservedWords);
+    }
+    for symbol in &prepared.syntax.extra_symbols {
+        let (name, kind) = prepared.symbol_info(*symbol);
+        if symbol.is_non_terminal() || kind.is_visible() {
+            found.push(Unsupported::Extra(name.to_string()));
+        }
+    }
+    found
+}
+
+/// Whether `production` is an operator on `symbol`: it has a precedence or
+/// an associativity, and starts or ends with `symbol`.
+fn is_operator(production: &Production, symbol: Symbol) -> bool {
+    (production.precedence != 0 || production.associativity.is_some())
+        && production.steps.len() > 1
+        && [production.steps.first(), production.steps.last()]
+            .into_iter()
+            .flatten()
+            .any(|step| step.symbol == symbol)
+}
+
+/// The rule `production` is just one of, unrenamed, if it is a unit
+/// production.
+fn unit_rule(production: &Production) -> Option<usize> {
+    match production.steps.as_slice() {
+        [step]
+            if step.symbol.is_non_terminal()
+                && step.alias.is_none()
+                && step.field_name.is_none() =>
+        {
+            Some(step.symbol.index)
+        }
+        _ => None,
+    }
+}
+
+/// The rules `variable` can be just one of, unrenamed.
+fn unit_rules(variable: &SyntaxVariable) -> impl Iterator<Item = usize> + '_ {
+    variable.productions.iter().filter_map(unit_rule)
+}
+
+/// Whether rule `from` can be just rule `to`, through any number of
+/// unit productions.
+fn reaches(variables: &[SyntaxVariable], from: usize, to: usize) -> bool {
+    let mut seen = HashSet::from([from]);
+    let mut pending = vec![from];
+    while let Some(index) = pending.pop() {
+        for next in unit_rules(&variables[index]) {
+            if next == to {
+                return true;
+            }
+            if seen.insert(next) {
+                pending.push(next);
+            }
+        }
+    }
+    false
+}
+
+/// Finds, for each rule, the operator rules to inline into it: those it
+/// can be just one of, that are used nowhere else, and whose productions
+/// are all operators on it. Operators that recurse through a rule they
+/// cannot be inlined into are returned as unsupported.
+fn inlined_operators(prepared: &PreparedGrammar) -> (Vec<Vec<usize>>, Vec<Unsupported>) {
+    let variables = &prepared.syntax.variables;
+    let mut uses = vec![0_usize; variables.len()];
+    for step in variables
+        .iter()
+        .flat_map(|variable| &variable.productions)
+        .flat_map(|production| &production.steps)
+    {
+        if step.symbol.is_non_terminal() {
+            uses[step.symbol.index] += 1;
+        }
+    }
+
+    let mut inlined = vec![Vec::new(); variables.len()];
+    for (index, variable) in variables.iter().enumerate() {
+        let symbol = Symbol::non_terminal(index);
+        for member in unit_rules(variable) {
+            if member != 0
+                && member != index
+                && uses[member] == 1
+                && variables[member]
+                    .productions
+                    .iter()
+                    .all(|production| is_operator(production, symbol))
+            {
+                inlined[index].push(member);
+            }
+        }
+    }
+
+    let absorbed: HashSet<usize> = inlined.iter().flatten().copied().collect();
+    let mut unsupported = Vec::new();
+    for (index, variable) in variables.iter().enumerate() {
+        if absorbed.contains(&index) {
+            continue;
+        }
+        for production in &variable.productions {
+            for step in [production.steps.first(), production.steps.last()]
+                .into_iter()
+                .flatten()
+            {
+                let through = step.symbol;
+                if !through.is_non_terminal()
+                    || through.index == index
+                    || !is_operator(production, through)
+                    || !reaches(variables, through.index, index)
+                {
+                    continue;
+                }
+                let feature = Unsupported::IndirectPrecedence {
+                    rule: variable.name.clone(),
+                    through: variables[through.index].name.clone(),
+                };
+                if !unsupported.contains(&feature) {
+                    unsupported.push(feature);
+                }
+            }
+        }
+    }
+    (inlined, unsupported)
+}
+
+/// The value a production step contributes to its parent.
+enum Piece {
+    /// A single node.
+    One(String),
+    /// Nodes spliced from a hidden rule. The flag records whether the
+    /// expression is an iterator that must be collected to form a `Vec`.
+    Many(String, bool),
+}
+
+struct Emitter<'a> {
+    grammar_name: &'a str,
+    prepared: &'a PreparedGrammar,
+    /// The nonterminal name of each syntax rule.
+    rules: Vec<String>,
+    /// The `Token` variant of each lexical token.
+    variants: Vec<String>,
+    /// How each token is spelled in the grammar's `extern` block: its own
+    /// text where that needs no escaping, its variant name otherwise.
+    terminals: Vec<String>,
+    /// The nonterminal wrapping each token in a leaf node, for the tokens
+    /// that appear in the tree.
+    leaves: Vec<Option<String>>,
+    /// The operator rules inlined into each syntax rule.
+    inlined: Vec<Vec<usize>>,
+    /// The nonterminal holding the alternatives of each syntax rule with
+    /// operators that are not operators themselves.
+    atoms: Vec<Option<String>>,
+}
+
+impl<'a> Emitter<'a> {
+    fn new(grammar: &'a Grammar, prepared: &'a PreparedGrammar, inlined: Vec<Vec<usize>>) -> Self {
+        let mut taken = BTreeSet::new();
+        let rules: Vec<String> = prepared
+            .syntax
+            .variables
+            .iter()
+            .map(|variable| unique_name(&camel_case(&words(&variable.name)), &mut taken))
+            .collect();
+
+        let tokens = &prepared.lexical.variables;
+        let mut taken_variants = BTreeSet::new();
+        let mut taken_terminals = BTreeSet::new();
+        let mut variants = Vec::with_capacity(tokens.len());
+        let mut terminals = Vec::with_capacity(tokens.len());
+        for token in tokens {
+            let variant = unique_name(&camel_case(&words(&token.name)), &mut taken_variants);
+            let plain = !token
+                .name
+                .chars()
+                .any(|c| c == '"' || c == '\\' || c.is_control());
+            let spelling = if plain { &token.name } else { &variant };
+            terminals.push(unique_name(spelling, &mut taken_terminals));
+            variants.push(variant);
+        }
+
+        let mut in_tree = HashSet::new();
+        for variable in &prepared.syntax.variables {
+            for step in variable.productions.iter().flat_map(|p| &p.steps) {
+                if step.symbol.kind == SymbolKind::Terminal
+                    && (tokens[step.symbol.index].kind.is_visible() || step.alias.is_some())
+                {
+                    in_tree.insert(step.symbol.index);
+                }
+            }
+        }
+        let leaves = variants
+            .iter()
+            .enumerate()
+            .map(|(i, variant)| {
+                in_tree
+                    .contains(&i)
+                    .then(|| unique_name(variant, &mut taken))
+            })
+            .collect();
+
+        let atoms = prepared
+            .syntax
+            .variables
+            .iter()
+            .enumerate()
+            .map(|(i, variable)| {
+                let has_operators = !inlined[i].is_empty()
+                    || variable
+                        .productions
+                        .iter()
+                        .any(|production| is_operator(production, Symbol::non_terminal(i)));
+                has_operators.then(|| unique_name(&format!("{}Atom", rules[i]), &mut taken))
+            })
+            .collect();
+
+        Self {
+            grammar_name: &grammar.name,
+            prepared,
+            rules,
+            variants,
+            terminals,
+            leaves,
+            inlined,
+            atoms,
+        }
+    }
+
+    fn tokens_module(&self) -> String {
+        let mut module = words(self.grammar_name)
+            .iter()
+            .map(|word| word.to_lowercase())
+            .collect::<Vec<_>>()
+            .join("_");
+        module.push_str("_tokens");
+        module
+    }
+
+    fn grammar_file(&self) -> String {
+        let mut out = format!(
+            "// lalrpop grammar for `{}`, generated by sapling.\n\
+             // Do not edit by hand.\n\n\
+             use sapling::{{Node, Range}};\n\
+             use super::{}::{{LexicalError, Location, Token}};\n\n\
+             grammar;\n\n\
+             extern {{\n    \
+                 type Location = Location;\n    \
+                 type Error = LexicalError;\n\n    \
+                 enum Token {{\n",
+            self.grammar_name,
+            self.tokens_module()
+        );
+        for (terminal, variant) in self.terminals.iter().zip(&self.variants) {
+            let _ = writeln!(out, "        \"{terminal}\" => Token::{variant},");
+        }
+        out.push_str("    }\n}\n");
+
+        let inline = &self.prepared.syntax.variables_to_inline;
+        let absorbed: HashSet<usize> = self.inlined.iter().flatten().copied().collect();
+        for (i, variable) in self.prepared.syntax.variables.iter().enumerate() {
+            if absorbed.contains(&i) {
+                continue;
+            }
+            out.push('\n');
+            if inline.contains(&Symbol::non_terminal(i)) {
+                out.push_str("#[inline]\n");
+            }
+            if i == 0 {
+                out.push_str("pub ");
+            }
+            let value = if variable.kind.is_visible() {
+                "Node"
+            } else {
+                "Vec<Node>"
+            };
+            let _ = writeln!(out, "{}: {value} = {{", self.rules[i]);
+            let (operators, atoms) = self.split(i, variable);
+            if let Some(atom) = &self.atoms[i] {
+                out.push_str(&self.levels(variable, atom, &operators));
+                let _ = writeln!(out, "}};\n\n{atom}: {value} = {{");
+            }
+            for production in atoms {
+                let _ = writeln!(
+                    out,
+                    "    {},",
+                    self.alternative(variable, None, &production.steps)
+                );
+            }
+            out.push_str("};\n");
+        }
+
+        for (i, leaf) in self.leaves.iter().enumerate() {
+            let Some(leaf) = leaf else { continue };
+            let token = &self.prepared.lexical.variables[i];
+            let _ = write!(
+                out,
+                "\n{leaf}: Node = <l:@L> \"{}\" <r:@R> => \
+                 Node::leaf({:?}, {}, Range::new(l.byte, r.byte, l.point, r.point));\n",
+                self.terminals[i],
+                token.name,
+                token.kind == VariableType::Named
+            );
+        }
+        out
+    }
+
+    /// Splits a rule's productions into its operators, inlined ones
+    /// included, and the rest. Each operator comes with the operator rule
+    /// it was inlined from, if any.
+    fn split(
+        &self,
+        index: usize,
+        variable: &SyntaxVariable,
+    ) -> (
+        Vec<(Option<&'a SyntaxVariable>, Production)>,
+        Vec<Production>,
+    ) {
+        let symbol = Symbol::non_terminal(index);
+        let members = &self.inlined[index];
+        let mut operators = Vec::new();
+        let mut atoms = Vec::new();
+        for production in variable.list_productions(index) {
+            if is_operator(&production, symbol) {
+                operators.push((None, production));
+            } else if !unit_rule(&production).is_some_and(|rule| members.contains(&rule)) {
+                atoms.push(production);
+            }
+        }
+        for &member in members {
+            let rule = &self.prepared.syntax.variables[member];
+            operators.extend(
+                rule.list_productions(member)
+                    .into_iter()
+                    .map(|production| (Some(rule), production)),
+            );
+        }
+        (operators, atoms)
+    }
+
+    /// Renders a rule's operators as precedence levels above its atoms.
+    /// Higher tree-sitter precedences bind tighter, so they take the lower
+    /// lalrpop levels, with the atoms at level 0.
+    fn levels(
+        &self,
+        variable: &SyntaxVariable,
+        atom: &str,
+        operators: &[(Option<&SyntaxVariable>, Production)],
+    ) -> String {
+        let mut precedences: Vec<i32> = operators.iter().map(|(_, p)| p.precedence).collect();
+        precedences.sort_unstable_by(|a, b| b.cmp(a));
+        precedences.dedup();
+
+        let mut ordered: Vec<&(Option<&SyntaxVariable>, Production)> = operators.iter().collect();
+        ordered.sort_by_key(|(_, production)| std::cmp::Reverse(production.precedence));
+        let mut out = format!("    #[precedence(level=\"0\")]\n    <c0:{atom}> => c0,\n");
+        for (member, production) in ordered {
+            let level = precedences
+                .iter()
+                .position(|precedence| *precedence == production.precedence)
+                .unwrap_or_default()
+                + 1;
+            let _ = write!(out, "    #[precedence(level=\"{level}\")]");
+            match production.associativity {
+                Some(Associativity::Left) => out.push_str(" #[assoc(side=\"left\")]"),
+                Some(Associativity::Right) => out.push_str(" #[assoc(side=\"right\")]"),
+                None => {}
+            }
+            let _ = writeln!(
+                out,
+                "\n    {},",
+                self.alternative(variable, *member, &production.steps)
+            );
+        }
+        out
+    }
+
+    /// Renders one alternative: its symbols, then the action building it.
+    /// An alternative inlined from an operator rule builds that rule's node
+    /// inside the one for `variable`.
+    fn alternative(
+        &self,
+        variable: &SyntaxVariable,
+        member: Option<&SyntaxVariable>,
+        steps: &[ProductionStep],
+    ) -> String {
+        let rule = member.unwrap_or(variable);
+        let visible = rule.kind.is_visible();
+        if steps.is_empty() {
+            return if visible {
+                format!(
+                    "<l:@L> => Node::leaf({:?}, true, Range::new(l.byte, l.byte, l.point, l.point))",
+                    rule.name
+                )
+            } else {
+                "=> Vec::new()".to_string()
+            };
+        }
+
+        let mut symbols = Vec::new();
+        let mut pieces = Vec::new();
+        for (i, step) in steps.iter().enumerate() {
+            match self.piece(i, step) {
+                Some((symbol, piece)) => {
+                    symbols.push(format!("<c{i}:{symbol}>"));
+                    pieces.push(piece);
+                }
+                None => symbols.push(format!("\"{}\"", self.terminals[step.symbol.index])),
+            }
+        }
+        let mut action = build(rule, children(pieces));
+        if member.is_some() {
+            let piece = if visible {
+                Piece::One(action)
+            } else {
+                Piece::Many(action, false)
+            };
+            action = build(variable, children(vec![piece]));
+        }
+        format!("{} => {action}", symbols.join(" "))
+    }
+
+    /// The nonterminal a step binds and the value it contributes, or `None`
+    /// for a hidden token that leaves no trace in the tree.
+    fn piece(&self, i: usize, step: &ProductionStep) -> Option<(String, Piece)> {
+        let value = format!("c{i}");
+        let (symbol, piece) = match step.symbol.kind {
+            SymbolKind::Terminal => {
+                let leaf = self.leaves[step.symbol.index].clone()?;
+                (leaf, Piece::One(value))
+            }
+            SymbolKind::NonTerminal => {
+                let variable = &self.prepared.syntax.variables[step.symbol.index];
+                let piece = if variable.kind.is_visible() {
+                    Piece::One(value)
+                } else {
+                    Piece::Many(value, false)
+                };
+                (self.rules[step.symbol.index].clone(), piece)
+            }
+            SymbolKind::External => unreachable!("external tokens are rejected before emitting"),
+        };
+        let piece = match (piece, &step.alias) {
+            (Piece::One(value), Some(alias)) => Piece::One(format!(
+                "{value}.with_kind({:?}, {})",
+                alias.value, alias.named
+            )),
+            (Piece::Many(value, _), Some(alias)) => Piece::One(format!(
+                "Node::branch({:?}, {}, {value})",
+                alias.value, alias.named
+            )),
+            (piece, None) => piece,
+        };
+        let piece = match (piece, &step.field_name) {
+            (Piece::One(value), Some(field)) => {
+                Piece::One(format!("{value}.with_field({field:?})"))
+            }
+            (Piece::Many(value, _), Some(field)) => Piece::Many(
+                format!("{value}.into_iter().map(|node| node.with_field({field:?}))"),
+                true,
+            ),
+            (piece, None) => piece,
+        };
+        Some((symbol, piece))
+    }
+
+    fn tokens_file(&self) -> String {
+        let mut out = format!(
+            "// Tokens for the `{name}` grammar's lalrpop parser, generated by sapling.\n\
+             // Do not edit by hand.\n\n\
+             use sapling::Point;\n\n\
+             /// A token of the `{name}` grammar.\n\
+             ///\n\
+             /// The lexer yields tokens as `(start, token, end)` triples of\n\
+             /// [`Location`]s. It skips whitespace, and any extras the grammar\n\
+             /// allows between tokens, since the parser does not accept them.\n\
+             #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]\n\
+             pub enum Token {{\n",
+            name = self.grammar_name
+        );
+        let extras: HashSet<usize> = self
+            .prepared
+            .syntax
+            .extra_symbols
+            .iter()
+            .filter(|symbol| symbol.kind == SymbolKind::Terminal)
+            .map(|symbol| symbol.index)
+            .collect();
+        for (i, token) in self.prepared.lexical.variables.iter().enumerate() {
+            let mut doc = describe(&token.rule);
+            if token.kind == VariableType::Named {
+                let _ = write!(doc, ", as `{}`", doc_text(&token.name));
+            }
+            doc.push('.');
+            if extras.contains(&i) {
+                doc.push_str(" An extra, for the lexer to skip.");
+            }
+            let _ = write!(out, "    /// {doc}\n    {},\n", self.variants[i]);
+        }
+        out.push_str(
+            "}\n\n\
+             /// A position in the source, as both a byte offset and a row and\n\
+             /// column.\n\
+             #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]\n\
+             pub struct Location {\n    \
+                 /// The byte offset.\n    \
+                 pub byte: usize,\n    \
+                 /// The row and column of `byte`.\n    \
+                 pub point: Point,\n\
+             }\n\n\
+             /// Input the lexer could not turn into a token.\n\
+             #[derive(Debug, Clone, Copy, PartialEq, Eq)]\n\
+             pub struct LexicalError {\n    \
+                 /// Where the unrecognized input starts.\n    \
+                 pub location: Location,\n\
+             }\n",
+        );
+        out
+    }
+}
+
+/// Builds the value of `variable` from the expression for its children.
+fn build(variable: &SyntaxVariable, children: String) -> String {
+    if variable.kind.is_visible() {
+        format!("Node::branch({:?}, true, {children})", variable.name)
+    } else {
+        children
+    }
+}
+
+/// Builds the expression for a node's children from its pieces.
+fn children(pieces: Vec<Piece>) -> String {
+    if pieces.iter().all(|piece| matches!(piece, Piece::One(_))) {
+        let nodes: Vec<String> = pieces
+            .into_iter()
+            .filter_map(|piece| match piece {
+                Piece::One(value) => Some(value),
+                Piece::Many(..) => None,
+            })
+            .collect();
+        return format!("vec![{}]", nodes.join(", "));
+    }
+    if let [Piece::Many(value, iterator)] = pieces.as_slice() {
+        return if *iterator {
+            format!("{value}.collect()")
+        } else {
+            value.clone()
+        };
+    }
+    let mut block = String::from("{\n        let mut children = Vec::new();\n");
+    for piece in pieces {
+        let _ = match piece {
+            Piece::One(value) => writeln!(block, "        children.push({value});"),
+            Piece::Many(value, _) => writeln!(block, "        children.extend({value});"),
+        };
+    }
+    block.push_str("        children\n    }");
+    block
+}
+
+/// Describes what a token matches, for its doc comment.
+fn describe(rule: &Rule) -> String {
+    if let Some(text) = rule.string_value() {
+        format!("The string `{}`", doc_text(text))
+    } else if let Some(pattern) = rule.pattern_value() {
+        format!("The pattern `{}`", doc_text(pattern))
+    } else {
+        format!("A `{}` rule", rule.type_name())
+    }
+}
+
+/// Escapes the control characters in `text`, which cannot appear in a doc
+/// comment as they are.
+fn doc_text(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            if c.is_control() {
+                c.escape_default().to_string()
+            } else {
+                c.to_string()
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::parse_grammar;
+
+    #[test]
+    fn test_reports_unsupported_features() {
+        let grammar = parse_grammar(
+            r#"{
+                "name": "glr",
+                "conflicts": [["a", "b"]],
+                "externals": [{"type": "SYMBOL", "name": "indent"}],
+                "extras": [{"type": "SYMBOL", "name": "comment"}],
+                "rules": {
+                    "a": {"type": "CHOICE", "members": [
+                        {"type": "SYMBOL", "name": "b"},
+                        {"type": "PREC_DYNAMIC", "value": 1, "content": {"type": "SYMBOL", "name": "indent"}},
+                        {"type": "SYMBOL", "name": "sum"},
+                        {"type": "SEQ", "members": [
+                            {"type": "STRING", "value": "("},
+                            {"type": "SYMBOL", "name": "sum"},
+                            {"type": "STRING", "value": ")"}
+                        ]}
+                    ]},
+                    "b": {"type": "IMMEDIATE_TOKEN", "content": {"type": "STRING", "value": "x"}},
+                    "sum": {"type": "PREC_LEFT", "value": 1, "content": {"type": "SEQ", "members": [
+                        {"type": "SYMBOL", "name": "a"},
+                        {"type": "STRING", "value": "+"},
+                        {"type": "SYMBOL", "name": "a"}
+                    ]}},
+                    "comment": {"type": "PATTERN", "value": "//.*"}
+                }
+            }"#,
+        )
+        .unwrap();
+        let Err(EmitError::Unsupported(features)) = emit(&grammar) else {
+            panic!("expected unsupported features");
+        };
+        assert_eq!(
+            features,
+            vec![
+                Unsupported::Conflict(vec!["a".to_string(), "b".to_string()]),
+                Unsupported::ExternalToken("indent".to_string()),
+                Unsupported::DynamicPrecedence {
+                    rule: "a".to_string()
+                },
+                Unsupported::ImmediateToken {
+                    rule: "b".to_string()
+                },
+                Unsupported::Extra("comment".to_string()),
+                // `sum` is used twice, so it cannot be inlined into `a`.
+                Unsupported::IndirectPrecedence {
+                    rule: "sum".to_string(),
+                    through: "a".to_string()
+                },
+            ]
+        );
+        assert_eq!(
+            EmitError::Unsupported(features).to_string(),
+            "the grammar uses features lalrpop cannot express:\n  \
+             - conflict between a, b needs GLR parsing\n  \
+             - token 'indent' comes from an external scanner\n  \
+             - rule 'a' uses dynamic precedence, which needs GLR parsing\n  \
+             - rule 'b' uses an immediate token\n  \
+             - extra 'comment' would be dropped from the tree\n  \
+             - rule 'sum' takes its precedence through 'a', which other rules also use"
+        );
+    }
+}
@@ -0,0 +1,420 @@
+//! Rust parse table emission.
+//!
+//! [`emit`] builds a grammar's tables with sapling's own
+//! [table builder](crate::tables) and writes them out as a self-contained
+//! Rust module: constant arrays for the symbols, parse actions, gotos,
+//! aliases and fields, a `LANGUAGE` static tying them together, and the
+//! lexer's DFA as a `match` on the lex state, with a second one for the
+//! keywords of a grammar that has a `word` token. A build script writes the
+//! module to `OUT_DIR` and the crate `include!`s it, so nothing about the
+//! grammar is computed at runtime:
+//!
+//! ```ignore
+//! mod calc {
+//!     include!(concat!(env!("OUT_DIR"), "/calc.rs"));
+//! }
+//!
+//! let tree = sapling::Parser::new(&calc::LANGUAGE).parse("let x = 1;");
+//! ```
+//!
+//! A grammar with external tokens names the type of its
+//! [`ExternalScanner`](crate::scanner::ExternalScanner), which the module
+//! creates with `Default` for each parse.
+
+use crate::grammar::Grammar;
+use crate::language::{ParseAction, ParseActionEntry};
+use crate::tables::lex::{LexState, LexTransition};
+use crate::tables::{self, TableError, Tables};
+use std::fmt::Write;
+
+/// The longest line the emitter writes when wrapping table entries.
+const LINE_WIDTH: usize = 100;
+
+/// Builds the tables for `grammar` and renders them as a Rust module.
+/// `scanner` is the path of the external scanner type, as the module
+/// would write it.
+///
+/// # Errors
+///
+/// Returns the [`TableError`] from building the tables.
+pub fn emit(grammar: &Grammar, scanner: Option<&str>) -> Result<String, TableError> {
+    Ok(render(&tables::build(grammar)?, scanner))
+}
+
+/// Renders tables that are already built as a Rust module.
+#[must_use]
+pub fn render(tables: &Tables, scanner: Option<&str>) -> String {
+    let mut out = format!(
+        "// Parse tables for the `{}` grammar, generated by sapling.\n\
+         // Do not edit by hand.\n\n\
+         use sapling::language::{{\n    \
+         FieldMapEntry, FieldMapSlice, Language, LexMode, Lexing, ParseAction, ParseActionEntry,\n    \
+         ParseTableEntry, StateId, SymbolId, SymbolMetadata,\n\
+         }};\n",
+        tables.name
+    );
+    if scanner.is_some() {
+        out.push_str("use sapling::ExternalScanner;\n");
+    }
+    out.push_str("use sapling::Lexer;\nuse std::borrow::Cow;\n");
+    render_language(&mut out, tables, scanner.is_some());
+    render_symbols(&mut out, tables);
+    render_parse_table(&mut out, tables);
+    render_productions(&mut out, tables);
+    render_lexer(&mut out, "lex", &tables.lex_states, tables);
+    if tables.keyword_capture_token.is_some() {
+        render_lexer(&mut out, "keyword_lex", &tables.keyword_states, tables);
+    }
+    render_external_tokens(&mut out, tables, scanner);
+    out
+}
+
+fn render_language(out: &mut String, tables: &Tables, has_scanner: bool) {
+    let _ = write!(
+        out,
+        "\n/// The `{name}` language.\n\
+         pub static LANGUAGE: Language = Language {{\n    \
+         name: Cow::Borrowed({name:?}),\n    \
+         symbol_names: Cow::Borrowed(SYMBOL_NAMES),\n    \
+         symbol_metadata: Cow::Borrowed(SYMBOL_METADATA),\n    \
+         token_count: {token_count},\n    \
+         field_names: Cow::Borrowed(FIELD_NAMES),\n    \
+         parse_states: Cow::Borrowed(PARSE_STATES),\n    \
+         parse_table: Cow::Borrowed(PARSE_TABLE),\n    \
+         parse_actions: Cow::Borrowed(PARSE_ACTIONS),\n    \
+         alias_sequences: Cow::Borrowed(ALIAS_SEQUENCES),\n    \
+         max_alias_sequence_length: {max_alias_sequence_length},\n    \
+         field_map_slices: Cow::Borrowed(FIELD_MAP_SLICES),\n    \
+         field_map_entries: Cow::Borrowed(FIELD_MAP_ENTRIES),\n    \
+         lex_modes: Cow::Borrowed(LEX_MODES),\n    \
+         lexing: {lexing},\n    \
+         external_symbols: Cow::Borrowed(EXTERNAL_SYMBOLS),\n    \
+         external_scanner_states: Cow::Borrowed(EXTERNAL_SCANNER_STATES),\n    \
+         external_scanner: {scanner},\n\
+         }};\n",
+        name = tables.name,
+        scanner = if has_scanner {
+            "Some(new_scanner)"
+        } else {
+            "None"
+        },
+        lexing = match tables.keyword_capture_token {
+            Some(word) => format!(
+                "Lexing::Keywords {{\n        \
+                 lex,\n        \
+                 keyword_lex,\n        \
+                 keyword_capture_token: {word},\n    \
+                 }}"
+            ),
+            None => "Lexing::Function(lex)".to_string(),
+        },
+        token_count = tables.token_count,
+        max_alias_sequence_length = tables.max_alias_sequence_length,
+    );
+}
+
+fn render_symbols(out: &mut String, tables: &Tables) {
+    out.push_str("\nconst SYMBOL_NAMES: &[Cow<'static, str>] = &[\n");
+    for (i, name) in tables.symbol_names.iter().enumerate() {
+        let _ = writeln!(out, "    Cow::Borrowed({name:?}), // {i}");
+    }
+    out.push_str("];\n\nconst SYMBOL_METADATA: &[SymbolMetadata] = &[\n");
+    for (metadata, name) in tables.symbol_metadata.iter().zip(&tables.symbol_names) {
+        let _ = writeln!(
+            out,
+            "    SymbolMetadata {{ visible: {}, named: {}, supertype: {} }}, // {}",
+            metadata.visible,
+            metadata.named,
+            metadata.supertype,
+            comment_text(name),
+        );
+    }
+    out.push_str("];\n\nconst FIELD_NAMES: &[Cow<'static, str>] = &[");
+    if tables.field_names.is_empty() {
+        out.push_str("];\n");
+    } else {
+        out.push('\n');
+        for name in &tables.field_names {
+            let _ = writeln!(out, "    Cow::Borrowed({name:?}),");
+        }
+        out.push_str("];\n");
+    }
+}
+
+fn render_parse_table(out: &mut String, tables: &Tables) {
+    out.push_str("\nconst PARSE_STATES: &[u32] = &[\n");
+    wrap(out, tables.parse_states.iter().map(ToString::to_string));
+    out.push_str("];\n\nconst PARSE_TABLE: &[ParseTableEntry] = &[\n");
+    for (state, bounds) in tables.parse_states.windows(2).enumerate() {
+        let _ = writeln!(out, "    // state {state}");
+        let row = &tables.parse_table[bounds[0] as usize..bounds[1] as usize];
+        wrap(
+            out,
+            row.iter()
+                .map(|entry| format!("ParseTableEntry::new({}, {})", entry.symbol, entry.value)),
+        );
+    }
+    out.push_str("];\n\nconst PARSE_ACTIONS: &[ParseActionEntry] = &[\n");
+    for (i, entry) in tables.parse_actions.iter().enumerate() {
+        let _ = match *entry {
+            ParseActionEntry::Header { count } => writeln!(
+                out,
+                "    ParseActionEntry::Header {{ count: {count} }}, // {i}"
+            ),
+            ParseActionEntry::Action(ParseAction::Shift { state, extra }) => writeln!(
+                out,
+                "    ParseActionEntry::Action(ParseAction::Shift {{ state: {state}, extra: {extra} }}),"
+            ),
+            ParseActionEntry::Action(ParseAction::Reduce {
+                symbol,
+                child_count,
+                production_id,
+            }) => writeln!(
+                out,
+                "    ParseActionEntry::Action(ParseAction::Reduce {{ symbol: {symbol}, \
+                 child_count: {child_count}, production_id: {production_id} }}), // {}",
+                comment_text(&tables.symbol_names[usize::from(symbol)])
+            ),
+            ParseActionEntry::Action(ParseAction::Accept) => {
+                writeln!(out, "    ParseActionEntry::Action(ParseAction::Accept),")
+            }
+        };
+    }
+    out.push_str("];\n");
+}
+
+fn render_productions(out: &mut String, tables: &Tables) {
+    out.push_str("\nconst ALIAS_SEQUENCES: &[u16] = &[");
+    if tables.alias_sequences.is_empty() {
+        out.push_str("];\n");
+    } else {
+        out.push('\n');
+        for sequence in tables
+            .alias_sequences
+            .chunks(tables.max_alias_sequence_length)
+        {
+            let symbols: Vec<String> = sequence.iter().map(ToString::to_string).collect();
+            let _ = writeln!(out, "    {},", symbols.join(", "));
+        }
+        out.push_str("];\n");
+    }
+    out.push_str("\nconst FIELD_MAP_SLICES: &[FieldMapSlice] = &[\n");
+    for slice in &tables.field_map_slices {
+        let _ = writeln!(
+            out,
+            "    FieldMapSlice {{ index: {}, length: {} }},",
+            slice.index, slice.length
+        );
+    }
+    out.push_str("];\n\nconst FIELD_MAP_ENTRIES: &[FieldMapEntry] = &[");
+    if tables.field_map_entries.is_empty() {
+        out.push_str("];\n");
+    } else {
+        out.push('\n');
+        wrap(
+            out,
+            tables.field_map_entries.iter().map(|entry| {
+                format!(
+                    "FieldMapEntry::new({}, {})",
+                    entry.field_id, entry.child_index
+                )
+            }),
+        );
+        out.push_str("];\n");
+    }
+    out.push_str("\nconst LEX_MODES: &[LexMode] = &[\n");
+    wrap(
+        out,
+        tables.lex_modes.iter().map(|mode| {
+            format!(
+                "LexMode::new({}, {})",
+                mode.lex_state, mode.external_lex_state
+            )
+        }),
+    );
+    out.push_str("];\n");
+}
+
+fn render_lexer(out: &mut String, name: &str, states: &[LexState], tables: &Tables) {
+    // Only a state that accepts and goes on sets the result.
+    let result = if states
+        .iter()
+        .any(|state| state.accept.is_some() && !state.transitions.is_empty())
+    {
+        "mut result"
+    } else {
+        "result"
+    };
+    let _ = write!(
+        out,
+        "\n#[allow(clippy::too_many_lines)]\n\
+         fn {name}(lexer: &mut Lexer<'_>, mut state: StateId) -> bool {{\n    \
+         let {result} = false;\n    \
+         loop {{\n        \
+         let lookahead = lexer.lookahead();\n        \
+         let (next, skip) = match state {{\n",
+    );
+    for (id, state) in states.iter().enumerate() {
+        let accept = state.accept.map(|token| {
+            // Tokens are numbered from 1, after the end of input.
+            let symbol = token + 1;
+            format!(
+                "lexer.accept({symbol}); // {}",
+                comment_text(&tables.symbol_names[symbol])
+            )
+        });
+        match (accept, state.transitions.is_empty()) {
+            (None, true) => {}
+            (Some(accept), true) => {
+                let _ = writeln!(
+                    out,
+                    "            {id} => {{\n                {accept}\n                return true;\n            }}"
+                );
+            }
+            (None, false) => {
+                let _ = writeln!(out, "            {id} => match lookahead {{");
+                render_transitions(out, &state.transitions, "                ", "result");
+                out.push_str("            },\n");
+            }
+            (Some(accept), false) => {
+                let _ = writeln!(
+                    out,
+                    "            {id} => {{\n                {accept}\n                \
+                     result = true;\n                match lookahead {{"
+                );
+                render_transitions(out, &state.transitions, "                    ", "true");
+                out.push_str("                }\n            }\n");
+            }
+        }
+    }
+    out.push_str(
+        "            _ => return result,\n        \
+         };\n        \
+         lexer.advance(skip);\n        \
+         state = next;\n    \
+         }\n\
+         }\n",
+    );
+}
+
+fn render_external_tokens(out: &mut String, tables: &Tables, scanner: Option<&str>) {
+    out.push_str("\nconst EXTERNAL_SYMBOLS: &[SymbolId] = &[");
+    if tables.external_symbols.is_empty() {
+        out.push_str("];\n\nconst EXTERNAL_SCANNER_STATES: &[bool] = &[];\n");
+    } else {
+        out.push('\n');
+        for &symbol in &tables.external_symbols {
+            let _ = writeln!(
+                out,
+                "    {symbol}, // {}",
+                comment_text(&tables.symbol_names[usize::from(symbol)])
+            );
+        }
+        out.push_str("];\n\nconst EXTERNAL_SCANNER_STATES: &[bool] = &[\n");
+        for (i, row) in tables
+            .external_scanner_states
+            .chunks(tables.external_symbols.len())
+            .enumerate()
+        {
+            let valid: Vec<String> = row.iter().map(ToString::to_string).collect();
+            let _ = writeln!(out, "    {}, // {i}", valid.join(", "));
+        }
+        out.push_str("];\n");
+    }
+    if let Some(scanner) = scanner {
+        let _ = write!(
+            out,
+            "\nfn new_scanner() -> Box<dyn ExternalScanner> {{\n    \
+             Box::new(<{scanner} as Default>::default())\n\
+             }}\n"
+        );
+    }
+}
+
+fn render_transitions(
+    out: &mut String,
+    transitions: &[LexTransition],
+    indent: &str,
+    fallback: &str,
+) {
+    for transition in transitions {
+        let ranges: Vec<String> = transition
+            .chars
+            .ranges
+            .iter()
+            .filter_map(|&range| range_pattern(range))
+            .collect();
+        let target = format!(" => ({}, {}),", transition.state, transition.skip);
+        let single_line = format!("{indent}Some({}){target}", ranges.join(" | "));
+        if single_line.len() <= LINE_WIDTH {
+            out.push_str(&single_line);
+            out.push('\n');
+            continue;
+        }
+        let _ = writeln!(out, "{indent}Some(");
+        let mut line = String::new();
+        for (i, range) in ranges.iter().enumerate() {
+            let piece = if i == 0 {
+                range.clone()
+            } else {
+                format!("| {range}")
+            };
+            if !line.is_empty() && indent.len() + 4 + line.len() + 1 + piece.len() > LINE_WIDTH {
+                let _ = writeln!(out, "{indent}    {line}");
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&piece);
+        }
+        let _ = writeln!(out, "{indent}    {line}");
+        let _ = writeln!(out, "{indent}){target}");
+    }
+    let _ = writeln!(out, "{indent}_ => return {fallback},");
+}
+
+/// A `char` pattern for an inclusive range of scalar values, skipping the
+/// surrogates, which are not `char`s.
+fn range_pattern((start, end): (u32, u32)) -> Option<String> {
+    let start = if (0xD800..=0xDFFF).contains(&start) {
+        0xE000
+    } else {
+        start
+    };
+    let end = if (0xD800..=0xDFFF).contains(&end) {
+        0xD7FF
+    } else {
+        end
+    };
+    let (start, end) = (char::from_u32(start)?, char::from_u32(end)?);
+    match start.cmp(&end) {
+        std::cmp::Ordering::Less => Some(format!("{start:?}..={end:?}")),
+        std::cmp::Ordering::Equal => Some(format!("{start:?}")),
+        std::cmp::Ordering::Greater => None,
+    }
+}
+
+/// Writes `items` separated by commas, as many to a line as fit.
+fn wrap(out: &mut String, items: impl Iterator<Item = String>) {
+    let mut line = String::new();
+    for item in items {
+        if !line.is_empty() && 4 + line.len() + 1 + item.len() + 1 > LINE_WIDTH {
+            let _ = writeln!(out, "    {line}");
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&item);
+        line.push(',');
+    }
+    if !line.is_empty() {
+        let _ = writeln!(out, "    {line}");
+    }
+}
+
+/// `text` made safe to put in a line comment.
+fn comment_text(text: &str) -> String {
+    text.escape_debug().to_string()
+}
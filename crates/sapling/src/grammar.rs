@@ -1,12 +1,21 @@
 //! Core structures and parsing logic for Tree-sitter grammars.
 //!
 //! This module defines the internal representation of a grammar as parsed from
-//! Tree-sitter's JSON format. It uses [`facet_json`] for deserialization and
-//! provides ergonomic accessors for inspecting rule properties and structure.
+//! Tree-sitter's JSON format. The JSON is read into a generic value tree and
+//! walked by hand, because rule payloads are polymorphic (a `value` may be a
+//! string or an integer) in a way a derived deserializer cannot express.
+//! Rules keep their declaration order: the first rule is the start rule.
+//! The types still derive [`Facet`](trait@facet::Facet) for reflection, with the ordered rule
+//! maps opaque to it.
 
+use crate::json::{self, Value};
 use facet::Facet;
-use std::collections::HashMap;
+use indexmap::IndexMap;
 
+#[cfg(feature = "js")]
+#[cfg_attr(docsrs, doc(cfg(feature = "js")))]
+pub mod js;
+pub(crate) mod prepare;
 pub mod rules;
 
 pub use rules::{Rule, RuleType, RuleValue};
@@ -38,8 +47,10 @@ pub struct Grammar {
     #[facet(default)]
     pub inherits: Option<String>,
 
-    /// Map of all rule identifiers to their corresponding definitions.
-    pub rules: HashMap<String, Rule>, // required: no default null
+    /// Map of all rule identifiers to their corresponding definitions, in
+    /// declaration order. The first rule is the grammar's start rule.
+    #[facet(opaque)]
+    pub rules: IndexMap<String, Rule>, // required: no default null
 
     /// “Extras” that may appear between other tokens, such as whitespace or comments.
     #[facet(default)]
@@ -50,8 +61,8 @@ pub struct Grammar {
     pub precedences: Option<Vec<Vec<Precedence>>>,
 
     /// Context-specific reserved word definitions.
-    #[facet(default)]
-    pub reserved: Option<HashMap<String, Vec<Rule>>>,
+    #[facet(default, opaque)]
+    pub reserved: Option<IndexMap<String, Vec<Rule>>>,
 
     /// Rules implemented externally via a scanner.
     #[facet(default)]
@@ -99,7 +110,336 @@ pub enum Precedence {
 /// Returns [`GrammarError::JsonParse`] if the provided string is not valid JSON
 /// or fails schema deserialization.
 pub fn parse_grammar(json: &str) -> Result<Grammar, GrammarError> {
-    facet_json::from_str(json).map_err(|e| GrammarError::JsonParse(e.to_string()))
+    let value = json::parse(json).map_err(|e| GrammarError::JsonParse(e.to_string()))?;
+    grammar_from_value(&value)
+}
+
+/// The top-level keys of a `grammar.json` that [`Grammar`] has a field for.
+const GRAMMAR_KEYS: &[&str] = &[
+    "$schema",
+    "name",
+    "inherits",
+    "word",
+    "rules",
+    "extras",
+    "conflicts",
+    "precedences",
+    "externals",
+    "inline",
+    "supertypes",
+    "reserved",
+];
+
+/// The keys of a rule object that [`Rule`] has a field for.
+const RULE_KEYS: &[&str] = &[
+    "type",
+    "value",
+    "name",
+    "content",
+    "context_name",
+    "members",
+    "named",
+    "flags",
+];
+
+/// Lists the keys of a `grammar.json` document that [`Grammar`] and
+/// [`Rule`] have no field for, by their path in the document, such as
+/// `rules.expression.members[1].comment`. [`parse_grammar`] ignores them, so
+/// [`Grammar::to_json`] would leave them out of a rewritten file.
+///
+/// # Errors
+///
+/// Returns [`GrammarError::JsonParse`] if the provided string is not valid JSON.
+pub fn unknown_keys(json: &str) -> Result<Vec<String>, GrammarError> {
+    let value = json::parse(json).map_err(|e| GrammarError::JsonParse(e.to_string()))?;
+    let mut found = Vec::new();
+    for (key, value) in value.as_object().unwrap_or_default() {
+        if !GRAMMAR_KEYS.contains(&key.as_str()) {
+            found.push(key.clone());
+            continue;
+        }
+        match key.as_str() {
+            "rules" => {
+                for (name, rule) in value.as_object().unwrap_or_default() {
+                    unknown_rule_keys(rule, &format!("rules.{name}"), RULE_KEYS, &mut found);
+                }
+            }
+            "extras" | "externals" => {
+                for (i, rule) in value.as_array().unwrap_or_default().iter().enumerate() {
+                    unknown_rule_keys(rule, &format!("{key}[{i}]"), RULE_KEYS, &mut found);
+                }
+            }
+            "reserved" => {
+                for (context, words) in value.as_object().unwrap_or_default() {
+                    for (i, rule) in words.as_array().unwrap_or_default().iter().enumerate() {
+                        let path = format!("reserved.{context}[{i}]");
+                        unknown_rule_keys(rule, &path, RULE_KEYS, &mut found);
+                    }
+                }
+            }
+            "precedences" => {
+                for (i, level) in value.as_array().unwrap_or_default().iter().enumerate() {
+                    for (j, entry) in level.as_array().unwrap_or_default().iter().enumerate() {
+                        let path = format!("precedences[{i}][{j}]");
+                        unknown_rule_keys(entry, &path, &["type", "value", "name"], &mut found);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(found)
+}
+
+/// Adds the keys of the rule object `rule`, and of the rules within it, that
+/// are not among `known` to `found`.
+fn unknown_rule_keys(rule: &Value, path: &str, known: &[&str], found: &mut Vec<String>) {
+    for (key, value) in rule.as_object().unwrap_or_default() {
+        if !known.contains(&key.as_str()) {
+            found.push(format!("{path}.{key}"));
+        } else if key == "content" {
+            unknown_rule_keys(value, &format!("{path}.content"), RULE_KEYS, found);
+        } else if key == "members" {
+            for (i, member) in value.as_array().unwrap_or_default().iter().enumerate() {
+                let path = format!("{path}.members[{i}]");
+                unknown_rule_keys(member, &path, RULE_KEYS, found);
+            }
+        }
+    }
+}
+
+fn grammar_from_value(value: &Value) -> Result<Grammar, GrammarError> {
+    if value.as_object().is_none() {
+        return Err(shape_error("grammar", "an object", value));
+    }
+
+    let name = value
+        .get("name")
+        .ok_or_else(|| missing_field("grammar", "name"))
+        .and_then(|v| string_from_value(v, "name"))?;
+
+    let rules_value = value
+        .get("rules")
+        .ok_or_else(|| missing_field("grammar", "rules"))?;
+    let rules_entries = rules_value
+        .as_object()
+        .ok_or_else(|| shape_error("rules", "an object", rules_value))?;
+    let mut rules = IndexMap::with_capacity(rules_entries.len());
+    for (rule_name, rule_value) in rules_entries {
+        let rule = Rule::from_json(rule_value, &format!("rules.{rule_name}"))?;
+        rules.insert(rule_name.clone(), rule);
+    }
+
+    let reserved = optional(value, "reserved", |v| {
+        let entries = v
+            .as_object()
+            .ok_or_else(|| shape_error("reserved", "an object", v))?;
+        entries
+            .iter()
+            .map(|(context, words)| {
+                let path = format!("reserved.{context}");
+                Ok((context.clone(), rules_from_value(words, &path)?))
+            })
+            .collect()
+    })?;
+
+    Ok(Grammar {
+        schema: optional(value, "$schema", |v| string_from_value(v, "$schema"))?,
+        name,
+        inherits: optional(value, "inherits", |v| string_from_value(v, "inherits"))?,
+        rules,
+        extras: optional(value, "extras", |v| rules_from_value(v, "extras"))?,
+        precedences: optional(value, "precedences", precedences_from_value)?,
+        reserved,
+        externals: optional(value, "externals", |v| rules_from_value(v, "externals"))?,
+        inline: optional(value, "inline", |v| strings_from_value(v, "inline"))?,
+        conflicts: optional(value, "conflicts", |v| {
+            array_from_value(v, "conflicts")?
+                .iter()
+                .enumerate()
+                .map(|(i, group)| strings_from_value(group, &format!("conflicts[{i}]")))
+                .collect()
+        })?,
+        word: optional(value, "word", |v| string_from_value(v, "word"))?,
+        supertypes: optional(value, "supertypes", |v| strings_from_value(v, "supertypes"))?,
+    })
+}
+
+impl Grammar {
+    /// Serializes the grammar in tree-sitter's `grammar.json` format, with
+    /// two-space indentation and no trailing newline. Fields that are not
+    /// set are left out.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        let rules = |rules: &[Rule]| Value::Array(rules.iter().map(Rule::to_json).collect());
+        let strings = |strings: &[String]| {
+            Value::Array(strings.iter().map(|s| Value::from(s.as_str())).collect())
+        };
+        let mut entries = Vec::new();
+        let mut push = |key: &str, value: Option<Value>| {
+            entries.extend(value.map(|value| (key.to_string(), value)));
+        };
+        push("$schema", self.schema.as_deref().map(Value::from));
+        push("name", Some(Value::from(self.name.as_str())));
+        push("inherits", self.inherits.as_deref().map(Value::from));
+        push("word", self.word.as_deref().map(Value::from));
+        push(
+            "rules",
+            Some(Value::Object(
+                self.rules
+                    .iter()
+                    .map(|(name, rule)| (name.clone(), rule.to_json()))
+                    .collect(),
+            )),
+        );
+        push("extras", self.extras.as_deref().map(rules));
+        push(
+            "conflicts",
+            self.conflicts
+                .as_ref()
+                .map(|groups| Value::Array(groups.iter().map(|group| strings(group)).collect())),
+        );
+        push(
+            "precedences",
+            self.precedences.as_ref().map(|levels| {
+                Value::Array(
+                    levels
+                        .iter()
+                        .map(|level| Value::Array(level.iter().map(Precedence::to_json).collect()))
+                        .collect(),
+                )
+            }),
+        );
+        push("externals", self.externals.as_deref().map(rules));
+        push("inline", self.inline.as_deref().map(strings));
+        push("supertypes", self.supertypes.as_deref().map(strings));
+        push(
+            "reserved",
+            self.reserved.as_ref().map(|reserved| {
+                Value::Object(
+                    reserved
+                        .iter()
+                        .map(|(context, words)| (context.clone(), rules(words)))
+                        .collect(),
+                )
+            }),
+        );
+        Value::Object(entries).to_pretty_string()
+    }
+
+    /// Rewrites every rule of the grammar, including its extras, externals
+    /// and reserved words, into its canonical shape with
+    /// [`Rule::canonicalize`]. Along with the fixed key order of
+    /// [`Grammar::to_json`], this gives one layout for grammars that differ
+    /// only in how their generator wrapped and ordered them, so that diffs
+    /// between them show only what changed.
+    pub fn canonicalize(&mut self) {
+        let rules = self
+            .rules
+            .values_mut()
+            .chain(self.extras.iter_mut().flatten())
+            .chain(self.externals.iter_mut().flatten())
+            .chain(
+                self.reserved
+                    .iter_mut()
+                    .flat_map(|r| r.values_mut().flatten()),
+            );
+        for rule in rules {
+            rule.canonicalize();
+        }
+    }
+}
+
+impl Precedence {
+    fn to_json(&self) -> Value {
+        match self {
+            Precedence::String(value) => Value::Object(vec![
+                ("type".to_string(), Value::from("STRING")),
+                ("value".to_string(), Value::from(value.as_str())),
+            ]),
+            Precedence::SymbolRule { name, .. } => Value::Object(vec![
+                ("type".to_string(), Value::from("SYMBOL")),
+                ("name".to_string(), Value::from(name.as_str())),
+            ]),
+        }
+    }
+}
+
+fn precedences_from_value(value: &Value) -> Result<Vec<Vec<Precedence>>, GrammarError> {
+    let mut levels = Vec::new();
+    for (i, level) in array_from_value(value, "precedences")?.iter().enumerate() {
+        let mut entries = Vec::new();
+        for (j, entry) in array_from_value(level, &format!("precedences[{i}]"))?
+            .iter()
+            .enumerate()
+        {
+            let path = format!("precedences[{i}][{j}]");
+            let rule = Rule::from_json(entry, &path)?;
+            let precedence = match (&rule.rule_type, rule.string_value(), &rule.name) {
+                (RuleType::String, Some(value), _) => Precedence::String(value.to_string()),
+                (RuleType::Symbol, _, Some(name)) => Precedence::SymbolRule {
+                    rule_type: RuleType::Symbol,
+                    name: name.clone(),
+                },
+                _ => return Err(shape_error(&path, "a STRING or SYMBOL rule", entry)),
+            };
+            entries.push(precedence);
+        }
+        levels.push(entries);
+    }
+    Ok(levels)
+}
+
+/// Reads `key` from `object` if it is present and not `null`.
+fn optional<T>(
+    object: &Value,
+    key: &str,
+    read: impl FnOnce(&Value) -> Result<T, GrammarError>,
+) -> Result<Option<T>, GrammarError> {
+    match object.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(v) => read(v).map(Some),
+    }
+}
+
+fn array_from_value<'a>(value: &'a Value, path: &str) -> Result<&'a [Value], GrammarError> {
+    value
+        .as_array()
+        .ok_or_else(|| shape_error(path, "an array", value))
+}
+
+fn string_from_value(value: &Value, path: &str) -> Result<String, GrammarError> {
+    value
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| shape_error(path, "a string", value))
+}
+
+fn strings_from_value(value: &Value, path: &str) -> Result<Vec<String>, GrammarError> {
+    array_from_value(value, path)?
+        .iter()
+        .enumerate()
+        .map(|(i, item)| string_from_value(item, &format!("{path}[{i}]")))
+        .collect()
+}
+
+fn rules_from_value(value: &Value, path: &str) -> Result<Vec<Rule>, GrammarError> {
+    array_from_value(value, path)?
+        .iter()
+        .enumerate()
+        .map(|(i, item)| Rule::from_json(item, &format!("{path}[{i}]")))
+        .collect()
+}
+
+pub(crate) fn missing_field(path: &str, field: &str) -> GrammarError {
+    GrammarError::JsonParse(format!("{path}: missing field '{field}'"))
+}
+
+pub(crate) fn shape_error(path: &str, expected: &str, found: &Value) -> GrammarError {
+    GrammarError::JsonParse(format!(
+        "{path}: expected {expected}, found {}",
+        found.kind()
+    ))
 }
 
 /// Possible errors raised during grammar parsing or validation.
@@ -154,9 +494,9 @@ mod tests {
 
         let grammar = parse_grammar(json).unwrap_or_else(|e| {
             if let GrammarError::JsonParse(inner) = e {
-                eprintln!("JSON parse error:\n{}", inner);
+                eprintln!("JSON parse error:\n{inner}");
             } else {
-                eprintln!("Grammar error: {}", e);
+                eprintln!("Grammar error: {e}");
             }
             std::process::exit(1);
         });
@@ -165,6 +505,90 @@ mod tests {
         assert_eq!(grammar.rules.len(), 2);
     }
 
+    #[test]
+    fn test_to_json_round_trips() {
+        let json = r#"{
+            "name": "test",
+            "word": "word",
+            "rules": {
+                "program": {"type": "REPEAT", "content": {"type": "CHOICE", "members": [
+                    {"type": "ALIAS", "content": {"type": "SYMBOL", "name": "word"}, "named": true, "value": "name"},
+                    {"type": "FIELD", "name": "op", "content": {"type": "STRING", "value": "+"}},
+                    {"type": "PREC_LEFT", "value": "sum", "content": {"type": "BLANK"}}
+                ]}},
+                "word": {"type": "PATTERN", "value": "[a-z]+", "flags": "i"}
+            },
+            "extras": [{"type": "PATTERN", "value": "\\s"}],
+            "conflicts": [["program", "word"]],
+            "precedences": [[{"type": "STRING", "value": "sum"}, {"type": "SYMBOL", "name": "word"}]],
+            "reserved": {"global": [{"type": "STRING", "value": "if"}]}
+        }"#;
+        let serialized = parse_grammar(json).unwrap().to_json();
+        assert!(serialized.starts_with("{\n  \"name\": \"test\",\n  \"word\": \"word\",\n"));
+        let alias = &serialized[serialized.find("\"ALIAS\"").unwrap()..];
+        assert!(
+            alias.find("\"content\"") < alias.find("\"value\""),
+            "an alias's value comes after its content"
+        );
+        let reparsed = parse_grammar(&serialized).unwrap();
+        assert_eq!(reparsed.to_json(), serialized);
+        assert_eq!(reparsed.rules, parse_grammar(json).unwrap().rules);
+    }
+
+    #[test]
+    fn test_unknown_keys_are_listed_by_path() {
+        let json = r#"{
+            "name": "test",
+            "version": 2,
+            "rules": {
+                "program": {"type": "SEQ", "doc": "x", "members": [
+                    {"type": "FIELD", "name": "a", "content": {"type": "BLANK", "span": 1}}
+                ]}
+            },
+            "extras": [{"type": "PATTERN", "value": "\\s", "flags": "i"}],
+            "precedences": [[{"type": "STRING", "value": "p", "named": true}]],
+            "reserved": {"global": [{"type": "STRING", "value": "if", "hint": 0}]}
+        }"#;
+        assert_eq!(
+            unknown_keys(json).unwrap(),
+            [
+                "version",
+                "rules.program.doc",
+                "rules.program.members[0].content.span",
+                "precedences[0][0].named",
+                "reserved.global[0].hint",
+            ]
+        );
+        assert!(matches!(unknown_keys("{"), Err(GrammarError::JsonParse(_))));
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        let wrapped = r#"{
+            "rules": {
+                "program": {"type": "SEQ", "members": [{"type": "SYMBOL", "name": "word"}]},
+                "word": {"flags": "i", "value": "[a-z]+", "type": "PATTERN"}
+            },
+            "extras": [{"type": "CHOICE", "members": [{"type": "PATTERN", "value": "\\s"}]}],
+            "name": "test"
+        }"#;
+        let plain = r#"{
+            "name": "test",
+            "rules": {
+                "program": {"type": "SYMBOL", "name": "word"},
+                "word": {"type": "PATTERN", "value": "[a-z]+", "flags": "i"}
+            },
+            "extras": [{"type": "PATTERN", "value": "\\s"}]
+        }"#;
+        let canonical = |json| {
+            let mut grammar = parse_grammar(json).unwrap();
+            grammar.canonicalize();
+            grammar.to_json()
+        };
+        assert_eq!(canonical(wrapped), canonical(plain));
+        assert_eq!(canonical(plain), parse_grammar(plain).unwrap().to_json());
+    }
+
     #[test]
     fn test_parse_precedence() {
         let json = r#"{
@@ -187,9 +611,9 @@ mod tests {
 
         let grammar = parse_grammar(json).unwrap_or_else(|e| {
             if let GrammarError::JsonParse(inner) = e {
-                eprintln!("JSON parse error:\n{}", inner);
+                eprintln!("JSON parse error:\n{inner}");
             } else {
-                eprintln!("Grammar error: {}", e);
+                eprintln!("Grammar error: {e}");
             }
             std::process::exit(1);
         });
@@ -0,0 +1,1115 @@
+//! Evaluating `grammar.js` files without Node.js.
+//!
+//! Grammars are written as JavaScript calls to tree-sitter's DSL, and only
+//! the `grammar.json` that `tree-sitter generate` writes from them is data.
+//! Here `grammar.js` runs in boa, a JavaScript engine written in Rust, with
+//! `grammar`, `seq`, `choice`, `prec` and the rest of the DSL implemented as
+//! native functions. They build the same rule objects as tree-sitter's
+//! `dsl.js` and check the same things, so the grammar that comes out is the
+//! one tree-sitter would write.
+//!
+//! Grammar files are loaded as `CommonJS` modules. `require` loads other
+//! grammars, relative to the file or from a `node_modules` directory above
+//! it, as grammars that extend another one do, and JSON files. ES modules
+//! and Node's built-in modules are not available.
+
+use super::{parse_grammar, Grammar, GrammarError};
+use boa_engine::native_function::NativeFunctionPointer;
+use boa_engine::object::builtins::{JsArray, JsProxy, JsRegExp};
+use boa_engine::object::{FunctionObjectBuilder, ObjectInitializer};
+use boa_engine::property::Attribute;
+use boa_engine::{
+    js_string, Context, JsError, JsNativeError, JsObject, JsResult, JsString, JsValue,
+    NativeFunction,
+};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The schema `tree-sitter generate` names at the top of `grammar.json`.
+const SCHEMA: &str = "https://tree-sitter.github.io/tree-sitter/assets/schemas/grammar.schema.json";
+
+/// Evaluates the `grammar.js` file at `path` into the grammar it exports.
+///
+/// # Errors
+///
+/// Returns an [`EvaluationError`] if the file can't be read, if evaluating
+/// it throws, such as when a rule refers to a rule that doesn't exist, or
+/// if it doesn't export a grammar.
+pub fn evaluate(path: impl AsRef<Path>) -> Result<Grammar, EvaluationError> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path).map_err(|error| EvaluationError::Read {
+        path: path.to_path_buf(),
+        error,
+    })?;
+    evaluate_str(&source, path)
+}
+
+/// Evaluates `source` as the `grammar.js` file at `path`, which is where
+/// the modules it requires are looked for.
+///
+/// # Errors
+///
+/// Returns an [`EvaluationError`] if evaluating `source` throws or it
+/// doesn't export a grammar.
+pub fn evaluate_str(source: &str, path: impl AsRef<Path>) -> Result<Grammar, EvaluationError> {
+    let mut context = Context::default();
+    let json = run(source, path.as_ref(), &mut context).map_err(|error| {
+        EvaluationError::Exception(match error.try_native(&mut context) {
+            Ok(error) => error.to_string(),
+            Err(_) => error.to_opaque(&mut context).display().to_string(),
+        })
+    })?;
+    let mut grammar = parse_grammar(&json).map_err(EvaluationError::Grammar)?;
+    grammar.schema.get_or_insert_with(|| SCHEMA.to_string());
+    Ok(grammar)
+}
+
+/// Possible errors raised while evaluating a `grammar.js` file.
+#[derive(Debug)]
+pub enum EvaluationError {
+    /// The file could not be read.
+    Read {
+        /// The file.
+        path: PathBuf,
+        /// Why it could not be read.
+        error: std::io::Error,
+    },
+
+    /// Evaluating the file threw, with this message.
+    Exception(String),
+
+    /// What the file exported is not a valid grammar.
+    Grammar(GrammarError),
+}
+
+impl fmt::Display for EvaluationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvaluationError::Read { path, error } => write!(f, "{}: {error}", path.display()),
+            EvaluationError::Exception(message) => f.write_str(message),
+            EvaluationError::Grammar(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for EvaluationError {}
+
+/// Runs a grammar file with the DSL defined, returning the JSON of the
+/// grammar it exports.
+fn run(source: &str, path: &Path, context: &mut Context) -> JsResult<String> {
+    define_dsl(context)?;
+    let cache = JsObject::with_object_proto(context.intrinsics());
+    let exports = run_module(source, path, &cache, context)?;
+    let grammar = match exports.as_object() {
+        Some(exports) => exports.get(js_string!("grammar"), context)?,
+        None => JsValue::undefined(),
+    };
+    if !grammar.is_object() {
+        return Err(error(format!(
+            "{} doesn't export a grammar: set module.exports to the result of grammar()",
+            path.display()
+        )));
+    }
+    let json = call_method("JSON", "stringify", &[grammar], context)?;
+    Ok(json.to_string(context)?.to_std_string_escaped())
+}
+
+/// Defines the DSL's functions as globals, along with `console`.
+fn define_dsl(context: &mut Context) -> JsResult<()> {
+    let functions: [(&str, usize, NativeFunctionPointer); 13] = [
+        ("alias", 2, alias),
+        ("blank", 0, blank),
+        ("choice", 0, choice),
+        ("field", 2, field),
+        ("grammar", 2, grammar),
+        ("optional", 1, optional),
+        ("prec", 2, prec),
+        ("repeat", 1, repeat),
+        ("repeat1", 1, repeat1),
+        ("reserved", 2, reserved),
+        ("seq", 0, seq),
+        ("sym", 1, sym),
+        ("token", 1, token),
+    ];
+    for (name, length, function) in functions {
+        let function = function_object(name, length, function, context);
+        context.register_global_property(JsString::from(name), function, Attribute::all())?;
+    }
+    let console = JsObject::with_object_proto(context.intrinsics());
+    context.register_global_property(js_string!("console"), console, Attribute::all())?;
+    let methods: [(&str, &str, usize, NativeFunctionPointer); 7] = [
+        ("prec", "left", 2, prec_left),
+        ("prec", "right", 2, prec_right),
+        ("prec", "dynamic", 2, prec_dynamic),
+        ("token", "immediate", 1, token_immediate),
+        ("console", "log", 0, log),
+        ("console", "warn", 0, log),
+        ("console", "error", 0, log),
+    ];
+    for (object, name, length, function) in methods {
+        let function = function_object(name, length, function, context);
+        global(object, context)?.set(JsString::from(name), function, true, context)?;
+    }
+    Ok(())
+}
+
+fn function_object(
+    name: &str,
+    length: usize,
+    function: NativeFunctionPointer,
+    context: &mut Context,
+) -> JsObject {
+    FunctionObjectBuilder::new(context.realm(), NativeFunction::from_fn_ptr(function))
+        .name(JsString::from(name))
+        .length(length)
+        .build()
+        .into()
+}
+
+/// Runs a `CommonJS` module, returning what it exports. Modules are cached by
+/// path, so a module required twice runs once.
+fn run_module(
+    source: &str,
+    path: &Path,
+    cache: &JsObject,
+    context: &mut Context,
+) -> JsResult<JsValue> {
+    let exports = JsObject::with_object_proto(context.intrinsics());
+    let module = JsObject::with_object_proto(context.intrinsics());
+    module.set(js_string!("exports"), exports.clone(), true, context)?;
+    cache.set(
+        JsString::from(path.to_string_lossy().as_ref()),
+        module.clone(),
+        true,
+        context,
+    )?;
+
+    // The module is compiled with the `Function` constructor rather than
+    // evaluated as a script, which boa can't do while a native function,
+    // such as `require`, is running.
+    let parameters = ["exports", "require", "module", "__filename", "__dirname"];
+    let mut args: Vec<JsValue> = parameters
+        .into_iter()
+        .map(|parameter| JsString::from(parameter).into())
+        .collect();
+    args.push(JsString::from(source).into());
+    let function = global("Function", context)?.call(&JsValue::undefined(), &args, context)?;
+    let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+    let require = FunctionObjectBuilder::new(
+        context.realm(),
+        NativeFunction::from_copy_closure_with_captures(require, (dir.clone(), cache.clone())),
+    )
+    .name(js_string!("require"))
+    .length(1)
+    .build();
+    let Some(function) = function.as_callable() else {
+        return Err(type_error("Function() returned a non-function"));
+    };
+    function.call(
+        &JsValue::undefined(),
+        &[
+            exports.into(),
+            require.into(),
+            module.clone().into(),
+            JsString::from(path.to_string_lossy().as_ref()).into(),
+            JsString::from(dir.to_string_lossy().as_ref()).into(),
+        ],
+        context,
+    )?;
+    module.get(js_string!("exports"), context)
+}
+
+/// `require(specifier)`, for a module in `dir`.
+fn require(
+    _: &JsValue,
+    args: &[JsValue],
+    (dir, cache): &(PathBuf, JsObject),
+    context: &mut Context,
+) -> JsResult<JsValue> {
+    let Some(JsValue::String(specifier)) = args.first() else {
+        return Err(type_error("require() takes the name of a module"));
+    };
+    let specifier = specifier.to_std_string_escaped();
+    let Some(path) = resolve(&specifier, dir) else {
+        return Err(error(format!("Cannot find module '{specifier}'")));
+    };
+    let cached = cache.get(JsString::from(path.to_string_lossy().as_ref()), context)?;
+    if let Some(module) = cached.as_object() {
+        return module.get(js_string!("exports"), context);
+    }
+    let source = fs::read_to_string(&path)
+        .map_err(|e| error(format!("Cannot read module '{specifier}': {e}")))?;
+    if path
+        .extension()
+        .is_some_and(|extension| extension == "json")
+    {
+        return call_method("JSON", "parse", &[JsString::from(source).into()], context);
+    }
+    run_module(&source, &path, cache, context)
+}
+
+/// Finds the file `require(specifier)` loads in a module in `dir`, as Node
+/// does: relative paths from `dir`, and other names from the `node_modules`
+/// directories of `dir` and the directories above it.
+fn resolve(specifier: &str, dir: &Path) -> Option<PathBuf> {
+    if specifier.starts_with("./")
+        || specifier.starts_with("../")
+        || Path::new(specifier).is_absolute()
+    {
+        return resolve_file(&dir.join(specifier));
+    }
+    dir.ancestors()
+        .find_map(|dir| resolve_file(&dir.join("node_modules").join(specifier)))
+}
+
+/// The file a module path names: itself, with `.js` or `.json` added, or
+/// the `index.js` in it.
+fn resolve_file(base: &Path) -> Option<PathBuf> {
+    let with_extension = |extension: &str| {
+        let mut path = base.as_os_str().to_owned();
+        path.push(extension);
+        PathBuf::from(path)
+    };
+    [
+        base.to_path_buf(),
+        with_extension(".js"),
+        with_extension(".json"),
+        base.join("index.js"),
+    ]
+    .into_iter()
+    .find(|path| path.is_file())
+}
+
+fn global(name: &str, context: &mut Context) -> JsResult<JsObject> {
+    let value = context.global_object().get(JsString::from(name), context)?;
+    value
+        .as_object()
+        .cloned()
+        .ok_or_else(|| type_error(format!("{name} is not an object")))
+}
+
+/// Calls a method of a global object, such as `JSON.parse`.
+fn call_method(
+    object: &str,
+    method: &str,
+    args: &[JsValue],
+    context: &mut Context,
+) -> JsResult<JsValue> {
+    let object = global(object, context)?;
+    let function = object.get(JsString::from(method), context)?;
+    match function.as_callable() {
+        Some(function) => function.call(&object.into(), args, context),
+        None => Err(type_error(format!("{method} is not a function"))),
+    }
+}
+
+fn error(message: impl Into<Box<str>>) -> JsError {
+    JsNativeError::error().with_message(message).into()
+}
+
+fn type_error(message: impl Into<Box<str>>) -> JsError {
+    JsNativeError::typ().with_message(message).into()
+}
+
+fn arg(args: &[JsValue], index: usize) -> JsValue {
+    args.get(index).cloned().unwrap_or_default()
+}
+
+fn array(values: Vec<JsValue>, context: &mut Context) -> JsValue {
+    JsArray::from_iter(values, context).into()
+}
+
+/// The elements of `value`, or `message` as an error if it isn't an array.
+fn elements(value: &JsValue, message: &str, context: &mut Context) -> JsResult<Vec<JsValue>> {
+    let Some(array) = value.as_object().filter(|object| object.is_array()) else {
+        return Err(error(message.to_string()));
+    };
+    let length = JsArray::from_object(array.clone())?.length(context)?;
+    (0..length).map(|i| array.get(i, context)).collect()
+}
+
+/// The names of the own enumerable properties of `object`, in order.
+fn keys(object: &JsObject, context: &mut Context) -> JsResult<Vec<JsString>> {
+    let keys = call_method("Object", "keys", &[object.clone().into()], context)?;
+    elements(&keys, "Object.keys() returned a non-array", context)?
+        .into_iter()
+        .map(|key| key.to_string(context))
+        .collect()
+}
+
+/// A rule object of the given type with `properties`.
+fn rule(rule_type: &str, properties: Vec<(&str, JsValue)>, context: &mut Context) -> JsValue {
+    let mut object = ObjectInitializer::new(context);
+    object.property(
+        js_string!("type"),
+        JsString::from(rule_type),
+        Attribute::all(),
+    );
+    for (key, value) in properties {
+        object.property(JsString::from(key), value, Attribute::all());
+    }
+    object.build().into()
+}
+
+fn symbol(name: JsString, context: &mut Context) -> JsValue {
+    rule("SYMBOL", vec![("name", name.into())], context)
+}
+
+/// Turns what a grammar gives as a rule into a rule object: strings match
+/// themselves, regular expressions become patterns, and rule objects are
+/// kept as they are.
+fn normalize(value: &JsValue, context: &mut Context) -> JsResult<JsValue> {
+    match value {
+        JsValue::Undefined => Err(error("Undefined symbol")),
+        JsValue::String(text) => Ok(rule(
+            "STRING",
+            vec![("value", text.clone().into())],
+            context,
+        )),
+        JsValue::Object(object) => {
+            if let Ok(regexp) = JsRegExp::from_object(object.clone()) {
+                let source = pattern_source(&regexp.source(context)?);
+                let flags = regexp.flags(context)?;
+                let mut properties = vec![("value", JsString::from(source).into())];
+                if !flags.is_empty() {
+                    properties.push(("flags", JsString::from(flags).into()));
+                }
+                return Ok(rule("PATTERN", properties, context));
+            }
+            if is_undefined_symbol(value, context)? {
+                return Err(JsError::from_opaque(value.clone()));
+            }
+            if object.get(js_string!("type"), context)?.is_string() {
+                return Ok(value.clone());
+            }
+            Err(type_error(format!("Invalid rule: {}", value.display())))
+        }
+        _ => Err(type_error(format!("Invalid rule: {}", value.display()))),
+    }
+}
+
+/// The source of a regular expression as V8 gives it, which escapes `/`
+/// only outside of classes and where it isn't already escaped, from boa's,
+/// which escapes every `/`.
+fn pattern_source(source: &str) -> String {
+    let source = source.replace("\\/", "/");
+    let mut escaped = String::with_capacity(source.len());
+    let mut in_class = false;
+    let mut chars = source.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                escaped.push(c);
+                escaped.extend(chars.next());
+                continue;
+            }
+            '/' if !in_class => escaped.push('\\'),
+            '[' => in_class = true,
+            ']' => in_class = false,
+            _ => {}
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Whether `value` is the error `$.name` gives for a rule that doesn't
+/// exist.
+fn is_undefined_symbol(value: &JsValue, context: &mut Context) -> JsResult<bool> {
+    match value.as_object() {
+        Some(object) => object.has_own_property(js_string!("symbol"), context),
+        None => Ok(false),
+    }
+}
+
+/// The `name` of the symbol `value` normalizes to.
+fn symbol_name(value: &JsValue, context: &mut Context) -> JsResult<JsValue> {
+    match normalize(value, context)?.as_object() {
+        Some(rule) => rule.get(js_string!("name"), context),
+        None => Ok(JsValue::undefined()),
+    }
+}
+
+fn normalize_all(values: &[JsValue], context: &mut Context) -> JsResult<Vec<JsValue>> {
+    values
+        .iter()
+        .map(|value| normalize(value, context))
+        .collect()
+}
+
+fn alias(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let aliased = normalize(&arg(args, 0), context)?;
+    let value = arg(args, 1);
+    let (named, name) = if value.is_string() {
+        (false, value.clone())
+    } else if is_undefined_symbol(&value, context)? {
+        let symbol = value
+            .as_object()
+            .map(|error| error.get(js_string!("symbol"), context))
+            .transpose()?
+            .unwrap_or_default();
+        (true, symbol_name(&symbol, context)?)
+    } else {
+        let object = value.as_object();
+        match object
+            .map(|o| o.get(js_string!("type"), context))
+            .transpose()?
+        {
+            Some(JsValue::String(rule_type)) if rule_type == js_string!("SYMBOL") => {
+                (true, symbol_name(&value, context)?)
+            }
+            _ => {
+                return Err(error(format!("Invalid alias value {}", value.display())));
+            }
+        }
+    };
+    Ok(rule(
+        "ALIAS",
+        vec![
+            ("content", aliased),
+            ("named", named.into()),
+            ("value", name),
+        ],
+        context,
+    ))
+}
+
+// Native functions return a `JsResult` whether or not they can fail.
+#[allow(clippy::unnecessary_wraps)]
+fn blank(_: &JsValue, _: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    Ok(rule("BLANK", Vec::new(), context))
+}
+
+fn choice(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let members = normalize_all(args, context)?;
+    let members = array(members, context);
+    Ok(rule("CHOICE", vec![("members", members)], context))
+}
+
+fn field(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let labelled = normalize(&arg(args, 1), context)?;
+    Ok(rule(
+        "FIELD",
+        vec![("name", arg(args, 0)), ("content", labelled)],
+        context,
+    ))
+}
+
+fn optional(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let members = vec![
+        normalize(&arg(args, 0), context)?,
+        rule("BLANK", Vec::new(), context),
+    ];
+    let members = array(members, context);
+    Ok(rule("CHOICE", vec![("members", members)], context))
+}
+
+/// A precedence rule of `rule_type`, from a precedence and a rule, or for
+/// `left` and `right` also from a rule alone, with a precedence of 0.
+fn precedence(rule_type: &str, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let (value, inner) = match (arg(args, 0), arg(args, 1)) {
+        (inner, JsValue::Undefined | JsValue::Null)
+            if matches!(rule_type, "PREC_LEFT" | "PREC_RIGHT") =>
+        {
+            (JsValue::from(0), inner)
+        }
+        (JsValue::Undefined | JsValue::Null, _) => {
+            return Err(error("Missing precedence value"));
+        }
+        (value, inner) => (value, inner),
+    };
+    let inner = normalize(&inner, context)?;
+    Ok(rule(
+        rule_type,
+        vec![("value", value), ("content", inner)],
+        context,
+    ))
+}
+
+fn prec(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    precedence("PREC", args, context)
+}
+
+fn prec_left(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    precedence("PREC_LEFT", args, context)
+}
+
+fn prec_right(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    precedence("PREC_RIGHT", args, context)
+}
+
+fn prec_dynamic(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    precedence("PREC_DYNAMIC", args, context)
+}
+
+fn wrap(rule_type: &str, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let inner = normalize(&arg(args, 0), context)?;
+    Ok(rule(rule_type, vec![("content", inner)], context))
+}
+
+fn repeat(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    wrap("REPEAT", args, context)
+}
+
+fn repeat1(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    wrap("REPEAT1", args, context)
+}
+
+fn token(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    wrap("TOKEN", args, context)
+}
+
+fn token_immediate(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    wrap("IMMEDIATE_TOKEN", args, context)
+}
+
+fn reserved(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let word_set = arg(args, 0);
+    if !word_set.is_string() {
+        return Err(error(format!(
+            "Invalid reserved word set name: {}",
+            word_set.display()
+        )));
+    }
+    let inner = normalize(&arg(args, 1), context)?;
+    Ok(rule(
+        "RESERVED",
+        vec![("content", inner), ("context_name", word_set)],
+        context,
+    ))
+}
+
+fn seq(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let members = normalize_all(args, context)?;
+    let members = array(members, context);
+    Ok(rule("SEQ", vec![("members", members)], context))
+}
+
+fn sym(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let name = arg(args, 0).to_string(context)?;
+    Ok(symbol(name, context))
+}
+
+/// `console.log` and friends, which print to standard error so that they
+/// don't mix with a grammar written to standard output.
+#[allow(clippy::unnecessary_wraps)]
+fn log(_: &JsValue, args: &[JsValue], _: &mut Context) -> JsResult<JsValue> {
+    let line: Vec<String> = args
+        .iter()
+        .map(|arg| match arg {
+            JsValue::String(text) => text.to_std_string_escaped(),
+            other => other.display().to_string(),
+        })
+        .collect();
+    eprintln!("{}", line.join(" "));
+    Ok(JsValue::undefined())
+}
+
+/// The `$` passed to a grammar's functions: `$.name` is the symbol `name`
+/// if `rules` has a property `name`, and otherwise an error, which is
+/// thrown where it is used as a rule. Without `rules`, every name is a
+/// symbol, as for `externals`, which names tokens defined elsewhere.
+fn rule_builder(rules: Option<&JsObject>, context: &mut Context) -> JsValue {
+    match rules {
+        Some(rules) => JsProxy::builder(rules.clone()).get(rule_or_error),
+        None => JsProxy::builder(JsObject::with_null_proto()).get(any_symbol),
+    }
+    .build(context)
+    .into()
+}
+
+fn rule_or_error(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let (JsValue::Object(rules), JsValue::String(name)) = (arg(args, 0), arg(args, 1)) else {
+        return Ok(JsValue::undefined());
+    };
+    let symbol = symbol(name.clone(), context);
+    if rules.has_own_property(name.clone(), context)? {
+        return Ok(symbol);
+    }
+    let error = JsNativeError::reference()
+        .with_message(format!(
+            "Undefined symbol '{}'",
+            name.to_std_string_escaped()
+        ))
+        .to_opaque(context);
+    error.set(js_string!("symbol"), symbol, true, context)?;
+    Ok(error.into())
+}
+
+#[allow(clippy::unnecessary_wraps)]
+fn any_symbol(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    match arg(args, 1) {
+        JsValue::String(name) => Ok(symbol(name, context)),
+        _ => Ok(JsValue::undefined()),
+    }
+}
+
+/// The grammar a grammar extends, with the defaults of one that doesn't.
+struct Base {
+    name: JsValue,
+    rules: JsObject,
+    extras: Vec<JsValue>,
+    conflicts: Vec<JsValue>,
+    precedences: JsValue,
+    externals: JsValue,
+    inline: Vec<JsValue>,
+    supertypes: Vec<JsValue>,
+    reserved: JsObject,
+    word: JsValue,
+}
+
+impl Base {
+    fn new(context: &mut Context) -> Self {
+        let whitespace = rule(
+            "PATTERN",
+            vec![("value", js_string!("\\s").into())],
+            context,
+        );
+        Self {
+            name: JsValue::null(),
+            rules: JsObject::with_object_proto(context.intrinsics()),
+            extras: vec![whitespace],
+            conflicts: Vec::new(),
+            precedences: array(Vec::new(), context),
+            externals: array(Vec::new(), context),
+            inline: Vec::new(),
+            supertypes: Vec::new(),
+            reserved: JsObject::with_object_proto(context.intrinsics()),
+            word: JsValue::undefined(),
+        }
+    }
+
+    /// Reads what `grammar()` returned for the grammar being extended.
+    fn from_value(value: &JsValue, context: &mut Context) -> JsResult<Self> {
+        let grammar = match value.as_object() {
+            Some(object) => object.get(js_string!("grammar"), context)?,
+            None => JsValue::undefined(),
+        };
+        let Some(grammar) = grammar.as_object() else {
+            return Err(error(
+                "The grammar being extended must be the result of grammar()",
+            ));
+        };
+        let mut get = |key: &str| grammar.get(JsString::from(key), context);
+        let name = get("name")?;
+        let rules = get("rules")?;
+        let extras = get("extras")?;
+        let conflicts = get("conflicts")?;
+        let precedences = get("precedences")?;
+        let externals = get("externals")?;
+        let inline = get("inline")?;
+        let supertypes = get("supertypes")?;
+        let reserved = get("reserved")?;
+        let word = get("word")?;
+        let object = |value: JsValue, context: &mut Context| {
+            value
+                .as_object()
+                .cloned()
+                .unwrap_or_else(|| JsObject::with_object_proto(context.intrinsics()))
+        };
+        Ok(Self {
+            name,
+            rules: object(rules, context),
+            extras: elements(&extras, "Invalid extras", context)?,
+            conflicts: elements(&conflicts, "Invalid conflicts", context)?,
+            precedences,
+            externals,
+            inline: elements(&inline, "Invalid inline", context)?,
+            supertypes: elements(&supertypes, "Invalid supertypes", context)?,
+            reserved: object(reserved, context),
+            word,
+        })
+    }
+}
+
+/// The function `options[key]`, if there is one.
+fn option_function(
+    options: &JsObject,
+    key: &str,
+    context: &mut Context,
+) -> JsResult<Option<JsObject>> {
+    let value = options.get(JsString::from(key), context)?;
+    if !value.to_boolean() {
+        return Ok(None);
+    }
+    match value.as_callable() {
+        Some(function) => Ok(Some(function.clone())),
+        None => Err(error(format!(
+            "Grammar's '{key}' property must be a function."
+        ))),
+    }
+}
+
+/// Symbols with the names in `names`, for passing previous lists of rule
+/// names to functions that extend them.
+fn symbols(names: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let mut symbols = Vec::new();
+    for name in names {
+        let name = name.to_string(context)?;
+        symbols.push(symbol(name, context));
+    }
+    Ok(array(symbols, context))
+}
+
+/// `grammar(options)`, or `grammar(base, options)` to extend `base`.
+#[allow(clippy::too_many_lines)]
+fn grammar(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let (base, options, inherits) = match (arg(args, 0), arg(args, 1)) {
+        (options, JsValue::Undefined | JsValue::Null) => (Base::new(context), options, None),
+        (base, options) => {
+            let base = Base::from_value(&base, context)?;
+            let name = base.name.clone();
+            (base, options, Some(name))
+        }
+    };
+    let Some(options) = options.as_object() else {
+        return Err(error("grammar() takes an object of options"));
+    };
+
+    let mut externals = base.externals.clone();
+    if let Some(function) = option_function(options, "externals", context)? {
+        let builder = rule_builder(None, context);
+        let rules = function.call(
+            &builder,
+            &[builder.clone(), base.externals.clone()],
+            context,
+        )?;
+        let rules = elements(
+            &rules,
+            "Grammar's 'externals' property must return an array of rules.",
+            context,
+        )?;
+        let rules = normalize_all(&rules, context)?;
+        externals = array(rules, context);
+    }
+
+    let rule_map = JsObject::with_null_proto();
+    let option_rules = options.get(js_string!("rules"), context)?;
+    let option_rules = match option_rules {
+        JsValue::Undefined | JsValue::Null => None,
+        JsValue::Object(rules) => Some(rules),
+        _ => return Err(error("Grammar's 'rules' property must be an object.")),
+    };
+    let mut names = Vec::new();
+    if let Some(rules) = &option_rules {
+        names = keys(rules, context)?;
+    }
+    for name in names.iter().cloned().chain(keys(&base.rules, context)?) {
+        rule_map.set(name, true, true, context)?;
+    }
+    for external in elements(&externals, "Invalid externals", context)? {
+        if let Some(external) = external.as_object() {
+            if let JsValue::String(name) = external.get(js_string!("name"), context)? {
+                rule_map.set(name, true, true, context)?;
+            }
+        }
+    }
+    let builder = rule_builder(Some(&rule_map), context);
+
+    let name = options.get(js_string!("name"), context)?;
+    let Some(name_text) = name.as_string().map(JsString::to_std_string_escaped) else {
+        return Err(error("Grammar's 'name' property must be a string."));
+    };
+    let is_word = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    if name_text.starts_with(|c: char| c.is_ascii_digit()) || !name_text.chars().all(is_word) {
+        return Err(error(
+            "Grammar's 'name' property must not start with a digit and cannot contain non-word characters.",
+        ));
+    }
+
+    let rules = JsObject::with_object_proto(context.intrinsics());
+    for key in keys(&base.rules, context)? {
+        let rule = base.rules.get(key.clone(), context)?;
+        rules.set(key, rule, true, context)?;
+    }
+    if let Some(option_rules) = &option_rules {
+        for key in names {
+            let function = option_rules.get(key.clone(), context)?;
+            let Some(function) = function.as_callable() else {
+                return Err(error(format!(
+                    "Grammar rules must all be functions. '{}' rule is not.",
+                    key.to_std_string_escaped()
+                )));
+            };
+            let previous = base.rules.get(key.clone(), context)?;
+            let rule = function.call(&builder, &[builder.clone(), previous], context)?;
+            if rule.is_undefined() {
+                return Err(error(format!(
+                    "Rule '{}' returned undefined.",
+                    key.to_std_string_escaped()
+                )));
+            }
+            let rule = normalize(&rule, context)?;
+            rules.set(key, rule, true, context)?;
+        }
+    }
+
+    let reserved = JsObject::with_object_proto(context.intrinsics());
+    for key in keys(&base.reserved, context)? {
+        let words = base.reserved.get(key.clone(), context)?;
+        reserved.set(key, words, true, context)?;
+    }
+    match options.get(js_string!("reserved"), context)? {
+        JsValue::Undefined | JsValue::Null => {}
+        JsValue::Object(sets) => {
+            for key in keys(&sets, context)? {
+                let set_name = key.to_std_string_escaped();
+                let function = sets.get(key.clone(), context)?;
+                let Some(function) = function.as_callable() else {
+                    return Err(error(format!(
+                        "Grammar reserved word sets must all be functions. '{set_name}' is not."
+                    )));
+                };
+                let previous = base.reserved.get(key.clone(), context)?;
+                let words = function.call(&builder, &[builder.clone(), previous], context)?;
+                let words = elements(
+                    &words,
+                    &format!("Grammar's reserved word set '{set_name}' must be an array of rules."),
+                    context,
+                )?;
+                let words = normalize_all(&words, context)?;
+                let words = array(words, context);
+                reserved.set(key, words, true, context)?;
+            }
+        }
+        _ => return Err(error("Grammar's 'reserved' property must be an object.")),
+    }
+
+    let mut extras = base.extras.clone();
+    if let Some(function) = option_function(options, "extras", context)? {
+        let previous = array(base.extras.clone(), context);
+        let rules = function.call(&builder, &[builder.clone(), previous], context)?;
+        let rules = elements(
+            &rules,
+            "Grammar's 'extras' property must return an array of rules.",
+            context,
+        )?;
+        extras = normalize_all(&rules, context)?;
+    }
+
+    let mut word = base.word.clone();
+    if let Some(function) = option_function(options, "word", context)? {
+        let rule = function.call(&builder, std::slice::from_ref(&builder), context)?;
+        word = match rule.as_object() {
+            Some(rule) => rule.get(js_string!("name"), context)?,
+            None => JsValue::undefined(),
+        };
+        if !word.is_string() {
+            return Err(error("Grammar's 'word' property must be a named rule."));
+        }
+    }
+
+    let mut conflicts = base.conflicts.clone();
+    if let Some(function) = option_function(options, "conflicts", context)? {
+        const MESSAGE: &str = "Grammar's conflicts must be an array of arrays of rules.";
+        let mut previous = Vec::new();
+        for conflict in &base.conflicts {
+            let names = elements(conflict, MESSAGE, context)?;
+            previous.push(symbols(&names, context)?);
+        }
+        let previous = array(previous, context);
+        let result = function.call(&builder, &[builder.clone(), previous], context)?;
+        conflicts = Vec::new();
+        for conflict in elements(&result, MESSAGE, context)? {
+            let mut names = Vec::new();
+            for symbol in elements(&conflict, MESSAGE, context)? {
+                names.push(symbol_name(&symbol, context)?);
+            }
+            conflicts.push(array(names, context));
+        }
+    }
+
+    let mut inline = base.inline.clone();
+    if let Some(function) = option_function(options, "inline", context)? {
+        let previous = symbols(&base.inline, context)?;
+        let result = function.call(&builder, &[builder.clone(), previous], context)?;
+        let rules = elements(
+            &result,
+            "Grammar's inline must be an array of rules.",
+            context,
+        )?;
+        inline = Vec::new();
+        // tree-sitter drops repeated and undefined rules here, with a
+        // warning, rather than failing.
+        for rule in rules {
+            if is_undefined_symbol(&rule, context)? {
+                continue;
+            }
+            let name = symbol_name(&rule, context)?;
+            if !inline.contains(&name) {
+                inline.push(name);
+            }
+        }
+    }
+
+    let mut supertypes = base.supertypes.clone();
+    if let Some(function) = option_function(options, "supertypes", context)? {
+        let previous = symbols(&base.supertypes, context)?;
+        let result = function.call(&builder, &[builder.clone(), previous], context)?;
+        let rules = elements(
+            &result,
+            "Grammar's supertypes must be an array of rules.",
+            context,
+        )?;
+        supertypes = Vec::new();
+        for rule in rules {
+            supertypes.push(symbol_name(&rule, context)?);
+        }
+    }
+
+    let mut precedences = base.precedences.clone();
+    if let Some(function) = option_function(options, "precedences", context)? {
+        const MESSAGE: &str = "Grammar's precedences must be an array of arrays of rules.";
+        let result = function.call(
+            &builder,
+            &[builder.clone(), base.precedences.clone()],
+            context,
+        )?;
+        let mut lists = Vec::new();
+        for list in elements(&result, MESSAGE, context)? {
+            let list = elements(&list, MESSAGE, context)?;
+            let list = normalize_all(&list, context)?;
+            lists.push(array(list, context));
+        }
+        precedences = array(lists, context);
+    }
+
+    if keys(&rules, context)?.is_empty() {
+        return Err(error("Grammar must have at least one rule."));
+    }
+
+    let extras = array(extras, context);
+    let conflicts = array(conflicts, context);
+    let inline = array(inline, context);
+    let supertypes = array(supertypes, context);
+    let mut grammar = ObjectInitializer::new(context);
+    grammar.property(js_string!("name"), name, Attribute::all());
+    if let Some(inherits) = inherits {
+        grammar.property(js_string!("inherits"), inherits, Attribute::all());
+    }
+    for (key, value) in [
+        ("word", word),
+        ("rules", rules.into()),
+        ("extras", extras),
+        ("conflicts", conflicts),
+        ("precedences", precedences),
+        ("externals", externals),
+        ("inline", inline),
+        ("supertypes", supertypes),
+        ("reserved", reserved.into()),
+    ] {
+        grammar.property(JsString::from(key), value, Attribute::all());
+    }
+    let grammar = grammar.build();
+    let mut result = ObjectInitializer::new(context);
+    result.property(js_string!("grammar"), grammar, Attribute::all());
+    Ok(result.build().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::{RuleType, RuleValue};
+
+    const GRAMMAR_JS: &str = r"
+        const PREC = { sum: 1, product: 2 };
+
+        module.exports = grammar({
+          name: 'calc',
+          extras: $ => [/\s/, $.comment],
+          word: $ => $.identifier,
+          conflicts: $ => [[$.expression, $.call]],
+          inline: $ => [$._value, $._value],
+          supertypes: $ => [$._value],
+          rules: {
+            program: $ => repeat($._statement),
+            _statement: $ => seq($.expression, optional(';')),
+            expression: $ => choice(
+              prec.left(PREC.sum, seq(field('left', $.expression), '+', $.expression)),
+              prec.left(PREC.product, seq($.expression, '*', $.expression)),
+              $._value,
+            ),
+            call: $ => prec.dynamic(1, seq($.identifier, token.immediate('('), ')')),
+            _value: $ => choice($.identifier, alias($.number, $.literal), $.call),
+            identifier: $ => /[a-z]+/i,
+            number: $ => token(/\d+/),
+            comment: $ => token(seq('#', /.*/)),
+          },
+        });
+    ";
+
+    #[test]
+    fn test_evaluate_str() {
+        let grammar = evaluate_str(GRAMMAR_JS, "grammar.js").unwrap();
+        assert_eq!(grammar.name, "calc");
+        assert_eq!(grammar.schema.as_deref(), Some(SCHEMA));
+        assert_eq!(grammar.word.as_deref(), Some("identifier"));
+        assert_eq!(
+            grammar.conflicts,
+            Some(vec![vec!["expression".to_string(), "call".to_string()]])
+        );
+        assert_eq!(grammar.inline, Some(vec!["_value".to_string()]));
+        assert_eq!(grammar.externals, Some(Vec::new()));
+        let extras = grammar.extras.as_ref().unwrap();
+        assert_eq!(extras[0].value, Some(RuleValue::String("\\s".to_string())));
+        assert_eq!(extras[1].name.as_deref(), Some("comment"));
+
+        let expression = &grammar.rules["expression"];
+        let sum = &expression.members[0];
+        assert_eq!(sum.rule_type, RuleType::PrecLeft);
+        assert_eq!(sum.value, Some(RuleValue::Integer(1)));
+        let left = &sum.content.as_ref().unwrap().members[0];
+        assert_eq!(left.rule_type, RuleType::Field);
+        assert_eq!(left.name.as_deref(), Some("left"));
+
+        let value = &grammar.rules["_value"];
+        assert_eq!(value.members[1].rule_type, RuleType::Alias);
+        assert_eq!(value.members[1].named, Some(true));
+        assert_eq!(
+            value.members[1].value,
+            Some(RuleValue::String("literal".to_string()))
+        );
+        let identifier = &grammar.rules["identifier"];
+        assert_eq!(identifier.rule_type, RuleType::Pattern);
+        assert_eq!(identifier.flags.as_deref(), Some("i"));
+        let call = &grammar.rules["call"].content.as_ref().unwrap().members[1];
+        assert_eq!(call.rule_type, RuleType::ImmediateToken);
+    }
+
+    #[test]
+    fn test_errors() {
+        let message = |source: &str| evaluate_str(source, "grammar.js").unwrap_err().to_string();
+        assert_eq!(
+            message("module.exports = grammar({ name: 'x', rules: { a: $ => $.b } })"),
+            "ReferenceError: Undefined symbol 'b'"
+        );
+        assert_eq!(
+            message("module.exports = grammar({ name: 'x', rules: { a: $ => 1 } })"),
+            "TypeError: Invalid rule: 1"
+        );
+        assert_eq!(
+            message("module.exports = grammar({ name: '1x', rules: { a: $ => 'a' } })"),
+            "Error: Grammar's 'name' property must not start with a digit and cannot \
+             contain non-word characters."
+        );
+        assert_eq!(
+            message("module.exports = { name: 'x' }"),
+            "Error: grammar.js doesn't export a grammar: set module.exports to the result \
+             of grammar()"
+        );
+    }
+
+    #[test]
+    fn test_upstream_grammar_matches_tree_sitter() {
+        // tree-sitter-javascript uses precedences, fields, aliases,
+        // token.immediate, reserved words, a word rule and externals.
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/node_types");
+        let grammar = evaluate(fixtures.join("javascript/grammar.js")).unwrap();
+        let expected = fs::read_to_string(fixtures.join("javascript/grammar.json")).unwrap();
+        assert!(
+            grammar.to_json() == expected,
+            "grammar.js evaluates to a different grammar.json than tree-sitter generate writes"
+        );
+    }
+}
@@ -0,0 +1,1043 @@
+//! Normalization of a grammar into flat productions.
+//!
+//! This follows the first stages of tree-sitter's `prepare_grammar`: symbol
+//! names are resolved, string and pattern tokens are pulled out into a
+//! lexical grammar, repetitions become auxiliary recursive rules, and each
+//! rule is expanded into the list of symbol sequences it can produce. Node
+//! types, query checks and code generation all read this form rather than
+//! the nested rule trees of `grammar.json`.
+
+use std::collections::HashSet;
+
+use super::{Grammar, GrammarError, Rule, RuleType, RuleValue};
+
+/// How a symbol appears in syntax trees.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) enum VariableType {
+    /// A rule whose name starts with `_`; its children are spliced into
+    /// the parent.
+    Hidden,
+    /// A rule generated during preparation, such as a repetition.
+    Auxiliary,
+    /// A literal string token.
+    Anonymous,
+    /// A visible, named rule or token.
+    Named,
+}
+
+impl VariableType {
+    fn for_name(name: &str) -> Self {
+        if name.starts_with('_') {
+            VariableType::Hidden
+        } else {
+            VariableType::Named
+        }
+    }
+
+    pub(crate) fn is_visible(self) -> bool {
+        matches!(self, VariableType::Anonymous | VariableType::Named)
+    }
+}
+
+/// Which table a [`Symbol`] indexes into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) enum SymbolKind {
+    /// A token in the [`LexicalGrammar`].
+    Terminal,
+    /// A rule in the [`SyntaxGrammar`].
+    NonTerminal,
+    /// A token produced by the external scanner.
+    External,
+}
+
+/// A resolved reference to a token, rule or external token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct Symbol {
+    pub(crate) kind: SymbolKind,
+    pub(crate) index: usize,
+}
+
+impl Symbol {
+    pub(crate) const fn terminal(index: usize) -> Self {
+        Self {
+            kind: SymbolKind::Terminal,
+            index,
+        }
+    }
+
+    pub(crate) const fn non_terminal(index: usize) -> Self {
+        Self {
+            kind: SymbolKind::NonTerminal,
+            index,
+        }
+    }
+
+    pub(crate) const fn external(index: usize) -> Self {
+        Self {
+            kind: SymbolKind::External,
+            index,
+        }
+    }
+
+    pub(crate) fn is_non_terminal(self) -> bool {
+        self.kind == SymbolKind::NonTerminal
+    }
+}
+
+/// An alternative name a symbol takes in the tree.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct Alias {
+    pub(crate) value: String,
+    pub(crate) named: bool,
+}
+
+/// One symbol in a production, with the metadata that applies to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ProductionStep {
+    pub(crate) symbol: Symbol,
+    pub(crate) alias: Option<Alias>,
+    pub(crate) field_name: Option<String>,
+    /// The precedence around the step. The last step of a precedence that
+    /// doesn't end the production takes the enclosing one, so that the
+    /// precedence of a parse item is that of the step before its dot.
+    pub(crate) precedence: Precedence,
+    pub(crate) associativity: Option<Associativity>,
+}
+
+/// The precedence of a production step.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub(crate) enum Precedence {
+    #[default]
+    None,
+    Integer(i32),
+    /// A name ordered by the grammar's `precedences` lists.
+    Name(String),
+}
+
+/// Which way a production groups with others of the same precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum Associativity {
+    Left,
+    Right,
+}
+
+/// One way a rule can be spelled out as a sequence of symbols.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Production {
+    pub(crate) steps: Vec<ProductionStep>,
+    /// The numeric precedence around the last step, which is the one that
+    /// applies when the production is reduced.
+    pub(crate) precedence: i32,
+    pub(crate) associativity: Option<Associativity>,
+}
+
+/// A rule of the syntax grammar.
+#[derive(Debug, Clone)]
+pub(crate) struct SyntaxVariable {
+    pub(crate) name: String,
+    pub(crate) kind: VariableType,
+    pub(crate) productions: Vec<Production>,
+}
+
+impl SyntaxVariable {
+    /// The rule's productions, with auxiliary repetitions `R -> R R | x`
+    /// turned into the unambiguous left recursion `R -> x | R x`. `index`
+    /// is the rule's own index.
+    pub(crate) fn list_productions(&self, index: usize) -> Vec<Production> {
+        let symbol = Symbol::non_terminal(index);
+        let is_pair = |production: &Production| {
+            production.steps.len() == 2
+                && production.steps.iter().all(|step| {
+                    step.symbol == symbol && step.alias.is_none() && step.field_name.is_none()
+                })
+        };
+        let Some(pair) = self.productions.iter().find(|p| is_pair(p)) else {
+            return self.productions.clone();
+        };
+        let bases: Vec<&Production> = self.productions.iter().filter(|p| !is_pair(p)).collect();
+        let mut productions: Vec<Production> = bases.iter().map(|p| (*p).clone()).collect();
+        productions.extend(bases.iter().map(|base| {
+            let mut production = (*base).clone();
+            production.steps.insert(0, pair.steps[0].clone());
+            production
+        }));
+        productions
+    }
+}
+
+/// A token of the lexical grammar.
+#[derive(Debug, Clone)]
+pub(crate) struct LexicalVariable {
+    pub(crate) name: String,
+    pub(crate) kind: VariableType,
+    /// The rule that matches the token's text.
+    pub(crate) rule: Rule,
+}
+
+/// A token produced by the external scanner.
+#[derive(Debug, Clone)]
+pub(crate) struct ExternalToken {
+    pub(crate) name: String,
+    pub(crate) kind: VariableType,
+}
+
+/// The syntactic half of a prepared grammar.
+#[derive(Debug, Clone)]
+pub(crate) struct SyntaxGrammar {
+    /// Rules in declaration order, followed by auxiliary rules. The first
+    /// rule is the start rule.
+    pub(crate) variables: Vec<SyntaxVariable>,
+    pub(crate) extra_symbols: Vec<Symbol>,
+    pub(crate) external_tokens: Vec<ExternalToken>,
+    pub(crate) supertype_symbols: Vec<Symbol>,
+    pub(crate) variables_to_inline: Vec<Symbol>,
+}
+
+/// The lexical half of a prepared grammar.
+#[derive(Debug, Clone)]
+pub(crate) struct LexicalGrammar {
+    pub(crate) variables: Vec<LexicalVariable>,
+    /// Extras that are skipped between tokens rather than appearing in
+    /// the tree, such as whitespace.
+    pub(crate) separators: Vec<Rule>,
+}
+
+/// A prepared grammar.
+#[derive(Debug, Clone)]
+pub(crate) struct PreparedGrammar {
+    pub(crate) syntax: SyntaxGrammar,
+    pub(crate) lexical: LexicalGrammar,
+}
+
+impl PreparedGrammar {
+    /// The name and visibility a symbol has when it is not aliased.
+    pub(crate) fn symbol_info(&self, symbol: Symbol) -> (&str, VariableType) {
+        match symbol.kind {
+            SymbolKind::Terminal => {
+                let variable = &self.lexical.variables[symbol.index];
+                (&variable.name, variable.kind)
+            }
+            SymbolKind::NonTerminal => {
+                let variable = &self.syntax.variables[symbol.index];
+                (&variable.name, variable.kind)
+            }
+            SymbolKind::External => {
+                let token = &self.syntax.external_tokens[symbol.index];
+                (&token.name, token.kind)
+            }
+        }
+    }
+}
+
+/// A rule after names are resolved and tokens extracted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Ir {
+    Blank,
+    Symbol(Symbol),
+    Choice(Vec<Ir>),
+    Seq(Vec<Ir>),
+    /// One or more repetitions.
+    Repeat(Box<Ir>),
+    Alias(Alias, Box<Ir>),
+    Field(String, Box<Ir>),
+    Prec(Precedence, Option<Associativity>, Box<Ir>),
+}
+
+/// Prepares `grammar` for analysis and code generation.
+///
+/// # Errors
+///
+/// Returns [`GrammarError::Validation`] if a rule references an undefined
+/// symbol, a rule is malformed, or a name is used both as an external token
+/// and as a syntax rule.
+pub(crate) fn prepare(grammar: &Grammar) -> Result<PreparedGrammar, GrammarError> {
+    let pruned = without_unused_rules(grammar);
+    let grammar = pruned.as_ref().unwrap_or(grammar);
+
+    let mut lowerer = Lowerer {
+        grammar,
+        lexical: Vec::new(),
+        usage_counts: Vec::new(),
+        variable_name: "",
+        token_count: 0,
+    };
+
+    let mut rules = Vec::with_capacity(grammar.rules.len());
+    for (name, rule) in &grammar.rules {
+        lowerer.variable_name = name;
+        lowerer.token_count = 0;
+        rules.push(lowerer.lower(rule)?);
+    }
+    let mut externals = Vec::new();
+    for rule in grammar.externals.iter().flatten() {
+        let (name, kind) = match rule.symbol_name() {
+            Some(name) => (name.to_string(), VariableType::for_name(name)),
+            None => (String::new(), VariableType::Anonymous),
+        };
+        lowerer.variable_name = "";
+        lowerer.token_count = 0;
+        let ir = match rule.symbol_name() {
+            Some(name) => lowerer
+                .resolve(name)
+                .unwrap_or(Ir::Symbol(Symbol::external(externals.len()))),
+            None => lowerer.lower(rule)?,
+        };
+        externals.push((name, kind, ir));
+    }
+
+    let remap = Remap::new(replace_token_rules(grammar, &rules, &mut lowerer));
+    let mut variables = Vec::new();
+    for (i, rule) in rules.into_iter().enumerate() {
+        if remap.replacements[i].is_none() {
+            let (name, _) = grammar.rules.get_index(i).expect("rule index in range");
+            variables.push((
+                name.clone(),
+                VariableType::for_name(name),
+                remap_ir(rule, &|symbol| remap.apply(symbol)),
+            ));
+        }
+    }
+    let external_tokens = external_tokens(externals, &remap, &lowerer.lexical)?;
+
+    let (extra_symbols, separators) = split_extras(grammar, &lowerer, &remap)?;
+
+    let resolve_name = |name: &String| match lowerer.resolve(name) {
+        Some(Ir::Symbol(symbol)) => Some(remap.apply(symbol)),
+        _ => None,
+    };
+    let supertype_symbols = grammar
+        .supertypes
+        .iter()
+        .flatten()
+        .map(|name| resolve_name(name).ok_or_else(|| undefined_symbol(name)))
+        .collect::<Result<Vec<_>, _>>()?;
+    // Inlining a rule that doesn't exist (or was dropped) is a no-op.
+    let variables_to_inline: Vec<Symbol> = grammar
+        .inline
+        .iter()
+        .flatten()
+        .filter_map(resolve_name)
+        .collect();
+
+    // Inlined rules and supertypes never appear in the tree, whatever their
+    // names.
+    let variables = expand_repeats(grammar, variables)
+        .into_iter()
+        .enumerate()
+        .map(|(i, (name, kind, rule))| SyntaxVariable {
+            name,
+            kind: if variables_to_inline.contains(&Symbol::non_terminal(i))
+                || supertype_symbols.contains(&Symbol::non_terminal(i))
+            {
+                VariableType::Hidden
+            } else {
+                kind
+            },
+            productions: flatten(rule),
+        })
+        .collect();
+
+    Ok(PreparedGrammar {
+        syntax: SyntaxGrammar {
+            variables,
+            extra_symbols,
+            external_tokens,
+            supertype_symbols,
+            variables_to_inline,
+        },
+        lexical: LexicalGrammar {
+            variables: lowerer.lexical,
+            separators,
+        },
+    })
+}
+
+/// Splits the extras into the symbols they name or tokens they match, and
+/// the separators skipped between tokens.
+fn split_extras(
+    grammar: &Grammar,
+    lowerer: &Lowerer,
+    remap: &Remap,
+) -> Result<(Vec<Symbol>, Vec<Rule>), GrammarError> {
+    let mut extra_symbols = Vec::new();
+    let mut separators = Vec::new();
+    for rule in grammar.extras.as_ref().unwrap_or(&default_extras()) {
+        if let Some(name) = rule.symbol_name() {
+            match lowerer.resolve(name) {
+                Some(Ir::Symbol(symbol)) => extra_symbols.push(remap.apply(symbol)),
+                _ => return Err(undefined_symbol(name)),
+            }
+        } else if let Some(index) = lowerer.find_token(rule) {
+            extra_symbols.push(Symbol::terminal(index));
+        } else {
+            // Other extras (typically whitespace patterns) are separators
+            // that never appear in the tree.
+            separators.push(rule.clone());
+        }
+    }
+    Ok((extra_symbols, separators))
+}
+
+/// The extras of a grammar that doesn't list any: whitespace.
+fn default_extras() -> Vec<Rule> {
+    vec![Rule {
+        rule_type: RuleType::Pattern,
+        value: Some(RuleValue::String("\\s".to_string())),
+        name: None,
+        content: None,
+        members: Vec::new(),
+        named: None,
+        flags: None,
+        context_name: None,
+    }]
+}
+
+/// Finds the rules that are nothing but a token used nowhere else, so that
+/// `identifier: /[a-z]+/` yields a named terminal rather than a rule
+/// wrapping an anonymous one. Returns the token replacing each rule.
+fn replace_token_rules(
+    grammar: &Grammar,
+    rules: &[Ir],
+    lowerer: &mut Lowerer<'_>,
+) -> Vec<Option<Symbol>> {
+    let mut replacements = vec![None; rules.len()];
+    for (i, rule) in rules.iter().enumerate().skip(1) {
+        let (name, original) = grammar.rules.get_index(i).expect("rule index in range");
+        let is_token = matches!(
+            original.rule_type,
+            RuleType::String | RuleType::Pattern | RuleType::Token | RuleType::ImmediateToken
+        );
+        if let Ir::Symbol(symbol) = rule {
+            if is_token
+                && symbol.kind == SymbolKind::Terminal
+                && lowerer.usage_counts[symbol.index] == 1
+            {
+                // A hidden rule for a literal string keeps the string visible.
+                let token = &mut lowerer.lexical[symbol.index];
+                let kind = VariableType::for_name(name);
+                if token.kind == VariableType::Auxiliary || kind != VariableType::Hidden {
+                    token.name.clone_from(name);
+                    token.kind = kind;
+                    replacements[i] = Some(*symbol);
+                }
+            }
+        }
+    }
+    replacements
+}
+
+/// Renumbers non-terminals once some rules have been replaced by tokens.
+struct Remap {
+    replacements: Vec<Option<Symbol>>,
+    /// For each rule, how many replaced rules precede it.
+    shifted: Vec<usize>,
+}
+
+impl Remap {
+    fn new(replacements: Vec<Option<Symbol>>) -> Self {
+        let shifted = replacements
+            .iter()
+            .scan(0, |removed, replacement| {
+                let shift = *removed;
+                *removed += usize::from(replacement.is_some());
+                Some(shift)
+            })
+            .collect();
+        Self {
+            replacements,
+            shifted,
+        }
+    }
+
+    fn apply(&self, symbol: Symbol) -> Symbol {
+        match symbol.kind {
+            SymbolKind::NonTerminal => self.replacements[symbol.index].unwrap_or(
+                Symbol::non_terminal(symbol.index - self.shifted[symbol.index]),
+            ),
+            _ => symbol,
+        }
+    }
+}
+
+fn external_tokens(
+    externals: Vec<(String, VariableType, Ir)>,
+    remap: &Remap,
+    lexical: &[LexicalVariable],
+) -> Result<Vec<ExternalToken>, GrammarError> {
+    let mut external_tokens = Vec::new();
+    for (name, kind, rule) in externals {
+        match remap_ir(rule, &|symbol| remap.apply(symbol)) {
+            Ir::Symbol(symbol) => match symbol.kind {
+                SymbolKind::External => external_tokens.push(ExternalToken { name, kind }),
+                SymbolKind::Terminal => external_tokens.push(ExternalToken {
+                    name: lexical[symbol.index].name.clone(),
+                    kind,
+                }),
+                SymbolKind::NonTerminal => {
+                    return Err(GrammarError::Validation(format!(
+                        "rule '{name}' cannot be used as both an external token and a non-terminal rule"
+                    )))
+                }
+            },
+            _ => {
+                return Err(GrammarError::Validation(
+                    "non-symbol rules cannot be used as external tokens".to_string(),
+                ))
+            }
+        }
+    }
+    Ok(external_tokens)
+}
+
+/// Expands repetitions into auxiliary rules appended after the others.
+fn expand_repeats(
+    grammar: &Grammar,
+    variables: Vec<(String, VariableType, Ir)>,
+) -> Vec<(String, VariableType, Ir)> {
+    let mut expander = RepeatExpander {
+        first_auxiliary: variables.len(),
+        auxiliary: Vec::new(),
+        existing: Vec::new(),
+        variable_name: String::new(),
+        repeat_count: 0,
+    };
+    let mut result = Vec::with_capacity(variables.len());
+    for (i, (name, mut kind, rule)) in variables.into_iter().enumerate() {
+        expander.variable_name.clone_from(&name);
+        expander.repeat_count = 0;
+        let symbol = Symbol::non_terminal(i);
+        let rule = match rule {
+            // A hidden rule that is a bare repetition recurses on itself
+            // rather than through another auxiliary rule.
+            Ir::Repeat(content)
+                if kind == VariableType::Hidden
+                    && grammar.rules[&name].rule_type == RuleType::Repeat1 =>
+            {
+                kind = VariableType::Auxiliary;
+                Ir::Choice(vec![
+                    Ir::Seq(vec![Ir::Symbol(symbol), Ir::Symbol(symbol)]),
+                    expander.expand(*content),
+                ])
+            }
+            rule => expander.expand(rule),
+        };
+        result.push((name, kind, rule));
+    }
+    result.extend(
+        expander
+            .auxiliary
+            .into_iter()
+            .map(|(name, rule)| (name, VariableType::Auxiliary, rule)),
+    );
+    result
+}
+
+/// Drops the rules unreachable from the start rule, supertypes, extras,
+/// externals, the word rule and reserved words, such as those left behind by an inheriting grammar. Returns
+/// `None` when every rule is used.
+fn without_unused_rules(grammar: &Grammar) -> Option<Grammar> {
+    let used = used_rules(grammar);
+    if used.len() == grammar.rules.len() {
+        return None;
+    }
+    let mut pruned = grammar.clone();
+    pruned.rules.retain(|name, _| used.contains(name.as_str()));
+    Some(pruned)
+}
+
+/// Names of the rules reachable from the start rule, supertypes, extras,
+/// externals, the word rule and reserved words.
+pub(crate) fn used_rules(grammar: &Grammar) -> HashSet<&str> {
+    fn references<'r>(rule: &'r Rule, names: &mut Vec<&'r str>) {
+        if let Some(name) = rule.symbol_name() {
+            names.push(name);
+        }
+        if let Some(content) = &rule.content {
+            references(content, names);
+        }
+        for member in &rule.members {
+            references(member, names);
+        }
+    }
+
+    let mut pending = Vec::new();
+    pending.extend(grammar.rules.keys().next().map(String::as_str));
+    pending.extend(grammar.supertypes.iter().flatten().map(String::as_str));
+    pending.extend(grammar.word.as_deref());
+    let reserved = grammar
+        .reserved
+        .iter()
+        .flat_map(|sets| sets.values().flatten());
+    for rule in grammar
+        .extras
+        .iter()
+        .chain(&grammar.externals)
+        .flatten()
+        .chain(reserved)
+    {
+        references(rule, &mut pending);
+    }
+    let mut used = HashSet::new();
+    while let Some(name) = pending.pop() {
+        if let Some((name, rule)) = grammar.rules.get_key_value(name) {
+            if used.insert(name.as_str()) {
+                references(rule, &mut pending);
+            }
+        }
+    }
+    used
+}
+
+fn content(rule: &Rule) -> Result<&Rule, GrammarError> {
+    rule.content.as_deref().ok_or_else(|| {
+        GrammarError::Validation(format!("{} rule is missing its content", rule.type_name()))
+    })
+}
+
+fn undefined_symbol(name: &str) -> GrammarError {
+    GrammarError::Validation(format!("undefined symbol '{name}'"))
+}
+
+/// Resolves names and extracts tokens from rules.
+struct Lowerer<'a> {
+    grammar: &'a Grammar,
+    lexical: Vec<LexicalVariable>,
+    usage_counts: Vec<usize>,
+    variable_name: &'a str,
+    token_count: usize,
+}
+
+impl Lowerer<'_> {
+    fn resolve(&self, name: &str) -> Option<Ir> {
+        if let Some(index) = self.grammar.rules.get_index_of(name) {
+            return Some(Ir::Symbol(Symbol::non_terminal(index)));
+        }
+        self.grammar
+            .externals
+            .iter()
+            .flatten()
+            .position(|rule| rule.symbol_name() == Some(name))
+            .map(|index| Ir::Symbol(Symbol::external(index)))
+    }
+
+    fn lower(&mut self, rule: &Rule) -> Result<Ir, GrammarError> {
+        Ok(match rule.rule_type {
+            RuleType::Blank => Ir::Blank,
+            RuleType::String | RuleType::Pattern | RuleType::Token | RuleType::ImmediateToken => {
+                Ir::Symbol(Symbol::terminal(self.extract_token(rule)))
+            }
+            RuleType::Symbol => {
+                let name = rule.name.as_deref().unwrap_or_default();
+                self.resolve(name).ok_or_else(|| undefined_symbol(name))?
+            }
+            RuleType::Choice => Ir::Choice(self.lower_all(&rule.members)?),
+            RuleType::Seq => Ir::Seq(self.lower_all(&rule.members)?),
+            RuleType::Repeat => Ir::Choice(vec![
+                Ir::Repeat(Box::new(self.lower(content(rule)?)?)),
+                Ir::Blank,
+            ]),
+            RuleType::Repeat1 => Ir::Repeat(Box::new(self.lower(content(rule)?)?)),
+            RuleType::Prec | RuleType::PrecLeft | RuleType::PrecRight => {
+                let associativity = match rule.rule_type {
+                    RuleType::PrecLeft => Some(Associativity::Left),
+                    RuleType::PrecRight => Some(Associativity::Right),
+                    _ => None,
+                };
+                let precedence = match &rule.value {
+                    Some(RuleValue::Integer(value)) => Precedence::Integer(*value),
+                    Some(RuleValue::String(name)) => Precedence::Name(name.clone()),
+                    None => Precedence::Integer(0),
+                };
+                Ir::Prec(
+                    precedence,
+                    associativity,
+                    Box::new(self.lower(content(rule)?)?),
+                )
+            }
+            RuleType::PrecDynamic | RuleType::Reserved => self.lower(content(rule)?)?,
+            RuleType::Field => Ir::Field(
+                rule.name.clone().unwrap_or_default(),
+                Box::new(self.lower(content(rule)?)?),
+            ),
+            RuleType::Alias => {
+                let value = match &rule.value {
+                    Some(RuleValue::String(value)) => value.clone(),
+                    _ => String::new(),
+                };
+                let alias = Alias {
+                    value,
+                    named: rule.named.unwrap_or(false),
+                };
+                Ir::Alias(alias, Box::new(self.lower(content(rule)?)?))
+            }
+        })
+    }
+
+    fn lower_all(&mut self, rules: &[Rule]) -> Result<Vec<Ir>, GrammarError> {
+        rules.iter().map(|rule| self.lower(rule)).collect()
+    }
+
+    fn extract_token(&mut self, rule: &Rule) -> usize {
+        let rule = token_key(rule);
+        if let Some(index) = self.find_token(rule) {
+            self.usage_counts[index] += 1;
+            return index;
+        }
+        let (name, kind) = if let Some(value) = token_string(rule) {
+            (value.to_string(), VariableType::Anonymous)
+        } else {
+            self.token_count += 1;
+            (
+                format!("{}_token{}", self.variable_name, self.token_count),
+                VariableType::Auxiliary,
+            )
+        };
+        self.lexical.push(LexicalVariable {
+            name,
+            kind,
+            rule: rule.clone(),
+        });
+        self.usage_counts.push(1);
+        self.lexical.len() - 1
+    }
+
+    fn find_token(&self, rule: &Rule) -> Option<usize> {
+        let rule = token_key(rule);
+        self.lexical.iter().position(|token| token.rule == *rule)
+    }
+}
+
+/// The rule that identifies a token: `token("x")` is the same token as `"x"`.
+fn token_key(rule: &Rule) -> &Rule {
+    match (rule.rule_type, rule.content.as_deref()) {
+        (RuleType::Token, Some(content))
+            if matches!(content.rule_type, RuleType::String | RuleType::Pattern) =>
+        {
+            content
+        }
+        _ => rule,
+    }
+}
+
+/// The literal text of a token that is a plain string, seen through `token`
+/// and precedence wrappers.
+fn token_string(rule: &Rule) -> Option<&str> {
+    let mut rule = rule;
+    if matches!(rule.rule_type, RuleType::Token | RuleType::ImmediateToken) {
+        rule = rule.content.as_deref()?;
+        while matches!(
+            rule.rule_type,
+            RuleType::Prec | RuleType::PrecLeft | RuleType::PrecRight | RuleType::PrecDynamic
+        ) {
+            rule = rule.content.as_deref()?;
+        }
+    }
+    rule.string_value()
+}
+
+fn remap_ir(rule: Ir, remap: &impl Fn(Symbol) -> Symbol) -> Ir {
+    match rule {
+        Ir::Blank => Ir::Blank,
+        Ir::Symbol(symbol) => Ir::Symbol(remap(symbol)),
+        Ir::Choice(members) => {
+            Ir::Choice(members.into_iter().map(|m| remap_ir(m, remap)).collect())
+        }
+        Ir::Seq(members) => Ir::Seq(members.into_iter().map(|m| remap_ir(m, remap)).collect()),
+        Ir::Repeat(content) => Ir::Repeat(Box::new(remap_ir(*content, remap))),
+        Ir::Alias(alias, content) => Ir::Alias(alias, Box::new(remap_ir(*content, remap))),
+        Ir::Field(name, content) => Ir::Field(name, Box::new(remap_ir(*content, remap))),
+        Ir::Prec(value, associativity, content) => {
+            Ir::Prec(value, associativity, Box::new(remap_ir(*content, remap)))
+        }
+    }
+}
+
+/// Replaces repetitions with references to auxiliary recursive rules.
+struct RepeatExpander {
+    first_auxiliary: usize,
+    auxiliary: Vec<(String, Ir)>,
+    /// Already-expanded repetition contents and their auxiliary symbols.
+    existing: Vec<(Ir, Symbol)>,
+    variable_name: String,
+    repeat_count: usize,
+}
+
+impl RepeatExpander {
+    fn expand(&mut self, rule: Ir) -> Ir {
+        match rule {
+            Ir::Blank | Ir::Symbol(_) => rule,
+            Ir::Choice(members) => {
+                Ir::Choice(members.into_iter().map(|m| self.expand(m)).collect())
+            }
+            Ir::Seq(members) => Ir::Seq(members.into_iter().map(|m| self.expand(m)).collect()),
+            Ir::Alias(alias, content) => Ir::Alias(alias, Box::new(self.expand(*content))),
+            Ir::Field(name, content) => Ir::Field(name, Box::new(self.expand(*content))),
+            Ir::Prec(value, associativity, content) => {
+                Ir::Prec(value, associativity, Box::new(self.expand(*content)))
+            }
+            Ir::Repeat(content) => {
+                let content = self.expand(*content);
+                if let Some((_, symbol)) = self.existing.iter().find(|(rule, _)| *rule == content) {
+                    return Ir::Symbol(*symbol);
+                }
+                self.repeat_count += 1;
+                let symbol = Symbol::non_terminal(self.first_auxiliary + self.auxiliary.len());
+                self.existing.push((content.clone(), symbol));
+                self.auxiliary.push((
+                    format!("{}_repeat{}", self.variable_name, self.repeat_count),
+                    Ir::Choice(vec![
+                        Ir::Seq(vec![Ir::Symbol(symbol), Ir::Symbol(symbol)]),
+                        content,
+                    ]),
+                ));
+                Ir::Symbol(symbol)
+            }
+        }
+    }
+}
+
+/// Expands a repetition-free rule into its distinct productions.
+fn flatten(rule: Ir) -> Vec<Production> {
+    let mut productions: Vec<Production> = Vec::new();
+    for choice in extract_choices(rule) {
+        let mut production = Production::default();
+        flatten_into(&choice, &mut production, &Context::default(), true);
+        if let Some(last) = production.steps.last() {
+            production.precedence = match last.precedence {
+                Precedence::Integer(value) => value,
+                _ => 0,
+            };
+            production.associativity = last.associativity;
+        }
+        if !productions.contains(&production) {
+            productions.push(production);
+        }
+    }
+    productions
+}
+
+/// Distributes choices outward, so that each result is choice-free.
+fn extract_choices(rule: Ir) -> Vec<Ir> {
+    match rule {
+        Ir::Choice(members) => members.into_iter().flat_map(extract_choices).collect(),
+        Ir::Seq(members) => {
+            let mut result = vec![Vec::new()];
+            for member in members {
+                let choices = extract_choices(member);
+                result = result
+                    .into_iter()
+                    .flat_map(|prefix: Vec<Ir>| {
+                        choices.iter().map(move |choice| {
+                            let mut seq = prefix.clone();
+                            seq.push(choice.clone());
+                            seq
+                        })
+                    })
+                    .collect();
+            }
+            result.into_iter().map(Ir::Seq).collect()
+        }
+        Ir::Alias(alias, content) => extract_choices(*content)
+            .into_iter()
+            .map(|rule| Ir::Alias(alias.clone(), Box::new(rule)))
+            .collect(),
+        Ir::Field(name, content) => extract_choices(*content)
+            .into_iter()
+            .map(|rule| Ir::Field(name.clone(), Box::new(rule)))
+            .collect(),
+        Ir::Prec(value, associativity, content) => extract_choices(*content)
+            .into_iter()
+            .map(|rule| Ir::Prec(value.clone(), associativity, Box::new(rule)))
+            .collect(),
+        Ir::Blank | Ir::Symbol(_) | Ir::Repeat(_) => vec![rule],
+    }
+}
+
+/// The aliases, fields and precedence wrapping a step being flattened.
+#[derive(Clone, Copy, Default)]
+struct Context<'a> {
+    alias: Option<&'a Alias>,
+    field: Option<&'a str>,
+    precedence: Option<&'a Precedence>,
+    associativity: Option<Associativity>,
+}
+
+/// Appends the steps of `rule` to `production`. `at_end` says whether
+/// nothing follows `rule` in the production.
+fn flatten_into(rule: &Ir, production: &mut Production, outer: &Context<'_>, at_end: bool) {
+    match rule {
+        Ir::Blank | Ir::Choice(_) | Ir::Repeat(_) => {}
+        Ir::Symbol(symbol) => {
+            production.steps.push(ProductionStep {
+                symbol: *symbol,
+                alias: outer.alias.cloned(),
+                field_name: outer.field.map(str::to_string),
+                precedence: outer.precedence.cloned().unwrap_or_default(),
+                associativity: outer.associativity,
+            });
+        }
+        Ir::Seq(members) => {
+            for (i, member) in members.iter().enumerate() {
+                flatten_into(member, production, outer, at_end && i + 1 == members.len());
+            }
+        }
+        Ir::Alias(alias, content) => {
+            let inner = Context {
+                alias: Some(alias),
+                ..*outer
+            };
+            flatten_into(content, production, &inner, at_end);
+        }
+        Ir::Field(name, content) => {
+            let inner = Context {
+                field: Some(name),
+                ..*outer
+            };
+            flatten_into(content, production, &inner, at_end);
+        }
+        Ir::Prec(precedence, associativity, content) => {
+            let inner = Context {
+                precedence: Some(precedence),
+                associativity: associativity.or(outer.associativity),
+                ..*outer
+            };
+            let count = production.steps.len();
+            flatten_into(content, production, &inner, at_end);
+            // Shifting what follows doesn't happen at this precedence.
+            if !at_end && production.steps.len() > count {
+                let last = production.steps.last_mut().expect("a step was pushed");
+                last.precedence = outer.precedence.cloned().unwrap_or_default();
+                if associativity.is_some() {
+                    last.associativity = outer.associativity;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_grammar;
+
+    fn prepare_json(json: &str) -> PreparedGrammar {
+        prepare(&parse_grammar(json).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_tokens_are_extracted_and_named() {
+        let prepared = prepare_json(
+            r#"{
+                "name": "t",
+                "rules": {
+                    "list": {"type": "SEQ", "members": [
+                        {"type": "STRING", "value": "["},
+                        {"type": "REPEAT", "content": {"type": "SYMBOL", "name": "word"}},
+                        {"type": "TOKEN", "content": {"type": "STRING", "value": "]"}}
+                    ]},
+                    "word": {"type": "PATTERN", "value": "[a-z]+"}
+                }
+            }"#,
+        );
+        let names: Vec<_> = prepared
+            .lexical
+            .variables
+            .iter()
+            .map(|v| (v.name.as_str(), v.kind))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("[", VariableType::Anonymous),
+                ("]", VariableType::Anonymous),
+                ("word", VariableType::Named),
+            ]
+        );
+        // `word` became a token, leaving `list` and its repetition.
+        let variables = &prepared.syntax.variables;
+        assert_eq!(variables.len(), 2);
+        assert_eq!(variables[1].name, "list_repeat1");
+        assert_eq!(variables[0].productions.len(), 2);
+        assert_eq!(variables[1].productions.len(), 2);
+    }
+
+    #[test]
+    fn test_productions_record_precedence_of_last_step() {
+        let prepared = prepare_json(
+            r#"{
+                "name": "t",
+                "rules": {
+                    "expr": {"type": "CHOICE", "members": [
+                        {"type": "PREC_RIGHT", "value": 2, "content": {"type": "SEQ", "members": [
+                            {"type": "SYMBOL", "name": "expr"},
+                            {"type": "STRING", "value": "^"},
+                            {"type": "SYMBOL", "name": "expr"}
+                        ]}},
+                        {"type": "SEQ", "members": [
+                            {"type": "STRING", "value": "-"},
+                            {"type": "PREC", "value": 3, "content": {"type": "SYMBOL", "name": "expr"}}
+                        ]},
+                        {"type": "STRING", "value": "x"}
+                    ]}
+                }
+            }"#,
+        );
+        let productions: Vec<_> = prepared.syntax.variables[0]
+            .productions
+            .iter()
+            .map(|p| (p.steps.len(), p.precedence, p.associativity))
+            .collect();
+        assert_eq!(
+            productions,
+            [
+                (3, 2, Some(Associativity::Right)),
+                (2, 3, None),
+                (1, 0, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fields_and_aliases_reach_steps() {
+        let prepared = prepare_json(
+            r#"{
+                "name": "t",
+                "rules": {
+                    "call": {"type": "SEQ", "members": [
+                        {"type": "FIELD", "name": "function", "content":
+                            {"type": "ALIAS", "named": true, "value": "callee", "content":
+                                {"type": "SYMBOL", "name": "_name"}}},
+                        {"type": "CHOICE", "members": [
+                            {"type": "STRING", "value": "()"},
+                            {"type": "BLANK"}
+                        ]}
+                    ]},
+                    "_name": {"type": "PATTERN", "value": "[a-z]+"}
+                }
+            }"#,
+        );
+        let productions = &prepared.syntax.variables[0].productions;
+        assert_eq!(productions.len(), 2);
+        let step = &productions[0].steps[0];
+        assert_eq!(step.field_name.as_deref(), Some("function"));
+        assert_eq!(
+            step.alias.as_ref().map(|a| a.value.as_str()),
+            Some("callee")
+        );
+        assert_eq!(
+            prepared.symbol_info(step.symbol),
+            ("_name", VariableType::Hidden)
+        );
+    }
+
+    #[test]
+    fn test_undefined_symbol() {
+        let grammar =
+            parse_grammar(r#"{"name": "t", "rules": {"a": {"type": "SYMBOL", "name": "b"}}}"#)
+                .unwrap();
+        assert!(matches!(
+            prepare(&grammar),
+            Err(GrammarError::Validation(_))
+        ));
+    }
+}
@@ -3,6 +3,8 @@
 //! This module contains the types used to model grammar rules and their
 //! structure according to the Tree-sitter JSON schema.
 
+use super::{missing_field, shape_error, GrammarError};
+use crate::json::Value;
 use facet::Facet;
 
 /// Represents a grammar rule in the Tree-sitter format.
@@ -14,7 +16,7 @@ use facet::Facet;
 /// A `Rule` can be atomic (like a literal or regex) or composite
 /// (like a sequence, choice, or precedence group). Together, they
 /// form a self-describing syntax graph.
-#[derive(Debug, Clone, Facet)]
+#[derive(Debug, Clone, PartialEq, Eq, Facet)]
 pub struct Rule {
     /// The discriminant identifying what kind of rule this is.
     #[facet(rename = "type")]
@@ -53,7 +55,7 @@ pub struct Rule {
 ///
 /// `RuleValue` abstracts small scalar payloads that alter how a rule behaves,
 /// such as precedence numbers or literal match text.
-#[derive(Debug, Clone, Facet)]
+#[derive(Debug, Clone, PartialEq, Eq, Facet)]
 #[repr(u8)]
 pub enum RuleValue {
     /// A string literal value (e.g. `"+"`, `"if"`).
@@ -68,7 +70,7 @@ pub enum RuleValue {
 /// Each variant corresponds to one of the `type` strings found in the JSON
 /// grammar format. Each variant captures a syntactic combinator, a primitive operation that
 /// are composed to define language structure, the atoms of a grammar.
-#[derive(Debug, Clone, Facet)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Facet)]
 #[repr(u8)]
 pub enum RuleType {
     /// An empty (ε) production.
@@ -124,7 +126,181 @@ pub enum RuleType {
     Reserved,
 }
 
+impl RuleType {
+    /// Looks up a rule type by its JSON `type` string (e.g. `"PREC_LEFT"`).
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "BLANK" => RuleType::Blank,
+            "STRING" => RuleType::String,
+            "PATTERN" => RuleType::Pattern,
+            "SYMBOL" => RuleType::Symbol,
+            "CHOICE" => RuleType::Choice,
+            "SEQ" => RuleType::Seq,
+            "REPEAT" => RuleType::Repeat,
+            "REPEAT1" => RuleType::Repeat1,
+            "PREC" => RuleType::Prec,
+            "PREC_LEFT" => RuleType::PrecLeft,
+            "PREC_RIGHT" => RuleType::PrecRight,
+            "PREC_DYNAMIC" => RuleType::PrecDynamic,
+            "FIELD" => RuleType::Field,
+            "ALIAS" => RuleType::Alias,
+            "TOKEN" => RuleType::Token,
+            "IMMEDIATE_TOKEN" => RuleType::ImmediateToken,
+            "RESERVED" => RuleType::Reserved,
+            _ => return None,
+        })
+    }
+}
+
 impl Rule {
+    /// Decodes a rule from its JSON object form. `path` locates the rule
+    /// within the grammar document for error messages.
+    pub(crate) fn from_json(value: &Value, path: &str) -> Result<Self, GrammarError> {
+        if value.as_object().is_none() {
+            return Err(shape_error(path, "a rule object", value));
+        }
+
+        let type_value = value
+            .get("type")
+            .ok_or_else(|| missing_field(path, "type"))?;
+        let type_name = type_value
+            .as_str()
+            .ok_or_else(|| shape_error(&format!("{path}.type"), "a string", type_value))?;
+        let rule_type = RuleType::from_name(type_name).ok_or_else(|| {
+            GrammarError::JsonParse(format!("{path}: unknown rule type '{type_name}'"))
+        })?;
+
+        let value_field = match value.get("value") {
+            None | Some(Value::Null) => None,
+            Some(Value::String(s)) => Some(RuleValue::String(s.clone())),
+            Some(v @ Value::Integer(i)) => {
+                Some(RuleValue::Integer(i32::try_from(*i).map_err(|_| {
+                    shape_error(&format!("{path}.value"), "a 32-bit integer", v)
+                })?))
+            }
+            Some(v) => {
+                return Err(shape_error(
+                    &format!("{path}.value"),
+                    "a string or integer",
+                    v,
+                ))
+            }
+        };
+
+        let string_field = |key: &str| -> Result<Option<String>, GrammarError> {
+            match value.get(key) {
+                None | Some(Value::Null) => Ok(None),
+                Some(Value::String(s)) => Ok(Some(s.clone())),
+                Some(v) => Err(shape_error(&format!("{path}.{key}"), "a string", v)),
+            }
+        };
+
+        let content = match value.get("content") {
+            None | Some(Value::Null) => None,
+            Some(v) => Some(Box::new(Rule::from_json(v, &format!("{path}.content"))?)),
+        };
+
+        let members = match value.get("members") {
+            None | Some(Value::Null) => Vec::new(),
+            Some(v) => v
+                .as_array()
+                .ok_or_else(|| shape_error(&format!("{path}.members"), "an array", v))?
+                .iter()
+                .enumerate()
+                .map(|(i, m)| Rule::from_json(m, &format!("{path}.members[{i}]")))
+                .collect::<Result<_, _>>()?,
+        };
+
+        let named = match value.get("named") {
+            None | Some(Value::Null) => None,
+            Some(v) => Some(
+                v.as_bool()
+                    .ok_or_else(|| shape_error(&format!("{path}.named"), "a boolean", v))?,
+            ),
+        };
+
+        Ok(Rule {
+            rule_type,
+            value: value_field,
+            name: string_field("name")?,
+            content,
+            members,
+            named,
+            flags: string_field("flags")?,
+            context_name: string_field("context_name")?,
+        })
+    }
+
+    /// Encodes a rule in its JSON object form, with keys in the order
+    /// tree-sitter writes them.
+    pub(crate) fn to_json(&self) -> Value {
+        let mut entries = vec![("type".to_string(), Value::from(self.type_name()))];
+        let value = self.value.as_ref().map(|value| match value {
+            RuleValue::String(s) => Value::from(s.as_str()),
+            RuleValue::Integer(i) => Value::Integer(i64::from(*i)),
+        });
+        let content = self.content.as_ref().map(|content| content.to_json());
+        let named = self.named.map(Value::from);
+        if self.rule_type == RuleType::Alias {
+            entries.extend(content.map(|v| ("content".to_string(), v)));
+            entries.extend(named.map(|v| ("named".to_string(), v)));
+            entries.extend(value.map(|v| ("value".to_string(), v)));
+            return Value::Object(entries);
+        }
+        entries.extend(value.map(|v| ("value".to_string(), v)));
+        entries.extend(
+            self.name
+                .as_deref()
+                .map(|v| ("name".to_string(), Value::from(v))),
+        );
+        entries.extend(content.map(|v| ("content".to_string(), v)));
+        // tree-sitter's `reserved` puts the word set's name after the rule.
+        entries.extend(
+            self.context_name
+                .as_deref()
+                .map(|v| ("context_name".to_string(), Value::from(v))),
+        );
+        if matches!(self.rule_type, RuleType::Seq | RuleType::Choice) {
+            entries.push((
+                "members".to_string(),
+                Value::Array(self.members.iter().map(Rule::to_json).collect()),
+            ));
+        }
+        entries.extend(named.map(|v| ("named".to_string(), v)));
+        entries.extend(
+            self.flags
+                .as_deref()
+                .map(|v| ("flags".to_string(), Value::from(v))),
+        );
+        Value::Object(entries)
+    }
+
+    /// Rewrites the rule into its canonical shape: members of a `SEQ` that
+    /// are themselves `SEQ`s are spliced into it, likewise for `CHOICE`, and
+    /// a `SEQ` or `CHOICE` of a single member is replaced by that member.
+    /// The rule matches the same text and builds the same trees as before.
+    pub fn canonicalize(&mut self) {
+        if let Some(content) = &mut self.content {
+            content.canonicalize();
+        }
+        if !matches!(self.rule_type, RuleType::Seq | RuleType::Choice) {
+            return;
+        }
+        let members = std::mem::take(&mut self.members);
+        for mut member in members {
+            member.canonicalize();
+            if member.rule_type == self.rule_type {
+                self.members.append(&mut member.members);
+            } else {
+                self.members.push(member);
+            }
+        }
+        if self.members.len() == 1 {
+            *self = self.members.remove(0);
+        }
+    }
+
     /// Returns the canonical string name of this rule type.
     #[must_use]
     pub fn type_name(&self) -> &'static str {
@@ -238,12 +414,21 @@ mod tests {
             }
         }"#;
 
-        let rules: Vec<Rule> = facet_json::from_str(&json).unwrap_or_else(|e| {
-            eprintln!("JSON parse error:\n{}", e);
-            std::process::exit(1);
-        });
+        let value = crate::json::parse(json).unwrap();
+        let rules: Vec<Rule> = value
+            .as_object()
+            .unwrap()
+            .iter()
+            .map(|(name, rule)| Rule::from_json(rule, name))
+            .collect::<Result<_, _>>()
+            .unwrap_or_else(|e| {
+                eprintln!("JSON parse error:\n{e}");
+                std::process::exit(1);
+            });
 
         assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].symbol_name(), Some("expression"));
+        assert_eq!(rules[1].members[1].pattern_value(), Some("[0-9]+"));
     }
 
     #[test]
@@ -261,11 +446,44 @@ mod tests {
             }
         }"#;
 
-        let rule: Rule = facet_json::from_str(&json).unwrap_or_else(|e| {
-            eprintln!("JSON parse error:\n{}", e);
+        let value = crate::json::parse(json).unwrap();
+        let rule = Rule::from_json(&value, "rule").unwrap_or_else(|e| {
+            eprintln!("JSON parse error:\n{e}");
             std::process::exit(1);
         });
         assert_eq!(rule.precedence(), Some(1));
         assert!(matches!(rule.rule_type, RuleType::PrecLeft));
     }
+
+    #[test]
+    fn test_canonicalize() {
+        let json = r#"{
+            "type": "REPEAT",
+            "content": {
+                "type": "SEQ",
+                "members": [
+                    {"type": "SEQ", "members": [
+                        {"type": "STRING", "value": "("},
+                        {"type": "CHOICE", "members": [{"type": "SYMBOL", "name": "expr"}]}
+                    ]},
+                    {"type": "CHOICE", "members": [
+                        {"type": "CHOICE", "members": [
+                            {"type": "STRING", "value": ")"},
+                            {"type": "BLANK"}
+                        ]},
+                        {"type": "STRING", "value": "]"}
+                    ]}
+                ]
+            }
+        }"#;
+
+        let mut rule = Rule::from_json(&crate::json::parse(json).unwrap(), "rule").unwrap();
+        rule.canonicalize();
+        let seq = rule.content.as_deref().unwrap();
+        assert_eq!(seq.rule_type, RuleType::Seq);
+        assert_eq!(seq.members.len(), 3);
+        assert_eq!(seq.members[1].symbol_name(), Some("expr"));
+        assert_eq!(seq.members[2].rule_type, RuleType::Choice);
+        assert_eq!(seq.members[2].members.len(), 3);
+    }
 }
@@ -0,0 +1,448 @@
+//! The graph of which rules refer to which.
+//!
+//! A [`RuleGraph`](crate::graph::RuleGraph) has a node per rule and an edge
+//! from each rule to every rule it refers to. Large grammars are easier to
+//! read a piece at a time, so a graph can be narrowed to the neighbourhood
+//! of one rule and have its hidden rules collapsed into the rules that use
+//! them before it is rendered as Graphviz DOT or a Mermaid flowchart.
+//! Rendering can colour each strongly connected component, the groups of
+//! rules that are mutually recursive, so the recursive core of a grammar
+//! stands out.
+
+use crate::grammar::Grammar;
+use crate::validate::collect_referenced_symbols;
+use indexmap::IndexSet;
+use std::collections::VecDeque;
+use std::fmt::Write;
+
+/// Fill colours for strongly connected components, from the Pastel1
+/// scheme of Color Brewer, reused in turn when there are more components.
+const PALETTE: [&str; 9] = [
+    "#fbb4ae", "#b3cde3", "#ccebc5", "#decbe4", "#fed9a6", "#ffffcc", "#e5d8bd", "#fddaec",
+    "#f2f2f2",
+];
+
+/// The rules of a grammar and the references between them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleGraph {
+    name: String,
+    /// The rule names, in declaration order.
+    rules: IndexSet<String>,
+    /// Edges as indices into `rules`, from a rule to the rules it refers
+    /// to, each once and in order of first reference.
+    edges: Vec<(usize, usize)>,
+}
+
+impl RuleGraph {
+    /// Builds the reference graph of `grammar`. References to names that
+    /// are not rules, such as external tokens, are left out.
+    #[must_use]
+    pub fn new(grammar: &Grammar) -> Self {
+        let rules: IndexSet<String> = grammar.rules.keys().cloned().collect();
+        let mut edges = IndexSet::new();
+        for (from, rule) in grammar.rules.values().enumerate() {
+            let mut symbols = Vec::new();
+            collect_referenced_symbols(rule, &mut symbols);
+            for symbol in symbols {
+                if let Some(to) = rules.get_index_of(&symbol) {
+                    edges.insert((from, to));
+                }
+            }
+        }
+        Self {
+            name: grammar.name.clone(),
+            rules,
+            edges: edges.into_iter().collect(),
+        }
+    }
+
+    /// The rule names, in declaration order.
+    pub fn rules(&self) -> impl Iterator<Item = &str> {
+        self.rules.iter().map(String::as_str)
+    }
+
+    /// The references, as pairs of the referring and referred-to rules.
+    pub fn edges(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.edges
+            .iter()
+            .map(|&(from, to)| (self.rules[from].as_str(), self.rules[to].as_str()))
+    }
+
+    /// The graph of the rules within `depth` references of `rule`, in
+    /// either direction, with the references between them. Returns `None`
+    /// if there is no such rule.
+    #[must_use]
+    pub fn focus(&self, rule: &str, depth: usize) -> Option<Self> {
+        let start = self.rules.get_index_of(rule)?;
+        let mut distance = vec![None; self.rules.len()];
+        distance[start] = Some(0);
+        let mut queue = VecDeque::from([start]);
+        while let Some(node) = queue.pop_front() {
+            let next = distance[node].unwrap_or_default() + 1;
+            if next > depth {
+                continue;
+            }
+            for &(from, to) in &self.edges {
+                let neighbour = match (from == node, to == node) {
+                    (true, _) => to,
+                    (_, true) => from,
+                    _ => continue,
+                };
+                if distance[neighbour].is_none() {
+                    distance[neighbour] = Some(next);
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+        Some(self.subgraph(|node| distance[node].is_some()))
+    }
+
+    /// The graph with hidden rules, those whose names start with `_`,
+    /// removed, and each visible rule linked to the visible rules it
+    /// reaches through hidden ones.
+    #[must_use]
+    pub fn collapse_hidden(&self) -> Self {
+        let hidden = |node: usize| self.rules[node].starts_with('_');
+        let mut edges = IndexSet::new();
+        for from in (0..self.rules.len()).filter(|&node| !hidden(node)) {
+            let mut seen = vec![false; self.rules.len()];
+            let mut stack = vec![from];
+            while let Some(node) = stack.pop() {
+                for &(_, to) in self.edges.iter().filter(|&&(source, _)| source == node) {
+                    if !hidden(to) {
+                        edges.insert((from, to));
+                    } else if !seen[to] {
+                        seen[to] = true;
+                        stack.push(to);
+                    }
+                }
+            }
+        }
+        let collapsed = Self {
+            edges: edges.into_iter().collect(),
+            ..self.clone()
+        };
+        collapsed.subgraph(|node| !hidden(node))
+    }
+
+    /// The graph of the rules `keep` accepts, with the edges between them.
+    fn subgraph(&self, keep: impl Fn(usize) -> bool) -> Self {
+        let mut index = vec![None; self.rules.len()];
+        let mut rules = IndexSet::new();
+        for (node, name) in self.rules.iter().enumerate() {
+            if keep(node) {
+                index[node] = Some(rules.len());
+                rules.insert(name.clone());
+            }
+        }
+        let edges = self
+            .edges
+            .iter()
+            .filter_map(|&(from, to)| Some((index[from]?, index[to]?)))
+            .collect();
+        Self {
+            name: self.name.clone(),
+            rules,
+            edges,
+        }
+    }
+
+    /// The strongly connected components with more than one rule, or with
+    /// a rule that refers to itself, each in declaration order. Components
+    /// are ordered by their first rule.
+    #[must_use]
+    pub fn recursive_components(&self) -> Vec<Vec<&str>> {
+        let mut components: Vec<Vec<usize>> = self
+            .components()
+            .into_iter()
+            .filter(|component| {
+                component.len() > 1 || self.edges.contains(&(component[0], component[0]))
+            })
+            .collect();
+        for component in &mut components {
+            component.sort_unstable();
+        }
+        components.sort_unstable();
+        components
+            .into_iter()
+            .map(|component| {
+                component
+                    .into_iter()
+                    .map(|node| self.rules[node].as_str())
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Tarjan's strongly connected components.
+    fn components(&self) -> Vec<Vec<usize>> {
+        struct Tarjan<'a> {
+            successors: &'a [Vec<usize>],
+            index: Vec<Option<usize>>,
+            low: Vec<usize>,
+            on_stack: Vec<bool>,
+            stack: Vec<usize>,
+            next: usize,
+            components: Vec<Vec<usize>>,
+        }
+
+        impl Tarjan<'_> {
+            fn visit(&mut self, node: usize) {
+                self.index[node] = Some(self.next);
+                self.low[node] = self.next;
+                self.next += 1;
+                self.stack.push(node);
+                self.on_stack[node] = true;
+                for &to in &self.successors[node] {
+                    match self.index[to] {
+                        None => {
+                            self.visit(to);
+                            self.low[node] = self.low[node].min(self.low[to]);
+                        }
+                        Some(index) if self.on_stack[to] => {
+                            self.low[node] = self.low[node].min(index);
+                        }
+                        Some(_) => {}
+                    }
+                }
+                if Some(self.low[node]) == self.index[node] {
+                    let mut component = Vec::new();
+                    while let Some(member) = self.stack.pop() {
+                        self.on_stack[member] = false;
+                        component.push(member);
+                        if member == node {
+                            break;
+                        }
+                    }
+                    self.components.push(component);
+                }
+            }
+        }
+
+        let mut successors = vec![Vec::new(); self.rules.len()];
+        for &(from, to) in &self.edges {
+            successors[from].push(to);
+        }
+        let mut tarjan = Tarjan {
+            successors: &successors,
+            index: vec![None; self.rules.len()],
+            low: vec![0; self.rules.len()],
+            on_stack: vec![false; self.rules.len()],
+            stack: Vec::new(),
+            next: 0,
+            components: Vec::new(),
+        };
+        for node in 0..self.rules.len() {
+            if tarjan.index[node].is_none() {
+                tarjan.visit(node);
+            }
+        }
+        tarjan.components
+    }
+
+    /// The fill colour of each rule: its recursive component's, or none.
+    fn colours(&self) -> Vec<Option<&'static str>> {
+        let mut colours = vec![None; self.rules.len()];
+        for (i, component) in self.recursive_components().iter().enumerate() {
+            for rule in component {
+                if let Some(node) = self.rules.get_index_of(*rule) {
+                    colours[node] = Some(PALETTE[i % PALETTE.len()]);
+                }
+            }
+        }
+        colours
+    }
+
+    /// Renders the graph as a Graphviz `digraph`. Hidden rules are drawn
+    /// dashed, and with `colour` the rules of each recursive component are
+    /// filled with a colour of their own.
+    #[must_use]
+    pub fn to_dot(&self, colour: bool) -> String {
+        let colours = if colour {
+            self.colours()
+        } else {
+            vec![None; self.rules.len()]
+        };
+        let mut out = format!("digraph {} {{\n", dot_id(&self.name));
+        out.push_str("  rankdir=LR;\n  node [shape=box];\n");
+        for (rule, fill) in self.rules.iter().zip(&colours) {
+            let mut attributes = Vec::new();
+            match (rule.starts_with('_'), fill) {
+                (true, Some(_)) => attributes.push("style=\"dashed,filled\"".to_string()),
+                (true, None) => attributes.push("style=dashed".to_string()),
+                (false, Some(_)) => attributes.push("style=filled".to_string()),
+                (false, None) => {}
+            }
+            if let Some(fill) = fill {
+                attributes.push(format!("fillcolor=\"{fill}\""));
+            }
+            if attributes.is_empty() {
+                let _ = writeln!(out, "  {};", dot_id(rule));
+            } else {
+                let _ = writeln!(out, "  {} [{}];", dot_id(rule), attributes.join(", "));
+            }
+        }
+        for (from, to) in self.edges() {
+            let _ = writeln!(out, "  {} -> {};", dot_id(from), dot_id(to));
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// Renders the graph as a Mermaid flowchart, with rules as nodes
+    /// `n0`, `n1` and so on, labelled with their names. Hidden rules are
+    /// drawn with rounded corners, and with `colour` each recursive
+    /// component gets a class of its own, `scc0`, `scc1` and so on, that
+    /// fills it with a colour.
+    #[must_use]
+    pub fn to_mermaid(&self, colour: bool) -> String {
+        let mut out = String::from("flowchart LR\n");
+        for (node, rule) in self.rules.iter().enumerate() {
+            let label = rule.replace('"', "#quot;");
+            if rule.starts_with('_') {
+                let _ = writeln!(out, "  n{node}(\"{label}\")");
+            } else {
+                let _ = writeln!(out, "  n{node}[\"{label}\"]");
+            }
+        }
+        for &(from, to) in &self.edges {
+            let _ = writeln!(out, "  n{from} --> n{to}");
+        }
+        if colour {
+            for (i, component) in self.recursive_components().iter().enumerate() {
+                let nodes: Vec<String> = component
+                    .iter()
+                    .filter_map(|rule| self.rules.get_index_of(*rule))
+                    .map(|node| format!("n{node}"))
+                    .collect();
+                let _ = writeln!(out, "  classDef scc{i} fill:{}", PALETTE[i % PALETTE.len()]);
+                let _ = writeln!(out, "  class {} scc{i}", nodes.join(","));
+            }
+        }
+        out
+    }
+}
+
+/// A DOT identifier: the name itself if it is a plain identifier,
+/// otherwise quoted.
+fn dot_id(name: &str) -> String {
+    let plain = name.chars().next().is_some_and(|c| !c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if plain {
+        name.to_string()
+    } else {
+        format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_grammar;
+
+    /// Expressions and statements that refer to each other through hidden
+    /// rules, with a `comment` nothing refers to.
+    const GRAMMAR: &str = r##"{
+        "name": "nested",
+        "rules": {
+            "program": {"type": "REPEAT", "content": {"type": "SYMBOL", "name": "_statement"}},
+            "_statement": {"type": "CHOICE", "members": [
+                {"type": "SYMBOL", "name": "block"},
+                {"type": "SYMBOL", "name": "expression"}
+            ]},
+            "block": {"type": "SEQ", "members": [
+                {"type": "STRING", "value": "{"},
+                {"type": "REPEAT", "content": {"type": "SYMBOL", "name": "_statement"}},
+                {"type": "STRING", "value": "}"}
+            ]},
+            "expression": {"type": "CHOICE", "members": [
+                {"type": "SYMBOL", "name": "number"},
+                {"type": "PREC_DYNAMIC", "value": 1, "content": {"type": "SEQ", "members": [
+                    {"type": "SYMBOL", "name": "expression"},
+                    {"type": "STRING", "value": "+"},
+                    {"type": "SYMBOL", "name": "number"}
+                ]}}
+            ]},
+            "number": {"type": "PATTERN", "value": "\\d+"},
+            "comment": {"type": "PATTERN", "value": "#.*"}
+        }
+    }"##;
+
+    fn graph() -> RuleGraph {
+        RuleGraph::new(&parse_grammar(GRAMMAR).unwrap())
+    }
+
+    #[test]
+    fn test_edges_follow_references() {
+        assert_eq!(
+            graph().edges().collect::<Vec<_>>(),
+            [
+                ("program", "_statement"),
+                ("_statement", "block"),
+                ("_statement", "expression"),
+                ("block", "_statement"),
+                ("expression", "number"),
+                ("expression", "expression"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_focus_and_collapse() {
+        let focused = graph().focus("block", 1).unwrap();
+        assert_eq!(focused.rules().collect::<Vec<_>>(), ["_statement", "block"]);
+        assert_eq!(
+            graph()
+                .focus("block", 2)
+                .unwrap()
+                .rules()
+                .collect::<Vec<_>>(),
+            ["program", "_statement", "block", "expression"]
+        );
+        assert!(graph().focus("missing", 1).is_none());
+
+        let collapsed = graph().collapse_hidden();
+        assert_eq!(
+            collapsed.edges().collect::<Vec<_>>(),
+            [
+                ("program", "block"),
+                ("program", "expression"),
+                ("block", "block"),
+                ("block", "expression"),
+                ("expression", "number"),
+                ("expression", "expression"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_recursive_components() {
+        assert_eq!(
+            graph().recursive_components(),
+            [vec!["_statement", "block"], vec!["expression"]]
+        );
+    }
+
+    #[test]
+    fn test_rendering() {
+        let dot = graph().to_dot(true);
+        assert!(
+            dot.starts_with("digraph nested {\n  rankdir=LR;\n  node [shape=box];\n  program;\n")
+        );
+        assert!(dot.contains(
+            "  _statement [style=\"dashed,filled\", fillcolor=\"#fbb4ae\"];\n  \
+             block [style=filled, fillcolor=\"#fbb4ae\"];\n"
+        ));
+        assert!(dot.contains("  expression [style=filled, fillcolor=\"#b3cde3\"];\n"));
+        assert!(dot.ends_with("  expression -> expression;\n}\n"));
+        assert!(!graph().to_dot(false).contains("fillcolor"));
+
+        let mermaid = graph().to_mermaid(true);
+        assert!(mermaid.starts_with("flowchart LR\n  n0[\"program\"]\n  n1(\"_statement\")\n"));
+        assert!(mermaid.contains("  n0 --> n1\n"));
+        assert!(mermaid.ends_with(
+            "  classDef scc0 fill:#fbb4ae\n  class n1,n2 scc0\n  \
+             classDef scc1 fill:#b3cde3\n  class n3 scc1\n"
+        ));
+    }
+}
@@ -0,0 +1,342 @@
+//! Syntax highlighting with tree-sitter's highlight queries.
+//!
+//! This follows `tree-sitter-highlight`. A
+//! [`HighlightConfiguration`](crate::highlight::HighlightConfiguration)
+//! holds a language's `highlights.scm`, and optionally its `locals.scm` and
+//! `injections.scm`; [`highlight`](crate::highlight::highlight()) parses a
+//! document and turns the captures into a stream of
+//! [`HighlightEvent`](crate::highlight::HighlightEvent)s: runs of source
+//! text, nested between the start and end of each highlight.
+//!
+//! - Each node takes its highlight from the first pattern that captures
+//!   it, so more specific patterns go first in the query file.
+//! - With a locals query, `@local.scope`, `@local.definition` and
+//!   `@local.reference` captures track variables: a reference to a
+//!   definition in an enclosing scope takes the definition's highlight,
+//!   and patterns marked `(#is-not? local)` apply to neither. A scope with
+//!   `(#set! local.scope-inherits false)` hides the definitions around it.
+//! - With an injections query, the text of each `@injection.content` node
+//!   is highlighted as the language named by an `@injection.language`
+//!   capture or an `injection.language` property, which the caller maps to
+//!   a configuration. Injections are found as described in
+//!   [`injection`](crate::injection), `injection.combined` included.
+//!
+//! [`render`](crate::highlight::render) turns events into HTML or
+//! ANSI-coloured text.
+
+pub mod render;
+
+use crate::injection::{injections, MAX_INJECTION_DEPTH};
+use crate::language::Language;
+use crate::lexer::WHOLE_DOCUMENT;
+use crate::locals;
+use crate::parser::Parser;
+use crate::query::{Query, QueryCursor, QueryError};
+use crate::tree::{self, Node};
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// A highlight, by index into the names given to
+/// [`HighlightConfiguration::configure`], or into
+/// [`HighlightConfiguration::names`] if it was never called.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Highlight(pub usize);
+
+/// One step of a highlighted document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HighlightEvent {
+    /// A run of source text, by byte offsets.
+    Source {
+        /// Where the run starts.
+        start: usize,
+        /// Where the run ends.
+        end: usize,
+    },
+    /// The start of a highlight, which lasts until the matching
+    /// [`HighlightEnd`](HighlightEvent::HighlightEnd).
+    HighlightStart(Highlight),
+    /// The end of the innermost highlight.
+    HighlightEnd,
+}
+
+/// A language's queries, ready to highlight with.
+#[derive(Debug, Clone)]
+pub struct HighlightConfiguration<'l> {
+    /// The language's name, as injections refer to it.
+    pub language_name: String,
+    language: &'l Language,
+    highlights: Query,
+    locals: Option<Query>,
+    injections: Option<Query>,
+    highlight_indices: Vec<Option<Highlight>>,
+}
+
+impl<'l> HighlightConfiguration<'l> {
+    /// Compiles the queries for `language`. An empty `injection_query` or
+    /// `locals_query` is left out.
+    ///
+    /// # Errors
+    ///
+    /// Returns the [`QueryError`] of the first query that fails to compile.
+    pub fn new(
+        language: &'l Language,
+        name: impl Into<String>,
+        highlights_query: &str,
+        injection_query: &str,
+        locals_query: &str,
+    ) -> Result<Self, QueryError> {
+        let optional = |source: &str| {
+            (!source.trim().is_empty())
+                .then(|| Query::for_language(language, source))
+                .transpose()
+        };
+        let highlights = Query::for_language(language, highlights_query)?;
+        let highlight_indices = (0..highlights.capture_names().len())
+            .map(|index| Some(Highlight(index)))
+            .collect();
+        Ok(Self {
+            language_name: name.into(),
+            language,
+            highlights,
+            locals: optional(locals_query)?,
+            injections: optional(injection_query)?,
+            highlight_indices,
+        })
+    }
+
+    /// The capture names of the highlights query.
+    #[must_use]
+    pub fn names(&self) -> &[String] {
+        self.highlights.capture_names()
+    }
+
+    /// Maps the highlights query's captures onto `recognized_names`, the
+    /// highlights a theme knows about. A capture takes the recognized name
+    /// sharing the most dot-separated parts with it, all of which it must
+    /// contain: `@function.builtin` becomes `function.builtin` if that is
+    /// recognized, or else `function`. Captures with no such name, such as
+    /// the `@local` and `@injection` ones, are not highlighted.
+    pub fn configure(&mut self, recognized_names: &[impl AsRef<str>]) {
+        self.highlight_indices = self
+            .highlights
+            .capture_names()
+            .iter()
+            .map(|capture| {
+                let parts: Vec<&str> = capture.split('.').collect();
+                let mut best: Option<(usize, usize)> = None;
+                for (index, name) in recognized_names.iter().enumerate() {
+                    let name = name.as_ref();
+                    if !name.split('.').all(|part| parts.contains(&part)) {
+                        continue;
+                    }
+                    let length = name.split('.').count();
+                    if best.is_none_or(|(_, best_length)| length > best_length) {
+                        best = Some((index, length));
+                    }
+                }
+                best.map(|(index, _)| Highlight(index))
+            })
+            .collect();
+    }
+}
+
+/// Highlights `source` with `config`, asking `injection_callback` for the
+/// configuration of each language injected into it.
+pub fn highlight<'a>(
+    config: &HighlightConfiguration<'_>,
+    source: &[u8],
+    mut injection_callback: impl FnMut(&str) -> Option<&'a HighlightConfiguration<'a>>,
+) -> impl Iterator<Item = HighlightEvent> {
+    let mut spans = Vec::new();
+    collect_spans(
+        config,
+        source,
+        WHOLE_DOCUMENT,
+        0,
+        &mut injection_callback,
+        &mut spans,
+    );
+    events(spans, source.len()).into_iter()
+}
+
+/// A highlighted range of the document.
+struct Span {
+    range: Range<usize>,
+    highlight: Highlight,
+}
+
+/// Adds the highlights of the `ranges` of `source` to `spans`.
+fn collect_spans<'a>(
+    config: &HighlightConfiguration<'_>,
+    source: &[u8],
+    ranges: &[tree::Range],
+    depth: usize,
+    injection_callback: &mut dyn FnMut(&str) -> Option<&'a HighlightConfiguration<'a>>,
+    spans: &mut Vec<Span>,
+) {
+    let mut parser = Parser::new(config.language);
+    if parser.set_included_ranges(ranges).is_err() {
+        return;
+    }
+    let tree = parser.parse(source);
+    let cursor = QueryCursor::new();
+    let locals = config
+        .locals
+        .as_ref()
+        .map(|locals| locals::resolve(locals, &tree, source))
+        .unwrap_or_default();
+
+    let mut highlights: HashMap<*const Node, Option<Highlight>> = HashMap::new();
+    let mut order = Vec::new();
+    for (capture_match, index) in cursor.captures(&config.highlights, &tree, source) {
+        let capture = capture_match.captures[index];
+        let key: *const Node = capture.node;
+        if highlights.contains_key(&key) {
+            continue;
+        }
+        let is_local = locals.contains_key(&key);
+        let applies = config
+            .highlights
+            .property_predicates(capture_match.pattern_index)
+            .iter()
+            .filter(|(property, _)| property.key == "local")
+            .all(|(_, is)| *is == is_local);
+        if !applies {
+            continue;
+        }
+        highlights.insert(key, config.highlight_indices[capture.index]);
+        order.push(capture.node);
+    }
+    for node in order {
+        let key: *const Node = node;
+        let own = highlights[&key];
+        let definition = locals
+            .get(&key)
+            .and_then(|definition| highlights.get(definition).copied().flatten());
+        let Some(highlight) = definition.or(own) else {
+            continue;
+        };
+        // A node of an injected language can span text of the host's.
+        let node_range = node.byte_range();
+        for range in ranges {
+            let start = node_range.start.max(range.start_byte);
+            let end = node_range.end.min(range.end_byte);
+            if start < end {
+                spans.push(Span {
+                    range: start..end,
+                    highlight,
+                });
+            }
+        }
+    }
+
+    let Some(query) = &config.injections else {
+        return;
+    };
+    if depth >= MAX_INJECTION_DEPTH {
+        return;
+    }
+    for injection in injections(query, &tree, source, ranges) {
+        if let Some(injected) = injection_callback(&injection.language) {
+            collect_spans(
+                injected,
+                source,
+                &injection.ranges,
+                depth + 1,
+                injection_callback,
+                spans,
+            );
+        }
+    }
+}
+
+/// Turns highlighted ranges into properly nested events covering
+/// `0..length`.
+fn events(mut spans: Vec<Span>, length: usize) -> Vec<HighlightEvent> {
+    // Outer ranges first; at equal ranges, the order they were found in,
+    // which puts the host language outside what is injected into it.
+    let mut order: Vec<usize> = (0..spans.len()).collect();
+    order.sort_by_key(|&i| {
+        (
+            spans[i].range.start,
+            std::cmp::Reverse(spans[i].range.end),
+            i,
+        )
+    });
+    let mut events = Vec::new();
+    let mut position = 0;
+    let mut open: Vec<usize> = Vec::new();
+    for i in order {
+        while let Some(&end) = open.last() {
+            if end > spans[i].range.start {
+                break;
+            }
+            open.pop();
+            source_until(&mut events, &mut position, end);
+            events.push(HighlightEvent::HighlightEnd);
+        }
+        // A range crossing the end of the enclosing one is cut short.
+        let end = open
+            .last()
+            .map_or(spans[i].range.end, |&outer| outer.min(spans[i].range.end));
+        spans[i].range.end = end;
+        if spans[i].range.start >= end {
+            continue;
+        }
+        source_until(&mut events, &mut position, spans[i].range.start);
+        events.push(HighlightEvent::HighlightStart(spans[i].highlight));
+        open.push(end);
+    }
+    while let Some(end) = open.pop() {
+        source_until(&mut events, &mut position, end);
+        events.push(HighlightEvent::HighlightEnd);
+    }
+    source_until(&mut events, &mut position, length);
+    events
+}
+
+/// Emits the source from `position` to `end`, if there is any.
+fn source_until(events: &mut Vec<HighlightEvent>, position: &mut usize, end: usize) {
+    if end > *position {
+        events.push(HighlightEvent::Source {
+            start: *position,
+            end,
+        });
+        *position = end;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(range: Range<usize>, index: usize) -> Span {
+        Span {
+            range,
+            highlight: Highlight(index),
+        }
+    }
+
+    #[test]
+    fn test_events_nest_and_clamp_overlaps() {
+        use HighlightEvent::{HighlightEnd as End, HighlightStart as Start, Source};
+        // 1 lies inside 0; 2 crosses the end of 0 and is cut short there.
+        let spans = vec![span(0..6, 0), span(1..3, 1), span(4..8, 2), span(5..5, 3)];
+        assert_eq!(
+            events(spans, 10),
+            [
+                Start(Highlight(0)),
+                Source { start: 0, end: 1 },
+                Start(Highlight(1)),
+                Source { start: 1, end: 3 },
+                End,
+                Source { start: 3, end: 4 },
+                Start(Highlight(2)),
+                Source { start: 4, end: 6 },
+                End,
+                End,
+                Source { start: 6, end: 10 },
+            ]
+        );
+    }
+}
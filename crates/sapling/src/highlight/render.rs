@@ -0,0 +1,77 @@
+//! Rendering highlight events as styled text.
+
+use super::{Highlight, HighlightEvent};
+use std::fmt::Write;
+
+/// Renders `source` as HTML, wrapping each highlight in a `<span>` with the
+/// attributes `attributes` gives it, such as `class="keyword"`. Text is
+/// escaped; the result is not wrapped in `<pre>`.
+pub fn render_html(
+    source: &[u8],
+    events: impl IntoIterator<Item = HighlightEvent>,
+    attributes: impl Fn(Highlight) -> String,
+) -> String {
+    let mut html = String::new();
+    for event in events {
+        match event {
+            HighlightEvent::Source { start, end } => {
+                for c in String::from_utf8_lossy(&source[start..end]).chars() {
+                    match c {
+                        '<' => html.push_str("&lt;"),
+                        '>' => html.push_str("&gt;"),
+                        '&' => html.push_str("&amp;"),
+                        '"' => html.push_str("&quot;"),
+                        '\'' => html.push_str("&#39;"),
+                        c => html.push(c),
+                    }
+                }
+            }
+            HighlightEvent::HighlightStart(highlight) => {
+                let attributes = attributes(highlight);
+                if attributes.is_empty() {
+                    html.push_str("<span>");
+                } else {
+                    let _ = write!(html, "<span {attributes}>");
+                }
+            }
+            HighlightEvent::HighlightEnd => html.push_str("</span>"),
+        }
+    }
+    html
+}
+
+/// Renders `source` for a terminal. `style` gives the SGR parameters of
+/// each highlight, such as `"1;31"` for bold red, or `None` to leave it
+/// unstyled. Leaving a highlight resets the terminal and restores the
+/// styles of the highlights around it.
+pub fn render_ansi(
+    source: &[u8],
+    events: impl IntoIterator<Item = HighlightEvent>,
+    style: impl Fn(Highlight) -> Option<String>,
+) -> String {
+    let mut text = String::new();
+    let mut styles: Vec<Option<String>> = Vec::new();
+    for event in events {
+        match event {
+            HighlightEvent::Source { start, end } => {
+                text.push_str(&String::from_utf8_lossy(&source[start..end]));
+            }
+            HighlightEvent::HighlightStart(highlight) => {
+                let style = style(highlight);
+                if let Some(style) = &style {
+                    let _ = write!(text, "\x1b[{style}m");
+                }
+                styles.push(style);
+            }
+            HighlightEvent::HighlightEnd => {
+                if styles.pop().flatten().is_some() {
+                    text.push_str("\x1b[0m");
+                    for style in styles.iter().flatten() {
+                        let _ = write!(text, "\x1b[{style}m");
+                    }
+                }
+            }
+        }
+    }
+    text
+}
@@ -0,0 +1,726 @@
+//! Importing grammars written for other parser generators.
+//!
+//! Each importer reads one notation into a [`Grammar`]:
+//! [`antlr`](crate::import::antlr) reads ANTLR4 `.g4` files,
+//! [`pest`](crate::import::pest) reads `.pest` files and
+//! [`ungrammar`](crate::import::ungrammar) reads rust-analyzer's
+//! `.ungram` files. Repetitions and optional parts become `REPEAT` and
+//! `CHOICE` rules, labels become fields, and rules that describe tokens
+//! become patterns, with the rules they use inlined.
+//!
+//! No notation maps onto tree-sitter's exactly. Where an importer meets
+//! something the grammar can't express, such as a semantic predicate or a
+//! lexer mode, or something that means something else in tree-sitter, such
+//! as an ordered choice whose order matters, it reports an
+//! [`ImportDiagnostic`](crate::import::ImportDiagnostic) and carries on,
+//! so the grammar it returns is a starting point that says where it needs
+//! work. Choices the notation leaves open, such as which rule to start
+//! from, are reported as notes.
+
+use crate::grammar::{Grammar, Rule, RuleType, RuleValue};
+use crate::validate::{collect_referenced_symbols, Severity};
+use indexmap::IndexMap;
+use std::collections::HashMap;
+use std::fmt;
+
+pub mod antlr;
+pub mod pest;
+pub mod ungrammar;
+
+/// A notation grammars can be imported from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// ANTLR4 grammars, in `.g4` files.
+    Antlr,
+    /// pest grammars, in `.pest` files.
+    Pest,
+    /// rust-analyzer's ungrammar, in `.ungram` files.
+    Ungrammar,
+}
+
+impl Format {
+    /// Looks up a format by its name: `antlr`, `pest` or `ungrammar`.
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "antlr" => Some(Format::Antlr),
+            "pest" => Some(Format::Pest),
+            "ungrammar" => Some(Format::Ungrammar),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format of a file from its extension.
+    #[must_use]
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "g4" => Some(Format::Antlr),
+            "pest" => Some(Format::Pest),
+            "ungram" => Some(Format::Ungrammar),
+            _ => None,
+        }
+    }
+}
+
+/// An imported grammar and what could not be carried over faithfully.
+#[derive(Debug, Clone)]
+pub struct Import {
+    /// The grammar.
+    pub grammar: Grammar,
+    /// Where the grammar differs from the one it was imported from, in the
+    /// order they were found.
+    pub diagnostics: Vec<ImportDiagnostic>,
+}
+
+/// Something an importer could not translate faithfully.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportDiagnostic {
+    /// A warning for what was dropped or approximated, or a note for a
+    /// choice the importer made that the source left open.
+    pub severity: Severity,
+    /// The 1-based line of the imported file it is on, if it is on one.
+    pub line: Option<usize>,
+    /// What was dropped, approximated or chosen, and why.
+    pub message: String,
+}
+
+impl fmt::Display for ImportDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {line}: {}", self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+/// A syntax error in an imported file, with a 1-based line and column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportError {
+    /// The line it is on.
+    pub line: usize,
+    /// The column it is at, in characters.
+    pub column: usize,
+    /// What was expected.
+    pub message: String,
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at line {} column {}",
+            self.message, self.line, self.column
+        )
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+/// Imports `source`, written in `format`, as a grammar called `name`.
+/// ANTLR grammars declare their own name, which is used instead. pest
+/// grammars start from a rule the importer chooses; [`pest::import`] takes
+/// one to start from instead.
+///
+/// # Errors
+///
+/// Returns an [`ImportError`] if `source` is not valid in `format`.
+pub fn import(format: Format, name: &str, source: &str) -> Result<Import, ImportError> {
+    match format {
+        Format::Antlr => antlr::import(source),
+        Format::Pest => pest::import(name, source, None),
+        Format::Ungrammar => ungrammar::import(name, source),
+    }
+}
+
+/// A grammar with nothing but a name and rules.
+fn grammar(name: &str, rules: IndexMap<String, Rule>) -> Grammar {
+    Grammar {
+        schema: None,
+        name: name.to_string(),
+        inherits: None,
+        rules,
+        extras: None,
+        precedences: None,
+        reserved: None,
+        externals: None,
+        inline: None,
+        conflicts: None,
+        word: None,
+        supertypes: None,
+    }
+}
+
+/// Moves the rule called `start` to the front, making it the start rule.
+fn make_start(rules: &mut IndexMap<String, Rule>, start: &str) {
+    if let Some(index) = rules.get_index_of(start) {
+        rules.move_index(index, 0);
+    }
+}
+
+/// The first rule no other rule refers to, which is most likely the one a
+/// grammar starts from.
+fn first_unreferenced(rules: &IndexMap<String, Rule>) -> Option<&str> {
+    let mut referenced = Vec::new();
+    for (name, rule) in rules {
+        let mut symbols = Vec::new();
+        collect_referenced_symbols(rule, &mut symbols);
+        referenced.extend(symbols.into_iter().filter(|symbol| symbol != name));
+    }
+    rules
+        .keys()
+        .find(|name| !referenced.contains(name))
+        .map(String::as_str)
+}
+
+/// Renames the symbols `rule` refers to.
+fn rename_symbols(rule: &mut Rule, renames: &HashMap<String, String>) {
+    if rule.rule_type == RuleType::Symbol {
+        if let Some(new_name) = rule.name.as_ref().and_then(|name| renames.get(name)) {
+            rule.name = Some(new_name.clone());
+        }
+    }
+    for member in &mut rule.members {
+        rename_symbols(member, renames);
+    }
+    if let Some(content) = &mut rule.content {
+        rename_symbols(content, renames);
+    }
+}
+
+fn rule(rule_type: RuleType) -> Rule {
+    Rule {
+        rule_type,
+        value: None,
+        name: None,
+        content: None,
+        members: Vec::new(),
+        named: None,
+        flags: None,
+        context_name: None,
+    }
+}
+
+fn blank() -> Rule {
+    rule(RuleType::Blank)
+}
+
+fn string(text: &str) -> Rule {
+    Rule {
+        value: Some(RuleValue::String(text.to_string())),
+        ..rule(RuleType::String)
+    }
+}
+
+fn symbol(name: &str) -> Rule {
+    Rule {
+        name: Some(name.to_string()),
+        ..rule(RuleType::Symbol)
+    }
+}
+
+/// A sequence, without blank members and flattened where it has only one.
+fn seq(members: Vec<Rule>) -> Rule {
+    let mut members: Vec<Rule> = members
+        .into_iter()
+        .filter(|member| member.rule_type != RuleType::Blank)
+        .collect();
+    match members.len() {
+        0 => blank(),
+        1 => members.remove(0),
+        _ => Rule {
+            members,
+            ..rule(RuleType::Seq)
+        },
+    }
+}
+
+/// A choice, flattened where it has only one member.
+fn choice(mut members: Vec<Rule>) -> Rule {
+    match members.len() {
+        0 => blank(),
+        1 => members.remove(0),
+        _ => Rule {
+            members,
+            ..rule(RuleType::Choice)
+        },
+    }
+}
+
+fn optional(content: Rule) -> Rule {
+    if content.rule_type == RuleType::Blank {
+        return content;
+    }
+    choice(vec![content, blank()])
+}
+
+/// A rule wrapping `content`, such as a repetition or a precedence.
+fn wrap(rule_type: RuleType, content: Rule) -> Rule {
+    Rule {
+        content: Some(Box::new(content)),
+        ..rule(rule_type)
+    }
+}
+
+fn field(name: &str, content: Rule) -> Rule {
+    Rule {
+        name: Some(name.to_string()),
+        ..wrap(RuleType::Field, content)
+    }
+}
+
+/// `content` repeated between `min` and `max` times, unbounded if `max` is
+/// `None`, spelled out with copies and optional parts.
+fn bounded(content: &Rule, min: u32, max: Option<u32>) -> Rule {
+    let mut members = vec![content.clone(); min as usize];
+    match max {
+        None if min > 0 => {
+            members.pop();
+            members.push(wrap(RuleType::Repeat1, content.clone()));
+        }
+        None => members.push(wrap(RuleType::Repeat, content.clone())),
+        Some(max) => {
+            let mut rest = None;
+            for _ in min..max {
+                let inner = match rest.take() {
+                    Some(rest) => seq(vec![content.clone(), rest]),
+                    None => content.clone(),
+                };
+                rest = Some(optional(inner));
+            }
+            members.extend(rest);
+        }
+    }
+    seq(members)
+}
+
+/// How tightly the top level of a [`Regex`] binds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Binding {
+    Alternation,
+    Concatenation,
+    Atom,
+}
+
+/// A regular expression assembled from the parts of a token rule.
+#[derive(Debug, Clone)]
+struct Regex {
+    source: String,
+    binding: Binding,
+    /// What goes between the brackets of a class matching the same
+    /// characters, if it matches single characters, for negating it.
+    class: Option<String>,
+}
+
+impl Regex {
+    fn new(source: impl Into<String>, binding: Binding) -> Self {
+        Self {
+            source: source.into(),
+            binding,
+            class: None,
+        }
+    }
+
+    /// Matches `text` exactly.
+    fn literal(text: &str) -> Self {
+        let mut chars = text.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            return Self {
+                class: Some(class_char(c)),
+                ..Self::new(escape_char(c), Binding::Atom)
+            };
+        }
+        Self::new(
+            text.chars().map(escape_char).collect::<String>(),
+            Binding::Concatenation,
+        )
+    }
+
+    /// Matches a character of the class whose contents are `body`.
+    fn class(body: &str) -> Self {
+        Self {
+            class: Some(body.to_string()),
+            ..Self::new(format!("[{body}]"), Binding::Atom)
+        }
+    }
+
+    /// Matches a character not in `self`, if `self` matches single
+    /// characters.
+    fn negated(&self) -> Option<Self> {
+        self.class
+            .as_ref()
+            .map(|body| Self::new(format!("[^{body}]"), Binding::Atom))
+    }
+
+    /// Matches everything up to and including the first `terminator`, as
+    /// a non-greedy loop followed by the terminator would, if the
+    /// terminator is one character or two different ones.
+    fn until(terminator: &str) -> Option<Self> {
+        let chars: Vec<char> = terminator.chars().collect();
+        let source = match chars[..] {
+            [end] => format!("[^{}]*{}", class_char(end), escape_char(end)),
+            // Runs of the first character, then anything but the second.
+            [first, second] if first != second => {
+                let class = class_char(first);
+                let first = escape_char(first);
+                format!(
+                    "([^{class}]|{first}+[^{class}{}])*{first}+{}",
+                    class_char(second),
+                    escape_char(second)
+                )
+            }
+            _ => return None,
+        };
+        Some(Self::new(source, Binding::Concatenation))
+    }
+
+    fn wrapped(self, at_least: Binding) -> String {
+        if self.binding < at_least {
+            format!("({})", self.source)
+        } else {
+            self.source
+        }
+    }
+
+    fn concatenation(parts: Vec<Self>) -> Self {
+        let mut parts = parts;
+        if parts.len() == 1 {
+            return parts.remove(0);
+        }
+        let source = parts
+            .into_iter()
+            .map(|part| part.wrapped(Binding::Concatenation))
+            .collect::<String>();
+        Self::new(source, Binding::Concatenation)
+    }
+
+    fn alternation(parts: Vec<Self>) -> Self {
+        let mut parts = parts;
+        if parts.len() == 1 {
+            return parts.remove(0);
+        }
+        let class = parts
+            .iter()
+            .map(|part| part.class.clone())
+            .collect::<Option<String>>();
+        let source = parts
+            .into_iter()
+            .map(|part| part.source)
+            .collect::<Vec<_>>()
+            .join("|");
+        Self {
+            class,
+            ..Self::new(source, Binding::Alternation)
+        }
+    }
+
+    /// Applies a quantifier such as `*` or `{2,3}`.
+    fn quantified(self, quantifier: &str) -> Self {
+        // Quantifying again needs brackets, as `a*?` is a lazy `a*`.
+        Self::new(
+            format!("{}{quantifier}", self.wrapped(Binding::Atom)),
+            Binding::Concatenation,
+        )
+    }
+
+    fn into_rule(self) -> Rule {
+        Rule {
+            value: Some(RuleValue::String(self.source)),
+            ..rule(RuleType::Pattern)
+        }
+    }
+}
+
+/// A character as it is written in a regular expression.
+fn escape_char(c: char) -> String {
+    match c {
+        '\\' | '.' | '+' | '*' | '?' | '(' | ')' | '|' | '[' | ']' | '{' | '}' | '^' | '$' => {
+            format!("\\{c}")
+        }
+        '-' => c.to_string(),
+        _ => class_char(c),
+    }
+}
+
+/// A character as it is written between the brackets of a class.
+fn class_char(c: char) -> String {
+    match c {
+        '\\' | ']' | '[' | '^' | '-' => format!("\\{c}"),
+        '\n' => "\\n".to_string(),
+        '\r' => "\\r".to_string(),
+        '\t' => "\\t".to_string(),
+        c if c.is_control() => format!("\\u{:04X}", u32::from(c)),
+        c => c.to_string(),
+    }
+}
+
+/// Reads the text of a grammar file a token at a time, skipping
+/// whitespace and comments.
+struct Scanner<'a> {
+    source: &'a str,
+    offset: usize,
+    /// Whether `/* */` comments are allowed as well as `//` ones.
+    block_comments: bool,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(source: &'a str, block_comments: bool) -> Self {
+        Self {
+            source,
+            offset: 0,
+            block_comments,
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.source[self.offset..]
+    }
+
+    /// The 1-based line of the current position.
+    fn line(&self) -> usize {
+        self.source[..self.offset].matches('\n').count() + 1
+    }
+
+    fn error(&self, message: impl Into<String>) -> ImportError {
+        let before = &self.source[..self.offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        ImportError {
+            line: self.line(),
+            column: before[line_start..].chars().count() + 1,
+            message: message.into(),
+        }
+    }
+
+    fn skip_trivia(&mut self) {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.offset += rest.len() - trimmed.len();
+            if trimmed.starts_with("//") {
+                self.offset += trimmed.find('\n').unwrap_or(trimmed.len());
+            } else if self.block_comments && trimmed.starts_with("/*") {
+                self.offset += trimmed[2..].find("*/").map_or(trimmed.len(), |i| i + 4);
+            } else {
+                return;
+            }
+        }
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.skip_trivia();
+        self.rest().is_empty()
+    }
+
+    /// The next character, after any trivia, without consuming it.
+    fn peek(&mut self) -> Option<char> {
+        self.skip_trivia();
+        self.rest().chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.rest().chars().next()?;
+        self.offset += c.len_utf8();
+        Some(c)
+    }
+
+    /// Consumes `text` if it comes next.
+    fn eat(&mut self, text: &str) -> bool {
+        self.skip_trivia();
+        if self.rest().starts_with(text) {
+            self.offset += text.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, text: &str) -> Result<(), ImportError> {
+        if self.eat(text) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected '{text}'")))
+        }
+    }
+
+    /// Consumes an identifier if one comes next.
+    fn identifier(&mut self) -> Option<&'a str> {
+        self.skip_trivia();
+        let rest = self.rest();
+        let length = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        if length == 0 || rest.starts_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+        self.offset += length;
+        Some(&rest[..length])
+    }
+
+    fn expect_identifier(&mut self) -> Result<&'a str, ImportError> {
+        self.identifier()
+            .ok_or_else(|| self.error("expected an identifier"))
+    }
+
+    /// Whether the identifier `word` comes next.
+    fn at_keyword(&mut self, word: &str) -> bool {
+        self.skip_trivia();
+        let rest = self.rest();
+        rest.starts_with(word)
+            && !rest[word.len()..].starts_with(|c: char| c.is_alphanumeric() || c == '_')
+    }
+
+    /// Consumes the keyword `word` if it comes next.
+    fn eat_keyword(&mut self, word: &str) -> bool {
+        let found = self.at_keyword(word);
+        if found {
+            self.offset += word.len();
+        }
+        found
+    }
+
+    /// Consumes a bracketed block of code, such as an action, whose first
+    /// bracket comes next, and returns what is inside it. Brackets inside
+    /// strings and character literals are not counted.
+    fn balanced(&mut self, open: char, close: char) -> Result<&'a str, ImportError> {
+        self.skip_trivia();
+        let start = self.offset;
+        if self.bump() != Some(open) {
+            return Err(self.error(format!("expected '{open}'")));
+        }
+        let mut depth = 1;
+        while depth > 0 {
+            match self.bump() {
+                None => {
+                    self.offset = start;
+                    return Err(self.error(format!("unclosed '{open}'")));
+                }
+                Some(quote @ ('"' | '\'')) if open != '[' => {
+                    while let Some(c) = self.bump() {
+                        if c == '\\' {
+                            self.bump();
+                        } else if c == quote || c == '\n' {
+                            break;
+                        }
+                    }
+                }
+                Some('\\') => {
+                    self.bump();
+                }
+                Some(c) if c == open => depth += 1,
+                Some(c) if c == close => depth -= 1,
+                Some(_) => {}
+            }
+        }
+        Ok(&self.source[start + open.len_utf8()..self.offset - close.len_utf8()])
+    }
+
+    /// Consumes a quoted string whose opening `quote` comes next, and
+    /// returns its contents with escapes as they are.
+    fn quoted(&mut self, quote: char) -> Result<&'a str, ImportError> {
+        self.skip_trivia();
+        let start = self.offset;
+        self.bump();
+        loop {
+            match self.bump() {
+                None | Some('\n') => {
+                    self.offset = start;
+                    return Err(self.error("unterminated string"));
+                }
+                Some('\\') => {
+                    self.bump();
+                }
+                Some(c) if c == quote => break,
+                Some(_) => {}
+            }
+        }
+        Ok(&self.source[start + 1..self.offset - 1])
+    }
+}
+
+/// Resolves the escapes shared by the notations: `\n`, `\r`, `\t`, `\0`,
+/// `\u` followed by four hex digits or by hex digits in braces, and a
+/// backslash before any other character, which stands for that character.
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('t') => out.push('\t'),
+            Some('b') => out.push('\u{8}'),
+            Some('f') => out.push('\u{c}'),
+            Some('0') => out.push('\0'),
+            Some(kind @ ('u' | 'x')) => {
+                let digits: String = if chars.peek() == Some(&'{') {
+                    chars.next();
+                    chars.by_ref().take_while(|&c| c != '}').collect()
+                } else {
+                    let count = if kind == 'u' { 4 } else { 2 };
+                    let mut digits = String::new();
+                    while digits.len() < count {
+                        match chars.next_if(char::is_ascii_hexdigit) {
+                            Some(c) => digits.push(c),
+                            None => break,
+                        }
+                    }
+                    digits
+                };
+                if let Some(c) = u32::from_str_radix(&digits, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                {
+                    out.push(c);
+                } else {
+                    out.push(kind);
+                    out.push_str(&digits);
+                }
+            }
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regex_building() {
+        let digits = Regex::class("0-9").quantified("+");
+        let sign = Regex::alternation(vec![Regex::literal("+"), Regex::literal("-")]);
+        assert_eq!(sign.source, "\\+|-");
+        assert_eq!(sign.negated().unwrap().source, "[^+\\-]");
+        let number = Regex::concatenation(vec![sign.quantified("?"), digits]);
+        assert_eq!(number.source, "(\\+|-)?[0-9]+");
+        assert_eq!(
+            Regex::literal("a.b").quantified("*").quantified("?").source,
+            "((a\\.b)*)?"
+        );
+        assert_eq!(Regex::until("*/").unwrap().source, "([^*]|\\*+[^*/])*\\*+/");
+        assert_eq!(Regex::until("\n").unwrap().source, "[^\\n]*\\n");
+        assert!(Regex::until("--").is_none());
+    }
+
+    #[test]
+    fn test_bounded_repetition() {
+        let a = string("a");
+        let two_to_three = bounded(&a, 2, Some(3));
+        assert_eq!(
+            two_to_three,
+            seq(vec![a.clone(), a.clone(), optional(a.clone())])
+        );
+        let at_least_one = bounded(&a, 1, None);
+        assert_eq!(at_least_one, wrap(RuleType::Repeat1, a));
+    }
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(unescape(r"a\n\'A\u{1F600}\x41"), "a\n'A\u{1F600}A");
+    }
+}
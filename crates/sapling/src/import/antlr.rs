@@ -0,0 +1,941 @@
+//! ANTLR4 grammar import.
+//!
+//! Parser rules become rules of the same name and lexer rules become
+//! tokens: a rule that is one literal becomes a string, and any other
+//! becomes a pattern, with the fragments it uses inlined. Literals in
+//! parser rules that some lexer rule matches exactly refer to that rule,
+//! as ANTLR would, and others become strings. Tokens the lexer skips become
+//! hidden extras, and tokens it sends to another channel, such as
+//! comments, become visible ones.
+//!
+//! Labels such as `left=expr` become fields. Labelled alternatives, which
+//! ANTLR gives contexts of their own, become rules named after their
+//! labels. Left-recursive rules get ANTLR's precedence back: the earlier an
+//! alternative that recurses, the higher its precedence, and it is
+//! left-associative unless it says `<assoc=right>`.
+//!
+//! Actions are dropped. A non-greedy `.*?` followed by a terminator of
+//! one character, or two different ones, becomes a pattern that stops at
+//! the first terminator. Other non-greedy loops, semantic predicates,
+//! lexer modes and commands, wildcards and sets in parser rules, and
+//! tokens the lexer grammar doesn't define are reported.
+
+use super::{
+    blank, choice, class_char, field, grammar, make_start, optional, rename_symbols, seq, string,
+    symbol, unescape, wrap, Binding, Import, ImportDiagnostic, ImportError, Regex, Scanner,
+};
+use crate::grammar::{Rule, RuleType, RuleValue};
+use crate::validate::Severity;
+use indexmap::IndexMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+/// An element of an alternative, as written.
+#[derive(Debug, Clone)]
+enum Element {
+    Literal(String),
+    Reference(String),
+    /// A `[...]` set, as the contents of a regex class.
+    Set(String),
+    Range(char, char),
+    Wildcard,
+    Not(Box<Element>),
+    Block(Vec<Alternative>),
+    /// An element followed by `?`, `*` or `+`, and whether it is greedy.
+    Quantified(Box<Element>, char, bool),
+    Labeled(String, Box<Element>),
+    Predicate,
+}
+
+#[derive(Debug, Clone)]
+struct Alternative {
+    elements: Vec<Element>,
+    label: Option<String>,
+    right_associative: bool,
+    /// Lexer commands such as `skip` or `channel(HIDDEN)`.
+    commands: Vec<String>,
+    line: usize,
+}
+
+#[derive(Debug)]
+struct RuleDefinition {
+    name: String,
+    fragment: bool,
+    alternatives: Vec<Alternative>,
+    line: usize,
+}
+
+impl RuleDefinition {
+    fn is_lexer_rule(&self) -> bool {
+        self.name.starts_with(|c: char| c.is_uppercase())
+    }
+}
+
+/// Imports an ANTLR4 grammar, named after its `grammar` declaration.
+///
+/// # Errors
+///
+/// Returns an [`ImportError`] if `source` is not a valid ANTLR4 grammar.
+pub fn import(source: &str) -> Result<Import, ImportError> {
+    let mut parser = Parser {
+        scanner: Scanner::new(source, true),
+        diagnostics: Vec::new(),
+    };
+    let name = parser.header()?;
+    let mut definitions = Vec::new();
+    let mut tokens = Vec::new();
+    while !parser.scanner.at_end() {
+        if !parser.prequel(&mut tokens)? {
+            definitions.push(parser.rule()?);
+        }
+    }
+    let mut translator = Translator {
+        lexer_rules: definitions
+            .iter()
+            .filter(|definition| definition.is_lexer_rule())
+            .map(|definition| (definition.name.as_str(), definition))
+            .collect(),
+        literal_tokens: HashMap::new(),
+        diagnostics: parser.diagnostics,
+    };
+    for definition in translator.lexer_rules.values() {
+        if let [Alternative { elements, .. }] = &definition.alternatives[..] {
+            if let [Element::Literal(text)] = &elements[..] {
+                if !definition.fragment {
+                    translator
+                        .literal_tokens
+                        .insert(text.clone(), definition.name.clone());
+                }
+            }
+        }
+    }
+    Ok(translator.translate(&name, &definitions, &tokens))
+}
+
+struct Parser<'a> {
+    scanner: Scanner<'a>,
+    diagnostics: Vec<ImportDiagnostic>,
+}
+
+impl Parser<'_> {
+    fn diagnostic(&mut self, line: usize, message: String) {
+        self.diagnostics.push(ImportDiagnostic {
+            severity: Severity::Warning,
+            line: Some(line),
+            message,
+        });
+    }
+
+    /// Reads `grammar Name;`, with `lexer` or `parser` before it, and
+    /// returns the name.
+    fn header(&mut self) -> Result<String, ImportError> {
+        if !self.scanner.eat_keyword("lexer") {
+            self.scanner.eat_keyword("parser");
+        }
+        if !self.scanner.eat_keyword("grammar") {
+            return Err(self.scanner.error("expected a grammar declaration"));
+        }
+        let name = self.scanner.expect_identifier()?.to_lowercase();
+        self.scanner.expect(";")?;
+        Ok(name)
+    }
+
+    /// Reads a declaration other than a rule, if one comes next: options,
+    /// tokens, channels, imports, named actions and modes.
+    fn prequel(&mut self, tokens: &mut Vec<(String, usize)>) -> Result<bool, ImportError> {
+        self.scanner.skip_trivia();
+        let line = self.scanner.line();
+        if self.scanner.eat_keyword("options") {
+            let options = self.scanner.balanced('{', '}')?;
+            if options.contains("tokenVocab") {
+                self.diagnostic(
+                    line,
+                    "the tokens come from a separate lexer grammar, which is not read; \
+                     import it too and merge its rules"
+                        .to_string(),
+                );
+            }
+            if options.contains("caseInsensitive") {
+                self.diagnostic(
+                    line,
+                    "the caseInsensitive option is ignored, so literals match their case only"
+                        .to_string(),
+                );
+            }
+        } else if self.scanner.eat_keyword("tokens") {
+            let declared = self.scanner.balanced('{', '}')?;
+            tokens.extend(
+                declared
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|token| !token.is_empty())
+                    .map(|token| (token.to_string(), line)),
+            );
+        } else if self.scanner.eat_keyword("channels") {
+            self.scanner.balanced('{', '}')?;
+        } else if self.scanner.eat_keyword("import") {
+            while !self.scanner.eat(";") {
+                if self.scanner.bump().is_none() {
+                    return Err(self.scanner.error("expected ';'"));
+                }
+            }
+            self.diagnostic(line, "imported grammars are not read".to_string());
+        } else if self.scanner.eat("@") {
+            self.scanner.expect_identifier()?;
+            if self.scanner.eat("::") {
+                self.scanner.expect_identifier()?;
+            }
+            self.scanner.balanced('{', '}')?;
+        } else if self.scanner.eat_keyword("mode") {
+            let mode = self.scanner.expect_identifier()?;
+            self.scanner.expect(";")?;
+            self.diagnostic(
+                line,
+                format!(
+                    "lexer modes have no equivalent; the rules of mode '{mode}' are imported \
+                     as tokens that can appear anywhere"
+                ),
+            );
+        } else {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    fn rule(&mut self) -> Result<RuleDefinition, ImportError> {
+        self.scanner.skip_trivia();
+        let line = self.scanner.line();
+        let fragment = self.scanner.eat_keyword("fragment");
+        for modifier in ["public", "private", "protected"] {
+            self.scanner.eat_keyword(modifier);
+        }
+        let name = self.scanner.expect_identifier()?.to_string();
+        if self.scanner.peek() == Some('[') {
+            self.scanner.balanced('[', ']')?;
+        }
+        if self.scanner.eat_keyword("returns") {
+            self.scanner.balanced('[', ']')?;
+        }
+        if self.scanner.eat_keyword("throws") {
+            self.scanner.expect_identifier()?;
+            while self.scanner.eat(",") {
+                self.scanner.expect_identifier()?;
+            }
+        }
+        if self.scanner.eat_keyword("locals") {
+            self.scanner.balanced('[', ']')?;
+        }
+        loop {
+            if self.scanner.eat_keyword("options") {
+                self.scanner.balanced('{', '}')?;
+            } else if self.scanner.eat("@") {
+                self.scanner.expect_identifier()?;
+                self.scanner.balanced('{', '}')?;
+            } else {
+                break;
+            }
+        }
+        self.scanner.expect(":")?;
+        let alternatives = self.alternatives()?;
+        self.scanner.expect(";")?;
+        while self.scanner.eat_keyword("catch") {
+            self.scanner.balanced('[', ']')?;
+            self.scanner.balanced('{', '}')?;
+        }
+        if self.scanner.eat_keyword("finally") {
+            self.scanner.balanced('{', '}')?;
+        }
+        Ok(RuleDefinition {
+            name,
+            fragment,
+            alternatives,
+            line,
+        })
+    }
+
+    fn alternatives(&mut self) -> Result<Vec<Alternative>, ImportError> {
+        let mut alternatives = vec![self.alternative()?];
+        while self.scanner.eat("|") {
+            alternatives.push(self.alternative()?);
+        }
+        Ok(alternatives)
+    }
+
+    fn alternative(&mut self) -> Result<Alternative, ImportError> {
+        self.scanner.skip_trivia();
+        let mut alternative = Alternative {
+            elements: Vec::new(),
+            label: None,
+            right_associative: false,
+            commands: Vec::new(),
+            line: self.scanner.line(),
+        };
+        loop {
+            match self.scanner.peek() {
+                None | Some(';' | ')' | '|') => break,
+                Some('#') => {
+                    self.scanner.bump();
+                    alternative.label = Some(self.scanner.expect_identifier()?.to_string());
+                    break;
+                }
+                Some('<') => {
+                    let options = self.scanner.balanced('<', '>')?;
+                    if options.replace(' ', "").contains("assoc=right") {
+                        alternative.right_associative = true;
+                    }
+                }
+                Some('-') if self.scanner.eat("->") => {
+                    loop {
+                        let mut command = self.scanner.expect_identifier()?.to_string();
+                        if self.scanner.peek() == Some('(') {
+                            let argument = self.scanner.balanced('(', ')')?;
+                            command = format!("{command}({})", argument.trim());
+                        }
+                        alternative.commands.push(command);
+                        if !self.scanner.eat(",") {
+                            break;
+                        }
+                    }
+                    break;
+                }
+                Some(_) => alternative.elements.extend(self.element()?),
+            }
+        }
+        Ok(alternative)
+    }
+
+    /// Reads an element with its `?`, `*` or `+`, or an action, which is
+    /// dropped.
+    fn element(&mut self) -> Result<Option<Element>, ImportError> {
+        let Some(mut element) = self.primary()? else {
+            return Ok(None);
+        };
+        while let Some(quantifier @ ('?' | '*' | '+')) = self.scanner.peek() {
+            self.scanner.bump();
+            let greedy = !self.scanner.rest().starts_with('?');
+            if !greedy {
+                self.scanner.bump();
+            }
+            element = Element::Quantified(Box::new(element), quantifier, greedy);
+        }
+        Ok(Some(element))
+    }
+
+    fn primary(&mut self) -> Result<Option<Element>, ImportError> {
+        let next = self.scanner.peek();
+        let line = self.scanner.line();
+        let element = match next {
+            Some('{') => {
+                self.scanner.balanced('{', '}')?;
+                if !self.scanner.rest().starts_with('?') {
+                    return Ok(None);
+                }
+                self.scanner.bump();
+                self.diagnostic(
+                    line,
+                    "semantic predicates have no equivalent and are dropped".to_string(),
+                );
+                Element::Predicate
+            }
+            Some('(') => {
+                self.scanner.bump();
+                let alternatives = self.alternatives()?;
+                self.scanner.expect(")")?;
+                Element::Block(alternatives)
+            }
+            Some('\'') => {
+                let text = unescape(self.scanner.quoted('\'')?);
+                if self.scanner.eat("..") {
+                    if self.scanner.peek() != Some('\'') {
+                        return Err(self.scanner.error("expected a character literal"));
+                    }
+                    let end = unescape(self.scanner.quoted('\'')?);
+                    match (single_char(&text), single_char(&end)) {
+                        (Some(start), Some(end)) => Element::Range(start, end),
+                        _ => return Err(self.scanner.error("ranges are of single characters")),
+                    }
+                } else {
+                    Element::Literal(text)
+                }
+            }
+            Some('[') => Element::Set(self.set()?),
+            Some('.') => {
+                self.scanner.bump();
+                Element::Wildcard
+            }
+            Some('~') => {
+                self.scanner.bump();
+                match self.primary()? {
+                    Some(element) => Element::Not(Box::new(element)),
+                    None => return Err(self.scanner.error("expected something to negate")),
+                }
+            }
+            _ => {
+                let name = self.scanner.expect_identifier()?.to_string();
+                if self.scanner.eat("+=") || self.scanner.eat("=") {
+                    match self.primary()? {
+                        Some(element) => Element::Labeled(name, Box::new(element)),
+                        None => return Err(self.scanner.error("expected an element to label")),
+                    }
+                } else {
+                    if self.scanner.peek() == Some('[') {
+                        // The arguments of a rule reference.
+                        self.scanner.balanced('[', ']')?;
+                    }
+                    Element::Reference(name)
+                }
+            }
+        };
+        Ok(Some(element))
+    }
+
+    /// Reads a `[...]` set and returns it as the contents of a regex class.
+    fn set(&mut self) -> Result<String, ImportError> {
+        self.scanner.bump();
+        let mut body = String::new();
+        loop {
+            let c = match self.scanner.bump() {
+                None | Some('\n') => return Err(self.scanner.error("unterminated set")),
+                Some(']') => break,
+                Some(c) => c,
+            };
+            if c == '\\' {
+                match self.scanner.bump() {
+                    Some(kind @ ('p' | 'P')) if self.scanner.rest().starts_with('{') => {
+                        let property = self.scanner.balanced('{', '}')?;
+                        let _ = write!(body, "\\{kind}{{{property}}}");
+                    }
+                    Some('u') => {
+                        let digits = if self.scanner.rest().starts_with('{') {
+                            format!("{{{}}}", self.scanner.balanced('{', '}')?)
+                        } else {
+                            let digits: String = self.scanner.rest().chars().take(4).collect();
+                            self.scanner.offset += digits.len();
+                            digits
+                        };
+                        for c in unescape(&format!("\\u{digits}")).chars() {
+                            body.push_str(&class_char(c));
+                        }
+                    }
+                    Some(escaped) => {
+                        for c in unescape(&format!("\\{escaped}")).chars() {
+                            body.push_str(&class_char(c));
+                        }
+                    }
+                    None => return Err(self.scanner.error("unterminated set")),
+                }
+            } else if c == '-' && !body.is_empty() && !self.scanner.rest().starts_with(']') {
+                body.push('-');
+            } else {
+                body.push_str(&class_char(c));
+            }
+        }
+        Ok(body)
+    }
+}
+
+fn single_char(text: &str) -> Option<char> {
+    let mut chars = text.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
+
+struct Translator<'a> {
+    /// Lexer rules and fragments by name.
+    lexer_rules: HashMap<&'a str, &'a RuleDefinition>,
+    /// The lexer rules that match a single literal, by the literal.
+    literal_tokens: HashMap<String, String>,
+    diagnostics: Vec<ImportDiagnostic>,
+}
+
+impl<'a> Translator<'a> {
+    fn diagnostic(&mut self, line: usize, message: String) {
+        self.diagnostics.push(ImportDiagnostic {
+            severity: Severity::Warning,
+            line: Some(line),
+            message,
+        });
+    }
+
+    fn translate(
+        mut self,
+        name: &str,
+        definitions: &'a [RuleDefinition],
+        tokens: &[(String, usize)],
+    ) -> Import {
+        let mut rules = IndexMap::new();
+        let mut lexer_rules = IndexMap::new();
+        let mut extras = Vec::new();
+        let mut renames = HashMap::new();
+        let mut start = None;
+        let taken: HashSet<&str> = definitions.iter().map(|d| d.name.as_str()).collect();
+
+        for definition in definitions {
+            if !definition.is_lexer_rule() {
+                if start.is_none() && refers_to_eof(&definition.alternatives) {
+                    start = Some(definition.name.clone());
+                }
+                let (rule, lifted) = self.parser_rule(definition, &taken);
+                rules.insert(definition.name.clone(), rule);
+                for (label, alternatives) in lifted {
+                    rules.insert(label, choice(alternatives));
+                }
+                continue;
+            }
+            if definition.fragment {
+                continue;
+            }
+            for command in definition.alternatives.iter().flat_map(|a| &a.commands) {
+                let name = definition.name.clone();
+                if command == "skip" {
+                    renames.insert(name.clone(), format!("_{name}"));
+                    extras.push(symbol(&name));
+                } else if command.starts_with("channel(") {
+                    extras.push(symbol(&name));
+                } else {
+                    self.diagnostic(
+                        definition.line,
+                        format!(
+                            "the lexer command '{command}' of '{name}' has no equivalent \
+                             and is ignored"
+                        ),
+                    );
+                }
+            }
+            let rule = self.lexer_rule(definition);
+            lexer_rules.insert(definition.name.clone(), rule);
+        }
+        let parser_start = definitions
+            .iter()
+            .find(|definition| !definition.is_lexer_rule())
+            .map(|definition| definition.name.clone());
+        rules.extend(lexer_rules);
+
+        let mut externals = Vec::new();
+        for (token, line) in tokens {
+            if !rules.contains_key(token) {
+                self.diagnostic(
+                    *line,
+                    format!(
+                        "the token '{token}' has no lexer rule, so it is imported as an \
+                         external token, which needs a scanner"
+                    ),
+                );
+                externals.push(symbol(token));
+            }
+        }
+
+        let mut rules: IndexMap<String, Rule> = rules
+            .into_iter()
+            .map(|(name, mut rule)| {
+                rename_symbols(&mut rule, &renames);
+                (renames.get(&name).cloned().unwrap_or(name), rule)
+            })
+            .collect();
+        if let Some(start) = start.or(parser_start) {
+            make_start(&mut rules, &start);
+        }
+        for extra in &mut extras {
+            rename_symbols(extra, &renames);
+        }
+        let has_lexer = definitions.iter().any(RuleDefinition::is_lexer_rule);
+        let mut grammar = grammar(name, rules);
+        grammar.extras = has_lexer.then_some(extras);
+        grammar.externals = (!externals.is_empty()).then_some(externals);
+        Import {
+            grammar,
+            diagnostics: self.diagnostics,
+        }
+    }
+
+    /// Translates a parser rule, returning it and the alternatives lifted
+    /// out of it into rules of their own, by label.
+    fn parser_rule(
+        &mut self,
+        definition: &RuleDefinition,
+        taken: &HashSet<&str>,
+    ) -> (Rule, IndexMap<String, Vec<Rule>>) {
+        let name = definition.name.as_str();
+        let alternatives = &definition.alternatives;
+        let left_recursive = alternatives
+            .iter()
+            .any(|alternative| first_reference(&alternative.elements) == Some(name));
+        let mut members = Vec::new();
+        let mut lifted: IndexMap<String, Vec<Rule>> = IndexMap::new();
+        for (i, alternative) in alternatives.iter().enumerate() {
+            let mut body = self.sequence(&alternative.elements, alternative.line);
+            let starts = first_reference(&alternative.elements) == Some(name);
+            let ends = last_reference(&alternative.elements) == Some(name);
+            if left_recursive && (starts || ends) {
+                let rule_type = if alternative.right_associative {
+                    RuleType::PrecRight
+                } else if starts && ends {
+                    RuleType::PrecLeft
+                } else {
+                    RuleType::Prec
+                };
+                let precedence = i32::try_from(alternatives.len() - i).unwrap_or(i32::MAX);
+                body = Rule {
+                    value: Some(RuleValue::Integer(precedence)),
+                    ..wrap(rule_type, body)
+                };
+            }
+            match &alternative.label {
+                Some(label) if taken.contains(label.as_str()) => {
+                    self.diagnostic(
+                        alternative.line,
+                        format!(
+                            "the alternative label '{label}' is also the name of a rule, so \
+                             the alternative is left in '{name}'"
+                        ),
+                    );
+                    members.push(body);
+                }
+                Some(label) => {
+                    if !lifted.contains_key(label) {
+                        members.push(symbol(label));
+                    }
+                    lifted.entry(label.clone()).or_default().push(body);
+                }
+                None => members.push(body),
+            }
+        }
+        (choice(members), lifted)
+    }
+
+    fn sequence(&mut self, elements: &[Element], line: usize) -> Rule {
+        seq(elements
+            .iter()
+            .map(|element| self.syntax(element, line))
+            .collect())
+    }
+
+    /// Translates an element of a parser rule.
+    fn syntax(&mut self, element: &Element, line: usize) -> Rule {
+        match element {
+            Element::Literal(text) => match self.literal_tokens.get(text) {
+                Some(token) => symbol(token),
+                None => string(text),
+            },
+            Element::Reference(name) if name == "EOF" => blank(),
+            Element::Reference(name) => symbol(name),
+            Element::Set(_) | Element::Range(..) => {
+                self.diagnostic(
+                    line,
+                    "character sets belong in lexer rules, and are dropped from parser rules"
+                        .to_string(),
+                );
+                blank()
+            }
+            Element::Wildcard => {
+                self.diagnostic(
+                    line,
+                    "'.' in a parser rule matches any token, which has no equivalent, and is \
+                     dropped"
+                        .to_string(),
+                );
+                blank()
+            }
+            Element::Not(_) => {
+                self.diagnostic(
+                    line,
+                    "'~' in a parser rule matches any token but the ones given, which has no \
+                     equivalent, and is dropped"
+                        .to_string(),
+                );
+                blank()
+            }
+            Element::Block(alternatives) => choice(
+                alternatives
+                    .iter()
+                    .map(|alternative| self.sequence(&alternative.elements, alternative.line))
+                    .collect(),
+            ),
+            Element::Quantified(inner, quantifier, greedy) => {
+                if !greedy {
+                    self.non_greedy(line);
+                }
+                let inner = self.syntax(inner, line);
+                match quantifier {
+                    '?' => optional(inner),
+                    '*' => wrap(RuleType::Repeat, inner),
+                    _ => wrap(RuleType::Repeat1, inner),
+                }
+            }
+            Element::Labeled(label, inner) => field(label, self.syntax(inner, line)),
+            Element::Predicate => blank(),
+        }
+    }
+
+    fn non_greedy(&mut self, line: usize) {
+        self.diagnostic(
+            line,
+            "non-greedy loops are imported as greedy ones, which match as much as they can"
+                .to_string(),
+        );
+    }
+
+    /// Translates a lexer rule into a string if it is one literal, and a
+    /// pattern otherwise.
+    fn lexer_rule(&mut self, definition: &'a RuleDefinition) -> Rule {
+        if let [Alternative { elements, .. }] = &definition.alternatives[..] {
+            if let [Element::Literal(text)] = &elements[..] {
+                return string(text);
+            }
+        }
+        let mut stack = vec![definition.name.as_str()];
+        self.alternatives_regex(&definition.alternatives, &definition.name, &mut stack)
+            .map_or_else(blank, Regex::into_rule)
+    }
+
+    fn alternatives_regex(
+        &mut self,
+        alternatives: &'a [Alternative],
+        rule: &str,
+        stack: &mut Vec<&'a str>,
+    ) -> Option<Regex> {
+        let parts: Vec<Regex> = alternatives
+            .iter()
+            .filter_map(|alternative| {
+                let mut parts = Vec::new();
+                let mut elements = alternative.elements.iter().peekable();
+                while let Some(element) = elements.next() {
+                    // `.*? 'end'` matches up to the first `end`.
+                    if let Element::Quantified(inner, '*', false) = element {
+                        if let (Element::Wildcard, Some(Element::Literal(end))) =
+                            (&**inner, elements.peek())
+                        {
+                            if let Some(regex) = Regex::until(end) {
+                                parts.push(regex);
+                                elements.next();
+                                continue;
+                            }
+                        }
+                    }
+                    parts.extend(self.regex(element, alternative.line, rule, stack));
+                }
+                (!parts.is_empty() || alternative.elements.is_empty())
+                    .then(|| Regex::concatenation(parts))
+            })
+            .collect();
+        (!parts.is_empty()).then(|| Regex::alternation(parts))
+    }
+
+    /// Translates an element of a lexer rule, or reports why it can't be
+    /// and returns `None`.
+    fn regex(
+        &mut self,
+        element: &'a Element,
+        line: usize,
+        rule: &str,
+        stack: &mut Vec<&'a str>,
+    ) -> Option<Regex> {
+        match element {
+            Element::Literal(text) => Some(Regex::literal(text)),
+            Element::Set(body) => Some(Regex::class(body)),
+            Element::Range(start, end) => Some(Regex::class(&format!(
+                "{}-{}",
+                class_char(*start),
+                class_char(*end)
+            ))),
+            Element::Wildcard => Some(Regex::new("[\\s\\S]", Binding::Atom)),
+            Element::Not(inner) => {
+                let negated = self.regex(inner, line, rule, stack)?.negated();
+                if negated.is_none() {
+                    self.diagnostic(
+                        line,
+                        format!(
+                            "'~' in '{rule}' applies to more than single characters, and is \
+                             dropped"
+                        ),
+                    );
+                }
+                negated
+            }
+            Element::Reference(name) => {
+                if stack.contains(&name.as_str()) {
+                    self.diagnostic(
+                        line,
+                        format!("'{rule}' is recursive through '{name}', which a pattern can't be"),
+                    );
+                    return None;
+                }
+                let Some(definition) = self.lexer_rules.get(name.as_str()).copied() else {
+                    self.diagnostic(
+                        line,
+                        format!("'{rule}' refers to '{name}', which is not a lexer rule"),
+                    );
+                    return None;
+                };
+                stack.push(&definition.name);
+                let regex = self.alternatives_regex(&definition.alternatives, rule, stack);
+                stack.pop();
+                regex
+            }
+            Element::Block(alternatives) => self.alternatives_regex(alternatives, rule, stack),
+            Element::Quantified(inner, quantifier, greedy) => {
+                if !greedy {
+                    self.non_greedy(line);
+                }
+                let regex = self.regex(inner, line, rule, stack)?;
+                Some(regex.quantified(&quantifier.to_string()))
+            }
+            Element::Labeled(_, inner) => self.regex(inner, line, rule, stack),
+            Element::Predicate => None,
+        }
+    }
+}
+
+/// The rule an alternative starts with, if it starts with one.
+fn first_reference(elements: &[Element]) -> Option<&str> {
+    reference(elements.first()?)
+}
+
+/// The rule an alternative ends with, if it ends with one.
+fn last_reference(elements: &[Element]) -> Option<&str> {
+    reference(elements.last()?)
+}
+
+fn reference(element: &Element) -> Option<&str> {
+    match element {
+        Element::Reference(name) => Some(name),
+        Element::Labeled(_, inner) => reference(inner),
+        _ => None,
+    }
+}
+
+/// Whether the alternatives refer to `EOF`, as start rules do.
+fn refers_to_eof(alternatives: &[Alternative]) -> bool {
+    fn element_refers(element: &Element) -> bool {
+        match element {
+            Element::Reference(name) => name == "EOF",
+            Element::Not(inner) | Element::Quantified(inner, ..) | Element::Labeled(_, inner) => {
+                element_refers(inner)
+            }
+            Element::Block(alternatives) => refers_to_eof(alternatives),
+            _ => false,
+        }
+    }
+    alternatives
+        .iter()
+        .any(|alternative| alternative.elements.iter().any(element_refers))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPR: &str = r#"
+        grammar Expr;
+
+        options { language = Java; }
+        @header { package expr; }
+
+        // The program is a list of statements.
+        prog : stat* EOF ;
+        stat : expr ';'                  # printExpr
+             | id=ID '=' value=expr ';'  # assign
+             ;
+        expr : <assoc=right> expr '^' expr
+             | left=expr op=('*'|'/') right=expr
+             | left=expr op=('+'|'-') right=expr
+             | INT
+             | ID
+             | '(' expr ')'
+             | {isType()}? ID '(' ')'
+             ;
+
+        MUL : '*' ;
+        ID  : LETTER (LETTER | [0-9])* ;
+        INT : '0' | [1-9] DIGIT* ;
+        STRING : '\'' (~['\\\r\n] | '\\' .)* '\'' ;
+        COMMENT : '/*' .*? '*/' -> channel(HIDDEN) ;
+        DOCSTRING : '"""' .*? '"""' ;
+        WS  : [ \t\r\n]+ -> skip ;
+        fragment LETTER : [a-zA-Z_] ;
+        fragment DIGIT : '0'..'9' ;
+    "#;
+
+    #[test]
+    fn test_import() {
+        let Import {
+            grammar,
+            diagnostics,
+        } = import(EXPR).unwrap();
+        assert_eq!(grammar.name, "expr");
+        let names: Vec<&str> = grammar.rules.keys().map(String::as_str).collect();
+        assert_eq!(
+            names,
+            [
+                "prog",
+                "stat",
+                "printExpr",
+                "assign",
+                "expr",
+                "MUL",
+                "ID",
+                "INT",
+                "STRING",
+                "COMMENT",
+                "DOCSTRING",
+                "_WS"
+            ]
+        );
+        assert_eq!(
+            grammar.rules["assign"],
+            seq(vec![
+                field("id", symbol("ID")),
+                string("="),
+                field("value", symbol("expr")),
+                string(";"),
+            ])
+        );
+        let pattern = |name: &str| grammar.rules[name].value.clone();
+        let pattern = |name| match pattern(name) {
+            Some(RuleValue::String(pattern)) => pattern,
+            _ => panic!("{name} is not a pattern"),
+        };
+        assert_eq!(pattern("ID"), "[a-zA-Z_]([a-zA-Z_]|[0-9])*");
+        assert_eq!(pattern("INT"), "0|[1-9][0-9]*");
+        assert_eq!(pattern("STRING"), "'([^'\\\\\\r\\n]|\\\\[\\s\\S])*'");
+        assert_eq!(pattern("COMMENT"), "/\\*([^*]|\\*+[^*/])*\\*+/");
+        assert_eq!(pattern("_WS"), "[ \\t\\r\\n]+");
+        assert_eq!(grammar.extras, Some(vec![symbol("COMMENT"), symbol("_WS")]));
+
+        let messages: Vec<String> = diagnostics.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            [
+                "line 18: semantic predicates have no equivalent and are dropped",
+                "line 26: non-greedy loops are imported as greedy ones, which match as much \
+                 as they can",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_left_recursion_gets_precedence() {
+        let grammar = import(EXPR).unwrap().grammar;
+        let Rule { members, .. } = &grammar.rules["expr"];
+        let precedence = |rule: &Rule| (rule.rule_type, rule.precedence());
+        assert_eq!(precedence(&members[0]), (RuleType::PrecRight, Some(7)));
+        assert_eq!(precedence(&members[1]), (RuleType::PrecLeft, Some(6)));
+        assert_eq!(precedence(&members[2]), (RuleType::PrecLeft, Some(5)));
+        assert_eq!(members[3], symbol("INT"));
+        // '*' is the MUL token, as it would be in ANTLR.
+        let operator = &members[1].content.as_ref().unwrap().members[1];
+        assert_eq!(
+            operator,
+            &field("op", choice(vec![symbol("MUL"), string("/")]))
+        );
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        let error = import("grammar g;\nrule : 'a' ( 'b' ;").unwrap_err();
+        assert_eq!(error.to_string(), "expected ')' at line 2 column 18");
+        assert!(import("rule : 'a' ;").is_err());
+    }
+}
@@ -0,0 +1,918 @@
+//! pest grammar import.
+//!
+//! Rules become rules of the same name, with silent rules (`_{ }`) hidden
+//! behind a leading underscore. `WHITESPACE` and `COMMENT`, which pest
+//! tries between the parts of every non-atomic rule, become extras, which
+//! is what extras are in tree-sitter. They must be tokens: silent ones go
+//! into the extras as patterns, others stay rules the extras name, and one
+//! that can't be a pattern is left out of the extras. Atomic rules (`@{ }` and `${ }`)
+//! become tokens: patterns, with the rules they use inlined, or strings
+//! when they are a single literal. Tags (`#name = ...`) become fields, and
+//! builtins such as `ASCII_DIGIT` or `LETTER` become the classes they
+//! stand for.
+//!
+//! Choices in pest are ordered: the first alternative that matches wins,
+//! where tree-sitter's lexer takes the longest match and its parser keeps
+//! every alternative that fits. Choices where the order is visible, an
+//! alternative that can match nothing ahead of others or a string ahead
+//! of a longer one it starts, are reported. So are the stack operations,
+//! and lookaheads, which are dropped except in two idioms of atomic rules:
+//! `!x ~ ANY` becomes a negated class where `x` matches single characters,
+//! and `(!"*/" ~ ANY)* ~ "*/"` a pattern that stops at the first `*/`
+//! where the terminator is one character or two different ones.
+//!
+//! pest starts from whichever rule its caller names. Unless one is given,
+//! the start rule is one that no other rule uses, preferring one that
+//! matches from `SOI`, and the choice is reported as a note.
+
+use super::{
+    blank, bounded, choice, class_char, field, grammar, make_start, optional, rename_symbols, seq,
+    string, symbol, unescape, wrap, Binding, Import, ImportDiagnostic, ImportError, Regex, Scanner,
+};
+use crate::grammar::{Rule, RuleType};
+use crate::validate::Severity;
+use indexmap::IndexMap;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// The rules pest tries between the parts of non-atomic rules.
+const IMPLICIT_RULES: [&str; 2] = ["WHITESPACE", "COMMENT"];
+
+/// The stack operations, which have no equivalent.
+const STACK_OPERATIONS: [&str; 6] = ["PUSH", "POP", "POP_ALL", "PEEK", "PEEK_ALL", "DROP"];
+
+/// An expression, as written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    Str(String),
+    /// A string matched regardless of case, `^"..."`.
+    Insensitive(String),
+    Range(char, char),
+    Identifier(String),
+    Seq(Vec<Expr>),
+    Choice(Vec<Expr>),
+    Optional(Box<Expr>),
+    Repeat(Box<Expr>),
+    Repeat1(Box<Expr>),
+    /// An expression repeated between a minimum and a maximum number of
+    /// times, or with no maximum.
+    Bounded(Box<Expr>, u32, Option<u32>),
+    PositiveLookahead(Box<Expr>),
+    NegativeLookahead(Box<Expr>),
+    Push(Box<Expr>),
+    Tag(String, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Modifier {
+    Normal,
+    /// `_`: the rule makes no node.
+    Silent,
+    /// `@`: no implicit whitespace, and no nodes inside.
+    Atomic,
+    /// `$`: no implicit whitespace, but nodes inside.
+    CompoundAtomic,
+    /// `!`: implicit whitespace again inside an atomic rule.
+    NonAtomic,
+}
+
+/// How `WHITESPACE` or `COMMENT` is imported.
+enum Extra {
+    /// As a token in the extras, for a silent rule no other rule uses.
+    Inline(Rule),
+    /// As a token rule that the extras name.
+    Rule(Rule),
+    /// As a rule that is left out of the extras, since it can't be a token.
+    Dropped(Rule),
+}
+
+#[derive(Debug)]
+struct Definition {
+    name: String,
+    modifier: Modifier,
+    body: Expr,
+    line: usize,
+}
+
+/// Imports a pest grammar as a grammar called `name`, starting from the
+/// rule `start`, or from one chosen as the module describes.
+///
+/// # Errors
+///
+/// Returns an [`ImportError`] if `source` is not a valid pest grammar.
+pub fn import(name: &str, source: &str, start: Option<&str>) -> Result<Import, ImportError> {
+    let mut parser = Parser {
+        scanner: Scanner::new(source, true),
+    };
+    let mut definitions = Vec::new();
+    while !parser.scanner.at_end() {
+        definitions.push(parser.definition()?);
+    }
+    let translator = Translator {
+        definitions: definitions
+            .iter()
+            .map(|definition| (definition.name.as_str(), definition))
+            .collect(),
+        diagnostics: Vec::new(),
+    };
+    Ok(translator.translate(name, &definitions, start))
+}
+
+struct Parser<'a> {
+    scanner: Scanner<'a>,
+}
+
+impl Parser<'_> {
+    fn definition(&mut self) -> Result<Definition, ImportError> {
+        self.scanner.skip_trivia();
+        let line = self.scanner.line();
+        let name = self.scanner.expect_identifier()?.to_string();
+        self.scanner.expect("=")?;
+        let modifier = match self.scanner.peek() {
+            Some('_') => Modifier::Silent,
+            Some('@') => Modifier::Atomic,
+            Some('$') => Modifier::CompoundAtomic,
+            Some('!') => Modifier::NonAtomic,
+            _ => Modifier::Normal,
+        };
+        if modifier != Modifier::Normal {
+            self.scanner.bump();
+        }
+        self.scanner.expect("{")?;
+        let body = self.choice()?;
+        self.scanner.expect("}")?;
+        Ok(Definition {
+            name,
+            modifier,
+            body,
+            line,
+        })
+    }
+
+    fn choice(&mut self) -> Result<Expr, ImportError> {
+        self.scanner.eat("|");
+        let mut alternatives = vec![self.sequence()?];
+        while self.scanner.eat("|") {
+            alternatives.push(self.sequence()?);
+        }
+        Ok(if alternatives.len() == 1 {
+            alternatives.remove(0)
+        } else {
+            Expr::Choice(alternatives)
+        })
+    }
+
+    fn sequence(&mut self) -> Result<Expr, ImportError> {
+        let mut parts = vec![self.prefixed()?];
+        while self.scanner.eat("~") {
+            parts.push(self.prefixed()?);
+        }
+        Ok(if parts.len() == 1 {
+            parts.remove(0)
+        } else {
+            Expr::Seq(parts)
+        })
+    }
+
+    fn prefixed(&mut self) -> Result<Expr, ImportError> {
+        if self.scanner.eat("#") {
+            let tag = self.scanner.expect_identifier()?.to_string();
+            self.scanner.expect("=")?;
+            return Ok(Expr::Tag(tag, Box::new(self.prefixed()?)));
+        }
+        if self.scanner.eat("&") {
+            return Ok(Expr::PositiveLookahead(Box::new(self.prefixed()?)));
+        }
+        if self.scanner.eat("!") {
+            return Ok(Expr::NegativeLookahead(Box::new(self.prefixed()?)));
+        }
+        let mut expr = self.term()?;
+        loop {
+            expr = match self.scanner.peek() {
+                Some('?') => Expr::Optional(Box::new(expr)),
+                Some('*') => Expr::Repeat(Box::new(expr)),
+                Some('+') => Expr::Repeat1(Box::new(expr)),
+                Some('{') => {
+                    let (min, max) = self.bounds()?;
+                    Expr::Bounded(Box::new(expr), min, max)
+                }
+                _ => return Ok(expr),
+            };
+            if !matches!(expr, Expr::Bounded(..)) {
+                self.scanner.bump();
+            }
+        }
+    }
+
+    /// Reads `{n}`, `{n,}`, `{,m}` or `{n,m}`.
+    fn bounds(&mut self) -> Result<(u32, Option<u32>), ImportError> {
+        let bounds = self.scanner.balanced('{', '}')?;
+        let number = |text: &str| text.trim().parse::<u32>().ok();
+        let parsed = match bounds.split_once(',') {
+            None => number(bounds).map(|n| (n, Some(n))),
+            Some((min, max)) if max.trim().is_empty() => number(min).map(|min| (min, None)),
+            Some((min, max)) if min.trim().is_empty() => number(max).map(|max| (0, Some(max))),
+            Some((min, max)) => number(min)
+                .zip(number(max))
+                .map(|(min, max)| (min, Some(max))),
+        };
+        parsed.ok_or_else(|| self.scanner.error("expected repetition bounds"))
+    }
+
+    fn term(&mut self) -> Result<Expr, ImportError> {
+        match self.scanner.peek() {
+            Some('(') => {
+                self.scanner.bump();
+                let expr = self.choice()?;
+                self.scanner.expect(")")?;
+                Ok(expr)
+            }
+            Some('"') => Ok(Expr::Str(unescape(self.scanner.quoted('"')?))),
+            Some('^') => {
+                self.scanner.bump();
+                if self.scanner.peek() != Some('"') {
+                    return Err(self.scanner.error("expected a string"));
+                }
+                Ok(Expr::Insensitive(unescape(self.scanner.quoted('"')?)))
+            }
+            Some('\'') => {
+                let start = self.character()?;
+                self.scanner.expect("..")?;
+                let end = self.character()?;
+                Ok(Expr::Range(start, end))
+            }
+            _ => {
+                let name = self.scanner.expect_identifier()?.to_string();
+                if name == "PUSH" && self.scanner.eat("(") {
+                    let expr = self.choice()?;
+                    self.scanner.expect(")")?;
+                    return Ok(Expr::Push(Box::new(expr)));
+                }
+                if name == "PEEK" && self.scanner.peek() == Some('[') {
+                    self.scanner.balanced('[', ']')?;
+                }
+                Ok(Expr::Identifier(name))
+            }
+        }
+    }
+
+    fn character(&mut self) -> Result<char, ImportError> {
+        if self.scanner.peek() != Some('\'') {
+            return Err(self.scanner.error("expected a character"));
+        }
+        let mut chars = unescape(self.scanner.quoted('\'')?)
+            .chars()
+            .collect::<Vec<_>>();
+        match chars.len() {
+            1 => Ok(chars.remove(0)),
+            _ => Err(self.scanner.error("expected a single character")),
+        }
+    }
+}
+
+struct Translator<'a> {
+    definitions: HashMap<&'a str, &'a Definition>,
+    diagnostics: Vec<ImportDiagnostic>,
+}
+
+impl<'a> Translator<'a> {
+    fn diagnostic(&mut self, line: usize, message: String) {
+        self.diagnostics.push(ImportDiagnostic {
+            severity: Severity::Warning,
+            line: Some(line),
+            message,
+        });
+    }
+
+    fn translate(
+        mut self,
+        name: &str,
+        definitions: &'a [Definition],
+        start: Option<&str>,
+    ) -> Import {
+        let start = self.start(definitions, start);
+        let mut renames = HashMap::new();
+        for definition in definitions {
+            let hidden = format!("_{}", definition.name);
+            if definition.modifier == Modifier::Silent
+                && !definition.name.starts_with('_')
+                && !self.definitions.contains_key(hidden.as_str())
+            {
+                renames.insert(definition.name.clone(), hidden);
+            }
+        }
+
+        let mut rules = IndexMap::new();
+        let mut extras = Vec::new();
+        for definition in definitions {
+            self.check_choices(&definition.body, definition);
+            let rule = if IMPLICIT_RULES.contains(&definition.name.as_str()) {
+                match self.extra(definition, definitions) {
+                    Extra::Inline(token) => {
+                        extras.push(token);
+                        continue;
+                    }
+                    Extra::Rule(rule) => {
+                        let name = renames.get(&definition.name).unwrap_or(&definition.name);
+                        extras.push(symbol(name));
+                        rule
+                    }
+                    Extra::Dropped(rule) => rule,
+                }
+            } else if matches!(
+                definition.modifier,
+                Modifier::Atomic | Modifier::CompoundAtomic
+            ) {
+                self.token(definition)
+            } else {
+                self.syntax(&definition.body, definition.line)
+            };
+            rules.insert(definition.name.clone(), rule);
+        }
+
+        let mut rules: IndexMap<String, Rule> = rules
+            .into_iter()
+            .map(|(name, mut rule)| {
+                rename_symbols(&mut rule, &renames);
+                (renames.get(&name).cloned().unwrap_or(name), rule)
+            })
+            .collect();
+        if let Some(start) = start {
+            make_start(&mut rules, renames.get(start).map_or(start, String::as_str));
+        }
+
+        let mut grammar = grammar(name, rules);
+        grammar.extras = Some(extras);
+        Import {
+            grammar,
+            diagnostics: self.diagnostics,
+        }
+    }
+
+    /// The rule to start from: `requested` if there is such a rule, and
+    /// otherwise one no other rule uses, reported as a note.
+    fn start(&mut self, definitions: &'a [Definition], requested: Option<&str>) -> Option<&'a str> {
+        if let Some(requested) = requested {
+            if let Some(definition) = self.definitions.get(requested) {
+                return Some(&definition.name);
+            }
+            self.diagnostics.push(ImportDiagnostic {
+                severity: Severity::Warning,
+                line: None,
+                message: format!("there is no rule '{requested}' to start from"),
+            });
+        }
+        let unused: Vec<&Definition> = definitions
+            .iter()
+            .filter(|definition| {
+                !IMPLICIT_RULES.contains(&definition.name.as_str())
+                    && !definitions.iter().any(|other| {
+                        other.name != definition.name && mentions(&other.body, &definition.name)
+                    })
+            })
+            .collect();
+        let from_soi = |definition: &&Definition| mentions(&definition.body, "SOI");
+        let (start, reason) = if let Some(start) = unused.iter().copied().find(from_soi) {
+            (start, "no other rule uses it and it matches from SOI")
+        } else if let Some(start) = unused.first().copied() {
+            (start, "no other rule uses it")
+        } else {
+            let start = definitions.iter().find(from_soi).or_else(|| {
+                definitions
+                    .iter()
+                    .find(|definition| !IMPLICIT_RULES.contains(&definition.name.as_str()))
+            })?;
+            (start, "every rule is used by another and it comes first")
+        };
+        let others: Vec<String> = unused
+            .iter()
+            .filter(|definition| definition.name != start.name)
+            .map(|definition| format!("'{}'", definition.name))
+            .collect();
+        let mut message = format!("'{}' is the start rule, as {reason}", start.name);
+        if !others.is_empty() {
+            let _ = write!(
+                message,
+                "; {} {} not used by other rules either",
+                others.join(", "),
+                if others.len() == 1 { "is" } else { "are" }
+            );
+        }
+        self.diagnostics.push(ImportDiagnostic {
+            severity: Severity::Note,
+            line: Some(start.line),
+            message,
+        });
+        Some(&start.name)
+    }
+
+    /// Translates `WHITESPACE` or `COMMENT`, which extras can only hold as
+    /// tokens.
+    fn extra(&mut self, definition: &'a Definition, definitions: &[Definition]) -> Extra {
+        let mut stack = vec![definition.name.as_str()];
+        let token = match &definition.body {
+            Expr::Str(text) => Ok(string(text)),
+            body => self.regex(body, &mut stack).map(Regex::into_rule),
+        };
+        match token {
+            Ok(token)
+                if definition.modifier == Modifier::Silent
+                    && !definitions.iter().any(|other| {
+                        other.name != definition.name && mentions(&other.body, &definition.name)
+                    }) =>
+            {
+                Extra::Inline(token)
+            }
+            Ok(token) => Extra::Rule(token),
+            Err(reason) => {
+                self.diagnostic(
+                    definition.line,
+                    format!(
+                        "'{}' can't be a token, as {reason}, so it is left out of the extras",
+                        definition.name
+                    ),
+                );
+                Extra::Dropped(self.syntax(&definition.body, definition.line))
+            }
+        }
+    }
+
+    /// Reports the choices in `expr` whose order decides what they match.
+    fn check_choices(&mut self, expr: &Expr, definition: &Definition) {
+        let rule = &definition.name;
+        if let Expr::Choice(alternatives) = expr {
+            let last = alternatives.len() - 1;
+            for (i, alternative) in alternatives[..last].iter().enumerate() {
+                if nullable(alternative) {
+                    self.diagnostic(
+                        definition.line,
+                        format!(
+                            "alternative {} of a choice in '{rule}' can match nothing, so pest \
+                             never tries the ones after it, but tree-sitter does",
+                            i + 1
+                        ),
+                    );
+                }
+                let Expr::Str(shorter) = alternative else {
+                    continue;
+                };
+                for later in &alternatives[i + 1..] {
+                    if let Expr::Str(longer) = later {
+                        if longer.len() > shorter.len() && longer.starts_with(shorter.as_str()) {
+                            self.diagnostic(
+                                definition.line,
+                                format!(
+                                    "\"{shorter}\" comes before \"{longer}\" in a choice in \
+                                     '{rule}', so pest matches \"{shorter}\" where tree-sitter \
+                                     matches \"{longer}\""
+                                ),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+        for child in children(expr) {
+            self.check_choices(child, definition);
+        }
+    }
+
+    /// Translates an expression of a non-atomic rule.
+    fn syntax(&mut self, expr: &Expr, line: usize) -> Rule {
+        match expr {
+            Expr::Str(text) => string(text),
+            Expr::Insensitive(text) => Rule {
+                flags: Some("i".to_string()),
+                ..Regex::literal(text).into_rule()
+            },
+            Expr::Range(start, end) => range(*start, *end).into_rule(),
+            Expr::Identifier(name) if self.definitions.contains_key(name.as_str()) => symbol(name),
+            Expr::Identifier(name) if matches!(name.as_str(), "SOI" | "EOI") => blank(),
+            Expr::Identifier(name) if STACK_OPERATIONS.contains(&name.as_str()) => {
+                self.stack_operation(name, line);
+                blank()
+            }
+            Expr::Identifier(name) => builtin(name).map_or_else(|| symbol(name), Regex::into_rule),
+            Expr::Seq(parts) => seq(parts.iter().map(|part| self.syntax(part, line)).collect()),
+            Expr::Choice(alternatives) => choice(
+                alternatives
+                    .iter()
+                    .map(|alternative| self.syntax(alternative, line))
+                    .collect(),
+            ),
+            Expr::Optional(inner) => optional(self.syntax(inner, line)),
+            Expr::Repeat(inner) => wrap(RuleType::Repeat, self.syntax(inner, line)),
+            Expr::Repeat1(inner) => wrap(RuleType::Repeat1, self.syntax(inner, line)),
+            Expr::Bounded(inner, min, max) => bounded(&self.syntax(inner, line), *min, *max),
+            Expr::PositiveLookahead(_) | Expr::NegativeLookahead(_) => {
+                self.diagnostic(
+                    line,
+                    "lookaheads have no equivalent outside tokens, and are dropped".to_string(),
+                );
+                blank()
+            }
+            Expr::Push(inner) => {
+                self.stack_operation("PUSH", line);
+                self.syntax(inner, line)
+            }
+            Expr::Tag(tag, inner) => field(tag, self.syntax(inner, line)),
+        }
+    }
+
+    fn stack_operation(&mut self, name: &str, line: usize) {
+        self.diagnostic(
+            line,
+            format!("the stack operation {name} has no equivalent, and is dropped"),
+        );
+    }
+
+    /// Translates an atomic rule into a string or a pattern, or reports
+    /// why it can't be one and translates it as a non-atomic rule.
+    fn token(&mut self, definition: &'a Definition) -> Rule {
+        if let Expr::Str(text) = &definition.body {
+            return string(text);
+        }
+        let mut stack = vec![definition.name.as_str()];
+        match self.regex(&definition.body, &mut stack) {
+            Ok(regex) => regex.into_rule(),
+            Err(reason) => {
+                self.diagnostic(
+                    definition.line,
+                    format!(
+                        "'{}' is atomic, but {reason}, so it is imported as a rule that \
+                         extras may appear inside",
+                        definition.name
+                    ),
+                );
+                self.syntax(&definition.body, definition.line)
+            }
+        }
+    }
+
+    /// Translates an expression of an atomic rule, or says why it can't
+    /// be a pattern.
+    fn regex(&mut self, expr: &'a Expr, stack: &mut Vec<&'a str>) -> Result<Regex, String> {
+        Ok(match expr {
+            Expr::Str(text) => Regex::literal(text),
+            Expr::Insensitive(text) => Regex::new(
+                format!("(?i:{})", Regex::literal(text).source),
+                Binding::Atom,
+            ),
+            Expr::Range(start, end) => range(*start, *end),
+            Expr::Identifier(name) => {
+                if let Some(definition) = self.definitions.get(name.as_str()).copied() {
+                    if stack.contains(&name.as_str()) {
+                        return Err(format!("it is recursive through '{name}'"));
+                    }
+                    stack.push(&definition.name);
+                    let regex = self.regex(&definition.body, stack);
+                    stack.pop();
+                    regex?
+                } else if matches!(name.as_str(), "SOI" | "EOI") {
+                    Regex::literal("")
+                } else {
+                    builtin(name).ok_or_else(|| format!("it uses {name}"))?
+                }
+            }
+            Expr::Seq(parts) => {
+                let mut regexes = Vec::new();
+                let mut parts = parts.iter().peekable();
+                while let Some(part) = parts.next() {
+                    // `(!"end" ~ ANY)* ~ "end"` matches up to the first `end`.
+                    if let (Some(end), Some(Expr::Str(next))) = (until(part), parts.peek()) {
+                        if end == next {
+                            if let Some(regex) = Regex::until(end) {
+                                regexes.push(regex);
+                                parts.next();
+                                continue;
+                            }
+                        }
+                    }
+                    // `!x ~ ANY` is any character but `x`.
+                    if let Expr::NegativeLookahead(excluded) = part {
+                        if parts
+                            .next_if_eq(&&Expr::Identifier("ANY".to_string()))
+                            .is_some()
+                        {
+                            regexes.push(
+                                self.regex(excluded, stack)?
+                                    .negated()
+                                    .ok_or("it excludes more than single characters")?,
+                            );
+                            continue;
+                        }
+                    }
+                    regexes.push(self.regex(part, stack)?);
+                }
+                Regex::concatenation(regexes)
+            }
+            Expr::Choice(alternatives) => Regex::alternation(
+                alternatives
+                    .iter()
+                    .map(|alternative| self.regex(alternative, stack))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Expr::Optional(inner) => self.regex(inner, stack)?.quantified("?"),
+            Expr::Repeat(inner) => self.regex(inner, stack)?.quantified("*"),
+            Expr::Repeat1(inner) => self.regex(inner, stack)?.quantified("+"),
+            Expr::Bounded(inner, min, max) => {
+                let bounds = match max {
+                    Some(max) if max == min => format!("{{{min}}}"),
+                    Some(max) => format!("{{{min},{max}}}"),
+                    None => format!("{{{min},}}"),
+                };
+                self.regex(inner, stack)?.quantified(&bounds)
+            }
+            Expr::PositiveLookahead(_) | Expr::NegativeLookahead(_) => {
+                return Err("it uses a lookahead".to_string())
+            }
+            Expr::Push(_) => return Err("it uses the stack".to_string()),
+            Expr::Tag(_, inner) => self.regex(inner, stack)?,
+        })
+    }
+}
+
+/// The string a `(!"end" ~ ANY)*` loop runs up to.
+fn until(expr: &Expr) -> Option<&str> {
+    let Expr::Repeat(inner) = expr else {
+        return None;
+    };
+    match &**inner {
+        Expr::Seq(parts) => match &parts[..] {
+            [Expr::NegativeLookahead(excluded), Expr::Identifier(any)] if any == "ANY" => {
+                match &**excluded {
+                    Expr::Str(end) => Some(end),
+                    _ => None,
+                }
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn range(start: char, end: char) -> Regex {
+    Regex::class(&format!("{}-{}", class_char(start), class_char(end)))
+}
+
+/// The pattern a builtin rule stands for.
+fn builtin(name: &str) -> Option<Regex> {
+    let class = match name {
+        "ANY" => return Some(Regex::new("[\\s\\S]", Binding::Atom)),
+        "NEWLINE" => {
+            // Excluding a line break's first character excludes them all.
+            return Some(Regex {
+                class: Some("\\n\\r".to_string()),
+                ..Regex::new("\\n|\\r\\n|\\r", Binding::Alternation)
+            });
+        }
+        "ASCII_DIGIT" => "0-9",
+        "ASCII_NONZERO_DIGIT" => "1-9",
+        "ASCII_BIN_DIGIT" => "01",
+        "ASCII_OCT_DIGIT" => "0-7",
+        "ASCII_HEX_DIGIT" => "0-9a-fA-F",
+        "ASCII_ALPHA_LOWER" => "a-z",
+        "ASCII_ALPHA_UPPER" => "A-Z",
+        "ASCII_ALPHA" => "a-zA-Z",
+        "ASCII_ALPHANUMERIC" => "a-zA-Z0-9",
+        "ASCII" => "\\u0000-\\u007F",
+        "LETTER" => "\\p{L}",
+        "LOWERCASE_LETTER" => "\\p{Ll}",
+        "UPPERCASE_LETTER" => "\\p{Lu}",
+        "NUMBER" => "\\p{N}",
+        "DECIMAL_NUMBER" => "\\p{Nd}",
+        "PUNCTUATION" => "\\p{P}",
+        "SYMBOL" => "\\p{S}",
+        "SEPARATOR" => "\\p{Z}",
+        "ALPHABETIC" => "\\p{Alphabetic}",
+        "WHITE_SPACE" => "\\p{White_Space}",
+        "XID_START" => "\\p{XID_Start}",
+        "XID_CONTINUE" => "\\p{XID_Continue}",
+        _ => return None,
+    };
+    Some(Regex::class(class))
+}
+
+fn children(expr: &Expr) -> Vec<&Expr> {
+    match expr {
+        Expr::Seq(parts) | Expr::Choice(parts) => parts.iter().collect(),
+        Expr::Optional(inner)
+        | Expr::Repeat(inner)
+        | Expr::Repeat1(inner)
+        | Expr::Bounded(inner, ..)
+        | Expr::PositiveLookahead(inner)
+        | Expr::NegativeLookahead(inner)
+        | Expr::Push(inner)
+        | Expr::Tag(_, inner) => vec![inner],
+        Expr::Str(_) | Expr::Insensitive(_) | Expr::Range(..) | Expr::Identifier(_) => Vec::new(),
+    }
+}
+
+/// Whether an expression can succeed without consuming anything, not
+/// following the rules it refers to.
+fn nullable(expr: &Expr) -> bool {
+    match expr {
+        Expr::Str(text) | Expr::Insensitive(text) => text.is_empty(),
+        Expr::Identifier(name) => matches!(name.as_str(), "SOI" | "EOI"),
+        Expr::Optional(_)
+        | Expr::Repeat(_)
+        | Expr::Bounded(_, 0, _)
+        | Expr::PositiveLookahead(_)
+        | Expr::NegativeLookahead(_) => true,
+        Expr::Seq(parts) => parts.iter().all(nullable),
+        Expr::Choice(parts) => parts.iter().any(nullable),
+        Expr::Repeat1(inner)
+        | Expr::Bounded(inner, ..)
+        | Expr::Push(inner)
+        | Expr::Tag(_, inner) => nullable(inner),
+        Expr::Range(..) => false,
+    }
+}
+
+/// Whether an expression refers to the rule or builtin `name`.
+fn mentions(expr: &Expr, name: &str) -> bool {
+    matches!(expr, Expr::Identifier(identifier) if identifier == name)
+        || children(expr)
+            .into_iter()
+            .any(|child| mentions(child, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::RuleValue;
+
+    const CALC: &str = r##"
+        // A calculator.
+        WHITESPACE = _{ " " | "\t" | NEWLINE }
+        COMMENT = _{ "/*" ~ (!"*/" ~ ANY)* ~ "*/" | "#" ~ (!NEWLINE ~ ANY)* }
+
+        number = @{ "-"? ~ ASCII_DIGIT+ ~ ("." ~ ASCII_DIGIT{1,3})? }
+        ident = @{ (ASCII_ALPHA | "_") ~ (ASCII_ALPHANUMERIC | "_")* }
+        operator = _{ "*" | "**" | "+" | "-" }
+        expr = { term ~ (operator ~ term)* }
+        term = _{ number | ident | "(" ~ expr ~ ")" }
+        call = { #function = ident ~ "(" ~ (expr ~ ("," ~ expr)*)? ~ ")" }
+        program = { SOI ~ (expr ~ ";")* ~ EOI }
+        statement = { !"end" ~ expr }
+    "##;
+
+    fn pattern(rule: &Rule) -> &str {
+        match &rule.value {
+            Some(RuleValue::String(pattern)) if rule.rule_type == RuleType::Pattern => pattern,
+            _ => panic!("{rule:?} is not a pattern"),
+        }
+    }
+
+    #[test]
+    fn test_import() {
+        let Import {
+            grammar,
+            diagnostics,
+        } = import("calc", CALC, None).unwrap();
+        let names: Vec<&str> = grammar.rules.keys().map(String::as_str).collect();
+        assert_eq!(
+            names,
+            [
+                "program",
+                "number",
+                "ident",
+                "_operator",
+                "expr",
+                "_term",
+                "call",
+                "statement"
+            ]
+        );
+        let extras = grammar.extras.as_deref().unwrap();
+        assert_eq!(pattern(&extras[0]), " |\\t|\\n|\\r\\n|\\r");
+        assert_eq!(
+            pattern(&grammar.rules["number"]),
+            "-?[0-9]+(\\.[0-9]{1,3})?"
+        );
+        assert_eq!(
+            pattern(&grammar.rules["ident"]),
+            "([a-zA-Z]|_)([a-zA-Z0-9]|_)*"
+        );
+        assert_eq!(
+            grammar.rules["expr"],
+            seq(vec![
+                symbol("_term"),
+                wrap(
+                    RuleType::Repeat,
+                    seq(vec![symbol("_operator"), symbol("_term")])
+                ),
+            ])
+        );
+        assert_eq!(
+            grammar.rules["call"].members[0],
+            field("function", symbol("ident"))
+        );
+
+        assert_eq!(
+            pattern(&extras[1]),
+            "/\\*([^*]|\\*+[^*/])*\\*+/|#[^\\n\\r]*"
+        );
+
+        let messages: Vec<String> = diagnostics.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            [
+                "line 12: 'program' is the start rule, as no other rule uses it and it matches \
+                 from SOI; 'call', 'statement' are not used by other rules either",
+                "line 8: \"*\" comes before \"**\" in a choice in 'operator', so pest matches \
+                 \"*\" where tree-sitter matches \"**\"",
+                "line 13: lookaheads have no equivalent outside tokens, and are dropped",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_negated_classes() {
+        let Import {
+            grammar,
+            diagnostics,
+        } = import(
+            "lines",
+            "line = @{ (!(NEWLINE | \"#\") ~ ANY)+ }\n\
+             file = { line* }\n\
+             block = @{ \"<!--\" ~ (!\"-->\" ~ ANY)* ~ \"-->\" }",
+            None,
+        )
+        .unwrap();
+        assert_eq!(pattern(&grammar.rules["line"]), "[^\\n\\r#]+");
+        assert_eq!(
+            diagnostics[1].to_string(),
+            "line 3: 'block' is atomic, but it excludes more than single characters, so it is \
+             imported as a rule that extras may appear inside"
+        );
+        assert_eq!(grammar.rules.keys().next().unwrap(), "file");
+    }
+
+    #[test]
+    fn test_start_rule() {
+        let source = "WHITESPACE = _{ \" \" }\n\
+                      item = { ASCII_DIGIT }\n\
+                      list = { item+ }\n\
+                      pair = { item ~ item }";
+        let Import {
+            grammar,
+            diagnostics,
+        } = import("items", source, None).unwrap();
+        assert_eq!(grammar.rules.keys().next().unwrap(), "list");
+        assert_eq!(diagnostics[0].severity, Severity::Note);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "line 3: 'list' is the start rule, as no other rule uses it; 'pair' is not used by \
+             other rules either"
+        );
+
+        let Import {
+            grammar,
+            diagnostics,
+        } = import("items", source, Some("pair")).unwrap();
+        assert_eq!(grammar.rules.keys().next().unwrap(), "pair");
+        assert!(diagnostics.is_empty());
+
+        let Import { diagnostics, .. } = import("items", source, Some("pairs")).unwrap();
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "there is no rule 'pairs' to start from"
+        );
+    }
+
+    #[test]
+    fn test_extras_are_tokens() {
+        let Import {
+            grammar,
+            diagnostics,
+        } = import(
+            "html",
+            "WHITESPACE = { \" \" }\n\
+             COMMENT = _{ \"<!--\" ~ (!\"-->\" ~ ANY)* ~ \"-->\" }\n\
+             document = { SOI ~ ASCII_ALPHA* ~ EOI }",
+            None,
+        )
+        .unwrap();
+        // A rule that makes nodes stays one, and the extras name it.
+        assert_eq!(grammar.extras, Some(vec![symbol("WHITESPACE")]));
+        assert_eq!(grammar.rules["WHITESPACE"], string(" "));
+        assert_eq!(
+            diagnostics[1].to_string(),
+            "line 2: 'COMMENT' can't be a token, as it excludes more than single characters, \
+             so it is left out of the extras"
+        );
+    }
+
+    #[test]
+    fn test_imported_grammar_parses() {
+        let Import { grammar, .. } = import("calc", CALC, None).unwrap();
+        let language = crate::tables::build(&grammar).unwrap().into_language();
+        let tree = crate::Parser::new(&language).parse("1 + x; # one\n(2 ** y) ;");
+        assert_eq!(
+            tree.to_sexp(),
+            "(program (expr (number) (ident)) (expr (expr (number) (ident))))"
+        );
+    }
+}
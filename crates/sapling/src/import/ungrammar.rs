@@ -0,0 +1,242 @@
+//! ungrammar import.
+//!
+//! Nodes become rules of the same name, labels (`name:Node`) become
+//! fields, and `?` and `*` become optional and repeated parts. A node that
+//! is nothing but a choice between other nodes, such as rust-analyzer's
+//! `Item`, makes no node of its own in the trees ungrammar describes, so it
+//! becomes a hidden rule and a supertype. The start rule is the first node
+//! no other node refers to.
+//!
+//! Ungrammar describes trees rather than text: its tokens, such as `'fn'`
+//! or `'ident'`, name kinds of token and are not defined anywhere. Each is
+//! imported as the string it names, which is right for keywords and
+//! punctuation, and this is reported, as kinds such as `'ident'` need
+//! patterns instead.
+
+use super::{
+    choice, field, first_unreferenced, grammar, make_start, optional, rename_symbols, seq, string,
+    symbol, wrap, Import, ImportDiagnostic, ImportError, Scanner,
+};
+use crate::grammar::{Rule, RuleType};
+use crate::validate::Severity;
+use indexmap::{IndexMap, IndexSet};
+use std::collections::HashMap;
+
+/// Imports an ungrammar as a grammar called `name`.
+///
+/// # Errors
+///
+/// Returns an [`ImportError`] if `source` is not a valid ungrammar.
+pub fn import(name: &str, source: &str) -> Result<Import, ImportError> {
+    let mut parser = Parser {
+        scanner: Scanner::new(source, false),
+        tokens: IndexSet::new(),
+    };
+    let mut rules = IndexMap::new();
+    while !parser.scanner.at_end() {
+        let node = parser.scanner.expect_identifier()?;
+        parser.scanner.expect("=")?;
+        let rule = parser.alternatives()?;
+        if rules.insert(node.to_string(), rule).is_some() {
+            return Err(parser.scanner.error(format!("'{node}' is defined twice")));
+        }
+    }
+
+    let start = first_unreferenced(&rules).map(str::to_string);
+    let mut renames = HashMap::new();
+    let mut supertypes = Vec::new();
+    for (node, rule) in &rules {
+        let hidden = format!("_{node}");
+        if rule.rule_type == RuleType::Choice
+            && rule
+                .members
+                .iter()
+                .all(|member| member.rule_type == RuleType::Symbol)
+            && !rules.contains_key(&hidden)
+        {
+            supertypes.push(hidden.clone());
+            renames.insert(node.clone(), hidden);
+        }
+    }
+    let mut rules: IndexMap<String, Rule> = rules
+        .into_iter()
+        .map(|(node, mut rule)| {
+            rename_symbols(&mut rule, &renames);
+            (renames.get(&node).cloned().unwrap_or(node), rule)
+        })
+        .collect();
+    if let Some(start) = start {
+        make_start(&mut rules, renames.get(&start).unwrap_or(&start));
+    }
+
+    let mut diagnostics = Vec::new();
+    if !parser.tokens.is_empty() {
+        diagnostics.push(ImportDiagnostic {
+            severity: Severity::Warning,
+            line: None,
+            message: format!(
+                "ungrammar doesn't define its tokens, so its {} tokens are imported as the \
+                 strings they name; those that name kinds of token, such as identifiers or \
+                 numbers, need patterns instead",
+                parser.tokens.len()
+            ),
+        });
+    }
+    let mut grammar = grammar(name, rules);
+    grammar.supertypes = (!supertypes.is_empty()).then_some(supertypes);
+    Ok(Import {
+        grammar,
+        diagnostics,
+    })
+}
+
+struct Parser<'a> {
+    scanner: Scanner<'a>,
+    /// The tokens used, in the order they first appear.
+    tokens: IndexSet<String>,
+}
+
+impl Parser<'_> {
+    fn alternatives(&mut self) -> Result<Rule, ImportError> {
+        self.scanner.eat("|");
+        let mut alternatives = vec![self.sequence()?];
+        while self.scanner.eat("|") {
+            alternatives.push(self.sequence()?);
+        }
+        Ok(choice(alternatives))
+    }
+
+    fn sequence(&mut self) -> Result<Rule, ImportError> {
+        let mut parts = Vec::new();
+        while let Some(part) = self.atom()? {
+            parts.push(part);
+        }
+        if parts.is_empty() {
+            return Err(self.scanner.error("expected a node, a token or '('"));
+        }
+        Ok(seq(parts))
+    }
+
+    /// Reads an atom with its `?` or `*`, if one comes next: the start of
+    /// the next node's definition is not one.
+    fn atom(&mut self) -> Result<Option<Rule>, ImportError> {
+        let mut rule = match self.scanner.peek() {
+            Some('\'') => {
+                let token = self.token()?;
+                self.tokens.insert(token.clone());
+                string(&token)
+            }
+            Some('(') => {
+                self.scanner.bump();
+                let rule = self.alternatives()?;
+                self.scanner.expect(")")?;
+                rule
+            }
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                let start = self.scanner.offset;
+                let name = self.scanner.expect_identifier()?;
+                if self.scanner.peek() == Some('=') {
+                    self.scanner.offset = start;
+                    return Ok(None);
+                }
+                if self.scanner.eat(":") {
+                    return match self.atom()? {
+                        Some(rule) => Ok(Some(field(name, rule))),
+                        None => Err(self.scanner.error("expected something to label")),
+                    };
+                }
+                symbol(name)
+            }
+            _ => return Ok(None),
+        };
+        loop {
+            rule = match self.scanner.peek() {
+                Some('?') => optional(rule),
+                Some('*') => wrap(RuleType::Repeat, rule),
+                _ => return Ok(Some(rule)),
+            };
+            self.scanner.bump();
+        }
+    }
+
+    /// Reads a quoted token, in which `\'` and `\\` are escapes.
+    fn token(&mut self) -> Result<String, ImportError> {
+        let quoted = self.scanner.quoted('\'')?;
+        Ok(quoted.replace("\\'", "'").replace("\\\\", "\\"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UNGRAMMAR: &str = r"
+        // Items and expressions, as rust-analyzer writes them.
+        SourceFile =
+          Item*
+
+        Item =
+          Fn
+        | Const
+
+        Fn =
+          'fn' Name ParamList ('->' ret:Type)?
+          body:BlockExpr
+
+        Const = 'const' Name ':' Type '=' Expr ';'
+        ParamList = '(' (Name (',' Name)* ','?)? ')'
+        BlockExpr = '{' Expr? '}'
+        Name = 'ident'
+        Type = Name
+        Expr = 'int_number' | BlockExpr
+    ";
+
+    #[test]
+    fn test_import() {
+        let Import {
+            grammar,
+            diagnostics,
+        } = import("items", UNGRAMMAR).unwrap();
+        let names: Vec<&str> = grammar.rules.keys().map(String::as_str).collect();
+        assert_eq!(
+            names,
+            [
+                "SourceFile",
+                "_Item",
+                "Fn",
+                "Const",
+                "ParamList",
+                "BlockExpr",
+                "Name",
+                "Type",
+                "Expr"
+            ]
+        );
+        assert_eq!(grammar.supertypes, Some(vec!["_Item".to_string()]));
+        assert_eq!(
+            grammar.rules["SourceFile"],
+            wrap(RuleType::Repeat, symbol("_Item"))
+        );
+        assert_eq!(
+            grammar.rules["Fn"],
+            seq(vec![
+                string("fn"),
+                symbol("Name"),
+                symbol("ParamList"),
+                optional(seq(vec![string("->"), field("ret", symbol("Type"))])),
+                field("body", symbol("BlockExpr")),
+            ])
+        );
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0]
+            .message
+            .starts_with("ungrammar doesn't define its tokens, so its 13 tokens"));
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        let error = import("bad", "A = 'a'\nB = ( 'b'").unwrap_err();
+        assert_eq!(error.to_string(), "expected ')' at line 2 column 10");
+        assert!(import("bad", "A = 'a'\nA = 'b'").is_err());
+    }
+}
@@ -0,0 +1,394 @@
+//! Languages embedded in other languages.
+//!
+//! Markdown's fenced code, HTML's `<script>` elements and the bodies of Rust
+//! macros are written in a language other than the document's. A grammar's
+//! `injections.scm` says where: each match marks an `@injection.content`
+//! node, and names its language with an `@injection.language` capture or an
+//! `injection.language` property. The content is parsed on its own with
+//! [`Parser::set_included_ranges`], so the injected tree's positions are
+//! those of the whole document.
+//!
+//! - The children of a content node belong to the host language, and are
+//!   left out of the injected text, unless the pattern sets
+//!   `injection.include-children`.
+//! - A pattern that sets `injection.combined` gathers all of its matches
+//!   into one injected document, as for the pieces of a PHP template.
+//! - Injected languages can inject others in turn, to a depth of eight.
+//!
+//! A [`LayerSet`](crate::injection::LayerSet) holds the tree of each
+//! language in a document, and keeps them up to date as the document
+//! changes. Each change is first described to
+//! [`edit`](crate::injection::LayerSet::edit), which moves the layers after
+//! it and marks those it touches; [`update`](crate::injection::LayerSet::update)
+//! then reparses only the marked layers and those whose language or ranges
+//! changed. The document's own layer covers every change, so it is always
+//! reparsed after an edit.
+
+use crate::language::Language;
+use crate::lexer::WHOLE_DOCUMENT;
+use crate::parser::Parser;
+use crate::query::{Query, QueryCursor, QueryError, QueryMatch};
+use crate::tree::{InputEdit, Node, Range};
+use std::collections::HashMap;
+
+/// How deeply injected languages may nest.
+pub(crate) const MAX_INJECTION_DEPTH: usize = 8;
+
+/// A language and its injections query.
+#[derive(Debug, Clone)]
+pub struct InjectionConfiguration<'l> {
+    /// The language's name, as injections refer to it.
+    pub language_name: String,
+    language: &'l Language,
+    query: Option<Query>,
+}
+
+impl<'l> InjectionConfiguration<'l> {
+    /// Compiles the injections query for `language`. An empty query injects
+    /// nothing.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`QueryError`] if the query does not compile.
+    pub fn new(
+        language: &'l Language,
+        name: impl Into<String>,
+        injection_query: &str,
+    ) -> Result<Self, QueryError> {
+        let query = (!injection_query.trim().is_empty())
+            .then(|| Query::for_language(language, injection_query))
+            .transpose()?;
+        Ok(Self {
+            language_name: name.into(),
+            language,
+            query,
+        })
+    }
+
+    /// The language.
+    #[must_use]
+    pub fn language(&self) -> &'l Language {
+        self.language
+    }
+}
+
+/// Text in one document to be parsed as another language.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Injection {
+    /// The name of the injected language.
+    pub language: String,
+    /// The ranges of the document holding the injected text, in order.
+    pub ranges: Vec<Range>,
+}
+
+/// Runs an injections `query` over `tree`, the tree of the `ranges` of
+/// `source`, and returns what it injects, in document order.
+#[must_use]
+pub fn injections(query: &Query, tree: &Node, source: &[u8], ranges: &[Range]) -> Vec<Injection> {
+    let mut found = Vec::new();
+    // Combined injections, by pattern and language.
+    let mut combined: HashMap<(usize, String), usize> = HashMap::new();
+    for injection_match in QueryCursor::new().matches(query, tree, source) {
+        let Some((language, contents, settings)) = injection(query, &injection_match, source)
+        else {
+            continue;
+        };
+        let mut content_ranges = Vec::new();
+        for content in contents {
+            content_ranges.extend(intersect(
+                ranges,
+                &content_ranges_of(content, settings.include_children),
+            ));
+        }
+        if content_ranges.is_empty() {
+            continue;
+        }
+        if settings.combined {
+            let key = (injection_match.pattern_index, language);
+            if let Some(&index) = combined.get(&key) {
+                let injection: &mut Injection = &mut found[index];
+                injection.ranges.extend(content_ranges);
+                continue;
+            }
+            combined.insert(key.clone(), found.len());
+            found.push(Injection {
+                language: key.1,
+                ranges: content_ranges,
+            });
+        } else {
+            found.push(Injection {
+                language,
+                ranges: content_ranges,
+            });
+        }
+    }
+    for injection in &mut found {
+        injection.ranges.sort_by_key(|range| range.start_byte);
+        injection
+            .ranges
+            .dedup_by(|range, previous| range.start_byte < previous.end_byte);
+    }
+    found
+}
+
+/// The properties an injection pattern sets.
+#[derive(Default)]
+struct Settings {
+    include_children: bool,
+    combined: bool,
+}
+
+/// The language, content nodes and settings of one match of an injections
+/// query, if it names both a language and content.
+fn injection<'tree>(
+    query: &Query,
+    injection_match: &QueryMatch<'tree>,
+    source: &[u8],
+) -> Option<(String, Vec<&'tree Node>, Settings)> {
+    let names = query.capture_names();
+    let mut language = None;
+    let mut contents = Vec::new();
+    for capture in &injection_match.captures {
+        match names[capture.index].as_str() {
+            "injection.language" | "language" => {
+                language = capture.node.utf8_text(source).ok().map(str::to_string);
+            }
+            "injection.content" | "content" => contents.push(capture.node),
+            _ => {}
+        }
+    }
+    let mut settings = Settings::default();
+    for property in query.property_settings(injection_match.pattern_index) {
+        match property.key.as_str() {
+            "injection.language" if language.is_none() => language.clone_from(&property.value),
+            "injection.include-children" => settings.include_children = true,
+            "injection.combined" => settings.combined = true,
+            _ => {}
+        }
+    }
+    let language = language.filter(|language| !language.is_empty())?;
+    (!contents.is_empty()).then_some((language, contents, settings))
+}
+
+/// The parts of `content` that hold injected text: the whole node, or the
+/// text between its children.
+fn content_ranges_of(content: &Node, include_children: bool) -> Vec<Range> {
+    let range = content.range();
+    if include_children || content.children().is_empty() {
+        return vec![range];
+    }
+    let mut ranges = Vec::new();
+    let mut start = range.start();
+    for child in content.children() {
+        if child.start_byte() > start.start_byte {
+            ranges.push(Range::new(
+                start.start_byte,
+                child.start_byte(),
+                start.start_point,
+                child.start_position(),
+            ));
+        }
+        if child.end_byte() > start.start_byte {
+            start = child.range().end();
+        }
+    }
+    if range.end_byte > start.start_byte {
+        ranges.push(Range::new(
+            start.start_byte,
+            range.end_byte,
+            start.start_point,
+            range.end_point,
+        ));
+    }
+    ranges
+}
+
+/// The non-empty parts of `pieces` that lie within `ranges`.
+fn intersect(ranges: &[Range], pieces: &[Range]) -> Vec<Range> {
+    let mut parts = Vec::new();
+    for piece in pieces {
+        for range in ranges {
+            let (start_byte, start_point) = if piece.start_byte >= range.start_byte {
+                (piece.start_byte, piece.start_point)
+            } else {
+                (range.start_byte, range.start_point)
+            };
+            let (end_byte, end_point) = if piece.end_byte <= range.end_byte {
+                (piece.end_byte, piece.end_point)
+            } else {
+                (range.end_byte, range.end_point)
+            };
+            if start_byte < end_byte {
+                parts.push(Range::new(start_byte, end_byte, start_point, end_point));
+            }
+        }
+    }
+    parts
+}
+
+/// The tree of one language in a document.
+#[derive(Debug, Clone)]
+pub struct Layer<'l> {
+    language_name: String,
+    language: &'l Language,
+    ranges: Vec<Range>,
+    /// Whether an edit changed the text of `ranges` since the layer was
+    /// parsed.
+    edited: bool,
+    tree: Node,
+    depth: usize,
+}
+
+impl<'l> Layer<'l> {
+    /// The name of the layer's language.
+    #[must_use]
+    pub fn language_name(&self) -> &str {
+        &self.language_name
+    }
+
+    /// The layer's language.
+    #[must_use]
+    pub fn language(&self) -> &'l Language {
+        self.language
+    }
+
+    /// The ranges of the document the layer was parsed from.
+    #[must_use]
+    pub fn included_ranges(&self) -> &[Range] {
+        &self.ranges
+    }
+
+    /// The layer's tree.
+    #[must_use]
+    pub fn tree(&self) -> &Node {
+        &self.tree
+    }
+
+    /// How deeply the layer is injected: 0 for the document's own language.
+    #[must_use]
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Whether `byte` is in one of the layer's ranges.
+    #[must_use]
+    pub fn contains(&self, byte: usize) -> bool {
+        self.ranges
+            .iter()
+            .any(|range| range.start_byte <= byte && byte < range.end_byte)
+    }
+}
+
+/// The trees of every language in a document.
+#[derive(Debug, Clone, Default)]
+pub struct LayerSet<'l> {
+    layers: Vec<Layer<'l>>,
+}
+
+impl<'l> LayerSet<'l> {
+    /// Parses `source` as `config`'s language, then each language injected
+    /// into it, asking `injection_callback` for their configurations.
+    /// Injections of languages it has none for are left out.
+    pub fn parse<'c>(
+        config: &'c InjectionConfiguration<'l>,
+        source: &[u8],
+        injection_callback: impl FnMut(&str) -> Option<&'c InjectionConfiguration<'l>>,
+    ) -> Self
+    where
+        'l: 'c,
+    {
+        let mut layers = Self::default();
+        layers.update(config, source, injection_callback);
+        layers
+    }
+
+    /// Moves the layers to where their text is after `edit`, and marks
+    /// those whose text it changes to be parsed again by the next
+    /// [`update`](Self::update). Call it for each change to the document,
+    /// in the order they are made.
+    pub fn edit(&mut self, edit: &InputEdit) {
+        for layer in &mut self.layers {
+            layer.edited |= layer.ranges.iter().any(|range| {
+                range.start_byte < edit.old_end_byte && edit.start_byte < range.end_byte
+            });
+            for range in &mut layer.ranges {
+                range.edit(edit);
+            }
+            layer.tree.edit(edit);
+        }
+    }
+
+    /// Brings the layers up to date with `source`, once its changes have
+    /// been passed to [`edit`](Self::edit). Every layer that no edit touched
+    /// is reused if it is still injected with the same language and ranges.
+    /// Returns how many layers were parsed.
+    pub fn update<'c>(
+        &mut self,
+        config: &'c InjectionConfiguration<'l>,
+        source: &[u8],
+        mut injection_callback: impl FnMut(&str) -> Option<&'c InjectionConfiguration<'l>>,
+    ) -> usize
+    where
+        'l: 'c,
+    {
+        let mut previous = std::mem::take(&mut self.layers);
+        let mut parsed = 0;
+        // Layers still to build, taken depth first so each follows its host.
+        let mut pending = vec![(config, WHOLE_DOCUMENT.to_vec(), 0)];
+        while let Some((config, ranges, depth)) = pending.pop() {
+            let reused = previous.iter().position(|layer| {
+                !layer.edited
+                    && layer.language_name == config.language_name
+                    && layer.ranges == ranges
+            });
+            let layer = if let Some(index) = reused {
+                previous.swap_remove(index)
+            } else {
+                let mut parser = Parser::new(config.language);
+                if parser.set_included_ranges(&ranges).is_err() {
+                    continue;
+                }
+                parsed += 1;
+                Layer {
+                    language_name: config.language_name.clone(),
+                    language: config.language,
+                    tree: parser.parse(source),
+                    ranges,
+                    edited: false,
+                    depth,
+                }
+            };
+            if let (Some(query), true) = (&config.query, depth < MAX_INJECTION_DEPTH) {
+                let found = injections(query, &layer.tree, source, &layer.ranges);
+                for injection in found.into_iter().rev() {
+                    if let Some(injected) = injection_callback(&injection.language) {
+                        pending.push((injected, injection.ranges, depth + 1));
+                    }
+                }
+            }
+            self.layers.push(layer);
+        }
+        parsed
+    }
+
+    /// The layers, each after the layer it is injected into.
+    #[must_use]
+    pub fn layers(&self) -> &[Layer<'l>] {
+        &self.layers
+    }
+
+    /// The tree of the document's own language.
+    #[must_use]
+    pub fn root(&self) -> Option<&Node> {
+        self.layers.first().map(Layer::tree)
+    }
+
+    /// The most deeply injected layer holding `byte`.
+    #[must_use]
+    pub fn layer_at(&self, byte: usize) -> Option<&Layer<'l>> {
+        self.layers
+            .iter()
+            .filter(|layer| layer.contains(byte))
+            .max_by_key(|layer| layer.depth)
+    }
+}
@@ -0,0 +1,228 @@
+//! Chunked text input.
+//!
+//! Editors rarely hold a buffer as one contiguous string: ropes, piece tables
+//! and gap buffers all store text in pieces. Rather than flattening the buffer
+//! before every parse, Sapling reads source text through the [`Input`] trait,
+//! which hands out one chunk at a time starting from a requested byte offset.
+//!
+//! Any `FnMut(usize, Point) -> &[u8]` closure is an [`Input`], mirroring the
+//! callback form of tree-sitter's `ts_parser_parse` (closures may also return
+//! owned chunks such as `Vec<u8>`). With the `rope` feature enabled,
+//! `RopeInput` adapts a `ropey::Rope` directly.
+
+/// A position in a text document, as a zero-based row and byte column.
+///
+/// Columns count bytes, not characters, matching tree-sitter's `TSPoint`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Point {
+    /// The zero-based line number.
+    pub row: usize,
+    /// The zero-based byte offset from the start of the line.
+    pub column: usize,
+}
+
+impl Point {
+    /// Creates a point from a row and byte column.
+    #[must_use]
+    pub const fn new(row: usize, column: usize) -> Self {
+        Self { row, column }
+    }
+}
+
+impl std::fmt::Display for Point {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({}, {})", self.row, self.column)
+    }
+}
+
+/// A source of text that is read one chunk at a time.
+///
+/// The reader asks for the text beginning at `byte_offset`, whose position in
+/// the document is `position`, and consumes as much of the returned chunk as it
+/// needs before asking again. A chunk may be shorter than the remaining text,
+/// and may end in the middle of a UTF-8 sequence. An empty chunk signals the
+/// end of the input.
+pub trait Input {
+    /// The chunk type handed out by [`read`](Input::read), typically a
+    /// borrowed `&[u8]`.
+    type Chunk: AsRef<[u8]>;
+
+    /// Returns the text starting at `byte_offset`, or an empty chunk at the
+    /// end of the input.
+    fn read(&mut self, byte_offset: usize, position: Point) -> Self::Chunk;
+}
+
+impl<F, T> Input for F
+where
+    F: FnMut(usize, Point) -> T,
+    T: AsRef<[u8]>,
+{
+    type Chunk = T;
+
+    fn read(&mut self, byte_offset: usize, position: Point) -> T {
+        self(byte_offset, position)
+    }
+}
+
+impl<'a> Input for &'a [u8] {
+    type Chunk = &'a [u8];
+
+    fn read(&mut self, byte_offset: usize, _position: Point) -> &'a [u8] {
+        self.get(byte_offset..).unwrap_or_default()
+    }
+}
+
+impl<'a> Input for &'a str {
+    type Chunk = &'a [u8];
+
+    fn read(&mut self, byte_offset: usize, _position: Point) -> &'a [u8] {
+        self.as_bytes().get(byte_offset..).unwrap_or_default()
+    }
+}
+
+/// An [`Input`] that reads directly from the chunks of a [`ropey::Rope`].
+///
+/// Each read returns the remainder of the rope chunk containing the requested
+/// offset, so no text is copied.
+#[cfg(feature = "rope")]
+#[cfg_attr(docsrs, doc(cfg(feature = "rope")))]
+#[derive(Debug, Clone, Copy)]
+pub struct RopeInput<'a> {
+    rope: &'a ropey::Rope,
+}
+
+#[cfg(feature = "rope")]
+impl<'a> RopeInput<'a> {
+    /// Wraps a rope for reading.
+    #[must_use]
+    pub fn new(rope: &'a ropey::Rope) -> Self {
+        Self { rope }
+    }
+
+    /// Converts a byte offset into the [`Point`] at which it falls.
+    ///
+    /// Offsets past the end of the rope are clamped to its end. Editors can
+    /// use this to describe edits in the row/column form that tree-sitter
+    /// style APIs expect. Rows start after `\n` only, as they do in parsed
+    /// trees, although ropey also breaks lines at `\r` and Unicode line
+    /// separators.
+    #[must_use]
+    pub fn point_at(&self, byte_offset: usize) -> Point {
+        let byte_offset = byte_offset.min(self.rope.len_bytes());
+        let (mut row, mut line_start, mut chunk_start) = (0, 0, 0);
+        for chunk in self.rope.chunks() {
+            if chunk_start >= byte_offset {
+                break;
+            }
+            let bytes = &chunk.as_bytes()[..chunk.len().min(byte_offset - chunk_start)];
+            for (i, &byte) in bytes.iter().enumerate() {
+                if byte == b'\n' {
+                    row += 1;
+                    line_start = chunk_start + i + 1;
+                }
+            }
+            chunk_start += chunk.len();
+        }
+        Point::new(row, byte_offset - line_start)
+    }
+}
+
+#[cfg(feature = "rope")]
+impl<'a> Input for RopeInput<'a> {
+    type Chunk = &'a [u8];
+
+    fn read(&mut self, byte_offset: usize, _position: Point) -> &'a [u8] {
+        if byte_offset >= self.rope.len_bytes() {
+            return &[];
+        }
+        let (chunk, chunk_start, _, _) = self.rope.chunk_at_byte(byte_offset);
+        &chunk.as_bytes()[byte_offset - chunk_start..]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads `input` to the end, one chunk at a time.
+    fn read_all(mut input: impl Input) -> (Vec<u8>, usize) {
+        let mut text = Vec::new();
+        let mut reads = 0;
+        loop {
+            let chunk = input.read(text.len(), Point::default());
+            if chunk.as_ref().is_empty() {
+                return (text, reads);
+            }
+            text.extend_from_slice(chunk.as_ref());
+            reads += 1;
+        }
+    }
+
+    #[test]
+    fn test_closure_input() {
+        let source = "fn main() {}\n".as_bytes();
+        // Hand out at most three bytes per read.
+        let input = |offset: usize, _: Point| &source[offset..(offset + 3).min(source.len())];
+        let (text, reads) = read_all(input);
+        assert_eq!(text, source);
+        assert_eq!(reads, 5);
+    }
+
+    #[test]
+    fn test_str_input() {
+        let (text, reads) = read_all("hello");
+        assert_eq!(text, b"hello");
+        assert_eq!(reads, 1);
+    }
+
+    #[cfg(feature = "rope")]
+    #[test]
+    fn test_rope_input_reads_every_chunk() {
+        let line = "let value = \"sapling 🌱\";\n";
+        let source = line.repeat(500);
+        let rope = ropey::Rope::from_str(&source);
+        assert!(rope.chunks().count() > 1);
+
+        let (text, reads) = read_all(RopeInput::new(&rope));
+        assert_eq!(text, source.as_bytes());
+        assert_eq!(reads, rope.chunks().count());
+    }
+
+    #[cfg(feature = "rope")]
+    #[test]
+    fn test_rope_input_point_at() {
+        let rope = ropey::Rope::from_str("ab\ncdé\nf");
+        let input = RopeInput::new(&rope);
+        assert_eq!(input.point_at(0), Point::new(0, 0));
+        assert_eq!(input.point_at(4), Point::new(1, 1));
+        assert_eq!(input.point_at(8), Point::new(2, 0));
+        assert_eq!(input.point_at(100), Point::new(2, 1));
+    }
+
+    #[cfg(feature = "rope")]
+    #[test]
+    fn test_rope_input_counts_rows_like_str_input() {
+        let grammar = crate::parse_grammar(
+            r#"{
+                "name": "words",
+                "rules": {
+                    "text": {"type": "REPEAT", "content": {"type": "SYMBOL", "name": "word"}},
+                    "word": {"type": "PATTERN", "value": "[a-z]+"}
+                }
+            }"#,
+        )
+        .unwrap();
+        let language = crate::tables::build(&grammar).unwrap().into_language();
+        let source = "ab\r\ncd\ref\u{2028}gh\r\n\r\nij".repeat(200);
+        let rope = ropey::Rope::from_str(&source);
+        assert!(rope.chunks().count() > 1);
+        let input = RopeInput::new(&rope);
+
+        let tree = crate::Parser::new(&language).parse(input);
+        assert_eq!(tree, crate::Parser::new(&language).parse(source.as_str()));
+        for word in tree.children() {
+            assert_eq!(input.point_at(word.start_byte()), word.start_position());
+            assert_eq!(input.point_at(word.end_byte()), word.end_position());
+        }
+    }
+}
@@ -0,0 +1,538 @@
+//! A small JSON value model for Tree-sitter's file formats.
+//!
+//! Tree-sitter's JSON documents are polymorphic in places that a derived
+//! deserializer cannot express: a rule's `value` may be a string or an integer,
+//! and precedence entries may be strings or symbol objects. Grammar loading
+//! therefore reads into a generic `Value` first and then walks it by hand.
+//!
+//! Objects keep their keys in document order, which matters for grammars (the
+//! first rule is the start rule) and for writing output that round-trips. The
+//! pretty printer matches `serde_json`'s layout so that generated files are
+//! byte-compatible with the ones the `tree-sitter` CLI writes.
+//!
+//! Only [`write_string`](crate::json::write_string) is public, for tools
+//! that write JSON of their own.
+
+use std::fmt::{self, Write};
+
+/// A parsed JSON value.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value {
+    /// The `null` literal.
+    Null,
+    /// A `true` or `false` literal.
+    Bool(bool),
+    /// A number without a fractional part or exponent.
+    Integer(i64),
+    /// Any other number.
+    Float(f64),
+    /// A string, with escapes resolved.
+    String(String),
+    /// An array of values.
+    Array(Vec<Value>),
+    /// An object, with keys in document order.
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Looks up `key` if this value is an object.
+    pub(crate) fn get(&self, key: &str) -> Option<&Value> {
+        self.as_object()
+            .and_then(|entries| entries.iter().find(|(k, _)| k == key).map(|(_, v)| v))
+    }
+
+    /// Returns the string contents if this value is a string.
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns the boolean if this value is `true` or `false`.
+    pub(crate) fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// Returns the elements if this value is an array.
+    pub(crate) fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// Returns the entries if this value is an object.
+    pub(crate) fn as_object(&self) -> Option<&[(String, Value)]> {
+        match self {
+            Value::Object(entries) => Some(entries),
+            _ => None,
+        }
+    }
+
+    /// A short description of the value's kind, for error messages.
+    pub(crate) fn kind(&self) -> &'static str {
+        match self {
+            Value::Null => "null",
+            Value::Bool(_) => "boolean",
+            Value::Integer(_) | Value::Float(_) => "number",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+        }
+    }
+}
+
+impl Value {
+    /// Serializes with two-space indentation, as `serde_json`'s
+    /// `to_string_pretty` does.
+    pub(crate) fn to_pretty_string(&self) -> String {
+        let mut out = String::new();
+        write_value(&mut out, self, Some(0));
+        out
+    }
+}
+
+impl fmt::Display for Value {
+    /// Writes the compact (single-line) serialization.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = String::new();
+        write_value(&mut out, self, None);
+        f.write_str(&out)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(i: i64) -> Self {
+        Value::Integer(i)
+    }
+}
+
+/// A syntax error in a JSON document, with a 1-based line and column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ParseError {
+    pub(crate) line: usize,
+    pub(crate) column: usize,
+    pub(crate) message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at line {} column {}",
+            self.message, self.line, self.column
+        )
+    }
+}
+
+/// Parses a complete JSON document.
+///
+/// # Errors
+///
+/// Returns a [`ParseError`] pointing at the first malformed token, or at any
+/// trailing content after the top-level value.
+pub(crate) fn parse(input: &str) -> Result<Value, ParseError> {
+    let mut parser = Parser { input, pos: 0 };
+    parser.skip_whitespace();
+    let value = parser.parse_value(0)?;
+    parser.skip_whitespace();
+    if parser.pos < input.len() {
+        return Err(parser.error("trailing characters"));
+    }
+    Ok(value)
+}
+
+/// Nesting limit, so that hostile input cannot overflow the stack.
+const MAX_DEPTH: usize = 512;
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: impl Into<String>) -> ParseError {
+        let consumed = &self.input[..self.pos];
+        let line = consumed.matches('\n').count() + 1;
+        let column = consumed
+            .rsplit('\n')
+            .next()
+            .map_or(0, |l| l.chars().count())
+            + 1;
+        ParseError {
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), ParseError> {
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(format!("expected '{}'", byte as char)))
+        }
+    }
+
+    fn parse_value(&mut self, depth: usize) -> Result<Value, ParseError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("nesting too deep"));
+        }
+        match self.peek() {
+            Some(b'{') => self.parse_object(depth),
+            Some(b'[') => self.parse_array(depth),
+            Some(b'"') => self.parse_string().map(Value::String),
+            Some(b'-' | b'0'..=b'9') => self.parse_number(),
+            Some(b't') => self.parse_literal("true", Value::Bool(true)),
+            Some(b'f') => self.parse_literal("false", Value::Bool(false)),
+            Some(b'n') => self.parse_literal("null", Value::Null),
+            Some(_) => Err(self.error("expected value")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn parse_literal(&mut self, word: &str, value: Value) -> Result<Value, ParseError> {
+        if self.input[self.pos..].starts_with(word) {
+            self.pos += word.len();
+            Ok(value)
+        } else {
+            Err(self.error("expected value"))
+        }
+    }
+
+    fn parse_object(&mut self, depth: usize) -> Result<Value, ParseError> {
+        self.expect(b'{')?;
+        let mut entries = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(Value::Object(entries));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some(b'"') {
+                return Err(self.error("expected string key"));
+            }
+            let key = self.parse_string()?;
+            self.skip_whitespace();
+            self.expect(b':')?;
+            self.skip_whitespace();
+            let value = self.parse_value(depth + 1)?;
+            entries.push((key, value));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(Value::Object(entries));
+                }
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+
+    fn parse_array(&mut self, depth: usize) -> Result<Value, ParseError> {
+        self.expect(b'[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(Value::Array(items));
+        }
+        loop {
+            self.skip_whitespace();
+            items.push(self.parse_value(depth + 1)?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(Value::Array(items));
+                }
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, ParseError> {
+        self.expect(b'"')?;
+        let mut out = String::new();
+        loop {
+            let rest = &self.input[self.pos..];
+            let Some(offset) = rest.find(['"', '\\']) else {
+                return Err(self.error("unterminated string"));
+            };
+            let chunk = &rest[..offset];
+            if let Some(bad) = chunk.find(|c: char| c < ' ') {
+                self.pos += bad;
+                return Err(self.error("control character in string"));
+            }
+            out.push_str(chunk);
+            self.pos += offset;
+            if self.peek() == Some(b'"') {
+                self.pos += 1;
+                return Ok(out);
+            }
+            self.pos += 1;
+            let escape = self
+                .peek()
+                .ok_or_else(|| self.error("unterminated string"))?;
+            self.pos += 1;
+            match escape {
+                b'"' => out.push('"'),
+                b'\\' => out.push('\\'),
+                b'/' => out.push('/'),
+                b'b' => out.push('\u{8}'),
+                b'f' => out.push('\u{c}'),
+                b'n' => out.push('\n'),
+                b'r' => out.push('\r'),
+                b't' => out.push('\t'),
+                b'u' => out.push(self.parse_unicode_escape()?),
+                _ => {
+                    self.pos -= 1;
+                    return Err(self.error("invalid escape"));
+                }
+            }
+        }
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, ParseError> {
+        let digits = self
+            .input
+            .get(self.pos..self.pos + 4)
+            .ok_or_else(|| self.error("invalid unicode escape"))?;
+        let code =
+            u32::from_str_radix(digits, 16).map_err(|_| self.error("invalid unicode escape"))?;
+        self.pos += 4;
+        Ok(code)
+    }
+
+    fn parse_unicode_escape(&mut self) -> Result<char, ParseError> {
+        let high = self.parse_hex4()?;
+        let code = if (0xD800..0xDC00).contains(&high) {
+            if !self.input[self.pos..].starts_with("\\u") {
+                return Err(self.error("unpaired surrogate"));
+            }
+            self.pos += 2;
+            let low = self.parse_hex4()?;
+            if !(0xDC00..0xE000).contains(&low) {
+                return Err(self.error("unpaired surrogate"));
+            }
+            0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or_else(|| self.error("invalid unicode escape"))
+    }
+
+    fn parse_number(&mut self) -> Result<Value, ParseError> {
+        let start = self.pos;
+        let bytes = self.input.as_bytes();
+        let mut end = self.pos;
+        let mut integral = true;
+        if bytes.get(end) == Some(&b'-') {
+            end += 1;
+        }
+        while let Some(b) = bytes.get(end) {
+            match b {
+                b'0'..=b'9' => {}
+                b'.' | b'e' | b'E' | b'+' | b'-' => integral = false,
+                _ => break,
+            }
+            end += 1;
+        }
+        let text = &self.input[start..end];
+        let value = if integral {
+            text.parse().ok().map(Value::Integer)
+        } else {
+            None
+        };
+        let value = match value {
+            Some(v) => v,
+            None => text
+                .parse()
+                .map(Value::Float)
+                .map_err(|_| self.error("invalid number"))?,
+        };
+        self.pos = end;
+        Ok(value)
+    }
+}
+
+fn write_value(out: &mut String, value: &Value, indent: Option<usize>) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Integer(i) => {
+            let _ = write!(out, "{i}");
+        }
+        Value::Float(f) => {
+            let _ = write!(out, "{f:?}");
+        }
+        Value::String(s) => write_string(out, s),
+        Value::Array(items) => {
+            write_sequence(out, '[', ']', items, indent, |out, item, indent| {
+                write_value(out, item, indent);
+            });
+        }
+        Value::Object(entries) => {
+            write_sequence(
+                out,
+                '{',
+                '}',
+                entries,
+                indent,
+                |out, (key, value), indent| {
+                    write_string(out, key);
+                    out.push(':');
+                    if indent.is_some() {
+                        out.push(' ');
+                    }
+                    write_value(out, value, indent);
+                },
+            );
+        }
+    }
+}
+
+fn write_sequence<T>(
+    out: &mut String,
+    open: char,
+    close: char,
+    items: &[T],
+    indent: Option<usize>,
+    mut write_item: impl FnMut(&mut String, &T, Option<usize>),
+) {
+    out.push(open);
+    if items.is_empty() {
+        out.push(close);
+        return;
+    }
+    let inner = indent.map(|level| level + 1);
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        if let Some(level) = inner {
+            out.push('\n');
+            push_indent(out, level);
+        }
+        write_item(out, item, inner);
+    }
+    if let Some(level) = indent {
+        out.push('\n');
+        push_indent(out, level);
+    }
+    out.push(close);
+}
+
+fn push_indent(out: &mut String, level: usize) {
+    for _ in 0..level {
+        out.push_str("  ");
+    }
+}
+
+/// Writes `s` as a quoted JSON string using `serde_json`'s escaping rules.
+pub fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            c if c < ' ' => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_preserves_key_order() {
+        let value = parse(r#"{"b": 1, "a": [true, null, "x"], "c": -2.5}"#).unwrap();
+        let keys: Vec<_> = value
+            .as_object()
+            .unwrap()
+            .iter()
+            .map(|(k, _)| k.as_str())
+            .collect();
+        assert_eq!(keys, ["b", "a", "c"]);
+        assert_eq!(value.get("b"), Some(&Value::Integer(1)));
+        assert_eq!(value.get("c"), Some(&Value::Float(-2.5)));
+    }
+
+    #[test]
+    fn test_parse_string_escapes() {
+        let value = parse(r#""a\"b\\c\né😀""#).unwrap();
+        assert_eq!(value.as_str(), Some("a\"b\\c\né😀"));
+    }
+
+    #[test]
+    fn test_parse_error_position() {
+        let err = parse("{\n  \"a\": tru\n}").unwrap_err();
+        assert_eq!((err.line, err.column), (2, 8));
+        assert!(parse("[1, 2] 3").is_err());
+        assert!(parse("\"unterminated").is_err());
+    }
+
+    #[test]
+    fn test_pretty_matches_serde_json_layout() {
+        let value = parse(r#"{"type": "x", "fields": {}, "types": [{"a": true}, 1]}"#).unwrap();
+        assert_eq!(
+            value.to_pretty_string(),
+            "{\n  \"type\": \"x\",\n  \"fields\": {},\n  \"types\": [\n    {\n      \"a\": true\n    },\n    1\n  ]\n}"
+        );
+    }
+
+    #[test]
+    fn test_display_is_compact() {
+        let value = parse(r#"{ "type": "x", "fields": {}, "children": [ {"a": true} ] }"#).unwrap();
+        assert_eq!(
+            value.to_string(),
+            r#"{"type":"x","fields":{},"children":[{"a":true}]}"#
+        );
+    }
+}
@@ -0,0 +1,473 @@
+//! Parse and lex tables for one grammar.
+//!
+//! A [`Language`] is everything the [`Parser`](crate::parser::Parser) needs
+//! to know about a grammar, in the flat, index-based layout tree-sitter's
+//! `parser.c` uses: symbol names and metadata, an LR action and goto table,
+//! the lex state each parse state starts lexing in, and the aliases and
+//! fields each production applies to its children.
+//!
+//! Every table is a [`Cow`](std::borrow::Cow), so a `Language` can be a
+//! `static` written out by [`emit::rust`](crate::emit::rust), with all of
+//! its data borrowed from constants, or assembled at runtime from owned
+//! vectors. A generated module lexes with a function compiled from the lex
+//! table; a language built at runtime with
+//! [`Tables::into_language`](crate::tables::Tables::into_language) walks
+//! the table itself.
+
+use crate::lexer::Lexer;
+use crate::scanner::ExternalScanner;
+use crate::tables::lex::LexState;
+use std::borrow::Cow;
+
+/// Identifies a symbol: a token, a rule, or an alias name.
+pub type SymbolId = u16;
+
+/// Identifies a parse state or a lex state.
+pub type StateId = u16;
+
+/// Identifies a field name.
+pub type FieldId = u16;
+
+/// A lexing function: starting in lex state `state`, consumes one token from
+/// `lexer` and returns whether a token was accepted.
+pub type LexFn = fn(lexer: &mut Lexer<'_>, state: StateId) -> bool;
+
+/// Creates a fresh external scanner for one parse.
+pub type NewScannerFn = fn() -> Box<dyn ExternalScanner>;
+
+/// The symbol the lexer reports at the end of the input.
+pub const END_SYMBOL: SymbolId = 0;
+
+/// How a symbol appears in syntax trees.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SymbolMetadata {
+    /// Whether nodes of this symbol appear in the tree. Hidden rules and
+    /// repetitions are spliced into their parent instead.
+    pub visible: bool,
+    /// Whether the symbol is a named rule or token rather than a literal.
+    pub named: bool,
+    /// Whether the symbol is one of the grammar's supertypes.
+    pub supertype: bool,
+}
+
+/// One cell of the parse table.
+///
+/// For a token, `value` is the index of the [`ParseActionEntry::Header`]
+/// introducing the actions to take; for a rule, it is the state to go to
+/// after reducing it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseTableEntry {
+    /// The lookahead token or the reduced rule.
+    pub symbol: SymbolId,
+    /// An action list index or a goto state, depending on `symbol`.
+    pub value: u16,
+}
+
+impl ParseTableEntry {
+    /// Creates a table cell.
+    #[must_use]
+    pub const fn new(symbol: SymbolId, value: u16) -> Self {
+        Self { symbol, value }
+    }
+}
+
+/// What the parser does on seeing a token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParseAction {
+    /// Pushes the token and moves to `state`. Extras such as comments are
+    /// shifted without changing state.
+    Shift {
+        /// The state to move to.
+        state: StateId,
+        /// Whether the token is an extra.
+        extra: bool,
+    },
+    /// Pops `child_count` children and wraps them in a `symbol` node.
+    Reduce {
+        /// The rule being reduced.
+        symbol: SymbolId,
+        /// The number of children, not counting extras among them.
+        child_count: u16,
+        /// Selects the aliases and fields applied to the children.
+        production_id: u16,
+    },
+    /// Finishes the parse.
+    Accept,
+}
+
+/// An element of the flattened action lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseActionEntry {
+    /// Introduces the `count` actions that follow it. A table cell points
+    /// at a header; the header at index 0 has no actions.
+    Header {
+        /// The number of actions in the list.
+        count: u16,
+    },
+    /// One action of the list.
+    Action(ParseAction),
+}
+
+/// Where lexing starts in a parse state.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LexMode {
+    /// The state of the lex function to start in.
+    pub lex_state: StateId,
+    /// The row of [`Language::external_scanner_states`] saying which
+    /// external tokens are valid. Row 0 has none, so the scanner is not
+    /// called.
+    pub external_lex_state: u16,
+}
+
+impl LexMode {
+    /// Creates a lex mode.
+    #[must_use]
+    pub const fn new(lex_state: StateId, external_lex_state: u16) -> Self {
+        Self {
+            lex_state,
+            external_lex_state,
+        }
+    }
+}
+
+/// How a language reads tokens.
+#[derive(Debug, Clone)]
+pub enum Lexing {
+    /// A generated lex function.
+    Function(LexFn),
+    /// Generated lex functions for a grammar with keywords: each token of
+    /// `keyword_capture_token` that `lex` reads is read again from its start
+    /// with `keyword_lex`, and becomes the keyword it matches in full if that
+    /// keyword is valid where it appears.
+    Keywords {
+        /// The lex function for every token.
+        lex: LexFn,
+        /// The lex function for the keywords, from lex state 0.
+        keyword_lex: LexFn,
+        /// The word token, which keywords are lexed as.
+        keyword_capture_token: SymbolId,
+    },
+    /// A lex table, walked one character at a time.
+    Table(LexTable),
+}
+
+/// The lexer DFA of a language built at runtime.
+///
+/// A grammar with a `word` token may also have a keyword DFA: each token of
+/// the word symbol is lexed again with it, from its start, and becomes the
+/// keyword it matches in full if that keyword is valid where it appears.
+#[derive(Debug, Clone, Default)]
+pub struct LexTable {
+    pub(crate) states: Vec<LexState>,
+    pub(crate) keyword_capture_token: Option<SymbolId>,
+    pub(crate) keyword_states: Vec<LexState>,
+}
+
+impl LexTable {
+    /// Reads one token from lex state `state`, turning words into the
+    /// keywords `is_valid` accepts.
+    fn lex(
+        &self,
+        lexer: &mut Lexer<'_>,
+        state: StateId,
+        is_valid: impl Fn(SymbolId) -> bool,
+    ) -> bool {
+        if !walk(&self.states, lexer, state) {
+            return false;
+        }
+        match self.keyword_capture_token {
+            Some(word) => relex_keyword(
+                lexer,
+                word,
+                |lexer| walk(&self.keyword_states, lexer, 0),
+                is_valid,
+            ),
+            None => true,
+        }
+    }
+}
+
+/// Reads the token `lexer` just accepted again with `keyword_lex` if it is
+/// `word`, keeping the keyword it matches in full if `is_valid` accepts it.
+/// Returns true, since a token was accepted either way.
+fn relex_keyword(
+    lexer: &mut Lexer<'_>,
+    word: SymbolId,
+    keyword_lex: impl FnOnce(&mut Lexer<'_>) -> bool,
+    is_valid: impl Fn(SymbolId) -> bool,
+) -> bool {
+    if lexer.result_symbol() != word {
+        return true;
+    }
+    let (start, end) = (lexer.token_start(), lexer.token_end());
+    lexer.reset(start);
+    let keyword = keyword_lex(lexer) && lexer.token_end() == end && is_valid(lexer.result_symbol());
+    if !keyword {
+        lexer.restore(start, end, word);
+    }
+    true
+}
+
+/// Lexes as a generated lex function does: follows transitions from `state`
+/// while the lookahead allows, accepting each token on the way.
+fn walk(states: &[LexState], lexer: &mut Lexer<'_>, state: StateId) -> bool {
+    let mut state = usize::from(state);
+    let mut result = false;
+    loop {
+        let Some(lex_state) = states.get(state) else {
+            return result;
+        };
+        if let Some(token) = lex_state.accept {
+            // Tokens are numbered from 1, after the end of input.
+            let Ok(symbol) = SymbolId::try_from(token + 1) else {
+                return result;
+            };
+            lexer.accept(symbol);
+            result = true;
+        }
+        let transition = lexer.lookahead().and_then(|c| {
+            lex_state
+                .transitions
+                .iter()
+                .find(|transition| transition.chars.contains(c))
+        });
+        let Some(transition) = transition else {
+            return result;
+        };
+        lexer.advance(transition.skip);
+        state = transition.state;
+    }
+}
+
+/// The range of [`Language::field_map_entries`] belonging to a production.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FieldMapSlice {
+    /// The first entry.
+    pub index: u16,
+    /// The number of entries.
+    pub length: u16,
+}
+
+/// A field assigned to one child of a production.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FieldMapEntry {
+    /// The field.
+    pub field_id: FieldId,
+    /// The child's position, not counting extras.
+    pub child_index: u16,
+}
+
+impl FieldMapEntry {
+    /// Creates a field map entry.
+    #[must_use]
+    pub const fn new(field_id: FieldId, child_index: u16) -> Self {
+        Self {
+            field_id,
+            child_index,
+        }
+    }
+}
+
+/// The tables for parsing one grammar.
+///
+/// Symbol 0 is [`END_SYMBOL`], followed by the grammar's tokens, its
+/// external tokens, its rules, and finally the alias names that are not
+/// also symbol names. Parse state 0 is the start state.
+#[derive(Debug, Clone)]
+pub struct Language {
+    /// The grammar's name.
+    pub name: Cow<'static, str>,
+    /// The name of each symbol, as it appears as a node kind.
+    pub symbol_names: Cow<'static, [Cow<'static, str>]>,
+    /// How each symbol appears in trees.
+    pub symbol_metadata: Cow<'static, [SymbolMetadata]>,
+    /// The number of token symbols, including [`END_SYMBOL`] and external
+    /// tokens. Symbols below this are tokens.
+    pub token_count: usize,
+    /// Field names, sorted, indexed by [`FieldId`].
+    pub field_names: Cow<'static, [Cow<'static, str>]>,
+    /// Where each state's row starts in `parse_table`, followed by the end
+    /// of the last row.
+    pub parse_states: Cow<'static, [u32]>,
+    /// The rows of the parse table, each sorted by symbol.
+    pub parse_table: Cow<'static, [ParseTableEntry]>,
+    /// The action lists the parse table points into.
+    pub parse_actions: Cow<'static, [ParseActionEntry]>,
+    /// For each production id, `max_alias_sequence_length` symbols giving
+    /// the alias of each child, or 0 where a child is not aliased.
+    pub alias_sequences: Cow<'static, [SymbolId]>,
+    /// The length of each alias sequence.
+    pub max_alias_sequence_length: usize,
+    /// For each production id, its entries in `field_map_entries`.
+    pub field_map_slices: Cow<'static, [FieldMapSlice]>,
+    /// The fields assigned by each production.
+    pub field_map_entries: Cow<'static, [FieldMapEntry]>,
+    /// Where lexing starts for each parse state.
+    pub lex_modes: Cow<'static, [LexMode]>,
+    /// The lexer.
+    pub lexing: Lexing,
+    /// The symbol of each external token, in the order of the grammar's
+    /// `externals`, which is the order scanners number them in.
+    pub external_symbols: Cow<'static, [SymbolId]>,
+    /// For each external lex state, whether each external token is valid,
+    /// with one row of `external_symbols.len()` entries per state.
+    pub external_scanner_states: Cow<'static, [bool]>,
+    /// Creates the scanner for the external tokens, if the grammar has one.
+    pub external_scanner: Option<NewScannerFn>,
+}
+
+impl Language {
+    /// The number of parse states.
+    #[must_use]
+    pub fn state_count(&self) -> usize {
+        self.lex_modes.len()
+    }
+
+    /// The number of symbols, including aliases.
+    #[must_use]
+    pub fn symbol_count(&self) -> usize {
+        self.symbol_names.len()
+    }
+
+    /// The name of `symbol`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `symbol` is out of range.
+    #[must_use]
+    pub fn symbol_name(&self, symbol: SymbolId) -> &str {
+        &self.symbol_names[usize::from(symbol)]
+    }
+
+    /// How `symbol` appears in trees.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `symbol` is out of range.
+    #[must_use]
+    pub fn symbol_metadata(&self, symbol: SymbolId) -> SymbolMetadata {
+        self.symbol_metadata[usize::from(symbol)]
+    }
+
+    /// Whether `symbol` is a token rather than a rule or alias.
+    #[must_use]
+    pub fn is_token(&self, symbol: SymbolId) -> bool {
+        usize::from(symbol) < self.token_count
+    }
+
+    /// The name of `field`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `field` is out of range.
+    #[must_use]
+    pub fn field_name(&self, field: FieldId) -> &str {
+        &self.field_names[usize::from(field)]
+    }
+
+    /// The actions to take in `state` on seeing the token `symbol`. More
+    /// than one action means the grammar declared a conflict here.
+    pub fn actions(
+        &self,
+        state: StateId,
+        symbol: SymbolId,
+    ) -> impl Iterator<Item = ParseAction> + '_ {
+        let start = self.lookup(state, symbol).map_or(0, usize::from);
+        let count = match self.parse_actions.get(start) {
+            Some(ParseActionEntry::Header { count }) => usize::from(*count),
+            _ => 0,
+        };
+        self.parse_actions[start + 1..start + 1 + count]
+            .iter()
+            .filter_map(|entry| match entry {
+                ParseActionEntry::Action(action) => Some(*action),
+                ParseActionEntry::Header { .. } => None,
+            })
+    }
+
+    /// The state to move to from `state` after reducing the rule `symbol`.
+    #[must_use]
+    pub fn goto(&self, state: StateId, symbol: SymbolId) -> Option<StateId> {
+        self.lookup(state, symbol)
+    }
+
+    /// The alias applied to the child at `child_index` by a production.
+    #[must_use]
+    pub fn alias(&self, production_id: u16, child_index: usize) -> Option<SymbolId> {
+        if child_index >= self.max_alias_sequence_length {
+            return None;
+        }
+        let index = usize::from(production_id) * self.max_alias_sequence_length + child_index;
+        self.alias_sequences
+            .get(index)
+            .copied()
+            .filter(|&symbol| symbol != 0)
+    }
+
+    /// The fields a production assigns to its children.
+    #[must_use]
+    pub fn field_map(&self, production_id: u16) -> &[FieldMapEntry] {
+        let slice = self
+            .field_map_slices
+            .get(usize::from(production_id))
+            .copied()
+            .unwrap_or_default();
+        let start = usize::from(slice.index);
+        &self.field_map_entries[start..start + usize::from(slice.length)]
+    }
+
+    /// Where to start lexing in `state`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `state` is out of range.
+    #[must_use]
+    pub fn lex_mode(&self, state: StateId) -> LexMode {
+        self.lex_modes[usize::from(state)]
+    }
+
+    /// Reads one token in parse state `state`, returning whether one was
+    /// accepted.
+    pub(crate) fn lex(&self, lexer: &mut Lexer<'_>, state: StateId) -> bool {
+        let lex_state = self.lex_mode(state).lex_state;
+        match &self.lexing {
+            Lexing::Function(lex) => lex(lexer, lex_state),
+            Lexing::Keywords {
+                lex,
+                keyword_lex,
+                keyword_capture_token,
+            } => {
+                lex(lexer, lex_state)
+                    && relex_keyword(
+                        lexer,
+                        *keyword_capture_token,
+                        |lexer| keyword_lex(lexer, 0),
+                        |symbol| self.actions(state, symbol).next().is_some(),
+                    )
+            }
+            Lexing::Table(table) => table.lex(lexer, lex_state, |symbol| {
+                self.actions(state, symbol).next().is_some()
+            }),
+        }
+    }
+
+    /// Which external tokens are valid in an external lex state, indexed as
+    /// the scanner numbers them.
+    #[must_use]
+    pub fn valid_external_tokens(&self, external_lex_state: u16) -> &[bool] {
+        let count = self.external_symbols.len();
+        let start = usize::from(external_lex_state) * count;
+        self.external_scanner_states
+            .get(start..start + count)
+            .unwrap_or_default()
+    }
+
+    fn lookup(&self, state: StateId, symbol: SymbolId) -> Option<u16> {
+        let state = usize::from(state);
+        let (start, end) = (self.parse_states[state], self.parse_states[state + 1]);
+        let row = &self.parse_table[start as usize..end as usize];
+        row.binary_search_by_key(&symbol, |entry| entry.symbol)
+            .ok()
+            .map(|i| row[i].value)
+    }
+}
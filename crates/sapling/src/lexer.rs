@@ -0,0 +1,424 @@
+//! The cursor a lexing function reads characters through.
+//!
+//! A [`Lexer`] sits at one position of the input and exposes the character
+//! there as its lookahead. Lex functions written out by
+//! [`emit::rust`](crate::emit::rust) step through their state machine by
+//! calling [`advance`](Lexer::advance), and record each token they recognise
+//! with [`accept`](Lexer::accept), which also marks where the token ends so
+//! that the longest match wins when a longer attempt fails. An
+//! [`ExternalScanner`](crate::scanner::ExternalScanner) drives the same
+//! cursor by hand.
+//!
+//! When a parser is limited to some [included
+//! ranges](crate::parser::Parser::set_included_ranges), the lexer reads
+//! them as if they were contiguous: the text between them is never seen,
+//! and the character after the end of one range is the first of the next.
+
+use crate::input::{Input, Point};
+use crate::language::SymbolId;
+use crate::tree::Range;
+
+/// The included ranges of a parser that reads the whole document.
+pub(crate) const WHOLE_DOCUMENT: &[Range] = &[Range::new(
+    0,
+    usize::MAX,
+    Point::new(0, 0),
+    Point::new(usize::MAX, usize::MAX),
+)];
+
+/// A byte offset together with its row and column.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct Position {
+    pub(crate) byte: usize,
+    pub(crate) point: Point,
+}
+
+/// Text the lexer can read from any byte offset.
+pub(crate) trait Source {
+    /// The text starting at `byte`, or an empty slice at the end.
+    fn bytes_at(&mut self, byte: usize, point: Point) -> &[u8];
+}
+
+/// Adapts an [`Input`], keeping hold of the chunk it last returned.
+pub(crate) struct Chunks<I: Input> {
+    input: I,
+    chunk: Option<I::Chunk>,
+    start: usize,
+}
+
+impl<I: Input> Chunks<I> {
+    pub(crate) fn new(input: I) -> Self {
+        Self {
+            input,
+            chunk: None,
+            start: 0,
+        }
+    }
+}
+
+impl<I: Input> Source for Chunks<I> {
+    fn bytes_at(&mut self, byte: usize, point: Point) -> &[u8] {
+        let cached = self
+            .chunk
+            .as_ref()
+            .is_some_and(|chunk| byte >= self.start && byte < self.start + chunk.as_ref().len());
+        if !cached {
+            self.chunk = Some(self.input.read(byte, point));
+            self.start = byte;
+        }
+        let chunk = self.chunk.as_ref().map_or(&[][..], AsRef::as_ref);
+        chunk.get(byte - self.start..).unwrap_or_default()
+    }
+}
+
+/// A character cursor over the text being parsed.
+pub struct Lexer<'a> {
+    source: &'a mut dyn Source,
+    ranges: &'a [Range],
+    /// The included range holding the lookahead.
+    range_index: usize,
+    position: Position,
+    /// Where the lookahead is, which is past a gap between included ranges
+    /// when `position` is at the end of one.
+    lookahead_start: Position,
+    lookahead: Option<char>,
+    lookahead_size: usize,
+    token_start: Position,
+    token_end: Position,
+    end_marked: bool,
+    result_symbol: SymbolId,
+}
+
+impl<'a> Lexer<'a> {
+    /// Creates a lexer reading `ranges` of `source`, which must be sorted
+    /// and must not overlap, positioned at the start of the first.
+    pub(crate) fn new(source: &'a mut dyn Source, ranges: &'a [Range]) -> Self {
+        let mut lexer = Self {
+            source,
+            ranges,
+            range_index: 0,
+            position: Position::default(),
+            lookahead_start: Position::default(),
+            lookahead: None,
+            lookahead_size: 0,
+            token_start: Position::default(),
+            token_end: Position::default(),
+            end_marked: false,
+            result_symbol: 0,
+        };
+        let start = ranges
+            .first()
+            .map_or_else(Position::default, |range| Position {
+                byte: range.start_byte,
+                point: range.start_point,
+            });
+        lexer.reset(start);
+        lexer
+    }
+
+    /// The character at the current position, or `None` at the end of the
+    /// input. Invalid UTF-8 reads as U+FFFD, one byte at a time.
+    #[must_use]
+    pub fn lookahead(&self) -> Option<char> {
+        self.lookahead
+    }
+
+    /// Whether the current position is the end of the input.
+    #[must_use]
+    pub fn eof(&self) -> bool {
+        self.lookahead.is_none()
+    }
+
+    /// Moves past the lookahead character. Skipped characters, such as
+    /// whitespace between tokens, are not part of the token.
+    pub fn advance(&mut self, skip: bool) {
+        let Some(c) = self.lookahead else {
+            return;
+        };
+        self.position = self.lookahead_start;
+        self.position.byte += self.lookahead_size;
+        if c == '\n' {
+            self.position.point.row += 1;
+            self.position.point.column = 0;
+        } else {
+            self.position.point.column += self.lookahead_size;
+        }
+        self.read_lookahead();
+        if skip {
+            // Skipped text can't end in a gap, so neither can the token.
+            self.position = self.lookahead_start;
+            self.token_start = self.position;
+        }
+    }
+
+    /// Moves past the lookahead character without making it part of the
+    /// token.
+    pub fn skip(&mut self) {
+        self.advance(true);
+    }
+
+    /// Marks the current position as the end of the token.
+    pub fn mark_end(&mut self) {
+        self.token_end = self.position;
+        self.end_marked = true;
+    }
+
+    /// Records that the text so far is a `symbol` token, ending here.
+    pub fn accept(&mut self, symbol: SymbolId) {
+        self.result_symbol = symbol;
+        self.mark_end();
+    }
+
+    /// The symbol of the token recognised so far.
+    #[must_use]
+    pub fn result_symbol(&self) -> SymbolId {
+        self.result_symbol
+    }
+
+    /// Sets the symbol of the token being recognised, without marking its
+    /// end. External scanners set the index of the external token.
+    pub fn set_result_symbol(&mut self, symbol: SymbolId) {
+        self.result_symbol = symbol;
+    }
+
+    /// The number of characters between the start of the line and the
+    /// current position.
+    pub fn get_column(&mut self) -> usize {
+        let line_start = self.position.byte - self.position.point.column;
+        let mut point = Point::new(self.position.point.row, 0);
+        let mut byte = line_start;
+        let mut column = 0;
+        while byte < self.position.byte {
+            let bytes = self.source.bytes_at(byte, point);
+            if bytes.is_empty() {
+                break;
+            }
+            let bytes = &bytes[..bytes.len().min(self.position.byte - byte)];
+            // Count the bytes that start a character.
+            column += bytes.iter().filter(|&&b| b & 0xC0 != 0x80).count();
+            byte += bytes.len();
+            point.column += bytes.len();
+        }
+        column
+    }
+
+    /// Moves to `position`, or the start of the next included range if it
+    /// is not in one, and starts a new token there.
+    pub(crate) fn reset(&mut self, position: Position) {
+        self.range_index = self
+            .ranges
+            .iter()
+            .position(|range| range.end_byte > position.byte)
+            .unwrap_or(self.ranges.len().saturating_sub(1));
+        self.position = position;
+        if let Some(range) = self.ranges.get(self.range_index) {
+            if position.byte < range.start_byte {
+                self.position = Position {
+                    byte: range.start_byte,
+                    point: range.start_point,
+                };
+            }
+        }
+        self.read_lookahead();
+        self.position = self.lookahead_start;
+        self.token_start = self.position;
+        self.token_end = self.position;
+        self.end_marked = false;
+    }
+
+    /// Goes back to a token recognised earlier, after lexing past it: the
+    /// token is a `symbol` again, from `start` to `end`.
+    pub(crate) fn restore(&mut self, start: Position, end: Position, symbol: SymbolId) {
+        self.reset(end);
+        self.token_start = start;
+        self.result_symbol = symbol;
+        self.end_marked = true;
+    }
+
+    /// Settles the extent of a token recognised by an external scanner: it
+    /// ends at the current position unless the scanner marked an end, and
+    /// is empty if that end comes before the skipped text.
+    pub(crate) fn finish_external(&mut self) {
+        if !self.end_marked {
+            self.token_end = self.position;
+        }
+        if self.token_end.byte < self.token_start.byte {
+            self.token_start = self.token_end;
+        }
+    }
+
+    pub(crate) fn position(&self) -> Position {
+        self.position
+    }
+
+    pub(crate) fn token_start(&self) -> Position {
+        self.token_start
+    }
+
+    pub(crate) fn token_end(&self) -> Position {
+        self.token_end
+    }
+
+    fn read_lookahead(&mut self) {
+        self.lookahead_start = self.position;
+        while self.range_index + 1 < self.ranges.len()
+            && self.position.byte >= self.ranges[self.range_index].end_byte
+        {
+            self.range_index += 1;
+            let range = &self.ranges[self.range_index];
+            self.lookahead_start = Position {
+                byte: range.start_byte,
+                point: range.start_point,
+            };
+        }
+        let Position { byte, point } = self.lookahead_start;
+        let limit = self
+            .ranges
+            .get(self.range_index)
+            .map_or(usize::MAX, |range| range.end_byte);
+        let bytes = self.source.bytes_at(byte, point);
+        let bytes = &bytes[..bytes.len().min(limit.saturating_sub(byte))];
+        let Some(&first) = bytes.first() else {
+            self.lookahead = None;
+            self.lookahead_size = 0;
+            return;
+        };
+        let width = utf8_width(first);
+        let decoded = if bytes.len() >= width {
+            decode(&bytes[..width])
+        } else {
+            // The character straddles a chunk boundary.
+            let mut buffer = bytes.to_vec();
+            while buffer.len() < width && byte + buffer.len() < limit {
+                let next = self.source.bytes_at(byte + buffer.len(), point);
+                if next.is_empty() {
+                    break;
+                }
+                let wanted = (width - buffer.len())
+                    .min(next.len())
+                    .min(limit - byte - buffer.len());
+                buffer.extend_from_slice(&next[..wanted]);
+            }
+            decode(&buffer)
+        };
+        (self.lookahead, self.lookahead_size) = match decoded {
+            Some(c) => (Some(c), width),
+            None => (Some(char::REPLACEMENT_CHARACTER), 1),
+        };
+    }
+}
+
+/// The length of the UTF-8 sequence starting with `byte`.
+fn utf8_width(byte: u8) -> usize {
+    match byte {
+        0xF0..=0xF7 => 4,
+        0xE0..=0xEF => 3,
+        0xC0..=0xDF => 2,
+        _ => 1,
+    }
+}
+
+fn decode(bytes: &[u8]) -> Option<char> {
+    std::str::from_utf8(bytes).ok()?.chars().next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_advance_tracks_points_and_skips() {
+        let mut source = Chunks::new(" a\néb");
+        let mut lexer = Lexer::new(&mut source, WHOLE_DOCUMENT);
+        lexer.advance(true);
+        assert_eq!(lexer.token_start().byte, 1);
+        assert_eq!(lexer.lookahead(), Some('a'));
+        lexer.advance(false);
+        lexer.accept(3);
+        lexer.advance(false);
+        assert_eq!(lexer.lookahead(), Some('é'));
+        lexer.advance(false);
+        assert_eq!(lexer.position().point, Point::new(1, 2));
+        assert_eq!(lexer.token_end().byte, 2);
+        assert_eq!(lexer.result_symbol(), 3);
+        lexer.advance(false);
+        assert!(lexer.eof());
+    }
+
+    #[test]
+    fn test_external_token_extent() {
+        let mut source = Chunks::new("x\n  y");
+        let mut lexer = Lexer::new(&mut source, WHOLE_DOCUMENT);
+        lexer.advance(false);
+        lexer.reset(lexer.position());
+        // A zero-width token before the skipped line break.
+        lexer.mark_end();
+        lexer.skip();
+        lexer.skip();
+        lexer.skip();
+        assert_eq!(lexer.get_column(), 2);
+        lexer.finish_external();
+        assert_eq!(lexer.token_start().byte, 1);
+        assert_eq!(lexer.token_end().byte, 1);
+
+        lexer.reset(lexer.position());
+        lexer.advance(false);
+        lexer.finish_external();
+        assert_eq!((lexer.token_start().byte, lexer.token_end().byte), (4, 5));
+    }
+
+    #[test]
+    fn test_included_ranges_skip_gaps() {
+        let text = "ab<x>cd\n<y>e";
+        let ranges = [
+            Range::from_bytes(text.as_bytes(), 2..5),
+            Range::from_bytes(text.as_bytes(), 8..11),
+        ];
+        let mut source = Chunks::new(text);
+        let mut lexer = Lexer::new(&mut source, &ranges);
+        assert_eq!(lexer.position().byte, 2);
+        let mut chars = String::new();
+        while let Some(c) = lexer.lookahead() {
+            chars.push(c);
+            lexer.advance(false);
+            if c == '>' {
+                // A token ending with a range ends there, not at the next.
+                lexer.mark_end();
+            }
+        }
+        assert_eq!(chars, "<x><y>");
+        assert_eq!(lexer.token_end().byte, 11);
+        assert_eq!(lexer.position().point, Point::new(1, 3));
+
+        // A token starting at the end of a range starts at the next one.
+        lexer.reset(Position {
+            byte: ranges[0].end_byte,
+            point: ranges[0].end_point,
+        });
+        assert_eq!(lexer.token_start().byte, 8);
+        assert_eq!(lexer.token_start().point, Point::new(1, 0));
+    }
+
+    #[test]
+    fn test_characters_split_across_chunks() {
+        let text = "xé€".as_bytes();
+        // Hand out one byte at a time.
+        let mut source = Chunks::new(|byte: usize, _| text.get(byte..=byte).unwrap_or_default());
+        let mut lexer = Lexer::new(&mut source, WHOLE_DOCUMENT);
+        let mut chars = Vec::new();
+        while let Some(c) = lexer.lookahead() {
+            chars.push(c);
+            lexer.advance(false);
+        }
+        assert_eq!(chars, ['x', 'é', '€']);
+        assert_eq!(lexer.get_column(), 3);
+        assert_eq!(lexer.position().byte, text.len());
+
+        let mut source = Chunks::new(&b"a\xffb"[..]);
+        let mut lexer = Lexer::new(&mut source, WHOLE_DOCUMENT);
+        lexer.advance(false);
+        assert_eq!(lexer.lookahead(), Some(char::REPLACEMENT_CHARACTER));
+        lexer.advance(false);
+        assert_eq!(lexer.lookahead(), Some('b'));
+    }
+}
@@ -2,6 +2,31 @@
 #![cfg_attr(docsrs, feature(doc_cfg))]
 #![allow(clippy::multiple_crate_versions)]
 
+/// Build script support.
+///
+/// A `build.rs` compiles the crate's `grammar.json` into a parse table
+/// module in `OUT_DIR`, failing the build if the grammar has problems.
+pub mod build;
+
+/// Tree-sitter test corpus files.
+///
+/// Grammar repositories test their parsers with corpus files of inputs and
+/// the trees they should parse into; these are read, run and updated here.
+pub mod corpus;
+
+/// Rust code generation from grammars.
+///
+/// Generated code is how a grammar becomes something the rest of a Rust
+/// program can use directly, starting with typed wrappers over syntax trees.
+pub mod codegen;
+
+/// Grammar translation into parsers.
+///
+/// A grammar is normalized, then either emitted as a `.lalrpop` file for
+/// lalrpop to compile, or built into parse tables and emitted as a Rust
+/// module for sapling's own [`Parser`].
+pub mod emit;
+
 /// Core structures and parsing logic for Tree-sitter grammars.
 ///
 /// This module defines how Sapling understands and manipulates the
@@ -9,6 +34,118 @@
 /// in the compiler builds upon these types.
 pub mod grammar;
 
+/// Rule reference graphs, rendered as Graphviz DOT or Mermaid.
+///
+/// Which rules refer to which is the shape of a grammar; drawing it, or the
+/// part of it around one rule, is the quickest way into an unfamiliar one.
+pub mod graph;
+
+/// Syntax highlighting with `highlights.scm` queries.
+///
+/// Highlighting is the most common use of queries: the highlighter turns a
+/// grammar's query files into a stream of events that renderers, such as
+/// the HTML and ANSI ones provided, turn into styled text.
+pub mod highlight;
+
+/// Grammar import from ANTLR4, pest and ungrammar.
+///
+/// Porting a language usually starts from a grammar written for another
+/// tool. Importing one gives a tree-sitter grammar to refine, with notes on
+/// what could not be carried over.
+pub mod import;
+
+/// Language injection: documents that embed other languages.
+///
+/// One document can hold several languages, such as the code blocks of a
+/// Markdown file. Each is parsed into its own layer of trees, limited to the
+/// ranges of the document it covers.
+pub mod injection;
+
+/// Chunked source text input.
+///
+/// Parsing reads text through a small trait rather than a `&str`, so that
+/// editors can hand over buffers stored as ropes or piece tables without
+/// flattening them first.
+pub mod input;
+
+/// Parse and lex tables, the compiled form of a grammar.
+///
+/// A `Language` is what a parser runs on. Generated Rust modules define one
+/// as a `static`, so parsing needs no grammar processing at runtime.
+pub mod language;
+
+/// The character cursor lexing functions read input through.
+pub mod lexer;
+
+/// Generic JSON values used to read and write Tree-sitter's file formats.
+pub mod json;
+
+/// Resolution of local variables for highlighting and tagging.
+mod locals;
+
+/// Node type information: the fields and children each node kind can have.
+///
+/// This is the static shape of a grammar's trees, as published in
+/// tree-sitter's `node-types.json`, and it is what query validation checks
+/// patterns against.
+pub mod node_types;
+
+/// Loading the `parser.c` files of existing tree-sitter grammars.
+///
+/// Grammars that ship only their generated C parser can run on sapling's
+/// runtime without a C compiler: the tables are read from the C source.
+pub mod parser_c;
+
+/// Parsing text into syntax trees with a [`Language`].
+///
+/// The parser is a table-driven LR parser, so the same runtime serves every
+/// grammar and only the tables differ.
+pub mod parser;
+
+/// Tree-sitter queries over syntax trees.
+///
+/// Queries are how editors and tools pick structure out of a tree: the
+/// `highlights.scm`, `locals.scm` and `tags.scm` files shipped with grammars
+/// are all written in this pattern language.
+pub mod query;
+
+/// Railroad diagrams of grammar rules, as SVG and as an HTML page.
+///
+/// Diagrams are the most readable documentation of a grammar's syntax, and
+/// are drawn straight from the rules with no external tools.
+pub mod railroad;
+
+/// External scanners for context-sensitive tokens.
+///
+/// Grammars whose tokens depend on state, such as indentation, implement
+/// [`ExternalScanner`] in Rust where tree-sitter grammars link a C scanner.
+pub mod scanner;
+
+/// Grammar size and complexity metrics.
+///
+/// Counting rules, tokens, fields and parse states shows at a glance how
+/// large a grammar is and where its complexity lives.
+pub mod stats;
+
+/// LR parse table and lexer DFA construction.
+///
+/// This is sapling's own table builder: it works on the same prepared
+/// grammar as node types and code generation, and needs no external tools.
+pub mod tables;
+
+/// Code navigation tags with `tags.scm` queries.
+///
+/// Tags are the definitions and references an editor jumps between, and
+/// what a ctags file lists, found by running a grammar's tags query.
+pub mod tags;
+
+/// Concrete syntax trees produced by parsing.
+///
+/// Trees are the hand-off point between a parser and everything that reads
+/// source structure. They carry tree-sitter's error conventions (`ERROR` and
+/// `MISSING` nodes) so tolerant parsing can be expressed and tested.
+pub mod tree;
+
 /// Grammar validation and consistency checking utilities.
 ///
 /// Validation exists to protect downstream stages (like codegen and analysis)
@@ -17,4 +154,14 @@ pub mod grammar;
 pub mod validate;
 
 pub use grammar::{parse_grammar, Grammar, GrammarError, Rule};
+#[cfg(feature = "rope")]
+pub use input::RopeInput;
+pub use input::{Input, Point};
+pub use language::Language;
+pub use lexer::Lexer;
+pub use node_types::NodeTypes;
+pub use parser::{IncludedRangesError, Parser};
+pub use query::{Query, QueryCapture, QueryCursor, QueryError, QueryMatch};
+pub use scanner::ExternalScanner;
+pub use tree::{AstNode, InputEdit, Node, Range};
 pub use validate::{validate, ValidationError};
@@ -0,0 +1,113 @@
+//! Local variables, as `locals.scm` describes them.
+//!
+//! A locals query marks scopes with `@local.scope`, and the names defined
+//! and used in them with `@local.definition` and `@local.reference`. A
+//! reference refers to the latest definition of the same name before it in
+//! the innermost scope that has one, looking outwards through scopes until
+//! one sets `(#set! local.scope-inherits false)`. Highlighting gives local
+//! variables their definition's highlight, and tagging leaves them out.
+
+use crate::query::{Query, QueryCursor};
+use crate::tree::Node;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// A `@local.scope`, with the definitions made directly in it.
+struct Scope {
+    range: Range<usize>,
+    inherits: bool,
+    /// Each definition's text, start and node.
+    definitions: Vec<(Vec<u8>, usize, *const Node)>,
+}
+
+/// Finds the definition each local reference refers to, by node. Each
+/// definition refers to itself.
+pub(crate) fn resolve(
+    locals: &Query,
+    tree: &Node,
+    source: &[u8],
+) -> HashMap<*const Node, *const Node> {
+    let text = |node: &Node| source[node.byte_range()].to_vec();
+    let capture_names = locals.capture_names();
+    let mut scopes = vec![Scope {
+        range: tree.byte_range(),
+        inherits: false,
+        definitions: Vec::new(),
+    }];
+    let mut references = Vec::new();
+    for locals_match in QueryCursor::new().matches(locals, tree, source) {
+        for capture in &locals_match.captures {
+            let node = capture.node;
+            match capture_names[capture.index].as_str() {
+                "local.scope" => {
+                    let inherits = locals
+                        .property_settings(locals_match.pattern_index)
+                        .iter()
+                        .find(|property| property.key == "local.scope-inherits")
+                        .is_none_or(|property| property.value.as_deref() != Some("false"));
+                    scopes.push(Scope {
+                        range: node.byte_range(),
+                        inherits,
+                        definitions: Vec::new(),
+                    });
+                }
+                "local.definition" => {
+                    let range = node.byte_range();
+                    let scope = innermost(&scopes, &range);
+                    scopes[scope]
+                        .definitions
+                        .push((text(node), range.start, node));
+                }
+                "local.reference" => references.push(node),
+                _ => {}
+            }
+        }
+    }
+
+    let mut resolved: HashMap<*const Node, *const Node> = scopes
+        .iter()
+        .flat_map(|scope| &scope.definitions)
+        .map(|&(_, _, definition)| (definition, definition))
+        .collect();
+    for node in references {
+        let key: *const Node = node;
+        let range = node.byte_range();
+        let name = text(node);
+        let mut enclosing: Vec<&Scope> = scopes
+            .iter()
+            .filter(|scope| contains(&scope.range, &range))
+            .collect();
+        enclosing.sort_by_key(|scope| scope.range.len());
+        for scope in enclosing {
+            let definition = scope
+                .definitions
+                .iter()
+                .rev()
+                .find(|(defined, start, definition)| {
+                    *defined == name && *start <= range.start && *definition != key
+                });
+            if let Some(&(_, _, definition)) = definition {
+                resolved.insert(key, definition);
+                break;
+            }
+            if !scope.inherits {
+                break;
+            }
+        }
+    }
+    resolved
+}
+
+/// The smallest scope containing `range`.
+fn innermost(scopes: &[Scope], range: &Range<usize>) -> usize {
+    scopes
+        .iter()
+        .enumerate()
+        .filter(|(_, scope)| contains(&scope.range, range))
+        .min_by_key(|(_, scope)| scope.range.len())
+        .map_or(0, |(index, _)| index)
+}
+
+fn contains(outer: &Range<usize>, inner: &Range<usize>) -> bool {
+    outer.start <= inner.start && inner.end <= outer.end
+}
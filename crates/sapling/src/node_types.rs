@@ -0,0 +1,719 @@
+//! Static node type information derived from a grammar.
+//!
+//! [`NodeTypes`] describes every node kind a grammar can produce: which
+//! fields it has, which kinds of children can fill them, and which subtypes
+//! a supertype stands for. This is the information tree-sitter publishes as
+//! `node-types.json`, and it is computed the same way: rules are flattened
+//! into productions, hidden rules are spliced into their parents, and the
+//! possible children of each visible node are summarized across every
+//! production that can build it.
+
+use crate::grammar::prepare::{
+    prepare, Alias, PreparedGrammar, Production, ProductionStep, Symbol, SyntaxGrammar,
+    VariableType,
+};
+use crate::grammar::{Grammar, GrammarError};
+use crate::json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// A reference to a node kind, as it appears in field and child lists.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeKind {
+    /// The node kind, e.g. `"identifier"` or `"+"`.
+    pub kind: String,
+    /// Whether the node is named.
+    pub named: bool,
+}
+
+/// What can fill a field, or a node's unnamed-field children.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChildInfo {
+    /// Whether more than one node can appear.
+    pub multiple: bool,
+    /// Whether at least one node always appears.
+    pub required: bool,
+    /// The kinds of node that can appear, sorted.
+    pub types: Vec<NodeKind>,
+}
+
+impl Default for ChildInfo {
+    fn default() -> Self {
+        Self {
+            multiple: false,
+            required: true,
+            types: Vec::new(),
+        }
+    }
+}
+
+impl ChildInfo {
+    fn to_json(&self) -> Value {
+        Value::Object(vec![
+            ("multiple".to_string(), Value::from(self.multiple)),
+            ("required".to_string(), Value::from(self.required)),
+            ("types".to_string(), kinds_to_json(&self.types)),
+        ])
+    }
+}
+
+fn kinds_to_json(kinds: &[NodeKind]) -> Value {
+    Value::Array(
+        kinds
+            .iter()
+            .map(|kind| {
+                Value::Object(vec![
+                    ("type".to_string(), Value::from(kind.kind.as_str())),
+                    ("named".to_string(), Value::from(kind.named)),
+                ])
+            })
+            .collect(),
+    )
+}
+
+/// The shape of one node kind.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeInfo {
+    /// The node kind.
+    pub kind: String,
+    /// Whether the node is named.
+    pub named: bool,
+    /// Whether this is the grammar's start rule.
+    pub root: bool,
+    /// Whether the node can appear anywhere, as an extra.
+    pub extra: bool,
+    /// The node's fields, for nodes built from syntax rules.
+    pub fields: Option<BTreeMap<String, ChildInfo>>,
+    /// The named children that are not in any field.
+    pub children: Option<ChildInfo>,
+    /// For supertypes, the kinds they stand for.
+    pub subtypes: Option<Vec<NodeKind>>,
+}
+
+impl NodeInfo {
+    fn to_json(&self) -> Value {
+        let mut entries = vec![
+            ("type".to_string(), Value::from(self.kind.as_str())),
+            ("named".to_string(), Value::from(self.named)),
+        ];
+        if self.root {
+            entries.push(("root".to_string(), Value::from(true)));
+        }
+        if self.extra {
+            entries.push(("extra".to_string(), Value::from(true)));
+        }
+        if let Some(fields) = &self.fields {
+            let fields = fields
+                .iter()
+                .map(|(name, info)| (name.clone(), info.to_json()))
+                .collect();
+            entries.push(("fields".to_string(), Value::Object(fields)));
+        }
+        if let Some(children) = &self.children {
+            entries.push(("children".to_string(), children.to_json()));
+        }
+        if let Some(subtypes) = &self.subtypes {
+            entries.push(("subtypes".to_string(), kinds_to_json(subtypes)));
+        }
+        Value::Object(entries)
+    }
+
+    fn leaf(kind: &str, named: bool, extra: bool) -> Self {
+        Self {
+            kind: kind.to_string(),
+            named,
+            root: false,
+            extra,
+            fields: None,
+            children: None,
+            subtypes: None,
+        }
+    }
+}
+
+/// The node kinds of a grammar, in `node-types.json` order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeTypes {
+    nodes: Vec<NodeInfo>,
+}
+
+impl NodeTypes {
+    /// Computes the node types of `grammar`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`GrammarError`] if the grammar references undefined
+    /// symbols or is otherwise too malformed to analyse.
+    pub fn from_grammar(grammar: &Grammar) -> Result<Self, GrammarError> {
+        let prepared = prepare(grammar)?;
+        let info = variable_info(&prepared);
+        Ok(Self {
+            nodes: node_infos(&prepared, &info),
+        })
+    }
+
+    /// All node kinds: supertypes first, then nodes built from syntax rules,
+    /// then tokens, each group sorted by kind.
+    pub fn iter(&self) -> impl Iterator<Item = &NodeInfo> {
+        self.nodes.iter()
+    }
+
+    /// Looks up a node kind.
+    #[must_use]
+    pub fn get(&self, kind: &str, named: bool) -> Option<&NodeInfo> {
+        self.nodes
+            .iter()
+            .find(|node| node.kind == kind && node.named == named)
+    }
+
+    /// Serializes the node types in tree-sitter's `node-types.json` format.
+    ///
+    /// The output is byte-for-byte what the `tree-sitter` CLI writes for the
+    /// same grammar: two-space indentation, no trailing newline, and `root`
+    /// and `extra` only where they are true.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        Value::Array(self.nodes.iter().map(NodeInfo::to_json).collect()).to_pretty_string()
+    }
+
+    /// Expands supertypes in `types` into the concrete kinds they stand for,
+    /// recursively.
+    #[must_use]
+    pub fn concrete_kinds(&self, types: &[NodeKind]) -> BTreeSet<NodeKind> {
+        let mut result = BTreeSet::new();
+        let mut stack: Vec<&NodeKind> = types.iter().collect();
+        while let Some(node_kind) = stack.pop() {
+            match self
+                .get(&node_kind.kind, node_kind.named)
+                .and_then(|node| node.subtypes.as_ref())
+            {
+                Some(subtypes) => stack.extend(subtypes),
+                None => {
+                    result.insert(node_kind.clone());
+                }
+            }
+        }
+        result
+    }
+}
+
+/// How many children of some kind a production can have.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ChildQuantity {
+    exists: bool,
+    required: bool,
+    multiple: bool,
+}
+
+impl ChildQuantity {
+    const ZERO: Self = Self {
+        exists: false,
+        required: false,
+        multiple: false,
+    };
+    const ONE: Self = Self {
+        exists: true,
+        required: true,
+        multiple: false,
+    };
+
+    /// Accounts for `other` following this quantity in the same production.
+    fn append(&mut self, other: Self) {
+        if other.exists {
+            if self.exists || other.multiple {
+                self.multiple = true;
+            }
+            if other.required {
+                self.required = true;
+            }
+            self.exists = true;
+        }
+    }
+
+    /// Accounts for `other` as an alternative production, returning whether
+    /// anything changed.
+    fn union(&mut self, other: Self) -> bool {
+        let before = *self;
+        self.exists |= other.exists;
+        self.required &= other.required;
+        self.multiple |= other.multiple;
+        *self != before
+    }
+}
+
+/// The type of a child: a symbol, or the alias it appears under.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum ChildType {
+    Normal(Symbol),
+    Aliased(Alias),
+}
+
+#[derive(Debug, Clone)]
+struct FieldInfo {
+    quantity: ChildQuantity,
+    types: BTreeSet<ChildType>,
+}
+
+impl Default for FieldInfo {
+    fn default() -> Self {
+        Self {
+            quantity: ChildQuantity::ONE,
+            types: BTreeSet::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct VariableInfo {
+    fields: BTreeMap<String, FieldInfo>,
+    children: FieldInfo,
+    children_without_fields: FieldInfo,
+}
+
+fn extend(target: &mut BTreeSet<ChildType>, types: impl IntoIterator<Item = ChildType>) -> bool {
+    let before = target.len();
+    target.extend(types);
+    target.len() != before
+}
+
+/// Summarizes the children of every syntax variable, iterating until the
+/// information inherited through hidden rules stops changing.
+fn variable_info(grammar: &PreparedGrammar) -> Vec<VariableInfo> {
+    let syntax = &grammar.syntax;
+    let mut result = vec![VariableInfo::default(); syntax.variables.len()];
+    let mut all_initialized = false;
+    loop {
+        let mut did_change = false;
+        for (i, variable) in syntax.variables.iter().enumerate() {
+            let mut info = result[i].clone();
+            for production in &variable.productions {
+                // Hidden rules later in the grammar have no information yet
+                // on the first pass, so their quantities would be wrong.
+                let uninitialized = !all_initialized
+                    && production.steps.iter().any(|step| {
+                        step.symbol.is_non_terminal()
+                            && step.symbol.index >= i
+                            && !is_visible(grammar, step)
+                    });
+                did_change |=
+                    add_production(grammar, &result, &mut info, production, uninitialized);
+            }
+            result[i] = info;
+        }
+        if !did_change && all_initialized {
+            break;
+        }
+        all_initialized = true;
+    }
+    result
+}
+
+/// The type of the child a production step builds.
+fn child_type(step: &ProductionStep) -> ChildType {
+    step.alias
+        .clone()
+        .map_or(ChildType::Normal(step.symbol), ChildType::Aliased)
+}
+
+fn is_visible(grammar: &PreparedGrammar, step: &ProductionStep) -> bool {
+    step.alias.is_some()
+        || grammar.symbol_info(step.symbol).1.is_visible()
+        || grammar.syntax.supertype_symbols.contains(&step.symbol)
+}
+
+fn is_named(grammar: &PreparedGrammar, step: &ProductionStep) -> bool {
+    match &step.alias {
+        Some(alias) => alias.named,
+        None => {
+            grammar.symbol_info(step.symbol).1 == VariableType::Named
+                || grammar.syntax.supertype_symbols.contains(&step.symbol)
+        }
+    }
+}
+
+/// Folds one production of a variable into its summary `info`, returning
+/// whether anything changed. Quantities are only merged once every hidden
+/// child has been summarized.
+fn add_production(
+    grammar: &PreparedGrammar,
+    result: &[VariableInfo],
+    info: &mut VariableInfo,
+    production: &Production,
+    uninitialized: bool,
+) -> bool {
+    let mut did_change = false;
+    let mut field_quantities: HashMap<&str, ChildQuantity> = HashMap::new();
+    let mut children_quantity = ChildQuantity::ZERO;
+    let mut unnamed_quantity = ChildQuantity::ZERO;
+
+    for step in &production.steps {
+        let child_type = child_type(step);
+        let visible = is_visible(grammar, step);
+        let hidden_rule =
+            (!visible && step.symbol.is_non_terminal()).then(|| &result[step.symbol.index]);
+
+        if visible {
+            did_change |= extend(&mut info.children.types, [child_type.clone()]);
+            children_quantity.append(ChildQuantity::ONE);
+        }
+
+        if let Some(field_name) = &step.field_name {
+            let field = info.fields.entry(field_name.clone()).or_default();
+            let quantity = field_quantities
+                .entry(field_name)
+                .or_insert(ChildQuantity::ZERO);
+            if let Some(child) = hidden_rule {
+                // A hidden rule in a field contributes all its children.
+                let children = &child.children;
+                did_change |= extend(&mut field.types, children.types.iter().cloned());
+                quantity.append(children.quantity);
+            } else {
+                if visible {
+                    did_change |= extend(&mut field.types, [child_type.clone()]);
+                }
+                quantity.append(ChildQuantity::ONE);
+            }
+        } else if visible && is_named(grammar, step) {
+            unnamed_quantity.append(ChildQuantity::ONE);
+            did_change |= extend(&mut info.children_without_fields.types, [child_type]);
+        }
+
+        if let Some(child) = hidden_rule {
+            for (field_name, child_field) in &child.fields {
+                field_quantities
+                    .entry(field_name)
+                    .or_insert(ChildQuantity::ZERO)
+                    .append(child_field.quantity);
+                let field = info.fields.entry(field_name.clone()).or_default();
+                did_change |= extend(&mut field.types, child_field.types.iter().cloned());
+            }
+            children_quantity.append(child.children.quantity);
+            did_change |= extend(
+                &mut info.children.types,
+                child.children.types.iter().cloned(),
+            );
+            let unnamed = &child.children_without_fields;
+            if step.field_name.is_none() && !unnamed.types.is_empty() {
+                unnamed_quantity.append(unnamed.quantity);
+                did_change |= extend(
+                    &mut info.children_without_fields.types,
+                    unnamed.types.iter().cloned(),
+                );
+            }
+        }
+    }
+
+    if !uninitialized {
+        did_change |= info.children.quantity.union(children_quantity);
+        did_change |= info
+            .children_without_fields
+            .quantity
+            .union(unnamed_quantity);
+        for (field_name, field) in &mut info.fields {
+            let quantity = field_quantities
+                .get(field_name.as_str())
+                .copied()
+                .unwrap_or(ChildQuantity::ZERO);
+            did_change |= field.quantity.union(quantity);
+        }
+    }
+    did_change
+}
+
+/// Drops empty child lists, and kinds already covered by a supertype listed
+/// alongside them.
+fn collapse_subtypes(
+    nodes: &mut BTreeMap<(String, bool), NodeInfo>,
+    mut subtype_map: Vec<(NodeKind, Vec<NodeKind>)>,
+) {
+    // Order supertypes so that nested ones are processed before their
+    // parents. The comparison is only a partial order, so the result depends
+    // on the starting order, which follows the rules as tree-sitter does.
+    subtype_map.sort_by(|(a, a_subtypes), (b, b_subtypes)| {
+        if b_subtypes.contains(a) {
+            std::cmp::Ordering::Less
+        } else if a_subtypes.contains(b) {
+            std::cmp::Ordering::Greater
+        } else {
+            std::cmp::Ordering::Equal
+        }
+    });
+    let collapse_subtypes = |info: &mut ChildInfo| {
+        for (supertype, subtypes) in &subtype_map {
+            if info.types.contains(supertype) {
+                info.types.retain(|t| !subtypes.contains(t));
+            }
+        }
+    };
+    for node in nodes.values_mut() {
+        if node.children.as_ref().is_some_and(|c| c.types.is_empty()) {
+            node.children = None;
+        }
+        if let Some(children) = &mut node.children {
+            collapse_subtypes(children);
+        }
+        for field in node.fields.iter_mut().flat_map(BTreeMap::values_mut) {
+            collapse_subtypes(field);
+        }
+    }
+}
+
+/// Every alias (or `None` for the bare symbol) each symbol appears under.
+fn aliases_by_symbol(syntax: &SyntaxGrammar) -> HashMap<Symbol, BTreeSet<Option<Alias>>> {
+    let mut aliases_by_symbol: HashMap<Symbol, BTreeSet<Option<Alias>>> = HashMap::new();
+    for extra in &syntax.extra_symbols {
+        aliases_by_symbol.entry(*extra).or_default().insert(None);
+    }
+    for variable in &syntax.variables {
+        for production in &variable.productions {
+            for step in &production.steps {
+                aliases_by_symbol
+                    .entry(step.symbol)
+                    .or_default()
+                    .insert(step.alias.clone());
+            }
+        }
+    }
+    aliases_by_symbol.insert(Symbol::non_terminal(0), BTreeSet::from([None]));
+
+    aliases_by_symbol
+}
+
+fn node_kind(grammar: &PreparedGrammar, child: &ChildType) -> NodeKind {
+    match child {
+        ChildType::Aliased(alias) => NodeKind {
+            kind: alias.value.clone(),
+            named: alias.named,
+        },
+        ChildType::Normal(symbol) => {
+            let (name, kind) = grammar.symbol_info(*symbol);
+            NodeKind {
+                kind: name.to_string(),
+                named: kind == VariableType::Named
+                    || grammar.syntax.supertype_symbols.contains(symbol),
+            }
+        }
+    }
+}
+
+/// Merges a field summary into its `node-types.json` entry.
+fn populate(grammar: &PreparedGrammar, json: &mut ChildInfo, field: &FieldInfo) {
+    if field.types.is_empty() {
+        json.required = false;
+    } else {
+        json.multiple |= field.quantity.multiple;
+        json.required &= field.quantity.required;
+        json.types
+            .extend(field.types.iter().map(|child| node_kind(grammar, child)));
+        json.types.sort_unstable();
+        json.types.dedup();
+    }
+}
+
+/// Adds the named tokens and external tokens to `nodes`, returning the
+/// anonymous ones.
+fn token_infos(
+    grammar: &PreparedGrammar,
+    aliases_by_symbol: &HashMap<Symbol, BTreeSet<Option<Alias>>>,
+    extra_names: &BTreeSet<&str>,
+    nodes: &mut BTreeMap<(String, bool), NodeInfo>,
+) -> Vec<NodeInfo> {
+    let mut anonymous = Vec::new();
+    let syntax = &grammar.syntax;
+    let tokens = grammar
+        .lexical
+        .variables
+        .iter()
+        .enumerate()
+        .map(|(i, v)| (Symbol::terminal(i), v.name.as_str(), v.kind))
+        .chain(
+            syntax
+                .external_tokens
+                .iter()
+                .enumerate()
+                .map(|(i, t)| (Symbol::external(i), t.name.as_str(), t.kind)),
+        );
+    for (symbol, name, kind) in tokens {
+        for alias in aliases_by_symbol.get(&symbol).into_iter().flatten() {
+            let (name, kind) = match alias {
+                Some(alias) => (
+                    alias.value.as_str(),
+                    if alias.named {
+                        VariableType::Named
+                    } else {
+                        VariableType::Anonymous
+                    },
+                ),
+                None => (name, kind),
+            };
+            match kind {
+                VariableType::Named => {
+                    let node = nodes
+                        .entry((name.to_string(), true))
+                        .or_insert_with(|| NodeInfo::leaf(name, true, extra_names.contains(name)));
+                    if let Some(children) = &mut node.children {
+                        children.required = false;
+                    }
+                    for field in node.fields.iter_mut().flat_map(BTreeMap::values_mut) {
+                        field.required = false;
+                    }
+                }
+                VariableType::Anonymous => {
+                    anonymous.push(NodeInfo::leaf(name, false, extra_names.contains(name)));
+                }
+                VariableType::Hidden | VariableType::Auxiliary => {}
+            }
+        }
+    }
+    anonymous
+}
+
+/// Builds the `node-types.json` entries from the variable summaries.
+fn node_infos(grammar: &PreparedGrammar, info: &[VariableInfo]) -> Vec<NodeInfo> {
+    let syntax = &grammar.syntax;
+    let aliases_by_symbol = aliases_by_symbol(syntax);
+    let extra_names: BTreeSet<&str> = syntax
+        .extra_symbols
+        .iter()
+        .map(|symbol| grammar.symbol_info(*symbol).0)
+        .collect();
+
+    let mut nodes: BTreeMap<(String, bool), NodeInfo> = BTreeMap::new();
+    let mut subtype_map: Vec<(NodeKind, Vec<NodeKind>)> = Vec::new();
+    for (i, variable_info) in info.iter().enumerate() {
+        let symbol = Symbol::non_terminal(i);
+        let variable = &syntax.variables[i];
+        if syntax.supertype_symbols.contains(&symbol) {
+            let mut subtypes: Vec<NodeKind> = variable_info
+                .children
+                .types
+                .iter()
+                .map(|child| node_kind(grammar, child))
+                .collect();
+            subtypes.sort_unstable();
+            subtypes.dedup();
+            let node = nodes
+                .entry((variable.name.clone(), true))
+                .or_insert_with(|| NodeInfo::leaf(&variable.name, true, false));
+            subtype_map.push((
+                NodeKind {
+                    kind: variable.name.clone(),
+                    named: true,
+                },
+                subtypes.clone(),
+            ));
+            node.subtypes = Some(subtypes);
+            continue;
+        }
+        if syntax.variables_to_inline.contains(&symbol) {
+            continue;
+        }
+        for alias in aliases_by_symbol.get(&symbol).into_iter().flatten() {
+            let (kind, named) = match alias {
+                Some(alias) => (alias.value.as_str(), alias.named),
+                None if variable.kind.is_visible() => {
+                    (variable.name.as_str(), variable.kind == VariableType::Named)
+                }
+                None => continue,
+            };
+            let mut existed = true;
+            let node = nodes.entry((kind.to_string(), named)).or_insert_with(|| {
+                existed = false;
+                NodeInfo {
+                    root: i == 0,
+                    fields: Some(BTreeMap::new()),
+                    ..NodeInfo::leaf(kind, named, extra_names.contains(kind))
+                }
+            });
+            let fields = node.fields.get_or_insert_with(BTreeMap::new);
+            for (name, field) in &variable_info.fields {
+                let json = fields.entry(name.clone()).or_insert_with(|| ChildInfo {
+                    // A same-named node from another rule lacks this field.
+                    required: !existed,
+                    ..ChildInfo::default()
+                });
+                populate(grammar, json, field);
+            }
+            for (name, json) in fields.iter_mut() {
+                if !variable_info.fields.contains_key(name) {
+                    json.required = false;
+                }
+            }
+            populate(
+                grammar,
+                node.children.get_or_insert_with(ChildInfo::default),
+                &variable_info.children_without_fields,
+            );
+        }
+    }
+
+    collapse_subtypes(&mut nodes, subtype_map);
+
+    let anonymous = token_infos(grammar, &aliases_by_symbol, &extra_names, &mut nodes);
+
+    let mut result: Vec<NodeInfo> = nodes.into_values().chain(anonymous).collect();
+    result.sort_by(|a, b| {
+        b.subtypes
+            .is_some()
+            .cmp(&a.subtypes.is_some())
+            .then_with(|| a.fields.is_none().cmp(&b.fields.is_none()))
+            .then_with(|| a.kind.cmp(&b.kind))
+            .then_with(|| b.named.cmp(&a.named))
+    });
+    result.dedup();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_grammar;
+
+    #[test]
+    fn test_fields_children_and_supertypes() {
+        let grammar = parse_grammar(
+            r#"{
+                "name": "t",
+                "supertypes": ["_expression"],
+                "rules": {
+                    "program": {"type": "REPEAT", "content": {"type": "SYMBOL", "name": "_expression"}},
+                    "_expression": {"type": "CHOICE", "members": [
+                        {"type": "SYMBOL", "name": "number"},
+                        {"type": "SYMBOL", "name": "sum"}
+                    ]},
+                    "sum": {"type": "SEQ", "members": [
+                        {"type": "FIELD", "name": "left", "content": {"type": "SYMBOL", "name": "_expression"}},
+                        {"type": "STRING", "value": "+"},
+                        {"type": "FIELD", "name": "right", "content": {"type": "SYMBOL", "name": "_expression"}}
+                    ]},
+                    "number": {"type": "PATTERN", "value": "\\d+"}
+                }
+            }"#,
+        )
+        .unwrap();
+        let node_types = NodeTypes::from_grammar(&grammar).unwrap();
+        let kinds: Vec<_> = node_types.iter().map(|n| n.kind.as_str()).collect();
+        assert_eq!(kinds, ["_expression", "program", "sum", "+", "number"]);
+
+        let expression = NodeKind {
+            kind: "_expression".to_string(),
+            named: true,
+        };
+        let sum = node_types.get("sum", true).unwrap();
+        let left = &sum.fields.as_ref().unwrap()["left"];
+        assert!(left.required && !left.multiple);
+        assert_eq!(left.types, std::slice::from_ref(&expression));
+        assert!(sum.children.is_none());
+
+        let program = node_types.get("program", true).unwrap();
+        let children = program.children.as_ref().unwrap();
+        assert!(children.multiple && !children.required);
+        assert!(program.root);
+
+        let concrete: Vec<_> = node_types
+            .concrete_kinds(&[expression])
+            .into_iter()
+            .map(|k| k.kind)
+            .collect();
+        assert_eq!(concrete, ["number", "sum"]);
+    }
+}
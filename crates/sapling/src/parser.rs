@@ -0,0 +1,1036 @@
+//! An LR parser driven by a [`Language`]'s tables.
+//!
+//! The parser lexes one token at a time in the lex state its current parse
+//! state asks for, then shifts, reduces or accepts as the parse table says.
+//! Reductions build [`Node`]s bottom-up: hidden rules hand their children to
+//! the parent, aliases rename children, and fields are attached from the
+//! production's field map. Extras such as comments are shifted wherever
+//! they occur and end up in the smallest node that encloses them.
+//!
+//! In parse states where external tokens are valid, the grammar's
+//! [`ExternalScanner`] gets the first try at each token. One scanner is
+//! created per parse, and its state is restored from the last token it
+//! produced before each attempt.
+//!
+//! A parser can be limited to [included
+//! ranges](Parser::set_included_ranges) of a document, which is how a
+//! language embedded in another, such as JavaScript in HTML's `<script>`
+//! elements, is parsed: the ranges are read as one text, and the tree's
+//! positions are those of the whole document.
+//!
+//! Where a grammar declares a conflict, the table holds several actions and
+//! this parser follows the first.
+//!
+//! Syntax errors are recovered from as tree-sitter does, and weighed with
+//! its costs. The parser either inserts a zero-width `MISSING` token that
+//! the lookahead can follow, or pops subtrees off the stack and skips
+//! tokens until one is valid, wrapping what it passed over in an `ERROR`
+//! node. The cheapest recovery wins, after a few tokens past each one are
+//! parsed to check that it holds up. Only text that never recovers before
+//! the end of the input leaves the whole tree in an `ERROR`.
+
+use crate::input::Input;
+use crate::language::{Language, ParseAction, StateId, SymbolId, END_SYMBOL};
+use crate::lexer::{Chunks, Lexer, Position, WHOLE_DOCUMENT};
+use crate::scanner::ExternalScanner;
+use crate::tree::{Node, Range};
+use std::fmt;
+
+/// The cost of each error in a tree, as tree-sitter weighs recoveries.
+const ERROR_COST_PER_RECOVERY: u32 = 500;
+/// The cost of each `MISSING` token.
+const ERROR_COST_PER_MISSING_TREE: u32 = 110;
+/// The cost of each token or subtree skipped into an `ERROR`.
+const ERROR_COST_PER_SKIPPED_TREE: u32 = 100;
+/// The cost of each line skipped into an `ERROR`.
+const ERROR_COST_PER_SKIPPED_LINE: u32 = 30;
+/// The cost of each byte skipped into an `ERROR`.
+const ERROR_COST_PER_SKIPPED_CHAR: u32 = 1;
+/// How many stack entries recovery can pop, as tree-sitter's stack
+/// summaries go this deep.
+const MAX_RECOVERY_DEPTH: usize = 16;
+/// How many more tokens recovery skips looking for something cheaper than
+/// the best recovery found, as tree-sitter drops parses that fall this many
+/// skipped tokens behind another.
+const MAX_SKIPPED_PAST_BEST: usize = 16;
+/// How many tokens past a recovery are parsed to check that it holds up.
+const MAX_TRIAL_TOKENS: usize = 4;
+
+/// Parses text with a [`Language`].
+#[derive(Debug, Clone)]
+pub struct Parser<'l> {
+    language: &'l Language,
+    included_ranges: Vec<Range>,
+}
+
+/// Included ranges that are out of order or overlap, with the index of the
+/// first range that starts before the previous one ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IncludedRangesError(pub usize);
+
+impl fmt::Display for IncludedRangesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "included range {} starts before the previous range ends",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for IncludedRangesError {}
+
+/// A token read by the lexer.
+#[derive(Clone, Copy)]
+struct Token {
+    symbol: SymbolId,
+    start: Position,
+    end: Position,
+}
+
+/// The external scanner for one parse, and its state as of the last token
+/// it produced.
+struct Scanner {
+    scanner: Box<dyn ExternalScanner>,
+    state: Vec<u8>,
+}
+
+/// Text skipped during error recovery: a token, or characters no token
+/// starts with.
+struct Skipped {
+    symbol: Option<SymbolId>,
+    start: Position,
+    end: Position,
+}
+
+/// A way to go on parsing after a syntax error.
+enum Recovery {
+    /// Insert a zero-width token and keep the lookahead.
+    Missing(SymbolId),
+    /// Wrap the top `depth` stack entries and the first `skipped` skipped
+    /// tokens in an `ERROR`, and go on with `lookahead`.
+    Skip {
+        depth: usize,
+        skipped: usize,
+        lookahead: Token,
+        scanner_state: Option<Vec<u8>>,
+    },
+}
+
+/// A parse stack entry: the state reached and the nodes pushed with it.
+struct Entry {
+    state: StateId,
+    /// A visible node, or the children of a hidden one.
+    nodes: Vec<Node>,
+    visible: bool,
+    extra: bool,
+    start: Position,
+    end: Position,
+}
+
+impl<'l> Parser<'l> {
+    /// Creates a parser for `language`.
+    #[must_use]
+    pub fn new(language: &'l Language) -> Self {
+        Self {
+            language,
+            included_ranges: WHOLE_DOCUMENT.to_vec(),
+        }
+    }
+
+    /// The language this parser parses.
+    #[must_use]
+    pub fn language(&self) -> &'l Language {
+        self.language
+    }
+
+    /// Limits parsing to `ranges` of the input, which must be in order and
+    /// must not overlap. An empty slice includes the whole input again.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`IncludedRangesError`], leaving the ranges unchanged, if
+    /// a range ends before it starts or starts before the previous one ends.
+    pub fn set_included_ranges(&mut self, ranges: &[Range]) -> Result<(), IncludedRangesError> {
+        let mut previous_end = 0;
+        for (index, range) in ranges.iter().enumerate() {
+            if range.start_byte < previous_end || range.end_byte < range.start_byte {
+                return Err(IncludedRangesError(index));
+            }
+            previous_end = range.end_byte;
+        }
+        self.included_ranges = if ranges.is_empty() {
+            WHOLE_DOCUMENT.to_vec()
+        } else {
+            ranges.to_vec()
+        };
+        Ok(())
+    }
+
+    /// The ranges of the input this parser reads.
+    #[must_use]
+    pub fn included_ranges(&self) -> &[Range] {
+        &self.included_ranges
+    }
+
+    /// Parses the text read from `input` into a syntax tree.
+    ///
+    /// Text that does not match the grammar produces a tree containing an
+    /// `ERROR` node rather than failing.
+    pub fn parse(&mut self, input: impl Input) -> Node {
+        let mut source = Chunks::new(input);
+        let mut lexer = Lexer::new(&mut source, &self.included_ranges);
+        let mut scanner = self.language.external_scanner.map(|new| Scanner {
+            scanner: new(),
+            state: Vec::new(),
+        });
+        let start = lexer.position();
+        let mut stack = vec![Entry {
+            state: 0,
+            nodes: Vec::new(),
+            visible: false,
+            extra: false,
+            start,
+            end: start,
+        }];
+        let mut lookahead = self.lex(&mut lexer, scanner.as_mut(), 0, start);
+        loop {
+            let state = stack.last().map_or(0, |entry| entry.state);
+            let action =
+                lookahead.and_then(|token| self.language.actions(state, token.symbol).next());
+            match (lookahead, action) {
+                (Some(token), Some(ParseAction::Shift { state: next, extra })) => {
+                    let end = token.end;
+                    stack.push(self.leaf(token, if extra { state } else { next }, extra));
+                    let lex_state = stack.last().map_or(0, |entry| entry.state);
+                    lookahead = self.lex(&mut lexer, scanner.as_mut(), lex_state, end);
+                }
+                (
+                    _,
+                    Some(ParseAction::Reduce {
+                        symbol,
+                        child_count,
+                        production_id,
+                    }),
+                ) => {
+                    if !self.reduce(&mut stack, symbol, child_count, production_id) {
+                        return error(stack, &mut lexer);
+                    }
+                    // The end of an extra was reduced without reading the
+                    // lookahead, which is read now.
+                    if self.language.lex_mode(state).lex_state == StateId::MAX {
+                        let (state, end) = stack
+                            .last()
+                            .map_or((0, start), |entry| (entry.state, entry.end));
+                        lookahead = self.lex(&mut lexer, scanner.as_mut(), state, end);
+                    }
+                }
+                (_, Some(ParseAction::Accept)) => return self.accept(stack),
+                _ => {
+                    lookahead = self.recover(&mut stack, lookahead, &mut lexer, scanner.as_mut());
+                    if lookahead.is_none() {
+                        return error(stack, &mut lexer);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Recovers from a syntax error: `lookahead` has no action in the state
+    /// on top of `stack`, or no token could be read there. Returns the token
+    /// to go on parsing with, or `None` once the rest of the input has been
+    /// skipped into an `ERROR` on top of the stack without finding one.
+    ///
+    /// States that can only reduce do so first. Then the cheapest of these
+    /// recoveries wins:
+    ///
+    /// - inserting a `MISSING` token after which the lookahead reduces;
+    /// - popping up to [`MAX_RECOVERY_DEPTH`] entries off the stack and
+    ///   skipping tokens (or characters no token starts with) until a token
+    ///   is valid in the state uncovered, all of which go in one `ERROR`.
+    ///
+    /// Costs are tree-sitter's, so the two parse broken text alike. A
+    /// recovery after which the next few tokens do not parse costs another
+    /// error more. Skipping stops once skipping more could not be cheaper
+    /// than the best recovery found, or [`MAX_SKIPPED_PAST_BEST`] tokens
+    /// after it was found.
+    fn recover(
+        &self,
+        stack: &mut Vec<Entry>,
+        lookahead: Option<Token>,
+        lexer: &mut Lexer<'_>,
+        mut scanner: Option<&mut Scanner>,
+    ) -> Option<Token> {
+        while let Some(ParseAction::Reduce {
+            symbol,
+            child_count,
+            production_id,
+        }) = self.default_reduction(stack.last().map_or(0, |entry| entry.state))
+        {
+            if !self.reduce(stack, symbol, child_count, production_id) {
+                return None;
+            }
+        }
+        let (best, mut skipped) = self.search(stack, lookahead, lexer, scanner.as_deref_mut());
+        match best {
+            Some(Recovery::Missing(symbol)) => {
+                self.insert_missing(stack, symbol);
+                lookahead
+            }
+            Some(Recovery::Skip {
+                depth,
+                skipped: count,
+                lookahead,
+                scanner_state,
+            }) => {
+                skipped.truncate(count);
+                self.push_error(stack, depth, skipped);
+                if let (Some(scanner), Some(state)) = (scanner, scanner_state) {
+                    scanner.state = state;
+                }
+                Some(lookahead)
+            }
+            None => {
+                self.push_error(stack, 0, skipped);
+                None
+            }
+        }
+    }
+
+    /// Finds the cheapest recovery from the error on top of `stack`, and
+    /// returns it with the text skipped while looking for it.
+    fn search(
+        &self,
+        stack: &[Entry],
+        lookahead: Option<Token>,
+        lexer: &mut Lexer<'_>,
+        mut scanner: Option<&mut Scanner>,
+    ) -> (Option<Recovery>, Vec<Skipped>) {
+        let states: Vec<StateId> = stack
+            .iter()
+            .filter(|entry| !entry.extra)
+            .map(|entry| entry.state)
+            .collect();
+        let pop_starts = pop_starts(stack);
+        let at = stack
+            .last()
+            .map_or_else(|| lexer.position(), |entry| entry.end);
+
+        let scanner_state = scanner.as_ref().map(|scanner| scanner.state.clone());
+        let mut best = lookahead.and_then(|token| {
+            self.insertion(
+                &states,
+                token,
+                lexer,
+                scanner.as_deref_mut(),
+                scanner_state.as_ref(),
+            )
+        });
+        // How many tokens had been skipped when the best recovery was found.
+        let mut found_after = 0;
+        let mut skipped: Vec<Skipped> = Vec::new();
+        let mut position = at;
+        let mut path_state = scanner_state.clone();
+        loop {
+            let count = skipped.len();
+            for depth in usize::from(count == 0)..=pop_starts.len() {
+                let target = &states[..states.len() - depth];
+                let Some(token) = self.relex(
+                    lexer,
+                    scanner.as_deref_mut(),
+                    path_state.as_ref(),
+                    target[target.len() - 1],
+                    position,
+                ) else {
+                    continue;
+                };
+                if !self.can_shift(target, token.symbol) {
+                    continue;
+                }
+                let start = match (depth, skipped.first()) {
+                    (0, Some(first)) => first.start,
+                    (0, None) => at,
+                    _ => pop_starts[depth - 1],
+                };
+                let end = skipped.last().map_or(at, |last| last.end);
+                let mut cost = skip_cost(depth + count, start, end);
+                if best.as_ref().is_some_and(|(best, _)| cost >= *best) {
+                    continue;
+                }
+                let token_state = scanner.as_ref().map(|scanner| scanner.state.clone());
+                let target = target.to_vec();
+                if !self.parses_ahead(
+                    lexer,
+                    scanner.as_deref_mut(),
+                    token_state.as_ref(),
+                    target,
+                    token,
+                ) {
+                    cost =
+                        cost.saturating_add(ERROR_COST_PER_RECOVERY + ERROR_COST_PER_SKIPPED_TREE);
+                }
+                if best.as_ref().is_none_or(|(best, _)| cost < *best) {
+                    let recovery = Recovery::Skip {
+                        depth,
+                        skipped: count,
+                        lookahead: token,
+                        scanner_state: token_state,
+                    };
+                    best = Some((cost, recovery));
+                    found_after = count;
+                }
+            }
+            let next_cost = skip_cost(skipped.len() + 1, position, position);
+            let give_up = best.as_ref().is_some_and(|(best, _)| {
+                next_cost >= *best || skipped.len() >= found_after + MAX_SKIPPED_PAST_BEST
+            });
+            if give_up {
+                break;
+            }
+            if !self.skip(
+                lexer,
+                scanner.as_deref_mut(),
+                &mut path_state,
+                &states,
+                &mut skipped,
+                position,
+            ) {
+                break;
+            }
+            position = skipped.last().map_or(position, |last| last.end);
+        }
+
+        if let (Some(scanner), Some(state)) = (scanner, scanner_state) {
+            scanner.state = state;
+        }
+        (best.map(|(_, recovery)| recovery), skipped)
+    }
+
+    /// The cost of inserting a `MISSING` token before `token`, if one fits.
+    fn insertion(
+        &self,
+        states: &[StateId],
+        token: Token,
+        lexer: &mut Lexer<'_>,
+        scanner: Option<&mut Scanner>,
+        scanner_state: Option<&Vec<u8>>,
+    ) -> Option<(u32, Recovery)> {
+        let (symbol, states) = self.missing_symbol(states, token.symbol)?;
+        let mut cost = ERROR_COST_PER_RECOVERY + ERROR_COST_PER_MISSING_TREE;
+        if !self.parses_ahead(lexer, scanner, scanner_state, states, token) {
+            cost += ERROR_COST_PER_RECOVERY + ERROR_COST_PER_SKIPPED_TREE;
+        }
+        Some((cost, Recovery::Missing(symbol)))
+    }
+
+    /// The reduction a state makes whatever the lookahead, if it can do
+    /// nothing else.
+    fn default_reduction(&self, state: StateId) -> Option<ParseAction> {
+        let mut reduction = None;
+        for symbol in self.token_symbols() {
+            match self.language.actions(state, symbol).next() {
+                None | Some(ParseAction::Shift { extra: true, .. }) => {}
+                Some(action @ ParseAction::Reduce { .. }) => {
+                    if reduction.is_some_and(|reduction| reduction != action) {
+                        return None;
+                    }
+                    reduction = Some(action);
+                }
+                Some(_) => return None,
+            }
+        }
+        reduction
+    }
+
+    /// The first token that, inserted on top of `states`, leads to a state
+    /// where `lookahead` reduces and can then be shifted, with the states
+    /// after inserting it.
+    fn missing_symbol(
+        &self,
+        states: &[StateId],
+        lookahead: SymbolId,
+    ) -> Option<(SymbolId, Vec<StateId>)> {
+        self.token_symbols().find_map(|symbol| {
+            let mut states = states.to_vec();
+            let Some(ParseAction::Shift {
+                state,
+                extra: false,
+            }) = self.reductions(&mut states, symbol)
+            else {
+                return None;
+            };
+            states.push(state);
+            let reduces = matches!(
+                self.language.actions(state, lookahead).next(),
+                Some(ParseAction::Reduce { .. })
+            );
+            (reduces && self.can_shift(&states, lookahead)).then_some((symbol, states))
+        })
+    }
+
+    /// Whether the text after `token` parses, for up to
+    /// [`MAX_TRIAL_TOKENS`] tokens, once `token` is shifted on top of
+    /// `states`: a recovery that leads straight to another error costs at
+    /// least that error more.
+    fn parses_ahead(
+        &self,
+        lexer: &mut Lexer<'_>,
+        mut scanner: Option<&mut Scanner>,
+        scanner_state: Option<&Vec<u8>>,
+        mut states: Vec<StateId>,
+        mut token: Token,
+    ) -> bool {
+        if let (Some(scanner), Some(state)) = (scanner.as_deref_mut(), scanner_state) {
+            scanner.state.clone_from(state);
+        }
+        for _ in 0..MAX_TRIAL_TOKENS {
+            match self.reductions(&mut states, token.symbol) {
+                Some(ParseAction::Shift { state, extra }) => {
+                    if !extra {
+                        states.push(state);
+                    }
+                    let state = states.last().copied().unwrap_or(0);
+                    let Some(next) = self.lex(lexer, scanner.as_deref_mut(), state, token.end)
+                    else {
+                        return false;
+                    };
+                    token = next;
+                }
+                Some(ParseAction::Accept) => return true,
+                _ => return false,
+            }
+        }
+        true
+    }
+
+    /// Whether `symbol` can be shifted or accepted on top of `states`, after
+    /// the reductions it leads to.
+    fn can_shift(&self, states: &[StateId], symbol: SymbolId) -> bool {
+        let mut states = states.to_vec();
+        matches!(
+            self.reductions(&mut states, symbol),
+            Some(ParseAction::Shift { .. } | ParseAction::Accept)
+        )
+    }
+
+    /// Applies to `states` the reductions `symbol` leads to, and returns the
+    /// action that follows them, or `None` if `symbol` is an error there.
+    fn reductions(&self, states: &mut Vec<StateId>, symbol: SymbolId) -> Option<ParseAction> {
+        loop {
+            let state = *states.last()?;
+            match self.language.actions(state, symbol).next()? {
+                ParseAction::Reduce {
+                    symbol: rule,
+                    child_count,
+                    ..
+                } => {
+                    let remaining = states.len().checked_sub(usize::from(child_count))?;
+                    states.truncate(remaining.max(1));
+                    let next = self.language.goto(*states.last()?, rule)?;
+                    states.push(next);
+                }
+                action => return Some(action),
+            }
+        }
+    }
+
+    /// Does the reductions `symbol` leads to, then pushes a zero-width
+    /// `MISSING` node for it where the last entry ends.
+    fn insert_missing(&self, stack: &mut Vec<Entry>, symbol: SymbolId) {
+        while let Some(action) = stack
+            .last()
+            .and_then(|entry| self.language.actions(entry.state, symbol).next())
+        {
+            match action {
+                ParseAction::Reduce {
+                    symbol: rule,
+                    child_count,
+                    production_id,
+                } => {
+                    if !self.reduce(stack, rule, child_count, production_id) {
+                        return;
+                    }
+                }
+                ParseAction::Shift { state, .. } => {
+                    let at = stack
+                        .last()
+                        .map_or_else(Position::default, |entry| entry.end);
+                    let metadata = self.language.symbol_metadata(symbol);
+                    let nodes = if metadata.visible {
+                        vec![Node::missing(
+                            self.language.symbol_name(symbol),
+                            metadata.named,
+                            at.byte,
+                            at.point,
+                        )]
+                    } else {
+                        Vec::new()
+                    };
+                    stack.push(Entry {
+                        state,
+                        nodes,
+                        visible: metadata.visible,
+                        extra: false,
+                        start: at,
+                        end: at,
+                    });
+                    return;
+                }
+                ParseAction::Accept => return,
+            }
+        }
+    }
+
+    /// Pops `depth` entries and pushes them, followed by `skipped`, as one
+    /// `ERROR`. The `ERROR` is an extra, so it ends up in whichever node
+    /// encloses it.
+    fn push_error(&self, stack: &mut Vec<Entry>, depth: usize, skipped: Vec<Skipped>) {
+        let mut popped = Vec::new();
+        let mut remaining = depth;
+        while remaining > 0 && stack.len() > 1 {
+            let entry = stack.pop().expect("stack is not empty");
+            if !entry.extra {
+                remaining -= 1;
+            }
+            popped.push(entry);
+        }
+        popped.reverse();
+        let start = popped
+            .first()
+            .map(|entry| entry.start)
+            .or_else(|| skipped.first().map(|skipped| skipped.start));
+        let end = skipped
+            .last()
+            .map(|skipped| skipped.end)
+            .or_else(|| popped.last().map(|entry| entry.end));
+        let (Some(mut start), Some(end)) = (start, end) else {
+            return;
+        };
+        let mut children = Vec::new();
+        // An error straight after another one, with nothing parsed in
+        // between, extends it.
+        let follows_error = stack.last().is_some_and(|entry| {
+            entry.extra && stack.len() > 1 && entry.nodes.first().is_some_and(Node::is_error)
+        });
+        if follows_error {
+            let previous = stack.pop().expect("stack is not empty");
+            start = previous.start;
+            for node in previous.nodes {
+                if node.children().is_empty() {
+                    children.push(node);
+                } else {
+                    children.extend(node.children().iter().cloned());
+                }
+            }
+        }
+        let Some(below) = stack.last() else {
+            return;
+        };
+        children.extend(popped.into_iter().flat_map(|entry| entry.nodes));
+        for skipped in skipped {
+            let range = range(skipped.start, skipped.end);
+            match skipped.symbol {
+                Some(symbol) => {
+                    let metadata = self.language.symbol_metadata(symbol);
+                    if metadata.visible {
+                        children.push(Node::leaf(
+                            self.language.symbol_name(symbol),
+                            metadata.named,
+                            range,
+                        ));
+                    }
+                }
+                None => children.push(Node::unexpected(range)),
+            }
+        }
+        let node = if children.is_empty() {
+            Node::unexpected(range(start, end))
+        } else {
+            Node::error(children)
+        };
+        let state = below.state;
+        stack.push(Entry {
+            state,
+            nodes: vec![node],
+            visible: true,
+            extra: true,
+            start,
+            end,
+        });
+    }
+
+    /// Skips one token at `at`, lexed in the first of `states` from the top
+    /// that has one there, or else the characters up to where one starts.
+    /// Returns `false` at the end of the input.
+    fn skip(
+        &self,
+        lexer: &mut Lexer<'_>,
+        mut scanner: Option<&mut Scanner>,
+        path_state: &mut Option<Vec<u8>>,
+        states: &[StateId],
+        skipped: &mut Vec<Skipped>,
+        at: Position,
+    ) -> bool {
+        let depth = states.len().min(MAX_RECOVERY_DEPTH + 1);
+        for &state in states.iter().rev().take(depth) {
+            let token = self.relex(
+                lexer,
+                scanner.as_deref_mut(),
+                path_state.as_ref(),
+                state,
+                at,
+            );
+            match token {
+                Some(token) if token.symbol == END_SYMBOL => return false,
+                Some(token) if token.end.byte > at.byte => {
+                    skipped.push(Skipped {
+                        symbol: Some(token.symbol),
+                        start: token.start,
+                        end: token.end,
+                    });
+                    *path_state = scanner.map(|scanner| scanner.state.clone());
+                    return true;
+                }
+                _ => {}
+            }
+        }
+        // No token starts here, so the next character is skipped.
+        lexer.reset(at);
+        let _ = self.language.lex(lexer, states[states.len() - 1]);
+        let start = lexer.token_start();
+        lexer.reset(start);
+        if lexer.eof() {
+            return false;
+        }
+        lexer.advance(false);
+        let end = lexer.position();
+        match skipped.last_mut() {
+            Some(last) if last.symbol.is_none() && last.end == start => last.end = end,
+            _ => skipped.push(Skipped {
+                symbol: None,
+                start,
+                end,
+            }),
+        }
+        true
+    }
+
+    /// Lexes at `at` in `state` with the scanner as it was after
+    /// `scanner_state`.
+    fn relex(
+        &self,
+        lexer: &mut Lexer<'_>,
+        mut scanner: Option<&mut Scanner>,
+        scanner_state: Option<&Vec<u8>>,
+        state: StateId,
+        at: Position,
+    ) -> Option<Token> {
+        if let (Some(scanner), Some(state)) = (&mut scanner, scanner_state) {
+            scanner.state.clone_from(state);
+        }
+        self.lex(lexer, scanner, state, at)
+    }
+
+    /// The grammar's tokens, other than the end of the input.
+    fn token_symbols(&self) -> impl Iterator<Item = SymbolId> {
+        (1..self.language.token_count).filter_map(|symbol| SymbolId::try_from(symbol).ok())
+    }
+
+    /// Reads the next token starting at `at`, or returns `None` if no token
+    /// valid in `state` starts there.
+    fn lex(
+        &self,
+        lexer: &mut Lexer<'_>,
+        scanner: Option<&mut Scanner>,
+        state: StateId,
+        at: Position,
+    ) -> Option<Token> {
+        let mode = self.language.lex_mode(state);
+        // A state finishing an extra that is a rule reduces it on the end
+        // symbol, without reading anything.
+        if mode.lex_state == StateId::MAX {
+            return Some(Token {
+                symbol: END_SYMBOL,
+                start: at,
+                end: at,
+            });
+        }
+        if let (Some(scanner), true) = (scanner, mode.external_lex_state != 0) {
+            lexer.reset(at);
+            scanner.scanner.deserialize(&scanner.state);
+            let valid = self.language.valid_external_tokens(mode.external_lex_state);
+            if scanner.scanner.scan(lexer, valid) {
+                let symbol = self
+                    .language
+                    .external_symbols
+                    .get(usize::from(lexer.result_symbol()));
+                if let Some(&symbol) = symbol {
+                    lexer.finish_external();
+                    scanner.state.clear();
+                    scanner.scanner.serialize(&mut scanner.state);
+                    return Some(Token {
+                        symbol,
+                        start: lexer.token_start(),
+                        end: lexer.token_end(),
+                    });
+                }
+            }
+        }
+        lexer.reset(at);
+        if self.language.lex(lexer, state) {
+            return Some(Token {
+                symbol: lexer.result_symbol(),
+                start: lexer.token_start(),
+                end: lexer.token_end(),
+            });
+        }
+        // Only separators may precede the end of the input.
+        let start = lexer.token_start();
+        lexer.reset(start);
+        lexer.eof().then_some(Token {
+            symbol: END_SYMBOL,
+            start,
+            end: start,
+        })
+    }
+
+    fn leaf(&self, token: Token, state: StateId, extra: bool) -> Entry {
+        let metadata = self.language.symbol_metadata(token.symbol);
+        let nodes = if metadata.visible {
+            vec![Node::leaf(
+                self.language.symbol_name(token.symbol),
+                metadata.named,
+                range(token.start, token.end),
+            )]
+        } else {
+            Vec::new()
+        };
+        Entry {
+            state,
+            nodes,
+            visible: metadata.visible,
+            extra,
+            start: token.start,
+            end: token.end,
+        }
+    }
+
+    /// Pops a production's children and pushes the node they form. Returns
+    /// `false` if the table has no goto for the result.
+    fn reduce(
+        &self,
+        stack: &mut Vec<Entry>,
+        symbol: SymbolId,
+        child_count: u16,
+        production_id: u16,
+    ) -> bool {
+        let language = self.language;
+        let finishes_extra = stack
+            .last()
+            .is_some_and(|entry| language.lex_mode(entry.state).lex_state == StateId::MAX);
+        // Extras after the last child belong to whatever comes next.
+        let mut trailing = Vec::new();
+        while stack.len() > 1 && stack.last().is_some_and(|entry| entry.extra) {
+            trailing.push(stack.pop().expect("stack is not empty"));
+        }
+        let mut popped = Vec::new();
+        let mut remaining = child_count;
+        while remaining > 0 && stack.len() > 1 {
+            let entry = stack.pop().expect("stack is not empty");
+            if !entry.extra {
+                remaining -= 1;
+            }
+            popped.push(entry);
+        }
+        popped.reverse();
+        let below = stack.last().expect("the start entry is never popped");
+        let (start, end) = match (popped.first(), popped.last()) {
+            (Some(first), Some(last)) => (first.start, last.end),
+            _ => (below.end, below.end),
+        };
+
+        let metadata = language.symbol_metadata(symbol);
+        // The children of a hidden supertype stand for it in the tree.
+        let supertype =
+            (metadata.supertype && !metadata.visible).then(|| language.symbol_name(symbol));
+        let fields = language.field_map(production_id);
+        let mut children = Vec::new();
+        let mut child_index = 0;
+        for entry in popped {
+            if entry.extra {
+                children.extend(entry.nodes);
+                continue;
+            }
+            let mut nodes = entry.nodes;
+            if let Some(alias) = language.alias(production_id, child_index) {
+                let name = language.symbol_name(alias);
+                let named = language.symbol_metadata(alias).named;
+                nodes = if entry.visible {
+                    nodes
+                        .into_iter()
+                        .map(|node| node.with_kind(name, named))
+                        .collect()
+                } else {
+                    vec![branch(name, named, nodes, entry.start, entry.end)]
+                };
+            }
+            for field in fields
+                .iter()
+                .filter(|field| usize::from(field.child_index) == child_index)
+            {
+                let name = language.field_name(field.field_id);
+                nodes = nodes
+                    .into_iter()
+                    .map(|node| {
+                        if node.field_name().is_some() {
+                            node
+                        } else {
+                            node.with_field(name)
+                        }
+                    })
+                    .collect();
+            }
+            if let Some(supertype) = supertype {
+                nodes = nodes
+                    .into_iter()
+                    .map(|node| node.with_supertype(supertype))
+                    .collect();
+            }
+            children.extend(nodes);
+            child_index += 1;
+        }
+
+        let nodes = if metadata.visible {
+            vec![branch(
+                language.symbol_name(symbol),
+                metadata.named,
+                children,
+                start,
+                end,
+            )]
+        } else {
+            children
+        };
+        let Some(state) = language.goto(below.state, symbol) else {
+            return false;
+        };
+        stack.push(Entry {
+            state,
+            nodes,
+            visible: metadata.visible,
+            // An extra rule goes back to the state it started in.
+            extra: finishes_extra && state == below.state,
+            start,
+            end,
+        });
+        for mut entry in trailing.into_iter().rev() {
+            entry.state = state;
+            stack.push(entry);
+        }
+        true
+    }
+
+    /// Builds the root from the start rule's node and the extras around it.
+    fn accept(&self, stack: Vec<Entry>) -> Node {
+        let symbol = self.root_symbol();
+        let empty = stack
+            .first()
+            .map_or_else(Range::default, |entry| range(entry.start, entry.start));
+        let mut root = None;
+        let mut before = Vec::new();
+        let mut after = Vec::new();
+        for entry in stack.into_iter().skip(1) {
+            if entry.extra {
+                if root.is_none() {
+                    before.extend(entry.nodes);
+                } else {
+                    after.extend(entry.nodes);
+                }
+            } else {
+                root = Some(entry);
+            }
+        }
+        let (mut children, empty) = match root {
+            Some(entry) if entry.visible => {
+                let node = entry.nodes.into_iter().next().expect("visible entry");
+                let range = node.range();
+                (node.children().to_vec(), range)
+            }
+            Some(entry) => (entry.nodes, range(entry.start, entry.end)),
+            None => (Vec::new(), empty),
+        };
+        children.splice(0..0, before);
+        children.extend(after);
+        let (name, named) = match symbol {
+            Some(symbol) => (
+                self.language.symbol_name(symbol),
+                self.language.symbol_metadata(symbol).named,
+            ),
+            None => ("", true),
+        };
+        if children.is_empty() {
+            return Node::leaf(name, named, empty);
+        }
+        Node::branch(name, named, children)
+    }
+
+    /// The start rule, which is the rule reduced when the table accepts.
+    fn root_symbol(&self) -> Option<SymbolId> {
+        let first_rule = SymbolId::try_from(self.language.token_count).ok()?;
+        (usize::from(first_rule) < self.language.symbol_count()).then_some(first_rule)
+    }
+}
+
+/// Wraps everything parsed so far, and the rest of the input from the
+/// lexer's position, in an `ERROR` node.
+fn error(stack: Vec<Entry>, lexer: &mut Lexer<'_>) -> Node {
+    let mut children: Vec<Node> = stack.into_iter().flat_map(|entry| entry.nodes).collect();
+    let start = lexer.position();
+    while !lexer.eof() {
+        lexer.advance(false);
+    }
+    let end = lexer.position();
+    if end.byte > start.byte {
+        children.push(Node::unexpected(range(start, end)));
+    }
+    Node::error(children)
+}
+
+/// Where the error starts when `depth` entries are popped off `stack`, at
+/// index `depth - 1`, for every depth the recovery searches.
+fn pop_starts(stack: &[Entry]) -> Vec<Position> {
+    stack
+        .iter()
+        .skip(1)
+        .rev()
+        .filter(|entry| !entry.extra)
+        .take(MAX_RECOVERY_DEPTH)
+        .map(|entry| entry.start)
+        .collect()
+}
+
+/// The cost of a recovery that skips `trees` tokens and subtrees, from
+/// `start` to `end`, into an `ERROR`.
+fn skip_cost(trees: usize, start: Position, end: Position) -> u32 {
+    let count = |count: usize| u32::try_from(count).unwrap_or(u32::MAX);
+    ERROR_COST_PER_SKIPPED_TREE
+        .saturating_mul(count(trees))
+        .saturating_add(ERROR_COST_PER_SKIPPED_CHAR.saturating_mul(count(end.byte - start.byte)))
+        .saturating_add(
+            ERROR_COST_PER_SKIPPED_LINE.saturating_mul(count(end.point.row - start.point.row)),
+        )
+        .saturating_add(ERROR_COST_PER_RECOVERY)
+}
+
+fn range(start: Position, end: Position) -> Range {
+    Range::new(start.byte, end.byte, start.point, end.point)
+}
+
+/// A node of `children`, or an empty one at `start` when there are none.
+fn branch(name: &str, named: bool, children: Vec<Node>, start: Position, end: Position) -> Node {
+    if children.is_empty() {
+        Node::leaf(name, named, range(start, end))
+    } else {
+        Node::branch(name, named, children)
+    }
+}
@@ -0,0 +1,1506 @@
+//! Reading the `parser.c` tree-sitter generates.
+//!
+//! Most tree-sitter grammars are published with the `src/parser.c` that
+//! `tree-sitter generate` wrote for them. [`read`](crate::parser_c::read)
+//! turns one into a [`Language`] without compiling it. The tables are
+//! static arrays, read as C initializers: `ts_parse_table` and
+//! `ts_small_parse_table` with the `ts_parse_actions` they point into,
+//! symbol names and metadata, field maps, alias sequences and lex modes.
+//! The lexer is the `ts_lex` function, a `switch` over lex states whose
+//! `case`s become the states of a
+//! [`LexTable`](crate::language::LexTable), together with
+//! `ts_lex_keywords` for grammars with a `word` token.
+//!
+//! Some parts of a `parser.c` are C code that is not read:
+//!
+//! - An external scanner is compiled separately, so a language with
+//!   external tokens only produces them once its
+//!   [`external_scanner`](Language::external_scanner) is set to one written
+//!   in Rust.
+//! - Reserved word sets are ignored, so keywords are always valid where
+//!   the parse table has actions for them.
+//! - A parse state's repetition shifts come after its other actions, as the
+//!   parser takes the first.
+
+use crate::language::{
+    FieldMapEntry, FieldMapSlice, Language, LexMode, LexTable, Lexing, ParseAction,
+    ParseActionEntry, ParseTableEntry, SymbolId, SymbolMetadata,
+};
+use crate::tables::lex::{LexState, LexTransition};
+use crate::tables::nfa::CharSet;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+
+/// The largest Unicode scalar value.
+const MAX_CHAR: u32 = char::MAX as u32;
+
+/// Why a `parser.c` could not be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParserCError {
+    /// The line of the problem, if it is on one.
+    pub line: Option<usize>,
+    /// What is wrong.
+    pub message: String,
+}
+
+impl ParserCError {
+    fn new(line: Option<usize>, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParserCError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {line}: {}", self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl std::error::Error for ParserCError {}
+
+type Result<T> = std::result::Result<T, ParserCError>;
+
+/// Reads the tables of a tree-sitter `parser.c` into a [`Language`].
+///
+/// # Errors
+///
+/// Returns a [`ParserCError`] if a table is missing or holds something a
+/// generated parser does not.
+pub fn read(source: &str) -> Result<Language> {
+    let tokens = tokenize(source)?;
+    File::scan(&tokens)?.language()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token<'s> {
+    Ident(&'s str),
+    Int(i64),
+    Str(String),
+    Punct(&'static str),
+}
+
+#[derive(Debug, Clone)]
+struct Lexeme<'s> {
+    token: Token<'s>,
+    line: usize,
+}
+
+/// Punctuation, longest first.
+const PUNCTUATION: &[&str] = &[
+    "==", "!=", "<=", ">=", "&&", "||", "->", "{", "}", "[", "]", "(", ")", ",", ";", "=", "<",
+    ">", "!", "-", "+", "*", "&", ".", ":", "?", "|", "/", "%", "~", "^",
+];
+
+/// Splits C source into tokens, keeping the numeric `#define`s as
+/// `NAME = value` and leaving out other preprocessor lines and comments.
+fn tokenize(source: &str) -> Result<Vec<Lexeme<'_>>> {
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut at_line_start = true;
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        if b == b'\n' {
+            line += 1;
+            at_line_start = true;
+            i += 1;
+            continue;
+        }
+        if b.is_ascii_whitespace() {
+            i += 1;
+            continue;
+        }
+        if b == b'#' && at_line_start {
+            let start = i;
+            // Directives continue over escaped line ends.
+            while i < bytes.len() && !(bytes[i] == b'\n' && bytes[i - 1] != b'\\') {
+                if bytes[i] == b'\n' {
+                    line += 1;
+                }
+                i += 1;
+            }
+            define(&source[start + 1..i], line, &mut tokens);
+            continue;
+        }
+        at_line_start = false;
+        if source[i..].starts_with("//") {
+            while i < bytes.len() && bytes[i] != b'\n' {
+                i += 1;
+            }
+        } else if source[i..].starts_with("/*") {
+            let end = source[i + 2..]
+                .find("*/")
+                .map_or(bytes.len(), |end| i + 2 + end + 2);
+            line += source[i..end].matches('\n').count();
+            i = end;
+        } else if b.is_ascii_alphabetic() || b == b'_' {
+            let start = i;
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                i += 1;
+            }
+            tokens.push(Lexeme {
+                token: Token::Ident(&source[start..i]),
+                line,
+            });
+        } else if b.is_ascii_digit() {
+            let start = i;
+            while i < bytes.len() && bytes[i].is_ascii_alphanumeric() {
+                i += 1;
+            }
+            let value = integer(&source[start..i])
+                .ok_or_else(|| ParserCError::new(Some(line), "invalid number"))?;
+            tokens.push(Lexeme {
+                token: Token::Int(value),
+                line,
+            });
+        } else if b == b'\'' || b == b'"' {
+            let (text, end) = quoted(source, i)
+                .ok_or_else(|| ParserCError::new(Some(line), "unterminated literal"))?;
+            i = end;
+            let token = if b == b'"' {
+                Token::Str(text)
+            } else {
+                let mut chars = text.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Token::Int(i64::from(u32::from(c))),
+                    _ => return Err(ParserCError::new(Some(line), "invalid character literal")),
+                }
+            };
+            tokens.push(Lexeme { token, line });
+        } else {
+            let punct = PUNCTUATION
+                .iter()
+                .find(|punct| source[i..].starts_with(**punct))
+                .ok_or_else(|| {
+                    let c = source[i..].chars().next().unwrap_or_default();
+                    ParserCError::new(Some(line), format!("unexpected character '{c}'"))
+                })?;
+            i += punct.len();
+            tokens.push(Lexeme {
+                token: Token::Punct(punct),
+                line,
+            });
+        }
+    }
+    Ok(tokens)
+}
+
+/// Adds `#define NAME <integer>` as the tokens `NAME = <integer> ;`.
+fn define<'s>(directive: &'s str, line: usize, tokens: &mut Vec<Lexeme<'s>>) {
+    let mut words = directive.split_whitespace();
+    let (Some("define"), Some(name), Some(value), None) =
+        (words.next(), words.next(), words.next(), words.next())
+    else {
+        return;
+    };
+    let Some(value) = integer(value) else {
+        return;
+    };
+    for token in [
+        Token::Ident(name),
+        Token::Punct("="),
+        Token::Int(value),
+        Token::Punct(";"),
+    ] {
+        tokens.push(Lexeme { token, line });
+    }
+}
+
+/// Parses a decimal, hexadecimal or octal C integer, with any suffix.
+fn integer(text: &str) -> Option<i64> {
+    let text = text.trim_end_matches(['u', 'U', 'l', 'L']);
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        i64::from_str_radix(hex, 16).ok()
+    } else if text.len() > 1 && text.starts_with('0') {
+        i64::from_str_radix(&text[1..], 8).ok()
+    } else {
+        text.parse().ok()
+    }
+}
+
+/// The text of the string or character literal starting at `start`, with
+/// escapes replaced, and the byte after it.
+fn quoted(source: &str, start: usize) -> Option<(String, usize)> {
+    let quote = source[start..].chars().next()?;
+    let mut chars = source[start + 1..].char_indices().peekable();
+    let mut text = String::new();
+    while let Some((offset, c)) = chars.next() {
+        match c {
+            '\n' => return None,
+            c if c == quote => return Some((text, start + 1 + offset + 1)),
+            '\\' => {
+                let (_, escape) = chars.next()?;
+                text.push(match escape {
+                    'n' => '\n',
+                    't' => '\t',
+                    'r' => '\r',
+                    'a' => '\u{7}',
+                    'b' => '\u{8}',
+                    'f' => '\u{c}',
+                    'v' => '\u{b}',
+                    'e' => '\u{1b}',
+                    'x' | 'U' => escaped(&mut chars, 16, 8, None)?,
+                    'u' => escaped(&mut chars, 16, 4, None)?,
+                    '0'..='7' => escaped(&mut chars, 8, 3, Some(escape))?,
+                    other => other,
+                });
+            }
+            c => text.push(c),
+        }
+    }
+    None
+}
+
+/// The character written as up to `max` digits in `radix`, starting with
+/// `first` if it has been read already.
+fn escaped(
+    chars: &mut std::iter::Peekable<std::str::CharIndices>,
+    radix: u32,
+    max: usize,
+    first: Option<char>,
+) -> Option<char> {
+    let mut digits: String = first.into_iter().collect();
+    while digits.len() < max {
+        match chars.peek() {
+            Some(&(_, digit)) if digit.is_digit(radix) => {
+                digits.push(digit);
+                chars.next();
+            }
+            _ => break,
+        }
+    }
+    u32::from_str_radix(&digits, radix)
+        .ok()
+        .and_then(char::from_u32)
+}
+
+/// A value in a C initializer.
+#[derive(Debug, Clone, PartialEq)]
+enum Value<'s> {
+    Int(i64),
+    Str(String),
+    /// A constant, or `NULL`, `true` or `false`.
+    Name(&'s str),
+    /// A macro such as `ACTIONS(3)` or `REDUCE(sym_rule, 2, 0, 1)`.
+    Call(&'s str, Vec<Item<'s>>),
+    List(Vec<Item<'s>>),
+}
+
+/// An element of an initializer list, with its designator if it has one.
+#[derive(Debug, Clone, PartialEq)]
+struct Item<'s> {
+    designator: Option<Designator<'s>>,
+    value: Value<'s>,
+    line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Designator<'s> {
+    Index(Value<'s>),
+    Field(&'s str),
+}
+
+/// A position in the token list.
+struct Cursor<'t, 's> {
+    tokens: &'t [Lexeme<'s>],
+    pos: usize,
+}
+
+impl<'t, 's> Cursor<'t, 's> {
+    fn new(tokens: &'t [Lexeme<'s>], pos: usize) -> Self {
+        Self { tokens, pos }
+    }
+
+    fn peek(&self) -> Option<&'t Token<'s>> {
+        self.peek_at(0)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&'t Token<'s>> {
+        self.tokens
+            .get(self.pos + offset)
+            .map(|lexeme| &lexeme.token)
+    }
+
+    fn line(&self) -> Option<usize> {
+        self.tokens
+            .get(self.pos)
+            .or_else(|| self.tokens.last())
+            .map(|lexeme| lexeme.line)
+    }
+
+    fn next(&mut self) -> Option<&'t Token<'s>> {
+        let token = self.peek();
+        self.pos += 1;
+        token
+    }
+
+    fn is(&self, punct: &str) -> bool {
+        matches!(self.peek(), Some(Token::Punct(p)) if *p == punct)
+    }
+
+    fn eat(&mut self, punct: &str) -> bool {
+        let found = self.is(punct);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, punct: &str) -> Result<()> {
+        if self.eat(punct) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected '{punct}'")))
+        }
+    }
+
+    fn ident(&mut self) -> Result<&'s str> {
+        match self.peek() {
+            Some(Token::Ident(name)) => {
+                self.pos += 1;
+                Ok(name)
+            }
+            _ => Err(self.error("expected a name")),
+        }
+    }
+
+    fn error(&self, message: impl Into<String>) -> ParserCError {
+        ParserCError::new(self.line(), message)
+    }
+
+    /// Moves past the bracketed group starting here, which opens with
+    /// `open`.
+    fn skip_group(&mut self, open: &str, close: &str) -> Result<()> {
+        self.expect(open)?;
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next() {
+                Some(Token::Punct(p)) if *p == open => depth += 1,
+                Some(Token::Punct(p)) if *p == close => depth -= 1,
+                Some(_) => {}
+                None => return Err(self.error(format!("expected '{close}'"))),
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves past the next `punct`.
+    fn skip_past(&mut self, punct: &str) -> Result<()> {
+        while !self.eat(punct) {
+            if self.next().is_none() {
+                return Err(self.error(format!("expected '{punct}'")));
+            }
+        }
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<Value<'s>> {
+        match self.peek() {
+            Some(Token::Punct("{")) => {
+                self.pos += 1;
+                Ok(Value::List(self.items("}")?))
+            }
+            Some(Token::Punct("-")) => {
+                self.pos += 1;
+                match self.value()? {
+                    Value::Int(value) => Ok(Value::Int(-value)),
+                    _ => Err(self.error("expected a number")),
+                }
+            }
+            Some(Token::Punct("(")) => {
+                // A cast, as in `(TSStateId)(-1)`, or parentheses.
+                let is_cast = matches!(self.peek_at(1), Some(Token::Ident(_)))
+                    && matches!(self.peek_at(2), Some(Token::Punct(")")))
+                    && matches!(
+                        self.peek_at(3),
+                        Some(Token::Int(_) | Token::Ident(_) | Token::Punct("(" | "-"))
+                    );
+                if is_cast {
+                    self.pos += 3;
+                    return self.value();
+                }
+                self.pos += 1;
+                let value = self.value()?;
+                self.expect(")")?;
+                Ok(value)
+            }
+            Some(Token::Int(value)) => {
+                self.pos += 1;
+                Ok(Value::Int(*value))
+            }
+            Some(Token::Str(text)) => {
+                self.pos += 1;
+                Ok(Value::Str(text.clone()))
+            }
+            Some(Token::Ident(name)) => {
+                self.pos += 1;
+                if self.eat("(") {
+                    Ok(Value::Call(name, self.items(")")?))
+                } else {
+                    Ok(Value::Name(name))
+                }
+            }
+            _ => Err(self.error("expected a value")),
+        }
+    }
+
+    /// The comma-separated items up to `close`, which is consumed.
+    fn items(&mut self, close: &str) -> Result<Vec<Item<'s>>> {
+        let mut items = Vec::new();
+        while !self.eat(close) {
+            let line = self.line().unwrap_or_default();
+            let designator = if self.eat("[") {
+                let index = self.value()?;
+                self.expect("]")?;
+                self.expect("=")?;
+                Some(Designator::Index(index))
+            } else if self.is(".") && matches!(self.peek_at(2), Some(Token::Punct("="))) {
+                self.pos += 1;
+                let field = self.ident()?;
+                self.pos += 1;
+                Some(Designator::Field(field))
+            } else {
+                None
+            };
+            let value = self.value()?;
+            items.push(Item {
+                designator,
+                value,
+                line,
+            });
+            if !self.eat(",") && !self.is(close) {
+                return Err(self.error(format!("expected ',' or '{close}'")));
+            }
+        }
+        Ok(items)
+    }
+}
+
+/// A function definition: its parameter names and the tokens of its body.
+struct Function<'t, 's> {
+    params: Vec<&'s str>,
+    body: Cursor<'t, 's>,
+}
+
+/// The definitions in a `parser.c` that the tables are read from.
+struct File<'t, 's> {
+    /// Enumerators and `#define`s.
+    constants: HashMap<&'s str, i64>,
+    arrays: HashMap<&'s str, Value<'s>>,
+    functions: HashMap<&'s str, Function<'t, 's>>,
+    keyword_capture_token: Option<&'s str>,
+}
+
+impl<'t, 's> File<'t, 's> {
+    fn scan(tokens: &'t [Lexeme<'s>]) -> Result<Self> {
+        let mut file = Self {
+            constants: HashMap::from([("ts_builtin_sym_end", 0)]),
+            arrays: HashMap::new(),
+            functions: HashMap::new(),
+            keyword_capture_token: None,
+        };
+        let mut cursor = Cursor::new(tokens, 0);
+        let mut depth = 0usize;
+        while let Some(token) = cursor.peek() {
+            match token {
+                Token::Ident("enum") => {
+                    cursor.pos += 1;
+                    file.enumeration(&mut cursor)?;
+                }
+                Token::Ident("keyword_capture_token")
+                    if matches!(cursor.peek_at(1), Some(Token::Punct("="))) =>
+                {
+                    cursor.pos += 2;
+                    file.keyword_capture_token = Some(cursor.ident()?);
+                }
+                // The declarations in `extern "C" { ... }` are still at the
+                // top level.
+                Token::Ident("extern")
+                    if matches!(cursor.peek_at(1), Some(Token::Str(_)))
+                        && matches!(cursor.peek_at(2), Some(Token::Punct("{"))) =>
+                {
+                    cursor.pos += 3;
+                }
+                Token::Ident(name) if depth == 0 => {
+                    cursor.pos += 1;
+                    file.definition(name, &mut cursor)?;
+                }
+                Token::Punct("{") => {
+                    depth += 1;
+                    cursor.pos += 1;
+                }
+                Token::Punct("}") => {
+                    depth = depth.saturating_sub(1);
+                    cursor.pos += 1;
+                }
+                _ => cursor.pos += 1,
+            }
+        }
+        Ok(file)
+    }
+
+    /// Reads the enumerators of an `enum`, after the keyword.
+    fn enumeration(&mut self, cursor: &mut Cursor<'t, 's>) -> Result<()> {
+        if matches!(cursor.peek(), Some(Token::Ident(_))) {
+            cursor.pos += 1;
+        }
+        if !cursor.eat("{") {
+            return Ok(());
+        }
+        let mut next = 0;
+        while !cursor.eat("}") {
+            let name = cursor.ident()?;
+            if cursor.eat("=") {
+                let line = cursor.line();
+                next = self.int(&cursor.value()?, line)?;
+            }
+            self.constants.insert(name, next);
+            next += 1;
+            if !cursor.eat(",") && !cursor.is("}") {
+                return Err(cursor.error("expected ',' or '}'"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads what follows a name at the top level: a `#define`'s value, an
+    /// array's initializer, or a function's parameters and body.
+    fn definition(&mut self, name: &'s str, cursor: &mut Cursor<'t, 's>) -> Result<()> {
+        if cursor.is("=") && matches!(cursor.peek_at(1), Some(Token::Int(_))) {
+            cursor.pos += 1;
+            let line = cursor.line();
+            let value = cursor.value()?;
+            self.constants.insert(name, self.int(&value, line)?);
+        } else if cursor.is("[") {
+            while cursor.is("[") {
+                cursor.skip_group("[", "]")?;
+            }
+            if cursor.eat("=") && cursor.is("{") {
+                self.arrays.insert(name, cursor.value()?);
+            }
+        } else if cursor.is("(") {
+            let start = cursor.pos;
+            cursor.skip_group("(", ")")?;
+            if !cursor.is("{") {
+                return Ok(());
+            }
+            // The parameter names are the last name before each comma.
+            let mut params = Vec::new();
+            let mut last = None;
+            for lexeme in &cursor.tokens[start + 1..cursor.pos] {
+                match &lexeme.token {
+                    Token::Ident(name) => last = Some(*name),
+                    Token::Punct("," | ")") => params.extend(last.take()),
+                    _ => {}
+                }
+            }
+            // The body is scanned too, for the `TSLanguage` fields.
+            let body = Cursor::new(cursor.tokens, cursor.pos);
+            self.functions.insert(name, Function { params, body });
+        }
+        Ok(())
+    }
+
+    fn int(&self, value: &Value<'s>, line: Option<usize>) -> Result<i64> {
+        match value {
+            Value::Int(value) => Ok(*value),
+            Value::Name("true") => Ok(1),
+            Value::Name("false" | "NULL") => Ok(0),
+            Value::Name(name) => self
+                .constants
+                .get(name)
+                .copied()
+                .ok_or_else(|| ParserCError::new(line, format!("unknown constant '{name}'"))),
+            Value::Call("SMALL_STATE", args) => {
+                let state = self.int(&arg(args, 0, line)?.value, line)?;
+                Ok(state - self.constant("LARGE_STATE_COUNT")?)
+            }
+            Value::Call("ACTIONS" | "STATE", args) => self.int(&arg(args, 0, line)?.value, line),
+            _ => Err(ParserCError::new(line, "expected a number")),
+        }
+    }
+
+    fn narrow<T: TryFrom<i64>>(&self, value: &Value<'s>, line: Option<usize>) -> Result<T> {
+        let value = self.int(value, line)?;
+        T::try_from(value).map_err(|_| ParserCError::new(line, format!("{value} is out of range")))
+    }
+
+    fn constant(&self, name: &str) -> Result<i64> {
+        self.constants
+            .get(name)
+            .copied()
+            .ok_or_else(|| ParserCError::new(None, format!("missing {name}")))
+    }
+
+    fn array(&self, name: &str) -> Result<&[Item<'s>]> {
+        self.optional_array(name)
+            .ok_or_else(|| ParserCError::new(None, format!("missing {name}")))
+    }
+
+    fn optional_array(&self, name: &str) -> Option<&[Item<'s>]> {
+        match self.arrays.get(name) {
+            Some(Value::List(items)) => Some(items),
+            _ => None,
+        }
+    }
+
+    /// The items of an array with their indices, following designators.
+    fn elements<'a>(&self, items: &'a [Item<'s>]) -> Result<Vec<(usize, &'a Item<'s>)>> {
+        let mut elements = Vec::with_capacity(items.len());
+        let mut index = 0;
+        for item in items {
+            if let Some(Designator::Index(designator)) = &item.designator {
+                index = self.narrow(designator, Some(item.line))?;
+            }
+            elements.push((index, item));
+            index += 1;
+        }
+        Ok(elements)
+    }
+
+    /// The value of the field `name` of a struct initializer.
+    fn field<'a>(items: &'a [Item<'s>], name: &str) -> Option<&'a Value<'s>> {
+        items
+            .iter()
+            .find(|item| matches!(item.designator, Some(Designator::Field(field)) if field == name))
+            .map(|item| &item.value)
+    }
+
+    fn flag(&self, items: &[Item<'s>], name: &str, line: usize) -> Result<bool> {
+        Self::field(items, name).map_or(Ok(false), |value| Ok(self.int(value, Some(line))? != 0))
+    }
+
+    fn language(&self) -> Result<Language> {
+        let symbol_names = self.symbol_names()?;
+        let symbol_count = symbol_names.len();
+        let token_count = usize::try_from(self.constant("TOKEN_COUNT")?)
+            .map_err(|_| ParserCError::new(None, "invalid TOKEN_COUNT"))?;
+        let lex_modes = self.lex_modes()?;
+        let (parse_actions, action_indices) = self.parse_actions()?;
+        let (parse_states, parse_table) =
+            self.parse_table(lex_modes.len(), token_count, &action_indices)?;
+        let production_count = self
+            .constants
+            .get("PRODUCTION_ID_COUNT")
+            .map_or(0, |&count| usize::try_from(count).unwrap_or(0));
+        let max_alias_sequence_length = self
+            .constants
+            .get("MAX_ALIAS_SEQUENCE_LENGTH")
+            .map_or(0, |&length| usize::try_from(length).unwrap_or(0));
+        let (field_map_slices, field_map_entries) = self.field_maps(production_count)?;
+        let (external_symbols, external_scanner_states) = self.external_tokens()?;
+        let keyword_capture_token = self
+            .keyword_capture_token
+            .map(|name| self.narrow(&Value::Name(name), None))
+            .transpose()?;
+        let keyword_states = match self.functions.get("ts_lex_keywords") {
+            Some(function) if keyword_capture_token.is_some() => self.lex_states(function)?,
+            _ => Vec::new(),
+        };
+        let lex = self
+            .functions
+            .get("ts_lex")
+            .ok_or_else(|| ParserCError::new(None, "missing ts_lex"))?;
+        // The parser indexes rows by these states, so they must exist.
+        let shifts = parse_actions.iter().filter_map(|entry| match entry {
+            ParseActionEntry::Action(ParseAction::Shift { state, .. }) => Some(*state),
+            _ => None,
+        });
+        let gotos = parse_table
+            .iter()
+            .filter(|entry| usize::from(entry.symbol) >= token_count)
+            .map(|entry| entry.value);
+        if let Some(state) = shifts
+            .chain(gotos)
+            .find(|&state| usize::from(state) >= lex_modes.len())
+        {
+            return Err(ParserCError::new(
+                None,
+                format!("state {state} is out of range"),
+            ));
+        }
+        Ok(Language {
+            name: Cow::Owned(self.name()),
+            symbol_metadata: Cow::Owned(self.symbol_metadata(symbol_count)?),
+            symbol_names: Cow::Owned(symbol_names.into_iter().map(Cow::Owned).collect()),
+            token_count,
+            field_names: Cow::Owned(self.field_names()?),
+            parse_states: Cow::Owned(parse_states),
+            parse_table: Cow::Owned(parse_table),
+            parse_actions: Cow::Owned(parse_actions),
+            alias_sequences: Cow::Owned(
+                self.alias_sequences(production_count, max_alias_sequence_length)?,
+            ),
+            max_alias_sequence_length,
+            field_map_slices: Cow::Owned(field_map_slices),
+            field_map_entries: Cow::Owned(field_map_entries),
+            lex_modes: Cow::Owned(lex_modes),
+            lexing: Lexing::Table(LexTable {
+                states: self.lex_states(lex)?,
+                keyword_capture_token,
+                keyword_states,
+            }),
+            external_symbols: Cow::Owned(external_symbols),
+            external_scanner_states: Cow::Owned(external_scanner_states),
+            external_scanner: None,
+        })
+    }
+
+    /// The grammar's name, from its `tree_sitter_<name>` function.
+    fn name(&self) -> String {
+        self.functions
+            .keys()
+            .filter_map(|name| name.strip_prefix("tree_sitter_"))
+            .find(|name| !name.contains("_external_scanner_"))
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    fn symbol_names(&self) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        for (index, item) in self.elements(self.array("ts_symbol_names")?)? {
+            let Value::Str(name) = &item.value else {
+                return Err(ParserCError::new(Some(item.line), "expected a string"));
+            };
+            if names.len() <= index {
+                names.resize(index + 1, String::new());
+            }
+            names[index].clone_from(name);
+        }
+        Ok(names)
+    }
+
+    fn symbol_metadata(&self, symbol_count: usize) -> Result<Vec<SymbolMetadata>> {
+        let mut metadata = vec![SymbolMetadata::default(); symbol_count];
+        for (index, item) in self.elements(self.array("ts_symbol_metadata")?)? {
+            let (Value::List(fields), Some(entry)) = (&item.value, metadata.get_mut(index)) else {
+                return Err(ParserCError::new(
+                    Some(item.line),
+                    "invalid symbol metadata",
+                ));
+            };
+            *entry = SymbolMetadata {
+                visible: self.flag(fields, "visible", item.line)?,
+                named: self.flag(fields, "named", item.line)?,
+                supertype: self.flag(fields, "supertype", item.line)?,
+            };
+        }
+        Ok(metadata)
+    }
+
+    /// The field names, leaving out the `NULL` at index 0, as sapling
+    /// numbers fields from 0.
+    fn field_names(&self) -> Result<Vec<Cow<'static, str>>> {
+        let mut names = Vec::new();
+        for (index, item) in
+            self.elements(self.optional_array("ts_field_names").unwrap_or_default())?
+        {
+            let Value::Str(name) = &item.value else {
+                continue;
+            };
+            let Some(index) = index.checked_sub(1) else {
+                continue;
+            };
+            if names.len() <= index {
+                names.resize(index + 1, Cow::Borrowed(""));
+            }
+            names[index] = Cow::Owned(name.clone());
+        }
+        Ok(names)
+    }
+
+    fn lex_modes(&self) -> Result<Vec<LexMode>> {
+        let mut modes = Vec::new();
+        for (index, item) in self.elements(self.array("ts_lex_modes")?)? {
+            let Value::List(fields) = &item.value else {
+                return Err(ParserCError::new(Some(item.line), "invalid lex mode"));
+            };
+            let line = Some(item.line);
+            // States that are never lexed in have a lex state of -1, which
+            // older generators give without a field name.
+            let lex_state = match Self::field(fields, "lex_state").or_else(|| {
+                fields
+                    .first()
+                    .filter(|item| item.designator.is_none())
+                    .map(|item| &item.value)
+            }) {
+                Some(value) => u16::try_from(self.int(value, line)?).unwrap_or(u16::MAX),
+                None => 0,
+            };
+            let external_lex_state = match Self::field(fields, "external_lex_state") {
+                Some(value) => self.narrow(value, line)?,
+                None => 0,
+            };
+            if modes.len() <= index {
+                modes.resize(index + 1, LexMode::default());
+            }
+            modes[index] = LexMode::new(lex_state, external_lex_state);
+        }
+        if let Ok(count) = self.constant("STATE_COUNT") {
+            modes.resize(usize::try_from(count).unwrap_or(0), LexMode::default());
+        }
+        if modes.len() > 1 {
+            modes.swap(0, 1);
+        }
+        Ok(modes)
+    }
+
+    /// The action lists, with the index each list of `ts_parse_actions`
+    /// has in them.
+    fn parse_actions(&self) -> Result<(Vec<ParseActionEntry>, HashMap<usize, u16>)> {
+        let items = self.elements(self.array("ts_parse_actions")?)?;
+        let mut entries = Vec::new();
+        let mut indices = HashMap::new();
+        let mut items = items.into_iter();
+        while let Some((index, item)) = items.next() {
+            let header = match &item.value {
+                Value::List(fields) => Self::field(fields, "entry"),
+                _ => None,
+            };
+            let Some(Value::List(header)) = header else {
+                return Err(ParserCError::new(
+                    Some(item.line),
+                    "expected an action list header",
+                ));
+            };
+            let count: usize = match Self::field(header, "count") {
+                Some(count) => self.narrow(count, Some(item.line))?,
+                None => 0,
+            };
+            let mut actions = Vec::with_capacity(count);
+            let mut repetitions = Vec::new();
+            for _ in 0..count {
+                let Some((_, item)) = items.next() else {
+                    return Err(ParserCError::new(Some(item.line), "missing parse actions"));
+                };
+                match self.parse_action(item)? {
+                    Some((action, true)) => repetitions.push(action),
+                    Some((action, false)) => actions.push(action),
+                    None => {}
+                }
+            }
+            actions.extend(repetitions);
+            let position = u16::try_from(entries.len())
+                .map_err(|_| ParserCError::new(Some(item.line), "too many parse actions"))?;
+            indices.insert(index, position);
+            entries.push(ParseActionEntry::Header {
+                count: u16::try_from(actions.len()).unwrap_or(u16::MAX),
+            });
+            entries.extend(actions.into_iter().map(ParseActionEntry::Action));
+        }
+        Ok((entries, indices))
+    }
+
+    /// One parse action, and whether it is a repetition shift. Error
+    /// recovery actions are left out.
+    fn parse_action(&self, item: &Item<'s>) -> Result<Option<(ParseAction, bool)>> {
+        let line = Some(item.line);
+        let Value::Call(name, args) = &item.value else {
+            return Err(ParserCError::new(line, "expected a parse action"));
+        };
+        let positional: Vec<&Value<'s>> = args
+            .iter()
+            .filter(|arg| arg.designator.is_none())
+            .map(|arg| &arg.value)
+            .collect();
+        let action = match *name {
+            "SHIFT" | "SHIFT_REPEAT" => ParseAction::Shift {
+                state: start_state_swapped(self.narrow(&arg(args, 0, line)?.value, line)?),
+                extra: false,
+            },
+            "SHIFT_EXTRA" => ParseAction::Shift {
+                state: 0,
+                extra: true,
+            },
+            "REDUCE" => {
+                // Older generators pass the production id by name.
+                let production_id = match (
+                    Self::field(args, "production_id"),
+                    positional.get(3).copied(),
+                ) {
+                    (Some(value), _) | (None, Some(value)) => self.narrow(value, line)?,
+                    (None, None) => 0,
+                };
+                ParseAction::Reduce {
+                    symbol: self.narrow(&arg(args, 0, line)?.value, line)?,
+                    child_count: self.narrow(&arg(args, 1, line)?.value, line)?,
+                    production_id,
+                }
+            }
+            "ACCEPT_INPUT" => ParseAction::Accept,
+            "RECOVER" => return Ok(None),
+            other => {
+                return Err(ParserCError::new(
+                    line,
+                    format!("unknown parse action '{other}'"),
+                ))
+            }
+        };
+        Ok(Some((action, *name == "SHIFT_REPEAT")))
+    }
+
+    /// The parse table: the rows of the large states, then those of the
+    /// small states, which list each value once with all its symbols.
+    fn parse_table(
+        &self,
+        state_count: usize,
+        token_count: usize,
+        action_indices: &HashMap<usize, u16>,
+    ) -> Result<(Vec<u32>, Vec<ParseTableEntry>)> {
+        let mut rows: Vec<Vec<ParseTableEntry>> = vec![Vec::new(); state_count];
+        let entry = |symbol: SymbolId, value: &Value<'s>, line: usize| -> Result<ParseTableEntry> {
+            let value: usize = self.narrow(value, Some(line))?;
+            let value = if usize::from(symbol) < token_count {
+                *action_indices.get(&value).ok_or_else(|| {
+                    ParserCError::new(Some(line), format!("no action list at {value}"))
+                })?
+            } else {
+                u16::try_from(start_state_swapped(value))
+                    .map_err(|_| ParserCError::new(Some(line), "state out of range"))?
+            };
+            Ok(ParseTableEntry::new(symbol, value))
+        };
+
+        for (state, item) in self.elements(self.array("ts_parse_table")?)? {
+            let (Value::List(cells), Some(row)) =
+                (&item.value, rows.get_mut(start_state_swapped(state)))
+            else {
+                return Err(ParserCError::new(
+                    Some(item.line),
+                    "invalid parse table row",
+                ));
+            };
+            for (symbol, cell) in self.elements(cells)? {
+                let symbol = SymbolId::try_from(symbol)
+                    .map_err(|_| ParserCError::new(Some(cell.line), "symbol out of range"))?;
+                row.push(entry(symbol, &cell.value, cell.line)?);
+            }
+        }
+
+        if let Some(small) = self.optional_array("ts_small_parse_table") {
+            let mut values: Vec<Option<&Item<'s>>> = Vec::new();
+            for (index, item) in self.elements(small)? {
+                if values.len() <= index {
+                    values.resize(index + 1, None);
+                }
+                values[index] = Some(item);
+            }
+            let large_state_count = usize::try_from(self.constant("LARGE_STATE_COUNT")?)
+                .map_err(|_| ParserCError::new(None, "invalid LARGE_STATE_COUNT"))?;
+            for (index, item) in self.elements(self.array("ts_small_parse_table_map")?)? {
+                let line = Some(item.line);
+                let Some(row) = rows.get_mut(start_state_swapped(large_state_count + index)) else {
+                    return Err(ParserCError::new(line, "state out of range"));
+                };
+                let mut offset: usize = self.narrow(&item.value, line)?;
+                let mut take = || {
+                    let value = values.get(offset).copied().flatten();
+                    offset += 1;
+                    value.ok_or_else(|| ParserCError::new(line, "small parse table ends early"))
+                };
+                let groups: usize = self.narrow(&take()?.value, line)?;
+                for _ in 0..groups {
+                    let value = take()?;
+                    let symbols: usize = self.narrow(&take()?.value, line)?;
+                    for _ in 0..symbols {
+                        let symbol = take()?;
+                        let symbol = self.narrow(&symbol.value, Some(symbol.line))?;
+                        row.push(entry(symbol, &value.value, value.line)?);
+                    }
+                }
+            }
+        }
+
+        let mut parse_states = Vec::with_capacity(state_count + 1);
+        let mut parse_table = Vec::new();
+        for mut row in rows {
+            parse_states.push(u32::try_from(parse_table.len()).unwrap_or(u32::MAX));
+            row.sort_by_key(|entry| entry.symbol);
+            row.dedup_by_key(|entry| entry.symbol);
+            parse_table.extend(row);
+        }
+        parse_states.push(u32::try_from(parse_table.len()).unwrap_or(u32::MAX));
+        Ok((parse_states, parse_table))
+    }
+
+    fn alias_sequences(&self, production_count: usize, length: usize) -> Result<Vec<SymbolId>> {
+        let mut sequences = vec![0; production_count * length];
+        let items = self
+            .optional_array("ts_alias_sequences")
+            .unwrap_or_default();
+        for (production, item) in self.elements(items)? {
+            let Value::List(aliases) = &item.value else {
+                return Err(ParserCError::new(Some(item.line), "invalid alias sequence"));
+            };
+            for (child, alias) in self.elements(aliases)? {
+                let index = production * length + child;
+                let Some(slot) = sequences.get_mut(index).filter(|_| child < length) else {
+                    return Err(ParserCError::new(Some(alias.line), "alias out of range"));
+                };
+                *slot = self.narrow(&alias.value, Some(alias.line))?;
+            }
+        }
+        Ok(sequences)
+    }
+
+    /// The field map slices and entries, leaving out the inherited entries:
+    /// sapling keeps the fields of hidden children on the children
+    /// themselves.
+    fn field_maps(
+        &self,
+        production_count: usize,
+    ) -> Result<(Vec<FieldMapSlice>, Vec<FieldMapEntry>)> {
+        let Some(slices) = self.optional_array("ts_field_map_slices") else {
+            return Ok((Vec::new(), Vec::new()));
+        };
+        let mut entries = Vec::new();
+        for (_, item) in self.elements(self.array("ts_field_map_entries")?)? {
+            let line = Some(item.line);
+            let Value::List(fields) = &item.value else {
+                return Err(ParserCError::new(line, "invalid field map entry"));
+            };
+            let field_id: u16 = self.narrow(&arg(fields, 0, line)?.value, line)?;
+            let child_index = self.narrow(&arg(fields, 1, line)?.value, line)?;
+            let inherited = self.flag(fields, "inherited", item.line)?;
+            entries.push((
+                FieldMapEntry::new(field_id.saturating_sub(1), child_index),
+                inherited,
+            ));
+        }
+        let mut field_map_slices = vec![FieldMapSlice::default(); production_count];
+        let mut field_map_entries = Vec::new();
+        for (production, item) in self.elements(slices)? {
+            let line = Some(item.line);
+            let Value::List(fields) = &item.value else {
+                return Err(ParserCError::new(line, "invalid field map slice"));
+            };
+            let field =
+                |name| Self::field(fields, name).map_or(Ok(0), |value| self.narrow(value, line));
+            let (index, length): (usize, usize) = (field("index")?, field("length")?);
+            let slice = entries
+                .get(index..index + length)
+                .ok_or_else(|| ParserCError::new(line, "field map slice out of range"))?;
+            let start = field_map_entries.len();
+            field_map_entries.extend(
+                slice
+                    .iter()
+                    .filter(|(_, inherited)| !inherited)
+                    .map(|(entry, _)| *entry),
+            );
+            if field_map_slices.len() <= production {
+                field_map_slices.resize(production + 1, FieldMapSlice::default());
+            }
+            field_map_slices[production] = FieldMapSlice {
+                index: u16::try_from(start).unwrap_or(u16::MAX),
+                length: u16::try_from(field_map_entries.len() - start).unwrap_or(u16::MAX),
+            };
+        }
+        Ok((field_map_slices, field_map_entries))
+    }
+
+    fn external_tokens(&self) -> Result<(Vec<SymbolId>, Vec<bool>)> {
+        let Some(symbol_map) = self.optional_array("ts_external_scanner_symbol_map") else {
+            return Ok((Vec::new(), Vec::new()));
+        };
+        let mut symbols = Vec::new();
+        for (index, item) in self.elements(symbol_map)? {
+            if symbols.len() <= index {
+                symbols.resize(index + 1, 0);
+            }
+            symbols[index] = self.narrow(&item.value, Some(item.line))?;
+        }
+        let count = symbols.len();
+        // Row 0 has no valid external tokens.
+        let mut states = vec![false; count];
+        let rows = self
+            .optional_array("ts_external_scanner_states")
+            .unwrap_or_default();
+        for (row, item) in self.elements(rows)? {
+            let Value::List(valid) = &item.value else {
+                return Err(ParserCError::new(
+                    Some(item.line),
+                    "invalid external scanner state",
+                ));
+            };
+            if states.len() < (row + 1) * count {
+                states.resize((row + 1) * count, false);
+            }
+            for (token, flag) in self.elements(valid)? {
+                if token >= count {
+                    return Err(ParserCError::new(
+                        Some(flag.line),
+                        "external token out of range",
+                    ));
+                }
+                states[row * count + token] = self.int(&flag.value, Some(flag.line))? != 0;
+            }
+        }
+        Ok((symbols, states))
+    }
+
+    /// Translates a lex function's `switch` into lex states, one per `case`.
+    fn lex_states(&self, function: &Function<'t, 's>) -> Result<Vec<LexState>> {
+        let mut cursor = Cursor::new(function.body.tokens, function.body.pos);
+        cursor.expect("{")?;
+        while cursor.peek() != Some(&Token::Ident("switch")) {
+            if cursor.next().is_none() {
+                return Err(cursor.error("expected a switch over lex states"));
+            }
+        }
+        cursor.pos += 1;
+        cursor.skip_group("(", ")")?;
+        cursor.expect("{")?;
+        let mut states: Vec<LexState> = Vec::new();
+        // The state the statements belong to, or `None` after `default:`.
+        let mut current = None;
+        loop {
+            let line = cursor.line();
+            let Some(token) = cursor.next() else {
+                return Err(cursor.error("expected '}'"));
+            };
+            match token {
+                Token::Punct("}") => break,
+                Token::Ident("case") => {
+                    let state: usize = self.narrow(&cursor.value()?, line)?;
+                    cursor.expect(":")?;
+                    if states.len() <= state {
+                        states.resize(state + 1, LexState::default());
+                    }
+                    current = Some(state);
+                }
+                Token::Ident("default") => {
+                    cursor.expect(":")?;
+                    current = None;
+                }
+                Token::Ident("ACCEPT_TOKEN") => {
+                    cursor.expect("(")?;
+                    let symbol: usize = self.narrow(&cursor.value()?, line)?;
+                    cursor.expect(")")?;
+                    if let Some(state) = current {
+                        // Tokens are numbered from 1, after the end of input,
+                        // which the parser recognises by itself.
+                        states[state].accept = symbol.checked_sub(1);
+                    }
+                }
+                Token::Ident(name @ ("ADVANCE" | "SKIP")) => {
+                    let chars = vec![(0, MAX_CHAR)];
+                    let transition = self.transition(&mut cursor, name, chars)?;
+                    if let Some(state) = current {
+                        states[state].transitions.extend(transition);
+                    }
+                }
+                Token::Ident("ADVANCE_MAP") => {
+                    cursor.expect("(")?;
+                    let line = cursor.line();
+                    let pairs = cursor.items(")")?;
+                    for pair in pairs.chunks(2) {
+                        let [c, next] = pair else {
+                            return Err(ParserCError::new(line, "odd ADVANCE_MAP"));
+                        };
+                        let c: u32 = self.narrow(&c.value, line)?;
+                        let transition = LexTransition {
+                            chars: CharSet::from_ranges([(c, c)]),
+                            state: self.narrow(&next.value, line)?,
+                            skip: false,
+                        };
+                        if let Some(state) = current {
+                            states[state].transitions.push(transition);
+                        }
+                    }
+                }
+                Token::Ident("if") => {
+                    cursor.expect("(")?;
+                    let chars = self.condition(&mut cursor, "lookahead")?;
+                    cursor.expect(")")?;
+                    let action = cursor.ident()?;
+                    if !matches!(action, "ADVANCE" | "SKIP") {
+                        return Err(cursor.error(format!("unsupported lexer action '{action}'")));
+                    }
+                    let transition = self.transition(&mut cursor, action, chars)?;
+                    if let Some(state) = current {
+                        states[state].transitions.extend(transition);
+                    }
+                }
+                Token::Ident("END_STATE" | "return") => cursor.skip_past(";")?,
+                Token::Punct(";") => {}
+                _ => {
+                    return Err(ParserCError::new(line, "unsupported lexer statement"));
+                }
+            }
+        }
+        Ok(states)
+    }
+
+    /// Reads the `(next)` of an `ADVANCE` or `SKIP` taken on `chars`. The
+    /// transition is left out if no character takes it, as for `eof`.
+    fn transition(
+        &self,
+        cursor: &mut Cursor<'t, 's>,
+        action: &str,
+        chars: Vec<(u32, u32)>,
+    ) -> Result<Option<LexTransition>> {
+        cursor.expect("(")?;
+        let line = cursor.line();
+        let state = self.narrow(&cursor.value()?, line)?;
+        cursor.expect(")")?;
+        cursor.eat(";");
+        Ok((!chars.is_empty()).then(|| LexTransition {
+            chars: CharSet::from_ranges(chars),
+            state,
+            skip: action == "SKIP",
+        }))
+    }
+
+    /// The characters a lexer condition holds for, where `var` is the
+    /// character tested: a `? :` expression over `||`, `&&`, `!` and
+    /// comparisons, with `eof` holding for none.
+    fn condition(&self, cursor: &mut Cursor<'t, 's>, var: &str) -> Result<Vec<(u32, u32)>> {
+        let test = self.disjunction(cursor, var)?;
+        if !cursor.eat("?") {
+            return Ok(test);
+        }
+        let then = self.condition(cursor, var)?;
+        cursor.expect(":")?;
+        let otherwise = self.condition(cursor, var)?;
+        Ok(union(
+            &intersection(&test, &then),
+            &intersection(&complement(&test), &otherwise),
+        ))
+    }
+
+    fn disjunction(&self, cursor: &mut Cursor<'t, 's>, var: &str) -> Result<Vec<(u32, u32)>> {
+        let mut chars = self.conjunction(cursor, var)?;
+        while cursor.eat("||") {
+            chars = union(&chars, &self.conjunction(cursor, var)?);
+        }
+        Ok(chars)
+    }
+
+    fn conjunction(&self, cursor: &mut Cursor<'t, 's>, var: &str) -> Result<Vec<(u32, u32)>> {
+        let mut chars = self.negation(cursor, var)?;
+        while cursor.eat("&&") {
+            chars = intersection(&chars, &self.negation(cursor, var)?);
+        }
+        Ok(chars)
+    }
+
+    fn negation(&self, cursor: &mut Cursor<'t, 's>, var: &str) -> Result<Vec<(u32, u32)>> {
+        if cursor.eat("!") {
+            return Ok(complement(&self.negation(cursor, var)?));
+        }
+        if cursor.eat("(") {
+            let chars = self.condition(cursor, var)?;
+            cursor.expect(")")?;
+            return Ok(chars);
+        }
+        let line = cursor.line();
+        match (cursor.peek(), cursor.peek_at(1)) {
+            (Some(Token::Ident("eof")), _) => {
+                cursor.pos += 1;
+                Ok(Vec::new())
+            }
+            (Some(Token::Ident("set_contains")), Some(Token::Punct("("))) => {
+                cursor.pos += 2;
+                let name = cursor.ident()?;
+                cursor.skip_past(")")?;
+                self.character_set(name, line)
+            }
+            (Some(Token::Ident(name)), Some(Token::Punct("("))) if *name != var => {
+                // An older generator's function testing one character.
+                cursor.pos += 2;
+                cursor.skip_past(")")?;
+                let function = self.functions.get(name).ok_or_else(|| {
+                    ParserCError::new(line, format!("unknown character set '{name}'"))
+                })?;
+                let mut body = Cursor::new(function.body.tokens, function.body.pos);
+                body.expect("{")?;
+                if body.ident()? != "return" {
+                    return Err(body.error("expected a return"));
+                }
+                let param = function.params.first().copied().unwrap_or_default();
+                self.condition(&mut body, param)
+            }
+            _ => self.comparison(cursor, var),
+        }
+    }
+
+    /// The characters `var` may be to satisfy a comparison with a constant.
+    fn comparison(&self, cursor: &mut Cursor<'t, 's>, var: &str) -> Result<Vec<(u32, u32)>> {
+        let line = cursor.line();
+        let operand = |cursor: &mut Cursor<'t, 's>| -> Result<Option<i64>> {
+            if cursor.peek() == Some(&Token::Ident(var)) {
+                cursor.pos += 1;
+                return Ok(None);
+            }
+            Ok(Some(self.int(&cursor.value()?, line)?))
+        };
+        let left = operand(cursor)?;
+        let operator = match cursor.next() {
+            Some(Token::Punct(operator @ ("==" | "!=" | "<" | "<=" | ">" | ">="))) => *operator,
+            _ => return Err(ParserCError::new(line, "unsupported lexer condition")),
+        };
+        let right = operand(cursor)?;
+        let (operator, value) = match (left, right) {
+            (None, Some(value)) => (operator, value),
+            // Turn `c <= lookahead` around.
+            (Some(value), None) => (
+                match operator {
+                    "<" => ">",
+                    "<=" => ">=",
+                    ">" => "<",
+                    ">=" => "<=",
+                    other => other,
+                },
+                value,
+            ),
+            _ => return Err(ParserCError::new(line, "unsupported lexer condition")),
+        };
+        let value = value.clamp(-1, i64::from(MAX_CHAR) + 1);
+        let below = |end: i64| u32::try_from(end).map_or(Vec::new(), |end| vec![(0, end)]);
+        let above = |start: i64| {
+            u32::try_from(start)
+                .ok()
+                .filter(|&start| start <= MAX_CHAR)
+                .map_or(Vec::new(), |start| vec![(start, MAX_CHAR)])
+        };
+        let equal = intersection(&below(value), &above(value));
+        Ok(match operator {
+            "==" => equal,
+            "!=" => complement(&equal),
+            "<" => below(value - 1),
+            "<=" => below(value),
+            ">" => above(value + 1),
+            _ => above(value),
+        })
+    }
+
+    /// The ranges of a `TSCharacterRange` array.
+    fn character_set(&self, name: &str, line: Option<usize>) -> Result<Vec<(u32, u32)>> {
+        let items = self
+            .optional_array(name)
+            .ok_or_else(|| ParserCError::new(line, format!("unknown character set '{name}'")))?;
+        let mut ranges = Vec::with_capacity(items.len());
+        for item in items {
+            let line = Some(item.line);
+            let Value::List(bounds) = &item.value else {
+                return Err(ParserCError::new(line, "invalid character range"));
+            };
+            ranges.push((
+                self.narrow(&arg(bounds, 0, line)?.value, line)?,
+                self.narrow(&arg(bounds, 1, line)?.value, line)?,
+            ));
+        }
+        Ok(CharSet::from_ranges(ranges).ranges)
+    }
+}
+
+/// Swaps parse states 0 and 1: tree-sitter starts in state 1, using state 0
+/// for error recovery, where sapling starts in state 0.
+fn start_state_swapped<T: From<u8> + PartialEq>(state: T) -> T {
+    if state == T::from(0) {
+        T::from(1)
+    } else if state == T::from(1) {
+        T::from(0)
+    } else {
+        state
+    }
+}
+
+/// The `index`th positional argument of a macro or struct initializer.
+fn arg<'a, 's>(items: &'a [Item<'s>], index: usize, line: Option<usize>) -> Result<&'a Item<'s>> {
+    items
+        .iter()
+        .filter(|item| item.designator.is_none())
+        .nth(index)
+        .ok_or_else(|| ParserCError::new(line, "missing argument"))
+}
+
+fn union(a: &[(u32, u32)], b: &[(u32, u32)]) -> Vec<(u32, u32)> {
+    CharSet::from_ranges(a.iter().chain(b).copied()).ranges
+}
+
+fn complement(set: &[(u32, u32)]) -> Vec<(u32, u32)> {
+    let mut ranges = Vec::new();
+    let mut next = 0;
+    for &(start, end) in set {
+        if start > next {
+            ranges.push((next, start - 1));
+        }
+        next = end.saturating_add(1);
+    }
+    if next <= MAX_CHAR {
+        ranges.push((next, MAX_CHAR));
+    }
+    ranges
+}
+
+fn intersection(a: &[(u32, u32)], b: &[(u32, u32)]) -> Vec<(u32, u32)> {
+    complement(&union(&complement(a), &complement(b)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lex_states(source: &str) -> Vec<LexState> {
+        let tokens = tokenize(source).unwrap();
+        let file = File::scan(&tokens).unwrap();
+        file.lex_states(&file.functions["ts_lex"]).unwrap()
+    }
+
+    #[test]
+    fn test_older_character_set_functions() {
+        let states = lex_states(
+            r"
+            static inline bool sym_identifier_character_set_1(int32_t c) {
+              return (c < 'a'
+                ? (c >= 'A' && c <= 'Z')
+                : (c <= 'z' || c == 0xe9));
+            }
+
+            static bool ts_lex(TSLexer *lexer, TSStateId state) {
+              START_LEXER();
+              switch (state) {
+                case 0:
+                  if (sym_identifier_character_set_1(lookahead)) ADVANCE(1);
+                  if (lookahead != 0 && lookahead != '\n') SKIP(0);
+                  END_STATE();
+                default:
+                  return false;
+              }
+            }
+            ",
+        );
+        let transitions = &states[0].transitions;
+        assert_eq!(
+            transitions[0].chars.ranges,
+            [(0x41, 0x5a), (0x61, 0x7a), (0xe9, 0xe9)]
+        );
+        assert!(!transitions[0].skip);
+        assert_eq!(transitions[1].chars.ranges, [(1, 9), (11, MAX_CHAR)]);
+        assert!(transitions[1].skip);
+    }
+
+    #[test]
+    fn test_literals() {
+        let tokens = tokenize(r#"'\'' '\\' 0x2028 017 "a\"bé""#).unwrap();
+        let values: Vec<_> = tokens.into_iter().map(|lexeme| lexeme.token).collect();
+        assert_eq!(
+            values,
+            [
+                Token::Int(39),
+                Token::Int(92),
+                Token::Int(0x2028),
+                Token::Int(15),
+                Token::Str("a\"bé".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,630 @@
+//! Tree-sitter queries.
+//!
+//! A [`Query`] is compiled from tree-sitter's S-expression query syntax, the
+//! language used by `highlights.scm`, `tags.scm` and friends. Compilation
+//! checks node kinds and field names against a [`Grammar`], so a typo or a
+//! renamed rule is reported up front rather than silently never matching.
+//! A [`QueryCursor`] then runs the query over a syntax tree, and
+//! [`validate()`](fn@validate) goes further than compilation, reporting
+//! patterns that the grammar's node types rule out.
+//!
+//! The text predicates `#eq?`, `#not-eq?`, `#any-eq?`, `#any-not-eq?`,
+//! `#match?`, `#not-match?`, `#any-match?`, `#any-not-match?`, `#any-of?`
+//! and `#not-any-of?` are evaluated by the cursor. `#set!` directives and
+//! `#is?`/`#is-not?` property predicates are exposed per pattern, and any
+//! other predicate is left for the caller via [`Query::general_predicates`].
+
+use crate::grammar::{Grammar, Rule, RuleType, RuleValue};
+use crate::language::Language;
+use regex::bytes::Regex;
+use std::collections::BTreeSet;
+use std::fmt;
+
+mod cursor;
+pub(crate) mod parser;
+mod validate;
+
+pub use cursor::{QueryCapture, QueryCursor, QueryMatch};
+pub use validate::validate;
+
+use parser::{NodeMatcher, RawArg, RawPredicate, Sequence, StepKind};
+
+/// The category of a [`QueryError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QueryErrorKind {
+    /// The query text is malformed.
+    Syntax,
+    /// A pattern names a node kind the grammar does not produce.
+    NodeType,
+    /// A pattern names a field the grammar does not define.
+    Field,
+    /// A predicate refers to a capture that does not exist.
+    Capture,
+    /// A predicate has the wrong number or kind of arguments.
+    Predicate,
+    /// A pattern can never match, such as a field on a top-level node.
+    Structure,
+}
+
+/// An error found while compiling a query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryError {
+    /// What kind of problem was found.
+    pub kind: QueryErrorKind,
+    /// The byte offset of the problem in the query source.
+    pub offset: usize,
+    /// The zero-based line of `offset`.
+    pub row: usize,
+    /// The zero-based byte column of `offset`.
+    pub column: usize,
+    /// A human-readable description.
+    pub message: String,
+}
+
+impl QueryError {
+    pub(crate) fn new(
+        source: &str,
+        offset: usize,
+        kind: QueryErrorKind,
+        message: impl Into<String>,
+    ) -> Self {
+        let before = &source[..offset.min(source.len())];
+        let row = before.matches('\n').count();
+        let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1);
+        Self {
+            kind,
+            offset,
+            row,
+            column,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "query error at {}:{}: {}",
+            self.row + 1,
+            self.column + 1,
+            self.message
+        )
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+/// An argument to a predicate that the query does not interpret itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryPredicateArg {
+    /// A capture, by index into [`Query::capture_names`].
+    Capture(usize),
+    /// A string or bare identifier.
+    String(String),
+}
+
+/// A predicate or directive left for the caller to interpret.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryPredicate {
+    /// The predicate name without its leading `#`, e.g. `"select-adjacent!"`.
+    pub operator: String,
+    /// The arguments, in order.
+    pub args: Vec<QueryPredicateArg>,
+}
+
+/// A key/value property set by `#set!` or tested by `#is?`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryProperty {
+    /// The property name.
+    pub key: String,
+    /// The property value, if one was given.
+    pub value: Option<String>,
+    /// The capture the property applies to, if one was given.
+    pub capture_id: Option<usize>,
+}
+
+/// A built-in predicate on capture text, evaluated by the cursor.
+#[derive(Debug, Clone)]
+pub(crate) enum TextPredicate {
+    /// `#eq?` and variants, comparing against a string or another capture.
+    Eq {
+        capture: usize,
+        other: RawArg,
+        negated: bool,
+        any: bool,
+    },
+    /// `#match?` and variants.
+    Match {
+        capture: usize,
+        regex: Regex,
+        negated: bool,
+        any: bool,
+    },
+    /// `#any-of?` and `#not-any-of?`.
+    AnyOf {
+        capture: usize,
+        values: Vec<String>,
+        negated: bool,
+    },
+}
+
+/// A compiled top-level pattern.
+#[derive(Debug, Clone)]
+pub(crate) struct Pattern {
+    pub(crate) sequence: Sequence,
+    pub(crate) start_byte: usize,
+    pub(crate) text_predicates: Vec<TextPredicate>,
+    property_settings: Vec<QueryProperty>,
+    property_predicates: Vec<(QueryProperty, bool)>,
+    general_predicates: Vec<QueryPredicate>,
+}
+
+/// A compiled set of query patterns.
+#[derive(Debug, Clone)]
+pub struct Query {
+    pub(crate) patterns: Vec<Pattern>,
+    capture_names: Vec<String>,
+}
+
+impl Query {
+    /// Compiles `source` against `grammar`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`QueryError`] if the query is malformed, names a node kind
+    /// or field that `grammar` does not define, references an undefined
+    /// capture, or passes bad arguments to a built-in predicate.
+    pub fn new(grammar: &Grammar, source: &str) -> Result<Self, QueryError> {
+        Self::compile(&Vocabulary::new(grammar), source)
+    }
+
+    /// Compiles `source` against the node kinds and fields of `language`,
+    /// for when only its tables are at hand, as with a generated module.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`QueryError`] as [`Query::new`] does.
+    pub fn for_language(language: &Language, source: &str) -> Result<Self, QueryError> {
+        Self::compile(&Vocabulary::from_language(language), source)
+    }
+
+    fn compile(vocabulary: &Vocabulary, source: &str) -> Result<Self, QueryError> {
+        let parsed = parser::parse(source)?;
+        for pattern in &parsed.patterns {
+            for step in &pattern.sequence.steps {
+                if step.field.is_some() {
+                    return Err(QueryError::new(
+                        source,
+                        step.offset,
+                        QueryErrorKind::Structure,
+                        "a top-level pattern cannot have a field",
+                    ));
+                }
+            }
+            vocabulary.check_sequence(source, &pattern.sequence)?;
+        }
+
+        let patterns = parsed
+            .patterns
+            .into_iter()
+            .map(|pattern| compile_pattern(source, pattern))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            patterns,
+            capture_names: parsed.capture_names,
+        })
+    }
+
+    /// The names of all captures, indexed by capture id.
+    #[must_use]
+    pub fn capture_names(&self) -> &[String] {
+        &self.capture_names
+    }
+
+    /// Looks up a capture id by name (without the `@`).
+    #[must_use]
+    pub fn capture_index_for_name(&self, name: &str) -> Option<usize> {
+        self.capture_names.iter().position(|n| n == name)
+    }
+
+    /// The number of top-level patterns.
+    #[must_use]
+    pub fn pattern_count(&self) -> usize {
+        self.patterns.len()
+    }
+
+    /// The byte offset where the pattern at `pattern_index` starts in the
+    /// query source.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pattern_index` is out of range.
+    #[must_use]
+    pub fn start_byte_for_pattern(&self, pattern_index: usize) -> usize {
+        self.patterns[pattern_index].start_byte
+    }
+
+    /// The properties set by `#set!` directives in a pattern.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pattern_index` is out of range.
+    #[must_use]
+    pub fn property_settings(&self, pattern_index: usize) -> &[QueryProperty] {
+        &self.patterns[pattern_index].property_settings
+    }
+
+    /// The `#is?` (`true`) and `#is-not?` (`false`) property predicates in a
+    /// pattern.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pattern_index` is out of range.
+    #[must_use]
+    pub fn property_predicates(&self, pattern_index: usize) -> &[(QueryProperty, bool)] {
+        &self.patterns[pattern_index].property_predicates
+    }
+
+    /// Predicates in a pattern that the query engine does not evaluate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pattern_index` is out of range.
+    #[must_use]
+    pub fn general_predicates(&self, pattern_index: usize) -> &[QueryPredicate] {
+        &self.patterns[pattern_index].general_predicates
+    }
+}
+
+fn compile_pattern(source: &str, parsed: parser::ParsedPattern) -> Result<Pattern, QueryError> {
+    let mut pattern = Pattern {
+        sequence: parsed.sequence,
+        start_byte: parsed.start_byte,
+        text_predicates: Vec::new(),
+        property_settings: Vec::new(),
+        property_predicates: Vec::new(),
+        general_predicates: Vec::new(),
+    };
+    for predicate in parsed.predicates {
+        compile_predicate(source, &predicate, &mut pattern)?;
+    }
+    Ok(pattern)
+}
+
+fn compile_predicate(
+    source: &str,
+    predicate: &RawPredicate,
+    pattern: &mut Pattern,
+) -> Result<(), QueryError> {
+    let error = |message: String| {
+        QueryError::new(source, predicate.offset, QueryErrorKind::Predicate, message)
+    };
+    let name = predicate.name.as_str();
+    let first_capture = || match predicate.args.first() {
+        Some(RawArg::Capture(id)) => Ok(*id),
+        _ => Err(error(format!(
+            "first argument to #{name} must be a capture"
+        ))),
+    };
+
+    match name {
+        "eq?" | "not-eq?" | "any-eq?" | "any-not-eq?" => {
+            if predicate.args.len() != 2 {
+                return Err(error(format!(
+                    "#{name} expects 2 arguments, got {}",
+                    predicate.args.len()
+                )));
+            }
+            pattern.text_predicates.push(TextPredicate::Eq {
+                capture: first_capture()?,
+                other: predicate.args[1].clone(),
+                negated: name.contains("not-"),
+                any: name.starts_with("any-"),
+            });
+        }
+        "match?" | "not-match?" | "any-match?" | "any-not-match?" => {
+            let capture = first_capture()?;
+            let [_, RawArg::String(pattern_source)] = predicate.args.as_slice() else {
+                return Err(error(format!(
+                    "#{name} expects a capture and a regex string"
+                )));
+            };
+            let regex = Regex::new(pattern_source)
+                .map_err(|e| error(format!("invalid regex in #{name}: {e}")))?;
+            pattern.text_predicates.push(TextPredicate::Match {
+                capture,
+                regex,
+                negated: name.contains("not-"),
+                any: name.starts_with("any-"),
+            });
+        }
+        "any-of?" | "not-any-of?" => {
+            let capture = first_capture()?;
+            let values = predicate.args[1..]
+                .iter()
+                .map(|arg| match arg {
+                    RawArg::String(s) => Ok(s.clone()),
+                    RawArg::Capture(_) => Err(error(format!(
+                        "arguments to #{name} after the capture must be strings"
+                    ))),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            if values.is_empty() {
+                return Err(error(format!("#{name} expects at least one string")));
+            }
+            pattern.text_predicates.push(TextPredicate::AnyOf {
+                capture,
+                values,
+                negated: name == "not-any-of?",
+            });
+        }
+        "set!" | "is?" | "is-not?" => {
+            let property = property_from_args(&predicate.args)
+                .ok_or_else(|| error(format!("invalid arguments to #{name}")))?;
+            match name {
+                "set!" => pattern.property_settings.push(property),
+                _ => pattern.property_predicates.push((property, name == "is?")),
+            }
+        }
+        _ => pattern.general_predicates.push(QueryPredicate {
+            operator: predicate.name.clone(),
+            args: predicate
+                .args
+                .iter()
+                .map(|arg| match arg {
+                    RawArg::Capture(id) => QueryPredicateArg::Capture(*id),
+                    RawArg::String(s) => QueryPredicateArg::String(s.clone()),
+                })
+                .collect(),
+        }),
+    }
+    Ok(())
+}
+
+/// Reads `[@capture] key [value]` property arguments.
+fn property_from_args(args: &[RawArg]) -> Option<QueryProperty> {
+    let (capture_id, rest) = match args.first() {
+        Some(RawArg::Capture(id)) => (Some(*id), &args[1..]),
+        _ => (None, args),
+    };
+    let strings = rest
+        .iter()
+        .map(|arg| match arg {
+            RawArg::String(s) => Some(s.clone()),
+            RawArg::Capture(_) => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    match strings.as_slice() {
+        [key] => Some(QueryProperty {
+            key: key.clone(),
+            value: None,
+            capture_id,
+        }),
+        [key, value] => Some(QueryProperty {
+            key: key.clone(),
+            value: Some(value.clone()),
+            capture_id,
+        }),
+        _ => None,
+    }
+}
+
+/// The node kinds and field names that can appear in a grammar's trees.
+pub(crate) struct Vocabulary {
+    pub(crate) named: BTreeSet<String>,
+    pub(crate) anonymous: BTreeSet<String>,
+    pub(crate) fields: BTreeSet<String>,
+}
+
+impl Vocabulary {
+    pub(crate) fn new(grammar: &Grammar) -> Self {
+        let mut vocabulary = Self {
+            named: BTreeSet::from([crate::tree::ERROR_KIND.to_string()]),
+            anonymous: BTreeSet::new(),
+            fields: BTreeSet::new(),
+        };
+        let inline = grammar.inline.as_deref().unwrap_or_default();
+        for (name, rule) in &grammar.rules {
+            if !name.starts_with('_') && !inline.contains(name) {
+                vocabulary.named.insert(name.clone());
+            }
+            vocabulary.collect(rule);
+        }
+        for supertype in grammar.supertypes.iter().flatten() {
+            vocabulary.named.insert(supertype.clone());
+        }
+        for rule in grammar.externals.iter().flatten() {
+            match (&rule.rule_type, &rule.name) {
+                (RuleType::Symbol, Some(name)) if !name.starts_with('_') => {
+                    vocabulary.named.insert(name.clone());
+                }
+                _ => vocabulary.collect(rule),
+            }
+        }
+        for rule in grammar.extras.iter().flatten() {
+            vocabulary.collect(rule);
+        }
+        vocabulary
+    }
+
+    /// The vocabulary of a language's tables: its visible symbols and its
+    /// supertypes, which queries may name although they are hidden.
+    pub(crate) fn from_language(language: &Language) -> Self {
+        let mut vocabulary = Self {
+            named: BTreeSet::from([crate::tree::ERROR_KIND.to_string()]),
+            anonymous: BTreeSet::new(),
+            fields: language
+                .field_names
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+        };
+        for (name, metadata) in language
+            .symbol_names
+            .iter()
+            .zip(language.symbol_metadata.iter())
+        {
+            if metadata.named && (metadata.visible || metadata.supertype) {
+                vocabulary.named.insert(name.to_string());
+            } else if !metadata.named && metadata.visible {
+                vocabulary.anonymous.insert(name.to_string());
+            }
+        }
+        vocabulary
+    }
+
+    fn collect(&mut self, rule: &Rule) {
+        match rule.rule_type {
+            RuleType::String => {
+                if let Some(value) = rule.string_value() {
+                    self.anonymous.insert(value.to_string());
+                }
+            }
+            RuleType::Alias => {
+                if let Some(RuleValue::String(value)) = &rule.value {
+                    if rule.named == Some(true) {
+                        self.named.insert(value.clone());
+                    } else {
+                        self.anonymous.insert(value.clone());
+                    }
+                }
+                // The aliased content itself does not surface as a node.
+            }
+            RuleType::Field => {
+                if let Some(name) = &rule.name {
+                    self.fields.insert(name.clone());
+                }
+                if let Some(content) = &rule.content {
+                    self.collect(content);
+                }
+            }
+            RuleType::Token | RuleType::ImmediateToken => {
+                // Only a token that is a plain string is its own anonymous node.
+                let mut content = rule.content.as_deref();
+                while let Some(inner) = content.filter(|r| {
+                    matches!(
+                        r.rule_type,
+                        RuleType::Prec
+                            | RuleType::PrecLeft
+                            | RuleType::PrecRight
+                            | RuleType::PrecDynamic
+                    )
+                }) {
+                    content = inner.content.as_deref();
+                }
+                if let Some(value) = content.and_then(Rule::string_value) {
+                    self.anonymous.insert(value.to_string());
+                }
+            }
+            _ => {
+                for member in &rule.members {
+                    self.collect(member);
+                }
+                if let Some(content) = &rule.content {
+                    self.collect(content);
+                }
+            }
+        }
+    }
+
+    fn check_sequence(&self, source: &str, sequence: &Sequence) -> Result<(), QueryError> {
+        sequence
+            .steps
+            .iter()
+            .try_for_each(|step| self.check_step(source, step))
+    }
+
+    fn check_step(&self, source: &str, step: &parser::Step) -> Result<(), QueryError> {
+        if let Some(field) = &step.field {
+            self.check_field(source, step.offset, field)?;
+        }
+        match &step.kind {
+            StepKind::Node {
+                matcher,
+                children,
+                negated_fields,
+            } => {
+                self.check_matcher(source, step.offset, matcher)?;
+                for (field, offset) in negated_fields {
+                    self.check_field(source, *offset, field)?;
+                }
+                self.check_sequence(source, children)
+            }
+            StepKind::Alternation(alternatives) => alternatives
+                .iter()
+                .try_for_each(|alt| self.check_step(source, alt)),
+            StepKind::Group(sequence) => self.check_sequence(source, sequence),
+        }
+    }
+
+    fn check_field(&self, source: &str, offset: usize, field: &str) -> Result<(), QueryError> {
+        if self.fields.contains(field) {
+            Ok(())
+        } else {
+            Err(QueryError::new(
+                source,
+                offset,
+                QueryErrorKind::Field,
+                format!("unknown field '{field}'"),
+            ))
+        }
+    }
+
+    fn check_matcher(
+        &self,
+        source: &str,
+        offset: usize,
+        matcher: &NodeMatcher,
+    ) -> Result<(), QueryError> {
+        let unknown = |kind: &str| {
+            QueryError::new(
+                source,
+                offset,
+                QueryErrorKind::NodeType,
+                format!("unknown node type '{kind}'"),
+            )
+        };
+        match matcher {
+            NodeMatcher::Any | NodeMatcher::AnyNamed | NodeMatcher::Missing(None) => Ok(()),
+            NodeMatcher::Named {
+                kind,
+                named,
+                supertype,
+            } => {
+                if let Some(supertype) = supertype {
+                    if !self.named.contains(supertype) {
+                        return Err(unknown(supertype));
+                    }
+                }
+                let known = if *named {
+                    self.named.contains(kind)
+                } else {
+                    self.anonymous.contains(kind)
+                };
+                if known {
+                    Ok(())
+                } else if *named {
+                    Err(unknown(kind))
+                } else {
+                    Err(unknown(&format!("\"{kind}\"")))
+                }
+            }
+            NodeMatcher::Anonymous(text) | NodeMatcher::Missing(Some((text, false))) => {
+                if self.anonymous.contains(text) {
+                    Ok(())
+                } else {
+                    Err(unknown(&format!("\"{text}\"")))
+                }
+            }
+            NodeMatcher::Missing(Some((kind, true))) => {
+                if self.named.contains(kind) {
+                    Ok(())
+                } else {
+                    Err(unknown(kind))
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,709 @@
+//! Running queries over syntax trees.
+//!
+//! Patterns are matched by backtracking over sibling lists. Each step is
+//! matched in continuation-passing style, so alternations, quantifiers and
+//! anchors compose without building an explicit automaton. Repetitions are
+//! greedy and each iteration commits to the first sibling that matches,
+//! which keeps the number of explored paths linear in the sibling count.
+
+use super::parser::{NodeMatcher, Quantifier, RawArg, Sequence, Step, StepKind};
+use super::{Pattern, Query, TextPredicate};
+use crate::tree::Node;
+use std::collections::HashSet;
+
+/// A node captured by a query pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueryCapture<'tree> {
+    /// The captured node.
+    pub node: &'tree Node,
+    /// The capture id, an index into [`Query::capture_names`].
+    pub index: usize,
+}
+
+/// One successful match of a query pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryMatch<'tree> {
+    /// The index of the pattern that matched.
+    pub pattern_index: usize,
+    /// The nodes captured by the match, in the order they were matched.
+    pub captures: Vec<QueryCapture<'tree>>,
+}
+
+impl<'tree> QueryMatch<'tree> {
+    /// The nodes captured under `index`.
+    pub fn nodes_for_capture_index(&self, index: usize) -> impl Iterator<Item = &'tree Node> + '_ {
+        self.captures
+            .iter()
+            .filter(move |c| c.index == index)
+            .map(|c| c.node)
+    }
+}
+
+/// Executes a [`Query`] against a syntax tree.
+#[derive(Debug, Clone, Default)]
+pub struct QueryCursor {
+    byte_range: Option<std::ops::Range<usize>>,
+}
+
+impl QueryCursor {
+    /// Creates a cursor that searches the whole tree.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Restricts matching to matches whose first node overlaps `range`.
+    pub fn set_byte_range(&mut self, range: std::ops::Range<usize>) -> &mut Self {
+        self.byte_range = Some(range);
+        self
+    }
+
+    /// Finds every match of `query` in the tree rooted at `node`.
+    ///
+    /// `source` is the text the tree was parsed from; it is needed to
+    /// evaluate text predicates such as `#eq?` and `#match?`. Matches are
+    /// returned in document order, and by pattern index for matches that
+    /// start at the same node.
+    pub fn matches<'tree>(
+        &self,
+        query: &Query,
+        node: &'tree Node,
+        source: &[u8],
+    ) -> impl Iterator<Item = QueryMatch<'tree>> {
+        let mut found = Vec::new();
+        for (pattern_index, pattern) in query.patterns.iter().enumerate() {
+            let mut search = Search {
+                pattern,
+                source,
+                byte_range: self.byte_range.as_ref(),
+                found: Vec::new(),
+            };
+            if !search.is_single() {
+                // The root has no parent whose children would be tried.
+                search.try_at(std::slice::from_ref(node), 0, node.start_byte());
+            }
+            search.visit(node);
+            found.extend(
+                search
+                    .found
+                    .into_iter()
+                    .map(|(start, captures)| (start, pattern_index, captures)),
+            );
+        }
+        found.sort_by_key(|(start, pattern_index, _)| (*start, *pattern_index));
+        found
+            .into_iter()
+            .map(|(_, pattern_index, captures)| QueryMatch {
+                pattern_index,
+                captures,
+            })
+    }
+
+    /// Finds every capture of `query` in the tree rooted at `node`.
+    ///
+    /// Each item is a match together with the position of one of its
+    /// captures. Captures are ordered by start position, outermost first,
+    /// which is the order a highlighter consumes them in.
+    pub fn captures<'tree>(
+        &self,
+        query: &Query,
+        node: &'tree Node,
+        source: &[u8],
+    ) -> impl Iterator<Item = (QueryMatch<'tree>, usize)> {
+        let mut captures: Vec<(QueryMatch<'tree>, usize)> = self
+            .matches(query, node, source)
+            .flat_map(|m| (0..m.captures.len()).map(move |i| (m.clone(), i)))
+            .collect();
+        captures.sort_by_key(|(m, i)| {
+            let node = m.captures[*i].node;
+            (
+                node.start_byte(),
+                std::cmp::Reverse(node.end_byte()),
+                m.pattern_index,
+                *i,
+            )
+        });
+        captures.into_iter()
+    }
+}
+
+/// The continuation called when a step or sequence has matched, with the
+/// index of the first sibling after the match.
+type Continuation<'k, 'tree> = &'k mut dyn FnMut(usize, &mut Vec<QueryCapture<'tree>>);
+
+/// How freely a step may skip siblings before matching.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Mode {
+    /// Any siblings may be skipped.
+    Free,
+    /// Only anonymous siblings may be skipped (after a `.` anchor).
+    Anchored,
+    /// The step must match the very next sibling.
+    Exact,
+}
+
+/// The search for one pattern's matches.
+struct Search<'a, 'tree> {
+    pattern: &'a Pattern,
+    source: &'a [u8],
+    byte_range: Option<&'a std::ops::Range<usize>>,
+    /// Matches as (start byte, captures).
+    found: Vec<(usize, Vec<QueryCapture<'tree>>)>,
+}
+
+impl<'tree> Search<'_, 'tree> {
+    /// Whether the pattern is a single unquantified node or alternation,
+    /// which is tried at each node rather than at each run of siblings.
+    fn is_single(&self) -> bool {
+        let steps = &self.pattern.sequence.steps;
+        steps.len() == 1
+            && steps[0].quantifier == Quantifier::One
+            && !matches!(steps[0].kind, StepKind::Group(_))
+    }
+
+    /// Tries the pattern at `node` and all of its descendants.
+    fn visit(&mut self, node: &'tree Node) {
+        if let Some(range) = self.byte_range {
+            if node.end_byte() < range.start || node.start_byte() > range.end {
+                return;
+            }
+        }
+        if self.is_single() {
+            self.try_at(std::slice::from_ref(node), 0, node.start_byte());
+        } else {
+            let children = node.children();
+            let before = self.found.len();
+            for start in 0..children.len() {
+                self.try_at(children, start, children[start].start_byte());
+            }
+            suppress_subsets(&mut self.found, before);
+        }
+        for child in node.children() {
+            self.visit(child);
+        }
+    }
+
+    /// Matches the pattern's sequence starting exactly at `siblings[start]`.
+    fn try_at(&mut self, siblings: &'tree [Node], start: usize, start_byte: usize) {
+        if let Some(range) = self.byte_range {
+            let first = &siblings[start];
+            if first.end_byte() < range.start
+                || first.start_byte() >= range.end.max(range.start + 1)
+            {
+                return;
+            }
+        }
+        let pattern = self.pattern;
+        let source = self.source;
+        let mut solutions = Vec::new();
+        let mut captures = Vec::new();
+        sequence(
+            &pattern.sequence.steps,
+            &[],
+            siblings,
+            start,
+            Mode::Exact,
+            false,
+            &mut captures,
+            &mut |end, captures| {
+                if end > start && predicates_hold(pattern, source, captures) {
+                    solutions.push(captures.clone());
+                }
+            },
+        );
+        let before = self.found.len();
+        self.found
+            .extend(solutions.into_iter().map(|captures| (start_byte, captures)));
+        suppress_subsets(&mut self.found, before);
+    }
+}
+
+/// Drops duplicate matches, and matches whose captures are a strict subset
+/// of another's, among `found[from..]`.
+///
+/// Optional and repeated steps produce one solution for every way of
+/// skipping them; only the fullest is reported.
+fn suppress_subsets(found: &mut Vec<(usize, Vec<QueryCapture<'_>>)>, from: usize) {
+    type Key = (*const Node, usize);
+    let sets: Vec<HashSet<Key>> = found[from..]
+        .iter()
+        .map(|(_, captures)| {
+            captures
+                .iter()
+                .map(|c| (std::ptr::from_ref(c.node), c.index))
+                .collect()
+        })
+        .collect();
+    let keep: Vec<bool> = (0..sets.len())
+        .map(|i| {
+            !sets.iter().enumerate().any(|(j, other)| {
+                j != i && sets[i].is_subset(other) && (sets[i].len() < other.len() || j < i)
+            })
+        })
+        .collect();
+    let mut index = 0;
+    found.retain(|_| {
+        let kept = index < from || keep[index - from];
+        index += 1;
+        kept
+    });
+}
+
+/// Matches `steps` against `nodes[pos..]`.
+///
+/// `extra` captures are applied to the node matched by the first step, and
+/// `anchor_end` requires that no named sibling follows the last step.
+#[allow(clippy::too_many_arguments)]
+fn sequence<'tree>(
+    steps: &[Step],
+    extra: &[usize],
+    nodes: &'tree [Node],
+    pos: usize,
+    mode: Mode,
+    anchor_end: bool,
+    captures: &mut Vec<QueryCapture<'tree>>,
+    k: Continuation<'_, 'tree>,
+) {
+    let Some((step, rest)) = steps.split_first() else {
+        if !anchor_end || nodes[pos..].iter().all(|n| !n.is_named()) {
+            k(pos, captures);
+        }
+        return;
+    };
+    let mode = if step.anchored {
+        mode.max(Mode::Anchored)
+    } else {
+        mode
+    };
+    quantified(
+        step,
+        extra,
+        nodes,
+        pos,
+        mode,
+        captures,
+        &mut |end, captures| {
+            sequence(rest, &[], nodes, end, Mode::Free, anchor_end, captures, k);
+        },
+    );
+    if step.quantifier.is_optional() {
+        sequence(rest, &[], nodes, pos, mode, anchor_end, captures, k);
+    }
+}
+
+/// Matches at least one occurrence of `step` at or after `nodes[pos]`.
+fn quantified<'tree>(
+    step: &Step,
+    extra: &[usize],
+    nodes: &'tree [Node],
+    pos: usize,
+    mode: Mode,
+    captures: &mut Vec<QueryCapture<'tree>>,
+    k: Continuation<'_, 'tree>,
+) {
+    for i in pos..nodes.len() {
+        match mode {
+            Mode::Exact if i > pos => break,
+            Mode::Anchored if i > pos && nodes[i - 1].is_named() => break,
+            _ => {}
+        }
+        once(step, extra, nodes, i, captures, &mut |end, captures| {
+            if step.quantifier.is_repeated() {
+                repeat(step, extra, nodes, end, captures, k);
+            } else {
+                k(end, captures);
+            }
+        });
+    }
+}
+
+/// Greedily matches further occurrences of a repeated step, then gives up.
+fn repeat<'tree>(
+    step: &Step,
+    extra: &[usize],
+    nodes: &'tree [Node],
+    pos: usize,
+    captures: &mut Vec<QueryCapture<'tree>>,
+    k: Continuation<'_, 'tree>,
+) {
+    for i in pos..nodes.len() {
+        let mut found = false;
+        once(step, extra, nodes, i, captures, &mut |end, captures| {
+            found = true;
+            repeat(step, extra, nodes, end, captures, k);
+        });
+        if found {
+            break;
+        }
+    }
+    k(pos, captures);
+}
+
+/// Matches one occurrence of `step` starting exactly at `nodes[i]`.
+fn once<'tree>(
+    step: &Step,
+    extra: &[usize],
+    nodes: &'tree [Node],
+    i: usize,
+    captures: &mut Vec<QueryCapture<'tree>>,
+    k: Continuation<'_, 'tree>,
+) {
+    match &step.kind {
+        StepKind::Node {
+            matcher,
+            children,
+            negated_fields,
+        } => {
+            let node = &nodes[i];
+            if !matcher_accepts(matcher, node)
+                || step
+                    .field
+                    .as_deref()
+                    .is_some_and(|field| node.field_name() != Some(field))
+                || negated_fields
+                    .iter()
+                    .any(|(field, _)| node.child_by_field_name(field).is_some())
+            {
+                return;
+            }
+            let depth = captures.len();
+            captures.extend(
+                step.captures
+                    .iter()
+                    .chain(extra)
+                    .map(|&index| QueryCapture { node, index }),
+            );
+            child_sequence(children, node, captures, &mut |_, captures| {
+                k(i + 1, captures);
+            });
+            captures.truncate(depth);
+        }
+        StepKind::Alternation(alternatives) => {
+            if let Some(field) = &step.field {
+                if nodes[i].field_name() != Some(field.as_str()) {
+                    return;
+                }
+            }
+            let extra: Vec<usize> = extra.iter().chain(&step.captures).copied().collect();
+            for alternative in alternatives {
+                let single = std::slice::from_ref(alternative);
+                sequence(single, &extra, nodes, i, Mode::Exact, false, captures, k);
+            }
+        }
+        StepKind::Group(group) => {
+            let extra: Vec<usize> = extra.iter().chain(&step.captures).copied().collect();
+            sequence(
+                &group.steps,
+                &extra,
+                nodes,
+                i,
+                Mode::Exact,
+                false,
+                captures,
+                k,
+            );
+        }
+    }
+}
+
+/// Matches a node pattern's child steps against `node`'s children.
+fn child_sequence<'tree>(
+    children: &Sequence,
+    node: &'tree Node,
+    captures: &mut Vec<QueryCapture<'tree>>,
+    k: Continuation<'_, 'tree>,
+) {
+    let mode = if children.steps.first().is_some_and(|s| s.anchored) {
+        Mode::Anchored
+    } else {
+        Mode::Free
+    };
+    sequence(
+        &children.steps,
+        &[],
+        node.children(),
+        0,
+        mode,
+        children.anchor_end,
+        captures,
+        k,
+    );
+}
+
+fn matcher_accepts(matcher: &NodeMatcher, node: &Node) -> bool {
+    match matcher {
+        NodeMatcher::Any => true,
+        NodeMatcher::AnyNamed => node.is_named(),
+        NodeMatcher::Named {
+            kind,
+            named,
+            supertype,
+        } => {
+            node.kind() == kind
+                && node.is_named() == *named
+                && supertype
+                    .as_ref()
+                    .is_none_or(|supertype| node.supertypes().contains(supertype))
+        }
+        NodeMatcher::Anonymous(text) => !node.is_named() && node.kind() == text,
+        NodeMatcher::Missing(None) => node.is_missing(),
+        NodeMatcher::Missing(Some((kind, named))) => {
+            node.is_missing() && node.kind() == kind && node.is_named() == *named
+        }
+    }
+}
+
+/// Evaluates a pattern's text predicates against one candidate match.
+fn predicates_hold(pattern: &Pattern, source: &[u8], captures: &[QueryCapture<'_>]) -> bool {
+    let text = |node: &Node| source.get(node.byte_range()).unwrap_or_default();
+    let nodes = |index: usize| {
+        captures
+            .iter()
+            .filter(move |c| c.index == index)
+            .map(|c| c.node)
+    };
+    // With no captured nodes (an absent optional capture) the predicate holds.
+    let check = |capture: usize, any: bool, test: &dyn Fn(&[u8]) -> bool| {
+        let mut nodes = nodes(capture).peekable();
+        if nodes.peek().is_none() {
+            return true;
+        }
+        if any {
+            nodes.any(|n| test(text(n)))
+        } else {
+            nodes.all(|n| test(text(n)))
+        }
+    };
+
+    pattern
+        .text_predicates
+        .iter()
+        .all(|predicate| match predicate {
+            TextPredicate::Eq {
+                capture,
+                other,
+                negated,
+                any,
+            } => {
+                let expected: Option<&[u8]> = match other {
+                    RawArg::String(s) => Some(s.as_bytes()),
+                    RawArg::Capture(other) => nodes(*other).next().map(text),
+                };
+                let Some(expected) = expected else {
+                    return true;
+                };
+                check(*capture, *any, &|t| (t == expected) != *negated)
+            }
+            TextPredicate::Match {
+                capture,
+                regex,
+                negated,
+                any,
+            } => check(*capture, *any, &|t| regex.is_match(t) != *negated),
+            TextPredicate::AnyOf {
+                capture,
+                values,
+                negated,
+            } => check(*capture, false, &|t| {
+                values.iter().any(|v| v.as_bytes() == t) != *negated
+            }),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_grammar;
+    use crate::tree::Range;
+
+    const GRAMMAR: &str = r##"{
+        "name": "mini",
+        "rules": {
+            "program": {"type": "REPEAT", "content": {"type": "SYMBOL", "name": "_item"}},
+            "_item": {"type": "CHOICE", "members": [
+                {"type": "SYMBOL", "name": "call"},
+                {"type": "SYMBOL", "name": "comment"}
+            ]},
+            "call": {"type": "SEQ", "members": [
+                {"type": "FIELD", "name": "function", "content": {"type": "SYMBOL", "name": "identifier"}},
+                {"type": "STRING", "value": "("},
+                {"type": "REPEAT", "content": {"type": "FIELD", "name": "argument",
+                    "content": {"type": "SYMBOL", "name": "identifier"}}},
+                {"type": "STRING", "value": ")"}
+            ]},
+            "identifier": {"type": "PATTERN", "value": "[a-z]+"},
+            "comment": {"type": "PATTERN", "value": "#.*"}
+        }
+    }"##;
+
+    /// Builds a tree for `src`, which must be a sequence of `# comment` lines
+    /// and `name(args...)` calls separated by newlines.
+    fn tree(src: &str) -> Node {
+        let mut items = Vec::new();
+        let mut offset = 0;
+        for line in src.split_inclusive('\n') {
+            let text = line.trim_end();
+            let leaf = |kind: &str, named, start: usize, end: usize| {
+                Node::leaf(kind, named, Range::from_bytes(src.as_bytes(), start..end))
+            };
+            if text.starts_with('#') {
+                items.push(leaf("comment", true, offset, offset + text.len()));
+            } else {
+                let open = text.find('(').unwrap();
+                let mut children =
+                    vec![leaf("identifier", true, offset, offset + open).with_field("function")];
+                children.push(leaf("(", false, offset + open, offset + open + 1));
+                let mut start = offset + open + 1;
+                for arg in text[open + 1..text.len() - 1]
+                    .split(' ')
+                    .filter(|a| !a.is_empty())
+                {
+                    let at = start + src[start..].find(arg).unwrap();
+                    children
+                        .push(leaf("identifier", true, at, at + arg.len()).with_field("argument"));
+                    start = at + arg.len();
+                }
+                let close = offset + text.len() - 1;
+                children.push(leaf(")", false, close, close + 1));
+                items.push(Node::branch("call", true, children));
+            }
+            offset += line.len();
+        }
+        Node::branch("program", true, items)
+    }
+
+    fn run(query: &str, src: &str) -> Vec<Vec<(String, String)>> {
+        let grammar = parse_grammar(GRAMMAR).unwrap();
+        let query = Query::new(&grammar, query).unwrap();
+        let root = tree(src);
+        QueryCursor::new()
+            .matches(&query, &root, src.as_bytes())
+            .map(|m| {
+                m.captures
+                    .iter()
+                    .map(|c| {
+                        (
+                            query.capture_names()[c.index].clone(),
+                            c.node.utf8_text(src.as_bytes()).unwrap().to_string(),
+                        )
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn pairs(items: &[(&str, &str)]) -> Vec<(String, String)> {
+        items
+            .iter()
+            .map(|(a, b)| ((*a).to_string(), (*b).to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_fields_and_predicates() {
+        let src = "print(a b)\nlog(c)\n";
+        let matches = run(
+            r#"(call function: (identifier) @fn (#eq? @fn "print") argument: (_) @arg)"#,
+            src,
+        );
+        assert_eq!(
+            matches,
+            [
+                pairs(&[("fn", "print"), ("arg", "a")]),
+                pairs(&[("fn", "print"), ("arg", "b")])
+            ]
+        );
+        let matches = run(r#"((identifier) @id (#match? @id "^[lc]"))"#, src);
+        assert_eq!(matches, [pairs(&[("id", "log")]), pairs(&[("id", "c")])]);
+    }
+
+    #[test]
+    fn test_anchors_and_quantifiers() {
+        let src = "f(a b c)\n";
+        assert_eq!(
+            run(r#"(call "(" . (identifier) @first)"#, src),
+            [pairs(&[("first", "a")])]
+        );
+        assert_eq!(
+            run("(call (identifier) @last . \")\")", src),
+            [pairs(&[("last", "c")])]
+        );
+        assert_eq!(
+            run("(call argument: (identifier)+ @args)", src),
+            [pairs(&[("args", "a"), ("args", "b"), ("args", "c")])]
+        );
+        assert_eq!(run("(call !argument) @bare", "f()\ng(x)\n").len(), 1);
+    }
+
+    #[test]
+    fn test_sibling_groups_and_alternations() {
+        let src = "# one\n# two\nf(x)\n# three\n";
+        let matches = run("((comment)+ @doc . (call) @call)", src);
+        assert_eq!(
+            matches,
+            [pairs(&[
+                ("doc", "# one"),
+                ("doc", "# two"),
+                ("call", "f(x)")
+            ])]
+        );
+        let matches = run("[(comment) @c (call function: (_) @f)]", src);
+        assert_eq!(matches.len(), 4);
+        assert_eq!(matches[2], pairs(&[("f", "f")]));
+    }
+
+    #[test]
+    fn test_unknown_names_are_rejected() {
+        let grammar = parse_grammar(GRAMMAR).unwrap();
+        let err = Query::new(&grammar, "(call (identifer))").unwrap_err();
+        assert_eq!(err.kind, super::super::QueryErrorKind::NodeType);
+        let err = Query::new(&grammar, "(call name: (identifier))").unwrap_err();
+        assert_eq!(err.kind, super::super::QueryErrorKind::Field);
+        assert!(Query::new(&grammar, "(_item)").is_err());
+        assert!(Query::new(&grammar, "\"(\" @paren").is_ok());
+    }
+
+    #[test]
+    fn test_supertypes_restrict_subtypes() {
+        let grammar = parse_grammar(
+            r#"{
+                "name": "supertypes",
+                "supertypes": ["_expression"],
+                "rules": {
+                    "program": {"type": "REPEAT", "content": {"type": "CHOICE", "members": [
+                        {"type": "SYMBOL", "name": "_expression"},
+                        {"type": "SYMBOL", "name": "declaration"}
+                    ]}},
+                    "declaration": {"type": "SEQ", "members": [
+                        {"type": "STRING", "value": "let"},
+                        {"type": "SYMBOL", "name": "identifier"}
+                    ]},
+                    "_expression": {"type": "CHOICE", "members": [
+                        {"type": "SYMBOL", "name": "identifier"},
+                        {"type": "SYMBOL", "name": "number"},
+                        {"type": "STRING", "value": "nil"}
+                    ]},
+                    "identifier": {"type": "PATTERN", "value": "[a-z]+"},
+                    "number": {"type": "PATTERN", "value": "[0-9]+"}
+                }
+            }"#,
+        )
+        .unwrap();
+        let language = crate::tables::build(&grammar).unwrap().into_language();
+        let src = "a let b nil 1";
+        let root = crate::Parser::new(&language).parse(src);
+        let captured = |query: &str| -> Vec<&str> {
+            let query = Query::new(&grammar, query).unwrap();
+            QueryCursor::new()
+                .captures(&query, &root, src.as_bytes())
+                .map(|(m, i)| m.captures[i].node.utf8_text(src.as_bytes()).unwrap())
+                .collect()
+        };
+
+        // `b` is an identifier, but not one standing for an expression.
+        assert_eq!(captured("(identifier) @id"), ["a", "b"]);
+        assert_eq!(captured("(_expression/identifier) @id"), ["a"]);
+        assert_eq!(captured("(_expression/\"nil\") @nil"), ["nil"]);
+        assert_eq!(captured("(_expression/number) @n"), ["1"]);
+    }
+}
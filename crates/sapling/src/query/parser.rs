@@ -0,0 +1,636 @@
+//! Parser for tree-sitter's query language.
+//!
+//! Produces the pattern structure that the cursor matches against trees and
+//! that grammar-aware checks walk. The syntax follows tree-sitter's own
+//! `query.c`: parenthesized node patterns, `"literal"` anonymous nodes, `_`
+//! wildcards, `field:` prefixes, `!field` negations, `.` anchors, `[...]`
+//! alternations, parenthesized sibling groups, `* + ?` quantifiers, `@capture`
+//! suffixes and `(#predicate! ...)` forms.
+
+use super::{QueryError, QueryErrorKind};
+
+/// How many times a step may repeat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Quantifier {
+    /// Exactly once.
+    One,
+    /// `?`
+    ZeroOrOne,
+    /// `*`
+    ZeroOrMore,
+    /// `+`
+    OneOrMore,
+}
+
+impl Quantifier {
+    /// Applies a further quantifier suffix, as in `(a)+?`.
+    fn combine(self, other: Quantifier) -> Quantifier {
+        match (self, other) {
+            (Quantifier::One, q) | (q, Quantifier::One) => q,
+            (Quantifier::OneOrMore, Quantifier::OneOrMore) => Quantifier::OneOrMore,
+            (Quantifier::ZeroOrOne, Quantifier::ZeroOrOne) => Quantifier::ZeroOrOne,
+            _ => Quantifier::ZeroOrMore,
+        }
+    }
+
+    /// Whether the step may match nothing.
+    pub(crate) fn is_optional(self) -> bool {
+        matches!(self, Quantifier::ZeroOrOne | Quantifier::ZeroOrMore)
+    }
+
+    /// Whether the step may match more than once.
+    pub(crate) fn is_repeated(self) -> bool {
+        matches!(self, Quantifier::ZeroOrMore | Quantifier::OneOrMore)
+    }
+}
+
+/// What a node step accepts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum NodeMatcher {
+    /// `_`: any node, named or anonymous.
+    Any,
+    /// `(_)`: any named node.
+    AnyNamed,
+    /// `(kind)`, optionally written `(supertype/kind)`, which only matches
+    /// a node that stands for the supertype. The subtype is anonymous when
+    /// written `(supertype/"text")`.
+    Named {
+        kind: String,
+        named: bool,
+        supertype: Option<String>,
+    },
+    /// `"text"`: an anonymous node.
+    Anonymous(String),
+    /// `(MISSING)` or `(MISSING kind)`; the flag is whether `kind` is named.
+    Missing(Option<(String, bool)>),
+}
+
+/// One element of a pattern.
+#[derive(Debug, Clone)]
+pub(crate) struct Step {
+    pub(crate) kind: StepKind,
+    /// The field the matched node must occupy in its parent.
+    pub(crate) field: Option<String>,
+    pub(crate) quantifier: Quantifier,
+    /// Capture ids applied to the matched node.
+    pub(crate) captures: Vec<usize>,
+    /// Whether an anchor (`.`) precedes this step among its siblings.
+    pub(crate) anchored: bool,
+    /// Byte offset of the step in the query source.
+    pub(crate) offset: usize,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum StepKind {
+    /// A single node, with constraints on its children.
+    Node {
+        matcher: NodeMatcher,
+        children: Sequence,
+        negated_fields: Vec<(String, usize)>,
+    },
+    /// `[a b c]`: any one of the alternatives.
+    Alternation(Vec<Step>),
+    /// `((a) (b))`: a run of sibling patterns.
+    Group(Sequence),
+}
+
+/// An ordered list of sibling steps.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Sequence {
+    pub(crate) steps: Vec<Step>,
+    /// Whether an anchor (`.`) follows the last step.
+    pub(crate) anchor_end: bool,
+}
+
+/// An argument to a predicate or directive.
+#[derive(Debug, Clone)]
+pub(crate) enum RawArg {
+    /// `@name`, resolved to a capture id.
+    Capture(usize),
+    /// A quoted string or a bare identifier.
+    String(String),
+}
+
+/// A `(#name ...)` form, before its arguments are checked.
+#[derive(Debug, Clone)]
+pub(crate) struct RawPredicate {
+    pub(crate) name: String,
+    pub(crate) args: Vec<RawArg>,
+    pub(crate) offset: usize,
+}
+
+/// A top-level pattern.
+#[derive(Debug, Clone)]
+pub(crate) struct ParsedPattern {
+    pub(crate) sequence: Sequence,
+    pub(crate) predicates: Vec<RawPredicate>,
+    pub(crate) start_byte: usize,
+}
+
+/// The output of parsing a whole query source.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ParsedQuery {
+    pub(crate) patterns: Vec<ParsedPattern>,
+    pub(crate) capture_names: Vec<String>,
+}
+
+/// Parses query source text.
+pub(crate) fn parse(source: &str) -> Result<ParsedQuery, QueryError> {
+    let mut parser = Parser {
+        source,
+        pos: 0,
+        capture_names: Vec::new(),
+        predicates: Vec::new(),
+    };
+    let mut patterns = Vec::new();
+    loop {
+        parser.skip_trivia();
+        if parser.pos >= source.len() {
+            break;
+        }
+        let start_byte = parser.pos;
+        let item = parser.parse_item(Context::TopLevel)?;
+        let step = match item {
+            Item::Step(step) => step,
+            Item::Anchor | Item::NegatedField(..) | Item::Predicate(_) => {
+                return Err(parser.error_at(
+                    start_byte,
+                    QueryErrorKind::Syntax,
+                    "expected a pattern",
+                ))
+            }
+        };
+        let sequence = match step.kind {
+            StepKind::Group(sequence)
+                if step.quantifier == Quantifier::One && step.captures.is_empty() =>
+            {
+                sequence
+            }
+            _ => Sequence {
+                steps: vec![step],
+                anchor_end: false,
+            },
+        };
+        if sequence.steps.is_empty() {
+            return Err(parser.error_at(start_byte, QueryErrorKind::Syntax, "empty pattern"));
+        }
+        patterns.push(ParsedPattern {
+            sequence,
+            predicates: std::mem::take(&mut parser.predicates),
+            start_byte,
+        });
+    }
+    Ok(ParsedQuery {
+        patterns,
+        capture_names: parser.capture_names,
+    })
+}
+
+/// Where an item is being parsed, which determines what is allowed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Context {
+    TopLevel,
+    /// Inside a node pattern's or group's parentheses.
+    Children,
+    /// Inside `[...]`.
+    Alternation,
+}
+
+enum Item {
+    Step(Step),
+    Anchor,
+    NegatedField(String, usize),
+    Predicate(RawPredicate),
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    pos: usize,
+    capture_names: Vec<String>,
+    /// Predicates of the top-level pattern being parsed.
+    predicates: Vec<RawPredicate>,
+}
+
+impl Parser<'_> {
+    fn error_at(
+        &self,
+        offset: usize,
+        kind: QueryErrorKind,
+        message: impl Into<String>,
+    ) -> QueryError {
+        QueryError::new(self.source, offset, kind, message)
+    }
+
+    fn error(&self, message: impl Into<String>) -> QueryError {
+        self.error_at(self.pos, QueryErrorKind::Syntax, message)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.source[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Skips whitespace and `;` line comments.
+    fn skip_trivia(&mut self) {
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() {
+                self.pos += c.len_utf8();
+            } else if c == ';' {
+                match self.source[self.pos..].find('\n') {
+                    Some(newline) => self.pos += newline + 1,
+                    None => self.pos = self.source.len(),
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn parse_identifier(&mut self) -> Option<String> {
+        let start = self.pos;
+        if !self
+            .peek()
+            .is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '-')
+        {
+            return None;
+        }
+        while self
+            .peek()
+            .is_some_and(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '?' | '!'))
+        {
+            self.bump();
+        }
+        Some(self.source[start..self.pos].to_string())
+    }
+
+    fn parse_string(&mut self) -> Result<String, QueryError> {
+        let start = self.pos;
+        if !self.eat('"') {
+            return Err(self.error("expected string"));
+        }
+        let mut out = String::new();
+        loop {
+            match self.bump() {
+                None => {
+                    return Err(self.error_at(start, QueryErrorKind::Syntax, "unterminated string"))
+                }
+                Some('"') => return Ok(out),
+                Some('\\') => match self.bump() {
+                    Some('n') => out.push('\n'),
+                    Some('r') => out.push('\r'),
+                    Some('t') => out.push('\t'),
+                    Some('0') => out.push('\0'),
+                    Some(c) => out.push(c),
+                    None => {
+                        return Err(self.error_at(
+                            start,
+                            QueryErrorKind::Syntax,
+                            "unterminated string",
+                        ))
+                    }
+                },
+                Some(c) => out.push(c),
+            }
+        }
+    }
+
+    fn capture_id(&mut self, name: &str) -> usize {
+        if let Some(id) = self.capture_names.iter().position(|n| n == name) {
+            id
+        } else {
+            self.capture_names.push(name.to_string());
+            self.capture_names.len() - 1
+        }
+    }
+
+    fn parse_item(&mut self, context: Context) -> Result<Item, QueryError> {
+        self.skip_trivia();
+        let start = self.pos;
+        let Some(c) = self.peek() else {
+            return Err(self.error("unexpected end of query"));
+        };
+
+        let kind = match c {
+            '.' if context != Context::TopLevel => {
+                self.bump();
+                return Ok(Item::Anchor);
+            }
+            '!' if context == Context::Children => {
+                self.bump();
+                let name = self
+                    .parse_identifier()
+                    .ok_or_else(|| self.error("expected field name after '!'"))?;
+                return Ok(Item::NegatedField(name, start));
+            }
+            '[' => self.parse_alternation(start)?,
+            '"' => StepKind::Node {
+                matcher: NodeMatcher::Anonymous(self.parse_string()?),
+                children: Sequence::default(),
+                negated_fields: Vec::new(),
+            },
+            '(' => {
+                self.bump();
+                self.skip_trivia();
+                match self.peek() {
+                    Some('#') => {
+                        let predicate = self.parse_predicate(start)?;
+                        if context == Context::TopLevel {
+                            return Err(self.error_at(
+                                start,
+                                QueryErrorKind::Syntax,
+                                "predicates must appear inside a pattern",
+                            ));
+                        }
+                        return Ok(Item::Predicate(predicate));
+                    }
+                    Some('(' | '[' | '"' | '.' | ')') => StepKind::Group(self.parse_children()?.0),
+                    _ => self.parse_node_body()?,
+                }
+            }
+            _ => {
+                let Some(name) = self.parse_identifier() else {
+                    return Err(self.error(format!("unexpected '{c}'")));
+                };
+                self.skip_trivia();
+                if self.eat(':') {
+                    let mut item = self.parse_item(context)?;
+                    return match &mut item {
+                        Item::Step(step) if step.field.is_none() => {
+                            step.field = Some(name);
+                            step.offset = start;
+                            Ok(item)
+                        }
+                        _ => Err(self.error_at(
+                            start,
+                            QueryErrorKind::Syntax,
+                            "expected a pattern after field name",
+                        )),
+                    };
+                }
+                if name != "_" {
+                    return Err(self.error_at(
+                        start,
+                        QueryErrorKind::Syntax,
+                        format!("bare identifier '{name}'; node patterns must be parenthesized"),
+                    ));
+                }
+                StepKind::Node {
+                    matcher: NodeMatcher::Any,
+                    children: Sequence::default(),
+                    negated_fields: Vec::new(),
+                }
+            }
+        };
+
+        let mut step = Step {
+            kind,
+            field: None,
+            quantifier: Quantifier::One,
+            captures: Vec::new(),
+            anchored: false,
+            offset: start,
+        };
+        self.parse_suffixes(&mut step)?;
+        Ok(Item::Step(step))
+    }
+
+    /// Parses `[...]` alternatives.
+    fn parse_alternation(&mut self, start: usize) -> Result<StepKind, QueryError> {
+        self.bump();
+        let mut alternatives = Vec::new();
+        loop {
+            self.skip_trivia();
+            if self.eat(']') {
+                break;
+            }
+            match self.parse_item(Context::Alternation)? {
+                Item::Step(step) => alternatives.push(step),
+                _ => return Err(self.error("expected a pattern in alternation")),
+            }
+        }
+        if alternatives.is_empty() {
+            return Err(self.error_at(start, QueryErrorKind::Syntax, "empty alternation"));
+        }
+        Ok(StepKind::Alternation(alternatives))
+    }
+
+    /// Parses trailing quantifiers and captures, in any order.
+    fn parse_suffixes(&mut self, step: &mut Step) -> Result<(), QueryError> {
+        loop {
+            self.skip_trivia();
+            let quantifier = match self.peek() {
+                Some('*') => Quantifier::ZeroOrMore,
+                Some('+') => Quantifier::OneOrMore,
+                Some('?') => Quantifier::ZeroOrOne,
+                Some('@') => {
+                    self.bump();
+                    let name = self
+                        .parse_identifier()
+                        .ok_or_else(|| self.error("expected capture name after '@'"))?;
+                    let id = self.capture_id(&name);
+                    step.captures.push(id);
+                    continue;
+                }
+                _ => return Ok(()),
+            };
+            self.bump();
+            step.quantifier = step.quantifier.combine(quantifier);
+        }
+    }
+
+    /// Parses the remainder of `(kind ...)` after the opening parenthesis.
+    fn parse_node_body(&mut self) -> Result<StepKind, QueryError> {
+        let matcher = if self.peek() == Some('"') {
+            NodeMatcher::Anonymous(self.parse_string()?)
+        } else {
+            let name = self
+                .parse_identifier()
+                .ok_or_else(|| self.error("expected node kind"))?;
+            if name == "_" {
+                NodeMatcher::AnyNamed
+            } else if name == "MISSING" {
+                self.skip_trivia();
+                let missing = if self.peek() == Some('"') {
+                    Some((self.parse_string()?, false))
+                } else {
+                    self.parse_identifier().map(|kind| (kind, true))
+                };
+                NodeMatcher::Missing(missing)
+            } else if self.eat('/') {
+                let (subtype, named) = if self.peek() == Some('"') {
+                    (self.parse_string()?, false)
+                } else {
+                    let subtype = self
+                        .parse_identifier()
+                        .ok_or_else(|| self.error("expected subtype after '/'"))?;
+                    (subtype, true)
+                };
+                NodeMatcher::Named {
+                    kind: subtype,
+                    named,
+                    supertype: Some(name),
+                }
+            } else {
+                NodeMatcher::Named {
+                    kind: name,
+                    named: true,
+                    supertype: None,
+                }
+            }
+        };
+        let (children, negated_fields) = self.parse_children()?;
+        Ok(StepKind::Node {
+            matcher,
+            children,
+            negated_fields,
+        })
+    }
+
+    /// Parses child items up to and including the closing parenthesis.
+    fn parse_children(&mut self) -> Result<(Sequence, Vec<(String, usize)>), QueryError> {
+        let mut sequence = Sequence::default();
+        let mut negated_fields = Vec::new();
+        let mut pending_anchor = false;
+        loop {
+            self.skip_trivia();
+            if self.eat(')') {
+                sequence.anchor_end = pending_anchor;
+                return Ok((sequence, negated_fields));
+            }
+            if self.pos >= self.source.len() {
+                return Err(self.error("expected ')'"));
+            }
+            match self.parse_item(Context::Children)? {
+                Item::Step(mut step) => {
+                    step.anchored = pending_anchor;
+                    pending_anchor = false;
+                    sequence.steps.push(step);
+                }
+                Item::Anchor => pending_anchor = true,
+                Item::NegatedField(name, offset) => negated_fields.push((name, offset)),
+                Item::Predicate(predicate) => self.predicates.push(predicate),
+            }
+        }
+    }
+
+    /// Parses `#name args... )` after the opening parenthesis.
+    fn parse_predicate(&mut self, start: usize) -> Result<RawPredicate, QueryError> {
+        self.eat('#');
+        let name = self
+            .parse_identifier()
+            .ok_or_else(|| self.error("expected predicate name"))?;
+        let mut args = Vec::new();
+        loop {
+            self.skip_trivia();
+            match self.peek() {
+                Some(')') => {
+                    self.bump();
+                    return Ok(RawPredicate {
+                        name,
+                        args,
+                        offset: start,
+                    });
+                }
+                Some('@') => {
+                    let capture_start = self.pos;
+                    self.bump();
+                    let capture = self
+                        .parse_identifier()
+                        .ok_or_else(|| self.error("expected capture name after '@'"))?;
+                    let id = self
+                        .capture_names
+                        .iter()
+                        .position(|n| *n == capture)
+                        .ok_or_else(|| {
+                            self.error_at(
+                                capture_start,
+                                QueryErrorKind::Capture,
+                                format!("undefined capture '@{capture}'"),
+                            )
+                        })?;
+                    args.push(RawArg::Capture(id));
+                }
+                Some('"') => args.push(RawArg::String(self.parse_string()?)),
+                Some(_) => {
+                    let word = self
+                        .parse_identifier()
+                        .ok_or_else(|| self.error("expected predicate argument"))?;
+                    args.push(RawArg::String(word));
+                }
+                None => return Err(self.error("expected ')'")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_nested_pattern() {
+        let parsed = parse(
+            r"
+            ; a comment
+            (pair
+              key: (string) @key
+              . value: [(number) (null)] @value
+              !extra)
+            ",
+        )
+        .unwrap();
+        assert_eq!(parsed.capture_names, ["key", "value"]);
+        let [pattern] = parsed.patterns.as_slice() else {
+            panic!("expected one pattern");
+        };
+        let StepKind::Node {
+            children,
+            negated_fields,
+            ..
+        } = &pattern.sequence.steps[0].kind
+        else {
+            panic!("expected a node step");
+        };
+        assert_eq!(children.steps.len(), 2);
+        assert_eq!(children.steps[0].field.as_deref(), Some("key"));
+        assert!(children.steps[1].anchored);
+        assert!(
+            matches!(children.steps[1].kind, StepKind::Alternation(ref alts) if alts.len() == 2)
+        );
+        assert_eq!(negated_fields[0].0, "extra");
+    }
+
+    #[test]
+    fn test_parse_group_with_predicate() {
+        let parsed = parse(r#"((comment)+ @doc . (function) @fn (#match? @doc "^///"))"#).unwrap();
+        let pattern = &parsed.patterns[0];
+        assert_eq!(pattern.sequence.steps.len(), 2);
+        assert_eq!(pattern.sequence.steps[0].quantifier, Quantifier::OneOrMore);
+        assert_eq!(pattern.predicates.len(), 1);
+        assert_eq!(pattern.predicates[0].name, "match?");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = parse("(identifier").unwrap_err();
+        assert_eq!(err.kind, QueryErrorKind::Syntax);
+        let err = parse("((a) @x (#eq? @y \"z\"))").unwrap_err();
+        assert_eq!(err.kind, QueryErrorKind::Capture);
+        assert_eq!(err.column, 14);
+        assert!(parse("identifier").is_err());
+    }
+}
@@ -0,0 +1,442 @@
+//! Checking queries against a grammar's node types.
+//!
+//! [`Query::new`](super::Query::new) rejects names a grammar never defines,
+//! but a query can name only real node kinds and fields and still never
+//! match: `(call_expression name: (identifier))` when the field is called
+//! `function`, or `(string (identifier))` when strings have no named
+//! children. [`validate`] walks each pattern with the [`NodeTypes`] of the
+//! grammar and reports every step that no tree could satisfy, so a renamed
+//! rule fails CI instead of quietly switching off highlighting.
+//!
+//! The checks work from the grammar's rules, not from parse tables, so they
+//! are conservative: a pattern is only reported when the node types rule it
+//! out entirely.
+
+use super::parser::{self, NodeMatcher, Sequence, Step, StepKind};
+use super::{QueryError, QueryErrorKind};
+use crate::node_types::{NodeInfo, NodeKind, NodeTypes};
+use crate::tree::ERROR_KIND;
+use std::collections::BTreeSet;
+
+/// Checks the query `source` against `node_types`, returning every problem
+/// found.
+///
+/// Besides syntax errors, this reports unknown node types and fields, fields
+/// used on nodes that never have them, and children that can never appear
+/// under their parent pattern. An empty result means every pattern can
+/// match some tree.
+#[must_use]
+pub fn validate(node_types: &NodeTypes, source: &str) -> Vec<QueryError> {
+    let parsed = match parser::parse(source) {
+        Ok(parsed) => parsed,
+        Err(error) => return vec![error],
+    };
+    let mut checker = Checker {
+        node_types,
+        source,
+        fields: node_types
+            .iter()
+            .flat_map(|node| node.fields.iter().flatten())
+            .map(|(name, _)| name.as_str())
+            .collect(),
+        errors: Vec::new(),
+    };
+    for pattern in &parsed.patterns {
+        checker.check_sequence(&pattern.sequence, Scope::TopLevel);
+    }
+    checker.errors
+}
+
+/// The nodes a parent pattern can match, for checking its children.
+struct Parent<'a> {
+    /// How the parent is written in the query, for messages.
+    name: String,
+    nodes: Vec<&'a NodeInfo>,
+}
+
+impl Parent<'_> {
+    /// The kinds that can appear in `field`, or anywhere among the children
+    /// when `field` is `None`.
+    fn child_kinds(&self, field: Option<&str>) -> Vec<NodeKind> {
+        let mut kinds = Vec::new();
+        for node in &self.nodes {
+            let fields = node.fields.iter().flatten();
+            if let Some(field) = field {
+                kinds.extend(
+                    fields
+                        .filter(|(name, _)| *name == field)
+                        .flat_map(|(_, info)| info.types.iter().cloned()),
+                );
+            } else {
+                kinds.extend(fields.flat_map(|(_, info)| info.types.iter().cloned()));
+                kinds.extend(node.children.iter().flat_map(|c| c.types.iter().cloned()));
+            }
+        }
+        kinds
+    }
+}
+
+/// Where a step sits in its pattern.
+#[derive(Clone, Copy)]
+enum Scope<'p, 'a> {
+    TopLevel,
+    /// Under a wildcard, or a parent whose kind is unknown.
+    Any,
+    Node(&'p Parent<'a>),
+}
+
+impl<'p, 'a> Scope<'p, 'a> {
+    fn parent(self) -> Option<&'p Parent<'a>> {
+        match self {
+            Scope::Node(parent) => Some(parent),
+            Scope::TopLevel | Scope::Any => None,
+        }
+    }
+}
+
+struct Checker<'a> {
+    node_types: &'a NodeTypes,
+    source: &'a str,
+    fields: BTreeSet<&'a str>,
+    errors: Vec<QueryError>,
+}
+
+impl<'a> Checker<'a> {
+    fn error(&mut self, offset: usize, kind: QueryErrorKind, message: String) {
+        self.errors
+            .push(QueryError::new(self.source, offset, kind, message));
+    }
+
+    fn check_sequence(&mut self, sequence: &Sequence, scope: Scope<'_, 'a>) {
+        for step in &sequence.steps {
+            self.check_step(step, scope, step.field.as_deref());
+        }
+    }
+
+    /// Checks `step` in `scope`, occupying `field`. Alternatives inherit the
+    /// field of their step.
+    fn check_step(&mut self, step: &Step, scope: Scope<'_, 'a>, field: Option<&str>) {
+        let parent = scope.parent();
+        if let Some(field) = field {
+            if matches!(scope, Scope::TopLevel) {
+                self.error(
+                    step.offset,
+                    QueryErrorKind::Structure,
+                    "a top-level pattern cannot have a field".to_string(),
+                );
+            } else {
+                self.check_field(step.offset, field, parent);
+            }
+        }
+        match &step.kind {
+            StepKind::Node {
+                matcher,
+                children,
+                negated_fields,
+            } => {
+                let kinds = self.matcher_kinds(step.offset, matcher);
+                if let (Some(parent), Some(kinds)) = (parent, &kinds) {
+                    self.check_child(step.offset, matcher, kinds, parent, field);
+                }
+                let this = kinds.map(|kinds| Parent {
+                    name: describe(matcher),
+                    nodes: kinds
+                        .iter()
+                        .filter_map(|kind| self.node_types.get(&kind.kind, kind.named))
+                        .collect(),
+                });
+                for (field, offset) in negated_fields {
+                    self.check_field(*offset, field, this.as_ref());
+                }
+                self.check_sequence(children, this.as_ref().map_or(Scope::Any, Scope::Node));
+            }
+            StepKind::Alternation(alternatives) => {
+                for alternative in alternatives {
+                    let field = alternative.field.as_deref().or(field);
+                    self.check_step(alternative, scope, field);
+                }
+            }
+            StepKind::Group(sequence) => self.check_sequence(sequence, scope),
+        }
+    }
+
+    fn check_field(&mut self, offset: usize, field: &str, parent: Option<&Parent<'a>>) {
+        if !self.fields.contains(field) {
+            self.error(
+                offset,
+                QueryErrorKind::Field,
+                format!("unknown field '{field}'"),
+            );
+            return;
+        }
+        if let Some(parent) = parent {
+            let has_field = parent
+                .nodes
+                .iter()
+                .any(|node| node.fields.as_ref().is_some_and(|f| f.contains_key(field)));
+            if !has_field {
+                self.error(
+                    offset,
+                    QueryErrorKind::Field,
+                    format!("'{}' has no field '{field}'", parent.name),
+                );
+            }
+        }
+    }
+
+    /// Reports a child whose kinds can never appear under `parent`.
+    fn check_child(
+        &mut self,
+        offset: usize,
+        matcher: &NodeMatcher,
+        kinds: &[NodeKind],
+        parent: &Parent<'a>,
+        field: Option<&str>,
+    ) {
+        // Unfielded tokens aren't recorded in node types, and extras can
+        // appear anywhere.
+        let anonymous = kinds.iter().all(|kind| !kind.named);
+        if (field.is_none() && anonymous) || kinds.iter().any(|kind| self.is_extra(kind)) {
+            return;
+        }
+        if let Some(field) = field {
+            if !parent.nodes.iter().any(|node| {
+                node.fields
+                    .as_ref()
+                    .is_some_and(|fields| fields.contains_key(field))
+            }) {
+                // Already reported as a missing field.
+                return;
+            }
+        }
+        let allowed = self.node_types.concrete_kinds(&parent.child_kinds(field));
+        if kinds.iter().any(|kind| allowed.contains(kind)) {
+            return;
+        }
+        let child = describe(matcher);
+        let message = match field {
+            Some(field) => format!(
+                "'{child}' cannot appear in field '{field}' of '{}'",
+                parent.name
+            ),
+            None => format!("'{child}' cannot be a child of '{}'", parent.name),
+        };
+        self.error(offset, QueryErrorKind::Structure, message);
+    }
+
+    fn is_extra(&self, kind: &NodeKind) -> bool {
+        self.node_types
+            .get(&kind.kind, kind.named)
+            .is_some_and(|node| node.extra)
+    }
+
+    /// The concrete kinds `matcher` can match, or `None` when it is
+    /// unconstrained or names something unknown (which is reported).
+    fn matcher_kinds(&mut self, offset: usize, matcher: &NodeMatcher) -> Option<Vec<NodeKind>> {
+        let exists = |kind: &str, named: bool| self.node_types.get(kind, named).is_some();
+        match matcher {
+            NodeMatcher::Any | NodeMatcher::AnyNamed | NodeMatcher::Missing(None) => None,
+            NodeMatcher::Named {
+                kind,
+                supertype: None,
+                ..
+            } if kind == ERROR_KIND => None,
+            NodeMatcher::Named {
+                kind,
+                supertype: None,
+                ..
+            } => {
+                if exists(kind, true) {
+                    let kind = NodeKind {
+                        kind: kind.clone(),
+                        named: true,
+                    };
+                    Some(
+                        self.node_types
+                            .concrete_kinds(&[kind])
+                            .into_iter()
+                            .collect(),
+                    )
+                } else {
+                    self.unknown(offset, kind);
+                    None
+                }
+            }
+            NodeMatcher::Named {
+                kind,
+                named,
+                supertype: Some(supertype),
+            } => {
+                let Some(subtypes) = self
+                    .node_types
+                    .get(supertype, true)
+                    .and_then(|node| node.subtypes.as_ref())
+                else {
+                    self.unknown(offset, supertype);
+                    return None;
+                };
+                let subtypes = self.node_types.concrete_kinds(subtypes);
+                if !exists(kind, *named) {
+                    self.unknown(offset, kind);
+                    return None;
+                }
+                let node_kind = NodeKind {
+                    kind: kind.clone(),
+                    named: *named,
+                };
+                if !subtypes.contains(&node_kind) {
+                    self.error(
+                        offset,
+                        QueryErrorKind::Structure,
+                        format!("'{kind}' is not a subtype of '{supertype}'"),
+                    );
+                    return None;
+                }
+                Some(vec![node_kind])
+            }
+            NodeMatcher::Anonymous(text) => {
+                if exists(text, false) {
+                    Some(vec![NodeKind {
+                        kind: text.clone(),
+                        named: false,
+                    }])
+                } else {
+                    self.unknown(offset, &format!("\"{text}\""));
+                    None
+                }
+            }
+            NodeMatcher::Missing(Some((kind, named))) => {
+                if !exists(kind, *named) {
+                    let kind = if *named {
+                        kind.clone()
+                    } else {
+                        format!("\"{kind}\"")
+                    };
+                    self.unknown(offset, &kind);
+                }
+                None
+            }
+        }
+    }
+
+    fn unknown(&mut self, offset: usize, kind: &str) {
+        self.error(
+            offset,
+            QueryErrorKind::NodeType,
+            format!("unknown node type '{kind}'"),
+        );
+    }
+}
+
+/// How `matcher` is written in a query, for messages.
+fn describe(matcher: &NodeMatcher) -> String {
+    match matcher {
+        NodeMatcher::Any => "_".to_string(),
+        NodeMatcher::AnyNamed => "(_)".to_string(),
+        NodeMatcher::Named {
+            kind,
+            supertype: None,
+            ..
+        } => kind.clone(),
+        NodeMatcher::Named {
+            kind,
+            named: true,
+            supertype: Some(supertype),
+        } => format!("{supertype}/{kind}"),
+        NodeMatcher::Named {
+            kind,
+            named: false,
+            supertype: Some(supertype),
+        } => format!("{supertype}/\"{kind}\""),
+        NodeMatcher::Anonymous(text) => format!("\"{text}\""),
+        NodeMatcher::Missing(_) => "MISSING".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_grammar;
+
+    const GRAMMAR: &str = r##"{
+        "name": "mini",
+        "extras": [{"type": "SYMBOL", "name": "comment"}, {"type": "PATTERN", "value": "\\s"}],
+        "supertypes": ["_expression"],
+        "rules": {
+            "program": {"type": "REPEAT", "content": {"type": "SYMBOL", "name": "call"}},
+            "call": {"type": "SEQ", "members": [
+                {"type": "FIELD", "name": "function", "content": {"type": "SYMBOL", "name": "identifier"}},
+                {"type": "STRING", "value": "("},
+                {"type": "REPEAT", "content": {"type": "FIELD", "name": "argument",
+                    "content": {"type": "SYMBOL", "name": "_expression"}}},
+                {"type": "STRING", "value": ")"}
+            ]},
+            "_expression": {"type": "CHOICE", "members": [
+                {"type": "SYMBOL", "name": "identifier"},
+                {"type": "SYMBOL", "name": "string"}
+            ]},
+            "string": {"type": "SEQ", "members": [
+                {"type": "STRING", "value": "\""},
+                {"type": "PATTERN", "value": "[^\"]*"},
+                {"type": "STRING", "value": "\""}
+            ]},
+            "identifier": {"type": "PATTERN", "value": "[a-z]+"},
+            "comment": {"type": "PATTERN", "value": "#.*"}
+        }
+    }"##;
+
+    fn messages(query: &str) -> Vec<String> {
+        let grammar = parse_grammar(GRAMMAR).unwrap();
+        let node_types = NodeTypes::from_grammar(&grammar).unwrap();
+        validate(&node_types, query)
+            .into_iter()
+            .map(|error| error.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_patterns() {
+        let query = r#"
+            (call function: (identifier) @name)
+            (call argument: (_expression/string))
+            (call argument: [(identifier) (string)] "(" (comment))
+            (program (call) @call)
+            (_ argument: (string))
+            (ERROR) (MISSING ")")
+        "#;
+        assert_eq!(messages(query), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_unknown_names() {
+        assert_eq!(
+            messages(r#"(call (callee)) (call name: (identifier)) "[""#),
+            [
+                "query error at 1:7: unknown node type 'callee'",
+                "query error at 1:23: unknown field 'name'",
+                "query error at 1:43: unknown node type '\"[\"'",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_impossible_structure() {
+        assert_eq!(
+            messages(
+                "(string (identifier))\n\
+                 (string function: (identifier))\n\
+                 (call function: (string))\n\
+                 (call argument: (_expression/call))\n\
+                 (call [argument: (call) (program)])"
+            ),
+            [
+                "query error at 1:9: 'identifier' cannot be a child of 'string'",
+                "query error at 2:9: 'string' has no field 'function'",
+                "query error at 3:7: 'string' cannot appear in field 'function' of 'call'",
+                "query error at 4:7: 'call' is not a subtype of '_expression'",
+                "query error at 5:8: 'call' cannot appear in field 'argument' of 'call'",
+                "query error at 5:25: 'program' cannot be a child of 'call'",
+            ]
+        );
+    }
+}
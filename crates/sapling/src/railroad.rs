@@ -0,0 +1,501 @@
+//! Railroad diagrams of grammar rules.
+//!
+//! A rule is drawn left to right as the tracks a parse can take through it:
+//! sequences run along one line, choices branch below it, and repetitions
+//! loop back under what they repeat. Strings are drawn in rounded boxes,
+//! patterns in boxes between slashes, and references to other rules in
+//! square boxes that link to those rules' diagrams on the
+//! [`render_html`](crate::railroad::render_html) page. Fields, aliases,
+//! precedence and token wrappers are drawn as labelled dashed boxes around
+//! what they apply to.
+//!
+//! Text is measured with a fixed width per character, which is exact for
+//! the monospace font the diagrams are styled with.
+
+use crate::grammar::{Grammar, Rule, RuleType, RuleValue};
+use std::fmt::Write;
+
+/// The radius of the arcs where tracks branch and join.
+const ARC: usize = 10;
+/// The space between the branches of a choice.
+const BRANCH_GAP: usize = 10;
+/// The length of track between the items of a sequence.
+const GAP: usize = 10;
+/// The height of a box.
+const BOX_HEIGHT: usize = 24;
+/// The space between a box's text and its sides.
+const BOX_PADDING: usize = 10;
+/// The width of a character of box text.
+const CHAR_WIDTH: usize = 8;
+/// The width of a character of a dashed box's label.
+const LABEL_CHAR_WIDTH: usize = 7;
+/// The height of a dashed box's label.
+const LABEL_HEIGHT: usize = 14;
+/// The space between a dashed box and what it contains.
+const GROUP_PADDING: usize = 8;
+/// The space around a whole diagram.
+const MARGIN: usize = 20;
+
+/// The styles of diagrams, shared by standalone SVGs and the HTML page.
+const STYLE: &str = "\
+svg.railroad path { stroke: #333; stroke-width: 2; fill: none; }
+svg.railroad text { font: 14px monospace; fill: #111; text-anchor: middle; }
+svg.railroad text.label { font-size: 12px; fill: #555; text-anchor: start; }
+svg.railroad rect { stroke: #333; stroke-width: 2; }
+svg.railroad .terminal rect { fill: #fff3d6; }
+svg.railroad .pattern rect { fill: #e8f5e0; }
+svg.railroad .nonterminal rect { fill: #d6e6ff; }
+svg.railroad .group rect { fill: none; stroke: #999; stroke-width: 1; stroke-dasharray: 4 3; }
+";
+
+/// A part of a diagram.
+enum Item {
+    /// A string token, quoted.
+    Terminal(String),
+    /// A pattern token.
+    Pattern(String),
+    /// A reference to a rule.
+    NonTerminal(String),
+    /// Nothing: a straight track.
+    Skip,
+    /// Items one after another.
+    Sequence(Vec<Item>),
+    /// Branches stacked downwards, the first on the main track.
+    Choice(Vec<Item>),
+    /// An item with a track looping back from its end to its start.
+    OneOrMore(Box<Item>),
+    /// An item in a dashed box, labelled with what applies to it.
+    Group(String, Box<Item>),
+}
+
+impl Item {
+    fn from_rule(rule: &Rule) -> Self {
+        let content = || rule.content.as_deref().map_or(Item::Skip, Item::from_rule);
+        let group = |label: String| Item::Group(label, Box::new(content()));
+        match rule.rule_type {
+            RuleType::Blank => Item::Skip,
+            RuleType::String => Item::Terminal(format!("\"{}\"", string_value(rule))),
+            RuleType::Pattern => {
+                let flags = rule.flags.as_deref().unwrap_or_default();
+                Item::Pattern(format!("/{}/{flags}", string_value(rule)))
+            }
+            RuleType::Symbol => Item::NonTerminal(rule.name.clone().unwrap_or_default()),
+            RuleType::Seq => Item::Sequence(rule.members.iter().map(Item::from_rule).collect()),
+            RuleType::Choice => {
+                // A blank member makes the choice optional; it is drawn
+                // once, as the main track, so the alternatives branch off.
+                let optional = rule
+                    .members
+                    .iter()
+                    .any(|member| member.rule_type == RuleType::Blank);
+                let branches = rule
+                    .members
+                    .iter()
+                    .filter(|member| member.rule_type != RuleType::Blank)
+                    .map(Item::from_rule);
+                Item::Choice(
+                    optional
+                        .then_some(Item::Skip)
+                        .into_iter()
+                        .chain(branches)
+                        .collect(),
+                )
+            }
+            RuleType::Repeat => {
+                Item::Choice(vec![Item::Skip, Item::OneOrMore(Box::new(content()))])
+            }
+            RuleType::Repeat1 => Item::OneOrMore(Box::new(content())),
+            RuleType::Prec => group(format!("prec({})", precedence(rule))),
+            RuleType::PrecLeft => group(format!("prec.left({})", precedence(rule))),
+            RuleType::PrecRight => group(format!("prec.right({})", precedence(rule))),
+            RuleType::PrecDynamic => group(format!("prec.dynamic({})", precedence(rule))),
+            RuleType::Field => group(format!("{}:", rule.name.as_deref().unwrap_or_default())),
+            RuleType::Alias => {
+                let alias = string_value(rule);
+                if rule.named == Some(true) {
+                    group(format!("as {alias}"))
+                } else {
+                    group(format!("as \"{alias}\""))
+                }
+            }
+            RuleType::Token => group("token".to_string()),
+            RuleType::ImmediateToken => group("token.immediate".to_string()),
+            RuleType::Reserved => group(format!(
+                "reserved({})",
+                rule.context_name.as_deref().unwrap_or_default()
+            )),
+        }
+    }
+
+    fn width(&self) -> usize {
+        match self {
+            Item::Terminal(text) | Item::Pattern(text) | Item::NonTerminal(text) => box_width(text),
+            Item::Skip => 0,
+            Item::Sequence(items) => {
+                items.iter().map(Item::width).sum::<usize>() + GAP * items.len().saturating_sub(1)
+            }
+            Item::Choice(branches) => {
+                branches.iter().map(Item::width).max().unwrap_or_default() + 4 * ARC
+            }
+            Item::OneOrMore(item) => item.width() + 2 * ARC,
+            Item::Group(label, item) => {
+                (item.width() + 2 * GROUP_PADDING).max(label_width(label) + GROUP_PADDING)
+            }
+        }
+    }
+
+    /// How far the item reaches above its track.
+    fn up(&self) -> usize {
+        match self {
+            Item::Terminal(_) | Item::Pattern(_) | Item::NonTerminal(_) => BOX_HEIGHT / 2,
+            Item::Skip => 0,
+            Item::Sequence(items) => items.iter().map(Item::up).max().unwrap_or_default(),
+            Item::Choice(branches) => branches.first().map_or(0, Item::up),
+            Item::OneOrMore(item) => item.up(),
+            Item::Group(_, item) => item.up() + GROUP_PADDING + LABEL_HEIGHT,
+        }
+    }
+
+    /// How far the item reaches below its track.
+    fn down(&self) -> usize {
+        match self {
+            Item::Terminal(_) | Item::Pattern(_) | Item::NonTerminal(_) => BOX_HEIGHT / 2,
+            Item::Skip => 0,
+            Item::Sequence(items) => items.iter().map(Item::down).max().unwrap_or_default(),
+            Item::Choice(branches) => {
+                let offsets = branch_offsets(branches);
+                branches
+                    .last()
+                    .zip(offsets.last())
+                    .map_or(0, |(branch, offset)| offset + branch.down())
+            }
+            Item::OneOrMore(item) => loop_offset(item),
+            Item::Group(_, item) => item.down() + GROUP_PADDING,
+        }
+    }
+
+    /// Draws the item with its track entering at `(x, y)`.
+    fn draw(&self, out: &mut String, x: usize, y: usize) {
+        match self {
+            Item::Terminal(text) => draw_box(out, "terminal", text, x, y, BOX_HEIGHT / 2),
+            Item::Pattern(text) => draw_box(out, "pattern", text, x, y, 0),
+            Item::NonTerminal(name) => {
+                out.push_str("<a href=\"#");
+                escape(out, name);
+                out.push_str("\">");
+                draw_box(out, "nonterminal", name, x, y, 0);
+                out.push_str("</a>");
+            }
+            Item::Skip => {}
+            Item::Sequence(items) => {
+                let mut x = x;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        line(out, x, y, x + GAP);
+                        x += GAP;
+                    }
+                    item.draw(out, x, y);
+                    x += item.width();
+                }
+            }
+            Item::Choice(branches) => {
+                let width = self.width();
+                let (start, end) = (x + 2 * ARC, x + width - 2 * ARC);
+                for (branch, offset) in branches.iter().zip(branch_offsets(branches)) {
+                    let branch_y = y + offset;
+                    if offset == 0 {
+                        line(out, x, y, start);
+                    } else {
+                        let _ = write!(
+                            out,
+                            "<path d=\"M{x} {y}A{ARC} {ARC} 0 0 1 {} {}V{}A{ARC} {ARC} 0 0 0 {start} {branch_y}\"/>",
+                            x + ARC,
+                            y + ARC,
+                            branch_y - ARC,
+                        );
+                    }
+                    branch.draw(out, start, branch_y);
+                    line(out, start + branch.width(), branch_y, end);
+                    if offset == 0 {
+                        line(out, end, y, x + width);
+                    } else {
+                        let _ = write!(
+                            out,
+                            "<path d=\"M{end} {branch_y}A{ARC} {ARC} 0 0 0 {} {}V{}A{ARC} {ARC} 0 0 1 {} {y}\"/>",
+                            end + ARC,
+                            branch_y - ARC,
+                            y + ARC,
+                            x + width,
+                        );
+                    }
+                }
+            }
+            Item::OneOrMore(item) => {
+                let width = self.width();
+                let back = y + loop_offset(item);
+                line(out, x, y, x + ARC);
+                item.draw(out, x + ARC, y);
+                line(out, x + ARC + item.width(), y, x + width);
+                let _ = write!(
+                    out,
+                    "<path d=\"M{} {y}A{ARC} {ARC} 0 0 1 {} {}V{}A{ARC} {ARC} 0 0 1 {} {back}\
+                     H{}A{ARC} {ARC} 0 0 1 {x} {}V{}A{ARC} {ARC} 0 0 1 {} {y}\"/>",
+                    x + width - ARC,
+                    x + width,
+                    y + ARC,
+                    back - ARC,
+                    x + width - ARC,
+                    x + ARC,
+                    back - ARC,
+                    y + ARC,
+                    x + ARC,
+                );
+            }
+            Item::Group(label, item) => {
+                let (width, up) = (self.width(), self.up());
+                let _ = write!(
+                    out,
+                    "<g class=\"group\"><rect x=\"{x}\" y=\"{}\" width=\"{width}\" height=\"{}\" rx=\"4\"/>\
+                     <text class=\"label\" x=\"{}\" y=\"{}\">",
+                    y - up,
+                    up + self.down(),
+                    x + GROUP_PADDING / 2,
+                    y - up + LABEL_HEIGHT - 2,
+                );
+                escape(out, label);
+                out.push_str("</text></g>");
+                line(out, x, y, x + GROUP_PADDING);
+                item.draw(out, x + GROUP_PADDING, y);
+                line(out, x + GROUP_PADDING + item.width(), y, x + width);
+            }
+        }
+    }
+}
+
+/// How far below the main track each branch of a choice runs. Branches
+/// clear the one above them and leave room for the arcs leading to them.
+fn branch_offsets(branches: &[Item]) -> Vec<usize> {
+    let mut offsets: Vec<usize> = Vec::with_capacity(branches.len());
+    for (i, branch) in branches.iter().enumerate() {
+        let offset = match i.checked_sub(1) {
+            None => 0,
+            Some(above) => {
+                (offsets[above] + branches[above].down() + BRANCH_GAP + branch.up()).max(2 * ARC)
+            }
+        };
+        offsets.push(offset);
+    }
+    offsets
+}
+
+/// How far below the main track the track looping back under `item` runs.
+fn loop_offset(item: &Item) -> usize {
+    (item.down() + BRANCH_GAP).max(2 * ARC)
+}
+
+fn box_width(text: &str) -> usize {
+    text.chars().count() * CHAR_WIDTH + 2 * BOX_PADDING
+}
+
+fn label_width(label: &str) -> usize {
+    label.chars().count() * LABEL_CHAR_WIDTH
+}
+
+fn draw_box(out: &mut String, class: &str, text: &str, x: usize, y: usize, radius: usize) {
+    let _ = write!(
+        out,
+        "<g class=\"{class}\"><rect x=\"{x}\" y=\"{}\" width=\"{}\" height=\"{BOX_HEIGHT}\" rx=\"{radius}\"/>\
+         <text x=\"{}\" y=\"{}\">",
+        y - BOX_HEIGHT / 2,
+        box_width(text),
+        x + box_width(text) / 2,
+        y + 5,
+    );
+    escape(out, text);
+    out.push_str("</text></g>");
+}
+
+fn line(out: &mut String, from: usize, y: usize, to: usize) {
+    if to > from {
+        let _ = write!(out, "<path d=\"M{from} {y}H{to}\"/>");
+    }
+}
+
+fn string_value(rule: &Rule) -> &str {
+    match &rule.value {
+        Some(RuleValue::String(value)) => value,
+        _ => "",
+    }
+}
+
+fn precedence(rule: &Rule) -> String {
+    match &rule.value {
+        Some(RuleValue::Integer(value)) => value.to_string(),
+        Some(RuleValue::String(name)) => format!("'{name}'"),
+        None => "0".to_string(),
+    }
+}
+
+/// Writes text with the characters XML reserves escaped.
+fn escape(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' => out.push_str("&amp;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+}
+
+/// Draws a whole diagram as an `<svg>` element, without styles.
+fn svg(rule: &Rule) -> String {
+    let item = Item::from_rule(rule);
+    let (up, down) = (item.up(), item.down());
+    let width = item.width() + 2 * MARGIN + 2 * GAP;
+    let height = up + down + 2 * MARGIN;
+    let y = MARGIN + up;
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" class=\"railroad\" \
+         width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">"
+    );
+    // The ends of the diagram are marked with bars across the track.
+    let (start, end) = (MARGIN, width - MARGIN);
+    let _ = write!(
+        out,
+        "<path d=\"M{start} {}V{}M{end} {}V{}\"/>",
+        y - ARC,
+        y + ARC,
+        y - ARC,
+        y + ARC
+    );
+    line(&mut out, start, y, start + GAP);
+    item.draw(&mut out, start + GAP, y);
+    line(&mut out, start + GAP + item.width(), y, end);
+    out.push_str("</svg>");
+    out
+}
+
+/// Renders a rule as a standalone SVG railroad diagram.
+#[must_use]
+pub fn render_svg(rule: &Rule) -> String {
+    let svg = svg(rule);
+    let (open, rest) = svg.split_at(svg.find('>').map_or(0, |end| end + 1));
+    format!("{open}<style>\n{STYLE}</style>{rest}\n")
+}
+
+/// Renders a page with the diagram of every rule of a grammar, in
+/// declaration order. Each rule's section has the rule's name as its `id`,
+/// which the boxes of references to it link to.
+#[must_use]
+pub fn render_html(grammar: &Grammar) -> String {
+    let mut name = String::new();
+    escape(&mut name, &grammar.name);
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{name} grammar</title>\n<style>\n\
+         body {{ font-family: sans-serif; margin: 2em; }}\n\
+         h2 {{ font: 1.1em monospace; }}\n\
+         h2 a {{ color: inherit; text-decoration: none; }}\n\
+         {STYLE}</style>\n</head>\n<body>\n<h1>{name}</h1>\n"
+    );
+    for (rule_name, rule) in &grammar.rules {
+        let mut id = String::new();
+        escape(&mut id, rule_name);
+        let _ = writeln!(
+            out,
+            "<section id=\"{id}\">\n<h2><a href=\"#{id}\">{id}</a></h2>\n{}\n</section>",
+            svg(rule)
+        );
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_grammar;
+
+    fn rule(json: &str) -> Rule {
+        let grammar =
+            parse_grammar(&format!(r#"{{"name": "t", "rules": {{"r": {json}}}}}"#)).unwrap();
+        grammar.rules["r"].clone()
+    }
+
+    #[test]
+    fn test_sequence_layout() {
+        let item = Item::from_rule(&rule(
+            r#"{"type": "SEQ", "members": [
+                {"type": "STRING", "value": "("},
+                {"type": "SYMBOL", "name": "expression"},
+                {"type": "STRING", "value": ")"}
+            ]}"#,
+        ));
+        // Boxes of 3, 10 and 3 characters, with two gaps between them.
+        assert_eq!(item.width(), 44 + 100 + 44 + 2 * GAP);
+        assert_eq!((item.up(), item.down()), (12, 12));
+    }
+
+    #[test]
+    fn test_choice_and_repeat_layout() {
+        let optional = Item::from_rule(&rule(
+            r#"{"type": "CHOICE", "members": [
+                {"type": "SYMBOL", "name": "a"},
+                {"type": "BLANK"}
+            ]}"#,
+        ));
+        let Item::Choice(branches) = &optional else {
+            panic!("not a choice");
+        };
+        assert!(matches!(branches[..], [Item::Skip, Item::NonTerminal(_)]));
+        // The box branches off below the main track, clearing the arcs.
+        assert_eq!(branch_offsets(branches), [0, 22]);
+        assert_eq!((optional.up(), optional.down()), (0, 34));
+
+        let repeat = Item::from_rule(&rule(
+            r#"{"type": "REPEAT1", "content": {"type": "SYMBOL", "name": "a"}}"#,
+        ));
+        assert_eq!(repeat.width(), box_width("a") + 2 * ARC);
+        assert_eq!(repeat.down(), 22);
+    }
+
+    #[test]
+    fn test_svg_labels_fields_and_precedence() {
+        let svg = render_svg(&rule(
+            r#"{"type": "PREC_LEFT", "value": 2, "content": {"type": "SEQ", "members": [
+                {"type": "FIELD", "name": "left", "content": {"type": "SYMBOL", "name": "expression"}},
+                {"type": "STRING", "value": "<"},
+                {"type": "PATTERN", "value": "[a-z]+"}
+            ]}}"#,
+        ));
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" class=\"railroad\""));
+        assert!(svg.contains("<style>"));
+        assert!(svg.contains(">prec.left(2)</text>"));
+        assert!(svg.contains(">left:</text>"));
+        assert!(svg.contains("<a href=\"#expression\"><g class=\"nonterminal\">"));
+        assert!(svg.contains(">&quot;&lt;&quot;</text>"));
+        assert!(svg.contains(">/[a-z]+/</text>"));
+        assert!(svg.ends_with("</svg>\n"));
+    }
+
+    #[test]
+    fn test_html_has_a_section_per_rule() {
+        let grammar = parse_grammar(
+            r#"{"name": "t", "rules": {
+                "program": {"type": "REPEAT", "content": {"type": "SYMBOL", "name": "word"}},
+                "word": {"type": "PATTERN", "value": "\\w+"}
+            }}"#,
+        )
+        .unwrap();
+        let html = render_html(&grammar);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>t grammar</title>"));
+        assert!(html
+            .contains("<section id=\"program\">\n<h2><a href=\"#program\">program</a></h2>\n<svg"));
+        assert!(html.contains("<section id=\"word\">"));
+        assert_eq!(html.matches("<svg").count(), 2);
+        assert!(html.ends_with("</body>\n</html>\n"));
+    }
+}
@@ -0,0 +1,47 @@
+//! Hand-written lexing for tokens a grammar cannot describe.
+//!
+//! Some tokens depend on context that regular expressions can't see, such
+//! as Python's indentation or the delimiter of a heredoc. A grammar lists
+//! them under `externals`, and an [`ExternalScanner`] recognises them. It
+//! takes the place of the C scanner (`scanner.c`) a tree-sitter grammar
+//! ships with, and follows the same protocol:
+//!
+//! - Before lexing in a parse state where any external token is valid, the
+//!   parser calls [`scan`](ExternalScanner::scan) with the valid tokens. A
+//!   scanner that recognises one sets the lexer's
+//!   [`result_symbol`](Lexer::set_result_symbol) to its index in the
+//!   grammar's `externals` and returns `true`; otherwise the parser's own
+//!   lexer runs.
+//! - The token ends where the scanner last called
+//!   [`mark_end`](Lexer::mark_end), or at the lexer's position if it never
+//!   did. Characters passed over with [`skip`](Lexer::skip) come before
+//!   the token rather than in it.
+//! - After each token it recognises, the scanner's state is saved with
+//!   [`serialize`](ExternalScanner::serialize), and it is restored with
+//!   [`deserialize`](ExternalScanner::deserialize) before every call to
+//!   `scan`, so a failed attempt that changed the state leaves no trace.
+//!   The first restore is from an empty buffer, which should reset it.
+//!
+//! A generated module creates its scanner with [`Default`]; see
+//! [`build::Builder::external_scanner`](crate::build::Builder::external_scanner).
+
+use crate::lexer::Lexer;
+
+/// Recognises a grammar's external tokens.
+pub trait ExternalScanner {
+    /// Tries to recognise one of the tokens marked valid in `valid_symbols`
+    /// at the lexer's position, returning whether it did.
+    fn scan(&mut self, lexer: &mut Lexer<'_>, valid_symbols: &[bool]) -> bool;
+
+    /// Appends the scanner's state to `buffer`. Stateless scanners need not
+    /// implement this.
+    fn serialize(&self, buffer: &mut Vec<u8>) {
+        let _ = buffer;
+    }
+
+    /// Restores a state written by [`serialize`](Self::serialize), or the
+    /// initial state from an empty buffer.
+    fn deserialize(&mut self, buffer: &[u8]) {
+        let _ = buffer;
+    }
+}
@@ -0,0 +1,280 @@
+//! Size and complexity metrics of a grammar.
+//!
+//! [`GrammarStats`](crate::stats::GrammarStats) counts what a grammar
+//! declares, how deeply its rule trees nest and which rules are largest, so
+//! grammars can be compared at a glance. The counts that need parse tables
+//! are filled in separately by
+//! [`with_tables`](crate::stats::GrammarStats::with_tables), as building
+//! tables can fail or take a while on large grammars.
+
+use crate::grammar::prepare::prepare;
+use crate::grammar::{Grammar, GrammarError, Rule, RuleType, RuleValue};
+use crate::json::Value;
+use crate::tables::Tables;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+/// How many of the largest rules are listed.
+const LARGEST_RULES: usize = 5;
+
+/// Metrics of one grammar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrammarStats {
+    /// The grammar's name.
+    pub name: String,
+    /// The number of rules, hidden ones included.
+    pub rules: usize,
+    /// The number of rules whose names start with `_`.
+    pub hidden_rules: usize,
+    /// The number of distinct tokens the lexer recognizes, counting each
+    /// string and pattern once wherever it is used.
+    pub tokens: usize,
+    /// The number of distinct field names.
+    pub fields: usize,
+    /// The number of distinct aliases, named and anonymous counted apart.
+    pub aliases: usize,
+    /// The number of declared conflict groups.
+    pub conflicts: usize,
+    /// The number of external tokens.
+    pub externals: usize,
+    /// The depth of the most deeply nested rule tree, where a rule that
+    /// is a single string, pattern or symbol has depth 1.
+    pub max_depth: usize,
+    /// The largest rules by number of rule tree nodes, largest first.
+    pub largest_rules: Vec<(String, usize)>,
+    /// The number of LR parse states, once tables are built.
+    pub parse_states: Option<usize>,
+    /// The number of lex states, once tables are built.
+    pub lex_states: Option<usize>,
+}
+
+impl GrammarStats {
+    /// Computes the metrics of `grammar` that need no parse tables.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`GrammarError`] if the grammar references undefined
+    /// symbols or is otherwise too malformed to count its tokens.
+    pub fn new(grammar: &Grammar) -> Result<Self, GrammarError> {
+        let prepared = prepare(grammar)?;
+        let mut fields = BTreeSet::new();
+        let mut aliases = BTreeSet::new();
+        let mut max_depth = 0;
+        let mut sizes = Vec::new();
+        for (name, rule) in &grammar.rules {
+            collect_names(rule, &mut fields, &mut aliases);
+            max_depth = max_depth.max(depth(rule));
+            sizes.push((name.clone(), size(rule)));
+        }
+        // A stable sort keeps rules of the same size in declaration order.
+        sizes.sort_by_key(|&(_, size)| std::cmp::Reverse(size));
+        sizes.truncate(LARGEST_RULES);
+
+        Ok(Self {
+            name: grammar.name.clone(),
+            rules: grammar.rules.len(),
+            hidden_rules: grammar
+                .rules
+                .keys()
+                .filter(|name| name.starts_with('_'))
+                .count(),
+            tokens: prepared.lexical.variables.len(),
+            fields: fields.len(),
+            aliases: aliases.len(),
+            conflicts: grammar.conflicts.as_ref().map_or(0, Vec::len),
+            externals: grammar.externals.as_ref().map_or(0, Vec::len),
+            max_depth,
+            largest_rules: sizes,
+            parse_states: None,
+            lex_states: None,
+        })
+    }
+
+    /// Fills in the state counts of the grammar's parse tables.
+    #[must_use]
+    pub fn with_tables(mut self, tables: &Tables) -> Self {
+        self.parse_states = Some(tables.state_count());
+        self.lex_states = Some(tables.lex_state_count());
+        self
+    }
+
+    /// Renders the metrics as aligned text, a metric per line:
+    ///
+    /// ```text
+    /// calc
+    ///   rules          11 (3 hidden)
+    ///   tokens         13
+    ///   fields         8
+    ///   aliases        0
+    ///   conflicts      0
+    ///   externals      0
+    ///   max depth      8
+    ///   parse states   46
+    ///   lex states     27
+    ///   largest rules  call (18), let (14), binary (11), _expression (4), _statement (3)
+    /// ```
+    ///
+    /// State counts show as `-` when tables were not built.
+    #[must_use]
+    pub fn to_human(&self) -> String {
+        let count = |n: Option<usize>| n.map_or_else(|| "-".to_string(), |n| n.to_string());
+        let largest = self
+            .largest_rules
+            .iter()
+            .map(|(name, size)| format!("{name} ({size})"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut out = format!("{}\n", self.name);
+        for (metric, value) in [
+            (
+                "rules",
+                format!("{} ({} hidden)", self.rules, self.hidden_rules),
+            ),
+            ("tokens", self.tokens.to_string()),
+            ("fields", self.fields.to_string()),
+            ("aliases", self.aliases.to_string()),
+            ("conflicts", self.conflicts.to_string()),
+            ("externals", self.externals.to_string()),
+            ("max depth", self.max_depth.to_string()),
+            ("parse states", count(self.parse_states)),
+            ("lex states", count(self.lex_states)),
+            ("largest rules", largest),
+        ] {
+            let _ = writeln!(out, "  {metric:<15}{value}");
+        }
+        out
+    }
+
+    /// Renders the metrics as compact JSON on one line, with state counts
+    /// that were not computed as `null` and the largest rules as an array
+    /// of `name` and `size` objects.
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        let count = |n: Option<usize>| n.map_or(Value::Null, integer);
+        let largest = self
+            .largest_rules
+            .iter()
+            .map(|(name, size)| {
+                Value::Object(vec![
+                    ("name".to_string(), Value::from(name.as_str())),
+                    ("size".to_string(), integer(*size)),
+                ])
+            })
+            .collect();
+        Value::Object(vec![
+            ("name".to_string(), Value::from(self.name.as_str())),
+            ("rules".to_string(), integer(self.rules)),
+            ("hidden_rules".to_string(), integer(self.hidden_rules)),
+            ("tokens".to_string(), integer(self.tokens)),
+            ("fields".to_string(), integer(self.fields)),
+            ("aliases".to_string(), integer(self.aliases)),
+            ("conflicts".to_string(), integer(self.conflicts)),
+            ("externals".to_string(), integer(self.externals)),
+            ("max_depth".to_string(), integer(self.max_depth)),
+            ("parse_states".to_string(), count(self.parse_states)),
+            ("lex_states".to_string(), count(self.lex_states)),
+            ("largest_rules".to_string(), Value::Array(largest)),
+        ])
+        .to_string()
+    }
+}
+
+fn integer(n: usize) -> Value {
+    Value::Integer(i64::try_from(n).unwrap_or(i64::MAX))
+}
+
+/// The subrules of a rule: its members, or its content.
+fn subrules(rule: &Rule) -> impl Iterator<Item = &Rule> {
+    rule.members.iter().chain(rule.content.as_deref())
+}
+
+fn depth(rule: &Rule) -> usize {
+    1 + subrules(rule).map(depth).max().unwrap_or_default()
+}
+
+fn size(rule: &Rule) -> usize {
+    1 + subrules(rule).map(size).sum::<usize>()
+}
+
+fn collect_names(
+    rule: &Rule,
+    fields: &mut BTreeSet<String>,
+    aliases: &mut BTreeSet<(String, bool)>,
+) {
+    match rule.rule_type {
+        RuleType::Field => {
+            if let Some(name) = &rule.name {
+                fields.insert(name.clone());
+            }
+        }
+        RuleType::Alias => {
+            if let Some(RuleValue::String(value)) = &rule.value {
+                aliases.insert((value.clone(), rule.named.unwrap_or_default()));
+            }
+        }
+        _ => {}
+    }
+    for subrule in subrules(rule) {
+        collect_names(subrule, fields, aliases);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_grammar;
+
+    const GRAMMAR: &str = r#"{
+        "name": "sums",
+        "conflicts": [["sum", "_expression"]],
+        "rules": {
+            "program": {"type": "REPEAT", "content": {"type": "SYMBOL", "name": "_expression"}},
+            "_expression": {"type": "CHOICE", "members": [
+                {"type": "SYMBOL", "name": "number"},
+                {"type": "SYMBOL", "name": "sum"}
+            ]},
+            "sum": {"type": "PREC_LEFT", "value": 1, "content": {"type": "SEQ", "members": [
+                {"type": "FIELD", "name": "left", "content": {"type": "SYMBOL", "name": "_expression"}},
+                {"type": "ALIAS", "named": false, "value": "plus", "content": {"type": "STRING", "value": "+"}},
+                {"type": "FIELD", "name": "right", "content": {"type": "SYMBOL", "name": "_expression"}}
+            ]}},
+            "number": {"type": "PATTERN", "value": "\\d+"}
+        }
+    }"#;
+
+    #[test]
+    fn test_grammar_stats() {
+        let stats = GrammarStats::new(&parse_grammar(GRAMMAR).unwrap()).unwrap();
+        assert_eq!((stats.rules, stats.hidden_rules, stats.tokens), (4, 1, 2));
+        assert_eq!((stats.fields, stats.aliases), (2, 1));
+        assert_eq!((stats.conflicts, stats.externals), (1, 0));
+        assert_eq!(stats.max_depth, 4);
+        assert_eq!(
+            stats.largest_rules,
+            [
+                ("sum".to_string(), 8),
+                ("_expression".to_string(), 3),
+                ("program".to_string(), 2),
+                ("number".to_string(), 1),
+            ]
+        );
+        assert_eq!(stats.parse_states, None);
+    }
+
+    #[test]
+    fn test_rendering() {
+        let stats = GrammarStats::new(&parse_grammar(GRAMMAR).unwrap()).unwrap();
+        let human = stats.to_human();
+        assert!(human.starts_with("sums\n  rules          4 (1 hidden)\n"));
+        assert!(human.contains("  parse states   -\n"));
+        assert!(
+            human.ends_with("  largest rules  sum (8), _expression (3), program (2), number (1)\n")
+        );
+        let json = stats
+            .with_tables(&crate::tables::build(&parse_grammar(GRAMMAR).unwrap()).unwrap())
+            .to_json();
+        assert!(json.starts_with(r#"{"name":"sums","rules":4,"hidden_rules":1,"tokens":2,"#));
+        assert!(json.ends_with(r#""largest_rules":[{"name":"sum","size":8},{"name":"_expression","size":3},{"name":"program","size":2},{"name":"number","size":1}]}"#));
+        assert!(!json.contains("null"));
+    }
+}
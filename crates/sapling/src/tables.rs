@@ -0,0 +1,1013 @@
+//! Parse and lex table construction.
+//!
+//! [`build`] turns a grammar into the tables a [`Language`] is made of:
+//! LALR(1) parse states from the prepared grammar's productions, and a
+//! lexer DFA with one start state per distinct set of tokens the parse
+//! states expect. External tokens are numbered after the grammar's own
+//! tokens, and each parse state also records which of them the scanner may
+//! return. The tables are kept in the same flat layout `Language`
+//! uses, so that [`emit::rust`](crate::emit::rust) can write them out as
+//! constants.
+//!
+//! Conflicts are resolved as tree-sitter resolves them: by integer and
+//! named precedence, then associativity, and otherwise only where the
+//! grammar's `conflicts` expect them. Rules listed in `inline` are spliced
+//! into the productions that use them while the states are built, unless
+//! they use themselves. Extras that are rules get states of their own,
+//! entered from any state on their first token. A grammar with a `word`
+//! token also gets a keyword DFA: string tokens the word matches are lexed
+//! as the word, then told apart again.
+//!
+//! [`Language`]: crate::language::Language
+
+pub(crate) mod lex;
+pub(crate) mod nfa;
+mod parse;
+
+use crate::grammar::prepare::{
+    prepare, Alias, Associativity, Precedence, PreparedGrammar, ProductionStep, Symbol, SymbolKind,
+    VariableType,
+};
+use crate::grammar::{self, Grammar, GrammarError, Rule, RuleType};
+use crate::language::{
+    FieldMapEntry, FieldMapSlice, Language, LexMode, LexTable, Lexing, ParseAction,
+    ParseActionEntry, ParseTableEntry, StateId, SymbolId, SymbolMetadata,
+};
+use lex::{LexState, LexTableBuilder, TokenInfo};
+use nfa::Nfa;
+use parse::{Action, ParseState};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+
+/// A parse conflict that precedence does not resolve and the grammar does
+/// not declare.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    /// The shortest sequence of symbols leading to the conflict, followed by
+    /// `•`, the lookahead token and `…`.
+    pub symbols: Vec<String>,
+    /// The lookahead token the conflict arises on.
+    pub lookahead: String,
+    /// Each way the symbols could be parsed.
+    pub interpretations: Vec<String>,
+    /// The rules involved, which is the `conflicts` entry that would allow
+    /// the conflict.
+    pub rules: Vec<String>,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "unresolved conflict for symbol sequence:\n")?;
+        writeln!(f, "  {}\n", self.symbols.join("  "))?;
+        writeln!(f, "possible interpretations:\n")?;
+        for (i, interpretation) in self.interpretations.iter().enumerate() {
+            writeln!(f, "  {}:  {interpretation}", i + 1)?;
+        }
+        write!(
+            f,
+            "\nadd a precedence or associativity to one of the rules, or declare the conflict [{}]",
+            self.rules.join(", ")
+        )
+    }
+}
+
+/// Why tables could not be built for a grammar.
+#[derive(Debug)]
+pub enum TableError {
+    /// The grammar itself is invalid.
+    Grammar(GrammarError),
+    /// The grammar is ambiguous where it does not say it may be.
+    Conflict(Conflict),
+    /// A token's pattern cannot be compiled.
+    Pattern {
+        /// The token, or an empty string for a separator.
+        token: String,
+        /// What is wrong with it.
+        message: String,
+    },
+    /// The grammar uses a feature the table builder does not support yet.
+    Unsupported(String),
+}
+
+impl fmt::Display for TableError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TableError::Grammar(e) => write!(f, "{e}"),
+            TableError::Conflict(conflict) => write!(f, "{conflict}"),
+            TableError::Pattern { token, message } if token.is_empty() => {
+                write!(f, "extra: {message}")
+            }
+            TableError::Pattern { token, message } => write!(f, "token '{token}': {message}"),
+            TableError::Unsupported(feature) => write!(f, "unsupported: {feature}"),
+        }
+    }
+}
+
+impl std::error::Error for TableError {}
+
+impl From<GrammarError> for TableError {
+    fn from(e: GrammarError) -> Self {
+        TableError::Grammar(e)
+    }
+}
+
+/// The parse and lex tables for a grammar, laid out as in
+/// [`Language`].
+#[derive(Debug, Clone)]
+pub struct Tables {
+    pub(crate) name: String,
+    pub(crate) symbol_names: Vec<String>,
+    pub(crate) symbol_metadata: Vec<SymbolMetadata>,
+    pub(crate) token_count: usize,
+    pub(crate) field_names: Vec<String>,
+    pub(crate) parse_states: Vec<u32>,
+    pub(crate) parse_table: Vec<ParseTableEntry>,
+    pub(crate) parse_actions: Vec<ParseActionEntry>,
+    pub(crate) alias_sequences: Vec<SymbolId>,
+    pub(crate) max_alias_sequence_length: usize,
+    pub(crate) field_map_slices: Vec<FieldMapSlice>,
+    pub(crate) field_map_entries: Vec<FieldMapEntry>,
+    pub(crate) lex_modes: Vec<LexMode>,
+    pub(crate) lex_states: Vec<LexState>,
+    /// The word token, which keywords are lexed as.
+    pub(crate) keyword_capture_token: Option<SymbolId>,
+    /// The DFA telling keywords apart from other words.
+    pub(crate) keyword_states: Vec<LexState>,
+    pub(crate) external_symbols: Vec<SymbolId>,
+    pub(crate) external_scanner_states: Vec<bool>,
+}
+
+impl Tables {
+    /// The number of parse states.
+    #[must_use]
+    pub fn state_count(&self) -> usize {
+        self.lex_modes.len()
+    }
+
+    /// The number of lex states.
+    #[must_use]
+    pub fn lex_state_count(&self) -> usize {
+        self.lex_states.len()
+    }
+
+    /// The number of symbols, including the end of input and aliases.
+    #[must_use]
+    pub fn symbol_count(&self) -> usize {
+        self.symbol_names.len()
+    }
+
+    /// Turns the tables into a [`Language`] that lexes by walking the lex
+    /// table, for parsing with a grammar loaded at runtime. External tokens
+    /// are never produced, as there is no scanner for them.
+    #[must_use]
+    pub fn into_language(self) -> Language {
+        let owned = |names: Vec<String>| names.into_iter().map(Cow::Owned).collect::<Vec<_>>();
+        Language {
+            name: Cow::Owned(self.name),
+            symbol_names: Cow::Owned(owned(self.symbol_names)),
+            symbol_metadata: Cow::Owned(self.symbol_metadata),
+            token_count: self.token_count,
+            field_names: Cow::Owned(owned(self.field_names)),
+            parse_states: Cow::Owned(self.parse_states),
+            parse_table: Cow::Owned(self.parse_table),
+            parse_actions: Cow::Owned(self.parse_actions),
+            alias_sequences: Cow::Owned(self.alias_sequences),
+            max_alias_sequence_length: self.max_alias_sequence_length,
+            field_map_slices: Cow::Owned(self.field_map_slices),
+            field_map_entries: Cow::Owned(self.field_map_entries),
+            lex_modes: Cow::Owned(self.lex_modes),
+            lexing: Lexing::Table(LexTable {
+                states: self.lex_states,
+                keyword_capture_token: self.keyword_capture_token,
+                keyword_states: self.keyword_states,
+            }),
+            external_symbols: Cow::Owned(self.external_symbols),
+            external_scanner_states: Cow::Owned(self.external_scanner_states),
+            external_scanner: None,
+        }
+    }
+}
+
+/// One symbol of a production, numbered as in the tables.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct LrStep {
+    pub(crate) symbol: usize,
+    pub(crate) alias: Option<Alias>,
+    pub(crate) field_name: Option<String>,
+    pub(crate) precedence: Precedence,
+    pub(crate) associativity: Option<Associativity>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct LrProduction {
+    pub(crate) lhs: usize,
+    pub(crate) steps: Vec<LrStep>,
+}
+
+/// An entry of one of the grammar's `precedences` lists.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum PrecedenceEntry {
+    Name(String),
+    /// A rule, which ranks the productions of that rule.
+    Symbol(usize),
+}
+
+/// The productions the parse table is built from.
+///
+/// Symbols are numbered as in the tables: the end of input, tokens, then
+/// rules, and one more for the augmented start rule.
+#[derive(Debug, Clone)]
+pub(crate) struct LrGrammar {
+    /// The productions of the rules, then those made by inlining.
+    pub(crate) productions: Vec<LrProduction>,
+    /// The productions of each symbol, empty for tokens. Those of inlined
+    /// rules only say what the rules start with.
+    pub(crate) productions_by_lhs: Vec<Vec<usize>>,
+    /// For each production and step that is an inlined rule, the
+    /// productions with the rule spliced in there.
+    pub(crate) inlined: HashMap<(usize, usize), Vec<usize>>,
+    pub(crate) token_count: usize,
+    /// The production `start' -> start`.
+    pub(crate) start_production: usize,
+    pub(crate) extra_tokens: Vec<usize>,
+    /// The extras that are rules.
+    pub(crate) extra_rules: Vec<usize>,
+    /// Whether each symbol is an auxiliary rule.
+    pub(crate) auxiliary: Vec<bool>,
+    /// The rule each symbol counts as in `conflicts`: repetitions count as
+    /// the rule they were extracted from.
+    pub(crate) conflict_owner: Vec<usize>,
+    pub(crate) expected_conflicts: Vec<BTreeSet<usize>>,
+    pub(crate) precedences: Vec<Vec<PrecedenceEntry>>,
+    pub(crate) names: Vec<String>,
+    /// Names for conflict messages, with anonymous tokens quoted.
+    pub(crate) display_names: Vec<String>,
+}
+
+/// Builds the parse and lex tables for `grammar`.
+///
+/// # Errors
+///
+/// Returns [`TableError::Grammar`] if the grammar is invalid,
+/// [`TableError::Conflict`] for the first conflict that neither precedence
+/// nor the grammar's `conflicts` resolve, [`TableError::Pattern`] for a
+/// pattern that can't be compiled, and [`TableError::Unsupported`] for
+/// extra rules that don't start with a token or end ambiguously, and for
+/// tables too large for 16-bit state ids.
+pub fn build(grammar: &Grammar) -> Result<Tables, TableError> {
+    let prepared = prepare(grammar)?;
+    let lr = lr_grammar(grammar, &prepared);
+    let states = parse::build(&lr)?;
+    let mut tables = Assembler::new(grammar, &prepared, &lr, &states).assemble(&states)?;
+    add_lexer(grammar, &prepared, &states, &mut tables)?;
+    Ok(tables)
+}
+
+/// Builds the lex tables, with a lex mode for each parse state.
+fn add_lexer(
+    grammar: &Grammar,
+    prepared: &PreparedGrammar,
+    states: &[ParseState],
+    tables: &mut Tables,
+) -> Result<(), TableError> {
+    let tokens: Vec<&Rule> = prepared
+        .lexical
+        .variables
+        .iter()
+        .map(|variable| &variable.rule)
+        .collect();
+    let pattern_error = |(token, message): (Option<usize>, String)| TableError::Pattern {
+        token: token.map_or_else(String::new, |i| prepared.lexical.variables[i].name.clone()),
+        message,
+    };
+    let nfa = Nfa::build(&tokens, &prepared.lexical.separators).map_err(pattern_error)?;
+    let infos: Vec<TokenInfo> = tokens.iter().map(|rule| token_info(rule)).collect();
+    let keywords = keywords(grammar, prepared, &nfa, &infos);
+    let mut lexer = LexTableBuilder::new(&nfa, &infos);
+    let lexical_count = prepared.lexical.variables.len();
+    let external_count = prepared.syntax.external_tokens.len();
+    let mut modes: HashMap<Vec<usize>, usize> = HashMap::new();
+    let mut external_states: HashMap<Vec<bool>, usize> = HashMap::new();
+    external_states.insert(vec![false; external_count], 0);
+    let mut lex_modes = Vec::with_capacity(states.len());
+    for state in states {
+        if state.end_of_extra {
+            // The extra is reduced without reading a token.
+            lex_modes.push(LexMode::new(StateId::MAX, 0));
+            continue;
+        }
+        // Token symbols are numbered from 1, after the end of input, and
+        // external tokens follow the lexical ones.
+        let mut valid = Vec::new();
+        let mut valid_external = vec![false; external_count];
+        for &symbol in state.actions.keys().filter(|&&symbol| symbol != 0) {
+            if symbol <= lexical_count {
+                valid.push(symbol - 1);
+            } else {
+                valid_external[symbol - lexical_count - 1] = true;
+            }
+        }
+        // Keywords are lexed as the word token, and told apart from it by
+        // the keyword lexer.
+        if let Some((word, keywords)) = &keywords {
+            let count = valid.len();
+            valid.retain(|token| !keywords.contains(token));
+            if valid.len() < count && !valid.contains(word) {
+                valid.push(*word);
+                valid.sort_unstable();
+            }
+        }
+        let start = if let Some(&start) = modes.get(&valid) {
+            start
+        } else {
+            let start = lexer.add_mode(&valid);
+            modes.insert(valid, start);
+            start
+        };
+        let next = external_states.len();
+        let external = *external_states.entry(valid_external).or_insert(next);
+        let start = to_u16(start, "lex states")?;
+        if start == StateId::MAX {
+            return Err(TableError::Unsupported(format!(
+                "{} or more lex states",
+                StateId::MAX
+            )));
+        }
+        lex_modes.push(LexMode::new(
+            start,
+            to_u16(external, "external lex states")?,
+        ));
+    }
+    let mut external_scanner_states = vec![Vec::new(); external_states.len()];
+    for (valid, index) in external_states {
+        external_scanner_states[index] = valid;
+    }
+    tables.lex_states = lexer.states;
+    tables.lex_modes = lex_modes;
+    if let Some((word, keywords)) = keywords {
+        // The keywords are matched on their own, without separators.
+        let nfa = Nfa::build(&tokens, &[]).map_err(pattern_error)?;
+        let mut lexer = LexTableBuilder::new(&nfa, &infos);
+        lexer.add_mode(&keywords);
+        tables.keyword_capture_token = Some(to_u16(word + 1, "symbols")?);
+        tables.keyword_states = lexer.states;
+    }
+    tables.external_symbols = (0..external_count)
+        .map(|index| to_u16(lexical_count + 1 + index, "symbols"))
+        .collect::<Result<_, _>>()?;
+    tables.external_scanner_states = external_scanner_states.concat();
+    Ok(())
+}
+
+/// The grammar's `word` token and its keywords: the string tokens it
+/// matches in full, as tree-sitter extracts them.
+fn keywords(
+    grammar: &Grammar,
+    prepared: &PreparedGrammar,
+    nfa: &Nfa,
+    infos: &[TokenInfo],
+) -> Option<(usize, Vec<usize>)> {
+    let word = grammar.word.as_deref()?;
+    let variables = &prepared.lexical.variables;
+    let word = variables
+        .iter()
+        .position(|variable| variable.name == word)?;
+    let keywords: Vec<usize> = variables
+        .iter()
+        .enumerate()
+        .filter(|&(token, variable)| {
+            token != word
+                && !infos[token].immediate
+                && literal(&variable.rule).is_some_and(|text| nfa.matches(word, text))
+        })
+        .map(|(token, _)| token)
+        .collect();
+    (!keywords.is_empty()).then_some((word, keywords))
+}
+
+/// The text of a token that is a plain string, seen through `token` and
+/// precedence.
+fn literal(rule: &Rule) -> Option<&str> {
+    let mut rule = rule;
+    while matches!(
+        rule.rule_type,
+        RuleType::Token
+            | RuleType::ImmediateToken
+            | RuleType::Prec
+            | RuleType::PrecLeft
+            | RuleType::PrecRight
+            | RuleType::PrecDynamic
+    ) {
+        rule = rule.content.as_deref()?;
+    }
+    rule.string_value()
+}
+
+fn to_u16(value: usize, what: &str) -> Result<u16, TableError> {
+    u16::try_from(value)
+        .map_err(|_| TableError::Unsupported(format!("more than {} {what}", u16::MAX)))
+}
+
+/// How a token ranks against others, from its rule.
+fn token_info(rule: &Rule) -> TokenInfo {
+    let immediate = rule.rule_type == RuleType::ImmediateToken;
+    let mut inner = rule;
+    if matches!(rule.rule_type, RuleType::Token | RuleType::ImmediateToken) {
+        inner = rule.content.as_deref().unwrap_or(rule);
+    }
+    let precedence = match inner.rule_type {
+        RuleType::Prec | RuleType::PrecLeft | RuleType::PrecRight => {
+            inner.precedence().unwrap_or(0)
+        }
+        _ => 0,
+    };
+    while matches!(
+        inner.rule_type,
+        RuleType::Prec | RuleType::PrecLeft | RuleType::PrecRight | RuleType::PrecDynamic
+    ) {
+        inner = inner.content.as_deref().unwrap_or(inner);
+        if inner.content.is_none() {
+            break;
+        }
+    }
+    TokenInfo {
+        precedence,
+        is_string: inner.rule_type == RuleType::String,
+        immediate,
+    }
+}
+
+/// Numbers the prepared grammar's symbols as the tables do.
+fn table_symbol(prepared: &PreparedGrammar, symbol: Symbol) -> usize {
+    match symbol.kind {
+        SymbolKind::Terminal => symbol.index + 1,
+        SymbolKind::External => prepared.lexical.variables.len() + 1 + symbol.index,
+        SymbolKind::NonTerminal => {
+            prepared.lexical.variables.len()
+                + 1
+                + prepared.syntax.external_tokens.len()
+                + symbol.index
+        }
+    }
+}
+
+/// The productions of the syntax rules in table symbols, followed by the
+/// start production, and the indices of each symbol's productions.
+fn lr_productions(
+    prepared: &PreparedGrammar,
+    token_count: usize,
+) -> (Vec<LrProduction>, Vec<Vec<usize>>) {
+    let variables = &prepared.syntax.variables;
+    let start_symbol = token_count + variables.len();
+    let lr_step = |step: &ProductionStep| LrStep {
+        symbol: table_symbol(prepared, step.symbol),
+        alias: step.alias.clone(),
+        field_name: step.field_name.clone(),
+        precedence: step.precedence.clone(),
+        associativity: step.associativity,
+    };
+
+    let mut productions = Vec::new();
+    let mut productions_by_lhs = vec![Vec::new(); start_symbol + 1];
+    for (index, variable) in variables.iter().enumerate() {
+        let lhs = token_count + index;
+        for production in variable.list_productions(index) {
+            productions_by_lhs[lhs].push(productions.len());
+            productions.push(LrProduction {
+                lhs,
+                steps: production.steps.iter().map(lr_step).collect(),
+            });
+        }
+    }
+    productions_by_lhs[start_symbol].push(productions.len());
+    productions.push(LrProduction {
+        lhs: start_symbol,
+        steps: vec![LrStep {
+            symbol: token_count,
+            alias: None,
+            field_name: None,
+            precedence: Precedence::None,
+            associativity: None,
+        }],
+    });
+    (productions, productions_by_lhs)
+}
+
+fn lr_grammar(grammar: &Grammar, prepared: &PreparedGrammar) -> LrGrammar {
+    let token_count = prepared.lexical.variables.len() + 1 + prepared.syntax.external_tokens.len();
+    let variables = &prepared.syntax.variables;
+    let start_symbol = token_count + variables.len();
+    let (mut productions, productions_by_lhs) = lr_productions(prepared, token_count);
+    let start_production = productions.len() - 1;
+    let mut inlined_rules: Vec<usize> = prepared
+        .syntax
+        .variables_to_inline
+        .iter()
+        .map(|&symbol| table_symbol(prepared, symbol))
+        .collect();
+    // Splicing a rule into itself would never end, so recursive rules are
+    // used as they are.
+    let recursive: Vec<usize> = inlined_rules
+        .iter()
+        .copied()
+        .filter(|&rule| uses_itself(&productions, &productions_by_lhs, &inlined_rules, rule))
+        .collect();
+    inlined_rules.retain(|rule| !recursive.contains(rule));
+    let inlined = inline(&mut productions, &productions_by_lhs, &inlined_rules);
+
+    let symbol_by_name = |name: &str| {
+        variables
+            .iter()
+            .position(|variable| variable.name == name)
+            .map(|index| token_count + index)
+    };
+    let mut conflict_owner: Vec<usize> = (0..=start_symbol).collect();
+    let mut auxiliary = vec![false; start_symbol + 1];
+    for (index, variable) in variables.iter().enumerate() {
+        if variable.kind != VariableType::Auxiliary {
+            continue;
+        }
+        auxiliary[token_count + index] = true;
+        let owner = variable
+            .name
+            .rfind("_repeat")
+            .and_then(|end| symbol_by_name(&variable.name[..end]));
+        if let Some(owner) = owner {
+            conflict_owner[token_count + index] = owner;
+        }
+    }
+    let expected_conflicts = grammar
+        .conflicts
+        .iter()
+        .flatten()
+        .map(|names| {
+            names
+                .iter()
+                .filter_map(|name| symbol_by_name(name))
+                .collect()
+        })
+        .collect();
+    let precedences = grammar
+        .precedences
+        .iter()
+        .flatten()
+        .map(|list| {
+            list.iter()
+                .filter_map(|entry| match entry {
+                    grammar::Precedence::String(name) => Some(PrecedenceEntry::Name(name.clone())),
+                    grammar::Precedence::SymbolRule { name, .. } => {
+                        symbol_by_name(name).map(PrecedenceEntry::Symbol)
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    let (extra_rules, extra_tokens) = prepared
+        .syntax
+        .extra_symbols
+        .iter()
+        .map(|&symbol| table_symbol(prepared, symbol))
+        .partition(|&symbol| symbol >= token_count);
+    let (names, display_names) = lr_names(prepared);
+
+    LrGrammar {
+        productions,
+        productions_by_lhs,
+        inlined,
+        token_count,
+        start_production,
+        extra_tokens,
+        extra_rules,
+        auxiliary,
+        conflict_owner,
+        expected_conflicts,
+        precedences,
+        names,
+        display_names,
+    }
+}
+
+/// The name of each table symbol, and the name to use for it in conflict
+/// messages, where literal tokens are quoted. The start symbol has no name.
+fn lr_names(prepared: &PreparedGrammar) -> (Vec<String>, Vec<String>) {
+    let mut names = vec!["end".to_string()];
+    let mut display_names = vec!["end".to_string()];
+    let tokens = prepared
+        .lexical
+        .variables
+        .iter()
+        .map(|variable| (&variable.name, variable.kind))
+        .chain(
+            prepared
+                .syntax
+                .external_tokens
+                .iter()
+                .map(|token| (&token.name, token.kind)),
+        );
+    for (name, kind) in tokens {
+        names.push(name.clone());
+        display_names.push(if kind == VariableType::Anonymous {
+            format!("'{name}'")
+        } else {
+            name.clone()
+        });
+    }
+    for variable in &prepared.syntax.variables {
+        names.push(variable.name.clone());
+        display_names.push(variable.name.clone());
+    }
+    names.push(String::new());
+    display_names.push(String::new());
+    (names, display_names)
+}
+
+/// The metadata of the end symbol, the tokens, the external tokens and the
+/// rules.
+fn symbol_metadata(prepared: &PreparedGrammar) -> Vec<SymbolMetadata> {
+    let supertypes: Vec<usize> = prepared
+        .syntax
+        .supertype_symbols
+        .iter()
+        .map(|&symbol| table_symbol(prepared, symbol))
+        .collect();
+    let mut metadata = vec![SymbolMetadata {
+        visible: false,
+        named: true,
+        supertype: false,
+    }];
+    let kinds = prepared
+        .lexical
+        .variables
+        .iter()
+        .map(|variable| variable.kind)
+        .chain(
+            prepared
+                .syntax
+                .external_tokens
+                .iter()
+                .map(|token| token.kind),
+        )
+        .chain(
+            prepared
+                .syntax
+                .variables
+                .iter()
+                .map(|variable| variable.kind),
+        );
+    for (i, kind) in kinds.enumerate() {
+        metadata.push(SymbolMetadata {
+            visible: kind.is_visible(),
+            named: matches!(kind, VariableType::Named | VariableType::Hidden),
+            supertype: supertypes.contains(&(i + 1)),
+        });
+    }
+    metadata
+}
+
+/// Splices inlined rules into the productions using them, one step at a
+/// time, adding the productions this makes. Returns the productions each
+/// step that is an inlined rule stands for.
+///
+/// A step is only spliced once the ones before it are, so productions that
+/// use several inlined rules grow one step at a time rather than as every
+/// combination at once.
+fn inline(
+    productions: &mut Vec<LrProduction>,
+    productions_by_lhs: &[Vec<usize>],
+    inlined_rules: &[usize],
+) -> HashMap<(usize, usize), Vec<usize>> {
+    let mut ids: HashMap<LrProduction, usize> = productions
+        .iter()
+        .enumerate()
+        .map(|(id, production)| (production.clone(), id))
+        .collect();
+    let mut inlined = HashMap::new();
+    let mut seen = HashSet::new();
+    let mut pending: Vec<(usize, usize)> = (0..productions.len())
+        .filter(|&id| !inlined_rules.contains(&productions[id].lhs))
+        .map(|id| (id, 0))
+        .collect();
+    while let Some((id, position)) = pending.pop() {
+        if !seen.insert((id, position)) {
+            continue;
+        }
+        let Some(step) = productions[id].steps.get(position) else {
+            continue;
+        };
+        if !inlined_rules.contains(&step.symbol) {
+            pending.push((id, position + 1));
+            continue;
+        }
+        let mut spliced = vec![productions[id].clone()];
+        let mut i = 0;
+        while i < spliced.len() {
+            match spliced[i].steps.get(position) {
+                Some(step) if inlined_rules.contains(&step.symbol) => {
+                    let outer = spliced.remove(i);
+                    let inner = &productions_by_lhs[outer.steps[position].symbol];
+                    spliced.splice(
+                        i..i,
+                        inner
+                            .iter()
+                            .map(|&inner| splice(&outer, position, &productions[inner])),
+                    );
+                }
+                _ => i += 1,
+            }
+        }
+        let mut spliced_ids = Vec::with_capacity(spliced.len());
+        for production in spliced {
+            let next = productions.len();
+            let spliced_id = *ids.entry(production.clone()).or_insert(next);
+            if spliced_id == next {
+                productions.push(production);
+            }
+            if !spliced_ids.contains(&spliced_id) {
+                spliced_ids.push(spliced_id);
+                pending.push((spliced_id, position));
+            }
+        }
+        inlined.insert((id, position), spliced_ids);
+    }
+    inlined
+}
+
+/// Whether `rule` uses itself, directly or through other `inlined` rules.
+fn uses_itself(
+    productions: &[LrProduction],
+    productions_by_lhs: &[Vec<usize>],
+    inlined: &[usize],
+    rule: usize,
+) -> bool {
+    let mut seen = HashSet::new();
+    let mut pending = vec![rule];
+    while let Some(symbol) = pending.pop() {
+        for &production in &productions_by_lhs[symbol] {
+            for step in &productions[production].steps {
+                if step.symbol == rule {
+                    return true;
+                }
+                if inlined.contains(&step.symbol) && seen.insert(step.symbol) {
+                    pending.push(step.symbol);
+                }
+            }
+        }
+    }
+    false
+}
+
+/// `outer` with the step at `position` replaced by the steps of `inner`.
+/// An alias or field on the replaced step applies to every step put in
+/// its place, and the last of them takes its precedence if it has none.
+fn splice(outer: &LrProduction, position: usize, inner: &LrProduction) -> LrProduction {
+    let mut production = outer.clone();
+    let removed = production
+        .steps
+        .splice(position..=position, inner.steps.iter().cloned())
+        .next()
+        .expect("the step is in range");
+    let inserted = &mut production.steps[position..position + inner.steps.len()];
+    for step in inserted.iter_mut() {
+        if removed.alias.is_some() {
+            step.alias.clone_from(&removed.alias);
+        }
+        if removed.field_name.is_some() {
+            step.field_name.clone_from(&removed.field_name);
+        }
+    }
+    if let Some(last) = inserted.last_mut() {
+        if last.precedence == Precedence::None {
+            last.precedence = removed.precedence;
+        }
+        if last.associativity.is_none() {
+            last.associativity = removed.associativity;
+        }
+    }
+    production
+}
+
+/// Lays out the parse table, symbols, aliases and fields in the flat form
+/// [`Language`] uses.
+struct Assembler<'a> {
+    grammar: &'a Grammar,
+    lr: &'a LrGrammar,
+    symbol_names: Vec<String>,
+    symbol_metadata: Vec<SymbolMetadata>,
+    field_names: Vec<String>,
+    /// The production id of each production.
+    production_ids: Vec<u16>,
+    alias_sequences: Vec<Vec<SymbolId>>,
+    field_maps: Vec<Vec<FieldMapEntry>>,
+}
+
+impl<'a> Assembler<'a> {
+    fn new(
+        grammar: &'a Grammar,
+        prepared: &'a PreparedGrammar,
+        lr: &'a LrGrammar,
+        states: &[ParseState],
+    ) -> Self {
+        let mut symbol_names = lr.names[..lr.names.len() - 1].to_vec();
+        let mut symbol_metadata = symbol_metadata(prepared);
+        // Productions of inlined rules, and those only spliced further, are
+        // never reduced.
+        let mut reduced = vec![false; lr.productions.len()];
+        for action in states
+            .iter()
+            .flat_map(|state| state.actions.values().flatten())
+        {
+            if let Action::Reduce(production) = action {
+                reduced[*production] = true;
+            }
+        }
+        let field_names: Vec<String> = lr
+            .productions
+            .iter()
+            .zip(&reduced)
+            .filter(|(_, &reduced)| reduced)
+            .flat_map(|(production, _)| &production.steps)
+            .filter_map(|step| step.field_name.clone())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+
+        let mut aliases: HashMap<Alias, SymbolId> = HashMap::new();
+        let mut ids: HashMap<(Vec<SymbolId>, Vec<FieldMapEntry>), u16> = HashMap::new();
+        let mut alias_sequences = vec![Vec::new()];
+        let mut field_maps = vec![Vec::new()];
+        ids.insert((Vec::new(), Vec::new()), 0);
+        let mut production_ids = Vec::with_capacity(lr.productions.len());
+        for (production, &reduced) in lr.productions.iter().zip(&reduced) {
+            if !reduced {
+                production_ids.push(0);
+                continue;
+            }
+            let mut sequence = Vec::new();
+            let mut fields = Vec::new();
+            for (i, step) in production.steps.iter().enumerate() {
+                let child_index = u16::try_from(i).unwrap_or(u16::MAX);
+                let alias = step.alias.as_ref().map_or(0, |alias| {
+                    *aliases.entry(alias.clone()).or_insert_with(|| {
+                        let existing = symbol_names.iter().zip(&symbol_metadata).position(
+                            |(name, metadata)| {
+                                *name == alias.value
+                                    && metadata.visible
+                                    && metadata.named == alias.named
+                            },
+                        );
+                        let symbol = existing.unwrap_or_else(|| {
+                            symbol_names.push(alias.value.clone());
+                            symbol_metadata.push(SymbolMetadata {
+                                visible: true,
+                                named: alias.named,
+                                supertype: false,
+                            });
+                            symbol_names.len() - 1
+                        });
+                        SymbolId::try_from(symbol).unwrap_or(SymbolId::MAX)
+                    })
+                });
+                sequence.push(alias);
+                if let Some(name) = &step.field_name {
+                    let field = field_names
+                        .binary_search(name)
+                        .expect("field names are collected");
+                    fields.push(FieldMapEntry::new(
+                        u16::try_from(field).unwrap_or(u16::MAX),
+                        child_index,
+                    ));
+                }
+            }
+            while sequence.last() == Some(&0) {
+                sequence.pop();
+            }
+            let next = u16::try_from(alias_sequences.len()).unwrap_or(u16::MAX);
+            let id = *ids
+                .entry((sequence.clone(), fields.clone()))
+                .or_insert(next);
+            if id == next {
+                alias_sequences.push(sequence);
+                field_maps.push(fields);
+            }
+            production_ids.push(id);
+        }
+
+        Self {
+            grammar,
+            lr,
+            symbol_names,
+            symbol_metadata,
+            field_names,
+            production_ids,
+            alias_sequences,
+            field_maps,
+        }
+    }
+
+    fn assemble(self, states: &[ParseState]) -> Result<Tables, TableError> {
+        to_u16(states.len(), "parse states")?;
+        to_u16(self.symbol_names.len(), "symbols")?;
+        let mut parse_states = Vec::with_capacity(states.len() + 1);
+        let mut parse_table = Vec::new();
+        let mut parse_actions = vec![ParseActionEntry::Header { count: 0 }];
+        let mut action_lists: HashMap<Vec<ParseAction>, u16> = HashMap::new();
+        for (state, parse_state) in states.iter().enumerate() {
+            parse_states.push(to_u32(parse_table.len())?);
+            let mut row: BTreeMap<usize, u16> = BTreeMap::new();
+            for (&token, actions) in &parse_state.actions {
+                let actions: Vec<ParseAction> = actions
+                    .iter()
+                    .map(|&action| self.action(action, state))
+                    .collect::<Result<_, _>>()?;
+                let index = if let Some(&index) = action_lists.get(&actions) {
+                    index
+                } else {
+                    let index = to_u16(parse_actions.len(), "parse actions")?;
+                    parse_actions.push(ParseActionEntry::Header {
+                        count: to_u16(actions.len(), "actions per entry")?,
+                    });
+                    parse_actions.extend(actions.iter().copied().map(ParseActionEntry::Action));
+                    action_lists.insert(actions, index);
+                    index
+                };
+                row.insert(token, index);
+            }
+            for (&symbol, &target) in &parse_state.gotos {
+                row.insert(symbol, to_u16(target, "parse states")?);
+            }
+            for (symbol, value) in row {
+                parse_table.push(ParseTableEntry::new(to_u16(symbol, "symbols")?, value));
+            }
+        }
+        parse_states.push(to_u32(parse_table.len())?);
+
+        let max_alias_sequence_length =
+            self.alias_sequences.iter().map(Vec::len).max().unwrap_or(0);
+        let mut alias_sequences = Vec::new();
+        for sequence in &self.alias_sequences {
+            alias_sequences.extend(sequence);
+            alias_sequences.resize(
+                alias_sequences.len() + max_alias_sequence_length - sequence.len(),
+                0,
+            );
+        }
+        let mut field_map_slices = Vec::new();
+        let mut field_map_entries = Vec::new();
+        for fields in &self.field_maps {
+            field_map_slices.push(FieldMapSlice {
+                index: to_u16(field_map_entries.len(), "field map entries")?,
+                length: to_u16(fields.len(), "fields per production")?,
+            });
+            field_map_entries.extend(fields);
+        }
+
+        Ok(Tables {
+            name: self.grammar.name.clone(),
+            symbol_names: self.symbol_names,
+            symbol_metadata: self.symbol_metadata,
+            token_count: self.lr.token_count,
+            field_names: self.field_names,
+            parse_states,
+            parse_table,
+            parse_actions,
+            alias_sequences,
+            max_alias_sequence_length,
+            field_map_slices,
+            field_map_entries,
+            lex_modes: Vec::new(),
+            lex_states: Vec::new(),
+            keyword_capture_token: None,
+            keyword_states: Vec::new(),
+            external_symbols: Vec::new(),
+            external_scanner_states: Vec::new(),
+        })
+    }
+
+    fn action(&self, action: Action, state: usize) -> Result<ParseAction, TableError> {
+        Ok(match action {
+            Action::Shift(target) => ParseAction::Shift {
+                state: to_u16(target, "parse states")?,
+                extra: false,
+            },
+            Action::ShiftExtra => ParseAction::Shift {
+                state: to_u16(state, "parse states")?,
+                extra: true,
+            },
+            Action::Reduce(production) => {
+                let production_info = &self.lr.productions[production];
+                ParseAction::Reduce {
+                    symbol: to_u16(production_info.lhs, "symbols")?,
+                    child_count: to_u16(production_info.steps.len(), "children")?,
+                    production_id: self.production_ids[production],
+                }
+            }
+            Action::Accept => ParseAction::Accept,
+        })
+    }
+}
+
+fn to_u32(value: usize) -> Result<u32, TableError> {
+    u32::try_from(value)
+        .map_err(|_| TableError::Unsupported("parse table is too large".to_string()))
+}
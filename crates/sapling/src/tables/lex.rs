@@ -0,0 +1,242 @@
+//! Lex table construction.
+//!
+//! Tree-sitter lexes in context: each parse state only looks for the tokens
+//! it has actions for. Every distinct set of valid tokens becomes a lex
+//! mode, and [`LexTableBuilder`] turns the NFA restricted to those tokens
+//! into a DFA by subset construction. DFA states are keyed by their NFA
+//! states, so modes that reach the same situation share lex states.
+//!
+//! Separators are matched in front of every token, with transitions marked
+//! as skips so the lexer leaves them out of the token. A separator under
+//! way runs as far as it can before a token may start. When several tokens
+//! match, the longest wins, then the higher lexical precedence, then a
+//! string over a pattern, then the token declared first. A token with
+//! higher precedence also stops the DFA from continuing a lower one past
+//! the point where the higher one matched.
+
+use super::nfa::{CharSet, Nfa, NfaState, Owner};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+/// How a token ranks against others matching the same text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct TokenInfo {
+    pub(crate) precedence: i32,
+    pub(crate) is_string: bool,
+    /// Whether the token must start right after the previous one, with no
+    /// separator in between.
+    pub(crate) immediate: bool,
+}
+
+/// A state of the lexer's DFA.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct LexState {
+    /// The token matched on reaching this state.
+    pub(crate) accept: Option<usize>,
+    pub(crate) transitions: Vec<LexTransition>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LexTransition {
+    pub(crate) chars: CharSet,
+    pub(crate) state: usize,
+    /// Whether the character is part of a separator rather than a token.
+    pub(crate) skip: bool,
+}
+
+/// A set of NFA states, with the mode whose separator loop it continues in
+/// when it is partway through a separator.
+type Key = (Vec<usize>, Option<usize>);
+
+pub(crate) struct LexTableBuilder<'a> {
+    nfa: &'a Nfa,
+    tokens: &'a [TokenInfo],
+    pub(crate) states: Vec<LexState>,
+    keys: Vec<Key>,
+    ids: HashMap<Key, usize>,
+    expanded: Vec<bool>,
+    /// For each mode built, the NFA states to continue in after a separator.
+    after_separator: Vec<Vec<usize>>,
+}
+
+impl<'a> LexTableBuilder<'a> {
+    pub(crate) fn new(nfa: &'a Nfa, tokens: &'a [TokenInfo]) -> Self {
+        Self {
+            nfa,
+            tokens,
+            states: Vec::new(),
+            keys: Vec::new(),
+            ids: HashMap::new(),
+            expanded: Vec::new(),
+            after_separator: Vec::new(),
+        }
+    }
+
+    /// Builds the DFA recognizing `valid` tokens, preceded by separators.
+    /// Returns its start state.
+    pub(crate) fn add_mode(&mut self, valid: &[usize]) -> usize {
+        let mode = self.after_separator.len();
+        let starts = |immediate: bool| {
+            let mut starts: Vec<usize> = valid
+                .iter()
+                .filter(|&&token| immediate || !self.tokens[token].immediate)
+                .map(|&token| self.nfa.token_starts[token])
+                .collect();
+            starts.extend(self.nfa.separator_start);
+            starts
+        };
+        let (initial, after) = (starts(true), starts(false));
+        let after = self.closure(after, &[]);
+        self.after_separator.push(after);
+        let initial = self.closure(initial, &self.after_separator[mode]);
+        let start = self.intern(initial, mode);
+
+        let mut pending: VecDeque<usize> = VecDeque::from([start]);
+        while let Some(id) = pending.pop_front() {
+            if std::mem::replace(&mut self.expanded[id], true) {
+                continue;
+            }
+            let nfa_states = self.keys[id].0.clone();
+            self.states[id] = self.expand(&nfa_states, mode, &mut pending);
+        }
+        start
+    }
+
+    fn expand(
+        &mut self,
+        nfa_states: &[usize],
+        mode: usize,
+        pending: &mut VecDeque<usize>,
+    ) -> LexState {
+        let nfa = self.nfa;
+        let accept = nfa_states
+            .iter()
+            .filter_map(|&state| match nfa.states[state] {
+                NfaState::Accept(token) => Some(token),
+                _ => None,
+            })
+            .max_by_key(|&token| {
+                let info = self.tokens[token];
+                (info.precedence, info.is_string, std::cmp::Reverse(token))
+            });
+        let floor = accept.map(|token| self.tokens[token].precedence);
+        let advances: Vec<(&CharSet, usize, Owner, usize)> = nfa_states
+            .iter()
+            .filter_map(|&state| match &nfa.states[state] {
+                NfaState::Advance { chars, next } => Some((chars, *next, nfa.owners[state], state)),
+                _ => None,
+            })
+            .filter(|&(_, _, owner, _)| match (owner, floor) {
+                (Owner::Token(token), Some(floor)) => self.tokens[token].precedence >= floor,
+                _ => true,
+            })
+            .collect();
+
+        // Whether each advance continues a separator already under way,
+        // rather than starting a new one.
+        let fresh = &self.after_separator[mode];
+        let continues_separator: Vec<bool> = advances
+            .iter()
+            .map(|&(_, _, owner, state)| {
+                owner == Owner::Separator && fresh.binary_search(&state).is_err()
+            })
+            .collect();
+
+        // Split the characters into intervals on which the same NFA states
+        // advance.
+        let mut bounds: Vec<u32> = advances
+            .iter()
+            .flat_map(|(chars, ..)| {
+                chars
+                    .ranges
+                    .iter()
+                    .flat_map(|&(start, end)| [start, end + 1])
+            })
+            .collect();
+        bounds.sort_unstable();
+        bounds.dedup();
+        let mut targets: Vec<Vec<usize>> = vec![Vec::new(); bounds.len()];
+        for (i, (chars, ..)) in advances.iter().enumerate() {
+            for &(start, end) in &chars.ranges {
+                let first = bounds.partition_point(|&bound| bound < start);
+                for interval in first..bounds.len() {
+                    if bounds[interval] > end {
+                        break;
+                    }
+                    targets[interval].push(i);
+                }
+            }
+        }
+
+        let mut grouped: BTreeMap<(usize, bool), Vec<(u32, u32)>> = BTreeMap::new();
+        for (interval, advancing) in targets.iter().enumerate() {
+            if advancing.is_empty() {
+                continue;
+            }
+            let range = (bounds[interval], bounds[interval + 1] - 1);
+            // A character that continues a separator already under way is
+            // skipped, so separators match as much as they can. Otherwise a
+            // character that can start or continue a token is never skipped.
+            let skip = advancing.iter().any(|&i| continues_separator[i])
+                || advancing.iter().all(|&i| advances[i].2 == Owner::Separator);
+            let next: Vec<usize> = advancing
+                .iter()
+                .filter(|&&i| (advances[i].2 == Owner::Separator) == skip)
+                .map(|&i| advances[i].1)
+                .collect();
+            let next = self.closure(next, &self.after_separator[mode]);
+            let id = self.intern(next, mode);
+            pending.push_back(id);
+            grouped.entry((id, skip)).or_default().push(range);
+        }
+        let mut transitions: Vec<LexTransition> = grouped
+            .into_iter()
+            .map(|((state, skip), ranges)| LexTransition {
+                chars: CharSet::from_ranges(ranges),
+                state,
+                skip,
+            })
+            .collect();
+        transitions.sort_by_key(|transition| transition.chars.ranges[0].0);
+        LexState {
+            accept,
+            transitions,
+        }
+    }
+
+    /// The states reachable from `states` without consuming anything. A
+    /// finished separator continues in `after_separator`.
+    fn closure(&self, states: Vec<usize>, after_separator: &[usize]) -> Vec<usize> {
+        let mut seen = vec![false; self.nfa.states.len()];
+        let mut stack = states;
+        let mut result = Vec::new();
+        while let Some(state) = stack.pop() {
+            if std::mem::replace(&mut seen[state], true) {
+                continue;
+            }
+            match &self.nfa.states[state] {
+                NfaState::Split(targets) => stack.extend(targets),
+                NfaState::SeparatorEnd => result.extend(after_separator),
+                NfaState::Advance { .. } | NfaState::Accept(_) => result.push(state),
+            }
+        }
+        result.sort_unstable();
+        result.dedup();
+        result
+    }
+
+    fn intern(&mut self, nfa_states: Vec<usize>, mode: usize) -> usize {
+        let separating = nfa_states
+            .iter()
+            .any(|&state| self.nfa.owners[state] == Owner::Separator);
+        let key = (nfa_states, separating.then_some(mode));
+        if let Some(&id) = self.ids.get(&key) {
+            return id;
+        }
+        let id = self.states.len();
+        self.ids.insert(key.clone(), id);
+        self.keys.push(key);
+        self.states.push(LexState::default());
+        self.expanded.push(false);
+        id
+    }
+}
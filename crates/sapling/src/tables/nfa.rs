@@ -0,0 +1,368 @@
+//! Nondeterministic automata for the grammar's tokens.
+//!
+//! Each token's rule (strings, patterns, and the sequences, choices and
+//! repetitions inside `token()`) is compiled into one shared NFA over
+//! Unicode scalar values, built back to front: every fragment is given the
+//! state to continue in once it has matched. Patterns are parsed with
+//! `regex-syntax`, after rewriting the JavaScript classes `\d` and `\w` to
+//! their ASCII meaning.
+
+use crate::grammar::{Rule, RuleType, RuleValue};
+use regex_syntax::hir::{Class, Hir, HirKind};
+
+/// A set of characters as sorted, disjoint, inclusive ranges of scalar
+/// values.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct CharSet {
+    pub(crate) ranges: Vec<(u32, u32)>,
+}
+
+impl CharSet {
+    pub(crate) fn from_char(c: char) -> Self {
+        Self {
+            ranges: vec![(u32::from(c), u32::from(c))],
+        }
+    }
+
+    pub(crate) fn contains(&self, c: char) -> bool {
+        let c = u32::from(c);
+        self.ranges
+            .binary_search_by(|&(start, end)| {
+                if end < c {
+                    std::cmp::Ordering::Less
+                } else if start > c {
+                    std::cmp::Ordering::Greater
+                } else {
+                    std::cmp::Ordering::Equal
+                }
+            })
+            .is_ok()
+    }
+
+    pub(crate) fn from_ranges(ranges: impl IntoIterator<Item = (u32, u32)>) -> Self {
+        let mut ranges: Vec<(u32, u32)> = ranges.into_iter().collect();
+        ranges.sort_unstable();
+        let mut merged: Vec<(u32, u32)> = Vec::with_capacity(ranges.len());
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some(last) if start <= last.1.saturating_add(1) => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        Self { ranges: merged }
+    }
+}
+
+/// Who an NFA state belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) enum Owner {
+    Token(usize),
+    Separator,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum NfaState {
+    /// Consumes one character from `chars`.
+    Advance { chars: CharSet, next: usize },
+    /// Continues in any of several states without consuming anything.
+    Split(Vec<usize>),
+    /// The token has been matched.
+    Accept(usize),
+    /// A separator has been matched, so another separator or a token may
+    /// follow.
+    SeparatorEnd,
+}
+
+/// The NFA for every token, plus a separator loop.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Nfa {
+    pub(crate) states: Vec<NfaState>,
+    pub(crate) owners: Vec<Owner>,
+    /// The start state of each token.
+    pub(crate) token_starts: Vec<usize>,
+    /// The start state of the separators, if there are any.
+    pub(crate) separator_start: Option<usize>,
+}
+
+impl Nfa {
+    /// Builds the NFA for `tokens`, and for `separators`, the extras that
+    /// are skipped between tokens. Returns the token or separator whose
+    /// pattern is invalid, and why, on failure.
+    pub(crate) fn build(
+        tokens: &[&Rule],
+        separators: &[Rule],
+    ) -> Result<Self, (Option<usize>, String)> {
+        let mut nfa = Self::default();
+        for (index, rule) in tokens.iter().enumerate() {
+            let owner = Owner::Token(index);
+            let accept = nfa.add(owner, NfaState::Accept(index));
+            let start = nfa
+                .expand_rule(rule, accept, owner)
+                .map_err(|message| (Some(index), message))?;
+            nfa.token_starts.push(start);
+        }
+        if !separators.is_empty() {
+            let end = nfa.add(Owner::Separator, NfaState::SeparatorEnd);
+            let mut starts = Vec::new();
+            for rule in separators {
+                starts.push(
+                    nfa.expand_rule(rule, end, Owner::Separator)
+                        .map_err(|message| (None, message))?,
+                );
+            }
+            nfa.separator_start = Some(nfa.add(Owner::Separator, NfaState::Split(starts)));
+        }
+        Ok(nfa)
+    }
+
+    /// Whether `token` matches all of `text`.
+    pub(crate) fn matches(&self, token: usize, text: &str) -> bool {
+        let mut states = self.closure(vec![self.token_starts[token]]);
+        for c in text.chars() {
+            let next = states
+                .iter()
+                .filter_map(|&state| match &self.states[state] {
+                    NfaState::Advance { chars, next } if chars.contains(c) => Some(*next),
+                    _ => None,
+                })
+                .collect();
+            states = self.closure(next);
+        }
+        states.iter().any(
+            |&state| matches!(self.states[state], NfaState::Accept(accepted) if accepted == token),
+        )
+    }
+
+    /// The states reachable from `states` without consuming anything.
+    fn closure(&self, mut pending: Vec<usize>) -> Vec<usize> {
+        let mut seen = vec![false; self.states.len()];
+        let mut result = Vec::new();
+        while let Some(state) = pending.pop() {
+            if std::mem::replace(&mut seen[state], true) {
+                continue;
+            }
+            match &self.states[state] {
+                NfaState::Split(targets) => pending.extend(targets),
+                _ => result.push(state),
+            }
+        }
+        result
+    }
+
+    fn add(&mut self, owner: Owner, state: NfaState) -> usize {
+        self.states.push(state);
+        self.owners.push(owner);
+        self.states.len() - 1
+    }
+
+    fn advance(&mut self, owner: Owner, chars: CharSet, next: usize) -> usize {
+        self.add(owner, NfaState::Advance { chars, next })
+    }
+
+    /// Adds states matching `rule`, then continuing at `next`. Returns the
+    /// state to start in.
+    fn expand_rule(&mut self, rule: &Rule, next: usize, owner: Owner) -> Result<usize, String> {
+        match rule.rule_type {
+            RuleType::Blank => Ok(next),
+            RuleType::String => {
+                let value = rule.string_value().unwrap_or_default();
+                Ok(value.chars().rev().fold(next, |next, c| {
+                    self.advance(owner, CharSet::from_char(c), next)
+                }))
+            }
+            RuleType::Pattern => {
+                let value = match &rule.value {
+                    Some(RuleValue::String(value)) => value.as_str(),
+                    _ => "",
+                };
+                let flags = rule.flags.as_deref().unwrap_or_default();
+                let hir = regex_syntax::ParserBuilder::new()
+                    .case_insensitive(flags.contains('i'))
+                    .build()
+                    .parse(&javascript_classes(value))
+                    .map_err(|e| format!("invalid pattern /{value}/: {e}"))?;
+                Ok(self.expand_hir(&hir, next, owner))
+            }
+            RuleType::Seq => {
+                let mut next = next;
+                for member in rule.members.iter().rev() {
+                    next = self.expand_rule(member, next, owner)?;
+                }
+                Ok(next)
+            }
+            RuleType::Choice => {
+                let mut starts = Vec::with_capacity(rule.members.len());
+                for member in &rule.members {
+                    starts.push(self.expand_rule(member, next, owner)?);
+                }
+                Ok(self.add(owner, NfaState::Split(starts)))
+            }
+            RuleType::Repeat | RuleType::Repeat1 => {
+                let content = rule.content.as_deref().ok_or("repeat without content")?;
+                let fork = self.add(owner, NfaState::Split(vec![next]));
+                let start = self.expand_rule(content, fork, owner)?;
+                if let NfaState::Split(targets) = &mut self.states[fork] {
+                    targets.push(start);
+                }
+                if rule.rule_type == RuleType::Repeat {
+                    Ok(self.add(owner, NfaState::Split(vec![start, next])))
+                } else {
+                    Ok(start)
+                }
+            }
+            RuleType::Symbol => Err(format!(
+                "'{}' is a rule, not a token",
+                rule.name.as_deref().unwrap_or_default()
+            )),
+            _ => {
+                let content = rule
+                    .content
+                    .as_deref()
+                    .ok_or_else(|| format!("{} without content", rule.type_name()))?;
+                self.expand_rule(content, next, owner)
+            }
+        }
+    }
+
+    fn expand_hir(&mut self, hir: &Hir, next: usize, owner: Owner) -> usize {
+        match hir.kind() {
+            // Anchors and word boundaries can't be checked by a lexer that
+            // sees one character at a time, so they match anywhere.
+            HirKind::Empty | HirKind::Look(_) => next,
+            HirKind::Literal(literal) => String::from_utf8_lossy(&literal.0)
+                .chars()
+                .rev()
+                .fold(next, |next, c| {
+                    self.advance(owner, CharSet::from_char(c), next)
+                }),
+            HirKind::Class(class) => {
+                let chars = match class {
+                    Class::Unicode(class) => CharSet::from_ranges(
+                        class
+                            .ranges()
+                            .iter()
+                            .map(|r| (u32::from(r.start()), u32::from(r.end()))),
+                    ),
+                    Class::Bytes(class) => CharSet::from_ranges(
+                        class
+                            .ranges()
+                            .iter()
+                            .map(|r| (u32::from(r.start()), u32::from(r.end()))),
+                    ),
+                };
+                self.advance(owner, chars, next)
+            }
+            HirKind::Capture(capture) => self.expand_hir(&capture.sub, next, owner),
+            HirKind::Concat(members) => members
+                .iter()
+                .rev()
+                .fold(next, |next, member| self.expand_hir(member, next, owner)),
+            HirKind::Alternation(members) => {
+                let starts = members
+                    .iter()
+                    .map(|member| self.expand_hir(member, next, owner))
+                    .collect();
+                self.add(owner, NfaState::Split(starts))
+            }
+            HirKind::Repetition(repetition) => {
+                let sub = &repetition.sub;
+                let mut next = next;
+                match repetition.max {
+                    None => {
+                        let fork = self.add(owner, NfaState::Split(vec![next]));
+                        let start = self.expand_hir(sub, fork, owner);
+                        if let NfaState::Split(targets) = &mut self.states[fork] {
+                            targets.push(start);
+                        }
+                        next = self.add(owner, NfaState::Split(vec![start, next]));
+                    }
+                    Some(max) => {
+                        for _ in repetition.min..max {
+                            let start = self.expand_hir(sub, next, owner);
+                            next = self.add(owner, NfaState::Split(vec![start, next]));
+                        }
+                    }
+                }
+                for _ in 0..repetition.min {
+                    next = self.expand_hir(sub, next, owner);
+                }
+                next
+            }
+        }
+    }
+}
+
+/// Rewrites the pattern classes whose JavaScript meaning differs from
+/// Rust's: `\d` and `\w` are ASCII-only in JavaScript.
+pub(crate) fn javascript_classes(pattern: &str) -> String {
+    let mut result = String::with_capacity(pattern.len());
+    let mut in_class = false;
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                let Some(escaped) = chars.next() else {
+                    result.push(c);
+                    break;
+                };
+                let replacement = match (escaped, in_class) {
+                    ('d', false) => "[0-9]",
+                    ('d', true) => "0-9",
+                    ('w', false) => "[a-zA-Z0-9_]",
+                    ('w', true) => "a-zA-Z0-9_",
+                    ('D', false) => "[^0-9]",
+                    ('W', false) => "[^a-zA-Z0-9_]",
+                    _ => {
+                        result.push(c);
+                        result.push(escaped);
+                        continue;
+                    }
+                };
+                result.push_str(replacement);
+            }
+            '[' if !in_class => {
+                in_class = true;
+                result.push(c);
+                // A `]` straight after `[` or `[^` is a literal.
+                let rest = chars.as_str();
+                let negated = rest.starts_with('^');
+                if negated {
+                    result.push('^');
+                    chars.next();
+                }
+                if chars.as_str().starts_with(']') {
+                    result.push_str("\\]");
+                    chars.next();
+                }
+            }
+            ']' if in_class => {
+                in_class = false;
+                result.push(c);
+            }
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_javascript_classes() {
+        assert_eq!(javascript_classes(r"\d+"), "[0-9]+");
+        assert_eq!(
+            javascript_classes(r"[\w$]\w*"),
+            "[a-zA-Z0-9_$][a-zA-Z0-9_]*"
+        );
+        assert_eq!(javascript_classes(r"[^\]]\\d"), r"[^\]]\\d");
+        assert_eq!(javascript_classes(r"[]a]"), r"[\]a]");
+    }
+
+    #[test]
+    fn test_char_sets_merge_ranges() {
+        let set = CharSet::from_ranges([(5, 7), (1, 2), (3, 4), (10, 10)]);
+        assert_eq!(set.ranges, [(1, 7), (10, 10)]);
+    }
+}
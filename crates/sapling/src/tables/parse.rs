@@ -0,0 +1,791 @@
+//! LALR(1) parse table construction.
+//!
+//! The LR(0) automaton is built from item cores first, then lookaheads are
+//! attached by propagation, as in the dragon book: each kernel item's closure
+//! is computed once with a placeholder lookahead to find which lookaheads
+//! arise spontaneously and which flow from one kernel item to another, and
+//! the flows are followed until nothing changes.
+//!
+//! Rules listed in `inline` are never closed over. An item whose dot is
+//! before one stands for the items of the productions with the rule spliced
+//! in at that step, so only the steps the parser reaches are expanded.
+//!
+//! Conflicts are resolved the way tree-sitter resolves them. An item's
+//! precedence is that of the step before its dot. Reductions on the same
+//! lookahead are compared first, keeping those of the highest precedence.
+//! Against a shift, the precedences of the items the lookahead continues
+//! decide, then the reductions' associativity. Integer precedences compare
+//! by value, and names and rules by their order in the grammar's
+//! `precedences` lists. A conflict that remains is an error unless the
+//! grammar's `conflicts` list names exactly the rules involved, in which
+//! case every action is kept.
+//!
+//! Extras that are rules get states of their own, entered by shifting the
+//! token one of their productions starts with in any state. The state
+//! finishing an extra reduces it without a lookahead, and its goto leads
+//! back to the state it was entered from.
+
+use super::{Conflict, LrGrammar, PrecedenceEntry, TableError};
+use crate::grammar::prepare::{Associativity, Precedence};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+
+/// A production and how far into it the parser is.
+type Item = (usize, usize);
+
+/// The precedence of an item whose dot is at the start.
+static NO_PRECEDENCE: Precedence = Precedence::None;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Action {
+    Shift(usize),
+    /// Pushes an extra, such as a comment, without changing state.
+    ShiftExtra,
+    Reduce(usize),
+    Accept,
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ParseState {
+    /// The actions for each token.
+    pub(crate) actions: BTreeMap<usize, Vec<Action>>,
+    /// The state to go to after reducing each rule.
+    pub(crate) gotos: BTreeMap<usize, usize>,
+    /// Whether the state finishes an extra that is a rule, reducing it
+    /// on the end symbol without reading a token.
+    pub(crate) end_of_extra: bool,
+}
+
+/// The reductions on one lookahead that precedence hasn't ruled out.
+#[derive(Debug, Default)]
+struct Reductions {
+    productions: Vec<usize>,
+    precedence: Precedence,
+    /// The rules the productions reduce to.
+    symbols: Vec<usize>,
+    left: bool,
+    right: bool,
+    non_associative: bool,
+}
+
+/// A set of tokens, with one more bit for the placeholder lookahead used
+/// while finding how lookaheads propagate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TokenSet(Vec<u64>);
+
+impl TokenSet {
+    fn new(size: usize) -> Self {
+        Self(vec![0; size.div_ceil(64)])
+    }
+
+    fn insert(&mut self, token: usize) {
+        self.0[token / 64] |= 1 << (token % 64);
+    }
+
+    fn remove(&mut self, token: usize) {
+        self.0[token / 64] &= !(1 << (token % 64));
+    }
+
+    fn contains(&self, token: usize) -> bool {
+        self.0[token / 64] & (1 << (token % 64)) != 0
+    }
+
+    /// Adds the tokens of `other`, returning whether any were new.
+    fn union(&mut self, other: &TokenSet) -> bool {
+        let mut changed = false;
+        for (word, other) in self.0.iter_mut().zip(&other.0) {
+            let merged = *word | other;
+            changed |= merged != *word;
+            *word = merged;
+        }
+        changed
+    }
+
+    fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.0.iter().enumerate().flat_map(|(i, &word)| {
+            (0..64)
+                .filter(move |bit| word & (1 << bit) != 0)
+                .map(move |bit| i * 64 + bit)
+        })
+    }
+}
+
+/// An LR(1) closure being computed.
+#[derive(Default)]
+struct Closure {
+    items: Vec<(Item, TokenSet)>,
+    index: HashMap<Item, usize>,
+    /// The items whose lookaheads haven't been passed on yet.
+    pending: VecDeque<usize>,
+}
+
+impl Closure {
+    fn add(&mut self, item: Item, lookahead: &TokenSet) {
+        if let Some(&i) = self.index.get(&item) {
+            if self.items[i].1.union(lookahead) {
+                self.pending.push_back(i);
+            }
+        } else {
+            self.index.insert(item, self.items.len());
+            self.pending.push_back(self.items.len());
+            self.items.push((item, lookahead.clone()));
+        }
+    }
+}
+
+struct Builder<'g> {
+    grammar: &'g LrGrammar,
+    /// The placeholder lookahead.
+    placeholder: usize,
+    /// The tokens each symbol can start with.
+    first: Vec<TokenSet>,
+    /// FIRST and nullability of each production suffix.
+    suffixes: Vec<Vec<(TokenSet, bool)>>,
+    kernels: Vec<Vec<Item>>,
+    /// Whether each state is inside an extra that is a rule.
+    extra: Vec<bool>,
+    transitions: Vec<BTreeMap<usize, usize>>,
+    /// The state each extra rule starts in, by the token it starts with.
+    extra_starts: Vec<(usize, usize)>,
+}
+
+/// Builds the parse table for `grammar`, or returns the first conflict
+/// that precedence doesn't resolve.
+pub(crate) fn build(grammar: &LrGrammar) -> Result<Vec<ParseState>, TableError> {
+    let placeholder = grammar.token_count;
+    let mut builder = Builder {
+        grammar,
+        placeholder,
+        first: Vec::new(),
+        suffixes: Vec::new(),
+        kernels: Vec::new(),
+        extra: Vec::new(),
+        transitions: Vec::new(),
+        extra_starts: Vec::new(),
+    };
+    builder.compute_suffixes();
+    builder.build_lr0()?;
+    let lookaheads = builder.propagate_lookaheads();
+    builder.resolve(&lookaheads)
+}
+
+impl Builder<'_> {
+    fn next_symbol(&self, (production, position): Item) -> Option<usize> {
+        self.grammar.productions[production]
+            .steps
+            .get(position)
+            .map(|step| step.symbol)
+    }
+
+    /// The precedence and associativity of the step before the dot.
+    fn item_precedence(
+        &self,
+        (production, position): Item,
+    ) -> (&Precedence, Option<Associativity>) {
+        match position.checked_sub(1) {
+            Some(index) => {
+                let step = &self.grammar.productions[production].steps[index];
+                (&step.precedence, step.associativity)
+            }
+            None => (&NO_PRECEDENCE, None),
+        }
+    }
+
+    /// The items `item` stands for: itself, or if its dot is before an
+    /// inlined rule, the same step of each production splicing it in.
+    fn expand(&self, item: Item) -> Vec<Item> {
+        match self.grammar.inlined.get(&item) {
+            Some(productions) => productions
+                .iter()
+                .map(|&production| (production, item.1))
+                .collect(),
+            None => vec![item],
+        }
+    }
+
+    fn compute_suffixes(&mut self) {
+        let grammar = self.grammar;
+        let size = self.placeholder + 1;
+        let symbol_count = grammar.productions_by_lhs.len();
+        let mut first = vec![TokenSet::new(size); symbol_count];
+        let mut nullable = vec![false; symbol_count];
+        for (token, set) in first.iter_mut().enumerate().take(grammar.token_count) {
+            set.insert(token);
+        }
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for production in &grammar.productions {
+                let mut all_nullable = true;
+                for step in &production.steps {
+                    let step_first = first[step.symbol].clone();
+                    changed |= first[production.lhs].union(&step_first);
+                    if !nullable[step.symbol] {
+                        all_nullable = false;
+                        break;
+                    }
+                }
+                if all_nullable && !nullable[production.lhs] {
+                    nullable[production.lhs] = true;
+                    changed = true;
+                }
+            }
+        }
+        self.suffixes = grammar
+            .productions
+            .iter()
+            .map(|production| {
+                let mut suffixes = vec![(TokenSet::new(size), true)];
+                for step in production.steps.iter().rev() {
+                    let (after, after_nullable) = suffixes.last().expect("starts non-empty");
+                    let mut set = first[step.symbol].clone();
+                    let suffix_nullable = nullable[step.symbol] && *after_nullable;
+                    if nullable[step.symbol] {
+                        set.union(after);
+                    }
+                    suffixes.push((set, suffix_nullable));
+                }
+                suffixes.reverse();
+                suffixes
+            })
+            .collect();
+        self.first = first;
+    }
+
+    fn closure0(&self, kernel: &[Item]) -> Vec<Item> {
+        let mut items: Vec<Item> = kernel.iter().flat_map(|&item| self.expand(item)).collect();
+        let mut added = vec![false; self.grammar.productions_by_lhs.len()];
+        let mut i = 0;
+        while i < items.len() {
+            if let Some(symbol) = self.next_symbol(items[i]) {
+                if !std::mem::replace(&mut added[symbol], true) {
+                    for &production in &self.grammar.productions_by_lhs[symbol] {
+                        items.extend(self.expand((production, 0)));
+                    }
+                }
+            }
+            i += 1;
+        }
+        items
+    }
+
+    /// The kernels of the states extra rules start in: each production of
+    /// an extra rule past its first token, grouped by that token.
+    fn extra_kernels(&self) -> Result<BTreeMap<usize, Vec<Item>>, TableError> {
+        let grammar = self.grammar;
+        let mut kernels: BTreeMap<usize, Vec<Item>> = BTreeMap::new();
+        for &rule in &grammar.extra_rules {
+            for &production in &grammar.productions_by_lhs[rule] {
+                for (production, _) in self.expand((production, 0)) {
+                    match self.next_symbol((production, 0)) {
+                        Some(token) if token < grammar.token_count => {
+                            kernels.entry(token).or_default().push((production, 1));
+                        }
+                        _ => {
+                            return Err(TableError::Unsupported(format!(
+                                "extra '{}' does not always start with a token",
+                                grammar.names[rule]
+                            )))
+                        }
+                    }
+                }
+            }
+        }
+        Ok(kernels)
+    }
+
+    fn build_lr0(&mut self) -> Result<(), TableError> {
+        let start = vec![(self.grammar.start_production, 0)];
+        let mut ids: HashMap<(Vec<Item>, bool), usize> =
+            HashMap::from([((start.clone(), false), 0)]);
+        self.kernels.push(start);
+        self.extra.push(false);
+        for (token, mut kernel) in self.extra_kernels()? {
+            kernel.sort_unstable();
+            kernel.dedup();
+            let state = self.kernels.len();
+            ids.insert((kernel.clone(), true), state);
+            self.kernels.push(kernel);
+            self.extra.push(true);
+            self.extra_starts.push((token, state));
+        }
+        let mut state = 0;
+        while state < self.kernels.len() {
+            let mut successors: BTreeMap<usize, Vec<Item>> = BTreeMap::new();
+            for item in self.closure0(&self.kernels[state]) {
+                if let Some(symbol) = self.next_symbol(item) {
+                    successors
+                        .entry(symbol)
+                        .or_default()
+                        .push((item.0, item.1 + 1));
+                }
+            }
+            // States inside extras are kept apart from the others, as
+            // they end differently.
+            let extra = self.extra[state];
+            let mut transitions = BTreeMap::new();
+            for (symbol, mut kernel) in successors {
+                kernel.sort_unstable();
+                kernel.dedup();
+                let next = self.kernels.len();
+                let target = *ids.entry((kernel.clone(), extra)).or_insert(next);
+                if target == next {
+                    self.kernels.push(kernel);
+                    self.extra.push(extra);
+                }
+                transitions.insert(symbol, target);
+            }
+            self.transitions.push(transitions);
+            state += 1;
+        }
+        Ok(())
+    }
+
+    /// The LR(1) closure of `kernel`: every item reachable from it, with
+    /// the lookaheads each can be reduced on.
+    fn closure1(&self, kernel: Vec<(Item, TokenSet)>) -> Vec<(Item, TokenSet)> {
+        let mut closure = Closure::default();
+        for (item, lookahead) in kernel {
+            for item in self.expand(item) {
+                closure.add(item, &lookahead);
+            }
+        }
+        while let Some(i) = closure.pending.pop_front() {
+            let ((production, position), _) = closure.items[i];
+            let Some(symbol) = self.next_symbol((production, position)) else {
+                continue;
+            };
+            let productions = &self.grammar.productions_by_lhs[symbol];
+            if productions.is_empty() {
+                continue;
+            }
+            let (first, nullable) = &self.suffixes[production][position + 1];
+            let mut lookahead = first.clone();
+            if *nullable {
+                lookahead.union(&closure.items[i].1);
+            }
+            for &child in productions {
+                for item in self.expand((child, 0)) {
+                    closure.add(item, &lookahead);
+                }
+            }
+        }
+        closure.items
+    }
+
+    fn propagate_lookaheads(&self) -> Vec<Vec<TokenSet>> {
+        let size = self.placeholder + 1;
+        let mut lookaheads: Vec<Vec<TokenSet>> = self
+            .kernels
+            .iter()
+            .map(|kernel| vec![TokenSet::new(size); kernel.len()])
+            .collect();
+        lookaheads[0][0].insert(0);
+        // Extra rules end where they started, which shows as the end symbol.
+        for (state, kernel_lookaheads) in lookaheads.iter_mut().enumerate() {
+            if self.extra[state] && self.extra_starts.iter().any(|&(_, start)| start == state) {
+                for lookahead in kernel_lookaheads {
+                    lookahead.insert(0);
+                }
+            }
+        }
+        let mut edges: Vec<Vec<Vec<(usize, usize)>>> = self
+            .kernels
+            .iter()
+            .map(|kernel| vec![Vec::new(); kernel.len()])
+            .collect();
+        for (state, kernel) in self.kernels.iter().enumerate() {
+            for (k, &item) in kernel.iter().enumerate() {
+                let mut placeholder = TokenSet::new(size);
+                placeholder.insert(self.placeholder);
+                for ((production, position), mut lookahead) in
+                    self.closure1(vec![(item, placeholder)])
+                {
+                    let Some(symbol) = self.next_symbol((production, position)) else {
+                        continue;
+                    };
+                    let target = self.transitions[state][&symbol];
+                    let target_item = self.kernels[target]
+                        .binary_search(&(production, position + 1))
+                        .expect("successor kernels contain advanced items");
+                    if lookahead.contains(self.placeholder) {
+                        lookahead.remove(self.placeholder);
+                        edges[state][k].push((target, target_item));
+                    }
+                    lookaheads[target][target_item].union(&lookahead);
+                }
+            }
+        }
+        let mut pending: VecDeque<(usize, usize)> = self
+            .kernels
+            .iter()
+            .enumerate()
+            .flat_map(|(state, kernel)| (0..kernel.len()).map(move |k| (state, k)))
+            .collect();
+        while let Some((state, k)) = pending.pop_front() {
+            let source = lookaheads[state][k].clone();
+            for &(target, target_item) in &edges[state][k] {
+                if lookaheads[target][target_item].union(&source) {
+                    pending.push_back((target, target_item));
+                }
+            }
+        }
+        lookaheads
+    }
+
+    fn resolve(&self, lookaheads: &[Vec<TokenSet>]) -> Result<Vec<ParseState>, TableError> {
+        let grammar = self.grammar;
+        let mut states = Vec::with_capacity(self.kernels.len());
+        for (state, kernel) in self.kernels.iter().enumerate() {
+            let items = self.closure1(
+                kernel
+                    .iter()
+                    .copied()
+                    .zip(lookaheads[state].iter().cloned())
+                    .collect(),
+            );
+            let mut parse_state = ParseState::default();
+            let mut reductions: BTreeMap<usize, Reductions> = BTreeMap::new();
+            for (item, lookahead) in &items {
+                if self.next_symbol(*item).is_some() {
+                    continue;
+                }
+                for token in lookahead.iter() {
+                    if item.0 == grammar.start_production {
+                        parse_state.actions.insert(token, vec![Action::Accept]);
+                    } else {
+                        self.add_reduction(reductions.entry(token).or_default(), *item);
+                    }
+                }
+            }
+            for (&symbol, &target) in &self.transitions[state] {
+                if symbol >= grammar.token_count {
+                    parse_state.gotos.insert(symbol, target);
+                    continue;
+                }
+                let actions = match reductions.remove(&symbol) {
+                    Some(reductions) => {
+                        self.resolve_conflict(state, &items, symbol, Some(target), &reductions)?
+                    }
+                    None => vec![Action::Shift(target)],
+                };
+                parse_state.actions.insert(symbol, actions);
+            }
+            for (token, reductions) in reductions {
+                if parse_state.actions.contains_key(&token) {
+                    continue;
+                }
+                let actions = if reductions.productions.len() == 1 {
+                    vec![Action::Reduce(reductions.productions[0])]
+                } else {
+                    self.resolve_conflict(state, &items, token, None, &reductions)?
+                };
+                parse_state.actions.insert(token, actions);
+            }
+            self.add_extras(state, &items, &mut parse_state)?;
+            states.push(parse_state);
+        }
+        Ok(states)
+    }
+
+    /// Adds the reduction of the finished `item` on a lookahead, unless one
+    /// of higher precedence is already there, and dropping those of lower
+    /// precedence.
+    fn add_reduction(&self, reductions: &mut Reductions, item: Item) {
+        let (precedence, associativity) = self.item_precedence(item);
+        let lhs = self.grammar.productions[item.0].lhs;
+        if !reductions.productions.is_empty() {
+            match self.compare_precedence(
+                (precedence, &[lhs]),
+                (&reductions.precedence, &reductions.symbols),
+            ) {
+                Ordering::Greater => *reductions = Reductions::default(),
+                Ordering::Equal => {}
+                Ordering::Less => return,
+            }
+        }
+        reductions.productions.push(item.0);
+        reductions.precedence = precedence.clone();
+        if !reductions.symbols.contains(&lhs) {
+            reductions.symbols.push(lhs);
+        }
+        match associativity {
+            Some(Associativity::Left) => reductions.left = true,
+            Some(Associativity::Right) => reductions.right = true,
+            None => reductions.non_associative = true,
+        }
+    }
+
+    /// Orders two precedences, each with the rules it applies to.
+    fn compare_precedence(
+        &self,
+        (left, left_symbols): (&Precedence, &[usize]),
+        (right, right_symbols): (&Precedence, &[usize]),
+    ) -> Ordering {
+        let matches = |entry: &PrecedenceEntry, precedence: &Precedence, symbols: &[usize]| match (
+            entry, precedence,
+        ) {
+            (PrecedenceEntry::Name(entry), Precedence::Name(name)) => entry == name,
+            (PrecedenceEntry::Name(_), _) => false,
+            (PrecedenceEntry::Symbol(symbol), _) => symbols.contains(symbol),
+        };
+        match (left, right) {
+            // Integers compare with each other and with no precedence, which
+            // counts as zero, unless both are zero.
+            (Precedence::Integer(left), Precedence::Integer(right))
+                if *left != 0 || *right != 0 =>
+            {
+                left.cmp(right)
+            }
+            (Precedence::Integer(left), Precedence::None) if *left != 0 => left.cmp(&0),
+            (Precedence::None, Precedence::Integer(right)) if *right != 0 => 0.cmp(right),
+            _ => self
+                .grammar
+                .precedences
+                .iter()
+                .find_map(|list| {
+                    let (mut saw_left, mut saw_right) = (false, false);
+                    for entry in list {
+                        if matches(entry, left, left_symbols) {
+                            saw_left = true;
+                            if saw_right {
+                                return Some(Ordering::Less);
+                            }
+                        } else if matches(entry, right, right_symbols) {
+                            saw_right = true;
+                            if saw_left {
+                                return Some(Ordering::Greater);
+                            }
+                        }
+                    }
+                    None
+                })
+                .unwrap_or(Ordering::Equal),
+        }
+    }
+
+    /// Picks the actions to take on `token`, given the state it could shift
+    /// to and the productions it could reduce.
+    fn resolve_conflict(
+        &self,
+        state: usize,
+        items: &[(Item, TokenSet)],
+        token: usize,
+        mut shift: Option<usize>,
+        reductions: &Reductions,
+    ) -> Result<Vec<Action>, TableError> {
+        let grammar = self.grammar;
+        let lhs = |item: &Item| grammar.productions[item.0].lhs;
+        // The items shifting `token` or something starting with it, other
+        // than those only just predicted, and the items reducing on it.
+        let mut conflicting: Vec<Item> = Vec::new();
+        let mut shift_precedences: Vec<(&Precedence, usize)> = Vec::new();
+        for (item, lookahead) in items {
+            if item.0 == grammar.start_production {
+                continue;
+            }
+            match self.next_symbol(*item) {
+                Some(symbol) if item.1 > 0 && self.first[symbol].contains(token) => {
+                    conflicting.push(*item);
+                    let precedence = (self.item_precedence(*item).0, lhs(item));
+                    if !shift_precedences.contains(&precedence) {
+                        shift_precedences.push(precedence);
+                    }
+                }
+                None if lookahead.contains(token) => conflicting.push(*item),
+                _ => {}
+            }
+        }
+
+        let mut reduces = reductions.productions.clone();
+        // A repetition following itself is ambiguous on purpose. The parser
+        // takes the first action, so the longest repetition comes first.
+        let repetition = conflicting.first().is_some_and(|first| {
+            grammar.auxiliary[lhs(first)] && conflicting.iter().all(|item| lhs(item) == lhs(first))
+        });
+        if repetition {
+            reduces.sort_by_key(|&production| {
+                std::cmp::Reverse(grammar.productions[production].steps.len())
+            });
+            return Ok(actions(shift, reduces));
+        }
+        if shift.is_some() {
+            let (mut shift_is_more, mut shift_is_less) = (false, false);
+            for &(precedence, symbol) in &shift_precedences {
+                match self.compare_precedence(
+                    (precedence, &[symbol]),
+                    (&reductions.precedence, &reductions.symbols),
+                ) {
+                    Ordering::Greater => shift_is_more = true,
+                    Ordering::Less => shift_is_less = true,
+                    Ordering::Equal => {}
+                }
+            }
+            let keep_shift = match (shift_is_more, shift_is_less) {
+                (true, false) => Some(true),
+                (false, true) => Some(false),
+                (false, false) => match (
+                    reductions.left,
+                    reductions.non_associative,
+                    reductions.right,
+                ) {
+                    (true, false, false) => Some(false),
+                    (false, false, true) => Some(true),
+                    _ => None,
+                },
+                (true, true) => None,
+            };
+            match keep_shift {
+                Some(true) => reduces.clear(),
+                Some(false) => {
+                    shift = None;
+                    conflicting.retain(|&item| self.next_symbol(item).is_none());
+                }
+                None => {}
+            }
+        }
+        if usize::from(shift.is_some()) + reduces.len() <= 1 {
+            return Ok(actions(shift, reduces));
+        }
+
+        let involved: BTreeSet<usize> = conflicting
+            .iter()
+            .map(|item| grammar.conflict_owner[lhs(item)])
+            .collect();
+        if !grammar.expected_conflicts.contains(&involved) {
+            return Err(TableError::Conflict(self.conflict(
+                state,
+                token,
+                &conflicting,
+                &involved,
+            )));
+        }
+        Ok(actions(shift, reduces))
+    }
+
+    /// Adds the ways into extras to a state, or checks that a state
+    /// finishing an extra rule does nothing else.
+    fn add_extras(
+        &self,
+        state: usize,
+        items: &[(Item, TokenSet)],
+        parse_state: &mut ParseState,
+    ) -> Result<(), TableError> {
+        let grammar = self.grammar;
+        if self.extra[state] && parse_state.actions.contains_key(&0) {
+            if parse_state.actions.len() > 1 {
+                let rules: BTreeSet<&str> = items
+                    .iter()
+                    .filter(|(item, _)| self.next_symbol(*item).is_none())
+                    .map(|(item, _)| grammar.names[grammar.productions[item.0].lhs].as_str())
+                    .collect();
+                return Err(TableError::Unsupported(format!(
+                    "extras must end unambiguously, but {} may end or go on",
+                    rules.into_iter().collect::<Vec<_>>().join(", ")
+                )));
+            }
+            parse_state.end_of_extra = true;
+            return Ok(());
+        }
+        for &(token, start) in &self.extra_starts {
+            parse_state
+                .actions
+                .entry(token)
+                .or_insert_with(|| vec![Action::Shift(start)]);
+        }
+        for &extra in &grammar.extra_tokens {
+            parse_state
+                .actions
+                .entry(extra)
+                .or_insert_with(|| vec![Action::ShiftExtra]);
+        }
+        for &rule in &grammar.extra_rules {
+            parse_state.gotos.insert(rule, state);
+        }
+        Ok(())
+    }
+
+    fn conflict(
+        &self,
+        state: usize,
+        token: usize,
+        conflicting: &[Item],
+        involved: &BTreeSet<usize>,
+    ) -> Conflict {
+        let grammar = self.grammar;
+        let sequence = self.symbol_sequence(state);
+        let names = |symbols: &[usize]| -> Vec<String> {
+            symbols
+                .iter()
+                .map(|&s| grammar.display_names[s].clone())
+                .collect()
+        };
+        let lookahead = grammar.display_names[token].clone();
+        let mut interpretations = Vec::new();
+        let (reduces, shifts): (Vec<Item>, Vec<Item>) = conflicting
+            .iter()
+            .partition(|&&item| self.next_symbol(item).is_none());
+        for &(production, position) in shifts.iter().chain(&reduces) {
+            let steps: Vec<usize> = grammar.productions[production]
+                .steps
+                .iter()
+                .map(|s| s.symbol)
+                .collect();
+            let mut words = names(&sequence[..sequence.len().saturating_sub(position)]);
+            let mut inner =
+                vec![grammar.display_names[grammar.productions[production].lhs].clone()];
+            inner.extend(names(&steps[..position]));
+            if position < steps.len() {
+                inner.push("•".to_string());
+                inner.extend(names(&steps[position..]));
+                words.push(format!("({})", inner.join("  ")));
+            } else {
+                words.push(format!("({})", inner.join("  ")));
+                words.extend(["•".to_string(), lookahead.clone(), "…".to_string()]);
+            }
+            interpretations.push(words.join("  "));
+        }
+        let mut symbols = names(&sequence);
+        symbols.extend(["•".to_string(), lookahead.clone(), "…".to_string()]);
+        Conflict {
+            symbols,
+            lookahead,
+            interpretations,
+            rules: involved
+                .iter()
+                .map(|&symbol| grammar.names[symbol].clone())
+                .collect(),
+        }
+    }
+
+    /// The shortest sequence of symbols leading from the start to `state`.
+    fn symbol_sequence(&self, state: usize) -> Vec<usize> {
+        let mut parents: Vec<Option<(usize, usize)>> = vec![None; self.kernels.len()];
+        let mut pending = VecDeque::from([0]);
+        let mut seen = vec![false; self.kernels.len()];
+        seen[0] = true;
+        while let Some(current) = pending.pop_front() {
+            for (&symbol, &next) in &self.transitions[current] {
+                if !std::mem::replace(&mut seen[next], true) {
+                    parents[next] = Some((current, symbol));
+                    pending.push_back(next);
+                }
+            }
+        }
+        let mut sequence = Vec::new();
+        let mut current = state;
+        while let Some((parent, symbol)) = parents[current] {
+            sequence.push(symbol);
+            current = parent;
+        }
+        sequence.reverse();
+        sequence
+    }
+}
+
+/// The actions kept on a token: the shift first, then the reductions.
+fn actions(shift: Option<usize>, reduces: Vec<usize>) -> Vec<Action> {
+    let mut actions: Vec<Action> = shift.map(Action::Shift).into_iter().collect();
+    actions.extend(reduces.into_iter().map(Action::Reduce));
+    actions
+}
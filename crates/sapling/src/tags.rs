@@ -0,0 +1,270 @@
+//! Code navigation tags with tree-sitter's tags queries.
+//!
+//! This follows `tree-sitter-tags`. A grammar's `tags.scm` captures each
+//! definition or reference as a whole, as `@definition.<kind>` or
+//! `@reference.<kind>`, together with its name as `@name`:
+//!
+//! ```scheme
+//! ((comment)* @doc
+//!   .
+//!   (function_definition name: (identifier) @name) @definition.function
+//!   (#strip! @doc "^//\s*")
+//!   (#select-adjacent! @doc @definition.function))
+//!
+//! (call function: (identifier) @name) @reference.call
+//! ```
+//!
+//! Nodes captured as `@doc` become the tag's documentation: `#strip!`
+//! removes what a regex matches from each, and `#select-adjacent!` keeps
+//! only the run of them ending on the line before the definition.
+//!
+//! A name is tagged once, by the first pattern in the query that tags it,
+//! and a pattern that also captures `@ignore` tags it as nothing: placed
+//! first, it keeps later patterns from tagging some names. With a locals
+//! query, references to local variables are left out, as they don't lead
+//! anywhere worth going.
+
+use crate::input::Point;
+use crate::language::Language;
+use crate::locals;
+use crate::parser::Parser;
+use crate::query::{Query, QueryCursor, QueryError, QueryMatch, QueryPredicateArg};
+use crate::tree::Node;
+use regex::bytes::Regex;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// The longest [`Tag::line_range`], in bytes.
+const MAX_LINE_LEN: usize = 180;
+
+/// A definition or reference found by a tags query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tag {
+    /// What was defined or referred to, from the capture name: `function`
+    /// for `@definition.function`.
+    pub kind: String,
+    /// The bytes of the whole definition or reference.
+    pub range: Range<usize>,
+    /// The bytes of the name.
+    pub name_range: Range<usize>,
+    /// The bytes of the line the name starts on, without surrounding
+    /// whitespace, and cut short after 180 bytes.
+    pub line_range: Range<usize>,
+    /// Where the name starts and ends.
+    pub span: Range<Point>,
+    /// The text of the nodes captured as `@doc`, one per line.
+    pub docs: Option<String>,
+    /// Whether the tag is a definition rather than a reference.
+    pub is_definition: bool,
+}
+
+/// A language's tags query, ready to tag with.
+#[derive(Debug, Clone)]
+pub struct TagsConfiguration<'l> {
+    language: &'l Language,
+    query: Query,
+    locals: Option<Query>,
+}
+
+impl<'l> TagsConfiguration<'l> {
+    /// Compiles the queries for `language`. An empty `locals_query` is left
+    /// out.
+    ///
+    /// # Errors
+    ///
+    /// Returns the [`QueryError`] of the first query that fails to compile.
+    pub fn new(
+        language: &'l Language,
+        tags_query: &str,
+        locals_query: &str,
+    ) -> Result<Self, QueryError> {
+        let locals = (!locals_query.trim().is_empty())
+            .then(|| Query::for_language(language, locals_query))
+            .transpose()?;
+        Ok(Self {
+            language,
+            query: Query::for_language(language, tags_query)?,
+            locals,
+        })
+    }
+
+    /// The language.
+    #[must_use]
+    pub fn language(&self) -> &'l Language {
+        self.language
+    }
+}
+
+/// Parses `source` and returns its tags, in order of their names.
+#[must_use]
+pub fn tags(config: &TagsConfiguration<'_>, source: &[u8]) -> Vec<Tag> {
+    let tree = Parser::new(config.language).parse(source);
+    let locals = config
+        .locals
+        .as_ref()
+        .map(|locals| locals::resolve(locals, &tree, source))
+        .unwrap_or_default();
+    // The tag of each name, or `None` where it is ignored, and the pattern
+    // that tagged it.
+    let mut tagged: HashMap<Range<usize>, (usize, Option<Tag>)> = HashMap::new();
+    for tag_match in QueryCursor::new().matches(&config.query, &tree, source) {
+        let Some((name, tag)) = tag(&config.query, &tag_match, source) else {
+            continue;
+        };
+        // A reference resolved to anything but itself is a local.
+        let key: *const Node = name;
+        let is_reference = tag.as_ref().is_some_and(|tag| !tag.is_definition);
+        if is_reference
+            && locals
+                .get(&key)
+                .is_some_and(|&definition| definition != key)
+        {
+            continue;
+        }
+        let pattern_index = tag_match.pattern_index;
+        tagged
+            .entry(name.byte_range())
+            .and_modify(|existing| {
+                if pattern_index < existing.0 {
+                    *existing = (pattern_index, tag.clone());
+                }
+            })
+            .or_insert((pattern_index, tag));
+    }
+    let mut tags: Vec<Tag> = tagged.into_values().filter_map(|(_, tag)| tag).collect();
+    tags.sort_by_key(|tag| tag.name_range.start);
+    tags
+}
+
+/// The name node and tag for one match of a tags query, if it has both a
+/// name and a kind. The tag is `None` if the match is ignored.
+fn tag<'tree>(
+    query: &Query,
+    tag_match: &QueryMatch<'tree>,
+    source: &[u8],
+) -> Option<(&'tree Node, Option<Tag>)> {
+    let names = query.capture_names();
+    let mut name = None;
+    let mut tagged = None;
+    let mut docs = Vec::new();
+    let mut ignored = false;
+    for capture in &tag_match.captures {
+        let capture_name = names[capture.index].as_str();
+        if let Some(kind) = capture_name.strip_prefix("definition.") {
+            tagged = Some((kind, true, capture.node));
+        } else if let Some(kind) = capture_name.strip_prefix("reference.") {
+            tagged = Some((kind, false, capture.node));
+        } else {
+            match capture_name {
+                "name" => name = Some(capture.node),
+                "doc" => docs.push((capture.index, capture.node)),
+                "ignore" => ignored = true,
+                _ => {}
+            }
+        }
+    }
+    let name = name?;
+    let (kind, is_definition, node) = tagged?;
+    if ignored {
+        return Some((name, None));
+    }
+
+    let mut strip = Vec::new();
+    for predicate in query.general_predicates(tag_match.pattern_index) {
+        match (predicate.operator.as_str(), predicate.args.as_slice()) {
+            ("strip!", [QueryPredicateArg::Capture(capture), QueryPredicateArg::String(regex)]) => {
+                if let Ok(regex) = Regex::new(regex) {
+                    strip.push((*capture, regex));
+                }
+            }
+            (
+                "select-adjacent!",
+                [QueryPredicateArg::Capture(capture), QueryPredicateArg::Capture(target)],
+            ) => {
+                let target = tag_match
+                    .captures
+                    .iter()
+                    .find(|other| other.index == *target)
+                    .map(|other| other.node);
+                if let Some(target) = target {
+                    select_adjacent(&mut docs, *capture, target);
+                }
+            }
+            _ => {}
+        }
+    }
+    let docs = (!docs.is_empty()).then(|| {
+        let lines: Vec<String> = docs
+            .iter()
+            .map(|&(index, doc)| {
+                let mut text = source[doc.byte_range()].to_vec();
+                for (_, regex) in strip.iter().filter(|(capture, _)| *capture == index) {
+                    text = regex.replace_all(&text, &b""[..]).into_owned();
+                }
+                String::from_utf8_lossy(&text).into_owned()
+            })
+            .collect();
+        lines.join("\n")
+    });
+
+    let name_range = name.byte_range();
+    let tag = Tag {
+        kind: kind.to_string(),
+        range: node.byte_range(),
+        line_range: line_range(source, name_range.start),
+        name_range,
+        span: name.start_position()..name.end_position(),
+        docs,
+        is_definition,
+    };
+    Some((name, Some(tag)))
+}
+
+/// Keeps the `capture` nodes among `docs` that lead up to `target`, each
+/// ending on the line before the next, or on the same line.
+fn select_adjacent(docs: &mut Vec<(usize, &Node)>, capture: usize, target: &Node) {
+    let mut next_row = target.start_position().row;
+    let mut keep = vec![true; docs.len()];
+    for (i, &(index, doc)) in docs.iter().enumerate().rev() {
+        if index != capture {
+            continue;
+        }
+        if doc.end_byte() <= target.start_byte() && doc.end_position().row + 1 >= next_row {
+            next_row = doc.start_position().row;
+        } else {
+            keep[i] = false;
+        }
+    }
+    let mut keep = keep.into_iter();
+    docs.retain(|_| keep.next().unwrap_or(true));
+}
+
+/// The line holding `byte`, trimmed and cut short at a character boundary.
+fn line_range(source: &[u8], byte: usize) -> Range<usize> {
+    let start = source[..byte]
+        .iter()
+        .rposition(|&b| b == b'\n')
+        .map_or(0, |i| i + 1);
+    let end = source[byte..]
+        .iter()
+        .position(|&b| b == b'\n')
+        .map_or(source.len(), |i| byte + i);
+    let line = &source[start..end];
+    let leading = line
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(line.len());
+    let trailing = line
+        .iter()
+        .rposition(|b| !b.is_ascii_whitespace())
+        .map_or(leading, |i| i + 1);
+    let (start, mut end) = (start + leading, start + trailing);
+    if end - start > MAX_LINE_LEN {
+        end = start + MAX_LINE_LEN;
+        // Back up to the start of a character.
+        while end > start && source[end] & 0xC0 == 0x80 {
+            end -= 1;
+        }
+    }
+    start..end
+}
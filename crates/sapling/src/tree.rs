@@ -0,0 +1,604 @@
+//! Concrete syntax trees.
+//!
+//! A [`Node`] is an owned tree node: its kind, whether it is named, the field
+//! it occupies in its parent, its source [`Range`], and its children. Parsers
+//! build trees bottom-up, creating leaves for tokens and wrapping finished
+//! children in their parent, which is also the order LR reductions happen in.
+//!
+//! Broken input is represented the way tree-sitter represents it. An `ERROR`
+//! node wraps text that could not be fitted into the grammar, and a
+//! zero-width `MISSING` node stands in for a token that should have been
+//! present. Both are visible in [`Node::to_sexp`] output, so corpus tests can
+//! assert on recovery behaviour. [`render`](crate::tree::render) shows
+//! trees in full, as indented text, JSON or XML.
+
+pub mod render;
+
+use crate::input::Point;
+use std::fmt::Write;
+
+/// The node kind used for error nodes.
+pub const ERROR_KIND: &str = "ERROR";
+
+/// A span of source text, in bytes and in row/column [`Point`]s.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Range {
+    /// The byte offset of the first byte in the span.
+    pub start_byte: usize,
+    /// The byte offset just past the last byte in the span.
+    pub end_byte: usize,
+    /// The position of `start_byte`.
+    pub start_point: Point,
+    /// The position of `end_byte`.
+    pub end_point: Point,
+}
+
+impl Range {
+    /// Creates a range from byte offsets and their positions.
+    #[must_use]
+    pub const fn new(
+        start_byte: usize,
+        end_byte: usize,
+        start_point: Point,
+        end_point: Point,
+    ) -> Self {
+        Self {
+            start_byte,
+            end_byte,
+            start_point,
+            end_point,
+        }
+    }
+
+    /// Creates the range covering `bytes` within `source`, computing the
+    /// row/column positions by scanning for newlines.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes` extends past the end of `source`.
+    #[must_use]
+    pub fn from_bytes(source: &[u8], bytes: std::ops::Range<usize>) -> Self {
+        Self::new(
+            bytes.start,
+            bytes.end,
+            point_at(source, bytes.start),
+            point_at(source, bytes.end),
+        )
+    }
+
+    /// Returns a zero-width range at this range's start.
+    #[must_use]
+    pub const fn start(&self) -> Self {
+        Self::new(
+            self.start_byte,
+            self.start_byte,
+            self.start_point,
+            self.start_point,
+        )
+    }
+
+    /// Returns a zero-width range at this range's end.
+    #[must_use]
+    pub const fn end(&self) -> Self {
+        Self::new(self.end_byte, self.end_byte, self.end_point, self.end_point)
+    }
+}
+
+impl Range {
+    /// Moves the range to where its text is after `edit`. An end inside the
+    /// replaced text moves back to where the edit starts and a start inside
+    /// it forward to where the new text ends, so only the text the range
+    /// keeps is covered. A range that never ends, as a parser's reading the
+    /// whole document, keeps its end.
+    pub fn edit(&mut self, edit: &InputEdit) {
+        if self.end_byte != usize::MAX && self.end_byte > edit.start_byte {
+            (self.end_byte, self.end_point) = if self.end_byte >= edit.old_end_byte {
+                edit.shift(self.end_byte, self.end_point)
+            } else {
+                (edit.start_byte, edit.start_position)
+            };
+        }
+        if self.start_byte >= edit.old_end_byte {
+            (self.start_byte, self.start_point) = edit.shift(self.start_byte, self.start_point);
+        } else if self.start_byte > edit.start_byte {
+            (self.start_byte, self.start_point) = (edit.new_end_byte, edit.new_end_position);
+        }
+        if self.end_byte < self.start_byte {
+            (self.end_byte, self.end_point) = (self.start_byte, self.start_point);
+        }
+    }
+}
+
+/// A change to a document's text: the bytes from `start_byte` to
+/// `old_end_byte` were replaced by those from `start_byte` to
+/// `new_end_byte`, as tree-sitter's `TSInputEdit` describes it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct InputEdit {
+    /// Where the change starts.
+    pub start_byte: usize,
+    /// Where the replaced text ended.
+    pub old_end_byte: usize,
+    /// Where the new text ends.
+    pub new_end_byte: usize,
+    /// The position of `start_byte`.
+    pub start_position: Point,
+    /// The position of `old_end_byte`, before the change.
+    pub old_end_position: Point,
+    /// The position of `new_end_byte`, after the change.
+    pub new_end_position: Point,
+}
+
+impl InputEdit {
+    /// Where a byte at or after the end of the replaced text is after the
+    /// change.
+    fn shift(&self, byte: usize, point: Point) -> (usize, Point) {
+        let column = if point.row == self.old_end_position.row {
+            point.column - self.old_end_position.column + self.new_end_position.column
+        } else {
+            point.column
+        };
+        (
+            byte - self.old_end_byte + self.new_end_byte,
+            Point::new(
+                point.row - self.old_end_position.row + self.new_end_position.row,
+                column,
+            ),
+        )
+    }
+}
+
+/// Computes the [`Point`] of `byte_offset` within `source`.
+fn point_at(source: &[u8], byte_offset: usize) -> Point {
+    let mut point = Point::default();
+    for &byte in &source[..byte_offset] {
+        if byte == b'\n' {
+            point.row += 1;
+            point.column = 0;
+        } else {
+            point.column += 1;
+        }
+    }
+    point
+}
+
+/// A node in a concrete syntax tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    kind: String,
+    named: bool,
+    missing: bool,
+    field: Option<String>,
+    supertypes: Vec<String>,
+    range: Range,
+    children: Vec<Node>,
+}
+
+impl Node {
+    /// Creates a token node with no children.
+    #[must_use]
+    pub fn leaf(kind: impl Into<String>, named: bool, range: Range) -> Self {
+        Self {
+            kind: kind.into(),
+            named,
+            missing: false,
+            field: None,
+            supertypes: Vec::new(),
+            range,
+            children: Vec::new(),
+        }
+    }
+
+    /// Creates an interior node spanning its children.
+    ///
+    /// A node with no children is zero-width at the default position; use
+    /// [`Node::leaf`] to place an empty node explicitly.
+    #[must_use]
+    pub fn branch(kind: impl Into<String>, named: bool, children: Vec<Node>) -> Self {
+        let range = match (children.first(), children.last()) {
+            (Some(first), Some(last)) => Range::new(
+                first.range.start_byte,
+                last.range.end_byte,
+                first.range.start_point,
+                last.range.end_point,
+            ),
+            _ => Range::default(),
+        };
+        Self {
+            kind: kind.into(),
+            named,
+            missing: false,
+            field: None,
+            supertypes: Vec::new(),
+            range,
+            children,
+        }
+    }
+
+    /// Creates an `ERROR` node wrapping text that could not be parsed.
+    #[must_use]
+    pub fn error(children: Vec<Node>) -> Self {
+        Self::branch(ERROR_KIND, true, children)
+    }
+
+    /// Creates an `ERROR` node covering `range` with no parsed children,
+    /// used when recovery skips raw characters.
+    #[must_use]
+    pub fn unexpected(range: Range) -> Self {
+        Self::leaf(ERROR_KIND, true, range)
+    }
+
+    /// Creates a zero-width node standing in for a token of kind `kind`
+    /// that the parser expected at `at_byte` (position `at`) but did not find.
+    #[must_use]
+    pub fn missing(kind: impl Into<String>, named: bool, at_byte: usize, at: Point) -> Self {
+        Self {
+            missing: true,
+            ..Self::leaf(kind, named, Range::new(at_byte, at_byte, at, at))
+        }
+    }
+
+    /// Attaches this node to its parent under the field `name`.
+    #[must_use]
+    pub fn with_field(mut self, name: impl Into<String>) -> Self {
+        self.field = Some(name.into());
+        self
+    }
+
+    /// Records that this node stands for the hidden supertype `name` in its
+    /// parent.
+    #[must_use]
+    pub fn with_supertype(mut self, name: impl Into<String>) -> Self {
+        self.supertypes.push(name.into());
+        self
+    }
+
+    /// Renames this node, as an alias in the grammar does.
+    #[must_use]
+    pub fn with_kind(mut self, kind: impl Into<String>, named: bool) -> Self {
+        self.kind = kind.into();
+        self.named = named;
+        self
+    }
+
+    /// The node's kind: a rule name for named nodes, or the token text for
+    /// anonymous nodes such as `"("`.
+    #[must_use]
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    /// Whether the node corresponds to a named rule rather than a literal token.
+    #[must_use]
+    pub fn is_named(&self) -> bool {
+        self.named
+    }
+
+    /// Whether this is an `ERROR` node.
+    #[must_use]
+    pub fn is_error(&self) -> bool {
+        self.kind == ERROR_KIND
+    }
+
+    /// Whether this is a zero-width node inserted by error recovery.
+    #[must_use]
+    pub fn is_missing(&self) -> bool {
+        self.missing
+    }
+
+    /// Whether this node or any descendant is an `ERROR` or `MISSING` node.
+    #[must_use]
+    pub fn has_error(&self) -> bool {
+        self.is_error() || self.missing || self.children.iter().any(Node::has_error)
+    }
+
+    /// The field this node occupies in its parent, if any.
+    #[must_use]
+    pub fn field_name(&self) -> Option<&str> {
+        self.field.as_deref()
+    }
+
+    /// The hidden supertypes this node stands for in its parent, innermost
+    /// first, as `(supertype/kind)` query patterns check.
+    #[must_use]
+    pub fn supertypes(&self) -> &[String] {
+        &self.supertypes
+    }
+
+    /// The source span of this node.
+    #[must_use]
+    pub fn range(&self) -> Range {
+        self.range
+    }
+
+    /// Moves the node and its descendants to where their text is after
+    /// `edit`, as [`Range::edit`] moves each range. A node that holds the
+    /// edit grows or shrinks with it; its kind and children are kept, so a
+    /// node the edit touched no longer describes its text.
+    pub fn edit(&mut self, edit: &InputEdit) {
+        self.range.edit(edit);
+        for child in &mut self.children {
+            child.edit(edit);
+        }
+    }
+
+    /// The byte offset where this node starts.
+    #[must_use]
+    pub fn start_byte(&self) -> usize {
+        self.range.start_byte
+    }
+
+    /// The byte offset where this node ends.
+    #[must_use]
+    pub fn end_byte(&self) -> usize {
+        self.range.end_byte
+    }
+
+    /// The position where this node starts.
+    #[must_use]
+    pub fn start_position(&self) -> Point {
+        self.range.start_point
+    }
+
+    /// The position where this node ends.
+    #[must_use]
+    pub fn end_position(&self) -> Point {
+        self.range.end_point
+    }
+
+    /// The byte range of this node, for slicing the source.
+    #[must_use]
+    pub fn byte_range(&self) -> std::ops::Range<usize> {
+        self.range.start_byte..self.range.end_byte
+    }
+
+    /// The node's children, named and anonymous.
+    #[must_use]
+    pub fn children(&self) -> &[Node] {
+        &self.children
+    }
+
+    /// The number of children, named and anonymous.
+    #[must_use]
+    pub fn child_count(&self) -> usize {
+        self.children.len()
+    }
+
+    /// The child at `index`, if any.
+    #[must_use]
+    pub fn child(&self, index: usize) -> Option<&Node> {
+        self.children.get(index)
+    }
+
+    /// The named children, skipping anonymous tokens.
+    pub fn named_children(&self) -> impl Iterator<Item = &Node> {
+        self.children.iter().filter(|c| c.named)
+    }
+
+    /// The first child attached under the field `name`.
+    #[must_use]
+    pub fn child_by_field_name(&self, name: &str) -> Option<&Node> {
+        self.children
+            .iter()
+            .find(|c| c.field.as_deref() == Some(name))
+    }
+
+    /// Every child attached under the field `name`, in order.
+    pub fn children_by_field_name<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Node> {
+        self.children
+            .iter()
+            .filter(move |c| c.field.as_deref() == Some(name))
+    }
+
+    /// The source text of this node.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the node's bytes are not valid UTF-8.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the node's range lies outside `source`.
+    pub fn utf8_text<'s>(&self, source: &'s [u8]) -> Result<&'s str, std::str::Utf8Error> {
+        std::str::from_utf8(&source[self.byte_range()])
+    }
+
+    /// Renders the tree as an S-expression in tree-sitter's format.
+    ///
+    /// Only named nodes are shown, prefixed by their field name if they have
+    /// one. Missing tokens appear as `(MISSING kind)`, or `(MISSING "text")`
+    /// for anonymous tokens, and error leaves as `(ERROR)`.
+    #[must_use]
+    pub fn to_sexp(&self) -> String {
+        let mut out = String::new();
+        self.write_sexp(&mut out, true);
+        out
+    }
+
+    fn write_sexp(&self, out: &mut String, is_root: bool) {
+        let visible = self.named || self.missing;
+        if visible {
+            if !is_root {
+                out.push(' ');
+                if let Some(field) = &self.field {
+                    let _ = write!(out, "{field}: ");
+                }
+            }
+            if self.missing {
+                if self.named {
+                    let _ = write!(out, "(MISSING {}", self.kind);
+                } else {
+                    let _ = write!(out, "(MISSING \"{}\"", escape_kind(&self.kind));
+                }
+            } else {
+                let _ = write!(out, "({}", self.kind);
+            }
+        } else if is_root {
+            let _ = write!(out, "(\"{}\")", escape_kind(&self.kind));
+        }
+        for child in &self.children {
+            child.write_sexp(out, false);
+        }
+        if visible {
+            out.push(')');
+        }
+    }
+}
+
+/// A typed view of one kind of [`Node`].
+///
+/// Implementations are generated per grammar by
+/// [`codegen::ast`](crate::codegen::ast): each wraps a borrowed node and only
+/// accepts nodes of its own kind, or of its subtypes for supertypes.
+pub trait AstNode<'tree>: Sized {
+    /// Wraps `node` if it has the right kind.
+    fn cast(node: &'tree Node) -> Option<Self>;
+
+    /// The underlying node.
+    fn syntax(&self) -> &'tree Node;
+}
+
+/// Escapes an anonymous node kind for display inside double quotes.
+fn escape_kind(kind: &str) -> String {
+    let mut out = String::with_capacity(kind.len());
+    for c in kind.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a token node over `text` found at `start` in `source`.
+    fn token(source: &str, start: usize, text: &str, kind: &str, named: bool) -> Node {
+        Node::leaf(
+            kind,
+            named,
+            Range::from_bytes(source.as_bytes(), start..start + text.len()),
+        )
+    }
+
+    #[test]
+    fn test_sexp_shows_named_nodes_and_fields() {
+        let source = "{\"a\": 1}";
+        let pair = Node::branch(
+            "pair",
+            true,
+            vec![
+                token(source, 1, "\"a\"", "string", true).with_field("key"),
+                token(source, 4, ":", ":", false),
+                token(source, 6, "1", "number", true).with_field("value"),
+            ],
+        );
+        let object = Node::branch(
+            "object",
+            true,
+            vec![
+                token(source, 0, "{", "{", false),
+                pair,
+                token(source, 7, "}", "}", false),
+            ],
+        );
+        let document = Node::branch("document", true, vec![object]);
+
+        assert_eq!(
+            document.to_sexp(),
+            "(document (object (pair key: (string) value: (number))))"
+        );
+        assert_eq!(document.byte_range(), 0..8);
+        assert!(!document.has_error());
+    }
+
+    #[test]
+    fn test_sexp_error_and_missing() {
+        let source = "[1 2";
+        let array = Node::branch(
+            "array",
+            true,
+            vec![
+                token(source, 0, "[", "[", false),
+                token(source, 1, "1", "number", true),
+                Node::error(vec![token(source, 3, "2", "number", true)]),
+                Node::missing("]", false, 4, Point::new(0, 4)),
+            ],
+        );
+        let document = Node::branch("document", true, vec![array]);
+
+        assert_eq!(
+            document.to_sexp(),
+            "(document (array (number) (ERROR (number)) (MISSING \"]\")))"
+        );
+        assert!(document.has_error());
+        let missing = &document.children()[0].children()[3];
+        assert!(missing.is_missing() && !missing.is_error());
+        assert_eq!(missing.byte_range(), 4..4);
+    }
+
+    #[test]
+    fn test_missing_named_token() {
+        let node = Node::missing("identifier", true, 10, Point::new(2, 3));
+        assert_eq!(node.to_sexp(), "(MISSING identifier)");
+        assert_eq!(node.start_position(), Point::new(2, 3));
+    }
+
+    #[test]
+    fn test_range_points_and_text() {
+        let source = b"ab\ncd\n";
+        let range = Range::from_bytes(source, 1..4);
+        assert_eq!(range.start_point, Point::new(0, 1));
+        assert_eq!(range.end_point, Point::new(1, 1));
+        let node = Node::leaf("x", true, range);
+        assert_eq!(node.utf8_text(source).unwrap(), "b\nc");
+    }
+
+    #[test]
+    fn test_edit_moves_ranges_after_the_change() {
+        // "ab\ncd\n" becomes "ab\nXYZcd\n": three bytes inserted at (1, 0).
+        let edit = InputEdit {
+            start_byte: 3,
+            old_end_byte: 3,
+            new_end_byte: 6,
+            start_position: Point::new(1, 0),
+            old_end_position: Point::new(1, 0),
+            new_end_position: Point::new(1, 3),
+        };
+        let source = b"ab\ncd\n";
+        let mut node = Node::branch(
+            "pair",
+            true,
+            vec![
+                Node::leaf("a", true, Range::from_bytes(source, 0..2)),
+                Node::leaf("c", true, Range::from_bytes(source, 3..5)),
+            ],
+        );
+        node.edit(&edit);
+        let edited = b"ab\nXYZcd\n";
+        assert_eq!(node.children()[0].range(), Range::from_bytes(edited, 0..2));
+        assert_eq!(node.children()[1].range(), Range::from_bytes(edited, 6..8));
+        assert_eq!(node.range(), Range::from_bytes(edited, 0..8));
+
+        // Replacing "cd" with "e" leaves only the text around it covered.
+        let edit = InputEdit {
+            start_byte: 3,
+            old_end_byte: 5,
+            new_end_byte: 4,
+            start_position: Point::new(1, 0),
+            old_end_position: Point::new(1, 2),
+            new_end_position: Point::new(1, 1),
+        };
+        let mut range = Range::from_bytes(source, 1..4);
+        range.edit(&edit);
+        assert_eq!(range, Range::from_bytes(b"ab\ne\n", 1..3));
+        let mut range = Range::from_bytes(source, 4..6);
+        range.edit(&edit);
+        assert_eq!(range, Range::from_bytes(b"ab\ne\n", 4..5));
+    }
+}
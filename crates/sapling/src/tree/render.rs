@@ -0,0 +1,233 @@
+//! Rendering syntax trees for reading and for other tools.
+//!
+//! [`Node::to_sexp`] shows the shape of a tree; these show all of it,
+//! anonymous tokens included, with where each node is in the source.
+
+use super::Node;
+use crate::json::Value;
+use std::fmt::Write;
+
+/// Renders a tree with a node per line, indented by two spaces per level:
+/// its field and kind, its byte range, and its start and end points.
+/// Anonymous kinds are quoted and missing nodes marked, as in
+/// [`Node::to_sexp`].
+///
+/// ```text
+/// program 0..4 [0, 0] - [0, 4]
+///   call 0..3 [0, 0] - [0, 3]
+///     function: identifier 0..1 [0, 0] - [0, 1]
+///     "()" 1..3 [0, 1] - [0, 3]
+/// ```
+#[must_use]
+pub fn render_tree(node: &Node) -> String {
+    let mut out = String::new();
+    write_tree(&mut out, node, 0);
+    out
+}
+
+fn write_tree(out: &mut String, node: &Node, depth: usize) {
+    out.push_str(&"  ".repeat(depth));
+    if let Some(field) = node.field_name() {
+        let _ = write!(out, "{field}: ");
+    }
+    if node.is_missing() {
+        out.push_str("MISSING ");
+    }
+    if node.is_named() {
+        out.push_str(node.kind());
+    } else {
+        let _ = write!(out, "\"{}\"", super::escape_kind(node.kind()));
+    }
+    let (start, end) = (node.start_position(), node.end_position());
+    let _ = writeln!(
+        out,
+        " {}..{} [{}, {}] - [{}, {}]",
+        node.start_byte(),
+        node.end_byte(),
+        start.row,
+        start.column,
+        end.row,
+        end.column
+    );
+    for child in node.children() {
+        write_tree(out, child, depth + 1);
+    }
+}
+
+/// Renders a tree as compact JSON on one line. Each node is an object with
+/// its `kind`, whether it is `named`, its `field` and whether it is
+/// `missing` when it has or is, its `start_byte` and `end_byte`, its
+/// `start_point` and `end_point` as `row` and `column`, and its `children`.
+#[must_use]
+pub fn render_json(node: &Node) -> String {
+    json(node).to_string()
+}
+
+fn json(node: &Node) -> Value {
+    let point = |point: crate::input::Point| {
+        Value::Object(vec![
+            ("row".to_string(), integer(point.row)),
+            ("column".to_string(), integer(point.column)),
+        ])
+    };
+    let mut entries = vec![
+        ("kind".to_string(), Value::from(node.kind())),
+        ("named".to_string(), Value::from(node.is_named())),
+    ];
+    if let Some(field) = node.field_name() {
+        entries.push(("field".to_string(), Value::from(field)));
+    }
+    if node.is_missing() {
+        entries.push(("missing".to_string(), Value::from(true)));
+    }
+    entries.extend([
+        ("start_byte".to_string(), integer(node.start_byte())),
+        ("end_byte".to_string(), integer(node.end_byte())),
+        ("start_point".to_string(), point(node.start_position())),
+        ("end_point".to_string(), point(node.end_position())),
+        (
+            "children".to_string(),
+            Value::Array(node.children().iter().map(json).collect()),
+        ),
+    ]);
+    Value::Object(entries)
+}
+
+fn integer(n: usize) -> Value {
+    Value::Integer(i64::try_from(n).unwrap_or(i64::MAX))
+}
+
+/// Renders a tree as an XML element, without a declaration. Named nodes
+/// become elements with `field`, `srow`, `scol`, `erow` and `ecol`
+/// attributes, missing ones are empty elements marked `missing="true"`,
+/// and everything else is the escaped source text, so the element's text
+/// is the source the tree covers.
+#[must_use]
+pub fn render_xml(node: &Node, source: &[u8]) -> String {
+    let mut out = String::new();
+    write_xml(&mut out, node, source);
+    out
+}
+
+fn write_xml(out: &mut String, node: &Node, source: &[u8]) {
+    if !node.is_named() && !node.is_missing() {
+        escape_xml(out, &source[node.byte_range()]);
+        return;
+    }
+    let (start, end) = (node.start_position(), node.end_position());
+    let tag = if node.is_named() {
+        node.kind()
+    } else {
+        "MISSING"
+    };
+    let _ = write!(out, "<{tag}");
+    if let Some(field) = node.field_name() {
+        let _ = write!(out, " field=\"{field}\"");
+    }
+    if node.is_missing() {
+        out.push_str(" missing=\"true\"");
+        if !node.is_named() {
+            out.push_str(" kind=\"");
+            escape_xml(out, node.kind().as_bytes());
+            out.push('"');
+        }
+    }
+    let _ = write!(
+        out,
+        " srow=\"{}\" scol=\"{}\" erow=\"{}\" ecol=\"{}\"",
+        start.row, start.column, end.row, end.column
+    );
+    if node.children().is_empty() && node.start_byte() == node.end_byte() {
+        out.push_str("/>");
+        return;
+    }
+    out.push('>');
+    if node.children().is_empty() {
+        escape_xml(out, &source[node.byte_range()]);
+    }
+    // Text between children, such as whitespace, belongs to no node.
+    let mut at = node.start_byte();
+    for child in node.children() {
+        escape_xml(out, &source[at..child.start_byte().max(at)]);
+        write_xml(out, child, source);
+        at = at.max(child.end_byte());
+    }
+    if !node.children().is_empty() {
+        escape_xml(out, &source[at..node.end_byte().max(at)]);
+    }
+    let _ = write!(out, "</{tag}>");
+}
+
+/// Writes text with the characters XML reserves escaped.
+fn escape_xml(out: &mut String, text: &[u8]) {
+    for c in String::from_utf8_lossy(text).chars() {
+        match c {
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' => out.push_str("&amp;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree::Range;
+
+    /// The tree of `f()` in a grammar of calls.
+    fn call() -> Node {
+        let source = b"f()";
+        let identifier =
+            Node::leaf("identifier", true, Range::from_bytes(source, 0..1)).with_field("function");
+        let parens = Node::leaf("()", false, Range::from_bytes(source, 1..3));
+        Node::branch("call", true, vec![identifier, parens])
+    }
+
+    #[test]
+    fn test_render_tree() {
+        assert_eq!(
+            render_tree(&call()),
+            "call 0..3 [0, 0] - [0, 3]\n  \
+             function: identifier 0..1 [0, 0] - [0, 1]\n  \
+             \"()\" 1..3 [0, 1] - [0, 3]\n"
+        );
+    }
+
+    #[test]
+    fn test_render_json() {
+        let json = render_json(&call());
+        assert!(json.starts_with(
+            r#"{"kind":"call","named":true,"start_byte":0,"end_byte":3,"start_point":{"row":0,"column":0}"#
+        ));
+        assert!(json.contains(r#"{"kind":"identifier","named":true,"field":"function","#));
+    }
+
+    #[test]
+    fn test_render_xml() {
+        let source = b"f( ";
+        let program = Node::branch(
+            "program",
+            true,
+            vec![Node::branch(
+                "call",
+                true,
+                vec![
+                    Node::leaf("identifier", true, Range::from_bytes(source, 0..1)),
+                    Node::leaf("(", false, Range::from_bytes(source, 1..2)),
+                    Node::missing(")", false, 3, Range::from_bytes(source, 3..3).start_point),
+                ],
+            )],
+        );
+        assert_eq!(
+            render_xml(&program, source),
+            "<program srow=\"0\" scol=\"0\" erow=\"0\" ecol=\"3\">\
+             <call srow=\"0\" scol=\"0\" erow=\"0\" ecol=\"3\">\
+             <identifier srow=\"0\" scol=\"0\" erow=\"0\" ecol=\"1\">f</identifier>( \
+             <MISSING missing=\"true\" kind=\")\" srow=\"0\" scol=\"3\" erow=\"0\" ecol=\"3\"/>\
+             </call></program>"
+        );
+    }
+}
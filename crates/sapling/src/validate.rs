@@ -2,11 +2,21 @@
 //!
 //! This module performs structural checks over parsed [`Grammar`](crate::grammar::Grammar)
 //! definitions, such as verifying symbol references, ensuring all rules are reachable,
-//! detecting left recursion, and confirming precedence consistency. It is used by
+//! detecting left recursion, and noting rules with several precedence levels. It is used by
 //! the `sapling` CLI and internal compiler passes to catch errors early.
+//!
+//! [`validate`](fn@crate::validate::validate) stops at the first error,
+//! while [`diagnostics`](crate::validate::diagnostics) collects everything
+//! every check finds, warnings and notes included, for reports such as the
+//! ones in [`report`](crate::validate::report).
 
+use crate::grammar::prepare::used_rules;
 use crate::grammar::{Grammar, Rule, RuleType};
-use std::collections::{HashMap, HashSet};
+use indexmap::IndexMap;
+use std::collections::HashSet;
+use std::fmt;
+
+pub mod report;
 
 /// Represents a validation failure encountered when checking a grammar.
 ///
@@ -26,6 +36,136 @@ impl ValidationError {
     }
 }
 
+/// How serious a [`Diagnostic`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// The grammar cannot be used.
+    Error,
+    /// The grammar can be used, but probably doesn't mean what it says.
+    Warning,
+    /// Something worth knowing that is not a problem.
+    Note,
+}
+
+impl Severity {
+    /// The lowercase name, which is also the SARIF `level`.
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The check that found a [`Diagnostic`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Check {
+    /// The file is not a grammar.
+    InvalidGrammar,
+    /// The grammar has no rules.
+    NoRules,
+    /// A rule refers to a symbol that is neither a rule nor external.
+    UndefinedSymbol,
+    /// A rule cannot be reached from the start rule, or from the extras,
+    /// externals, supertypes, word rule or reserved words.
+    UnreachableRule,
+    /// A rule is given more than one precedence level, as operator rules
+    /// usually are.
+    MixedPrecedence,
+    /// A rule refers to itself first.
+    LeftRecursion,
+}
+
+impl Check {
+    /// Every check, in the order their diagnostics are reported.
+    pub const ALL: [Check; 6] = [
+        Check::InvalidGrammar,
+        Check::NoRules,
+        Check::UndefinedSymbol,
+        Check::UnreachableRule,
+        Check::MixedPrecedence,
+        Check::LeftRecursion,
+    ];
+
+    /// A short kebab-case identifier, such as `undefined-symbol`.
+    #[must_use]
+    pub fn code(self) -> &'static str {
+        match self {
+            Check::InvalidGrammar => "invalid-grammar",
+            Check::NoRules => "no-rules",
+            Check::UndefinedSymbol => "undefined-symbol",
+            Check::UnreachableRule => "unreachable-rule",
+            Check::MixedPrecedence => "mixed-precedence",
+            Check::LeftRecursion => "left-recursion",
+        }
+    }
+
+    /// What the check looks for, in a sentence.
+    #[must_use]
+    pub fn description(self) -> &'static str {
+        match self {
+            Check::InvalidGrammar => "The file is not a valid grammar.json.",
+            Check::NoRules => "The grammar has no rules.",
+            Check::UndefinedSymbol => "A rule refers to a symbol that is not defined.",
+            Check::UnreachableRule => {
+                "A rule cannot be reached from the start rule or the grammar's other roots."
+            }
+            Check::MixedPrecedence => "A rule is given more than one precedence level.",
+            Check::LeftRecursion => "A rule is left-recursive.",
+        }
+    }
+
+    /// How serious what the check finds is.
+    #[must_use]
+    pub fn severity(self) -> Severity {
+        match self {
+            Check::InvalidGrammar | Check::NoRules | Check::UndefinedSymbol => Severity::Error,
+            Check::UnreachableRule => Severity::Warning,
+            Check::MixedPrecedence | Check::LeftRecursion => Severity::Note,
+        }
+    }
+}
+
+/// Something a check found in a grammar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The check that found it.
+    pub check: Check,
+    /// What was found.
+    pub message: String,
+    /// The rule it was found in, if any.
+    pub rule: Option<String>,
+    /// The 1-based line of the grammar file it is on, if known. Grammars
+    /// don't record where they were read from, so this is only set by
+    /// [`report`].
+    pub line: Option<usize>,
+}
+
+impl Diagnostic {
+    fn new(check: Check, message: impl Into<String>, rule: Option<&str>) -> Self {
+        Self {
+            check,
+            message: message.into(),
+            rule: rule.map(str::to_string),
+            line: None,
+        }
+    }
+
+    /// The severity of the check that found it.
+    #[must_use]
+    pub fn severity(&self) -> Severity {
+        self.check.severity()
+    }
+}
+
 /// Performs semantic validation of a parsed [`Grammar`].
 ///
 /// This function runs several consistency passes over the grammar:
@@ -33,56 +173,72 @@ impl ValidationError {
 /// - Checks that all referenced symbols are defined.
 /// - Warns about unreachable rules.
 /// - Detects immediate left recursion.
-/// - Verifies precedence consistency.
+/// - Notes rules with several precedence levels.
+///
+/// Warnings and notes don't fail validation; [`diagnostics`] returns them.
 ///
 /// # Errors
 ///
 /// Returns a [`ValidationError`] if any structural rule violation is detected.
 pub fn validate(grammar: &Grammar) -> Result<(), ValidationError> {
-    // Check for undefined symbol references
-    check_undefined_symbols(grammar)?;
-
-    // Check for unreachable rules
-    check_unreachable_rules(grammar)?;
-
-    // Detect problematic left recursion
-    check_left_recursion(grammar);
-
-    // Validate precedence usage
-    check_precedence(grammar);
+    match diagnostics(grammar)
+        .into_iter()
+        .find(|diagnostic| diagnostic.severity() == Severity::Error)
+    {
+        Some(error) => Err(ValidationError::new(error.message)),
+        None => Ok(()),
+    }
+}
 
-    Ok(())
+/// Runs every check over a grammar, returning all they find in the order
+/// the checks run.
+#[must_use]
+pub fn diagnostics(grammar: &Grammar) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    check_undefined_symbols(grammar, &mut diagnostics);
+    check_unreachable_rules(grammar, &mut diagnostics);
+    check_precedence(grammar, &mut diagnostics);
+    check_left_recursion(grammar, &mut diagnostics);
+    diagnostics
 }
 
-fn check_undefined_symbols(grammar: &Grammar) -> Result<(), ValidationError> {
-    let defined: HashSet<_> = grammar.rules.keys().collect();
+fn check_undefined_symbols(grammar: &Grammar, diagnostics: &mut Vec<Diagnostic>) {
+    // External tokens are defined by the scanner rather than a rule.
+    let externals = grammar
+        .externals
+        .iter()
+        .flatten()
+        .filter(|rule| rule.rule_type == RuleType::Symbol)
+        .filter_map(|rule| rule.name.as_ref());
+    let defined: HashSet<_> = grammar.rules.keys().chain(externals).collect();
 
     for (rule_name, rule) in &grammar.rules {
-        check_rule_symbols(rule, &defined, rule_name)?;
+        check_rule_symbols(rule, &defined, rule_name, diagnostics);
     }
-
-    Ok(())
 }
 
 fn check_rule_symbols(
     rule: &Rule,
     defined: &HashSet<&String>,
     context: &str,
-) -> Result<(), ValidationError> {
+    diagnostics: &mut Vec<Diagnostic>,
+) {
     match rule.rule_type {
         RuleType::Symbol => {
             if let Some(name) = &rule.name {
                 if !defined.contains(name) {
-                    return Err(ValidationError::new(format!(
-                        "undefined symbol '{name}' referenced in rule '{context}'"
-                    )));
+                    diagnostics.push(Diagnostic::new(
+                        Check::UndefinedSymbol,
+                        format!("undefined symbol '{name}' referenced in rule '{context}'"),
+                        Some(context),
+                    ));
                 }
             }
         }
 
         RuleType::Choice | RuleType::Seq => {
             for member in &rule.members {
-                check_rule_symbols(member, defined, context)?;
+                check_rule_symbols(member, defined, context, diagnostics);
             }
         }
 
@@ -91,10 +247,12 @@ fn check_rule_symbols(
         | RuleType::Prec
         | RuleType::PrecLeft
         | RuleType::PrecRight
+        | RuleType::PrecDynamic
         | RuleType::Field
-        | RuleType::Alias => {
+        | RuleType::Alias
+        | RuleType::Reserved => {
             if let Some(content) = &rule.content {
-                check_rule_symbols(content, defined, context)?;
+                check_rule_symbols(content, defined, context, diagnostics);
             }
         }
 
@@ -102,36 +260,26 @@ fn check_rule_symbols(
         | RuleType::String
         | RuleType::Pattern
         | RuleType::Token
-        | RuleType::ImmediateToken
-        | RuleType::Reserved
-        | RuleType::PrecDynamic => {
+        | RuleType::ImmediateToken => {
             // terminals / others: nothing to traverse
         }
     }
-    Ok(())
 }
 
-fn check_unreachable_rules(grammar: &Grammar) -> Result<(), ValidationError> {
-    // Start from the first rule (convention: entry point)
-    let entry_point = grammar
-        .rules
-        .keys()
-        .next()
-        .ok_or_else(|| ValidationError::new("grammar has no rules"))?;
-
-    let mut reachable = HashSet::new();
-    let mut to_visit = vec![entry_point.clone()];
-
-    while let Some(rule_name) = to_visit.pop() {
-        if !reachable.insert(rule_name.clone()) {
-            continue; // Already visited
-        }
-
-        if let Some(rule) = grammar.rules.get(&rule_name) {
-            collect_referenced_symbols(rule, &mut to_visit);
-        }
+fn check_unreachable_rules(grammar: &Grammar, diagnostics: &mut Vec<Diagnostic>) {
+    if grammar.rules.is_empty() {
+        diagnostics.push(Diagnostic::new(
+            Check::NoRules,
+            "grammar has no rules",
+            None,
+        ));
+        return;
     }
 
+    // Extras, externals, supertypes, the word rule and reserved words are
+    // used even when no rule refers to them, as they are when preparing
+    // the grammar.
+    let reachable = used_rules(grammar);
     for rule_name in grammar.rules.keys() {
         // grammar.inline is Option<Vec<String>> in grammar.rs: handle safely
         let inline_contains = grammar
@@ -139,15 +287,19 @@ fn check_unreachable_rules(grammar: &Grammar) -> Result<(), ValidationError> {
             .as_ref()
             .is_some_and(|v| v.contains(rule_name));
 
-        if !reachable.contains(rule_name) && !inline_contains {
-            eprintln!("warning: unreachable rule '{rule_name}'");
+        if !reachable.contains(rule_name.as_str()) && !inline_contains {
+            diagnostics.push(Diagnostic::new(
+                Check::UnreachableRule,
+                format!("unreachable rule '{rule_name}'"),
+                Some(rule_name),
+            ));
         }
     }
-
-    Ok(())
 }
 
-fn collect_referenced_symbols(rule: &Rule, symbols: &mut Vec<String>) {
+/// Collects the names of the rules `rule` refers to, in order and with
+/// repeats.
+pub(crate) fn collect_referenced_symbols(rule: &Rule, symbols: &mut Vec<String>) {
     match rule.rule_type {
         RuleType::Symbol => {
             if let Some(name) = &rule.name {
@@ -166,8 +318,10 @@ fn collect_referenced_symbols(rule: &Rule, symbols: &mut Vec<String>) {
         | RuleType::Prec
         | RuleType::PrecLeft
         | RuleType::PrecRight
+        | RuleType::PrecDynamic
         | RuleType::Field
-        | RuleType::Alias => {
+        | RuleType::Alias
+        | RuleType::Reserved => {
             if let Some(content) = &rule.content {
                 collect_referenced_symbols(content, symbols);
             }
@@ -177,22 +331,24 @@ fn collect_referenced_symbols(rule: &Rule, symbols: &mut Vec<String>) {
         | RuleType::String
         | RuleType::Pattern
         | RuleType::Token
-        | RuleType::ImmediateToken
-        | RuleType::Reserved
-        | RuleType::PrecDynamic => {
+        | RuleType::ImmediateToken => {
             // nothing to collect
         }
     }
 }
 
-fn check_left_recursion(grammar: &Grammar) {
+fn check_left_recursion(grammar: &Grammar, diagnostics: &mut Vec<Diagnostic>) {
     // Detect immediate left recursion that lalrpop can't handle
     // lalrpop handles left recursion just fine, but we document it
 
     for (rule_name, rule) in &grammar.rules {
         if has_immediate_left_recursion(rule, rule_name) {
             // This is actually fine for lalrpop, just document it
-            eprintln!("info: rule '{rule_name}' has left recursion (handled by lalrpop)");
+            diagnostics.push(Diagnostic::new(
+                Check::LeftRecursion,
+                format!("rule '{rule_name}' has left recursion (handled by lalrpop)"),
+                Some(rule_name),
+            ));
         }
     }
 }
@@ -232,23 +388,30 @@ fn has_immediate_left_recursion(rule: &Rule, target: &str) -> bool {
     }
 }
 
-fn check_precedence(grammar: &Grammar) {
+fn check_precedence(grammar: &Grammar, diagnostics: &mut Vec<Diagnostic>) {
     // Validate that precedence is used consistently
-    let mut prec_levels: HashMap<String, Vec<i32>> = HashMap::new();
+    let mut prec_levels: IndexMap<String, Vec<i32>> = IndexMap::new();
 
     for (rule_name, rule) in &grammar.rules {
         collect_precedence_levels(rule, &mut prec_levels, rule_name);
     }
 
-    // Check for conflicting precedence declarations
-    for (rule, levels) in &prec_levels {
+    // Grading the alternatives of one rule is the usual way to write
+    // operators, so several distinct levels are only worth a note.
+    for (rule, levels) in &mut prec_levels {
+        let mut seen = HashSet::new();
+        levels.retain(|&level| seen.insert(level));
         if levels.len() > 1 {
-            eprintln!("warning: rule '{rule}' has multiple precedence levels: {levels:?}");
+            diagnostics.push(Diagnostic::new(
+                Check::MixedPrecedence,
+                format!("rule '{rule}' has multiple precedence levels: {levels:?}"),
+                Some(rule),
+            ));
         }
     }
 }
 
-fn collect_precedence_levels(rule: &Rule, levels: &mut HashMap<String, Vec<i32>>, context: &str) {
+fn collect_precedence_levels(rule: &Rule, levels: &mut IndexMap<String, Vec<i32>>, context: &str) {
     match rule.rule_type {
         RuleType::Prec | RuleType::PrecLeft | RuleType::PrecRight | RuleType::PrecDynamic => {
             // Use the helper method in grammar.rs if present, else read value via rule.value
@@ -275,3 +438,149 @@ fn collect_precedence_levels(rule: &Rule, levels: &mut HashMap<String, Vec<i32>>
         _ => {}
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_grammar;
+
+    /// A grammar whose `program` wraps `inner` in `wrapper`.
+    fn wrapping(wrapper: &str, inner: &str) -> Grammar {
+        parse_grammar(&format!(
+            r#"{{
+                "name": "wrapped",
+                "rules": {{
+                    "program": {{{wrapper}, "content": {{"type": "SYMBOL", "name": "{inner}"}}}},
+                    "item": {{"type": "STRING", "value": "x"}}
+                }}
+            }}"#
+        ))
+        .unwrap()
+    }
+
+    const PREC_DYNAMIC: &str = r#""type": "PREC_DYNAMIC", "value": 1"#;
+    const RESERVED: &str = r#""type": "RESERVED", "context_name": "global""#;
+
+    fn checks(grammar: &Grammar) -> Vec<(Check, String)> {
+        diagnostics(grammar)
+            .into_iter()
+            .map(|diagnostic| (diagnostic.check, diagnostic.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_undefined_symbols_under_prec_dynamic() {
+        assert_eq!(
+            checks(&wrapping(PREC_DYNAMIC, "missing")),
+            [
+                (
+                    Check::UndefinedSymbol,
+                    "undefined symbol 'missing' referenced in rule 'program'".to_string()
+                ),
+                (
+                    Check::UnreachableRule,
+                    "unreachable rule 'item'".to_string()
+                ),
+            ]
+        );
+        assert_eq!(checks(&wrapping(PREC_DYNAMIC, "item")), []);
+    }
+
+    #[test]
+    fn test_undefined_symbols_under_reserved() {
+        assert_eq!(
+            checks(&wrapping(RESERVED, "missing")),
+            [
+                (
+                    Check::UndefinedSymbol,
+                    "undefined symbol 'missing' referenced in rule 'program'".to_string()
+                ),
+                (
+                    Check::UnreachableRule,
+                    "unreachable rule 'item'".to_string()
+                ),
+            ]
+        );
+        assert_eq!(checks(&wrapping(RESERVED, "item")), []);
+    }
+
+    #[test]
+    fn test_rules_used_outside_the_start_rule_are_reachable() {
+        let grammar = parse_grammar(
+            r#"{
+                "name": "roots",
+                "word": "identifier",
+                "rules": {
+                    "program": {"type": "SYMBOL", "name": "expression"},
+                    "expression": {"type": "SYMBOL", "name": "identifier"},
+                    "identifier": {"type": "PATTERN", "value": "[a-z]+"},
+                    "comment": {"type": "PATTERN", "value": "//.*"},
+                    "heredoc": {"type": "STRING", "value": "<<"},
+                    "keyword": {"type": "STRING", "value": "if"},
+                    "unused": {"type": "STRING", "value": "?"}
+                },
+                "extras": [{"type": "SYMBOL", "name": "comment"}],
+                "externals": [{"type": "SYMBOL", "name": "heredoc"}],
+                "reserved": {"global": [{"type": "SYMBOL", "name": "keyword"}]}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            checks(&grammar),
+            [(
+                Check::UnreachableRule,
+                "unreachable rule 'unused'".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_precedence_levels_are_deduplicated_and_noted() {
+        let prec = |value: i32| format!(r#""type": "PREC", "value": {value}"#);
+        let twice = parse_grammar(&format!(
+            r#"{{
+                "name": "twice",
+                "rules": {{
+                    "program": {{
+                        "type": "CHOICE",
+                        "members": [
+                            {{{}, "content": {{"type": "STRING", "value": "a"}}}},
+                            {{{}, "content": {{"type": "STRING", "value": "b"}}}}
+                        ]
+                    }}
+                }}
+            }}"#,
+            prec(-1),
+            prec(-1)
+        ))
+        .unwrap();
+        assert_eq!(checks(&twice), []);
+
+        let graded = parse_grammar(&format!(
+            r#"{{
+                "name": "graded",
+                "rules": {{
+                    "program": {{
+                        "type": "CHOICE",
+                        "members": [
+                            {{{}, "content": {{"type": "STRING", "value": "a"}}}},
+                            {{{}, "content": {{"type": "STRING", "value": "b"}}}},
+                            {{{}, "content": {{"type": "STRING", "value": "c"}}}}
+                        ]
+                    }}
+                }}
+            }}"#,
+            prec(1),
+            prec(2),
+            prec(1)
+        ))
+        .unwrap();
+        let found = diagnostics(&graded);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].severity(), Severity::Note);
+        assert_eq!(
+            found[0].message,
+            "rule 'program' has multiple precedence levels: [1, 2]"
+        );
+    }
+}
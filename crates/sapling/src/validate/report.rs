@@ -0,0 +1,383 @@
+//! Rendering diagnostics for people and for tools.
+//!
+//! A [`GrammarReport`] holds what was found in one grammar file, with the
+//! line of each diagnostic's rule where it can be found. Reports render as
+//! text in the style of compiler errors, as JSON, or as SARIF 2.1.0, the
+//! format GitHub code scanning reads.
+
+use super::{diagnostics, Check, Diagnostic, Severity};
+use crate::grammar::parse_grammar;
+use crate::json::{self, Value};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// The SARIF schema reports are written against.
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// The diagnostics of one grammar file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrammarReport {
+    /// The path of the file, as it is to be shown.
+    pub path: String,
+    /// What the checks found, in the order of [`Check::ALL`].
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl GrammarReport {
+    /// Checks the `grammar.json` at `path`, whose contents are `source`. A
+    /// file that is not a grammar gets a single
+    /// [`Check::InvalidGrammar`] error.
+    #[must_use]
+    pub fn check(path: impl Into<String>, source: &str) -> Self {
+        let diagnostics = match parse_grammar(source) {
+            Ok(grammar) => {
+                let lines = rule_lines(source);
+                diagnostics(&grammar)
+                    .into_iter()
+                    .map(|mut diagnostic| {
+                        diagnostic.line = diagnostic
+                            .rule
+                            .as_ref()
+                            .and_then(|rule| lines.get(rule).copied());
+                        diagnostic
+                    })
+                    .collect()
+            }
+            Err(error) => vec![Diagnostic::new(
+                Check::InvalidGrammar,
+                error.to_string(),
+                None,
+            )],
+        };
+        Self {
+            path: path.into(),
+            diagnostics,
+        }
+    }
+
+    /// The number of diagnostics of a severity.
+    #[must_use]
+    pub fn count(&self, severity: Severity) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity() == severity)
+            .count()
+    }
+
+    /// Renders the diagnostics as text, each as its severity, check and
+    /// message on one line and where it is on the next:
+    ///
+    /// ```text
+    /// error[undefined-symbol]: undefined symbol 'expr' referenced in rule 'program'
+    ///   --> grammar.json:4
+    /// ```
+    #[must_use]
+    pub fn to_human(&self) -> String {
+        let mut out = String::new();
+        for diagnostic in &self.diagnostics {
+            let _ = writeln!(
+                out,
+                "{}[{}]: {}",
+                diagnostic.severity(),
+                diagnostic.check.code(),
+                diagnostic.message
+            );
+            let _ = match diagnostic.line {
+                Some(line) => writeln!(out, "  --> {}:{line}", self.path),
+                None => writeln!(out, "  --> {}", self.path),
+            };
+        }
+        out
+    }
+}
+
+/// Renders reports as a JSON array with an object per file, holding its
+/// `path` and its `diagnostics`.
+#[must_use]
+pub fn to_json(reports: &[GrammarReport]) -> String {
+    let files = reports
+        .iter()
+        .map(|report| {
+            let diagnostics = report
+                .diagnostics
+                .iter()
+                .map(|diagnostic| {
+                    let mut entries = vec![
+                        (
+                            "severity".to_string(),
+                            Value::from(diagnostic.severity().as_str()),
+                        ),
+                        ("code".to_string(), Value::from(diagnostic.check.code())),
+                        (
+                            "message".to_string(),
+                            Value::from(diagnostic.message.as_str()),
+                        ),
+                    ];
+                    if let Some(rule) = &diagnostic.rule {
+                        entries.push(("rule".to_string(), Value::from(rule.as_str())));
+                    }
+                    if let Some(line) = diagnostic.line {
+                        entries.push(("line".to_string(), integer(line)));
+                    }
+                    Value::Object(entries)
+                })
+                .collect();
+            Value::Object(vec![
+                ("path".to_string(), Value::from(report.path.as_str())),
+                ("diagnostics".to_string(), Value::Array(diagnostics)),
+            ])
+        })
+        .collect();
+    Value::Array(files).to_pretty_string()
+}
+
+/// Renders reports as a SARIF 2.1.0 log with a single run, describing each
+/// check that found something as a rule of the `sapling` tool.
+#[must_use]
+pub fn to_sarif(reports: &[GrammarReport]) -> String {
+    let found: Vec<Check> = Check::ALL
+        .into_iter()
+        .filter(|check| {
+            reports
+                .iter()
+                .flat_map(|report| &report.diagnostics)
+                .any(|diagnostic| diagnostic.check == *check)
+        })
+        .collect();
+    let rules = found
+        .iter()
+        .map(|check| {
+            Value::Object(vec![
+                ("id".to_string(), Value::from(check.code())),
+                ("shortDescription".to_string(), text(check.description())),
+                (
+                    "defaultConfiguration".to_string(),
+                    Value::Object(vec![(
+                        "level".to_string(),
+                        Value::from(check.severity().as_str()),
+                    )]),
+                ),
+            ])
+        })
+        .collect();
+
+    let mut results = Vec::new();
+    for report in reports {
+        for diagnostic in &report.diagnostics {
+            let mut location = vec![(
+                "artifactLocation".to_string(),
+                Value::Object(vec![(
+                    "uri".to_string(),
+                    Value::from(report.path.replace('\\', "/")),
+                )]),
+            )];
+            if let Some(line) = diagnostic.line {
+                location.push((
+                    "region".to_string(),
+                    Value::Object(vec![("startLine".to_string(), integer(line))]),
+                ));
+            }
+            let rule_index = found
+                .iter()
+                .position(|check| *check == diagnostic.check)
+                .unwrap_or_default();
+            results.push(Value::Object(vec![
+                ("ruleId".to_string(), Value::from(diagnostic.check.code())),
+                ("ruleIndex".to_string(), integer(rule_index)),
+                (
+                    "level".to_string(),
+                    Value::from(diagnostic.severity().as_str()),
+                ),
+                ("message".to_string(), text(&diagnostic.message)),
+                (
+                    "locations".to_string(),
+                    Value::Array(vec![Value::Object(vec![(
+                        "physicalLocation".to_string(),
+                        Value::Object(location),
+                    )])]),
+                ),
+            ]));
+        }
+    }
+
+    let driver = Value::Object(vec![
+        ("name".to_string(), Value::from("sapling")),
+        (
+            "informationUri".to_string(),
+            Value::from(env!("CARGO_PKG_REPOSITORY")),
+        ),
+        (
+            "version".to_string(),
+            Value::from(env!("CARGO_PKG_VERSION")),
+        ),
+        ("rules".to_string(), Value::Array(rules)),
+    ]);
+    let run = Value::Object(vec![
+        (
+            "tool".to_string(),
+            Value::Object(vec![("driver".to_string(), driver)]),
+        ),
+        ("results".to_string(), Value::Array(results)),
+    ]);
+    Value::Object(vec![
+        ("$schema".to_string(), Value::from(SARIF_SCHEMA)),
+        ("version".to_string(), Value::from("2.1.0")),
+        ("runs".to_string(), Value::Array(vec![run])),
+    ])
+    .to_pretty_string()
+}
+
+/// A SARIF message object.
+fn text(text: &str) -> Value {
+    Value::Object(vec![("text".to_string(), Value::from(text))])
+}
+
+fn integer(n: usize) -> Value {
+    Value::Integer(i64::try_from(n).unwrap_or(i64::MAX))
+}
+
+/// The 1-based line of each rule's key in the `rules` object of a
+/// `grammar.json`, found by walking the JSON so that keys of the same name
+/// elsewhere, such as a rule's `type` or `name`, are passed over.
+fn rule_lines(source: &str) -> HashMap<String, usize> {
+    let mut lines = HashMap::new();
+    // Whether each open container is an object.
+    let mut containers = Vec::new();
+    let mut expect_key = false;
+    let mut top_key = None;
+    let mut line = 1;
+    let mut chars = source.char_indices();
+    while let Some((at, c)) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            '{' | '[' => {
+                containers.push(c == '{');
+                expect_key = c == '{';
+            }
+            '}' | ']' => {
+                containers.pop();
+                expect_key = false;
+            }
+            ',' => expect_key = containers.last() == Some(&true),
+            '"' => {
+                let mut end = source.len();
+                let mut escaped = false;
+                for (i, c) in chars.by_ref() {
+                    if escaped {
+                        escaped = false;
+                    } else if c == '\\' {
+                        escaped = true;
+                    } else if c == '"' {
+                        end = i + 1;
+                        break;
+                    }
+                }
+                if !std::mem::take(&mut expect_key) {
+                    continue;
+                }
+                let key = json::parse(&source[at..end])
+                    .ok()
+                    .and_then(|key| key.as_str().map(str::to_string));
+                match containers.len() {
+                    1 => top_key = key,
+                    2 if top_key.as_deref() == Some("rules") => {
+                        if let Some(key) = key {
+                            lines.entry(key).or_insert(line);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRAMMAR: &str = r#"{
+  "name": "broken",
+  "rules": {
+    "program": {"type": "SYMBOL", "name": "statement"},
+    "unused" : {"type": "STRING", "value": "x"}
+  }
+}"#;
+
+    #[test]
+    fn test_rules_are_located() {
+        let report = GrammarReport::check("grammar.json", GRAMMAR);
+        let located: Vec<_> = report
+            .diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.check, diagnostic.line))
+            .collect();
+        assert_eq!(
+            located,
+            [
+                (Check::UndefinedSymbol, Some(4)),
+                (Check::UnreachableRule, Some(5)),
+            ]
+        );
+        assert_eq!(
+            report.to_human(),
+            "error[undefined-symbol]: undefined symbol 'statement' referenced in rule 'program'\n  \
+             --> grammar.json:4\n\
+             warning[unreachable-rule]: unreachable rule 'unused'\n  --> grammar.json:5\n"
+        );
+    }
+
+    #[test]
+    fn test_rules_named_like_keys_are_located() {
+        let source = r#"{
+  "name": "keys",
+  "rules": {
+    "program": {"type": "SYMBOL", "name": "value"},
+    "value": {"type": "SYMBOL", "name": "type"},
+    "type": {"type": "STRING", "value": "\\\"name\\\": 1"},
+    "name": {"type": "STRING", "value": "x"}
+  }
+}"#;
+        let report = GrammarReport::check("grammar.json", source);
+        assert_eq!(
+            report
+                .diagnostics
+                .iter()
+                .map(|diagnostic| (diagnostic.rule.as_deref(), diagnostic.line))
+                .collect::<Vec<_>>(),
+            [(Some("name"), Some(7))]
+        );
+    }
+
+    #[test]
+    fn test_invalid_json_is_an_error() {
+        let report = GrammarReport::check("grammar.json", "{");
+        assert_eq!(report.count(Severity::Error), 1);
+        assert_eq!(report.diagnostics[0].check, Check::InvalidGrammar);
+        assert_eq!(report.diagnostics[0].line, None);
+    }
+
+    #[test]
+    fn test_sarif_lists_the_checks_found() {
+        let sarif = to_sarif(&[GrammarReport::check("dir\\grammar.json", GRAMMAR)]);
+        let log = json::parse(&sarif).unwrap();
+        let run = &log.get("runs").unwrap().as_array().unwrap()[0];
+        let rules = run
+            .get("tool")
+            .and_then(|tool| tool.get("driver"))
+            .and_then(|driver| driver.get("rules"))
+            .and_then(Value::as_array)
+            .unwrap();
+        assert_eq!(rules.len(), 2);
+        let results = run.get("results").and_then(Value::as_array).unwrap();
+        assert_eq!(
+            results[1].get("ruleIndex"),
+            Some(&Value::Integer(1)),
+            "{sarif}"
+        );
+        assert!(sarif.contains("\"uri\": \"dir/grammar.json\""));
+        assert!(sarif.contains("\"startLine\": 5"));
+    }
+}
@@ -0,0 +1,81 @@
+#![allow(missing_docs)]
+//! Typed AST generation, checked against a generated file that is also
+//! compiled here.
+
+use sapling::{parse_grammar, AstNode, Node, Range};
+use std::fs;
+use std::path::Path;
+
+#[allow(dead_code)]
+mod calc {
+    include!("fixtures/ast/ast.rs");
+}
+
+use calc::{BinaryOperator, Expression, Primary, Program, ProgramChild};
+
+fn fixture(name: &str) -> String {
+    fs::read_to_string(
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/ast")
+            .join(name),
+    )
+    .unwrap()
+}
+
+const SOURCE: &[u8] = b"let x = a + 1;";
+
+fn leaf(kind: &str, named: bool, bytes: std::ops::Range<usize>) -> Node {
+    Node::leaf(kind, named, Range::from_bytes(SOURCE, bytes))
+}
+
+#[test]
+fn generated_ast_is_up_to_date() {
+    let grammar = parse_grammar(&fixture("grammar.json")).unwrap();
+    let generated = sapling::codegen::ast::generate(&grammar).unwrap();
+    assert!(
+        generated == fixture("ast.rs"),
+        "tests/fixtures/ast/ast.rs is stale; regenerate it from grammar.json"
+    );
+}
+
+#[test]
+fn generated_ast_reads_a_tree() {
+    let binary = Node::branch(
+        "binary",
+        true,
+        vec![
+            leaf("identifier", true, 8..9).with_field("left"),
+            leaf("+", false, 10..11).with_field("operator"),
+            leaf("number", true, 12..13).with_field("right"),
+        ],
+    );
+    let statement = Node::branch(
+        "let",
+        true,
+        vec![
+            leaf("let", false, 0..3),
+            leaf("identifier", true, 4..5).with_field("name"),
+            leaf("=", false, 6..7),
+            binary.with_field("value"),
+            leaf(";", false, 13..14),
+        ],
+    );
+    let tree = Node::branch("program", true, vec![statement]);
+
+    let program = Program::cast(&tree).unwrap();
+    let [ProgramChild::Let(statement)] = program.children()[..] else {
+        panic!("expected a single let statement");
+    };
+    assert_eq!(statement.name().unwrap().syntax().byte_range(), 4..5);
+    assert!(statement.r#type().is_none());
+    let Some(Expression::Binary(binary)) = statement.value() else {
+        panic!("expected a binary expression");
+    };
+    assert!(matches!(binary.operator(), Some(BinaryOperator::Plus(_))));
+    assert!(matches!(
+        binary.right(),
+        Some(Expression::Primary(Primary::Number(_)))
+    ));
+    assert_eq!(binary.syntax().byte_range(), 8..13);
+    assert!(Program::cast(binary.syntax()).is_none());
+}
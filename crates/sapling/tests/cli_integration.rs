@@ -7,293 +7,686 @@ mod cli_integration {
     use tempfile::TempDir;
 
     #[test]
-    fn cli_applies_literal_target_patch() {
-        let temp = TempDir::new().unwrap();
+    fn cli_without_a_command_prints_usage() {
+        cargo_bin_cmd!("sapling")
+            .assert()
+            .code(2)
+            .stdout(predicate::str::contains("Usage: sapling <COMMAND>"))
+            .stdout(predicate::str::contains("validate"));
+    }
 
-        let source_file = temp.path().join("hello.txt");
-        fs::write(&source_file, "Hello Louis!").unwrap();
-
-        let patch_file = temp.path().join("patches.json");
-        let patch_json = format!(
-            r#"[{{
-                "file": "{}",
-                "snippet": {{
-                    "At": {{
-                        "target": {{"Literal": "Louis"}},
-                        "mode": "Include"
-                    }}
-                }},
-                "replacement": "World"
-            }}]"#,
-            source_file.display()
-        );
-        fs::write(&patch_file, patch_json).unwrap();
+    #[test]
+    fn cli_rejects_unknown_commands() {
+        cargo_bin_cmd!("sapling")
+            .arg("patches.json")
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("unknown command 'patches.json'"));
+    }
 
+    #[test]
+    fn cli_command_help_lists_options() {
         cargo_bin_cmd!("sapling")
-            .arg(patch_file.to_str().unwrap())
+            .args(["generate", "--help"])
             .assert()
             .success()
-            .stderr(predicate::str::contains("Patched:"));
+            .stdout(predicate::str::contains(
+                "Usage: sapling generate [OPTIONS] <GRAMMAR_JSON>",
+            ))
+            .stdout(predicate::str::contains("-o, --output <DIR>"));
+    }
 
-        let result = fs::read_to_string(&source_file).unwrap();
-        assert_eq!(result, "Hello World!");
+    #[test]
+    fn cli_reports_missing_arguments_with_usage() {
+        cargo_bin_cmd!("sapling")
+            .args(["parse", "input.mini"])
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains(
+                "Usage: sapling parse --grammar <GRAMMAR_JSON> [OPTIONS] <PATH>...",
+            ));
     }
 
     #[test]
-    fn cli_applies_line_range_patch() {
+    fn cli_reports_unreadable_files() {
         let temp = TempDir::new().unwrap();
+        let missing = temp.path().join("missing.json");
+        cargo_bin_cmd!("sapling")
+            .args(["parse", "-g", missing.to_str().unwrap(), "input.mini"])
+            .assert()
+            .code(1)
+            .stderr(predicate::str::starts_with(format!(
+                "error: {}: ",
+                missing.display()
+            )));
+    }
 
-        let source_file = temp.path().join("lines.txt");
-        fs::write(&source_file, "line1\nline2\nline3\nline4\n").unwrap();
-
-        let patch_json = format!(
-            r#"[{{
-                "file": "{}",
-                "snippet": {{
-                    "Between": {{
-                        "start": {{
-                            "target": {{"Line": 1}},
-                            "mode": "Include"
-                        }},
-                        "end": {{
-                            "target": {{"Line": 3}},
-                            "mode": "Exclude"
-                        }}
-                    }}
-                }},
-                "replacement": "replaced\n"
-            }}]"#,
-            source_file.display()
-        );
+    #[test]
+    fn cli_validate_reports_invalid_grammars() {
+        let temp = TempDir::new().unwrap();
+        let valid = temp.path().join("valid.json");
+        fs::write(&valid, MINI_GRAMMAR).unwrap();
+        let invalid = temp.path().join("invalid.json");
+        fs::write(&invalid, MINI_GRAMMAR.replace("\"call\"}", "\"cal\"}")).unwrap();
 
         cargo_bin_cmd!("sapling")
-            .write_stdin(patch_json)
+            .args(["validate", valid.to_str().unwrap()])
             .assert()
-            .success();
-
-        let result = fs::read_to_string(&source_file).unwrap();
-        assert_eq!(result, "line1\nreplaced\nline4\n");
+            .success()
+            .stdout(predicate::str::is_empty());
+        cargo_bin_cmd!("sapling")
+            .args([
+                "validate",
+                valid.to_str().unwrap(),
+                invalid.to_str().unwrap(),
+            ])
+            .assert()
+            .code(1)
+            .stdout(format!(
+                "error[undefined-symbol]: undefined symbol 'cal' referenced in rule 'program'\n  \
+                 --> {path}:4\n\
+                 warning[unreachable-rule]: unreachable rule 'call'\n  --> {path}:5\n\
+                 warning[unreachable-rule]: unreachable rule 'identifier'\n  --> {path}:9\n",
+                path = invalid.display()
+            ))
+            .stderr("1 error(s), 2 warning(s)\n");
     }
 
     #[test]
-    fn cli_applies_between_markers_patch() {
+    fn cli_validate_denies_warnings_on_request() {
         let temp = TempDir::new().unwrap();
-
-        let source_file = temp.path().join("markers.html");
-        fs::write(&source_file, "<!-- start -->old content<!-- end -->").unwrap();
-
-        let patch_json = format!(
-            r#"[{{
-                "file": "{}",
-                "snippet": {{
-                    "Between": {{
-                        "start": {{
-                            "target": {{"Literal": "<!-- start -->"}},
-                            "mode": "Exclude"
-                        }},
-                        "end": {{
-                            "target": {{"Literal": "<!-- end -->"}},
-                            "mode": "Exclude"
-                        }}
-                    }}
-                }},
-                "replacement": "new content"
-            }}]"#,
-            source_file.display()
-        );
+        let grammar = temp.path().join("grammar.json");
+        fs::write(
+            &grammar,
+            MINI_GRAMMAR.replace(
+                "\"rules\": {",
+                "\"rules\": {\n            \"start\": {\"type\": \"SYMBOL\", \"name\": \"program\"},\n            \"unused\": {\"type\": \"BLANK\"},",
+            ),
+        )
+        .unwrap();
 
         cargo_bin_cmd!("sapling")
-            .write_stdin(patch_json)
+            .args(["validate", "--format", "json", grammar.to_str().unwrap()])
             .assert()
-            .success();
+            .success()
+            .stdout(predicate::str::contains("\"code\": \"unreachable-rule\""))
+            .stdout(predicate::str::contains("\"line\": 5"));
+        cargo_bin_cmd!("sapling")
+            .args(["validate", "--deny-warnings", "-f", "sarif"])
+            .arg(&grammar)
+            .assert()
+            .code(1)
+            .stdout(predicate::str::contains("\"version\": \"2.1.0\""))
+            .stdout(predicate::str::contains("\"ruleId\": \"unreachable-rule\""));
+        cargo_bin_cmd!("sapling")
+            .args(["validate", "--format", "xml", grammar.to_str().unwrap()])
+            .assert()
+            .code(1)
+            .stderr(predicate::str::contains("unknown format 'xml'"));
+    }
 
-        let result = fs::read_to_string(&source_file).unwrap();
-        assert_eq!(result, "<!-- start -->new content<!-- end -->");
+    #[test]
+    fn cli_fmt_rewrites_grammars_in_canonical_form() {
+        let temp = TempDir::new().unwrap();
+        let grammar = temp.path().join("grammar.json");
+        fs::write(&grammar, MINI_GRAMMAR).unwrap();
+        let path = grammar.to_str().unwrap();
+
+        cargo_bin_cmd!("sapling")
+            .args(["fmt", "--check", path])
+            .assert()
+            .code(1)
+            .stdout(format!("{path}: not formatted\n"));
+        assert_eq!(fs::read_to_string(&grammar).unwrap(), MINI_GRAMMAR);
+        cargo_bin_cmd!("sapling")
+            .args(["fmt", path])
+            .assert()
+            .success()
+            .stdout(format!("{path}\n"));
+        let formatted = fs::read_to_string(&grammar).unwrap();
+        assert!(formatted.starts_with("{\n  \"name\": \"mini\",\n  \"rules\": {\n"));
+        cargo_bin_cmd!("sapling")
+            .args(["fmt", "--check", path])
+            .assert()
+            .success()
+            .stdout(predicate::str::is_empty());
     }
 
     #[test]
-    fn cli_applies_pattern_patch() {
+    fn cli_fmt_refuses_to_drop_unknown_keys() {
         let temp = TempDir::new().unwrap();
+        let grammar = temp.path().join("grammar.json");
+        let source = MINI_GRAMMAR
+            .replace(r#""name": "mini","#, r#""name": "mini", "author": "me","#)
+            .replace(r#""value": "()"}"#, r#""value": "()", "note": "empty"}"#);
+        fs::write(&grammar, &source).unwrap();
+        let path = grammar.to_str().unwrap();
+
+        for args in [["fmt", "--check"].as_slice(), ["fmt"].as_slice()] {
+            cargo_bin_cmd!("sapling")
+                .args(args)
+                .arg(path)
+                .assert()
+                .code(1)
+                .stderr(predicate::str::contains(format!(
+                    "{path}: formatting would drop keys sapling does not know: \
+                     author, rules.call.members[1].note"
+                )));
+        }
+        assert_eq!(fs::read_to_string(&grammar).unwrap(), source);
+    }
 
-        let source_file = temp.path().join("version.txt");
-        fs::write(&source_file, "version=1.2.3").unwrap();
-
-        let patch_json = format!(
-            r#"[{{
-                "file": "{}",
-                "snippet": {{
-                    "At": {{
-                        "target": {{"Pattern": "\\d+\\.\\d+\\.\\d+"}},
-                        "mode": "Include"
-                    }}
-                }},
-                "replacement": "2.0.0"
-            }}]"#,
-            source_file.display()
-        );
+    #[test]
+    fn cli_parse_prints_trees() {
+        let temp = TempDir::new().unwrap();
+        let grammar = temp.path().join("grammar.json");
+        fs::write(&grammar, MINI_GRAMMAR).unwrap();
+        let good = temp.path().join("good.mini");
+        fs::write(&good, "f()\ng()").unwrap();
+        let bad = temp.path().join("bad.mini");
+        fs::write(&bad, "f(").unwrap();
 
         cargo_bin_cmd!("sapling")
-            .write_stdin(patch_json)
+            .args(["parse", "--grammar", grammar.to_str().unwrap()])
+            .arg(&good)
             .assert()
-            .success();
+            .success()
+            .stdout(
+                "(program\n  (call\n    function: (identifier))\n  (call\n    function: (identifier)))\n",
+            );
+        cargo_bin_cmd!("sapling")
+            .args(["parse", "-g", grammar.to_str().unwrap()])
+            .args([&good, &bad])
+            .assert()
+            .code(1)
+            .stderr(format!("{}: parsed with errors\n", bad.display()));
+    }
 
-        let result = fs::read_to_string(&source_file).unwrap();
-        assert_eq!(result, "version=2.0.0");
+    #[test]
+    fn cli_parse_prints_trees_in_other_formats() {
+        let temp = TempDir::new().unwrap();
+        let grammar = temp.path().join("grammar.json");
+        fs::write(&grammar, MINI_GRAMMAR).unwrap();
+        let input = temp.path().join("input.mini");
+        fs::write(&input, "f()").unwrap();
+        let parse = |format: &str| {
+            let output = cargo_bin_cmd!("sapling")
+                .args(["parse", "-g", grammar.to_str().unwrap(), "-f", format])
+                .arg(&input)
+                .output()
+                .unwrap();
+            assert!(output.status.success());
+            String::from_utf8(output.stdout).unwrap()
+        };
+
+        assert_eq!(
+            parse("tree"),
+            "program 0..3 [0, 0] - [0, 3]\n  \
+             call 0..3 [0, 0] - [0, 3]\n    \
+             function: identifier 0..1 [0, 0] - [0, 1]\n    \
+             \"()\" 1..3 [0, 1] - [0, 3]\n"
+        );
+        let json = parse("json");
+        assert!(json.starts_with(&format!(
+            "{{\"path\":\"{}\",\"tree\":{{\"kind\":\"program\"",
+            input.display()
+        )));
+        assert_eq!(json.lines().count(), 1);
+        assert!(parse("xml").contains(
+            "<call srow=\"0\" scol=\"0\" erow=\"0\" ecol=\"3\">\
+             <identifier field=\"function\" srow=\"0\" scol=\"0\" erow=\"0\" ecol=\"1\">f\
+             </identifier>()</call>"
+        ));
+        cargo_bin_cmd!("sapling")
+            .args(["parse", "-g", grammar.to_str().unwrap(), "-f", "yaml"])
+            .arg(&input)
+            .assert()
+            .code(1)
+            .stderr(predicate::str::contains("unknown format 'yaml'"));
     }
 
     #[test]
-    fn cli_applies_deletion_patch() {
+    fn cli_parse_summarizes_directories_quietly() {
         let temp = TempDir::new().unwrap();
+        let grammar = temp.path().join("grammar.json");
+        fs::write(&grammar, MINI_GRAMMAR).unwrap();
+        let sources = temp.path().join("sources");
+        fs::create_dir(&sources).unwrap();
+        fs::write(sources.join("a.mini"), "f()").unwrap();
+        fs::write(sources.join("b.mini"), "f(\n").unwrap();
+        fs::write(sources.join("notes.txt"), "not a call").unwrap();
 
-        let source_file = temp.path().join("delete.txt");
-        fs::write(&source_file, "keep this\ndelete this\nkeep this").unwrap();
-
-        let patch_json = format!(
-            r#"[{{
-                "file": "{}",
-                "snippet": {{
-                    "At": {{
-                        "target": {{"Literal": "delete this\n"}},
-                        "mode": "Include"
-                    }}
-                }},
-                "replacement": ""
-            }}]"#,
-            source_file.display()
-        );
+        cargo_bin_cmd!("sapling")
+            .args(["parse", "-g", grammar.to_str().unwrap(), "-q", "--stat"])
+            .args(["-e", ".mini", "--time"])
+            .arg(&sources)
+            .assert()
+            .code(1)
+            .stdout(format!(
+                "{}: ERROR [0, 0] - [0, 2]\n\
+                 Total parses: 2; successful parses: 1; failed parses: 1; \
+                 success percentage: 50.00%\n",
+                sources.join("b.mini").display()
+            ))
+            .stderr(predicate::str::contains(" ms\n").count(2));
+    }
+
+    #[test]
+    fn cli_stats_reports_grammar_metrics() {
+        let temp = TempDir::new().unwrap();
+        let grammar = temp.path().join("grammar.json");
+        fs::write(&grammar, MINI_GRAMMAR).unwrap();
+
+        let output = cargo_bin_cmd!("sapling")
+            .args(["stats", grammar.to_str().unwrap()])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.starts_with("mini\n  rules          3 (0 hidden)\n  tokens         2\n"));
+        assert!(stdout.contains("  fields         1\n"));
+        assert!(!stdout.contains("  parse states   -\n"));
+        assert!(stdout.ends_with("  largest rules  call (4), program (2), identifier (1)\n"));
 
         cargo_bin_cmd!("sapling")
-            .write_stdin(patch_json)
+            .args([
+                "stats",
+                "--no-tables",
+                "-f",
+                "json",
+                grammar.to_str().unwrap(),
+            ])
             .assert()
-            .success();
+            .success()
+            .stdout(predicate::str::starts_with(format!(
+                "{{\"path\":\"{}\",\"stats\":{{\"name\":\"mini\",\"rules\":3,",
+                grammar.display()
+            )))
+            .stdout(predicate::str::contains("\"parse_states\":null,"));
+    }
 
-        let result = fs::read_to_string(&source_file).unwrap();
-        assert_eq!(result, "keep this\nkeep this");
+    #[test]
+    fn cli_graph_prints_rule_references() {
+        let temp = TempDir::new().unwrap();
+        let grammar = temp.path().join("grammar.json");
+        fs::write(&grammar, MINI_GRAMMAR).unwrap();
+
+        cargo_bin_cmd!("sapling")
+            .args(["graph", grammar.to_str().unwrap()])
+            .assert()
+            .success()
+            .stdout(
+                "digraph mini {\n  rankdir=LR;\n  node [shape=box];\n  program;\n  call;\n  \
+                 identifier;\n  program -> call;\n  call -> identifier;\n}\n",
+            );
+        cargo_bin_cmd!("sapling")
+            .args(["graph", grammar.to_str().unwrap(), "-f", "mermaid"])
+            .args(["--focus", "identifier", "--depth", "1"])
+            .assert()
+            .success()
+            .stdout("flowchart LR\n  n0[\"call\"]\n  n1[\"identifier\"]\n  n0 --> n1\n");
+        cargo_bin_cmd!("sapling")
+            .args(["graph", grammar.to_str().unwrap(), "--focus", "statement"])
+            .assert()
+            .code(1)
+            .stderr("error: cannot focus on 'statement': there is no such rule\n");
     }
 
     #[test]
-    fn cli_dry_run_does_not_modify_file() {
+    fn cli_railroad_writes_diagrams_and_a_page() {
         let temp = TempDir::new().unwrap();
+        let grammar = temp.path().join("grammar.json");
+        fs::write(&grammar, MINI_GRAMMAR).unwrap();
+        let out = temp.path().join("railroad");
 
-        let source_file = temp.path().join("original.txt");
-        let original_content = "Don't change me!";
-        fs::write(&source_file, original_content).unwrap();
-
-        let patch_json = format!(
-            r#"[{{
-                "file": "{}",
-                "snippet": {{
-                    "At": {{
-                        "target": {{"Literal": "Don't"}},
-                        "mode": "Include"
-                    }}
-                }},
-                "replacement": "Please"
-            }}]"#,
-            source_file.display()
-        );
+        cargo_bin_cmd!("sapling")
+            .args(["railroad", grammar.to_str().unwrap()])
+            .assert()
+            .success()
+            .stdout(format!(
+                "{}\n{}\n{}\n{}\n",
+                out.join("program.svg").display(),
+                out.join("call.svg").display(),
+                out.join("identifier.svg").display(),
+                out.join("index.html").display()
+            ));
+        let call = fs::read_to_string(out.join("call.svg")).unwrap();
+        assert!(call.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(call.contains(">function:</text>"));
+        let page = fs::read_to_string(out.join("index.html")).unwrap();
+        assert!(page.contains("<a href=\"#identifier\"><g class=\"nonterminal\">"));
+        assert!(page.contains("<section id=\"identifier\">"));
+    }
+
+    #[test]
+    fn cli_ebnf_prints_the_grammar_in_either_notation() {
+        let temp = TempDir::new().unwrap();
+        let grammar = temp.path().join("grammar.json");
+        fs::write(&grammar, MINI_GRAMMAR).unwrap();
 
         cargo_bin_cmd!("sapling")
-            .arg("--dry-run")
-            .write_stdin(patch_json)
+            .args(["ebnf", grammar.to_str().unwrap()])
             .assert()
             .success()
-            .stderr(predicate::str::contains("Would patch:"));
+            .stdout(
+                "/* The mini grammar, in W3C EBNF, written by sapling. */\n\n\
+                 program ::= call*\n\n\
+                 call ::= /* field function */ identifier \"()\"\n\n\
+                 identifier ::= [a-z]+\n",
+            );
+        cargo_bin_cmd!("sapling")
+            .args(["ebnf", "--notation", "iso", grammar.to_str().unwrap()])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(
+                "\ncall = (* field function *) identifier , \"()\" ;\n",
+            ))
+            .stdout(predicate::str::contains(
+                "\nidentifier = { ? [a-z] ? }- ;\n",
+            ));
+        cargo_bin_cmd!("sapling")
+            .args(["ebnf", "-n", "bnf", grammar.to_str().unwrap()])
+            .assert()
+            .failure()
+            .stderr("error: unknown notation 'bnf'; expected iso or w3c\n");
+    }
+
+    #[test]
+    fn cli_import_translates_a_pest_grammar() {
+        let temp = TempDir::new().unwrap();
+        let source = temp.path().join("sums.pest");
+        fs::write(
+            &source,
+            "WHITESPACE = _{ \" \" }\n\
+             sum = { SOI ~ number ~ (op ~ number)* ~ EOI }\n\
+             op = { \"+\" | \"++\" }\n\
+             number = @{ ASCII_DIGIT+ }\n",
+        )
+        .unwrap();
+        let grammar = temp.path().join("grammar.json");
 
-        let result = fs::read_to_string(&source_file).unwrap();
-        assert_eq!(result, original_content);
+        cargo_bin_cmd!("sapling")
+            .args(["import", source.to_str().unwrap(), "-o"])
+            .arg(&grammar)
+            .assert()
+            .success()
+            .stderr(predicate::str::contains(
+                "sums.pest:2: note: 'sum' is the start rule",
+            ))
+            .stderr(predicate::str::contains(
+                "sums.pest:3: warning: \"+\" comes before \"++\" in a choice in 'op'",
+            ));
+        let json = fs::read_to_string(&grammar).unwrap();
+        assert!(json.contains("\"name\": \"sums\""), "{json}");
+        assert!(json.contains("\"value\": \"[0-9]+\""), "{json}");
+        cargo_bin_cmd!("sapling")
+            .args(["validate", grammar.to_str().unwrap()])
+            .assert()
+            .success();
+        cargo_bin_cmd!("sapling")
+            .args(["import", grammar.to_str().unwrap()])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("cannot tell the format of"));
     }
 
     #[test]
-    fn cli_multiple_patches_same_file() {
+    fn cli_generate_writes_tables_and_node_types() {
         let temp = TempDir::new().unwrap();
+        let grammar = temp.path().join("grammar.json");
+        fs::write(&grammar, MINI_GRAMMAR).unwrap();
+        let out = temp.path().join("generated");
 
-        let source_file = temp.path().join("multi.txt");
-        fs::write(&source_file, "foo bar baz").unwrap();
-
-        let patch_json = format!(
-            r#"[
-                {{
-                    "file": "{}",
-                    "snippet": {{
-                        "At": {{
-                            "target": {{"Literal": "foo"}},
-                            "mode": "Include"
-                        }}
-                    }},
-                    "replacement": "FOO"
-                }},
-                {{
-                    "file": "{}",
-                    "snippet": {{
-                        "At": {{
-                            "target": {{"Literal": "baz"}},
-                            "mode": "Include"
-                        }}
-                    }},
-                    "replacement": "BAZ"
-                }}
-            ]"#,
-            source_file.display(),
-            source_file.display()
-        );
+        cargo_bin_cmd!("sapling")
+            .args(["generate", grammar.to_str().unwrap(), "--output"])
+            .arg(&out)
+            .assert()
+            .success()
+            .stdout(format!(
+                "{}\n{}\n",
+                out.join("mini.rs").display(),
+                out.join("node-types.json").display()
+            ));
+        let module = fs::read_to_string(out.join("mini.rs")).unwrap();
+        assert!(module.contains("pub static LANGUAGE: Language"));
+        let node_types = fs::read_to_string(out.join("node-types.json")).unwrap();
+        assert!(node_types.contains("\"type\": \"call\""));
+    }
+
+    #[cfg(feature = "js")]
+    #[test]
+    fn cli_generate_evaluates_grammar_js() {
+        let temp = TempDir::new().unwrap();
+        let grammar = temp.path().join("grammar.js");
+        fs::write(
+            &grammar,
+            "module.exports = grammar({\n\
+               name: 'mini',\n\
+               rules: {\n\
+                 program: $ => repeat($.call),\n\
+                 call: $ => seq(field('function', $.identifier), '()'),\n\
+                 identifier: $ => /[a-z]+/,\n\
+               },\n\
+             });\n",
+        )
+        .unwrap();
+        let out = temp.path().join("generated");
 
         cargo_bin_cmd!("sapling")
-            .write_stdin(patch_json)
+            .args(["generate", grammar.to_str().unwrap(), "--output"])
+            .arg(&out)
             .assert()
-            .success();
+            .success()
+            .stdout(predicate::str::contains("grammar.json"));
+        let json = fs::read_to_string(out.join("grammar.json")).unwrap();
+        let expected = sapling::parse_grammar(MINI_GRAMMAR).unwrap();
+        assert_eq!(sapling::parse_grammar(&json).unwrap().rules, expected.rules);
+
+        fs::write(
+            &grammar,
+            "module.exports = grammar({ name: 'mini', rules: { a: $ => $.b } });\n",
+        )
+        .unwrap();
+        cargo_bin_cmd!("sapling")
+            .args(["stats", grammar.to_str().unwrap()])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(
+                "ReferenceError: Undefined symbol 'b'",
+            ));
+    }
 
-        let result = fs::read_to_string(&source_file).unwrap();
-        assert_eq!(result, "FOO bar BAZ");
+    #[test]
+    fn cli_query_prints_captures() {
+        let temp = TempDir::new().unwrap();
+        let grammar = temp.path().join("grammar.json");
+        fs::write(&grammar, MINI_GRAMMAR).unwrap();
+        let query = temp.path().join("calls.scm");
+        fs::write(&query, "(call function: (identifier) @function)").unwrap();
+        let source = temp.path().join("a.mini");
+        fs::write(&source, "f()\n  gh()").unwrap();
+
+        let path = source.display();
+        cargo_bin_cmd!("sapling")
+            .args(["query", grammar.to_str().unwrap(), query.to_str().unwrap()])
+            .arg(&source)
+            .assert()
+            .success()
+            .stdout(format!(
+                "{path}:1:1: @function \"f\"\n{path}:2:3: @function \"gh\"\n"
+            ));
     }
 
+    const MINI_GRAMMAR: &str = r#"{
+        "name": "mini",
+        "rules": {
+            "program": {"type": "REPEAT", "content": {"type": "SYMBOL", "name": "call"}},
+            "call": {"type": "SEQ", "members": [
+                {"type": "FIELD", "name": "function", "content": {"type": "SYMBOL", "name": "identifier"}},
+                {"type": "STRING", "value": "()"}
+            ]},
+            "identifier": {"type": "PATTERN", "value": "[a-z]+"}
+        }
+    }"#;
+
     #[test]
-    fn cli_rejects_overlapping_patches() {
+    fn cli_check_queries_accepts_valid_queries() {
         let temp = TempDir::new().unwrap();
+        let grammar = temp.path().join("grammar.json");
+        fs::write(&grammar, MINI_GRAMMAR).unwrap();
+        let query = temp.path().join("highlights.scm");
+        fs::write(
+            &query,
+            "(call function: (identifier) @function)\n\"()\" @punctuation\n",
+        )
+        .unwrap();
 
-        let source_file = temp.path().join("overlap.txt");
-        fs::write(&source_file, "abcdef").unwrap();
-
-        let patch_json = format!(
-            r#"[
-                {{
-                    "file": "{}",
-                    "snippet": {{
-                        "At": {{
-                            "target": {{"Literal": "bcd"}},
-                            "mode": "Include"
-                        }}
-                    }},
-                    "replacement": "XXX"
-                }},
-                {{
-                    "file": "{}",
-                    "snippet": {{
-                        "At": {{
-                            "target": {{"Literal": "def"}},
-                            "mode": "Include"
-                        }}
-                    }},
-                    "replacement": "YYY"
-                }}
-            ]"#,
-            source_file.display(),
-            source_file.display()
-        );
+        cargo_bin_cmd!("sapling")
+            .args([
+                "check-queries",
+                grammar.to_str().unwrap(),
+                query.to_str().unwrap(),
+            ])
+            .assert()
+            .success()
+            .stdout(predicate::str::is_empty());
+    }
+
+    #[test]
+    fn cli_check_queries_reports_problems() {
+        let temp = TempDir::new().unwrap();
+        let grammar = temp.path().join("grammar.json");
+        fs::write(&grammar, MINI_GRAMMAR).unwrap();
+        let query = temp.path().join("highlights.scm");
+        fs::write(&query, "(call name: (identifier))\n(program (function))\n").unwrap();
 
         cargo_bin_cmd!("sapling")
-            .write_stdin(patch_json)
+            .args([
+                "check-queries",
+                grammar.to_str().unwrap(),
+                query.to_str().unwrap(),
+            ])
             .assert()
             .failure()
-            .stderr(predicate::str::contains("Overlapping"));
+            .stdout(predicate::str::contains(
+                "highlights.scm:1:7: unknown field 'name'",
+            ))
+            .stdout(predicate::str::contains(
+                "highlights.scm:2:10: unknown node type 'function'",
+            ));
     }
 
     #[test]
-    fn cli_fails_on_invalid_json() {
+    fn cli_tags_writes_sorted_ctags() {
+        let temp = TempDir::new().unwrap();
+        let grammar = temp.path().join("grammar.json");
+        fs::write(&grammar, MINI_GRAMMAR).unwrap();
+        let query = temp.path().join("tags.scm");
+        fs::write(
+            &query,
+            "(call function: (identifier) @name) @definition.call\n",
+        )
+        .unwrap();
+        let src = temp.path().join("src");
+        fs::create_dir_all(src.join(".hidden")).unwrap();
+        fs::write(src.join("a.mini"), "zed()\n\nalpha()\n").unwrap();
+        fs::write(src.join("notes.txt"), "skipped()\n").unwrap();
+        fs::write(src.join(".hidden/b.mini"), "hidden()\n").unwrap();
+
+        let a = src.join("a.mini");
+        let a = a.display();
         cargo_bin_cmd!("sapling")
-            .write_stdin("not valid json")
+            .args([
+                "tags",
+                "--extension",
+                "mini",
+                grammar.to_str().unwrap(),
+                query.to_str().unwrap(),
+                src.to_str().unwrap(),
+            ])
+            .assert()
+            .success()
+            .stdout(predicate::str::starts_with("!_TAG_FILE_FORMAT\t2\t"))
+            .stdout(predicate::str::contains(format!(
+                "alpha\t{a}\t3;\"\tcall\tline:3\nzed\t{a}\t1;\"\tcall\tline:1\n"
+            )))
+            .stdout(predicate::str::contains("skipped").not())
+            .stdout(predicate::str::contains("hidden").not());
+    }
+
+    #[test]
+    fn cli_test_runs_and_updates_a_corpus() {
+        let temp = TempDir::new().unwrap();
+        let grammar = temp.path().join("grammar.json");
+        fs::write(&grammar, MINI_GRAMMAR).unwrap();
+        let corpus = temp.path().join("test/corpus");
+        fs::create_dir_all(&corpus).unwrap();
+        fs::write(
+            corpus.join("calls.txt"),
+            "===\nOne call\n===\nf()\n---\n\n(program (call function: (identifier)))\n\n\
+             ===\nTwo calls\n===\nf() g()\n---\n\n(program (call))\n\n\
+             ===\nSkipped\n:skip\n===\nf\n---\n",
+        )
+        .unwrap();
+
+        cargo_bin_cmd!("sapling")
+            .current_dir(temp.path())
+            .args(["test", grammar.to_str().unwrap()])
             .assert()
             .failure()
-            .stderr(predicate::str::contains("Error:"));
+            .stdout(predicate::str::contains("✓ One call"))
+            .stdout(predicate::str::contains("✗ Two calls"))
+            .stdout(predicate::str::contains("⌀ Skipped (skipped)"))
+            .stdout(predicate::str::contains(
+                " (program\n-  (call))\n+  (call\n+    (identifier))\n+  (call\n+    (identifier)))",
+            ))
+            .stdout(predicate::str::contains("1 passed, 1 failed, 1 skipped"));
+
+        cargo_bin_cmd!("sapling")
+            .current_dir(temp.path())
+            .args(["test", "--update", grammar.to_str().unwrap()])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("✎ Two calls (updated)"));
+        cargo_bin_cmd!("sapling")
+            .args(["test", grammar.to_str().unwrap(), corpus.to_str().unwrap()])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("2 passed, 0 failed, 1 skipped"));
+        assert!(fs::read_to_string(corpus.join("calls.txt"))
+            .unwrap()
+            .contains("---\n\n(program\n  (call\n    (identifier))\n  (call\n    (identifier)))\n\n===\nSkipped"));
+    }
+
+    #[test]
+    fn cli_refuses_to_parse_with_external_tokens() {
+        let temp = TempDir::new().unwrap();
+        let grammar = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/scanner/grammar.json"
+        );
+        let input = temp.path().join("input.py");
+        fs::write(&input, "x = 1\n").unwrap();
+        let tags = temp.path().join("tags.scm");
+        fs::write(&tags, "").unwrap();
+
+        for args in [
+            vec!["parse", "-g", grammar, input.to_str().unwrap()],
+            vec!["test", grammar, temp.path().to_str().unwrap()],
+            vec![
+                "tags",
+                grammar,
+                tags.to_str().unwrap(),
+                temp.path().to_str().unwrap(),
+            ],
+        ] {
+            cargo_bin_cmd!("sapling")
+                .args(&args)
+                .assert()
+                .code(1)
+                .stderr(predicate::str::contains(
+                    "the grammar declares external tokens, which need a scanner",
+                ));
+        }
     }
 }
@@ -0,0 +1,86 @@
+#![allow(missing_docs)]
+//! Running corpus tests against the calc grammar.
+
+use sapling::corpus::{parse_corpus, update, Outcome};
+
+mod calc {
+    include!("fixtures/tables/calc.rs");
+}
+
+const CORPUS: &str = "\
+==========
+Let
+==========
+let x = 1;
+---
+
+(program
+  (let
+    name: (identifier)
+    value: (number)))
+
+==========
+Calls without fields
+==========
+f(x);
+---
+
+(program (expression_statement (call (identifier) (identifier))))
+
+==========
+Wrong tree
+==========
+x;
+---
+
+(program (let))
+
+==========
+Broken let
+:error
+==========
+let = 1;
+---
+
+==========
+Other language
+:language(json)
+==========
+{}
+---
+
+(document (object))
+";
+
+#[test]
+fn tests_pass_fail_and_skip() {
+    let outcomes: Vec<Outcome> = parse_corpus(CORPUS)
+        .iter()
+        .map(|test| test.run(&calc::LANGUAGE))
+        .collect();
+    assert_eq!(
+        outcomes,
+        [
+            Outcome::Passed,
+            Outcome::Passed,
+            Outcome::Failed {
+                actual: "(program (expression_statement (identifier)))".to_string()
+            },
+            Outcome::Passed,
+            Outcome::Skipped,
+        ]
+    );
+}
+
+#[test]
+fn updated_tests_pass() {
+    let tests = parse_corpus(CORPUS);
+    let Outcome::Failed { actual } = tests[2].run(&calc::LANGUAGE) else {
+        panic!("the wrong tree passed");
+    };
+    let updated = update(CORPUS, &[(&tests[2], &actual)]);
+    assert!(updated.contains("---\n\n(program\n  (expression_statement\n    (identifier)))\n\n"));
+    let tests = parse_corpus(&updated);
+    assert_eq!(tests[2].run(&calc::LANGUAGE), Outcome::Passed);
+    assert_eq!(tests.len(), 5);
+}
@@ -0,0 +1,143 @@
+#![allow(missing_docs)]
+//! External scanners, with a port of the indentation half of
+//! tree-sitter-python's `scanner.c` driving a generated indentation grammar.
+
+use sapling::{parse_grammar, validate, Parser};
+use std::fs;
+use std::path::Path;
+
+mod scanner {
+    use sapling::{ExternalScanner, Lexer};
+
+    // Indices into the grammar's `externals`.
+    const NEWLINE: u16 = 0;
+    const INDENT: u16 = 1;
+    const DEDENT: u16 = 2;
+
+    /// Tracks the indentation of the enclosing blocks.
+    pub struct IndentScanner {
+        indents: Vec<u8>,
+    }
+
+    impl Default for IndentScanner {
+        fn default() -> Self {
+            Self { indents: vec![0] }
+        }
+    }
+
+    impl ExternalScanner for IndentScanner {
+        fn scan(&mut self, lexer: &mut Lexer<'_>, valid_symbols: &[bool]) -> bool {
+            // Layout tokens are empty and come before the line break.
+            lexer.mark_end();
+            let mut found_end_of_line = false;
+            loop {
+                match lexer.lookahead() {
+                    Some('\n') => {
+                        found_end_of_line = true;
+                        lexer.skip();
+                    }
+                    Some(' ' | '\t' | '\r' | '\x0c') => lexer.skip(),
+                    None => {
+                        found_end_of_line = true;
+                        break;
+                    }
+                    Some(_) => break,
+                }
+            }
+            if !found_end_of_line {
+                return false;
+            }
+            let indent = if lexer.eof() { 0 } else { lexer.get_column() };
+            let indent = u8::try_from(indent).unwrap_or(u8::MAX);
+            let current = self.indents.last().copied().unwrap_or(0);
+            if valid_symbols[usize::from(INDENT)] && indent > current {
+                self.indents.push(indent);
+                lexer.set_result_symbol(INDENT);
+                return true;
+            }
+            if (valid_symbols[usize::from(DEDENT)] || !valid_symbols[usize::from(NEWLINE)])
+                && indent < current
+            {
+                self.indents.pop();
+                lexer.set_result_symbol(DEDENT);
+                return true;
+            }
+            if valid_symbols[usize::from(NEWLINE)] {
+                lexer.set_result_symbol(NEWLINE);
+                return true;
+            }
+            false
+        }
+
+        fn serialize(&self, buffer: &mut Vec<u8>) {
+            buffer.extend_from_slice(&self.indents[1..]);
+        }
+
+        fn deserialize(&mut self, buffer: &[u8]) {
+            self.indents.clear();
+            self.indents.push(0);
+            self.indents.extend_from_slice(buffer);
+        }
+    }
+}
+
+mod indent {
+    include!("fixtures/scanner/indent.rs");
+}
+
+fn fixture(path: &str) -> String {
+    fs::read_to_string(
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/scanner")
+            .join(path),
+    )
+    .unwrap()
+}
+
+#[test]
+fn generated_tables_are_up_to_date() {
+    let grammar = parse_grammar(&fixture("grammar.json")).unwrap();
+    assert!(validate(&grammar).is_ok());
+    let generated =
+        sapling::emit::rust::emit(&grammar, Some("crate::scanner::IndentScanner")).unwrap();
+    assert!(
+        generated == fixture("indent.rs"),
+        "tests/fixtures/scanner/indent.rs is stale; regenerate it from grammar.json"
+    );
+}
+
+#[test]
+fn indentation_delimits_blocks() {
+    let source = "if a:\n    b\n    if c:\n        d\n    e\nf\n";
+    let tree = Parser::new(&indent::LANGUAGE).parse(source);
+    assert_eq!(
+        tree.to_sexp(),
+        "(module (if_statement condition: (identifier) body: (block \
+         (expression_statement (identifier)) \
+         (if_statement condition: (identifier) body: (block (expression_statement (identifier)))) \
+         (expression_statement (identifier)))) \
+         (expression_statement (identifier)))"
+    );
+    // The block ends where its last statement does, before the dedent.
+    let block = tree.child(0).unwrap().child_by_field_name("body").unwrap();
+    assert_eq!(
+        block.utf8_text(source.as_bytes()),
+        Ok("b\n    if c:\n        d\n    e")
+    );
+}
+
+#[test]
+fn blocks_close_at_the_end_of_input() {
+    let tree = Parser::new(&indent::LANGUAGE).parse("if a:\n  if b:\n    c");
+    assert_eq!(
+        tree.to_sexp(),
+        "(module (if_statement condition: (identifier) body: (block \
+         (if_statement condition: (identifier) body: (block (expression_statement (identifier)))))))"
+    );
+}
+
+#[test]
+fn missing_indentation_is_an_error() {
+    let tree = Parser::new(&indent::LANGUAGE).parse("if a:\nb\n");
+    assert!(tree.has_error());
+}
@@ -0,0 +1,345 @@
+// Typed syntax tree for the `calc` grammar, generated by sapling.
+// Do not edit by hand.
+
+use sapling::{AstNode, Node};
+
+/// Any `_expression` node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expression<'tree> {
+    /// A `_primary` node.
+    Primary(Primary<'tree>),
+    /// A `binary` node.
+    Binary(Binary<'tree>),
+    /// A `call` node.
+    Call(Call<'tree>),
+}
+
+impl<'tree> AstNode<'tree> for Expression<'tree> {
+    fn cast(node: &'tree Node) -> Option<Self> {
+        match (node.kind(), node.is_named()) {
+            ("binary", true) => Some(Self::Binary(Binary(node))),
+            ("call", true) => Some(Self::Call(Call(node))),
+            _ => Primary::cast(node).map(Self::Primary),
+        }
+    }
+
+    fn syntax(&self) -> &'tree Node {
+        match *self {
+            Self::Primary(node) => node.syntax(),
+            Self::Binary(node) => node.syntax(),
+            Self::Call(node) => node.syntax(),
+        }
+    }
+}
+
+/// Any `_primary` node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Primary<'tree> {
+    /// An `identifier` node.
+    Identifier(Identifier<'tree>),
+    /// A `number` node.
+    Number(Number<'tree>),
+}
+
+impl<'tree> AstNode<'tree> for Primary<'tree> {
+    fn cast(node: &'tree Node) -> Option<Self> {
+        match (node.kind(), node.is_named()) {
+            ("identifier", true) => Some(Self::Identifier(Identifier(node))),
+            ("number", true) => Some(Self::Number(Number(node))),
+            _ => None,
+        }
+    }
+
+    fn syntax(&self) -> &'tree Node {
+        match *self {
+            Self::Identifier(node) => node.syntax(),
+            Self::Number(node) => node.syntax(),
+        }
+    }
+}
+
+/// A `binary` node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Binary<'tree>(&'tree Node);
+
+impl<'tree> AstNode<'tree> for Binary<'tree> {
+    fn cast(node: &'tree Node) -> Option<Self> {
+        (node.kind() == "binary" && node.is_named()).then_some(Self(node))
+    }
+
+    fn syntax(&self) -> &'tree Node {
+        self.0
+    }
+}
+
+impl<'tree> Binary<'tree> {
+    /// The `left` field.
+    #[must_use]
+    pub fn left(self) -> Option<Expression<'tree>> {
+        self.0
+            .child_by_field_name("left")
+            .and_then(Expression::cast)
+    }
+
+    /// The `operator` field.
+    #[must_use]
+    pub fn operator(self) -> Option<BinaryOperator<'tree>> {
+        self.0
+            .child_by_field_name("operator")
+            .and_then(BinaryOperator::cast)
+    }
+
+    /// The `right` field.
+    #[must_use]
+    pub fn right(self) -> Option<Expression<'tree>> {
+        self.0
+            .child_by_field_name("right")
+            .and_then(Expression::cast)
+    }
+}
+
+/// The kinds of node in the `operator` field of `binary`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOperator<'tree> {
+    /// The `+` token.
+    Plus(&'tree Node),
+    /// The `+=` token.
+    PlusEq(&'tree Node),
+    /// The `-` token.
+    Dash(&'tree Node),
+}
+
+impl<'tree> AstNode<'tree> for BinaryOperator<'tree> {
+    fn cast(node: &'tree Node) -> Option<Self> {
+        match (node.kind(), node.is_named()) {
+            ("+", false) => Some(Self::Plus(node)),
+            ("+=", false) => Some(Self::PlusEq(node)),
+            ("-", false) => Some(Self::Dash(node)),
+            _ => None,
+        }
+    }
+
+    fn syntax(&self) -> &'tree Node {
+        match *self {
+            Self::Plus(node) | Self::PlusEq(node) | Self::Dash(node) => node,
+        }
+    }
+}
+
+/// A `call` node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Call<'tree>(&'tree Node);
+
+impl<'tree> AstNode<'tree> for Call<'tree> {
+    fn cast(node: &'tree Node) -> Option<Self> {
+        (node.kind() == "call" && node.is_named()).then_some(Self(node))
+    }
+
+    fn syntax(&self) -> &'tree Node {
+        self.0
+    }
+}
+
+impl<'tree> Call<'tree> {
+    /// The `argument` field.
+    #[must_use]
+    pub fn argument(self) -> Vec<Expression<'tree>> {
+        self.0
+            .children_by_field_name("argument")
+            .filter_map(Expression::cast)
+            .collect()
+    }
+
+    /// The `function` field.
+    #[must_use]
+    pub fn function(self) -> Option<CallFunction<'tree>> {
+        self.0
+            .child_by_field_name("function")
+            .and_then(CallFunction::cast)
+    }
+}
+
+/// The kinds of node in the `function` field of `call`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallFunction<'tree> {
+    /// A `call` node.
+    Call(Call<'tree>),
+    /// An `identifier` node.
+    Identifier(Identifier<'tree>),
+}
+
+impl<'tree> AstNode<'tree> for CallFunction<'tree> {
+    fn cast(node: &'tree Node) -> Option<Self> {
+        match (node.kind(), node.is_named()) {
+            ("call", true) => Some(Self::Call(Call(node))),
+            ("identifier", true) => Some(Self::Identifier(Identifier(node))),
+            _ => None,
+        }
+    }
+
+    fn syntax(&self) -> &'tree Node {
+        match *self {
+            Self::Call(node) => node.syntax(),
+            Self::Identifier(node) => node.syntax(),
+        }
+    }
+}
+
+/// An `expression_statement` node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExpressionStatement<'tree>(&'tree Node);
+
+impl<'tree> AstNode<'tree> for ExpressionStatement<'tree> {
+    fn cast(node: &'tree Node) -> Option<Self> {
+        (node.kind() == "expression_statement" && node.is_named()).then_some(Self(node))
+    }
+
+    fn syntax(&self) -> &'tree Node {
+        self.0
+    }
+}
+
+impl<'tree> ExpressionStatement<'tree> {
+    /// The named children that are not in a field.
+    #[must_use]
+    pub fn children(self) -> Option<Expression<'tree>> {
+        self.0
+            .named_children()
+            .filter(|node| node.field_name().is_none())
+            .find_map(Expression::cast)
+    }
+}
+
+/// A `let` node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Let<'tree>(&'tree Node);
+
+impl<'tree> AstNode<'tree> for Let<'tree> {
+    fn cast(node: &'tree Node) -> Option<Self> {
+        (node.kind() == "let" && node.is_named()).then_some(Self(node))
+    }
+
+    fn syntax(&self) -> &'tree Node {
+        self.0
+    }
+}
+
+impl<'tree> Let<'tree> {
+    /// The `name` field.
+    #[must_use]
+    pub fn name(self) -> Option<Identifier<'tree>> {
+        self.0
+            .child_by_field_name("name")
+            .and_then(Identifier::cast)
+    }
+
+    /// The `type` field.
+    #[must_use]
+    pub fn r#type(self) -> Option<Identifier<'tree>> {
+        self.0
+            .child_by_field_name("type")
+            .and_then(Identifier::cast)
+    }
+
+    /// The `value` field.
+    #[must_use]
+    pub fn value(self) -> Option<Expression<'tree>> {
+        self.0
+            .child_by_field_name("value")
+            .and_then(Expression::cast)
+    }
+}
+
+/// A `program` node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Program<'tree>(&'tree Node);
+
+impl<'tree> AstNode<'tree> for Program<'tree> {
+    fn cast(node: &'tree Node) -> Option<Self> {
+        (node.kind() == "program" && node.is_named()).then_some(Self(node))
+    }
+
+    fn syntax(&self) -> &'tree Node {
+        self.0
+    }
+}
+
+impl<'tree> Program<'tree> {
+    /// The named children that are not in a field.
+    #[must_use]
+    pub fn children(self) -> Vec<ProgramChild<'tree>> {
+        self.0
+            .named_children()
+            .filter(|node| node.field_name().is_none())
+            .filter_map(ProgramChild::cast)
+            .collect()
+    }
+}
+
+/// The kinds of node among the unnamed children of `program`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgramChild<'tree> {
+    /// An `expression_statement` node.
+    ExpressionStatement(ExpressionStatement<'tree>),
+    /// A `let` node.
+    Let(Let<'tree>),
+}
+
+impl<'tree> AstNode<'tree> for ProgramChild<'tree> {
+    fn cast(node: &'tree Node) -> Option<Self> {
+        match (node.kind(), node.is_named()) {
+            ("expression_statement", true) => Some(Self::ExpressionStatement(ExpressionStatement(node))),
+            ("let", true) => Some(Self::Let(Let(node))),
+            _ => None,
+        }
+    }
+
+    fn syntax(&self) -> &'tree Node {
+        match *self {
+            Self::ExpressionStatement(node) => node.syntax(),
+            Self::Let(node) => node.syntax(),
+        }
+    }
+}
+
+/// A `comment` node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Comment<'tree>(&'tree Node);
+
+impl<'tree> AstNode<'tree> for Comment<'tree> {
+    fn cast(node: &'tree Node) -> Option<Self> {
+        (node.kind() == "comment" && node.is_named()).then_some(Self(node))
+    }
+
+    fn syntax(&self) -> &'tree Node {
+        self.0
+    }
+}
+
+/// An `identifier` node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Identifier<'tree>(&'tree Node);
+
+impl<'tree> AstNode<'tree> for Identifier<'tree> {
+    fn cast(node: &'tree Node) -> Option<Self> {
+        (node.kind() == "identifier" && node.is_named()).then_some(Self(node))
+    }
+
+    fn syntax(&self) -> &'tree Node {
+        self.0
+    }
+}
+
+/// A `number` node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Number<'tree>(&'tree Node);
+
+impl<'tree> AstNode<'tree> for Number<'tree> {
+    fn cast(node: &'tree Node) -> Option<Self> {
+        (node.kind() == "number" && node.is_named()).then_some(Self(node))
+    }
+
+    fn syntax(&self) -> &'tree Node {
+        self.0
+    }
+}
@@ -0,0 +1,183 @@
+// Parse tables for the `template` grammar, generated by sapling.
+// Do not edit by hand.
+
+use sapling::language::{
+    FieldMapEntry, FieldMapSlice, Language, LexMode, Lexing, ParseAction, ParseActionEntry,
+    ParseTableEntry, StateId, SymbolId, SymbolMetadata,
+};
+use sapling::Lexer;
+use std::borrow::Cow;
+
+/// The `template` language.
+pub static LANGUAGE: Language = Language {
+    name: Cow::Borrowed("template"),
+    symbol_names: Cow::Borrowed(SYMBOL_NAMES),
+    symbol_metadata: Cow::Borrowed(SYMBOL_METADATA),
+    token_count: 4,
+    field_names: Cow::Borrowed(FIELD_NAMES),
+    parse_states: Cow::Borrowed(PARSE_STATES),
+    parse_table: Cow::Borrowed(PARSE_TABLE),
+    parse_actions: Cow::Borrowed(PARSE_ACTIONS),
+    alias_sequences: Cow::Borrowed(ALIAS_SEQUENCES),
+    max_alias_sequence_length: 0,
+    field_map_slices: Cow::Borrowed(FIELD_MAP_SLICES),
+    field_map_entries: Cow::Borrowed(FIELD_MAP_ENTRIES),
+    lex_modes: Cow::Borrowed(LEX_MODES),
+    lexing: Lexing::Function(lex),
+    external_symbols: Cow::Borrowed(EXTERNAL_SYMBOLS),
+    external_scanner_states: Cow::Borrowed(EXTERNAL_SCANNER_STATES),
+    external_scanner: None,
+};
+
+const SYMBOL_NAMES: &[Cow<'static, str>] = &[
+    Cow::Borrowed("end"), // 0
+    Cow::Borrowed("{"), // 1
+    Cow::Borrowed("}"), // 2
+    Cow::Borrowed("text_token1"), // 3
+    Cow::Borrowed("template"), // 4
+    Cow::Borrowed("embed"), // 5
+    Cow::Borrowed("text"), // 6
+    Cow::Borrowed("code"), // 7
+    Cow::Borrowed("template_repeat1"), // 8
+];
+
+const SYMBOL_METADATA: &[SymbolMetadata] = &[
+    SymbolMetadata { visible: false, named: true, supertype: false }, // end
+    SymbolMetadata { visible: true, named: false, supertype: false }, // {
+    SymbolMetadata { visible: true, named: false, supertype: false }, // }
+    SymbolMetadata { visible: false, named: false, supertype: false }, // text_token1
+    SymbolMetadata { visible: true, named: true, supertype: false }, // template
+    SymbolMetadata { visible: true, named: true, supertype: false }, // embed
+    SymbolMetadata { visible: true, named: true, supertype: false }, // text
+    SymbolMetadata { visible: true, named: true, supertype: false }, // code
+    SymbolMetadata { visible: false, named: false, supertype: false }, // template_repeat1
+];
+
+const FIELD_NAMES: &[Cow<'static, str>] = &[
+    Cow::Borrowed("code"),
+];
+
+const PARSE_STATES: &[u32] = &[
+    0, 7, 9, 12, 13, 16, 19, 24, 25, 26, 29, 32, 35,
+];
+
+const PARSE_TABLE: &[ParseTableEntry] = &[
+    // state 0
+    ParseTableEntry::new(0, 1), ParseTableEntry::new(1, 3), ParseTableEntry::new(3, 5),
+    ParseTableEntry::new(4, 3), ParseTableEntry::new(5, 4), ParseTableEntry::new(6, 5),
+    ParseTableEntry::new(8, 6),
+    // state 1
+    ParseTableEntry::new(3, 7), ParseTableEntry::new(7, 8),
+    // state 2
+    ParseTableEntry::new(0, 9), ParseTableEntry::new(1, 9), ParseTableEntry::new(3, 9),
+    // state 3
+    ParseTableEntry::new(0, 11),
+    // state 4
+    ParseTableEntry::new(0, 13), ParseTableEntry::new(1, 13), ParseTableEntry::new(3, 13),
+    // state 5
+    ParseTableEntry::new(0, 13), ParseTableEntry::new(1, 13), ParseTableEntry::new(3, 13),
+    // state 6
+    ParseTableEntry::new(0, 15), ParseTableEntry::new(1, 3), ParseTableEntry::new(3, 5),
+    ParseTableEntry::new(5, 9), ParseTableEntry::new(6, 10),
+    // state 7
+    ParseTableEntry::new(2, 17),
+    // state 8
+    ParseTableEntry::new(2, 19),
+    // state 9
+    ParseTableEntry::new(0, 21), ParseTableEntry::new(1, 21), ParseTableEntry::new(3, 21),
+    // state 10
+    ParseTableEntry::new(0, 21), ParseTableEntry::new(1, 21), ParseTableEntry::new(3, 21),
+    // state 11
+    ParseTableEntry::new(0, 23), ParseTableEntry::new(1, 23), ParseTableEntry::new(3, 23),
+];
+
+const PARSE_ACTIONS: &[ParseActionEntry] = &[
+    ParseActionEntry::Header { count: 0 }, // 0
+    ParseActionEntry::Header { count: 1 }, // 1
+    ParseActionEntry::Action(ParseAction::Reduce { symbol: 4, child_count: 0, production_id: 0 }), // template
+    ParseActionEntry::Header { count: 1 }, // 3
+    ParseActionEntry::Action(ParseAction::Shift { state: 1, extra: false }),
+    ParseActionEntry::Header { count: 1 }, // 5
+    ParseActionEntry::Action(ParseAction::Shift { state: 2, extra: false }),
+    ParseActionEntry::Header { count: 1 }, // 7
+    ParseActionEntry::Action(ParseAction::Shift { state: 7, extra: false }),
+    ParseActionEntry::Header { count: 1 }, // 9
+    ParseActionEntry::Action(ParseAction::Reduce { symbol: 6, child_count: 1, production_id: 0 }), // text
+    ParseActionEntry::Header { count: 1 }, // 11
+    ParseActionEntry::Action(ParseAction::Accept),
+    ParseActionEntry::Header { count: 1 }, // 13
+    ParseActionEntry::Action(ParseAction::Reduce { symbol: 8, child_count: 1, production_id: 0 }), // template_repeat1
+    ParseActionEntry::Header { count: 1 }, // 15
+    ParseActionEntry::Action(ParseAction::Reduce { symbol: 4, child_count: 1, production_id: 0 }), // template
+    ParseActionEntry::Header { count: 1 }, // 17
+    ParseActionEntry::Action(ParseAction::Reduce { symbol: 7, child_count: 1, production_id: 0 }), // code
+    ParseActionEntry::Header { count: 1 }, // 19
+    ParseActionEntry::Action(ParseAction::Shift { state: 11, extra: false }),
+    ParseActionEntry::Header { count: 1 }, // 21
+    ParseActionEntry::Action(ParseAction::Reduce { symbol: 8, child_count: 2, production_id: 0 }), // template_repeat1
+    ParseActionEntry::Header { count: 1 }, // 23
+    ParseActionEntry::Action(ParseAction::Reduce { symbol: 5, child_count: 3, production_id: 1 }), // embed
+];
+
+const ALIAS_SEQUENCES: &[u16] = &[];
+
+const FIELD_MAP_SLICES: &[FieldMapSlice] = &[
+    FieldMapSlice { index: 0, length: 0 },
+    FieldMapSlice { index: 0, length: 1 },
+];
+
+const FIELD_MAP_ENTRIES: &[FieldMapEntry] = &[
+    FieldMapEntry::new(0, 1),
+];
+
+const LEX_MODES: &[LexMode] = &[
+    LexMode::new(0, 0), LexMode::new(3, 0), LexMode::new(0, 0), LexMode::new(4, 0),
+    LexMode::new(0, 0), LexMode::new(0, 0), LexMode::new(0, 0), LexMode::new(5, 0),
+    LexMode::new(5, 0), LexMode::new(0, 0), LexMode::new(0, 0), LexMode::new(0, 0),
+];
+
+#[allow(clippy::too_many_lines)]
+fn lex(lexer: &mut Lexer<'_>, mut state: StateId) -> bool {
+    let mut result = false;
+    loop {
+        let lookahead = lexer.lookahead();
+        let (next, skip) = match state {
+            0 => match lookahead {
+                Some('\0'..='z' | '|' | '~'..='\u{10ffff}') => (1, false),
+                Some('{') => (2, false),
+                _ => return result,
+            },
+            1 => {
+                lexer.accept(3); // text_token1
+                result = true;
+                match lookahead {
+                    Some('\0'..='z' | '|' | '~'..='\u{10ffff}') => (1, false),
+                    _ => return true,
+                }
+            }
+            2 => {
+                lexer.accept(1); // {
+                return true;
+            }
+            3 => match lookahead {
+                Some('\0'..='z' | '|' | '~'..='\u{10ffff}') => (1, false),
+                _ => return result,
+            },
+            5 => match lookahead {
+                Some('}') => (6, false),
+                _ => return result,
+            },
+            6 => {
+                lexer.accept(2); // }
+                return true;
+            }
+            _ => return result,
+        };
+        lexer.advance(skip);
+        state = next;
+    }
+}
+
+const EXTERNAL_SYMBOLS: &[SymbolId] = &[];
+
+const EXTERNAL_SCANNER_STATES: &[bool] = &[];
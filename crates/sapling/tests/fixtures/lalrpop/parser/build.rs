@@ -0,0 +1,29 @@
+// Build script of the crate `tests/lalrpop.rs` compiles: emits the
+// grammar named by `EXPR_GRAMMAR` for lalrpop and runs lalrpop on it.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+fn main() {
+    let grammar = env::var("EXPR_GRAMMAR").expect("EXPR_GRAMMAR is not set");
+    println!("cargo::rerun-if-env-changed=EXPR_GRAMMAR");
+    println!("cargo::rerun-if-changed={grammar}");
+
+    let grammar = sapling::parse_grammar(&fs::read_to_string(&grammar).unwrap()).unwrap();
+    let output = sapling::emit::lalrpop::emit(&grammar).unwrap();
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    let path = out_dir.join(format!("{}.lalrpop", grammar.name));
+    fs::write(&path, &output.grammar).unwrap();
+    fs::write(
+        out_dir.join(format!("{}_tokens.rs", grammar.name)),
+        &output.tokens,
+    )
+    .unwrap();
+    lalrpop::Configuration::new()
+        .set_in_dir(&out_dir)
+        .set_out_dir(&out_dir)
+        .log_quiet()
+        .process_file(&path)
+        .unwrap();
+}
@@ -0,0 +1,86 @@
+// Parses standard input with the lalrpop parser emitted for the `expr`
+// grammar and prints the tree, for `tests/lalrpop.rs`.
+
+mod expr_tokens {
+    include!(concat!(env!("OUT_DIR"), "/expr_tokens.rs"));
+}
+
+#[allow(clippy::all, unused)]
+mod expr {
+    include!(concat!(env!("OUT_DIR"), "/expr.rs"));
+}
+
+use expr_tokens::{LexicalError, Location, Token};
+use sapling::Point;
+use std::io::Read;
+use std::process::ExitCode;
+
+type Spanned = Result<(Location, Token, Location), LexicalError>;
+
+/// A hand-written lexer for the `expr` grammar's tokens.
+struct Lexer<'s> {
+    source: &'s str,
+    at: Location,
+}
+
+impl Lexer<'_> {
+    fn advance(&mut self, len: usize) {
+        for c in self.source[self.at.byte..self.at.byte + len].chars() {
+            self.at.point = if c == '\n' {
+                Point::new(self.at.point.row + 1, 0)
+            } else {
+                Point::new(self.at.point.row, self.at.point.column + c.len_utf8())
+            };
+        }
+        self.at.byte += len;
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Spanned;
+
+    fn next(&mut self) -> Option<Spanned> {
+        let rest = &self.source[self.at.byte..];
+        let blank = rest.len() - rest.trim_start().len();
+        self.advance(blank);
+        let rest = &self.source[self.at.byte..];
+        let first = rest.chars().next()?;
+        let run = |accept: fn(&char) -> bool| rest.chars().take_while(accept).count();
+        let (token, len) = match first {
+            ';' => (Token::Semi, 1),
+            '+' => (Token::Plus, 1),
+            '-' => (Token::Dash, 1),
+            '*' => (Token::Star, 1),
+            '/' => (Token::Slash, 1),
+            '^' => (Token::Caret, 1),
+            '(' => (Token::Lparen, 1),
+            ',' => (Token::Comma, 1),
+            ')' => (Token::Rparen, 1),
+            'a'..='z' => (Token::Identifier, run(char::is_ascii_lowercase)),
+            '0'..='9' => (Token::Number, run(char::is_ascii_digit)),
+            _ => return Some(Err(LexicalError { location: self.at })),
+        };
+        let start = self.at;
+        self.advance(len);
+        Some(Ok((start, token, self.at)))
+    }
+}
+
+fn main() -> ExitCode {
+    let mut source = String::new();
+    std::io::stdin().read_to_string(&mut source).unwrap();
+    let lexer = Lexer {
+        source: &source,
+        at: Location::default(),
+    };
+    match expr::ProgramParser::new().parse(lexer) {
+        Ok(tree) => {
+            println!("{}", tree.to_sexp());
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("{error:?}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,264 @@
+// Parse tables for the `indent` grammar, generated by sapling.
+// Do not edit by hand.
+
+use sapling::language::{
+    FieldMapEntry, FieldMapSlice, Language, LexMode, Lexing, ParseAction, ParseActionEntry,
+    ParseTableEntry, StateId, SymbolId, SymbolMetadata,
+};
+use sapling::ExternalScanner;
+use sapling::Lexer;
+use std::borrow::Cow;
+
+/// The `indent` language.
+pub static LANGUAGE: Language = Language {
+    name: Cow::Borrowed("indent"),
+    symbol_names: Cow::Borrowed(SYMBOL_NAMES),
+    symbol_metadata: Cow::Borrowed(SYMBOL_METADATA),
+    token_count: 7,
+    field_names: Cow::Borrowed(FIELD_NAMES),
+    parse_states: Cow::Borrowed(PARSE_STATES),
+    parse_table: Cow::Borrowed(PARSE_TABLE),
+    parse_actions: Cow::Borrowed(PARSE_ACTIONS),
+    alias_sequences: Cow::Borrowed(ALIAS_SEQUENCES),
+    max_alias_sequence_length: 0,
+    field_map_slices: Cow::Borrowed(FIELD_MAP_SLICES),
+    field_map_entries: Cow::Borrowed(FIELD_MAP_ENTRIES),
+    lex_modes: Cow::Borrowed(LEX_MODES),
+    lexing: Lexing::Function(lex),
+    external_symbols: Cow::Borrowed(EXTERNAL_SYMBOLS),
+    external_scanner_states: Cow::Borrowed(EXTERNAL_SCANNER_STATES),
+    external_scanner: Some(new_scanner),
+};
+
+const SYMBOL_NAMES: &[Cow<'static, str>] = &[
+    Cow::Borrowed("end"), // 0
+    Cow::Borrowed("if"), // 1
+    Cow::Borrowed(":"), // 2
+    Cow::Borrowed("identifier"), // 3
+    Cow::Borrowed("_newline"), // 4
+    Cow::Borrowed("_indent"), // 5
+    Cow::Borrowed("_dedent"), // 6
+    Cow::Borrowed("module"), // 7
+    Cow::Borrowed("_statement"), // 8
+    Cow::Borrowed("expression_statement"), // 9
+    Cow::Borrowed("if_statement"), // 10
+    Cow::Borrowed("block"), // 11
+    Cow::Borrowed("module_repeat1"), // 12
+];
+
+const SYMBOL_METADATA: &[SymbolMetadata] = &[
+    SymbolMetadata { visible: false, named: true, supertype: false }, // end
+    SymbolMetadata { visible: true, named: false, supertype: false }, // if
+    SymbolMetadata { visible: true, named: false, supertype: false }, // :
+    SymbolMetadata { visible: true, named: true, supertype: false }, // identifier
+    SymbolMetadata { visible: false, named: true, supertype: false }, // _newline
+    SymbolMetadata { visible: false, named: true, supertype: false }, // _indent
+    SymbolMetadata { visible: false, named: true, supertype: false }, // _dedent
+    SymbolMetadata { visible: true, named: true, supertype: false }, // module
+    SymbolMetadata { visible: false, named: true, supertype: false }, // _statement
+    SymbolMetadata { visible: true, named: true, supertype: false }, // expression_statement
+    SymbolMetadata { visible: true, named: true, supertype: false }, // if_statement
+    SymbolMetadata { visible: true, named: true, supertype: false }, // block
+    SymbolMetadata { visible: false, named: false, supertype: false }, // module_repeat1
+];
+
+const FIELD_NAMES: &[Cow<'static, str>] = &[
+    Cow::Borrowed("body"),
+    Cow::Borrowed("condition"),
+];
+
+const PARSE_STATES: &[u32] = &[
+    0, 8, 9, 10, 11, 15, 19, 23, 29, 30, 34, 38, 40, 46, 50, 56, 60,
+];
+
+const PARSE_TABLE: &[ParseTableEntry] = &[
+    // state 0
+    ParseTableEntry::new(0, 1), ParseTableEntry::new(1, 3), ParseTableEntry::new(3, 5),
+    ParseTableEntry::new(7, 3), ParseTableEntry::new(8, 4), ParseTableEntry::new(9, 5),
+    ParseTableEntry::new(10, 6), ParseTableEntry::new(12, 7),
+    // state 1
+    ParseTableEntry::new(3, 7),
+    // state 2
+    ParseTableEntry::new(4, 9),
+    // state 3
+    ParseTableEntry::new(0, 11),
+    // state 4
+    ParseTableEntry::new(0, 13), ParseTableEntry::new(1, 13), ParseTableEntry::new(3, 13),
+    ParseTableEntry::new(6, 13),
+    // state 5
+    ParseTableEntry::new(0, 15), ParseTableEntry::new(1, 15), ParseTableEntry::new(3, 15),
+    ParseTableEntry::new(6, 15),
+    // state 6
+    ParseTableEntry::new(0, 15), ParseTableEntry::new(1, 15), ParseTableEntry::new(3, 15),
+    ParseTableEntry::new(6, 15),
+    // state 7
+    ParseTableEntry::new(0, 17), ParseTableEntry::new(1, 3), ParseTableEntry::new(3, 5),
+    ParseTableEntry::new(8, 10), ParseTableEntry::new(9, 5), ParseTableEntry::new(10, 6),
+    // state 8
+    ParseTableEntry::new(2, 19),
+    // state 9
+    ParseTableEntry::new(0, 21), ParseTableEntry::new(1, 21), ParseTableEntry::new(3, 21),
+    ParseTableEntry::new(6, 21),
+    // state 10
+    ParseTableEntry::new(0, 23), ParseTableEntry::new(1, 23), ParseTableEntry::new(3, 23),
+    ParseTableEntry::new(6, 23),
+    // state 11
+    ParseTableEntry::new(5, 25), ParseTableEntry::new(11, 13),
+    // state 12
+    ParseTableEntry::new(1, 3), ParseTableEntry::new(3, 5), ParseTableEntry::new(8, 4),
+    ParseTableEntry::new(9, 5), ParseTableEntry::new(10, 6), ParseTableEntry::new(12, 14),
+    // state 13
+    ParseTableEntry::new(0, 27), ParseTableEntry::new(1, 27), ParseTableEntry::new(3, 27),
+    ParseTableEntry::new(6, 27),
+    // state 14
+    ParseTableEntry::new(1, 3), ParseTableEntry::new(3, 5), ParseTableEntry::new(6, 29),
+    ParseTableEntry::new(8, 10), ParseTableEntry::new(9, 5), ParseTableEntry::new(10, 6),
+    // state 15
+    ParseTableEntry::new(0, 31), ParseTableEntry::new(1, 31), ParseTableEntry::new(3, 31),
+    ParseTableEntry::new(6, 31),
+];
+
+const PARSE_ACTIONS: &[ParseActionEntry] = &[
+    ParseActionEntry::Header { count: 0 }, // 0
+    ParseActionEntry::Header { count: 1 }, // 1
+    ParseActionEntry::Action(ParseAction::Reduce { symbol: 7, child_count: 0, production_id: 0 }), // module
+    ParseActionEntry::Header { count: 1 }, // 3
+    ParseActionEntry::Action(ParseAction::Shift { state: 1, extra: false }),
+    ParseActionEntry::Header { count: 1 }, // 5
+    ParseActionEntry::Action(ParseAction::Shift { state: 2, extra: false }),
+    ParseActionEntry::Header { count: 1 }, // 7
+    ParseActionEntry::Action(ParseAction::Shift { state: 8, extra: false }),
+    ParseActionEntry::Header { count: 1 }, // 9
+    ParseActionEntry::Action(ParseAction::Shift { state: 9, extra: false }),
+    ParseActionEntry::Header { count: 1 }, // 11
+    ParseActionEntry::Action(ParseAction::Accept),
+    ParseActionEntry::Header { count: 1 }, // 13
+    ParseActionEntry::Action(ParseAction::Reduce { symbol: 12, child_count: 1, production_id: 0 }), // module_repeat1
+    ParseActionEntry::Header { count: 1 }, // 15
+    ParseActionEntry::Action(ParseAction::Reduce { symbol: 8, child_count: 1, production_id: 0 }), // _statement
+    ParseActionEntry::Header { count: 1 }, // 17
+    ParseActionEntry::Action(ParseAction::Reduce { symbol: 7, child_count: 1, production_id: 0 }), // module
+    ParseActionEntry::Header { count: 1 }, // 19
+    ParseActionEntry::Action(ParseAction::Shift { state: 11, extra: false }),
+    ParseActionEntry::Header { count: 1 }, // 21
+    ParseActionEntry::Action(ParseAction::Reduce { symbol: 9, child_count: 2, production_id: 0 }), // expression_statement
+    ParseActionEntry::Header { count: 1 }, // 23
+    ParseActionEntry::Action(ParseAction::Reduce { symbol: 12, child_count: 2, production_id: 0 }), // module_repeat1
+    ParseActionEntry::Header { count: 1 }, // 25
+    ParseActionEntry::Action(ParseAction::Shift { state: 12, extra: false }),
+    ParseActionEntry::Header { count: 1 }, // 27
+    ParseActionEntry::Action(ParseAction::Reduce { symbol: 10, child_count: 4, production_id: 1 }), // if_statement
+    ParseActionEntry::Header { count: 1 }, // 29
+    ParseActionEntry::Action(ParseAction::Shift { state: 15, extra: false }),
+    ParseActionEntry::Header { count: 1 }, // 31
+    ParseActionEntry::Action(ParseAction::Reduce { symbol: 11, child_count: 3, production_id: 0 }), // block
+];
+
+const ALIAS_SEQUENCES: &[u16] = &[];
+
+const FIELD_MAP_SLICES: &[FieldMapSlice] = &[
+    FieldMapSlice { index: 0, length: 0 },
+    FieldMapSlice { index: 0, length: 2 },
+];
+
+const FIELD_MAP_ENTRIES: &[FieldMapEntry] = &[
+    FieldMapEntry::new(1, 1), FieldMapEntry::new(0, 3),
+];
+
+const LEX_MODES: &[LexMode] = &[
+    LexMode::new(0, 0), LexMode::new(4, 0), LexMode::new(5, 1), LexMode::new(5, 0),
+    LexMode::new(0, 2), LexMode::new(0, 2), LexMode::new(0, 2), LexMode::new(0, 0),
+    LexMode::new(6, 0), LexMode::new(0, 2), LexMode::new(0, 2), LexMode::new(5, 3),
+    LexMode::new(0, 0), LexMode::new(0, 2), LexMode::new(0, 2), LexMode::new(0, 2),
+];
+
+#[allow(clippy::too_many_lines)]
+fn lex(lexer: &mut Lexer<'_>, mut state: StateId) -> bool {
+    let mut result = false;
+    loop {
+        let lookahead = lexer.lookahead();
+        let (next, skip) = match state {
+            0 => match lookahead {
+                Some(
+                    '\t'..='\r' | ' ' | '\u{85}' | '\u{a0}' | '\u{1680}' | '\u{2000}'..='\u{200a}'
+                    | '\u{2028}'..='\u{2029}' | '\u{202f}' | '\u{205f}' | '\u{3000}'
+                ) => (0, true),
+                Some('a'..='h' | 'j'..='z') => (1, false),
+                Some('i') => (2, false),
+                _ => return result,
+            },
+            1 => {
+                lexer.accept(3); // identifier
+                result = true;
+                match lookahead {
+                    Some('a'..='z') => (1, false),
+                    _ => return true,
+                }
+            }
+            2 => {
+                lexer.accept(3); // identifier
+                result = true;
+                match lookahead {
+                    Some('a'..='e' | 'g'..='z') => (1, false),
+                    Some('f') => (3, false),
+                    _ => return true,
+                }
+            }
+            3 => {
+                lexer.accept(1); // if
+                result = true;
+                match lookahead {
+                    Some('a'..='z') => (1, false),
+                    _ => return true,
+                }
+            }
+            4 => match lookahead {
+                Some(
+                    '\t'..='\r' | ' ' | '\u{85}' | '\u{a0}' | '\u{1680}' | '\u{2000}'..='\u{200a}'
+                    | '\u{2028}'..='\u{2029}' | '\u{202f}' | '\u{205f}' | '\u{3000}'
+                ) => (4, true),
+                Some('a'..='z') => (1, false),
+                _ => return result,
+            },
+            5 => match lookahead {
+                Some(
+                    '\t'..='\r' | ' ' | '\u{85}' | '\u{a0}' | '\u{1680}' | '\u{2000}'..='\u{200a}'
+                    | '\u{2028}'..='\u{2029}' | '\u{202f}' | '\u{205f}' | '\u{3000}'
+                ) => (5, true),
+                _ => return result,
+            },
+            6 => match lookahead {
+                Some(
+                    '\t'..='\r' | ' ' | '\u{85}' | '\u{a0}' | '\u{1680}' | '\u{2000}'..='\u{200a}'
+                    | '\u{2028}'..='\u{2029}' | '\u{202f}' | '\u{205f}' | '\u{3000}'
+                ) => (6, true),
+                Some(':') => (7, false),
+                _ => return result,
+            },
+            7 => {
+                lexer.accept(2); // :
+                return true;
+            }
+            _ => return result,
+        };
+        lexer.advance(skip);
+        state = next;
+    }
+}
+
+const EXTERNAL_SYMBOLS: &[SymbolId] = &[
+    4, // _newline
+    5, // _indent
+    6, // _dedent
+];
+
+const EXTERNAL_SCANNER_STATES: &[bool] = &[
+    false, false, false, // 0
+    true, false, false, // 1
+    false, false, true, // 2
+    false, true, false, // 3
+];
+
+fn new_scanner() -> Box<dyn ExternalScanner> {
+    Box::new(<crate::scanner::IndentScanner as Default>::default())
+}
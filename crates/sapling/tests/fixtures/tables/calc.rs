@@ -0,0 +1,710 @@
+// Parse tables for the `calc` grammar, generated by sapling.
+// Do not edit by hand.
+
+use sapling::language::{
+    FieldMapEntry, FieldMapSlice, Language, LexMode, Lexing, ParseAction, ParseActionEntry,
+    ParseTableEntry, StateId, SymbolId, SymbolMetadata,
+};
+use sapling::Lexer;
+use std::borrow::Cow;
+
+/// The `calc` language.
+pub static LANGUAGE: Language = Language {
+    name: Cow::Borrowed("calc"),
+    symbol_names: Cow::Borrowed(SYMBOL_NAMES),
+    symbol_metadata: Cow::Borrowed(SYMBOL_METADATA),
+    token_count: 14,
+    field_names: Cow::Borrowed(FIELD_NAMES),
+    parse_states: Cow::Borrowed(PARSE_STATES),
+    parse_table: Cow::Borrowed(PARSE_TABLE),
+    parse_actions: Cow::Borrowed(PARSE_ACTIONS),
+    alias_sequences: Cow::Borrowed(ALIAS_SEQUENCES),
+    max_alias_sequence_length: 0,
+    field_map_slices: Cow::Borrowed(FIELD_MAP_SLICES),
+    field_map_entries: Cow::Borrowed(FIELD_MAP_ENTRIES),
+    lex_modes: Cow::Borrowed(LEX_MODES),
+    lexing: Lexing::Keywords {
+        lex,
+        keyword_lex,
+        keyword_capture_token: 11,
+    },
+    external_symbols: Cow::Borrowed(EXTERNAL_SYMBOLS),
+    external_scanner_states: Cow::Borrowed(EXTERNAL_SCANNER_STATES),
+    external_scanner: None,
+};
+
+const SYMBOL_NAMES: &[Cow<'static, str>] = &[
+    Cow::Borrowed("end"), // 0
+    Cow::Borrowed("let"), // 1
+    Cow::Borrowed(":"), // 2
+    Cow::Borrowed("="), // 3
+    Cow::Borrowed(";"), // 4
+    Cow::Borrowed("+"), // 5
+    Cow::Borrowed("-"), // 6
+    Cow::Borrowed("+="), // 7
+    Cow::Borrowed("("), // 8
+    Cow::Borrowed(","), // 9
+    Cow::Borrowed(")"), // 10
+    Cow::Borrowed("identifier"), // 11
+    Cow::Borrowed("number"), // 12
+    Cow::Borrowed("comment"), // 13
+    Cow::Borrowed("program"), // 14
+    Cow::Borrowed("_statement"), // 15
+    Cow::Borrowed("let"), // 16
+    Cow::Borrowed("expression_statement"), // 17
+    Cow::Borrowed("_expression"), // 18
+    Cow::Borrowed("_primary"), // 19
+    Cow::Borrowed("binary"), // 20
+    Cow::Borrowed("call"), // 21
+    Cow::Borrowed("program_repeat1"), // 22
+    Cow::Borrowed("call_repeat1"), // 23
+];
+
+const SYMBOL_METADATA: &[SymbolMetadata] = &[
+    SymbolMetadata { visible: false, named: true, supertype: false }, // end
+    SymbolMetadata { visible: true, named: false, supertype: false }, // let
+    SymbolMetadata { visible: true, named: false, supertype: false }, // :
+    SymbolMetadata { visible: true, named: false, supertype: false }, // =
+    SymbolMetadata { visible: true, named: false, supertype: false }, // ;
+    SymbolMetadata { visible: true, named: false, supertype: false }, // +
+    SymbolMetadata { visible: true, named: false, supertype: false }, // -
+    SymbolMetadata { visible: true, named: false, supertype: false }, // +=
+    SymbolMetadata { visible: true, named: false, supertype: false }, // (
+    SymbolMetadata { visible: true, named: false, supertype: false }, // ,
+    SymbolMetadata { visible: true, named: false, supertype: false }, // )
+    SymbolMetadata { visible: true, named: true, supertype: false }, // identifier
+    SymbolMetadata { visible: true, named: true, supertype: false }, // number
+    SymbolMetadata { visible: true, named: true, supertype: false }, // comment
+    SymbolMetadata { visible: true, named: true, supertype: false }, // program
+    SymbolMetadata { visible: false, named: true, supertype: false }, // _statement
+    SymbolMetadata { visible: true, named: true, supertype: false }, // let
+    SymbolMetadata { visible: true, named: true, supertype: false }, // expression_statement
+    SymbolMetadata { visible: false, named: true, supertype: true }, // _expression
+    SymbolMetadata { visible: false, named: true, supertype: true }, // _primary
+    SymbolMetadata { visible: true, named: true, supertype: false }, // binary
+    SymbolMetadata { visible: true, named: true, supertype: false }, // call
+    SymbolMetadata { visible: false, named: false, supertype: false }, // program_repeat1
+    SymbolMetadata { visible: false, named: false, supertype: false }, // call_repeat1
+];
+
+const FIELD_NAMES: &[Cow<'static, str>] = &[
+    Cow::Borrowed("argument"),
+    Cow::Borrowed("function"),
+    Cow::Borrowed("left"),
+    Cow::Borrowed("name"),
+    Cow::Borrowed("operator"),
+    Cow::Borrowed("right"),
+    Cow::Borrowed("type"),
+    Cow::Borrowed("value"),
+];
+
+const PARSE_STATES: &[u32] = &[
+    0, 14, 16, 24, 31, 33, 38, 43, 48, 53, 60, 67, 75, 87, 90, 98, 103, 110, 117, 124, 132, 137,
+    139, 146, 154, 161, 168, 175, 182, 190, 197, 199, 204, 211, 219, 222, 230, 233, 240, 245, 251,
+    258, 266, 274, 279, 285, 290,
+];
+
+const PARSE_TABLE: &[ParseTableEntry] = &[
+    // state 0
+    ParseTableEntry::new(0, 1), ParseTableEntry::new(1, 3), ParseTableEntry::new(11, 5),
+    ParseTableEntry::new(12, 7), ParseTableEntry::new(13, 9), ParseTableEntry::new(14, 4),
+    ParseTableEntry::new(15, 5), ParseTableEntry::new(16, 6), ParseTableEntry::new(17, 7),
+    ParseTableEntry::new(18, 8), ParseTableEntry::new(19, 9), ParseTableEntry::new(20, 10),
+    ParseTableEntry::new(21, 11), ParseTableEntry::new(22, 12),
+    // state 1
+    ParseTableEntry::new(11, 11), ParseTableEntry::new(13, 13),
+    // state 2
+    ParseTableEntry::new(4, 15), ParseTableEntry::new(5, 15), ParseTableEntry::new(6, 15),
+    ParseTableEntry::new(7, 15), ParseTableEntry::new(8, 17), ParseTableEntry::new(9, 15),
+    ParseTableEntry::new(10, 15), ParseTableEntry::new(13, 19),
+    // state 3
+    ParseTableEntry::new(4, 15), ParseTableEntry::new(5, 15), ParseTableEntry::new(6, 15),
+    ParseTableEntry::new(7, 15), ParseTableEntry::new(9, 15), ParseTableEntry::new(10, 15),
+    ParseTableEntry::new(13, 21),
+    // state 4
+    ParseTableEntry::new(0, 23), ParseTableEntry::new(13, 25),
+    // state 5
+    ParseTableEntry::new(0, 27), ParseTableEntry::new(1, 27), ParseTableEntry::new(11, 27),
+    ParseTableEntry::new(12, 27), ParseTableEntry::new(13, 29),
+    // state 6
+    ParseTableEntry::new(0, 31), ParseTableEntry::new(1, 31), ParseTableEntry::new(11, 31),
+    ParseTableEntry::new(12, 31), ParseTableEntry::new(13, 33),
+    // state 7
+    ParseTableEntry::new(0, 31), ParseTableEntry::new(1, 31), ParseTableEntry::new(11, 31),
+    ParseTableEntry::new(12, 31), ParseTableEntry::new(13, 35),
+    // state 8
+    ParseTableEntry::new(4, 37), ParseTableEntry::new(5, 39), ParseTableEntry::new(6, 41),
+    ParseTableEntry::new(7, 43), ParseTableEntry::new(13, 45),
+    // state 9
+    ParseTableEntry::new(4, 47), ParseTableEntry::new(5, 47), ParseTableEntry::new(6, 47),
+    ParseTableEntry::new(7, 47), ParseTableEntry::new(9, 47), ParseTableEntry::new(10, 47),
+    ParseTableEntry::new(13, 49),
+    // state 10
+    ParseTableEntry::new(4, 47), ParseTableEntry::new(5, 47), ParseTableEntry::new(6, 47),
+    ParseTableEntry::new(7, 47), ParseTableEntry::new(9, 47), ParseTableEntry::new(10, 47),
+    ParseTableEntry::new(13, 51),
+    // state 11
+    ParseTableEntry::new(4, 47), ParseTableEntry::new(5, 47), ParseTableEntry::new(6, 47),
+    ParseTableEntry::new(7, 47), ParseTableEntry::new(8, 53), ParseTableEntry::new(9, 47),
+    ParseTableEntry::new(10, 47), ParseTableEntry::new(13, 55),
+    // state 12
+    ParseTableEntry::new(0, 57), ParseTableEntry::new(1, 3), ParseTableEntry::new(11, 5),
+    ParseTableEntry::new(12, 7), ParseTableEntry::new(13, 59), ParseTableEntry::new(15, 20),
+    ParseTableEntry::new(16, 6), ParseTableEntry::new(17, 7), ParseTableEntry::new(18, 8),
+    ParseTableEntry::new(19, 9), ParseTableEntry::new(20, 10), ParseTableEntry::new(21, 11),
+    // state 13
+    ParseTableEntry::new(2, 61), ParseTableEntry::new(3, 63), ParseTableEntry::new(13, 65),
+    // state 14
+    ParseTableEntry::new(10, 67), ParseTableEntry::new(11, 5), ParseTableEntry::new(12, 7),
+    ParseTableEntry::new(13, 69), ParseTableEntry::new(18, 24), ParseTableEntry::new(19, 9),
+    ParseTableEntry::new(20, 10), ParseTableEntry::new(21, 11),
+    // state 15
+    ParseTableEntry::new(0, 71), ParseTableEntry::new(1, 71), ParseTableEntry::new(11, 71),
+    ParseTableEntry::new(12, 71), ParseTableEntry::new(13, 73),
+    // state 16
+    ParseTableEntry::new(11, 5), ParseTableEntry::new(12, 7), ParseTableEntry::new(13, 75),
+    ParseTableEntry::new(18, 25), ParseTableEntry::new(19, 9), ParseTableEntry::new(20, 10),
+    ParseTableEntry::new(21, 11),
+    // state 17
+    ParseTableEntry::new(11, 5), ParseTableEntry::new(12, 7), ParseTableEntry::new(13, 77),
+    ParseTableEntry::new(18, 26), ParseTableEntry::new(19, 9), ParseTableEntry::new(20, 10),
+    ParseTableEntry::new(21, 11),
+    // state 18
+    ParseTableEntry::new(11, 5), ParseTableEntry::new(12, 7), ParseTableEntry::new(13, 79),
+    ParseTableEntry::new(18, 27), ParseTableEntry::new(19, 9), ParseTableEntry::new(20, 10),
+    ParseTableEntry::new(21, 11),
+    // state 19
+    ParseTableEntry::new(10, 81), ParseTableEntry::new(11, 5), ParseTableEntry::new(12, 7),
+    ParseTableEntry::new(13, 83), ParseTableEntry::new(18, 29), ParseTableEntry::new(19, 9),
+    ParseTableEntry::new(20, 10), ParseTableEntry::new(21, 11),
+    // state 20
+    ParseTableEntry::new(0, 85), ParseTableEntry::new(1, 85), ParseTableEntry::new(11, 85),
+    ParseTableEntry::new(12, 85), ParseTableEntry::new(13, 87),
+    // state 21
+    ParseTableEntry::new(11, 89), ParseTableEntry::new(13, 91),
+    // state 22
+    ParseTableEntry::new(11, 5), ParseTableEntry::new(12, 7), ParseTableEntry::new(13, 93),
+    ParseTableEntry::new(18, 31), ParseTableEntry::new(19, 9), ParseTableEntry::new(20, 10),
+    ParseTableEntry::new(21, 11),
+    // state 23
+    ParseTableEntry::new(4, 95), ParseTableEntry::new(5, 95), ParseTableEntry::new(6, 95),
+    ParseTableEntry::new(7, 95), ParseTableEntry::new(8, 95), ParseTableEntry::new(9, 95),
+    ParseTableEntry::new(10, 95), ParseTableEntry::new(13, 97),
+    // state 24
+    ParseTableEntry::new(5, 39), ParseTableEntry::new(6, 41), ParseTableEntry::new(7, 43),
+    ParseTableEntry::new(9, 99), ParseTableEntry::new(10, 101), ParseTableEntry::new(13, 103),
+    ParseTableEntry::new(23, 34),
+    // state 25
+    ParseTableEntry::new(4, 105), ParseTableEntry::new(5, 105), ParseTableEntry::new(6, 105),
+    ParseTableEntry::new(7, 105), ParseTableEntry::new(9, 105), ParseTableEntry::new(10, 105),
+    ParseTableEntry::new(13, 107),
+    // state 26
+    ParseTableEntry::new(4, 105), ParseTableEntry::new(5, 105), ParseTableEntry::new(6, 105),
+    ParseTableEntry::new(7, 105), ParseTableEntry::new(9, 105), ParseTableEntry::new(10, 105),
+    ParseTableEntry::new(13, 109),
+    // state 27
+    ParseTableEntry::new(4, 105), ParseTableEntry::new(5, 105), ParseTableEntry::new(6, 105),
+    ParseTableEntry::new(7, 105), ParseTableEntry::new(9, 105), ParseTableEntry::new(10, 105),
+    ParseTableEntry::new(13, 111),
+    // state 28
+    ParseTableEntry::new(4, 95), ParseTableEntry::new(5, 95), ParseTableEntry::new(6, 95),
+    ParseTableEntry::new(7, 95), ParseTableEntry::new(8, 95), ParseTableEntry::new(9, 95),
+    ParseTableEntry::new(10, 95), ParseTableEntry::new(13, 113),
+    // state 29
+    ParseTableEntry::new(5, 39), ParseTableEntry::new(6, 41), ParseTableEntry::new(7, 43),
+    ParseTableEntry::new(9, 99), ParseTableEntry::new(10, 115), ParseTableEntry::new(13, 117),
+    ParseTableEntry::new(23, 36),
+    // state 30
+    ParseTableEntry::new(3, 119), ParseTableEntry::new(13, 121),
+    // state 31
+    ParseTableEntry::new(4, 123), ParseTableEntry::new(5, 39), ParseTableEntry::new(6, 41),
+    ParseTableEntry::new(7, 43), ParseTableEntry::new(13, 125),
+    // state 32
+    ParseTableEntry::new(11, 5), ParseTableEntry::new(12, 7), ParseTableEntry::new(13, 127),
+    ParseTableEntry::new(18, 39), ParseTableEntry::new(19, 9), ParseTableEntry::new(20, 10),
+    ParseTableEntry::new(21, 11),
+    // state 33
+    ParseTableEntry::new(4, 129), ParseTableEntry::new(5, 129), ParseTableEntry::new(6, 129),
+    ParseTableEntry::new(7, 129), ParseTableEntry::new(8, 129), ParseTableEntry::new(9, 129),
+    ParseTableEntry::new(10, 129), ParseTableEntry::new(13, 131),
+    // state 34
+    ParseTableEntry::new(9, 133), ParseTableEntry::new(10, 135), ParseTableEntry::new(13, 137),
+    // state 35
+    ParseTableEntry::new(4, 129), ParseTableEntry::new(5, 129), ParseTableEntry::new(6, 129),
+    ParseTableEntry::new(7, 129), ParseTableEntry::new(8, 129), ParseTableEntry::new(9, 129),
+    ParseTableEntry::new(10, 129), ParseTableEntry::new(13, 139),
+    // state 36
+    ParseTableEntry::new(9, 133), ParseTableEntry::new(10, 141), ParseTableEntry::new(13, 143),
+    // state 37
+    ParseTableEntry::new(11, 5), ParseTableEntry::new(12, 7), ParseTableEntry::new(13, 145),
+    ParseTableEntry::new(18, 43), ParseTableEntry::new(19, 9), ParseTableEntry::new(20, 10),
+    ParseTableEntry::new(21, 11),
+    // state 38
+    ParseTableEntry::new(0, 147), ParseTableEntry::new(1, 147), ParseTableEntry::new(11, 147),
+    ParseTableEntry::new(12, 147), ParseTableEntry::new(13, 149),
+    // state 39
+    ParseTableEntry::new(5, 39), ParseTableEntry::new(6, 41), ParseTableEntry::new(7, 43),
+    ParseTableEntry::new(9, 151), ParseTableEntry::new(10, 151), ParseTableEntry::new(13, 153),
+    // state 40
+    ParseTableEntry::new(11, 5), ParseTableEntry::new(12, 7), ParseTableEntry::new(13, 155),
+    ParseTableEntry::new(18, 44), ParseTableEntry::new(19, 9), ParseTableEntry::new(20, 10),
+    ParseTableEntry::new(21, 11),
+    // state 41
+    ParseTableEntry::new(4, 157), ParseTableEntry::new(5, 157), ParseTableEntry::new(6, 157),
+    ParseTableEntry::new(7, 157), ParseTableEntry::new(8, 157), ParseTableEntry::new(9, 157),
+    ParseTableEntry::new(10, 157), ParseTableEntry::new(13, 159),
+    // state 42
+    ParseTableEntry::new(4, 157), ParseTableEntry::new(5, 157), ParseTableEntry::new(6, 157),
+    ParseTableEntry::new(7, 157), ParseTableEntry::new(8, 157), ParseTableEntry::new(9, 157),
+    ParseTableEntry::new(10, 157), ParseTableEntry::new(13, 161),
+    // state 43
+    ParseTableEntry::new(4, 163), ParseTableEntry::new(5, 39), ParseTableEntry::new(6, 41),
+    ParseTableEntry::new(7, 43), ParseTableEntry::new(13, 165),
+    // state 44
+    ParseTableEntry::new(5, 39), ParseTableEntry::new(6, 41), ParseTableEntry::new(7, 43),
+    ParseTableEntry::new(9, 167), ParseTableEntry::new(10, 167), ParseTableEntry::new(13, 169),
+    // state 45
+    ParseTableEntry::new(0, 171), ParseTableEntry::new(1, 171), ParseTableEntry::new(11, 171),
+    ParseTableEntry::new(12, 171), ParseTableEntry::new(13, 173),
+];
+
+const PARSE_ACTIONS: &[ParseActionEntry] = &[
+    ParseActionEntry::Header { count: 0 }, // 0
+    ParseActionEntry::Header { count: 1 }, // 1
+    ParseActionEntry::Action(ParseAction::Reduce { symbol: 14, child_count: 0, production_id: 0 }), // program
+    ParseActionEntry::Header { count: 1 }, // 3
+    ParseActionEntry::Action(ParseAction::Shift { state: 1, extra: false }),
+    ParseActionEntry::Header { count: 1 }, // 5
+    ParseActionEntry::Action(ParseAction::Shift { state: 2, extra: false }),
+    ParseActionEntry::Header { count: 1 }, // 7
+    ParseActionEntry::Action(ParseAction::Shift { state: 3, extra: false }),
+    ParseActionEntry::Header { count: 1 }, // 9
+    ParseActionEntry::Action(ParseAction::Shift { state: 0, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 11
+    ParseActionEntry::Action(ParseAction::Shift { state: 13, extra: false }),
+    ParseActionEntry::Header { count: 1 }, // 13
+    ParseActionEntry::Action(ParseAction::Shift { state: 1, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 15
+    ParseActionEntry::Action(ParseAction::Reduce { symbol: 19, child_count: 1, production_id: 0 }), // _primary
+    ParseActionEntry::Header { count: 1 }, // 17
+    ParseActionEntry::Action(ParseAction::Shift { state: 14, extra: false }),
+    ParseActionEntry::Header { count: 1 }, // 19
+    ParseActionEntry::Action(ParseAction::Shift { state: 2, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 21
+    ParseActionEntry::Action(ParseAction::Shift { state: 3, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 23
+    ParseActionEntry::Action(ParseAction::Accept),
+    ParseActionEntry::Header { count: 1 }, // 25
+    ParseActionEntry::Action(ParseAction::Shift { state: 4, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 27
+    ParseActionEntry::Action(ParseAction::Reduce { symbol: 22, child_count: 1, production_id: 0 }), // program_repeat1
+    ParseActionEntry::Header { count: 1 }, // 29
+    ParseActionEntry::Action(ParseAction::Shift { state: 5, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 31
+    ParseActionEntry::Action(ParseAction::Reduce { symbol: 15, child_count: 1, production_id: 0 }), // _statement
+    ParseActionEntry::Header { count: 1 }, // 33
+    ParseActionEntry::Action(ParseAction::Shift { state: 6, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 35
+    ParseActionEntry::Action(ParseAction::Shift { state: 7, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 37
+    ParseActionEntry::Action(ParseAction::Shift { state: 15, extra: false }),
+    ParseActionEntry::Header { count: 1 }, // 39
+    ParseActionEntry::Action(ParseAction::Shift { state: 16, extra: false }),
+    ParseActionEntry::Header { count: 1 }, // 41
+    ParseActionEntry::Action(ParseAction::Shift { state: 17, extra: false }),
+    ParseActionEntry::Header { count: 1 }, // 43
+    ParseActionEntry::Action(ParseAction::Shift { state: 18, extra: false }),
+    ParseActionEntry::Header { count: 1 }, // 45
+    ParseActionEntry::Action(ParseAction::Shift { state: 8, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 47
+    ParseActionEntry::Action(ParseAction::Reduce { symbol: 18, child_count: 1, production_id: 0 }), // _expression
+    ParseActionEntry::Header { count: 1 }, // 49
+    ParseActionEntry::Action(ParseAction::Shift { state: 9, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 51
+    ParseActionEntry::Action(ParseAction::Shift { state: 10, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 53
+    ParseActionEntry::Action(ParseAction::Shift { state: 19, extra: false }),
+    ParseActionEntry::Header { count: 1 }, // 55
+    ParseActionEntry::Action(ParseAction::Shift { state: 11, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 57
+    ParseActionEntry::Action(ParseAction::Reduce { symbol: 14, child_count: 1, production_id: 0 }), // program
+    ParseActionEntry::Header { count: 1 }, // 59
+    ParseActionEntry::Action(ParseAction::Shift { state: 12, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 61
+    ParseActionEntry::Action(ParseAction::Shift { state: 21, extra: false }),
+    ParseActionEntry::Header { count: 1 }, // 63
+    ParseActionEntry::Action(ParseAction::Shift { state: 22, extra: false }),
+    ParseActionEntry::Header { count: 1 }, // 65
+    ParseActionEntry::Action(ParseAction::Shift { state: 13, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 67
+    ParseActionEntry::Action(ParseAction::Shift { state: 23, extra: false }),
+    ParseActionEntry::Header { count: 1 }, // 69
+    ParseActionEntry::Action(ParseAction::Shift { state: 14, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 71
+    ParseActionEntry::Action(ParseAction::Reduce { symbol: 17, child_count: 2, production_id: 0 }), // expression_statement
+    ParseActionEntry::Header { count: 1 }, // 73
+    ParseActionEntry::Action(ParseAction::Shift { state: 15, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 75
+    ParseActionEntry::Action(ParseAction::Shift { state: 16, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 77
+    ParseActionEntry::Action(ParseAction::Shift { state: 17, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 79
+    ParseActionEntry::Action(ParseAction::Shift { state: 18, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 81
+    ParseActionEntry::Action(ParseAction::Shift { state: 28, extra: false }),
+    ParseActionEntry::Header { count: 1 }, // 83
+    ParseActionEntry::Action(ParseAction::Shift { state: 19, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 85
+    ParseActionEntry::Action(ParseAction::Reduce { symbol: 22, child_count: 2, production_id: 0 }), // program_repeat1
+    ParseActionEntry::Header { count: 1 }, // 87
+    ParseActionEntry::Action(ParseAction::Shift { state: 20, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 89
+    ParseActionEntry::Action(ParseAction::Shift { state: 30, extra: false }),
+    ParseActionEntry::Header { count: 1 }, // 91
+    ParseActionEntry::Action(ParseAction::Shift { state: 21, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 93
+    ParseActionEntry::Action(ParseAction::Shift { state: 22, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 95
+    ParseActionEntry::Action(ParseAction::Reduce { symbol: 21, child_count: 3, production_id: 5 }), // call
+    ParseActionEntry::Header { count: 1 }, // 97
+    ParseActionEntry::Action(ParseAction::Shift { state: 23, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 99
+    ParseActionEntry::Action(ParseAction::Shift { state: 32, extra: false }),
+    ParseActionEntry::Header { count: 1 }, // 101
+    ParseActionEntry::Action(ParseAction::Shift { state: 33, extra: false }),
+    ParseActionEntry::Header { count: 1 }, // 103
+    ParseActionEntry::Action(ParseAction::Shift { state: 24, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 105
+    ParseActionEntry::Action(ParseAction::Reduce { symbol: 20, child_count: 3, production_id: 3 }), // binary
+    ParseActionEntry::Header { count: 1 }, // 107
+    ParseActionEntry::Action(ParseAction::Shift { state: 25, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 109
+    ParseActionEntry::Action(ParseAction::Shift { state: 26, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 111
+    ParseActionEntry::Action(ParseAction::Shift { state: 27, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 113
+    ParseActionEntry::Action(ParseAction::Shift { state: 28, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 115
+    ParseActionEntry::Action(ParseAction::Shift { state: 35, extra: false }),
+    ParseActionEntry::Header { count: 1 }, // 117
+    ParseActionEntry::Action(ParseAction::Shift { state: 29, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 119
+    ParseActionEntry::Action(ParseAction::Shift { state: 37, extra: false }),
+    ParseActionEntry::Header { count: 1 }, // 121
+    ParseActionEntry::Action(ParseAction::Shift { state: 30, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 123
+    ParseActionEntry::Action(ParseAction::Shift { state: 38, extra: false }),
+    ParseActionEntry::Header { count: 1 }, // 125
+    ParseActionEntry::Action(ParseAction::Shift { state: 31, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 127
+    ParseActionEntry::Action(ParseAction::Shift { state: 32, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 129
+    ParseActionEntry::Action(ParseAction::Reduce { symbol: 21, child_count: 4, production_id: 4 }), // call
+    ParseActionEntry::Header { count: 1 }, // 131
+    ParseActionEntry::Action(ParseAction::Shift { state: 33, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 133
+    ParseActionEntry::Action(ParseAction::Shift { state: 40, extra: false }),
+    ParseActionEntry::Header { count: 1 }, // 135
+    ParseActionEntry::Action(ParseAction::Shift { state: 41, extra: false }),
+    ParseActionEntry::Header { count: 1 }, // 137
+    ParseActionEntry::Action(ParseAction::Shift { state: 34, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 139
+    ParseActionEntry::Action(ParseAction::Shift { state: 35, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 141
+    ParseActionEntry::Action(ParseAction::Shift { state: 42, extra: false }),
+    ParseActionEntry::Header { count: 1 }, // 143
+    ParseActionEntry::Action(ParseAction::Shift { state: 36, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 145
+    ParseActionEntry::Action(ParseAction::Shift { state: 37, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 147
+    ParseActionEntry::Action(ParseAction::Reduce { symbol: 16, child_count: 5, production_id: 2 }), // let
+    ParseActionEntry::Header { count: 1 }, // 149
+    ParseActionEntry::Action(ParseAction::Shift { state: 38, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 151
+    ParseActionEntry::Action(ParseAction::Reduce { symbol: 23, child_count: 2, production_id: 6 }), // call_repeat1
+    ParseActionEntry::Header { count: 1 }, // 153
+    ParseActionEntry::Action(ParseAction::Shift { state: 39, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 155
+    ParseActionEntry::Action(ParseAction::Shift { state: 40, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 157
+    ParseActionEntry::Action(ParseAction::Reduce { symbol: 21, child_count: 5, production_id: 4 }), // call
+    ParseActionEntry::Header { count: 1 }, // 159
+    ParseActionEntry::Action(ParseAction::Shift { state: 41, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 161
+    ParseActionEntry::Action(ParseAction::Shift { state: 42, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 163
+    ParseActionEntry::Action(ParseAction::Shift { state: 45, extra: false }),
+    ParseActionEntry::Header { count: 1 }, // 165
+    ParseActionEntry::Action(ParseAction::Shift { state: 43, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 167
+    ParseActionEntry::Action(ParseAction::Reduce { symbol: 23, child_count: 3, production_id: 7 }), // call_repeat1
+    ParseActionEntry::Header { count: 1 }, // 169
+    ParseActionEntry::Action(ParseAction::Shift { state: 44, extra: true }),
+    ParseActionEntry::Header { count: 1 }, // 171
+    ParseActionEntry::Action(ParseAction::Reduce { symbol: 16, child_count: 7, production_id: 1 }), // let
+    ParseActionEntry::Header { count: 1 }, // 173
+    ParseActionEntry::Action(ParseAction::Shift { state: 45, extra: true }),
+];
+
+const ALIAS_SEQUENCES: &[u16] = &[];
+
+const FIELD_MAP_SLICES: &[FieldMapSlice] = &[
+    FieldMapSlice { index: 0, length: 0 },
+    FieldMapSlice { index: 0, length: 3 },
+    FieldMapSlice { index: 3, length: 2 },
+    FieldMapSlice { index: 5, length: 3 },
+    FieldMapSlice { index: 8, length: 2 },
+    FieldMapSlice { index: 10, length: 1 },
+    FieldMapSlice { index: 11, length: 1 },
+    FieldMapSlice { index: 12, length: 1 },
+];
+
+const FIELD_MAP_ENTRIES: &[FieldMapEntry] = &[
+    FieldMapEntry::new(3, 1), FieldMapEntry::new(6, 3), FieldMapEntry::new(7, 5),
+    FieldMapEntry::new(3, 1), FieldMapEntry::new(7, 3), FieldMapEntry::new(2, 0),
+    FieldMapEntry::new(4, 1), FieldMapEntry::new(5, 2), FieldMapEntry::new(1, 0),
+    FieldMapEntry::new(0, 2), FieldMapEntry::new(1, 0), FieldMapEntry::new(0, 1),
+    FieldMapEntry::new(0, 2),
+];
+
+const LEX_MODES: &[LexMode] = &[
+    LexMode::new(0, 0), LexMode::new(4, 0), LexMode::new(5, 0), LexMode::new(13, 0),
+    LexMode::new(14, 0), LexMode::new(0, 0), LexMode::new(0, 0), LexMode::new(0, 0),
+    LexMode::new(15, 0), LexMode::new(13, 0), LexMode::new(13, 0), LexMode::new(5, 0),
+    LexMode::new(0, 0), LexMode::new(16, 0), LexMode::new(19, 0), LexMode::new(0, 0),
+    LexMode::new(0, 0), LexMode::new(0, 0), LexMode::new(0, 0), LexMode::new(19, 0),
+    LexMode::new(0, 0), LexMode::new(4, 0), LexMode::new(0, 0), LexMode::new(5, 0),
+    LexMode::new(20, 0), LexMode::new(13, 0), LexMode::new(13, 0), LexMode::new(13, 0),
+    LexMode::new(5, 0), LexMode::new(20, 0), LexMode::new(21, 0), LexMode::new(15, 0),
+    LexMode::new(0, 0), LexMode::new(5, 0), LexMode::new(22, 0), LexMode::new(5, 0),
+    LexMode::new(22, 0), LexMode::new(0, 0), LexMode::new(0, 0), LexMode::new(20, 0),
+    LexMode::new(0, 0), LexMode::new(5, 0), LexMode::new(5, 0), LexMode::new(15, 0),
+    LexMode::new(20, 0), LexMode::new(0, 0),
+];
+
+#[allow(clippy::too_many_lines)]
+fn lex(lexer: &mut Lexer<'_>, mut state: StateId) -> bool {
+    let mut result = false;
+    loop {
+        let lookahead = lexer.lookahead();
+        let (next, skip) = match state {
+            0 => match lookahead {
+                Some(
+                    '\t'..='\r' | ' ' | '\u{85}' | '\u{a0}' | '\u{1680}' | '\u{2000}'..='\u{200a}'
+                    | '\u{2028}'..='\u{2029}' | '\u{202f}' | '\u{205f}' | '\u{3000}'
+                ) => (0, true),
+                Some('#') => (1, false),
+                Some('0'..='9') => (2, false),
+                Some('a'..='z') => (3, false),
+                _ => return result,
+            },
+            1 => {
+                lexer.accept(13); // comment
+                result = true;
+                match lookahead {
+                    Some('\0'..='\t' | '\u{b}'..='\u{10ffff}') => (1, false),
+                    _ => return true,
+                }
+            }
+            2 => {
+                lexer.accept(12); // number
+                result = true;
+                match lookahead {
+                    Some('0'..='9') => (2, false),
+                    _ => return true,
+                }
+            }
+            3 => {
+                lexer.accept(11); // identifier
+                result = true;
+                match lookahead {
+                    Some('a'..='z') => (3, false),
+                    _ => return true,
+                }
+            }
+            4 => match lookahead {
+                Some(
+                    '\t'..='\r' | ' ' | '\u{85}' | '\u{a0}' | '\u{1680}' | '\u{2000}'..='\u{200a}'
+                    | '\u{2028}'..='\u{2029}' | '\u{202f}' | '\u{205f}' | '\u{3000}'
+                ) => (4, true),
+                Some('#') => (1, false),
+                Some('a'..='z') => (3, false),
+                _ => return result,
+            },
+            5 => match lookahead {
+                Some(
+                    '\t'..='\r' | ' ' | '\u{85}' | '\u{a0}' | '\u{1680}' | '\u{2000}'..='\u{200a}'
+                    | '\u{2028}'..='\u{2029}' | '\u{202f}' | '\u{205f}' | '\u{3000}'
+                ) => (5, true),
+                Some('#') => (1, false),
+                Some('(') => (6, false),
+                Some(')') => (7, false),
+                Some('+') => (8, false),
+                Some(',') => (9, false),
+                Some('-') => (10, false),
+                Some(';') => (11, false),
+                _ => return result,
+            },
+            6 => {
+                lexer.accept(8); // (
+                return true;
+            }
+            7 => {
+                lexer.accept(10); // )
+                return true;
+            }
+            8 => {
+                lexer.accept(5); // +
+                result = true;
+                match lookahead {
+                    Some('=') => (12, false),
+                    _ => return true,
+                }
+            }
+            9 => {
+                lexer.accept(9); // ,
+                return true;
+            }
+            10 => {
+                lexer.accept(6); // -
+                return true;
+            }
+            11 => {
+                lexer.accept(4); // ;
+                return true;
+            }
+            12 => {
+                lexer.accept(7); // +=
+                return true;
+            }
+            13 => match lookahead {
+                Some(
+                    '\t'..='\r' | ' ' | '\u{85}' | '\u{a0}' | '\u{1680}' | '\u{2000}'..='\u{200a}'
+                    | '\u{2028}'..='\u{2029}' | '\u{202f}' | '\u{205f}' | '\u{3000}'
+                ) => (13, true),
+                Some('#') => (1, false),
+                Some(')') => (7, false),
+                Some('+') => (8, false),
+                Some(',') => (9, false),
+                Some('-') => (10, false),
+                Some(';') => (11, false),
+                _ => return result,
+            },
+            14 => match lookahead {
+                Some(
+                    '\t'..='\r' | ' ' | '\u{85}' | '\u{a0}' | '\u{1680}' | '\u{2000}'..='\u{200a}'
+                    | '\u{2028}'..='\u{2029}' | '\u{202f}' | '\u{205f}' | '\u{3000}'
+                ) => (14, true),
+                Some('#') => (1, false),
+                _ => return result,
+            },
+            15 => match lookahead {
+                Some(
+                    '\t'..='\r' | ' ' | '\u{85}' | '\u{a0}' | '\u{1680}' | '\u{2000}'..='\u{200a}'
+                    | '\u{2028}'..='\u{2029}' | '\u{202f}' | '\u{205f}' | '\u{3000}'
+                ) => (15, true),
+                Some('#') => (1, false),
+                Some('+') => (8, false),
+                Some('-') => (10, false),
+                Some(';') => (11, false),
+                _ => return result,
+            },
+            16 => match lookahead {
+                Some(
+                    '\t'..='\r' | ' ' | '\u{85}' | '\u{a0}' | '\u{1680}' | '\u{2000}'..='\u{200a}'
+                    | '\u{2028}'..='\u{2029}' | '\u{202f}' | '\u{205f}' | '\u{3000}'
+                ) => (16, true),
+                Some('#') => (1, false),
+                Some(':') => (17, false),
+                Some('=') => (18, false),
+                _ => return result,
+            },
+            17 => {
+                lexer.accept(2); // :
+                return true;
+            }
+            18 => {
+                lexer.accept(3); // =
+                return true;
+            }
+            19 => match lookahead {
+                Some(
+                    '\t'..='\r' | ' ' | '\u{85}' | '\u{a0}' | '\u{1680}' | '\u{2000}'..='\u{200a}'
+                    | '\u{2028}'..='\u{2029}' | '\u{202f}' | '\u{205f}' | '\u{3000}'
+                ) => (19, true),
+                Some('#') => (1, false),
+                Some(')') => (7, false),
+                Some('0'..='9') => (2, false),
+                Some('a'..='z') => (3, false),
+                _ => return result,
+            },
+            20 => match lookahead {
+                Some(
+                    '\t'..='\r' | ' ' | '\u{85}' | '\u{a0}' | '\u{1680}' | '\u{2000}'..='\u{200a}'
+                    | '\u{2028}'..='\u{2029}' | '\u{202f}' | '\u{205f}' | '\u{3000}'
+                ) => (20, true),
+                Some('#') => (1, false),
+                Some(')') => (7, false),
+                Some('+') => (8, false),
+                Some(',') => (9, false),
+                Some('-') => (10, false),
+                _ => return result,
+            },
+            21 => match lookahead {
+                Some(
+                    '\t'..='\r' | ' ' | '\u{85}' | '\u{a0}' | '\u{1680}' | '\u{2000}'..='\u{200a}'
+                    | '\u{2028}'..='\u{2029}' | '\u{202f}' | '\u{205f}' | '\u{3000}'
+                ) => (21, true),
+                Some('#') => (1, false),
+                Some('=') => (18, false),
+                _ => return result,
+            },
+            22 => match lookahead {
+                Some(
+                    '\t'..='\r' | ' ' | '\u{85}' | '\u{a0}' | '\u{1680}' | '\u{2000}'..='\u{200a}'
+                    | '\u{2028}'..='\u{2029}' | '\u{202f}' | '\u{205f}' | '\u{3000}'
+                ) => (22, true),
+                Some('#') => (1, false),
+                Some(')') => (7, false),
+                Some(',') => (9, false),
+                _ => return result,
+            },
+            _ => return result,
+        };
+        lexer.advance(skip);
+        state = next;
+    }
+}
+
+#[allow(clippy::too_many_lines)]
+fn keyword_lex(lexer: &mut Lexer<'_>, mut state: StateId) -> bool {
+    let result = false;
+    loop {
+        let lookahead = lexer.lookahead();
+        let (next, skip) = match state {
+            0 => match lookahead {
+                Some('l') => (1, false),
+                _ => return result,
+            },
+            1 => match lookahead {
+                Some('e') => (2, false),
+                _ => return result,
+            },
+            2 => match lookahead {
+                Some('t') => (3, false),
+                _ => return result,
+            },
+            3 => {
+                lexer.accept(1); // let
+                return true;
+            }
+            _ => return result,
+        };
+        lexer.advance(skip);
+        state = next;
+    }
+}
+
+const EXTERNAL_SYMBOLS: &[SymbolId] = &[];
+
+const EXTERNAL_SCANNER_STATES: &[bool] = &[];
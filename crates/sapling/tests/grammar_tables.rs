@@ -0,0 +1,71 @@
+#![allow(missing_docs)]
+//! Parse and lex table construction for the grammars in the node types
+//! fixtures, which tree-sitter builds.
+
+use sapling::tables::{self, Tables};
+use sapling::{parse_grammar, Parser};
+use std::fs;
+use std::path::Path;
+
+fn build(language: &str) -> Tables {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/node_types")
+        .join(language)
+        .join("grammar.json");
+    let grammar = parse_grammar(&fs::read_to_string(path).unwrap()).unwrap();
+    tables::build(&grammar).unwrap_or_else(|e| panic!("{language}: {e}"))
+}
+
+#[test]
+fn bash_tables_build() {
+    let language = build("bash").into_language();
+    assert_eq!(
+        Parser::new(&language).parse("echo hi | grep h\n").to_sexp(),
+        "(program (pipeline (command name: (command_name (word)) argument: (word)) \
+         (command name: (command_name (word)) argument: (word))))"
+    );
+}
+
+#[test]
+fn javascript_tables_build() {
+    let language = build("javascript").into_language();
+    // `typeof` is lexed as an identifier, then told apart as a keyword.
+    let tree = Parser::new(&language).parse("let typeofx = typeof x;");
+    assert_eq!(
+        tree.to_sexp(),
+        "(program (lexical_declaration (variable_declarator name: (identifier) \
+         value: (unary_expression argument: (identifier)))))"
+    );
+}
+
+#[test]
+fn python_tables_build() {
+    let language = build("python").into_language();
+    assert_eq!(
+        Parser::new(&language).parse("x = 1 # one\n").to_sexp(),
+        "(module (expression_statement (assignment left: (identifier) right: (integer))) \
+         (comment))"
+    );
+    // Supertypes such as `parameter` and `expression` are hidden.
+    assert_eq!(
+        Parser::new(&language)
+            .parse("def f(x): return x+1\n")
+            .to_sexp(),
+        "(module (function_definition name: (identifier) parameters: (parameters (identifier)) \
+         body: (block (return_statement (binary_operator left: (identifier) \
+         right: (integer))))))"
+    );
+}
+
+#[test]
+fn rust_tables_build() {
+    let language = build("rust").into_language();
+    // Line comments are rules, reduced as extras wherever they appear.
+    let tree = Parser::new(&language).parse("fn main() {\n    // hi\n    let x = 1;\n}\n");
+    assert_eq!(
+        tree.to_sexp(),
+        "(source_file (function_item name: (identifier) parameters: (parameters) \
+         body: (block (line_comment) (let_declaration pattern: (identifier) \
+         value: (integer_literal)))))"
+    );
+}
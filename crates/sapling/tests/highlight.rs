@@ -0,0 +1,251 @@
+#![allow(missing_docs)]
+//! Highlighting generated languages: calc on its own, with locals, and
+//! injected into the code of a template language.
+
+use sapling::highlight::render::{render_ansi, render_html};
+use sapling::highlight::{highlight, Highlight, HighlightConfiguration, HighlightEvent};
+use sapling::{parse_grammar, Parser};
+use std::fs;
+use std::path::Path;
+
+mod calc {
+    include!("fixtures/tables/calc.rs");
+}
+
+mod template {
+    include!("fixtures/highlight/template.rs");
+}
+
+const CALC_HIGHLIGHTS: &str = r#"
+((identifier) @function.builtin
+  (#eq? @function.builtin "print")
+  (#is-not? local))
+(let name: (identifier) @variable.definition)
+(call function: (identifier) @function)
+(identifier) @variable
+(number) @number
+(comment) @comment
+"let" @keyword
+["+" "-"] @operator
+"#;
+
+const CALC_LOCALS: &str = r"
+(program) @local.scope
+(let name: (identifier) @local.definition)
+(identifier) @local.reference
+";
+
+const TEMPLATE_HIGHLIGHTS: &str = r#"
+["{" "}"] @punctuation.bracket
+"#;
+
+const TEMPLATE_INJECTIONS: &str = r#"
+((code) @injection.content
+  (#set! injection.language "calc"))
+"#;
+
+const NAMES: [&str; 8] = [
+    "comment",
+    "function",
+    "function.builtin",
+    "keyword",
+    "number",
+    "operator",
+    "punctuation.bracket",
+    "variable",
+];
+
+fn calc_config(locals: &str) -> HighlightConfiguration<'static> {
+    let mut config =
+        HighlightConfiguration::new(&calc::LANGUAGE, "calc", CALC_HIGHLIGHTS, "", locals).unwrap();
+    config.configure(&NAMES);
+    config
+}
+
+/// The highlighted runs of `source`, as `(text, name)` pairs.
+fn runs(source: &str, events: impl Iterator<Item = HighlightEvent>) -> Vec<(&str, Vec<&str>)> {
+    let mut stack = Vec::new();
+    let mut runs = Vec::new();
+    for event in events {
+        match event {
+            HighlightEvent::Source { start, end } => {
+                if !stack.is_empty() {
+                    runs.push((&source[start..end], stack.clone()));
+                }
+            }
+            HighlightEvent::HighlightStart(Highlight(index)) => stack.push(NAMES[index]),
+            HighlightEvent::HighlightEnd => {
+                stack.pop();
+            }
+        }
+    }
+    runs
+}
+
+#[test]
+fn template_tables_are_up_to_date() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/highlight");
+    let grammar = parse_grammar(&fs::read_to_string(path.join("grammar.json")).unwrap()).unwrap();
+    let generated = sapling::emit::rust::emit(&grammar, None).unwrap();
+    assert!(
+        generated == fs::read_to_string(path.join("template.rs")).unwrap(),
+        "tests/fixtures/highlight/template.rs is stale; regenerate it from grammar.json"
+    );
+}
+
+#[test]
+fn highlights_follow_the_first_matching_pattern() {
+    let source = "let x = f(1) + y; # done";
+    let config = calc_config("");
+    let events: Vec<_> = highlight(&config, source.as_bytes(), |_| None).collect();
+    assert_eq!(
+        runs(source, events.iter().copied()),
+        [
+            ("let", vec!["keyword"]),
+            ("x", vec!["variable"]),
+            ("f", vec!["function"]),
+            ("1", vec!["number"]),
+            ("+", vec!["operator"]),
+            ("y", vec!["variable"]),
+            ("# done", vec!["comment"]),
+        ]
+    );
+    // The events cover the whole source, in order.
+    let mut position = 0;
+    for event in events {
+        if let HighlightEvent::Source { start, end } = event {
+            assert_eq!(start, position);
+            position = end;
+        }
+    }
+    assert_eq!(position, source.len());
+}
+
+#[test]
+fn unrecognized_captures_are_not_highlighted() {
+    let mut config = calc_config("");
+    config.configure(&["number"]);
+    let source = "let x = 1;";
+    let events = highlight(&config, source.as_bytes(), |_| None);
+    let highlighted: Vec<_> = events
+        .filter_map(|event| match event {
+            HighlightEvent::HighlightStart(highlight) => Some(highlight),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(highlighted, [Highlight(0)]);
+}
+
+#[test]
+fn local_references_take_their_definition_highlight() {
+    let source = "print(a); let print = 1; print(print);";
+    let config = calc_config(CALC_LOCALS);
+    let runs = runs(source, highlight(&config, source.as_bytes(), |_| None));
+    let names: Vec<_> = runs
+        .iter()
+        .filter(|(text, _)| *text == "print")
+        .map(|(_, names)| names.last().copied().unwrap())
+        .collect();
+    // Before its definition `print` is the builtin; afterwards it is a
+    // local, highlighted like the definition, whose `variable.definition`
+    // capture falls back to `variable`.
+    assert_eq!(
+        names,
+        ["function.builtin", "variable", "variable", "variable"]
+    );
+}
+
+#[test]
+fn injected_languages_are_highlighted_in_place() {
+    let source = "Total: {a + 1} items";
+    let calc = calc_config("");
+    let mut template = HighlightConfiguration::new(
+        &template::LANGUAGE,
+        "template",
+        TEMPLATE_HIGHLIGHTS,
+        TEMPLATE_INJECTIONS,
+        "",
+    )
+    .unwrap();
+    template.configure(&NAMES);
+    let mut requested = Vec::new();
+    let events = highlight(&template, source.as_bytes(), |name| {
+        requested.push(name.to_string());
+        (name == "calc").then_some(&calc)
+    });
+    assert_eq!(
+        runs(source, events),
+        [
+            ("{", vec!["punctuation.bracket"]),
+            ("a", vec!["variable"]),
+            ("+", vec!["operator"]),
+            ("1", vec!["number"]),
+            ("}", vec!["punctuation.bracket"]),
+        ]
+    );
+    assert_eq!(requested, ["calc"]);
+    assert!(!Parser::new(&template::LANGUAGE).parse(source).has_error());
+}
+
+#[test]
+fn combined_injections_are_highlighted_as_one_document() {
+    let source = "{let x =} and {1;}";
+    let calc = calc_config("");
+    let injections = r#"
+        ((code) @injection.content
+          (#set! injection.language "calc")
+          (#set! injection.combined))
+    "#;
+    let mut template =
+        HighlightConfiguration::new(&template::LANGUAGE, "template", "", injections, "").unwrap();
+    template.configure(&NAMES);
+    let events = highlight(&template, source.as_bytes(), |_| Some(&calc));
+    assert_eq!(
+        runs(source, events),
+        [
+            ("let", vec!["keyword"]),
+            ("x", vec!["variable"]),
+            ("1", vec!["number"]),
+        ]
+    );
+}
+
+#[test]
+fn html_is_escaped_and_nested() {
+    let source = "f(a < b);";
+    let events = [
+        HighlightEvent::HighlightStart(Highlight(1)),
+        HighlightEvent::Source { start: 0, end: 1 },
+        HighlightEvent::HighlightEnd,
+        HighlightEvent::Source { start: 1, end: 2 },
+        HighlightEvent::HighlightStart(Highlight(7)),
+        HighlightEvent::Source { start: 2, end: 7 },
+        HighlightEvent::HighlightEnd,
+        HighlightEvent::Source { start: 7, end: 9 },
+    ];
+    let html = render_html(source.as_bytes(), events, |Highlight(index)| {
+        format!("class=\"{}\"", NAMES[index])
+    });
+    assert_eq!(
+        html,
+        "<span class=\"function\">f</span>(<span class=\"variable\">a &lt; b</span>);"
+    );
+}
+
+#[test]
+fn ansi_restores_enclosing_styles() {
+    let source = "{a}";
+    let events = [
+        HighlightEvent::HighlightStart(Highlight(0)),
+        HighlightEvent::Source { start: 0, end: 1 },
+        HighlightEvent::HighlightStart(Highlight(1)),
+        HighlightEvent::Source { start: 1, end: 2 },
+        HighlightEvent::HighlightEnd,
+        HighlightEvent::Source { start: 2, end: 3 },
+        HighlightEvent::HighlightEnd,
+    ];
+    let text = render_ansi(source.as_bytes(), events, |Highlight(index)| {
+        Some(["2", "1;31"][index].to_string())
+    });
+    assert_eq!(text, "\x1b[2m{\x1b[1;31ma\x1b[0m\x1b[2m}\x1b[0m");
+}
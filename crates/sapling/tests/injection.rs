@@ -0,0 +1,173 @@
+#![allow(missing_docs)]
+//! Included ranges and injection layers, with calc embedded in the code of
+//! the template language.
+
+use sapling::injection::{InjectionConfiguration, LayerSet};
+use sapling::{IncludedRangesError, InputEdit, Parser, Point, Range};
+
+mod calc {
+    include!("fixtures/tables/calc.rs");
+}
+
+mod template {
+    include!("fixtures/highlight/template.rs");
+}
+
+const SEPARATE: &str = r#"
+((code) @injection.content
+  (#set! injection.language "calc"))
+"#;
+
+const COMBINED: &str = r#"
+((code) @injection.content
+  (#set! injection.language "calc")
+  (#set! injection.combined))
+"#;
+
+fn configs(
+    injections: &str,
+) -> (
+    InjectionConfiguration<'static>,
+    InjectionConfiguration<'static>,
+) {
+    (
+        InjectionConfiguration::new(&template::LANGUAGE, "template", injections).unwrap(),
+        InjectionConfiguration::new(&calc::LANGUAGE, "calc", "").unwrap(),
+    )
+}
+
+#[test]
+fn included_ranges_are_parsed_as_one_text() {
+    let source = "let x = 1; <skipped> let y = x;";
+    let mut parser = Parser::new(&calc::LANGUAGE);
+    parser
+        .set_included_ranges(&[
+            Range::from_bytes(source.as_bytes(), 0..11),
+            Range::from_bytes(source.as_bytes(), 20..31),
+        ])
+        .unwrap();
+    let tree = parser.parse(source);
+    assert_eq!(
+        tree.to_sexp(),
+        "(program (let name: (identifier) value: (number)) \
+         (let name: (identifier) value: (identifier)))"
+    );
+    let second = tree.child(1).unwrap();
+    assert_eq!(second.byte_range(), 21..31);
+    assert_eq!(second.utf8_text(source.as_bytes()), Ok("let y = x;"));
+}
+
+#[test]
+fn included_ranges_may_split_a_token() {
+    let source = "le<gap>t x = 1;";
+    let mut parser = Parser::new(&calc::LANGUAGE);
+    parser
+        .set_included_ranges(&[
+            Range::from_bytes(source.as_bytes(), 0..2),
+            Range::from_bytes(source.as_bytes(), 7..15),
+        ])
+        .unwrap();
+    let tree = parser.parse(source);
+    assert!(!tree.has_error(), "{}", tree.to_sexp());
+    assert_eq!(tree.child(0).unwrap().byte_range(), 0..15);
+}
+
+#[test]
+fn overlapping_ranges_are_rejected() {
+    let source = b"let x = 1;";
+    let mut parser = Parser::new(&calc::LANGUAGE);
+    let ranges = [
+        Range::from_bytes(source, 0..5),
+        Range::from_bytes(source, 4..10),
+    ];
+    assert_eq!(
+        parser.set_included_ranges(&ranges),
+        Err(IncludedRangesError(1))
+    );
+    assert_eq!(parser.included_ranges().len(), 1);
+    assert_eq!(
+        IncludedRangesError(1).to_string(),
+        "included range 1 starts before the previous range ends"
+    );
+}
+
+#[test]
+fn each_injection_is_a_layer() {
+    let source = "{let x =} and {1;}";
+    let (template, calc) = configs(SEPARATE);
+    let layers = LayerSet::parse(&template, source.as_bytes(), |name| {
+        (name == "calc").then_some(&calc)
+    });
+    let layers = layers.layers();
+    assert_eq!(layers.len(), 3);
+    assert_eq!(layers[0].language_name(), "template");
+    assert_eq!(layers[1].language_name(), "calc");
+    assert_eq!(layers[1].depth(), 1);
+    assert_eq!(layers[1].included_ranges()[0].start_byte, 1);
+    assert!(layers[1].tree().has_error());
+}
+
+#[test]
+fn combined_injections_share_a_layer() {
+    let source = "{let x =} and {1;}";
+    let (template, calc) = configs(COMBINED);
+    let layers = LayerSet::parse(&template, source.as_bytes(), |_| Some(&calc));
+    assert_eq!(layers.layers().len(), 2);
+    let combined = &layers.layers()[1];
+    assert_eq!(combined.included_ranges().len(), 2);
+    assert_eq!(
+        combined.tree().to_sexp(),
+        "(program (let name: (identifier) value: (number)))"
+    );
+    assert_eq!(layers.layer_at(16).unwrap().language_name(), "calc");
+    assert_eq!(layers.layer_at(10).unwrap().language_name(), "template");
+}
+
+/// The edit replacing `old_end - start` bytes at `start` of a one-line
+/// document with `new_end - start` bytes.
+fn edit(start: usize, old_end: usize, new_end: usize) -> InputEdit {
+    InputEdit {
+        start_byte: start,
+        old_end_byte: old_end,
+        new_end_byte: new_end,
+        start_position: Point::new(0, start),
+        old_end_position: Point::new(0, old_end),
+        new_end_position: Point::new(0, new_end),
+    }
+}
+
+#[test]
+fn updates_reparse_only_edited_layers() {
+    let (template, calc) = configs(SEPARATE);
+    let mut layers = LayerSet::parse(&template, b"{a;} and {b;}", |_| Some(&calc));
+    // Nothing was edited, so nothing is parsed.
+    assert_eq!(
+        layers.update(&template, b"{a;} and {b;}", |_| Some(&calc)),
+        0
+    );
+    // Text after the injections: only the template is parsed again.
+    layers.edit(&edit(13, 13, 14));
+    assert_eq!(
+        layers.update(&template, b"{a;} and {b;}!", |_| Some(&calc)),
+        1
+    );
+    // A changed injection is parsed again too, but not its neighbour.
+    layers.edit(&edit(10, 11, 11));
+    assert_eq!(
+        layers.update(&template, b"{a;} and {c;}!", |_| Some(&calc)),
+        2
+    );
+    assert_eq!(layers.layers().len(), 3);
+    // Text before the injections moves them without parsing them again.
+    layers.edit(&edit(0, 0, 2));
+    assert_eq!(
+        layers.update(&template, b"  {a;} and {c;}!", |_| Some(&calc)),
+        1
+    );
+    let moved = &layers.layers()[1];
+    assert_eq!(moved.included_ranges()[0].start_byte, 3);
+    assert_eq!(moved.tree().start_byte(), 3);
+    // Without the callback's help, injections are left out.
+    assert_eq!(layers.update(&template, b"  {a;} and {c;}!", |_| None), 0);
+    assert_eq!(layers.layers().len(), 1);
+}
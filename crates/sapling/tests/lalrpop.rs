@@ -0,0 +1,145 @@
+#![allow(missing_docs)]
+//! lalrpop emission, checked by running lalrpop on the emitted grammars
+//! and by compiling one emitted parser into a crate that parses with it.
+
+use sapling::emit::lalrpop::{emit, EmitError, Unsupported};
+use sapling::{parse_grammar, tables, Grammar, Parser};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+fn fixture(name: &str) -> String {
+    fs::read_to_string(
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name),
+    )
+    .unwrap()
+}
+
+/// Emits `grammar` and runs lalrpop on it, failing on any conflict.
+fn build(grammar: &Grammar) {
+    let output = emit(grammar).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(format!("{}.lalrpop", grammar.name));
+    fs::write(&path, &output.grammar).unwrap();
+    lalrpop::Configuration::new()
+        .set_in_dir(dir.path())
+        .set_out_dir(dir.path())
+        .log_quiet()
+        .process_file(&path)
+        .unwrap_or_else(|e| panic!("lalrpop rejected the grammar: {e}\n{}", output.grammar));
+    assert!(dir.path().join(format!("{}.rs", grammar.name)).exists());
+}
+
+#[test]
+fn operators_through_a_hidden_choice_build_without_conflicts() {
+    build(&parse_grammar(&fixture("lalrpop/grammar.json")).unwrap());
+}
+
+#[test]
+fn calc_builds_once_its_comments_are_dropped() {
+    let mut grammar = parse_grammar(&fixture("ast/grammar.json")).unwrap();
+    let Err(EmitError::Unsupported(features)) = emit(&grammar) else {
+        panic!("expected the comment extra to be unsupported");
+    };
+    assert_eq!(features, [Unsupported::Extra("comment".to_string())]);
+
+    if let Some(extras) = &mut grammar.extras {
+        extras.retain(|extra| extra.symbol_name() != Some("comment"));
+    }
+    build(&grammar);
+}
+
+/// Builds the crate in `tests/fixtures/lalrpop/parser`, whose build script
+/// emits `lalrpop/grammar.json` and runs lalrpop on it, and parses `source`
+/// with it. Returns the printed tree, or the parse error.
+fn parse_with_emitted_parser(source: &str) -> Result<String, String> {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let fixture = manifest_dir.join("tests/fixtures/lalrpop/parser");
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("lalrpop-parser");
+    fs::create_dir_all(&dir).unwrap();
+    let manifest = format!(
+        r#"[package]
+name = "expr-parser"
+edition = "2021"
+publish = false
+build = '{build}'
+
+[[bin]]
+name = "expr-parser"
+path = '{main}'
+
+[dependencies]
+lalrpop-util = {{ default-features = false, version = "0.22.2" }}
+sapling = {{ path = '{sapling}' }}
+
+[build-dependencies]
+lalrpop = {{ default-features = false, version = "0.22.2" }}
+sapling = {{ path = '{sapling}' }}
+
+[workspace]
+"#,
+        build = fixture.join("build.rs").display(),
+        main = fixture.join("main.rs").display(),
+        sapling = manifest_dir.display(),
+    );
+    fs::write(dir.join("Cargo.toml"), manifest).unwrap();
+    // Resolve to the versions the workspace is tested with.
+    if !dir.join("Cargo.lock").exists() {
+        fs::copy(
+            manifest_dir.join("../../Cargo.lock"),
+            dir.join("Cargo.lock"),
+        )
+        .unwrap();
+    }
+
+    let mut child = Command::new(env!("CARGO"))
+        .args(["run", "--quiet", "--offline", "--manifest-path"])
+        .arg(dir.join("Cargo.toml"))
+        .env("CARGO_TARGET_DIR", dir.join("target"))
+        .env(
+            "EXPR_GRAMMAR",
+            manifest_dir.join("tests/fixtures/lalrpop/grammar.json"),
+        )
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(source.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    if output.status.success() {
+        Ok(stdout.trim_end().to_string())
+    } else {
+        Err(stderr)
+    }
+}
+
+#[test]
+fn emitted_parser_compiles_and_parses_like_the_tables() {
+    let source = "-a + f(b, 2) * c ^ d ^ 3;\n(x);";
+    let expected = "(program \
+        (statement value: (binary \
+          left: (unary operand: (identifier)) \
+          right: (binary \
+            left: (call function: (identifier) argument: (identifier) argument: (number)) \
+            right: (binary left: (identifier) right: (binary left: (identifier) right: (number)))))) \
+        (statement value: (parenthesized (identifier))))";
+    assert_eq!(parse_with_emitted_parser(source).unwrap(), expected);
+
+    let grammar = parse_grammar(&fixture("lalrpop/grammar.json")).unwrap();
+    let language = tables::build(&grammar).unwrap().into_language();
+    assert_eq!(Parser::new(&language).parse(source).to_sexp(), expected);
+
+    let error = parse_with_emitted_parser("a +;").unwrap_err();
+    assert!(error.contains("UnrecognizedToken"), "{error}");
+}
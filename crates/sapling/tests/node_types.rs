@@ -0,0 +1,46 @@
+#![allow(missing_docs)]
+//! `node-types.json` generation against the files tree-sitter ships.
+
+use sapling::{parse_grammar, NodeTypes};
+use std::fs;
+use std::path::Path;
+
+fn assert_matches_tree_sitter(language: &str) {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/node_types")
+        .join(language);
+    let grammar = parse_grammar(&fs::read_to_string(dir.join("grammar.json")).unwrap()).unwrap();
+    let expected = fs::read_to_string(dir.join("node-types.json")).unwrap();
+    let actual = NodeTypes::from_grammar(&grammar).unwrap().to_json();
+    if actual != expected {
+        let line = actual
+            .lines()
+            .zip(expected.lines())
+            .position(|(a, e)| a != e)
+            .unwrap_or_else(|| actual.lines().count().min(expected.lines().count()));
+        panic!(
+            "{language}: node-types.json differs from tree-sitter's at line {}",
+            line + 1
+        );
+    }
+}
+
+#[test]
+fn bash_node_types_match_tree_sitter() {
+    assert_matches_tree_sitter("bash");
+}
+
+#[test]
+fn javascript_node_types_match_tree_sitter() {
+    assert_matches_tree_sitter("javascript");
+}
+
+#[test]
+fn python_node_types_match_tree_sitter() {
+    assert_matches_tree_sitter("python");
+}
+
+#[test]
+fn rust_node_types_match_tree_sitter() {
+    assert_matches_tree_sitter("rust");
+}
@@ -0,0 +1,84 @@
+#![allow(missing_docs)]
+//! Loading a tree-sitter `parser.c` and parsing with it, with keywords
+//! lexed by its keyword lexer.
+
+use sapling::parser_c::{self, ParserCError};
+use sapling::Parser;
+use std::fs;
+use std::path::Path;
+
+fn parser_c() -> String {
+    fs::read_to_string(
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/parser_c/parser.c"),
+    )
+    .unwrap()
+}
+
+#[test]
+fn tables_are_read() {
+    let language = parser_c::read(&parser_c()).unwrap();
+    assert_eq!(language.name, "declarations");
+    assert_eq!(language.state_count(), 9);
+    assert_eq!(language.symbol_count(), 7);
+    assert_eq!(language.token_count, 4);
+    assert_eq!(language.symbol_name(5), "declaration");
+    assert!(!language.symbol_metadata(6).visible);
+    assert_eq!(language.field_names.as_ref(), ["name"]);
+}
+
+#[test]
+fn parses_with_the_loaded_tables() {
+    let language = parser_c::read(&parser_c()).unwrap();
+    let source = "let x;\n  let café ;";
+    let tree = Parser::new(&language).parse(source);
+    assert_eq!(
+        tree.to_sexp(),
+        "(program (declaration name: (identifier)) (declaration name: (identifier)))"
+    );
+    let name = tree.child(1).unwrap().child_by_field_name("name").unwrap();
+    assert_eq!(name.utf8_text(source.as_bytes()), Ok("café"));
+    assert_eq!(Parser::new(&language).parse("").to_sexp(), "(program)");
+}
+
+#[test]
+fn keywords_are_only_lexed_where_valid() {
+    let language = parser_c::read(&parser_c()).unwrap();
+    let source = "let let; let letter;";
+    let tree = Parser::new(&language).parse(source);
+    assert!(!tree.has_error(), "{}", tree.to_sexp());
+    let names: Vec<_> = tree
+        .children()
+        .iter()
+        .map(|declaration| {
+            let name = declaration.child_by_field_name("name").unwrap();
+            name.utf8_text(source.as_bytes()).unwrap()
+        })
+        .collect();
+    assert_eq!(names, ["let", "letter"]);
+    assert!(Parser::new(&language).parse("letter x;").has_error());
+}
+
+#[test]
+fn problems_are_reported_with_their_line() {
+    let source = parser_c();
+    let missing = source.replace("ts_parse_actions[] =", "ts_other_actions[] =");
+    assert_eq!(
+        parser_c::read(&missing).unwrap_err().to_string(),
+        "missing ts_parse_actions"
+    );
+    let unsupported = source.replace("SHIFT(8)", "JUMP(8)");
+    let error = parser_c::read(&unsupported).unwrap_err();
+    assert_eq!(error.message, "unknown parse action 'JUMP'");
+    let line = source
+        .lines()
+        .position(|line| line.contains("SHIFT(8)"))
+        .unwrap()
+        + 1;
+    assert_eq!(
+        error,
+        ParserCError {
+            line: Some(line),
+            message: error.message.clone()
+        }
+    );
+}
@@ -0,0 +1,178 @@
+#![allow(missing_docs)]
+//! Rust parse table emission, checked against a generated file that is also
+//! compiled here and used to parse.
+
+use sapling::tables::TableError;
+use sapling::{parse_grammar, Parser};
+use std::fs;
+use std::path::Path;
+
+mod calc {
+    include!("fixtures/tables/calc.rs");
+}
+
+fn fixture(path: &str) -> String {
+    fs::read_to_string(
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(path),
+    )
+    .unwrap()
+}
+
+#[test]
+fn generated_tables_are_up_to_date() {
+    let grammar = parse_grammar(&fixture("ast/grammar.json")).unwrap();
+    let generated = sapling::emit::rust::emit(&grammar, None).unwrap();
+    assert!(
+        generated == fixture("tables/calc.rs"),
+        "tests/fixtures/tables/calc.rs is stale; regenerate it from ast/grammar.json"
+    );
+}
+
+#[test]
+fn generated_tables_parse_a_program() {
+    let source = "let x = a + 1;\nf(x, 2);";
+    let tree = Parser::new(&calc::LANGUAGE).parse(source);
+    assert_eq!(
+        tree.to_sexp(),
+        "(program (let name: (identifier) value: (binary left: (identifier) right: (number))) \
+         (expression_statement (call function: (identifier) argument: (identifier) argument: (number))))"
+    );
+    let binary = tree.child(0).unwrap().child_by_field_name("value").unwrap();
+    assert_eq!(binary.byte_range(), 8..13);
+    assert_eq!(
+        binary
+            .child_by_field_name("operator")
+            .unwrap()
+            .utf8_text(source.as_bytes()),
+        Ok("+")
+    );
+}
+
+#[test]
+fn runtime_languages_parse_like_generated_ones() {
+    let grammar = parse_grammar(&fixture("ast/grammar.json")).unwrap();
+    let language = sapling::tables::build(&grammar).unwrap().into_language();
+    for source in [
+        "let x = a + 1;\nf(x, 2);",
+        "# comment\nletter;  # trailing",
+        "let = 1;",
+        "",
+    ] {
+        assert_eq!(
+            Parser::new(&language).parse(source),
+            Parser::new(&calc::LANGUAGE).parse(source),
+            "{source:?}"
+        );
+    }
+}
+
+#[test]
+fn generated_tables_keep_extras_and_keywords_apart() {
+    let tree = Parser::new(&calc::LANGUAGE).parse("# comment\nletter;  # trailing");
+    assert_eq!(
+        tree.to_sexp(),
+        "(program (comment) (expression_statement (identifier)) (comment))"
+    );
+    assert_eq!(tree.end_byte(), 29);
+}
+
+#[test]
+fn generated_tables_parse_empty_input() {
+    let tree = Parser::new(&calc::LANGUAGE).parse("");
+    assert_eq!(tree.to_sexp(), "(program)");
+    assert!(!tree.has_error());
+}
+
+#[test]
+fn generated_tables_report_errors() {
+    let tree = Parser::new(&calc::LANGUAGE).parse("let = 1;");
+    assert!(tree.has_error());
+}
+
+fn arithmetic(addition: &str) -> sapling::Grammar {
+    parse_grammar(&format!(
+        r#"{{
+            "name": "arithmetic",
+            "rules": {{
+                "expression": {{
+                    "type": "CHOICE",
+                    "members": [
+                        {addition},
+                        {{ "type": "PATTERN", "value": "\\d+" }}
+                    ]
+                }}
+            }}
+        }}"#
+    ))
+    .unwrap()
+}
+
+const ADDITION: &str = r#"{
+    "type": "SEQ",
+    "members": [
+        { "type": "SYMBOL", "name": "expression" },
+        { "type": "STRING", "value": "+" },
+        { "type": "SYMBOL", "name": "expression" }
+    ]
+}"#;
+
+#[test]
+fn ambiguous_grammars_are_rejected() {
+    let Err(TableError::Conflict(conflict)) =
+        sapling::emit::rust::emit(&arithmetic(ADDITION), None)
+    else {
+        panic!("expected a conflict");
+    };
+    assert_eq!(conflict.lookahead, "'+'");
+    assert_eq!(conflict.rules, ["expression"]);
+    assert!(conflict.to_string().contains("unresolved conflict"));
+}
+
+#[test]
+fn associativity_resolves_conflicts() {
+    let addition = format!(r#"{{ "type": "PREC_LEFT", "value": 1, "content": {ADDITION} }}"#);
+    let tables = sapling::tables::build(&arithmetic(&addition)).unwrap();
+    assert_eq!(tables.symbol_count(), 4);
+    assert!(tables.state_count() > 0);
+}
+
+#[test]
+fn separators_run_to_their_end() {
+    let grammar = parse_grammar(
+        r##"{
+            "name": "statements",
+            "rules": {
+                "program": {
+                    "type": "REPEAT",
+                    "content": {
+                        "type": "SEQ",
+                        "members": [
+                            {
+                                "type": "CHOICE",
+                                "members": [
+                                    { "type": "SYMBOL", "name": "number" },
+                                    { "type": "SYMBOL", "name": "ident" }
+                                ]
+                            },
+                            { "type": "STRING", "value": ";" }
+                        ]
+                    }
+                },
+                "number": { "type": "PATTERN", "value": "[0-9]+" },
+                "ident": { "type": "PATTERN", "value": "[a-z]+" }
+            },
+            "extras": [
+                { "type": "PATTERN", "value": "\\s" },
+                { "type": "PATTERN", "value": "#[^\\n]*" }
+            ]
+        }"##,
+    )
+    .unwrap();
+    let language = sapling::tables::build(&grammar).unwrap().into_language();
+    // The comment swallows the words after '#' rather than ending there.
+    let tree = Parser::new(&language).parse("1; # a b\nx;");
+    assert_eq!(tree.to_sexp(), "(program (number) (ident))");
+    assert!(!tree.has_error());
+}
@@ -0,0 +1,83 @@
+#![allow(missing_docs)]
+//! Recovering from syntax errors in JSON, checked against the trees
+//! tree-sitter builds for the same broken inputs.
+
+use sapling::corpus::{parse_corpus, Outcome};
+use sapling::language::Language;
+use sapling::{parser_c, Node, Parser};
+use std::fs;
+use std::path::Path;
+
+fn fixture(name: &str) -> String {
+    fs::read_to_string(
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/json")
+            .join(name),
+    )
+    .unwrap()
+}
+
+fn json() -> Language {
+    parser_c::read(&fixture("parser.c")).unwrap()
+}
+
+fn find<'a>(node: &'a Node, test: &impl Fn(&Node) -> bool) -> Option<&'a Node> {
+    if test(node) {
+        return Some(node);
+    }
+    node.children().iter().find_map(|child| find(child, test))
+}
+
+#[test]
+fn malformed_inputs_recover_like_tree_sitter() {
+    let language = json();
+    let failures: Vec<(String, String)> = parse_corpus(&fixture("recovery.txt"))
+        .iter()
+        .filter_map(|test| match test.run(&language) {
+            Outcome::Failed { actual } => Some((test.name.clone(), actual)),
+            Outcome::Passed | Outcome::Skipped => None,
+        })
+        .collect();
+    assert_eq!(failures, []);
+}
+
+#[test]
+fn missing_tokens_are_zero_width_after_the_last_token() {
+    let language = json();
+    let tree = Parser::new(&language).parse("{\"a\": [1, 2  }");
+    let missing = find(&tree, &Node::is_missing).unwrap();
+    assert_eq!(missing.kind(), "]");
+    assert_eq!(missing.byte_range(), 11..11);
+    assert!(tree.has_error());
+}
+
+#[test]
+fn skipped_tokens_are_covered_by_the_error() {
+    let language = json();
+    let tree = Parser::new(&language).parse("[1 2]");
+    let error = find(&tree, &Node::is_error).unwrap();
+    assert_eq!(error.byte_range(), 1..2);
+    assert_eq!(tree.byte_range(), 0..5);
+}
+
+#[test]
+fn characters_no_token_starts_with_are_skipped() {
+    let language = json();
+    let tree = Parser::new(&language).parse("[1, 2 @ 3]");
+    assert_eq!(
+        tree.to_sexp(),
+        "(document (array (number) (number) (ERROR (ERROR) (number))))"
+    );
+    let error = find(&tree, &Node::is_error).unwrap();
+    assert_eq!(error.byte_range(), 6..9);
+    assert_eq!(error.children()[0].byte_range(), 6..7);
+}
+
+#[test]
+fn text_that_never_recovers_is_one_error() {
+    let language = json();
+    let tree = Parser::new(&language).parse("@@@ 1");
+    assert_eq!(tree.to_sexp(), "(document (ERROR (ERROR)) (number))");
+    let error = find(&tree, &Node::is_error).unwrap();
+    assert_eq!(error.byte_range(), 0..3);
+}
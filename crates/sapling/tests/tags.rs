@@ -0,0 +1,93 @@
+#![allow(missing_docs)]
+//! Tagging calc programs: definitions with their comments, and calls.
+
+use sapling::tags::{tags, Tag, TagsConfiguration};
+use sapling::Point;
+
+mod calc {
+    include!("fixtures/tables/calc.rs");
+}
+
+const TAGS: &str = r#"
+((identifier) @name @reference.call @ignore
+  (#eq? @name "skip"))
+
+((comment)* @doc
+  .
+  (let name: (identifier) @name) @definition.variable
+  (#strip! @doc "^#\\s*")
+  (#select-adjacent! @doc @definition.variable))
+
+(call function: (identifier) @name) @reference.call
+"#;
+
+const LOCALS: &str = r"
+(program) @local.scope
+(let name: (identifier) @local.definition)
+(identifier) @local.reference
+";
+
+fn summary(source: &str, tags: &[Tag]) -> Vec<(String, String, bool)> {
+    tags.iter()
+        .map(|tag| {
+            (
+                source[tag.name_range.clone()].to_string(),
+                tag.kind.clone(),
+                tag.is_definition,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn definitions_and_references_are_tagged() {
+    let source = "let f = 1;\n  print(f, g(2));   \nskip(f);";
+    let config = TagsConfiguration::new(&calc::LANGUAGE, TAGS, "").unwrap();
+    let tags = tags(&config, source.as_bytes());
+    assert_eq!(
+        summary(source, &tags),
+        [
+            ("f".to_string(), "variable".to_string(), true),
+            ("print".to_string(), "call".to_string(), false),
+            ("g".to_string(), "call".to_string(), false),
+        ]
+    );
+    let print = &tags[1];
+    assert_eq!(print.range, 13..27);
+    assert_eq!(print.span, Point::new(1, 2)..Point::new(1, 7));
+    assert_eq!(&source[print.line_range.clone()], "print(f, g(2));");
+    assert_eq!(print.docs, None);
+}
+
+#[test]
+fn adjacent_comments_document_definitions() {
+    let source = "# unrelated\n\n# The answer.\n#   Of everything.\nlet answer = 42;";
+    let config = TagsConfiguration::new(&calc::LANGUAGE, TAGS, "").unwrap();
+    let tags = tags(&config, source.as_bytes());
+    assert_eq!(tags.len(), 1);
+    assert_eq!(tags[0].docs.as_deref(), Some("The answer.\nOf everything."));
+}
+
+#[test]
+fn references_to_locals_are_left_out() {
+    let source = "f(1); let f = 2; f(3); g(4);";
+    let config = TagsConfiguration::new(&calc::LANGUAGE, TAGS, LOCALS).unwrap();
+    let tags = tags(&config, source.as_bytes());
+    let names: Vec<_> = tags
+        .iter()
+        .map(|tag| (tag.name_range.start, tag.is_definition))
+        .collect();
+    // The call before the definition is not to the local.
+    assert_eq!(names, [(0, false), (10, true), (23, false)]);
+}
+
+#[test]
+fn long_lines_are_cut_at_a_character_boundary() {
+    let source = format!("f(1); # {}", "é".repeat(100));
+    let config = TagsConfiguration::new(&calc::LANGUAGE, TAGS, "").unwrap();
+    let tags = tags(&config, source.as_bytes());
+    let line = &tags[0].line_range;
+    assert_eq!(line.start, 0);
+    assert!(line.len() <= 180);
+    assert!(source.is_char_boundary(line.end));
+}